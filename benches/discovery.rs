@@ -0,0 +1,51 @@
+//! Filter discovery timing: `cargo bench --bench discovery`.
+//!
+//! Generates a directory of filter files and reports the median wall time of
+//! `discover_all_filters` over several runs. Set `TOKF_BENCH_FILES` to change
+//! the number of files (default 500).
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::time::{Duration, Instant};
+
+use tokf::config;
+
+const RUNS: usize = 20;
+
+fn main() {
+    let files: usize = std::env::var("TOKF_BENCH_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(500);
+    let dir = tempfile::TempDir::new().unwrap();
+    for i in 0..files {
+        std::fs::write(
+            dir.path().join(format!("tool-{i:04}.toml")),
+            format!(
+                "command = \"tool{i} run\"\nskip = [\"^debug\", \"^trace\"]\n\n\
+                 [on_success]\noutput = \"ok\"\n\n[on_failure]\ntail = 20\n"
+            ),
+        )
+        .unwrap();
+    }
+    let search_dirs = [dir.path().to_path_buf()];
+
+    for (label, include_builtin) in [("local only", false), ("local + built-in", true)] {
+        let mut times: Vec<Duration> = (0..RUNS)
+            .map(|_| {
+                let start = Instant::now();
+                let filters = config::discover_all_filters(&search_dirs, include_builtin).unwrap();
+                let elapsed = start.elapsed();
+                assert!(filters.len() >= files);
+                elapsed
+            })
+            .collect();
+        times.sort();
+        let threads = std::thread::available_parallelism().map_or(1, std::num::NonZero::get);
+        println!(
+            "discover_all_filters ({label}, {files} files, {threads} threads): median {:?}, min {:?}",
+            times[RUNS / 2],
+            times[0]
+        );
+    }
+}
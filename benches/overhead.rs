@@ -0,0 +1,67 @@
+//! End-to-end overhead of `tokf run`: `cargo bench --bench overhead`.
+//!
+//! Times each scenario run directly and through the `tokf` binary and reports
+//! the median and fastest wall time of both. Set `TOKF_BENCH_RUNS` to change
+//! the number of runs (default 30).
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const SCENARIOS: &[(&str, &[&str])] = &[
+    ("echo (startup)", &["echo", "hello"]),
+    ("seq 200k lines (passthrough)", &["seq", "1", "200000"]),
+    (
+        "git --version (built-in filter lookup)",
+        &["git", "--version"],
+    ),
+];
+
+fn time(runs: usize, args: &[&str], through_tokf: bool) -> (Duration, Duration) {
+    let mut times: Vec<Duration> = (0..runs)
+        .map(|_| {
+            let mut cmd = if through_tokf {
+                let mut c = Command::new(env!("CARGO_BIN_EXE_tokf"));
+                c.arg("run").args(args);
+                c
+            } else {
+                let mut c = Command::new(args[0]);
+                c.args(&args[1..]);
+                c
+            };
+            let start = Instant::now();
+            cmd.stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    (times[runs / 2], times[0])
+}
+
+fn main() {
+    let runs: usize = std::env::var("TOKF_BENCH_RUNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    // Keep user config and the tracking DB out of the measurement.
+    let home = tempfile::TempDir::new().unwrap();
+    // SAFETY: single-threaded; set before any child process is spawned.
+    unsafe {
+        std::env::set_var("TOKF_HOME", home.path());
+        std::env::set_var("TOKF_DB_PATH", home.path().join("tracking.db"));
+    }
+
+    for (label, args) in SCENARIOS {
+        let (direct, direct_min) = time(runs, args, false);
+        let (wrapped, wrapped_min) = time(runs, args, true);
+        println!(
+            "{label}: direct median {direct:?} (min {direct_min:?}), \
+             tokf run median {wrapped:?} (min {wrapped_min:?}), overhead {:?}",
+            wrapped.saturating_sub(direct)
+        );
+    }
+}
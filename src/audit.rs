@@ -0,0 +1,157 @@
+//! `tokf audit <old> <new>`: apply two versions of a filter to the same
+//! fixtures and report what the change hides, reveals, and saves.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use tokf::config;
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+use crate::filter_file::expand_paths;
+
+#[derive(Args)]
+pub struct AuditArgs {
+    /// The filter before the change (e.g. from `git show HEAD:filters/x.toml`)
+    old: PathBuf,
+    /// The filter after the change
+    new: PathBuf,
+    /// Fixture files or glob patterns to run both versions against
+    #[arg(default_value = "tests/fixtures/**/*.txt")]
+    fixtures: Vec<String>,
+    /// Exit code to assume for branch selection
+    #[arg(long, default_value_t = 0)]
+    exit_code: i32,
+}
+
+pub fn cmd_audit(args: &AuditArgs) -> i32 {
+    match run(args) {
+        Ok(report) => {
+            print!("{report}");
+            0
+        }
+        Err(e) => e.report(),
+    }
+}
+
+fn run(args: &AuditArgs) -> Result<String, CliError> {
+    let old = load(&args.old)?;
+    let new = load(&args.new)?;
+    let files = expand_paths(&args.fixtures)?;
+
+    let mut report = String::new();
+    let (mut changed, mut old_total, mut new_total) = (0, 0, 0);
+    for path in &files {
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+        let result = CommandResult::from_output(&String::from_utf8_lossy(&bytes), args.exit_code);
+        let before = filter::apply(&old, &result, &[]).output;
+        let after = filter::apply(&new, &result, &[]).output;
+        old_total += before.len();
+        new_total += after.len();
+        if before != after {
+            changed += 1;
+            write_change(&mut report, path, &before, &after);
+        }
+    }
+    let _ = writeln!(
+        report,
+        "{changed} of {} fixtures changed; output {} \u{2192} {} bytes ({})",
+        files.len(),
+        old_total,
+        new_total,
+        signed_delta(old_total, new_total)
+    );
+    Ok(report)
+}
+
+fn load(path: &Path) -> Result<FilterConfig, CliError> {
+    config::try_load_filter(path)
+        .kind(ErrorKind::Config)?
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::FilterNotFound,
+                format_args!("filter not found: {}", path.display()),
+            )
+        })
+}
+
+fn write_change(report: &mut String, path: &Path, before: &str, after: &str) {
+    let (hidden, revealed) = line_changes(before, after);
+    let _ = writeln!(
+        report,
+        "{}: {} \u{2192} {} bytes ({})",
+        path.display(),
+        before.len(),
+        after.len(),
+        signed_delta(before.len(), after.len())
+    );
+    for line in hidden {
+        let _ = writeln!(report, "  - {line}");
+    }
+    for line in revealed {
+        let _ = writeln!(report, "  + {line}");
+    }
+    report.push('\n');
+}
+
+/// Lines only the old output shows (hidden by the change) and lines only the
+/// new output shows (revealed), counting repeats, each in output order.
+fn line_changes<'a>(before: &'a str, after: &'a str) -> (Vec<&'a str>, Vec<&'a str>) {
+    (only_in(before, after), only_in(after, before))
+}
+
+fn only_in<'a>(text: &'a str, other: &str) -> Vec<&'a str> {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for line in other.lines() {
+        *remaining.entry(line).or_default() += 1;
+    }
+    text.lines()
+        .filter(|line| match remaining.get_mut(line) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+fn signed_delta(before: usize, after: usize) -> String {
+    if after >= before {
+        format!("+{}", after - before)
+    } else {
+        format!("-{}", before - after)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_changes_count_repeats() {
+        let (hidden, revealed) = line_changes("a\nb\nb\nc", "b\nc\nd");
+        assert_eq!(hidden, vec!["a", "b"]);
+        assert_eq!(revealed, vec!["d"]);
+    }
+
+    #[test]
+    fn identical_outputs_have_no_changes() {
+        let (hidden, revealed) = line_changes("x\ny", "x\ny");
+        assert!(hidden.is_empty() && revealed.is_empty());
+    }
+
+    #[test]
+    fn delta_is_signed() {
+        assert_eq!(signed_delta(10, 4), "-6");
+        assert_eq!(signed_delta(4, 10), "+6");
+        assert_eq!(signed_delta(3, 3), "+0");
+    }
+}
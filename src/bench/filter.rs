@@ -0,0 +1,210 @@
+//! `tokf bench filter`: time one filter on a fixture, and each of its
+//! regexes on the fixture's lines, to find what is slow in the hook path.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use tokf::config;
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::format;
+use tokf::runner::CommandResult;
+
+use super::ms;
+
+/// A pattern taking at least this share of all regex time is flagged.
+const DOMINANT_SHARE: f64 = 0.5;
+/// Patterns listed in the per-pattern table.
+const TOP_PATTERNS: usize = 5;
+
+pub fn cmd_filter(filter_path: &Path, fixture: &Path, runs: usize, exit_code: i32) -> i32 {
+    let cfg = match config::try_load_filter(filter_path) {
+        Ok(Some(cfg)) => cfg,
+        Ok(None) => {
+            eprintln!("[tokf] bench: filter not found: {}", filter_path.display());
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("[tokf] bench: {e:#}");
+            return 1;
+        }
+    };
+    let content = match std::fs::read(fixture) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            eprintln!("[tokf] bench: failed to read {}: {e}", fixture.display());
+            return 1;
+        }
+    };
+
+    let times = time_filter(&cfg, &content, exit_code, runs);
+    let lines = content.lines().count();
+    println!(
+        "{} on {} ({}, {lines} lines), {runs} runs",
+        filter_path.display(),
+        fixture.display(),
+        format::bytes(i64::try_from(content.len()).unwrap_or(i64::MAX)),
+    );
+    println!("{}", format_latency(&times, content.len(), lines));
+
+    let patterns = time_patterns(&cfg, &content);
+    if !patterns.is_empty() {
+        println!("slowest patterns (one pass over every fixture line)");
+        for row in format_patterns(&patterns) {
+            println!("{row}");
+        }
+    }
+    0
+}
+
+/// Sorted wall times of `runs` applications of the filter.
+fn time_filter(cfg: &FilterConfig, content: &str, exit_code: i32, runs: usize) -> Vec<Duration> {
+    let result = CommandResult::from_output(content, exit_code);
+    let mut times: Vec<Duration> = (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            let _ = filter::apply(cfg, &result, &[]);
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times
+}
+
+/// Value at percentile `p` (0–100) of sorted `times`.
+fn percentile(times: &[Duration], p: usize) -> Duration {
+    times
+        .get((times.len() * p / 100).min(times.len().saturating_sub(1)))
+        .copied()
+        .unwrap_or_default()
+}
+
+fn format_latency(times: &[Duration], bytes: usize, lines: usize) -> String {
+    let p50 = percentile(times, 50);
+    let p95 = percentile(times, 95);
+    let secs = p50.as_secs_f64();
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    let (mb_per_sec, lines_per_sec) = if secs > 0.0 {
+        (
+            bytes as f64 / 1e6 / secs,
+            (lines as f64 / secs).round() as i64,
+        )
+    } else {
+        (0.0, 0)
+    };
+    format!(
+        "  p50         {}\n  p95         {}\n  throughput  {mb_per_sec:.1} MB/s, {} lines/s",
+        ms(p50),
+        ms(p95),
+        format::compact(lines_per_sec)
+    )
+}
+
+/// Time for one regex to test every line of the fixture.
+#[derive(Debug, Clone, PartialEq)]
+struct PatternTiming {
+    key: String,
+    pattern: String,
+    elapsed: Duration,
+    /// Share of the summed time of all patterns, 0–1.
+    share: f64,
+}
+
+/// Every valid regex of the filter, slowest first. Each is run over all
+/// lines a few times and the fastest pass kept, to damp scheduler noise.
+fn time_patterns(cfg: &FilterConfig, content: &str) -> Vec<PatternTiming> {
+    const PASSES: usize = 3;
+    let lines: Vec<&str> = content.lines().collect();
+    let mut timings: Vec<PatternTiming> = config::lint::regexes(cfg)
+        .into_iter()
+        .filter_map(|(key, pattern)| {
+            let re = Regex::new(&pattern).ok()?;
+            let elapsed = (0..PASSES)
+                .map(|_| {
+                    let start = Instant::now();
+                    let hits = lines.iter().filter(|l| re.is_match(l)).count();
+                    std::hint::black_box(hits);
+                    start.elapsed()
+                })
+                .min()
+                .unwrap_or_default();
+            Some(PatternTiming {
+                key,
+                pattern,
+                elapsed,
+                share: 0.0,
+            })
+        })
+        .collect();
+    let total: f64 = timings.iter().map(|t| t.elapsed.as_secs_f64()).sum();
+    if total > 0.0 {
+        for t in &mut timings {
+            t.share = t.elapsed.as_secs_f64() / total;
+        }
+    }
+    timings.sort_by_key(|t| std::cmp::Reverse(t.elapsed));
+    timings
+}
+
+fn format_patterns(timings: &[PatternTiming]) -> Vec<String> {
+    let single = timings.len() == 1;
+    timings
+        .iter()
+        .take(TOP_PATTERNS)
+        .map(|t| {
+            let flag = if !single && t.share >= DOMINANT_SHARE {
+                "  \u{2190} dominates"
+            } else {
+                ""
+            };
+            format!(
+                "  {:24} {:>9} {:>4.0}%  {}{flag}",
+                t.key,
+                ms(t.elapsed),
+                t.share * 100.0,
+                t.pattern
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_pick_sorted_positions() {
+        let times: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&times, 50), Duration::from_millis(51));
+        assert_eq!(percentile(&times, 95), Duration::from_millis(96));
+        assert_eq!(percentile(&[], 95), Duration::ZERO);
+    }
+
+    #[test]
+    fn dominant_pattern_is_flagged() {
+        let t = |key: &str, micros, share| PatternTiming {
+            key: key.to_string(),
+            pattern: "x".to_string(),
+            elapsed: Duration::from_micros(micros),
+            share,
+        };
+        let rows = format_patterns(&[t("skip[1]", 800, 0.8), t("skip[0]", 200, 0.2)]);
+        assert!(rows[0].ends_with("\u{2190} dominates"), "{rows:?}");
+        assert!(!rows[1].contains("dominates"));
+    }
+
+    #[test]
+    fn every_valid_pattern_is_timed() {
+        let cfg: FilterConfig =
+            toml::from_str("command = \"x\"\nskip = [\"^a\", \"(bad\"]\nkeep = [\"b$\"]").unwrap();
+        let timings = time_patterns(&cfg, "a\nb\nab\n");
+        let mut keys: Vec<&str> = timings.iter().map(|t| t.key.as_str()).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["keep[0]", "skip[0]"]);
+        let share: f64 = timings.iter().map(|t| t.share).sum();
+        assert!(share == 0.0 || (share - 1.0).abs() < 1e-9);
+    }
+}
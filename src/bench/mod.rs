@@ -0,0 +1,161 @@
+//! `tokf bench overhead`: time commands run directly and through `tokf run`
+//! on this machine, to check what wrapping them actually costs.
+//! `tokf bench filter` times a single filter instead (see [`filter`]).
+
+mod filter;
+
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use clap::Subcommand;
+
+/// Scenarios timed when no command is given: a trivial command, where
+/// startup dominates, and one with a lot of output to capture and filter.
+const DEFAULT_SCENARIOS: &[&[&str]] = &[&["echo", "hello"], &["seq", "1", "200000"]];
+
+#[derive(Subcommand)]
+pub enum BenchAction {
+    /// Compare running commands directly and through `tokf run`
+    Overhead {
+        /// Runs per command and mode; the median is reported
+        #[arg(long, default_value_t = 20)]
+        runs: usize,
+        /// Command to time instead of the default scenarios
+        #[arg(trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+    /// Time a filter on a fixture: p50/p95 latency, throughput, and the
+    /// slowest regexes
+    Filter {
+        /// Path to the filter file
+        filter_path: PathBuf,
+        /// Path to the fixture file
+        fixture_path: PathBuf,
+        /// Times to apply the filter
+        #[arg(long, default_value_t = 200)]
+        runs: usize,
+        /// Simulated exit code for branch selection
+        #[arg(long, default_value_t = 0)]
+        exit_code: i32,
+    },
+}
+
+pub fn run_bench_action(action: &BenchAction) -> i32 {
+    match action {
+        BenchAction::Overhead { runs, command } => cmd_overhead(command, (*runs).max(1)),
+        BenchAction::Filter {
+            filter_path,
+            fixture_path,
+            runs,
+            exit_code,
+        } => filter::cmd_filter(filter_path, fixture_path, (*runs).max(1), *exit_code),
+    }
+}
+
+fn cmd_overhead(command: &[String], runs: usize) -> i32 {
+    let tokf = match std::env::current_exe() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("[tokf] bench: cannot locate the tokf binary: {e}");
+            return 1;
+        }
+    };
+    let scenarios: Vec<Vec<String>> = if command.is_empty() {
+        DEFAULT_SCENARIOS
+            .iter()
+            .map(|s| s.iter().map(ToString::to_string).collect())
+            .collect()
+    } else {
+        vec![command.to_vec()]
+    };
+
+    println!("{runs} runs each, median (min)");
+    for scenario in &scenarios {
+        let direct = time_runs(runs, || {
+            let mut cmd = Command::new(&scenario[0]);
+            cmd.args(&scenario[1..]);
+            cmd
+        });
+        let wrapped = time_runs(runs, || {
+            let mut cmd = Command::new(&tokf);
+            cmd.arg("run").args(scenario);
+            cmd
+        });
+        let (Some(direct), Some(wrapped)) = (direct, wrapped) else {
+            eprintln!("[tokf] bench: failed to run \"{}\"", scenario.join(" "));
+            return 1;
+        };
+        println!("{}", format_row(&scenario.join(" "), direct, wrapped));
+    }
+    0
+}
+
+/// Median and fastest wall time of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Timing {
+    median: Duration,
+    min: Duration,
+}
+
+/// Time `runs` runs of the built command, output discarded. `None` if the
+/// command could not be started.
+fn time_runs(runs: usize, mut build: impl FnMut() -> Command) -> Option<Timing> {
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let mut cmd = build();
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let start = Instant::now();
+        cmd.status().ok()?;
+        times.push(start.elapsed());
+    }
+    times.sort();
+    Some(Timing {
+        median: times[times.len() / 2],
+        min: times[0],
+    })
+}
+
+fn format_row(label: &str, direct: Timing, wrapped: Timing) -> String {
+    let overhead = wrapped.median.saturating_sub(direct.median);
+    format!(
+        "{label}\n  direct    {} ({})\n  tokf run  {} ({})\n  overhead  +{}",
+        ms(direct.median),
+        ms(direct.min),
+        ms(wrapped.median),
+        ms(wrapped.min),
+        ms(overhead)
+    )
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.2}ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_row_reports_median_overhead() {
+        let t = |median, min| Timing {
+            median: Duration::from_micros(median),
+            min: Duration::from_micros(min),
+        };
+        assert_eq!(
+            format_row("echo hello", t(1_000, 900), t(3_500, 3_000)),
+            "echo hello\n  direct    1.00ms (0.90ms)\n  tokf run  3.50ms (3.00ms)\n  overhead  +2.50ms"
+        );
+    }
+
+    #[test]
+    fn time_runs_fails_for_missing_command() {
+        assert_eq!(
+            time_runs(2, || Command::new("tokf-no-such-command-xyz")),
+            None
+        );
+    }
+}
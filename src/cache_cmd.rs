@@ -11,13 +11,20 @@ pub enum CacheAction {
     Clear,
     /// Show cache location, size, and validity status
     Info,
+    /// Rebuild the cache from all search dirs now (e.g. in CI images or
+    /// after installing a filter pack)
+    Warm,
+    /// Show entry count, size on disk, and hits/misses since the last clear
+    Stats,
 }
 
-pub fn run_cache_action(action: &CacheAction) -> i32 {
+pub fn run_cache_action(action: &CacheAction, include_builtin: bool) -> i32 {
     let search_dirs = config::default_search_dirs();
     match action {
         CacheAction::Clear => cmd_cache_clear(&search_dirs),
         CacheAction::Info => cmd_cache_info(&search_dirs),
+        CacheAction::Warm => cmd_cache_warm(&search_dirs, include_builtin),
+        CacheAction::Stats => cmd_cache_stats(&search_dirs),
     }
 }
 
@@ -26,6 +33,8 @@ fn cmd_cache_clear(search_dirs: &[PathBuf]) -> i32 {
         eprintln!("[tokf] cache: no cache location determined");
         return 0;
     };
+    // The counters start over with the cache.
+    let _ = std::fs::remove_file(cache::stats_path(&path));
     match std::fs::remove_file(&path) {
         Ok(()) => {
             eprintln!("[tokf] cache cleared: {}", path.display());
@@ -77,3 +86,39 @@ fn cmd_cache_info(search_dirs: &[PathBuf]) -> i32 {
 
     0
 }
+
+fn cmd_cache_warm(search_dirs: &[PathBuf], include_builtin: bool) -> i32 {
+    match cache::warm(search_dirs, include_builtin) {
+        Ok((path, count)) => {
+            eprintln!("[tokf] cache warmed: {count} filters in {}", path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("[tokf] cache warm error: {e:#}");
+            1
+        }
+    }
+}
+
+fn cmd_cache_stats(search_dirs: &[PathBuf]) -> i32 {
+    let Some(path) = cache::cache_path(search_dirs) else {
+        eprintln!("[tokf] cache: no cache location");
+        return 0;
+    };
+    println!("cache path: {}", path.display());
+    let entries = cache::load_manifest(&path).map_or(0, |m| m.filters.len());
+    let size = std::fs::metadata(&path).map_or(0, |m| m.len());
+    println!("entries: {entries}");
+    println!("size: {size} bytes");
+
+    let stats = cache::load_stats(&path);
+    let lookups = stats.hits + stats.misses;
+    println!("hits: {}", stats.hits);
+    println!("misses: {}", stats.misses);
+    if lookups > 0 {
+        #[allow(clippy::cast_precision_loss)]
+        let rate = stats.hits as f64 * 100.0 / lookups as f64;
+        println!("hit rate: {rate:.1}%");
+    }
+    0
+}
@@ -0,0 +1,76 @@
+//! `tokf check` and `tokf test`: validate a filter file, or try it against a
+//! fixture without running the command.
+
+use std::path::Path;
+
+use tokf::config;
+use tokf::filter;
+use tokf::runner;
+
+use crate::cli_error::{self, CliError, ErrorKind, ResultExt as _};
+use crate::{Cli, stdout};
+
+/// `tokf check <filter>`: parse and lint a filter file.
+pub fn cmd_check(filter_path: &Path) -> i32 {
+    match config::try_load_filter(filter_path) {
+        Ok(Some(cfg)) => {
+            let source = std::fs::read_to_string(filter_path).unwrap_or_default();
+            let problems = config::lint::lint(&source, &cfg);
+            if !problems.is_empty() {
+                for p in &problems {
+                    eprintln!("[tokf] {}:{p}", filter_path.display());
+                }
+                return cli_error::report(
+                    ErrorKind::Config,
+                    &format_args!("{} problem(s) in {}", problems.len(), filter_path.display()),
+                );
+            }
+            eprintln!(
+                "[tokf] {} is valid (command: \"{}\")",
+                filter_path.display(),
+                cfg.command.first()
+            );
+            0
+        }
+        Ok(None) => cli_error::report(
+            ErrorKind::FilterNotFound,
+            &format_args!("file not found: {}", filter_path.display()),
+        ),
+        Err(e) => cli_error::report(ErrorKind::Config, &format_args!("{e:#}")),
+    }
+}
+
+/// `tokf test <filter> <fixture>`: apply a filter to a saved output.
+pub fn cmd_test(
+    filter_path: &Path,
+    fixture_path: &Path,
+    exit_code: i32,
+    cli: &Cli,
+) -> Result<i32, CliError> {
+    let cfg = config::try_load_filter(filter_path)
+        .kind(ErrorKind::Config)?
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::FilterNotFound,
+                format_args!("filter not found: {}", filter_path.display()),
+            )
+        })?;
+
+    let fixture = std::fs::read_to_string(fixture_path)
+        .map_err(|e| anyhow::anyhow!("failed to read fixture: {}: {e}", fixture_path.display()))?;
+    let cmd_result = runner::CommandResult::from_output(&fixture, exit_code);
+
+    let start = std::time::Instant::now();
+    let filtered = filter::apply(&cfg, &cmd_result, &[]);
+    let elapsed = start.elapsed();
+
+    if cli.timing {
+        eprintln!("[tokf] filter took {:.1}ms", elapsed.as_secs_f64() * 1000.0);
+    }
+
+    if !filtered.output.is_empty() {
+        stdout::print_line(&filtered.output);
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,51 @@
+//! `tokf ls`: every active filter and the command it handles.
+
+use tokf::config;
+
+use crate::inventory;
+
+pub fn cmd_ls(verbose: bool, json: bool, include_builtin: bool) -> i32 {
+    let search_dirs = config::default_search_dirs();
+    let filters = match config::cache::discover_with_cache(&search_dirs, include_builtin) {
+        Ok(filters) => filters,
+        Err(e) => return super::discovery_failed(&e),
+    };
+
+    if json {
+        let entries: Vec<_> = filters.iter().map(inventory::FilterEntry::new).collect();
+        return inventory::print(&entries);
+    }
+
+    if !include_builtin {
+        eprintln!("[tokf] built-in filters disabled (--no-builtin or use_builtin_filters = false)");
+    }
+
+    for filter in &filters {
+        // Display: relative path without .toml extension  →  command
+        let display_name = filter
+            .relative_path
+            .with_extension("")
+            .display()
+            .to_string();
+        println!(
+            "{display_name}  \u{2192}  {}",
+            filter.config.command.first()
+        );
+
+        if verbose {
+            eprintln!(
+                "[tokf]   source: {}  [{}]",
+                filter.source_path.display(),
+                filter.priority_label()
+            );
+            let patterns = filter.config.command.patterns();
+            if patterns.len() > 1 {
+                for p in patterns {
+                    eprintln!("[tokf]     pattern: \"{p}\"");
+                }
+            }
+        }
+    }
+
+    0
+}
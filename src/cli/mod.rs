@@ -0,0 +1,20 @@
+//! Subcommand handlers, kept out of `main.rs` so it only parses arguments
+//! and dispatches.
+//!
+//! `ls`, `which` and `show` always use the cache; `--no-cache` only affects
+//! `tokf run`.
+
+pub mod check;
+pub mod ls;
+pub mod run;
+pub mod which;
+
+use crate::cli_error::{self, ErrorKind};
+
+/// Report that filter discovery failed; returns the exit code.
+pub fn discovery_failed(e: &anyhow::Error) -> i32 {
+    cli_error::report(
+        ErrorKind::Config,
+        &format_args!("failed to discover filters: {e:#}"),
+    )
+}
@@ -0,0 +1,307 @@
+//! `tokf run <command>`: resolve a filter for the command (falling back to
+//! `_default.toml`), run it and print the filtered output.
+
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+
+use tokf::config;
+use tokf::config::types::FilterConfig;
+use tokf::daemon;
+use tokf::filter;
+use tokf::runner;
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+#[cfg(feature = "tracking")]
+pub use crate::gain::record_run;
+use crate::{Cli, RunOptions, diff, last_run, sarif, stdout, steps};
+
+/// Find the first filter that matches `command_args` using the discovery model.
+/// Returns `(Option<FilterConfig>, words_consumed)`.
+pub fn find_filter(
+    command_args: &[String],
+    verbose: bool,
+    no_cache: bool,
+    include_builtin: bool,
+) -> anyhow::Result<(Option<FilterConfig>, usize)> {
+    let search_dirs = config::default_search_dirs();
+    if !no_cache
+        && daemon::enabled()
+        && let Some(found) = daemon::resolve(
+            &daemon::Scope::current(&search_dirs, include_builtin),
+            command_args,
+        )
+    {
+        tracing::debug!(matched = found.is_some(), "filter resolved by the daemon");
+        if let Some(m) = found {
+            if verbose {
+                report_match(&m.relative_path, &m.source_path, &m.config);
+            }
+            return Ok((Some(m.config), m.consumed));
+        }
+        return Ok((default_filter(command_args, &search_dirs, verbose), 0));
+    }
+
+    let resolved = if no_cache {
+        config::discover_all_filters(&search_dirs, include_builtin)?
+    } else {
+        config::cache::discover_with_cache(&search_dirs, include_builtin)?
+    };
+    let words: Vec<&str> = command_args.iter().map(String::as_str).collect();
+
+    for filter in &resolved {
+        if let Some(consumed) = filter.matches(&words) {
+            if verbose {
+                report_match(&filter.relative_path, &filter.source_path, &filter.config);
+            }
+            return Ok((Some(filter.config.clone()), consumed));
+        }
+    }
+
+    Ok((default_filter(command_args, &search_dirs, verbose), 0))
+}
+
+/// No filter matched: the project's or user's `_default.toml`, if any.
+fn default_filter(
+    command_args: &[String],
+    search_dirs: &[PathBuf],
+    verbose: bool,
+) -> Option<FilterConfig> {
+    let found = config::load_default_filter(search_dirs);
+    if verbose {
+        match &found {
+            Some((path, _)) => eprintln!(
+                "[tokf] no filter found for '{}', applying {}",
+                command_args.join(" "),
+                path.display()
+            ),
+            None => report_no_match(command_args),
+        }
+    }
+    found.map(|(_, cfg)| cfg)
+}
+
+fn report_match(relative_path: &Path, source_path: &Path, config: &FilterConfig) {
+    eprintln!(
+        "[tokf] matched {} (command: \"{}\") in {}",
+        relative_path.display(),
+        config.command.first(),
+        source_path
+            .parent()
+            .map_or("?", |p| p.to_str().unwrap_or("?")),
+    );
+}
+
+fn report_no_match(command_args: &[String]) {
+    eprintln!(
+        "[tokf] no filter found for '{}', passing through",
+        command_args.join(" ")
+    );
+}
+
+/// Words of `command_args` that form the command itself; the rest are passed
+/// on as arguments. Without a filter match, the head is the program plus any
+/// `VAR=value` words.
+pub fn head_len(command_args: &[String], words_consumed: usize) -> usize {
+    if words_consumed > 0 {
+        words_consumed
+    } else {
+        (config::env_prefix_len(command_args) + 1).min(command_args.len())
+    }
+}
+
+/// `--timeout`, else the filter's `timeout_secs`.
+pub fn command_timeout(
+    filter_cfg: Option<&FilterConfig>,
+    opts: &RunOptions,
+) -> Option<std::time::Duration> {
+    opts.timeout
+        .or_else(|| filter_cfg.and_then(|c| c.timeout_secs))
+        .map(std::time::Duration::from_secs)
+}
+
+/// Run the command (or the filter's `run` override). `--timeout` takes
+/// precedence over the filter's `timeout_secs`; `--pty` or the filter's `pty`
+/// runs it under a pseudo-terminal. The filter's `[env]` is set for the child.
+pub fn run_command(
+    filter_cfg: Option<&FilterConfig>,
+    head_len: usize,
+    command_args: &[String],
+    remaining_args: &[String],
+    opts: &RunOptions,
+) -> anyhow::Result<runner::CommandResult> {
+    let timeout = command_timeout(filter_cfg, opts);
+    let mut cmd = if let Some(cfg) = filter_cfg
+        && let Some(run_cmd) = &cfg.run
+    {
+        let env_len = config::env_prefix_len(command_args);
+        runner::build_shell_command(run_cmd, remaining_args, &command_args[..env_len])
+    } else {
+        runner::build_command(&command_args[..head_len].join(" "), remaining_args)?
+    };
+    if let Some(cfg) = filter_cfg {
+        runner::apply_filter_env(&mut cmd, &cfg.env);
+    }
+    let result = if opts.pty || filter_cfg.is_some_and(|c| c.pty) {
+        runner::execute_pty(cmd, timeout)?
+    } else {
+        runner::execute_command(cmd, timeout)?
+    };
+    if let Some(secs) = result.timed_out {
+        eprintln!("[tokf] command timed out after {secs}s");
+    }
+    Ok(result)
+}
+
+/// One run, as recorded in the tracking DB.
+#[cfg_attr(not(feature = "tracking"), allow(dead_code))]
+pub struct RunRecord<'a> {
+    pub command_args: &'a [String],
+    /// `None` when no filter was applied.
+    pub filter_name: Option<&'a str>,
+    pub input_bytes: usize,
+    pub output: &'a str,
+    pub filter_time_ms: u128,
+    pub exit_code: i32,
+    /// The filter's `[trend]` label and its count in this run.
+    pub counter: Option<(&'a str, i64)>,
+    pub used_fallback: bool,
+}
+
+/// Built without the `tracking` feature: runs are not recorded.
+#[cfg(not(feature = "tracking"))]
+pub const fn record_run(_record: &RunRecord<'_>) {}
+
+pub fn cmd_run(command_args: &[String], opts: &RunOptions, cli: &Cli) -> Result<i32, CliError> {
+    let include_builtin = config::settings::builtins_enabled(cli.no_builtin);
+    let (filter_cfg, words_consumed) = if cli.no_filter {
+        (None, 0)
+    } else {
+        find_filter(command_args, cli.verbose, cli.no_cache, include_builtin)
+            .kind(ErrorKind::Config)?
+    };
+
+    let head_len = head_len(command_args, words_consumed);
+    let remaining_args = command_args[head_len..].to_vec();
+
+    let step_outputs = filter_cfg.as_ref().map(|cfg| {
+        let timeout = command_timeout(Some(cfg), opts);
+        steps::run_steps(cfg, &remaining_args, timeout, include_builtin)
+    });
+    let mut cmd_result = run_command(
+        filter_cfg.as_ref(),
+        head_len,
+        command_args,
+        &remaining_args,
+        opts,
+    )
+    .kind(ErrorKind::Spawn)?;
+    cmd_result.steps = step_outputs.unwrap_or_default();
+    if let Some(path) = &opts.sarif {
+        sarif::write_report(path, filter_cfg.as_ref(), &cmd_result.combined);
+    }
+
+    let Some(cfg) = filter_cfg else {
+        return Ok(passthrough(command_args, &cmd_result, opts.diff));
+    };
+
+    let input_bytes = cmd_result.combined.len();
+    let start = std::time::Instant::now();
+    let mut filtered = apply_or_fallback(&cfg, &cmd_result, &remaining_args)?;
+    let elapsed = start.elapsed();
+    if opts.save_raw || cfg.save_raw {
+        last_run::attach(&cmd_result.combined, &mut filtered.output);
+    }
+
+    if cli.timing {
+        eprintln!("[tokf] filter took {:.1}ms", elapsed.as_secs_f64() * 1000.0);
+    }
+
+    if !filtered.output.is_empty() {
+        stdout::print_line(&filtered.output);
+    }
+    if opts.diff {
+        diff::print_diff(&cmd_result.combined, &filtered.output);
+    }
+
+    let exit_code = effective_exit_code(cmd_result.exit_code, filtered.failed);
+    let counter = cfg
+        .trend
+        .as_ref()
+        .and_then(|t| t.count(&cmd_result.combined).map(|n| (t.label.as_str(), n)));
+    record_run(&RunRecord {
+        command_args,
+        filter_name: Some(cfg.command.first()),
+        input_bytes,
+        output: &filtered.output,
+        filter_time_ms: elapsed.as_millis(),
+        exit_code,
+        counter,
+        used_fallback: filtered.used_fallback,
+    });
+
+    Ok(exit_code)
+}
+
+/// Print the unfiltered output of a run no filter matched and record it.
+fn passthrough(command_args: &[String], cmd_result: &runner::CommandResult, diff: bool) -> i32 {
+    if diff {
+        eprintln!("[tokf] diff: no filter matched; output passed through unchanged");
+    }
+    if !cmd_result.combined.is_empty() {
+        stdout::print_line(&cmd_result.combined);
+    }
+    // filter_time_ms = 0: no filter was applied, not 0ms of filtering.
+    record_run(&RunRecord {
+        command_args,
+        filter_name: None,
+        input_bytes: cmd_result.combined.len(),
+        output: &cmd_result.combined,
+        filter_time_ms: 0,
+        exit_code: cmd_result.exit_code,
+        counter: None,
+        used_fallback: false,
+    });
+    cmd_result.exit_code
+}
+
+/// Apply the filter; if it panics, print the raw output so nothing is lost
+/// and fail with [`ErrorKind::FilterPanic`].
+fn apply_or_fallback(
+    cfg: &FilterConfig,
+    cmd_result: &runner::CommandResult,
+    args: &[String],
+) -> Result<filter::FilterResult, CliError> {
+    let filtered = catch_unwind(AssertUnwindSafe(|| filter::apply(cfg, cmd_result, args)))
+        .map_err(|_| {
+            if !cmd_result.combined.is_empty() {
+                stdout::print_line(&cmd_result.combined);
+            }
+            CliError::new(
+                ErrorKind::FilterPanic,
+                format!(
+                    "filter \"{}\" panicked; printed unfiltered output (command exited {})",
+                    cfg.command.first(),
+                    cmd_result.exit_code
+                ),
+            )
+        })?;
+    tracing::debug!(
+        filter = cfg.command.first(),
+        exit_code = cmd_result.exit_code,
+        input_bytes = cmd_result.combined.len(),
+        output_bytes = filtered.output.len(),
+        "filtered command output"
+    );
+    Ok(filtered)
+}
+
+/// Exit code reported by `tokf run`: a zero exit becomes 1 when the filter's
+/// `fail_when` condition held. Non-zero exits are passed through unchanged.
+pub fn effective_exit_code(exit_code: i32, failed: bool) -> i32 {
+    if failed && exit_code == 0 {
+        eprintln!("[tokf] fail_when condition met, exiting 1");
+        1
+    } else {
+        exit_code
+    }
+}
@@ -0,0 +1,106 @@
+//! `tokf which <command>`: the filter that would handle a command, or with
+//! `--all` every filter matching it.
+
+use tokf::config;
+
+use crate::inventory;
+
+#[allow(clippy::fn_params_excessive_bools)] // one per CLI flag
+pub fn cmd_which(
+    command: &str,
+    all: bool,
+    verbose: bool,
+    json: bool,
+    include_builtin: bool,
+) -> i32 {
+    if all {
+        which_all(command, json, include_builtin)
+    } else {
+        which_winner(command, verbose, json, include_builtin)
+    }
+}
+
+fn which_winner(command: &str, verbose: bool, json: bool, include_builtin: bool) -> i32 {
+    let search_dirs = config::default_search_dirs();
+    let filters = match config::cache::discover_with_cache(&search_dirs, include_builtin) {
+        Ok(filters) => filters,
+        Err(e) => return super::discovery_failed(&e),
+    };
+
+    let words: Vec<&str> = command.split_whitespace().collect();
+
+    for filter in &filters {
+        if filter.matches(&words).is_some() {
+            if json {
+                return inventory::print(&inventory::FilterEntry::new(filter));
+            }
+            let display_name = filter
+                .relative_path
+                .with_extension("")
+                .display()
+                .to_string();
+            println!(
+                "{}  [{}]  command: \"{}\"",
+                display_name,
+                filter.priority_label(),
+                filter.config.command.first()
+            );
+            if verbose {
+                eprintln!("[tokf] source: {}", filter.source_path.display());
+            }
+            return 0;
+        }
+    }
+
+    eprintln!("[tokf] no filter found for \"{command}\"");
+    1
+}
+
+/// `tokf which --all`: every filter matching `command`, winner included,
+/// ranked as discovery ranks them.
+fn which_all(command: &str, json: bool, include_builtin: bool) -> i32 {
+    let search_dirs = config::default_search_dirs();
+    let active = match config::cache::discover_with_cache(&search_dirs, include_builtin) {
+        Ok(filters) => filters,
+        Err(e) => return super::discovery_failed(&e),
+    };
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let winner = active.iter().find(|f| f.matches(&words).is_some());
+
+    let ranked = config::discover_ranked_filters(&search_dirs, include_builtin);
+    let matching: Vec<_> = ranked
+        .iter()
+        .filter(|f| f.matches(&words).is_some())
+        .collect();
+    if matching.is_empty() {
+        eprintln!("[tokf] no filter found for \"{command}\"");
+        return 1;
+    }
+
+    let entries: Vec<_> = matching
+        .into_iter()
+        .map(|filter| inventory::FilterEntry {
+            status: Some(match winner {
+                Some(w) if w.source_path == filter.source_path => "wins",
+                Some(w) if w.overlays.contains(&filter.source_path) => "merged",
+                _ => "shadowed",
+            }),
+            ..inventory::FilterEntry::new(filter)
+        })
+        .collect();
+    if json {
+        return inventory::print(&entries);
+    }
+    for entry in entries {
+        let status = match entry.status {
+            Some("wins") => "  \u{2190} wins",
+            Some("merged") => "  (merged into winner)",
+            _ => "",
+        };
+        println!(
+            "{}  [{}]  specificity: {}  command: \"{}\"  {}{}",
+            entry.name, entry.priority, entry.specificity, entry.command, entry.source, status
+        );
+    }
+    0
+}
@@ -0,0 +1,173 @@
+//! tokf's own failures: exit codes and `--error-format`.
+//!
+//! The codes follow `sysexits.h` and shell conventions, so a wrapper can tell
+//! tokf failing apart from the wrapped command failing:
+//!
+//! | Kind | Exit code |
+//! |---|---|
+//! | other error | 1 |
+//! | filter not found | 66 |
+//! | filter panicked (raw output was printed) | 70 |
+//! | invalid filter config / discovery failure | 78 |
+//! | command could not be spawned | 127 |
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+/// Category of a tokf failure; decides the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Other,
+    FilterNotFound,
+    FilterPanic,
+    Config,
+    Spawn,
+}
+
+impl ErrorKind {
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::FilterNotFound => 66,
+            Self::FilterPanic => 70,
+            Self::Config => 78,
+            Self::Spawn => 127,
+        }
+    }
+
+    /// Stable name used in `--error-format json` output.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Other => "error",
+            Self::FilterNotFound => "filter_not_found",
+            Self::FilterPanic => "filter_panic",
+            Self::Config => "config",
+            Self::Spawn => "spawn",
+        }
+    }
+}
+
+/// How tokf reports its own errors on stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// `[tokf] error: …`
+    #[default]
+    Text,
+    /// One JSON object per error: `{"kind", "exit_code", "message"}`
+    Json,
+}
+
+static FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+/// Select the error format for the rest of the process. First call wins.
+pub fn set_format(format: ErrorFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Print a tokf error in the selected format and return its exit code.
+pub fn report(kind: ErrorKind, message: &dyn Display) -> i32 {
+    let format = FORMAT.get().copied().unwrap_or_default();
+    eprintln!("{}", render(format, kind, &message.to_string()));
+    kind.exit_code()
+}
+
+fn render(format: ErrorFormat, kind: ErrorKind, message: &str) -> String {
+    match format {
+        ErrorFormat::Text => format!("[tokf] error: {message}"),
+        ErrorFormat::Json => serde_json::json!({
+            "kind": kind.as_str(),
+            "exit_code": kind.exit_code(),
+            "message": message,
+        })
+        .to_string(),
+    }
+}
+
+/// An [`anyhow::Error`] tagged with the kind it should be reported as.
+pub struct CliError {
+    kind: ErrorKind,
+    error: anyhow::Error,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Display) -> Self {
+        Self {
+            kind,
+            error: anyhow::anyhow!("{message}"),
+        }
+    }
+
+    /// Print the error and return its exit code.
+    pub fn report(&self) -> i32 {
+        report(self.kind, &format_args!("{:#}", self.error))
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            kind: ErrorKind::Other,
+            error,
+        }
+    }
+}
+
+/// Tag a failed result with the [`ErrorKind`] to report it as.
+pub trait ResultExt<T> {
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError> {
+        self.map_err(|e| CliError {
+            kind,
+            error: e.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_format_keeps_tokf_prefix() {
+        assert_eq!(
+            render(ErrorFormat::Text, ErrorKind::Config, "bad filter"),
+            "[tokf] error: bad filter"
+        );
+    }
+
+    #[test]
+    fn json_format_carries_kind_and_code() {
+        let line = render(ErrorFormat::Json, ErrorKind::Spawn, "no such file");
+        let v: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(v["kind"], "spawn");
+        assert_eq!(v["exit_code"], 127);
+        assert_eq!(v["message"], "no such file");
+    }
+
+    #[test]
+    fn kinds_have_distinct_exit_codes() {
+        let kinds = [
+            ErrorKind::Other,
+            ErrorKind::FilterNotFound,
+            ErrorKind::FilterPanic,
+            ErrorKind::Config,
+            ErrorKind::Spawn,
+        ];
+        let mut codes: Vec<i32> = kinds.iter().map(|k| k.exit_code()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), kinds.len());
+    }
+}
@@ -0,0 +1,406 @@
+//! Per-directory records in the manifest, so a rebuild re-reads only what
+//! changed.
+//!
+//! Every search dir is stored with its mtime and, for each filter file, the
+//! file's mtime and the filter it parsed to — before the policy, ranking and
+//! shadowing are applied. A rebuild takes dirs whose mtime is unchanged from
+//! the previous manifest as they are, and in a changed dir parses only new or
+//! modified files. The embedded stdlib is kept the same way and reused until
+//! the tokf binary changes.
+//!
+//! With `cache_validation = "hash"` each file also records a blake3 hash of
+//! its contents, and hashes rather than mtimes decide what is unchanged.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{CachedFilter, Mode, ResolvedManifest, cached_to_filter, filter_to_cached, mtime};
+use crate::config::dir_defaults;
+use crate::config::settings::CacheValidation;
+use crate::config::{
+    FilterSource, ResolvedFilter, embedded_sources, load_each, load_sources, report_skipped_packs,
+    walk_filter_dir,
+};
+
+/// A filter file as of its last parse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub path: String,
+    pub mtime: u64,
+    /// blake3 of the contents; only recorded in hash mode.
+    pub hash: Option<[u8; 32]>,
+    /// `None` if the file did not parse.
+    pub filter: Option<CachedFilter>,
+}
+
+/// A search dir as of its last walk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedDir {
+    pub path: String,
+    pub mtime: u64,
+    pub files: Vec<CachedFile>,
+}
+
+/// Everything a rebuild loaded: the records to store, and every filter in
+/// priority order, ready to be ranked.
+pub(super) struct Loaded {
+    pub dirs: Vec<CachedDir>,
+    pub builtin: Option<Vec<CachedFilter>>,
+    pub filters: Vec<ResolvedFilter>,
+}
+
+/// Load the filters of `search_dirs` (and the stdlib if `include_builtin`),
+/// reusing what `previous` recorded for unchanged dirs and files.
+///
+/// `fingerprint` holds the dir mtimes the new records are stamped with;
+/// taking them before the walk means an edit made during it is seen as a
+/// change next time. `previous` must come from the same tokf binary.
+pub(super) fn load(
+    search_dirs: &[PathBuf],
+    fingerprint: &[(String, u64)],
+    mode: &Mode,
+    previous: Option<ResolvedManifest>,
+) -> anyhow::Result<Loaded> {
+    let (mut prev_dirs, prev_builtin) = previous.map_or_else(Default::default, |m| {
+        let dirs: HashMap<String, CachedDir> =
+            m.dirs.into_iter().map(|d| (d.path.clone(), d)).collect();
+        (dirs, m.builtin)
+    });
+
+    let mut dirs = Vec::with_capacity(search_dirs.len());
+    let mut pending = Pending {
+        validation: mode.validation,
+        ..Pending::default()
+    };
+    for (priority, dir) in search_dirs.iter().enumerate() {
+        let key = dir.to_string_lossy().into_owned();
+        let dir_mtime = fingerprint.get(priority).map_or(0, |(_, m)| *m);
+        let previous = prev_dirs.remove(&key);
+        let files = match previous {
+            Some(unchanged)
+                if mode.validation == CacheValidation::Mtime && unchanged.mtime == dir_mtime =>
+            {
+                unchanged.files
+            }
+            previous => pending.walk(dir, dirs.len(), previous),
+        };
+        dirs.push(CachedDir {
+            path: key,
+            mtime: dir_mtime,
+            files,
+        });
+    }
+
+    // Parse everything that changed in one go, then slot the results in.
+    let mut fresh: HashMap<(usize, usize), ResolvedFilter> = HashMap::new();
+    for (slot, parsed) in pending.slots.into_iter().zip(load_each(pending.sources)) {
+        if let Some(filter) = parsed {
+            dirs[slot.0].files[slot.1].filter = Some(filter_to_cached(&filter)?);
+            fresh.insert(slot, filter);
+        }
+    }
+
+    let mut filters = Vec::new();
+    for (d, dir) in dirs.iter().enumerate() {
+        let priority = u8::try_from(d).unwrap_or(u8::MAX);
+        for (f, file) in dir.files.iter().enumerate() {
+            if let Some(filter) = fresh.remove(&(d, f)) {
+                filters.push(filter);
+            } else if let Some(cached) = &file.filter {
+                let mut filter = cached_to_filter(cached.clone())?;
+                filter.priority = priority;
+                filters.push(filter);
+            }
+        }
+    }
+
+    let builtin = load_builtin(prev_builtin, mode.include_builtin, &mut filters)?;
+    Ok(Loaded {
+        dirs,
+        builtin,
+        filters,
+    })
+}
+
+/// Files of changed dirs that need parsing, and where their records sit.
+#[derive(Default)]
+struct Pending {
+    /// `(dir index, file index)` of each source's record.
+    slots: Vec<(usize, usize)>,
+    sources: Vec<FilterSource>,
+    validation: CacheValidation,
+}
+
+impl Pending {
+    /// Walk the changed dir at `index` in the search order. Files whose mtime
+    /// (or hash, in hash mode) matches their `previous` record keep it; the
+    /// rest are queued.
+    fn walk(&mut self, dir: &Path, index: usize, previous: Option<CachedDir>) -> Vec<CachedFile> {
+        let mut prev_files: HashMap<String, CachedFile> = previous
+            .map(|d| d.files.into_iter().map(|f| (f.path.clone(), f)).collect())
+            .unwrap_or_default();
+        let priority = u8::try_from(index).unwrap_or(u8::MAX);
+        let (paths, skipped) = walk_filter_dir(dir);
+        report_skipped_packs(&skipped);
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let record = CachedFile {
+                path: path.to_string_lossy().into_owned(),
+                mtime: stamp(&path),
+                hash: match self.validation {
+                    CacheValidation::Mtime => None,
+                    CacheValidation::Hash => hash(&path),
+                },
+                filter: None,
+            };
+            match prev_files.remove(&record.path) {
+                Some(same) if self.unchanged(&same, &record) => files.push(same),
+                _ => {
+                    self.slots.push((index, files.len()));
+                    self.sources.push(FilterSource::Disk {
+                        relative_path: path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+                        path,
+                        priority,
+                    });
+                    files.push(record);
+                }
+            }
+        }
+        files
+    }
+
+    fn unchanged(&self, previous: &CachedFile, current: &CachedFile) -> bool {
+        match self.validation {
+            CacheValidation::Mtime => previous.mtime == current.mtime,
+            CacheValidation::Hash => previous.hash.is_some() && previous.hash == current.hash,
+        }
+    }
+}
+
+/// The mtime of the filter file at `path`, mixed with its `_dir.toml`'s (if
+/// any), so editing, adding or removing the defaults re-parses the filter.
+fn stamp(path: &Path) -> u64 {
+    let own = mtime(path);
+    dir_defaults::path_for(path).map_or(own, |defaults| own ^ mtime(&defaults).rotate_left(1))
+}
+
+/// blake3 of the file at `path` and its `_dir.toml` (if any), or `None` if
+/// the file cannot be read.
+fn hash(path: &Path) -> Option<[u8; 32]> {
+    let bytes = std::fs::read(path).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&bytes);
+    if let Some(defaults) = dir_defaults::path_for(path).and_then(|p| std::fs::read(p).ok()) {
+        hasher.update(b"\0").update(&defaults);
+    }
+    Some(*hasher.finalize().as_bytes())
+}
+
+/// A filter file's identity for staleness checks: its path and stamp, plus
+/// its hash in hash mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp {
+    path: PathBuf,
+    mtime: u64,
+    hash: Option<[u8; 32]>,
+}
+
+/// Every filter file under `search_dirs`, subdirectories included, as it is
+/// now. Two equal snapshots mean discovery would read the same files with the
+/// same contents.
+pub fn snapshot(search_dirs: &[PathBuf], validation: CacheValidation) -> Vec<FileStamp> {
+    search_dirs
+        .iter()
+        .flat_map(|dir| walk_filter_dir(dir).0)
+        .map(|path| FileStamp {
+            mtime: stamp(&path),
+            hash: match validation {
+                CacheValidation::Mtime => None,
+                CacheValidation::Hash => hash(&path),
+            },
+            path,
+        })
+        .collect()
+}
+
+/// True iff `search_dirs` hold exactly the filter files `manifest` recorded,
+/// each with the contents it had then. Used in hash mode, where dir mtimes
+/// say nothing.
+pub(super) fn contents_unchanged(manifest: &ResolvedManifest, search_dirs: &[PathBuf]) -> bool {
+    manifest.dirs.len() == search_dirs.len()
+        && manifest.dirs.iter().zip(search_dirs).all(|(record, dir)| {
+            let (paths, _) = walk_filter_dir(dir);
+            record.path == dir.to_string_lossy()
+                && record.files.len() == paths.len()
+                && record.files.iter().zip(&paths).all(|(file, path)| {
+                    file.path == path.to_string_lossy()
+                        && file.hash.is_some()
+                        && file.hash == hash(path)
+                })
+        })
+}
+
+/// The stdlib records: reused from the previous manifest when it has them,
+/// parsed when they are needed and missing. Appends the stdlib filters to
+/// `filters` when `include_builtin`.
+fn load_builtin(
+    previous: Option<Vec<CachedFilter>>,
+    include_builtin: bool,
+    filters: &mut Vec<ResolvedFilter>,
+) -> anyhow::Result<Option<Vec<CachedFilter>>> {
+    if let Some(cached) = previous {
+        if include_builtin {
+            for c in &cached {
+                filters.push(cached_to_filter(c.clone())?);
+            }
+        }
+        return Ok(Some(cached));
+    }
+    if !include_builtin {
+        return Ok(None);
+    }
+    let parsed = load_sources(embedded_sources());
+    let cached = parsed
+        .iter()
+        .map(filter_to_cached)
+        .collect::<anyhow::Result<_>>()?;
+    filters.extend(parsed);
+    Ok(Some(cached))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::super::{
+        cache_path, compute_mtimes, discover_with_cache, is_cache_valid, load_fresh_manifest,
+        load_manifest, rebuild,
+    };
+    use super::*;
+    use crate::config::policy::FilterPolicy;
+
+    fn commands(filters: &[ResolvedFilter]) -> Vec<&str> {
+        filters.iter().map(|f| f.config.command.first()).collect()
+    }
+
+    #[test]
+    fn rebuild_reuses_unchanged_dirs() {
+        let tmp = TempDir::new().unwrap();
+        let local = tmp.path().join(".tokf/filters");
+        let user = tmp.path().join("user");
+        fs::create_dir_all(&local).unwrap();
+        fs::create_dir_all(&user).unwrap();
+        fs::write(user.join("u.toml"), "command = \"user cmd\"").unwrap();
+        let search_dirs = vec![local.clone(), user.clone()];
+        discover_with_cache(&search_dirs, false).unwrap();
+
+        // Rewriting a file in place leaves its dir's mtime alone, so the
+        // user dir is taken from the manifest without being read again.
+        fs::write(user.join("u.toml"), "command = \"edited\"").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(local.join("l.toml"), "command = \"local cmd\"").unwrap();
+
+        let filters = discover_with_cache(&search_dirs, false).unwrap();
+        assert_eq!(commands(&filters), ["local cmd", "user cmd"]);
+        assert_eq!(filters[1].priority, 1);
+    }
+
+    #[test]
+    fn rebuild_reparses_only_modified_files_of_a_changed_dir() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join(".tokf/filters");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("kept.toml"), "command = \"kept\"").unwrap();
+        fs::write(dir.join("edited.toml"), "command = \"before\"").unwrap();
+        let search_dirs = vec![dir.clone()];
+        discover_with_cache(&search_dirs, false).unwrap();
+
+        // Same mtime as recorded: the old parse is reused, content unseen.
+        let kept = dir.join("kept.toml");
+        let recorded = fs::metadata(&kept).unwrap().modified().unwrap();
+        fs::write(&kept, "command = \"kept but unseen\"").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&kept)
+            .unwrap()
+            .set_modified(recorded)
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(dir.join("edited.toml"), "command = \"after\"").unwrap();
+        fs::write(dir.join("new.toml"), "command = \"new\"").unwrap();
+
+        let mut names = commands(&discover_with_cache(&search_dirs, false).unwrap())
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["after", "kept", "new"]);
+    }
+
+    #[test]
+    fn rebuild_applies_a_new_policy_to_recorded_filters() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join(".tokf/filters");
+        fs::create_dir_all(dir.join("git")).unwrap();
+        fs::write(dir.join("git/log.toml"), "command = \"git log\"").unwrap();
+        fs::write(dir.join("git/diff.toml"), "command = \"git diff\"").unwrap();
+        let search_dirs = vec![dir];
+        let path = cache_path(&search_dirs).unwrap();
+        let mut mode = Mode {
+            include_builtin: false,
+            policy: FilterPolicy::default(),
+            validation: CacheValidation::Mtime,
+        };
+        rebuild(&path, &search_dirs, &mode).unwrap();
+
+        mode.policy.disable = vec!["git/log".to_string()];
+        let filters = rebuild(&path, &search_dirs, &mode).unwrap();
+        assert_eq!(commands(&filters), ["git diff"]);
+        let manifest = load_manifest(&path).unwrap();
+        assert_eq!(
+            manifest.dirs[0].files.len(),
+            2,
+            "disabled filters stay recorded"
+        );
+    }
+
+    #[test]
+    fn hash_mode_sees_edits_that_keep_mtimes() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join(".tokf/filters");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("tool.toml");
+        fs::write(&file, "command = \"before\"").unwrap();
+        let search_dirs = vec![dir];
+        let path = cache_path(&search_dirs).unwrap();
+        let mode = Mode {
+            include_builtin: false,
+            policy: FilterPolicy::default(),
+            validation: CacheValidation::Hash,
+        };
+        rebuild(&path, &search_dirs, &mode).unwrap();
+        assert!(load_fresh_manifest(&path, &search_dirs, &mode).is_some());
+
+        // Rewrite the file in place and restore its mtime: invisible to
+        // mtime validation, but the contents no longer hash the same.
+        let recorded = fs::metadata(&file).unwrap().modified().unwrap();
+        fs::write(&file, "command = \"after\"").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(recorded)
+            .unwrap();
+        let stale = load_manifest(&path).unwrap();
+        assert_eq!(stale.dir_mtimes, compute_mtimes(&search_dirs));
+        assert!(!is_cache_valid(&stale, &search_dirs));
+
+        let filters = rebuild(&path, &search_dirs, &mode).unwrap();
+        assert_eq!(commands(&filters), ["after"]);
+    }
+}
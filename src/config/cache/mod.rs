@@ -0,0 +1,445 @@
+mod dirs;
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::policy::FilterPolicy;
+use super::settings::{CacheValidation, load_settings};
+use super::types::FilterConfig;
+use super::{
+    ResolvedFilter, command_pattern_to_regex, discover_filters_with, pattern_specificity, rank,
+};
+pub use dirs::{CachedDir, CachedFile, FileStamp, snapshot};
+
+const CACHE_VERSION: u32 = 16;
+
+/// A command pattern with everything the hook derives from it, computed once
+/// when the manifest is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedPattern {
+    pub pattern: String,
+    /// The rewrite regex from [`command_pattern_to_regex`].
+    pub rewrite_regex: String,
+    pub specificity: usize,
+    /// Whether `rewrite_regex` compiles. Invalid patterns never become rewrite rules.
+    pub valid: bool,
+}
+
+impl CachedPattern {
+    fn derive(pattern: &str) -> Self {
+        let rewrite_regex = command_pattern_to_regex(pattern);
+        Self {
+            pattern: pattern.to_string(),
+            valid: regex::Regex::new(&rewrite_regex).is_ok(),
+            rewrite_regex,
+            specificity: pattern_specificity(pattern),
+        }
+    }
+}
+
+/// The patterns of `config` with their derived rewrite regexes.
+pub fn derive_patterns(config: &FilterConfig) -> Vec<CachedPattern> {
+    config
+        .command
+        .patterns()
+        .iter()
+        .map(|p| CachedPattern::derive(p))
+        .collect()
+}
+
+/// A single filter serialized for the binary cache.
+///
+/// `FilterConfig` uses `#[serde(untagged)]` on `CommandPattern`, which bincode
+/// cannot handle (it requires `deserialize_any`). We therefore serialize the
+/// config as a JSON string and embed it in the bincode blob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFilter {
+    /// `FilterConfig` serialized as a JSON string.
+    pub config_json: String,
+    /// `source_path` stored as a UTF-8 string via `to_string_lossy()`. Non-UTF-8 path bytes
+    /// are replaced with U+FFFD — filters still work correctly; only the displayed path is affected.
+    pub source_path: String,
+    pub relative_path: String,
+    pub priority: u8,
+    /// Source paths of merged-in `merge = true` filters, lossy like `source_path`.
+    pub overlays: Vec<String>,
+    /// Pre-derived command patterns, readable without parsing `config_json`.
+    pub patterns: Vec<CachedPattern>,
+}
+
+/// The on-disk binary manifest: version guard, mtime fingerprints, and the filter list.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolvedManifest {
+    pub version: u32,
+    /// `(dir_path_string, mtime_nanos_since_epoch)` for each search dir plus `"<binary>"`.
+    pub dir_mtimes: Vec<(String, u64)>,
+    /// Whether the embedded stdlib was part of discovery when this was written.
+    pub include_builtin: bool,
+    /// The `disable` / `prefer_local` policy in effect when this was written.
+    pub policy: FilterPolicy,
+    /// How this manifest is checked against the filter files.
+    pub validation: CacheValidation,
+    /// The resolved filters, in resolution order.
+    pub filters: Vec<CachedFilter>,
+    /// Each search dir's files as last parsed, for incremental rebuilds.
+    pub dirs: Vec<CachedDir>,
+    /// The embedded stdlib as last parsed; `None` until it was first needed.
+    pub builtin: Option<Vec<CachedFilter>>,
+}
+
+fn filter_to_cached(rf: &ResolvedFilter) -> anyhow::Result<CachedFilter> {
+    Ok(CachedFilter {
+        config_json: serde_json::to_string(&rf.config).context("serialize FilterConfig")?,
+        source_path: rf.source_path.to_string_lossy().into_owned(),
+        relative_path: rf.relative_path.to_string_lossy().into_owned(),
+        priority: rf.priority,
+        overlays: rf
+            .overlays
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect(),
+        patterns: derive_patterns(&rf.config),
+    })
+}
+
+fn cached_to_filter(cf: CachedFilter) -> anyhow::Result<ResolvedFilter> {
+    Ok(ResolvedFilter {
+        config: serde_json::from_str::<FilterConfig>(&cf.config_json)
+            .context("deserialize FilterConfig")?,
+        source_path: PathBuf::from(cf.source_path),
+        relative_path: PathBuf::from(cf.relative_path),
+        priority: cf.priority,
+        overlays: cf.overlays.into_iter().map(PathBuf::from).collect(),
+    })
+}
+
+/// Determine where to write the cache manifest.
+///
+/// - If `search_dirs[0]`'s parent (`.tokf/`) exists on disk → use `.tokf/cache/manifest.bin`
+/// - Otherwise → use `<user_cache_dir>/tokf/manifest.bin` (`$TOKF_HOME/cache/` if set)
+/// - Returns `None` if no cache location can be determined.
+pub fn cache_path(search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if let Some(first_dir) = search_dirs.first()
+        && let Some(tokf_dir) = first_dir.parent()
+        && tokf_dir.exists()
+    {
+        return Some(tokf_dir.join("cache/manifest.bin"));
+    }
+    crate::paths::cache_dir().map(|d| d.join("manifest.bin"))
+}
+
+/// Return the mtime of `path` as nanoseconds since the Unix epoch, or 0 on error.
+///
+/// Nanosecond precision ensures that sub-second file writes are detected on
+/// high-resolution filesystems (APFS, ext4 with `noatime`).
+fn mtime(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |d| {
+            d.as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(u64::from(d.subsec_nanos()))
+        })
+}
+
+fn binary_mtime() -> u64 {
+    static CACHE: OnceLock<u64> = OnceLock::new();
+    *CACHE.get_or_init(|| std::env::current_exe().ok().as_deref().map_or(0, mtime))
+}
+
+/// Fingerprint of `search_dirs`: each dir's mtime plus the tokf binary's.
+/// Anything that changes it makes resolved filters stale.
+pub fn compute_mtimes(search_dirs: &[PathBuf]) -> Vec<(String, u64)> {
+    let mut mtimes: Vec<(String, u64)> = search_dirs
+        .iter()
+        .map(|d| (d.to_string_lossy().into_owned(), mtime(d)))
+        .collect();
+    mtimes.push(("<binary>".to_string(), binary_mtime()));
+    mtimes
+}
+
+/// Returns true iff the cached manifest is still valid for the given search dirs.
+///
+/// With `cache_validation = "hash"` the dir mtimes are ignored; every filter
+/// file is hashed and compared instead.
+pub fn is_cache_valid(manifest: &ResolvedManifest, search_dirs: &[PathBuf]) -> bool {
+    if manifest.version != CACHE_VERSION {
+        return false;
+    }
+    match manifest.validation {
+        CacheValidation::Mtime => manifest.dir_mtimes == compute_mtimes(search_dirs),
+        CacheValidation::Hash => {
+            manifest.dir_mtimes.last() == compute_mtimes(&[]).last()
+                && dirs::contents_unchanged(manifest, search_dirs)
+        }
+    }
+}
+
+/// The settings a manifest is written under. A manifest written under
+/// different ones counts as a miss.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mode {
+    include_builtin: bool,
+    policy: FilterPolicy,
+    validation: CacheValidation,
+}
+
+impl Mode {
+    fn current(include_builtin: bool) -> Self {
+        let settings = load_settings();
+        Self {
+            include_builtin,
+            policy: settings.filter_policy(),
+            validation: settings.cache_validation(),
+        }
+    }
+
+    fn matches(&self, manifest: &ResolvedManifest) -> bool {
+        manifest.include_builtin == self.include_builtin
+            && manifest.policy == self.policy
+            && manifest.validation == self.validation
+    }
+}
+
+/// Load a previously written manifest from disk.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or the binary data is malformed.
+pub fn load_manifest(path: &Path) -> anyhow::Result<ResolvedManifest> {
+    let data = std::fs::read(path).context("read cache file")?;
+    bincode::deserialize(&data).map_err(|e| anyhow::anyhow!("deserialize cache: {e}"))
+}
+
+impl ResolvedManifest {
+    fn new(
+        loaded: dirs::Loaded,
+        filters: &[ResolvedFilter],
+        fingerprint: Vec<(String, u64)>,
+        mode: &Mode,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            version: CACHE_VERSION,
+            dir_mtimes: fingerprint,
+            include_builtin: mode.include_builtin,
+            policy: mode.policy.clone(),
+            validation: mode.validation,
+            filters: filters
+                .iter()
+                .map(filter_to_cached)
+                .collect::<anyhow::Result<_>>()?,
+            dirs: loaded.dirs,
+            builtin: loaded.builtin,
+        })
+    }
+}
+
+fn write_manifest(path: &Path, manifest: &ResolvedManifest) -> anyhow::Result<()> {
+    let data = bincode::serialize(manifest).map_err(|e| anyhow::anyhow!("serialize cache: {e}"))?;
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("cache path has no parent"))?;
+    std::fs::create_dir_all(parent).context("create cache dir")?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, &data).context("write cache tmp")?;
+    std::fs::rename(&tmp, path).context("rename cache tmp to final")?;
+    Ok(())
+}
+
+/// The manifest at `path`, if it was written under `mode` and the search
+/// dirs have not changed since.
+fn load_fresh_manifest(
+    path: &Path,
+    search_dirs: &[PathBuf],
+    mode: &Mode,
+) -> Option<ResolvedManifest> {
+    load_manifest(path)
+        .ok()
+        .filter(|manifest| mode.matches(manifest) && is_cache_valid(manifest, search_dirs))
+}
+
+/// Re-discover and try to persist the result. Dirs and files unchanged since
+/// the previous manifest are reused rather than parsed again. Write failures
+/// are logged, never propagated.
+fn rebuild(
+    path: &Path,
+    search_dirs: &[PathBuf],
+    mode: &Mode,
+) -> anyhow::Result<Vec<ResolvedFilter>> {
+    let fingerprint = compute_mtimes(search_dirs);
+    // Records from another tokf binary may have parsed differently.
+    let previous = load_manifest(path)
+        .ok()
+        .filter(|m| m.version == CACHE_VERSION && m.dir_mtimes.last() == fingerprint.last());
+    let mut loaded = match dirs::load(search_dirs, &fingerprint, mode, previous) {
+        Ok(loaded) => loaded,
+        Err(_) => dirs::load(search_dirs, &fingerprint, mode, None)?,
+    };
+    let filters = rank(std::mem::take(&mut loaded.filters), &mode.policy);
+    let written = ResolvedManifest::new(loaded, &filters, fingerprint, mode)
+        .and_then(|manifest| write_manifest(path, &manifest));
+    if let Err(e) = written {
+        tracing::warn!("cache write failed: {e:#}");
+    }
+    Ok(filters)
+}
+
+/// Hit and miss counts for a manifest since the cache was last cleared.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Where lookups against the manifest at `manifest_path` are counted: the
+/// hit and miss counts as two little-endian `u64`s, so the file never grows.
+pub fn stats_path(manifest_path: &Path) -> PathBuf {
+    manifest_path.with_extension("stats")
+}
+
+/// Count one lookup. Failures are ignored; counters are best effort, and a
+/// lookup racing another process's may go uncounted.
+fn record_lookup(manifest_path: &Path, hit: bool) {
+    tracing::debug!(
+        cache = %manifest_path.display(),
+        "discovery cache {}",
+        if hit { "hit" } else { "miss, rebuilt" }
+    );
+    let mut stats = load_stats(manifest_path);
+    if hit {
+        stats.hits += 1;
+    } else {
+        stats.misses += 1;
+    }
+    let mut data = stats.hits.to_le_bytes().to_vec();
+    data.extend_from_slice(&stats.misses.to_le_bytes());
+    // Written aside and renamed into place, so readers never see half a file.
+    let path = stats_path(manifest_path);
+    let tmp = path.with_extension(format!("stats.{}", std::process::id()));
+    if std::fs::write(&tmp, data)
+        .and_then(|()| std::fs::rename(&tmp, &path))
+        .is_err()
+    {
+        let _ = std::fs::remove_file(&tmp);
+    }
+}
+
+/// Lookup counts for the manifest at `manifest_path`; zero if none were
+/// recorded or the file is not in the expected format.
+pub fn load_stats(manifest_path: &Path) -> CacheStats {
+    let data = std::fs::read(stats_path(manifest_path)).unwrap_or_default();
+    let (Ok(hits), Ok(misses)) = (
+        <[u8; 8]>::try_from(data.get(..8).unwrap_or_default()),
+        <[u8; 8]>::try_from(data.get(8..).unwrap_or_default()),
+    ) else {
+        return CacheStats::default();
+    };
+    CacheStats {
+        hits: u64::from_le_bytes(hits),
+        misses: u64::from_le_bytes(misses),
+    }
+}
+
+/// Rebuild the manifest from all search dirs, whatever its state.
+/// Returns the manifest path and the number of filters written.
+///
+/// # Errors
+///
+/// Returns an error if no cache location can be determined, discovery
+/// fails, or the manifest cannot be written.
+pub fn warm(search_dirs: &[PathBuf], include_builtin: bool) -> anyhow::Result<(PathBuf, usize)> {
+    let mode = Mode::current(include_builtin);
+    let path = cache_path(search_dirs)
+        .ok_or_else(|| anyhow::anyhow!("no cache location could be determined"))?;
+    let fingerprint = compute_mtimes(search_dirs);
+    let mut loaded = dirs::load(search_dirs, &fingerprint, &mode, None)?;
+    let filters = rank(std::mem::take(&mut loaded.filters), &mode.policy);
+    let manifest = ResolvedManifest::new(loaded, &filters, fingerprint, &mode)?;
+    write_manifest(&path, &manifest)?;
+    Ok((path, filters.len()))
+}
+
+/// Discover all filters using the binary cache when possible.
+///
+/// Flow:
+/// 1. Determine cache path; if none, fall through to `discover_filters_with`.
+/// 2. Try to load and validate the cached manifest; on hit, return immediately.
+///    A manifest written with a different `include_builtin` mode or filter
+///    policy counts as a miss.
+/// 3. On miss: call `discover_filters_with`, attempt to persist the result, then return.
+///
+/// Cache write failures are logged to stderr but never propagated.
+///
+/// # Errors
+///
+/// Returns `Err` only if discovery itself fails (unexpected I/O error).
+pub fn discover_with_cache(
+    search_dirs: &[PathBuf],
+    include_builtin: bool,
+) -> anyhow::Result<Vec<ResolvedFilter>> {
+    let mode = Mode::current(include_builtin);
+    let Some(path) = cache_path(search_dirs) else {
+        return discover_filters_with(search_dirs, include_builtin, &mode.policy);
+    };
+
+    if let Some(manifest) = load_fresh_manifest(&path, search_dirs, &mode) {
+        let result: anyhow::Result<Vec<ResolvedFilter>> =
+            manifest.filters.into_iter().map(cached_to_filter).collect();
+        if let Ok(filters) = result {
+            record_lookup(&path, true);
+            return Ok(filters);
+        }
+        // JSON deserialization failed — fall through to a full rebuild
+    }
+
+    let filters = rebuild(&path, search_dirs, &mode)?;
+    record_lookup(&path, false);
+    Ok(filters)
+}
+
+/// The command patterns of every discovered filter, in resolution order.
+///
+/// This is the hook's hot path: on a cache hit the pre-derived patterns are
+/// read straight from the manifest, without parsing any filter config or
+/// re-deriving rewrite regexes. Misses rebuild the cache like
+/// [`discover_with_cache`].
+///
+/// # Errors
+///
+/// Same as [`discover_with_cache`].
+pub fn discover_patterns_with_cache(
+    search_dirs: &[PathBuf],
+    include_builtin: bool,
+) -> anyhow::Result<Vec<CachedPattern>> {
+    let mode = Mode::current(include_builtin);
+    let filters = match cache_path(search_dirs) {
+        Some(path) => {
+            if let Some(manifest) = load_fresh_manifest(&path, search_dirs, &mode) {
+                record_lookup(&path, true);
+                return Ok(manifest
+                    .filters
+                    .into_iter()
+                    .flat_map(|f| f.patterns)
+                    .collect());
+            }
+            let filters = rebuild(&path, search_dirs, &mode)?;
+            record_lookup(&path, false);
+            filters
+        }
+        None => discover_filters_with(search_dirs, include_builtin, &mode.policy)?,
+    };
+    Ok(filters
+        .iter()
+        .flat_map(|f| derive_patterns(&f.config))
+        .collect())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests;
@@ -0,0 +1,300 @@
+use std::fs;
+
+use serial_test::serial;
+use tempfile::TempDir;
+
+use super::*;
+
+fn make_resolved_filter(command: &str, priority: u8) -> ResolvedFilter {
+    let config: FilterConfig = toml::from_str(&format!("command = \"{command}\"")).unwrap();
+    ResolvedFilter {
+        config,
+        source_path: PathBuf::from(format!("/fake/{command}.toml")),
+        relative_path: PathBuf::from(format!("{command}.toml")),
+        priority,
+        overlays: Vec::new(),
+    }
+}
+
+#[test]
+fn roundtrip_serialize_deserialize() {
+    let rf = make_resolved_filter("echo test", 0);
+    let cached = filter_to_cached(&rf).unwrap();
+    let manifest = ResolvedManifest {
+        version: CACHE_VERSION,
+        dir_mtimes: vec![("<binary>".to_string(), 42)],
+        include_builtin: true,
+        policy: FilterPolicy::default(),
+        validation: CacheValidation::Mtime,
+        filters: vec![cached],
+        dirs: Vec::new(),
+        builtin: None,
+    };
+    let data = bincode::serialize(&manifest).unwrap();
+    let manifest2: ResolvedManifest = bincode::deserialize(&data).unwrap();
+
+    assert_eq!(manifest2.version, CACHE_VERSION);
+    assert_eq!(manifest2.filters.len(), 1);
+    assert_eq!(manifest2.dir_mtimes, vec![("<binary>".to_string(), 42u64)]);
+
+    let rf2 = cached_to_filter(manifest2.filters.into_iter().next().unwrap()).unwrap();
+    assert_eq!(rf2.config.command.first(), "echo test");
+}
+
+#[test]
+fn stale_on_version_mismatch() {
+    let manifest = ResolvedManifest {
+        version: 0, // wrong version
+        dir_mtimes: compute_mtimes(&[]),
+        include_builtin: true,
+        policy: FilterPolicy::default(),
+        validation: CacheValidation::Mtime,
+        filters: vec![],
+        dirs: Vec::new(),
+        builtin: None,
+    };
+    assert!(!is_cache_valid(&manifest, &[]));
+}
+
+#[test]
+fn stale_on_dir_mtime_change() {
+    let tmp = TempDir::new().unwrap();
+    let filters_dir = tmp.path().join("filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    let search_dirs = vec![filters_dir.clone()];
+
+    let manifest = ResolvedManifest {
+        version: CACHE_VERSION,
+        dir_mtimes: compute_mtimes(&search_dirs),
+        include_builtin: true,
+        policy: FilterPolicy::default(),
+        validation: CacheValidation::Mtime,
+        filters: vec![],
+        dirs: Vec::new(),
+        builtin: None,
+    };
+    assert!(is_cache_valid(&manifest, &search_dirs));
+
+    // Brief pause then write a file to update the directory mtime
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    fs::write(filters_dir.join("new.toml"), "command = \"new\"").unwrap();
+
+    assert!(!is_cache_valid(&manifest, &search_dirs));
+}
+
+#[test]
+fn cache_path_project_local() {
+    let tmp = TempDir::new().unwrap();
+    let tokf_dir = tmp.path().join(".tokf");
+    fs::create_dir_all(&tokf_dir).unwrap();
+    let search_dirs = vec![tokf_dir.join("filters")];
+
+    let path = cache_path(&search_dirs).unwrap();
+    assert!(path.starts_with(&tokf_dir));
+    assert!(path.ends_with("cache/manifest.bin"));
+}
+
+#[test]
+#[serial]
+fn cache_path_user_fallback() {
+    // A parent path that definitely doesn't exist on disk
+    let search_dirs = vec![PathBuf::from("/tokf_test_nonexistent_dir/.tokf/filters")];
+    let path = cache_path(&search_dirs);
+
+    if let Some(user_cache) = crate::paths::cache_dir() {
+        assert_eq!(path, Some(user_cache.join("manifest.bin")));
+    } else {
+        assert!(path.is_none());
+    }
+}
+
+#[test]
+fn write_failure_does_not_propagate() {
+    let tmp = TempDir::new().unwrap();
+    let tokf_dir = tmp.path().join(".tokf");
+    fs::create_dir_all(&tokf_dir).unwrap();
+    // Block cache dir creation by placing a regular file at that path
+    fs::write(tokf_dir.join("cache"), b"not a directory").unwrap();
+
+    let search_dirs = vec![tokf_dir.join("filters")];
+    let result = discover_with_cache(&search_dirs, true);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn cached_filter_roundtrip() {
+    let config: FilterConfig = toml::from_str("command = \"git push\"").unwrap();
+    let rf = ResolvedFilter {
+        config,
+        source_path: PathBuf::from("/some/path/push.toml"),
+        relative_path: PathBuf::from("git/push.toml"),
+        priority: 1,
+        overlays: vec![PathBuf::from("/local/push.toml")],
+    };
+    let cached = filter_to_cached(&rf).unwrap();
+    let rf2 = cached_to_filter(cached).unwrap();
+
+    assert_eq!(rf2.config.command.first(), "git push");
+    assert_eq!(rf2.source_path, PathBuf::from("/some/path/push.toml"));
+    assert_eq!(rf2.relative_path, PathBuf::from("git/push.toml"));
+    assert_eq!(rf2.priority, 1);
+    assert_eq!(rf2.overlays, vec![PathBuf::from("/local/push.toml")]);
+}
+
+#[test]
+fn cached_filter_carries_derived_patterns() {
+    let config: FilterConfig =
+        toml::from_str(r#"command = ["git push", "{yarn|npm} test"]"#).unwrap();
+    let rf = ResolvedFilter {
+        config,
+        source_path: PathBuf::from("/some/path/push.toml"),
+        relative_path: PathBuf::from("git/push.toml"),
+        priority: 0,
+        overlays: Vec::new(),
+    };
+    let cached = filter_to_cached(&rf).unwrap();
+
+    assert_eq!(cached.patterns.len(), 2);
+    assert_eq!(cached.patterns[0].pattern, "git push");
+    assert_eq!(
+        cached.patterns[0].rewrite_regex,
+        command_pattern_to_regex("git push")
+    );
+    assert_eq!(cached.patterns[0].specificity, 2);
+    assert!(cached.patterns.iter().all(|p| p.valid));
+}
+
+#[test]
+fn patterns_read_from_manifest_without_parsing_configs() {
+    let tmp = TempDir::new().unwrap();
+    let tokf_dir = tmp.path().join(".tokf");
+    let filters_dir = tokf_dir.join("filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    fs::write(filters_dir.join("mine.toml"), "command = \"my cmd\"").unwrap();
+    let search_dirs = vec![filters_dir];
+
+    let first = discover_patterns_with_cache(&search_dirs, false).unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].pattern, "my cmd");
+
+    // Corrupt the stored config: a hit must not need it.
+    let path = cache_path(&search_dirs).unwrap();
+    let mut manifest = load_manifest(&path).unwrap();
+    manifest.filters[0].config_json = "not json".to_string();
+    fs::write(&path, bincode::serialize(&manifest).unwrap()).unwrap();
+
+    let second = discover_patterns_with_cache(&search_dirs, false).unwrap();
+    assert_eq!(second, first);
+}
+
+#[test]
+fn binary_sentinel_in_mtimes() {
+    let mtimes = compute_mtimes(&[]);
+    assert!(mtimes.iter().any(|(k, _)| k == "<binary>"));
+}
+
+#[test]
+fn stale_cache_triggers_rebuild() {
+    let tmp = TempDir::new().unwrap();
+    let tokf_dir = tmp.path().join(".tokf");
+    let filters_dir = tokf_dir.join("filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+
+    fs::write(filters_dir.join("first.toml"), "command = \"first cmd\"").unwrap();
+    let search_dirs = vec![filters_dir.clone()];
+
+    // First run: populates cache
+    let filters1 = discover_with_cache(&search_dirs, true).unwrap();
+    let count1 = filters1.iter().filter(|f| f.priority < u8::MAX).count();
+    assert_eq!(count1, 1);
+
+    // Brief pause then add a new filter (updates dir mtime)
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    fs::write(filters_dir.join("second.toml"), "command = \"second cmd\"").unwrap();
+
+    // Second run: cache is stale, rebuilds with both filters
+    let filters2 = discover_with_cache(&search_dirs, true).unwrap();
+    let count2 = filters2.iter().filter(|f| f.priority < u8::MAX).count();
+    assert_eq!(count2, 2);
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn builtin_mode_change_invalidates_cache() {
+    let tmp = TempDir::new().unwrap();
+    let tokf_dir = tmp.path().join(".tokf");
+    let filters_dir = tokf_dir.join("filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    fs::write(filters_dir.join("mine.toml"), "command = \"my cmd\"").unwrap();
+    let search_dirs = vec![filters_dir];
+
+    let with_builtin = discover_with_cache(&search_dirs, true).unwrap();
+    assert!(with_builtin.iter().any(|f| f.priority == u8::MAX));
+
+    let without = discover_with_cache(&search_dirs, false).unwrap();
+    assert_eq!(without.len(), 1);
+    assert_eq!(without[0].config.command.first(), "my cmd");
+
+    let again = discover_with_cache(&search_dirs, true).unwrap();
+    assert_eq!(again.len(), with_builtin.len());
+}
+
+#[test]
+fn lookups_are_counted_until_cleared() {
+    let tmp = TempDir::new().unwrap();
+    let filters_dir = tmp.path().join(".tokf/filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    fs::write(filters_dir.join("a.toml"), "command = \"a\"").unwrap();
+    let search_dirs = vec![filters_dir];
+    let path = cache_path(&search_dirs).unwrap();
+
+    assert_eq!(load_stats(&path), CacheStats::default());
+    discover_with_cache(&search_dirs, false).unwrap();
+    discover_with_cache(&search_dirs, false).unwrap();
+    discover_patterns_with_cache(&search_dirs, false).unwrap();
+    assert_eq!(load_stats(&path), CacheStats { hits: 2, misses: 1 });
+
+    fs::remove_file(stats_path(&path)).unwrap();
+    assert_eq!(load_stats(&path), CacheStats::default());
+}
+
+#[test]
+fn stats_file_keeps_a_fixed_size() {
+    let tmp = TempDir::new().unwrap();
+    let filters_dir = tmp.path().join(".tokf/filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    fs::write(filters_dir.join("a.toml"), "command = \"a\"").unwrap();
+    let search_dirs = vec![filters_dir];
+    let path = cache_path(&search_dirs).unwrap();
+
+    for _ in 0..20 {
+        discover_with_cache(&search_dirs, false).unwrap();
+    }
+    assert_eq!(
+        load_stats(&path),
+        CacheStats {
+            hits: 19,
+            misses: 1
+        }
+    );
+    assert_eq!(fs::metadata(stats_path(&path)).unwrap().len(), 16);
+}
+
+#[test]
+fn warm_writes_a_fresh_manifest() {
+    let tmp = TempDir::new().unwrap();
+    let filters_dir = tmp.path().join(".tokf/filters");
+    fs::create_dir_all(&filters_dir).unwrap();
+    fs::write(filters_dir.join("a.toml"), "command = \"a\"").unwrap();
+    let search_dirs = vec![filters_dir];
+
+    let (path, count) = warm(&search_dirs, false).unwrap();
+    assert_eq!(count, 1);
+    let manifest = load_manifest(&path).unwrap();
+    assert!(is_cache_valid(&manifest, &search_dirs));
+    assert_eq!(
+        load_stats(&path),
+        CacheStats::default(),
+        "warming is not a lookup"
+    );
+}
@@ -0,0 +1,100 @@
+//! `_dir.toml`: defaults shared by every filter in one directory, so a
+//! `cargo/` folder can declare its common skips, `strip_ansi` or `[env]` once.
+//!
+//! The defaults are merged into each filter's TOML before it is parsed:
+//! `skip` lists are concatenated (defaults first), tables such as `[env]` are
+//! merged key by key, and any other key the filter sets wins. Only filters
+//! directly in the same directory are affected, not those in subdirectories.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// File name of the per-directory defaults.
+pub const DIR_DEFAULTS: &str = "_dir.toml";
+
+/// The defaults file that applies to the filter at `filter_path`.
+pub fn path_for(filter_path: &Path) -> Option<PathBuf> {
+    filter_path.parent().map(|dir| dir.join(DIR_DEFAULTS))
+}
+
+/// The parsed defaults for the filter at `filter_path`, if its directory has them.
+///
+/// # Errors
+///
+/// Returns an error if the defaults file exists but cannot be read or parsed.
+pub fn load_for(filter_path: &Path) -> anyhow::Result<Option<toml::Table>> {
+    let Some(path) = path_for(filter_path) else {
+        return Ok(None);
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(anyhow::Error::new(e).context(format!(
+                "failed to read directory defaults: {}",
+                path.display()
+            )));
+        }
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("failed to parse directory defaults: {}", path.display()))
+}
+
+/// Fold `defaults` into a filter's `table`; the filter's own values win.
+pub fn merge(table: &mut toml::Table, defaults: toml::Table) {
+    for (key, default) in defaults {
+        match (table.get_mut(&key), default) {
+            (None, default) => {
+                table.insert(key, default);
+            }
+            (Some(toml::Value::Array(own)), toml::Value::Array(mut shared)) if key == "skip" => {
+                shared.append(own);
+                *own = shared;
+            }
+            (Some(toml::Value::Table(own)), toml::Value::Table(shared)) => merge(own, shared),
+            (Some(_), _) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn skip_concatenates_tables_merge_and_filter_values_win() {
+        let mut filter = table(
+            "command = \"cargo build\"\nskip = [\"^warning: unused\"]\nstrip_ansi = false\n\
+             [env]\nCARGO_TERM_COLOR = \"always\"\n",
+        );
+        merge(
+            &mut filter,
+            table(
+                "skip = [\"^\\\\s+Compiling \"]\nstrip_ansi = true\ndedup = true\n\
+                 [env]\nCARGO_TERM_COLOR = \"never\"\nCARGO_TERM_PROGRESS_WHEN = \"never\"\n",
+            ),
+        );
+        assert_eq!(
+            filter,
+            table(
+                "command = \"cargo build\"\nskip = [\"^\\\\s+Compiling \", \"^warning: unused\"]\n\
+                 strip_ansi = false\ndedup = true\n\
+                 [env]\nCARGO_TERM_COLOR = \"always\"\nCARGO_TERM_PROGRESS_WHEN = \"never\"\n",
+            )
+        );
+    }
+
+    #[test]
+    fn keep_and_other_lists_are_not_concatenated() {
+        let mut filter = table("keep = [\"^error\"]");
+        merge(&mut filter, table("keep = [\"^warning\"]"));
+        assert_eq!(filter, table("keep = [\"^error\"]"));
+    }
+}
@@ -0,0 +1,464 @@
+//! Static checks behind `tokf check`: regexes that do not compile and
+//! template variables nothing defines, each reported at its TOML key.
+//!
+//! At runtime an invalid regex is silently dropped and an unknown variable
+//! renders empty, so these mistakes are otherwise invisible. The softer
+//! quality warnings of `tokf lint` live in [`warnings`].
+
+pub mod warnings;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::types::{Aggregates, FilterConfig, OutputBranch, ParseConfig};
+
+/// Variables every branch template can use besides sections and aggregates.
+const BUILTIN_VARS: &[&str] = &[
+    "output",
+    "stdout",
+    "stderr",
+    "timeout",
+    "args",
+    "flags",
+    "lines_removed",
+    "reduction_pct",
+];
+
+/// Variables bound per item inside `{#each}`.
+const ITEM_VARS: &[&str] = &["index", "value", "first_line", "rest", "line_count", "name"];
+
+/// `{name}`, `{name.prop}`, `{name | pipe}` and `{#if name}` / `{#each name}`.
+static VAR_REF: LazyLock<Option<Regex>> = LazyLock::new(|| {
+    Regex::new(
+        r"\{(?:#(?:if|unless|each)\s+)?\s*([A-Za-z_][A-Za-z0-9_]*)(?:\.[A-Za-z0-9_]+)?\s*[|}]",
+    )
+    .ok()
+});
+
+/// One problem in a filter file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Problem {
+    /// Key path of the offending value, e.g. `section[1].enter`.
+    pub key: String,
+    pub message: String,
+    /// 1-based line of the value, when it can be located.
+    pub line: Option<usize>,
+    /// 1-based column of the value, when it can be located.
+    pub column: Option<usize>,
+}
+
+impl Problem {
+    fn at(doc: Option<&toml_edit::ImDocument<&str>>, path: &KeyPath, message: String) -> Self {
+        let position = doc.and_then(|d| locate(d, path));
+        Self {
+            key: path.to_string(),
+            message,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{line}:{column}: ")?;
+        }
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// One step of a key path.
+#[derive(Debug, Clone)]
+enum Seg {
+    Key(&'static str),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+struct KeyPath(Vec<Seg>);
+
+impl KeyPath {
+    fn key(&self, key: &'static str) -> Self {
+        let mut next = self.clone();
+        next.0.push(Seg::Key(key));
+        next
+    }
+
+    fn index(&self, i: usize) -> Self {
+        let mut next = self.clone();
+        next.0.push(Seg::Index(i));
+        next
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, seg) in self.0.iter().enumerate() {
+            match seg {
+                Seg::Key(k) if i == 0 => write!(f, "{k}")?,
+                Seg::Key(k) => write!(f, ".{k}")?,
+                Seg::Index(n) => write!(f, "[{n}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check every regex and branch template in `config`, whose TOML text is
+/// `source` (used only to locate problems).
+pub fn lint(source: &str, config: &FilterConfig) -> Vec<Problem> {
+    let linter = Linter::run(config);
+    let doc = toml_edit::ImDocument::parse(source).ok();
+    linter
+        .found
+        .into_iter()
+        .map(|(path, message)| Problem::at(doc.as_ref(), &path, message))
+        .collect()
+}
+
+/// Every regex in `config` with its key path (`skip[0]`, `section[1].enter`,
+/// …), in the order the checks visit them.
+pub fn regexes(config: &FilterConfig) -> Vec<(String, String)> {
+    Linter::run(config)
+        .patterns
+        .into_iter()
+        .map(|(path, pattern)| (path.to_string(), pattern))
+        .collect()
+}
+
+struct Linter {
+    found: Vec<(KeyPath, String)>,
+    /// Every regex visited, valid or not.
+    patterns: Vec<(KeyPath, String)>,
+    /// `collect_as` names declared by `[[section]]`.
+    collected: HashSet<String>,
+}
+
+impl Linter {
+    fn run(config: &FilterConfig) -> Self {
+        let mut linter = Self {
+            found: Vec::new(),
+            patterns: Vec::new(),
+            collected: config
+                .section
+                .iter()
+                .filter_map(|s| s.collect_as.clone())
+                .collect(),
+        };
+        linter.filter(config);
+        linter
+    }
+
+    fn filter(&mut self, c: &FilterConfig) {
+        let root = KeyPath::default();
+        self.regexes(&root.key("skip"), &c.skip);
+        self.regexes(&root.key("keep"), &c.keep);
+        if let Some(extract) = &c.extract {
+            self.regex(root.key("extract").key("pattern"), &extract.pattern);
+        }
+        for (i, rule) in c.replace.iter().enumerate() {
+            self.regex(root.key("replace").index(i).key("pattern"), &rule.pattern);
+        }
+        for (i, section) in c.section.iter().enumerate() {
+            let path = root.key("section").index(i);
+            let fields = [
+                ("enter", &section.enter),
+                ("exit", &section.exit),
+                ("match", &section.match_pattern),
+                ("split_on", &section.split_on),
+            ];
+            for (key, pattern) in fields {
+                if let Some(pattern) = pattern {
+                    self.regex(path.key(key), pattern);
+                }
+            }
+        }
+        if let Some(parse) = &c.parse {
+            self.parse(&root.key("parse"), parse);
+        }
+        if let Some(trend) = &c.trend {
+            self.regex(root.key("trend").key("pattern"), &trend.pattern);
+        }
+        for (i, rule) in c.diagnostic.iter().enumerate() {
+            self.regex(
+                root.key("diagnostic").index(i).key("pattern"),
+                &rule.pattern,
+            );
+        }
+        if let Some(fallback) = &c.fallback {
+            let strategies = fallback.strategies();
+            for (i, strategy) in strategies.iter().enumerate() {
+                let path = if strategies.len() == 1 {
+                    root.key("fallback")
+                } else {
+                    root.key("fallback").index(i)
+                };
+                self.regexes(&path.key("skip"), &strategy.skip);
+            }
+        }
+        if let Some(branch) = &c.on_success {
+            self.branch(&root.key("on_success"), branch);
+        }
+        if let Some(branch) = &c.on_failure {
+            self.branch(&root.key("on_failure"), branch);
+        }
+        for (i, arg_branch) in c.branch.iter().enumerate() {
+            self.branch(&root.key("branch").index(i), &arg_branch.branch);
+        }
+    }
+
+    fn parse(&mut self, path: &KeyPath, parse: &ParseConfig) {
+        if let Some(line) = &parse.branch {
+            self.regex(path.key("branch").key("pattern"), &line.pattern);
+        }
+        if let Some(group) = &parse.group {
+            let path = path.key("group").key("key").key("pattern");
+            self.regex(path, &group.key.pattern);
+        }
+    }
+
+    fn branch(&mut self, path: &KeyPath, b: &OutputBranch) {
+        self.regexes(&path.key("skip"), &b.skip);
+        self.regexes(&path.key("keep"), &b.keep);
+        if let Some(extract) = &b.extract {
+            self.regex(path.key("extract").key("pattern"), &extract.pattern);
+        }
+        if let Some(tail) = &b.tail_adaptive {
+            self.regex(path.key("tail_adaptive").key("pattern"), &tail.pattern);
+        }
+        let mut defined = HashSet::new();
+        for (i, agg) in b.aggregate.iter().flat_map(Aggregates::rules).enumerate() {
+            let key = match b.aggregate {
+                Some(Aggregates::Multiple(_)) => path.key("aggregate").index(i),
+                _ => path.key("aggregate"),
+            };
+            self.regex(key.key("pattern"), &agg.pattern);
+            if !self.collected.contains(&agg.from) {
+                self.found.push((
+                    key.key("from"),
+                    format!("no [[section]] has collect_as = \"{}\"", agg.from),
+                ));
+            }
+            defined.extend(agg.names().cloned());
+        }
+        if let Some(template) = &b.output {
+            self.template(&path.key("output"), template, &defined);
+        }
+    }
+
+    fn template(&mut self, path: &KeyPath, template: &str, defined: &HashSet<String>) {
+        let mut reported = HashSet::new();
+        for name in template_refs(template) {
+            let known = BUILTIN_VARS.contains(&name)
+                || ITEM_VARS.contains(&name)
+                || is_positional_arg(name)
+                || self.collected.contains(name)
+                || defined.contains(name);
+            if !known && reported.insert(name.to_string()) {
+                self.found.push((
+                    path.clone(),
+                    format!("unknown template variable {{{name}}} (not a collect_as name, aggregate, or built-in)"),
+                ));
+            }
+        }
+    }
+
+    fn regexes(&mut self, path: &KeyPath, patterns: &[String]) {
+        for (i, pattern) in patterns.iter().enumerate() {
+            self.regex(path.index(i), pattern);
+        }
+    }
+
+    fn regex(&mut self, path: KeyPath, pattern: &str) {
+        self.patterns.push((path.clone(), pattern.to_string()));
+        if let Err(e) = Regex::new(pattern) {
+            // Keep the final "error: …" line; the caret diagram above it
+            // does not line up once printed after the key path.
+            let text = e.to_string();
+            let reason = text.lines().last().unwrap_or_default();
+            let reason = reason.strip_prefix("error: ").unwrap_or(reason);
+            self.found.push((path, format!("invalid regex: {reason}")));
+        }
+    }
+}
+
+/// Base names of the variables a template references, in order.
+fn template_refs(template: &str) -> impl Iterator<Item = &str> {
+    VAR_REF
+        .iter()
+        .flat_map(move |re| re.captures_iter(template))
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
+}
+
+/// `arg1`, `arg2`, …
+fn is_positional_arg(name: &str) -> bool {
+    name.strip_prefix("arg")
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Line and column of the value at `path`. `[[branch]]` fields are looked up
+/// directly on the branch table, matching its flattened layout.
+fn locate(doc: &toml_edit::ImDocument<&str>, path: &KeyPath) -> Option<(usize, usize)> {
+    let mut item = doc.as_item();
+    for seg in &path.0 {
+        item = match seg {
+            Seg::Key(k) => item.get(*k)?,
+            Seg::Index(i) => item.get(*i)?,
+        };
+    }
+    let offset = item.span()?.start;
+    let before = &doc.raw()[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(offset, |nl| offset - nl - 1) + 1;
+    Some((line, column))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn lint_str(src: &str) -> Vec<String> {
+        let config: FilterConfig = toml::from_str(src).unwrap();
+        lint(src, &config).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn valid_filter_has_no_problems() {
+        let problems = lint_str(
+            r#"
+command = "cargo test"
+skip = ["^\\s*Compiling "]
+
+[[section]]
+enter = "^failures:$"
+exit = "^test result:"
+collect_as = "failures"
+
+[on_failure]
+output = "{failures.count} failed ({lines_removed} lines hidden)\n{#each failures}{index}. {name}\n{/each}{arg1}"
+"#,
+        );
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[test]
+    fn invalid_regexes_are_reported_at_their_key() {
+        let problems = lint_str(
+            r#"command = "make"
+skip = ["ok", "(unclosed"]
+
+[[section]]
+enter = "["
+"#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "2:15: skip[1]: invalid regex: unclosed group",
+                "5:9: section[0].enter: invalid regex: unclosed character class",
+            ]
+        );
+    }
+
+    #[test]
+    fn regexes_lists_every_pattern_with_its_key() {
+        let config: FilterConfig = toml::from_str(
+            "command = \"x\"\nskip = [\"a\"]\n[[section]]\nenter = \"b\"\n[on_failure]\nkeep = [\"(c\"]",
+        )
+        .unwrap();
+        assert_eq!(
+            regexes(&config),
+            vec![
+                ("skip[0]".to_string(), "a".to_string()),
+                ("section[0].enter".to_string(), "b".to_string()),
+                ("on_failure.keep[0]".to_string(), "(c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn branch_regexes_are_checked() {
+        let problems = lint_str(
+            r#"command = "x"
+
+[[branch]]
+when_args = ["--doc"]
+keep = ["+"]
+"#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("5:9: branch[0].keep[0]: invalid regex"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn diagnostic_regexes_are_checked() {
+        let problems = lint_str(
+            r#"command = "x"
+
+[[diagnostic]]
+pattern = "(?P<file>"
+"#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("4:11: diagnostic[0].pattern: invalid regex"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn unknown_template_variables_are_reported() {
+        let problems = lint_str(
+            r#"command = "x"
+
+[[section]]
+match = "^error"
+collect_as = "errors"
+
+[on_failure]
+output = "{erors.count} errors: {errors}\n{#if warnings}warn{/if}"
+"#,
+        );
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("on_failure.output: unknown template variable {erors}"));
+        assert!(problems[1].contains("{warnings}"));
+    }
+
+    #[test]
+    fn aggregate_defines_variables_and_needs_a_section() {
+        let problems = lint_str(
+            r#"command = "x"
+
+[on_success]
+output = "{passed} passed"
+
+[on_success.aggregate]
+from = "summary"
+pattern = "(\\d+) passed"
+sum = "passed"
+"#,
+        );
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("on_success.aggregate.from: no [[section]]"));
+    }
+
+    #[test]
+    fn stdlib_filters_pass() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("filters");
+        let pattern = format!("{}/**/*.toml", dir.display());
+        for path in glob::glob(&pattern).unwrap().flatten() {
+            let src = std::fs::read_to_string(&path).unwrap();
+            let config: FilterConfig = toml::from_str(&src).unwrap();
+            let problems = lint(&src, &config);
+            assert!(problems.is_empty(), "{}: {problems:?}", path.display());
+        }
+    }
+}
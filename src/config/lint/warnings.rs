@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use super::{KeyPath, Problem, template_refs};
+use crate::config::types::{Aggregates, CommandPattern, FilterConfig, OutputBranch, RenderMode};
+
+/// A `tokf lint` finding: a [`Problem`] tagged with the rule that found it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Warning {
+    /// Stable rule name, e.g. `unused-section`.
+    pub rule: &'static str,
+    #[serde(flatten)]
+    pub problem: Problem,
+}
+
+/// Quality warnings for `config`, whose TOML text is `source`.
+///
+/// `fixtures` are sample outputs of the command; `skip` patterns matching no
+/// line of any fixture are reported, and the check is off without fixtures.
+pub fn warnings(source: &str, config: &FilterConfig, fixtures: &[String]) -> Vec<Warning> {
+    let mut found: Vec<(&'static str, KeyPath, String)> = Vec::new();
+    let root = KeyPath::default();
+
+    broad_command(config, &root, &mut found);
+    unreachable_match_output(config, &root, &mut found);
+    broad_patterns(
+        &root.key("skip"),
+        &config.skip,
+        "drops every line",
+        &mut found,
+    );
+    broad_patterns(
+        &root.key("keep"),
+        &config.keep,
+        "keeps every line",
+        &mut found,
+    );
+    unmatched_skip(&root.key("skip"), &config.skip, fixtures, &mut found);
+    for (path, branch) in branches(config, &root) {
+        if branch.output.is_some() && branch.extract.is_some() {
+            found.push((
+                "output-with-extract",
+                path.key("extract"),
+                "ignored: `output` is set, so the branch renders its template instead".to_string(),
+            ));
+        }
+        broad_patterns(
+            &path.key("skip"),
+            &branch.skip,
+            "drops every line",
+            &mut found,
+        );
+        broad_patterns(
+            &path.key("keep"),
+            &branch.keep,
+            "keeps every line",
+            &mut found,
+        );
+        unmatched_skip(&path.key("skip"), &branch.skip, fixtures, &mut found);
+    }
+    unused_sections(config, &root, &mut found);
+
+    let doc = toml_edit::ImDocument::parse(source).ok();
+    found
+        .into_iter()
+        .map(|(rule, path, message)| Warning {
+            rule,
+            problem: Problem::at(doc.as_ref(), &path, message),
+        })
+        .collect()
+}
+
+type Found = Vec<(&'static str, KeyPath, String)>;
+
+/// `on_success`, `on_failure` and every `[[branch]]`, with their key paths.
+fn branches<'a>(config: &'a FilterConfig, root: &KeyPath) -> Vec<(KeyPath, &'a OutputBranch)> {
+    let mut out = Vec::new();
+    if let Some(b) = &config.on_success {
+        out.push((root.key("on_success"), b));
+    }
+    if let Some(b) = &config.on_failure {
+        out.push((root.key("on_failure"), b));
+    }
+    for (i, b) in config.branch.iter().enumerate() {
+        out.push((root.key("branch").index(i), &b.branch));
+    }
+    out
+}
+
+/// A command pattern whose first word is a wildcard claims every command.
+fn broad_command(config: &FilterConfig, root: &KeyPath, found: &mut Found) {
+    let patterns = config.command.patterns();
+    for (i, pattern) in patterns.iter().enumerate() {
+        let first = pattern.split_whitespace().next().unwrap_or_default();
+        if matches!(first, "" | "*" | "**" | "?") {
+            let path = match config.command {
+                CommandPattern::Single(_) => root.key("command"),
+                CommandPattern::Multiple(_) => root.key("command").index(i),
+            };
+            found.push((
+                "broad-pattern",
+                path,
+                format!("\"{pattern}\" starts with a wildcard and matches any command"),
+            ));
+        }
+    }
+}
+
+/// A rule whose `contains` includes an earlier rule's never fires: the
+/// earlier one always matches first.
+fn unreachable_match_output(config: &FilterConfig, root: &KeyPath, found: &mut Found) {
+    for (j, later) in config.match_output.iter().enumerate() {
+        if let Some(i) = config.match_output[..j]
+            .iter()
+            .position(|earlier| later.contains.contains(&earlier.contains))
+        {
+            found.push((
+                "unreachable-match-output",
+                root.key("match_output").index(j),
+                format!("never fires: match_output[{i}] matches whenever this rule would"),
+            ));
+        }
+    }
+}
+
+/// Patterns matching the empty string match every line.
+fn broad_patterns(path: &KeyPath, patterns: &[String], effect: &str, found: &mut Found) {
+    for (i, pattern) in patterns.iter().enumerate() {
+        if Regex::new(pattern).is_ok_and(|re| re.is_match("")) {
+            found.push((
+                "broad-pattern",
+                path.index(i),
+                format!("\"{pattern}\" matches any line and {effect}"),
+            ));
+        }
+    }
+}
+
+fn unmatched_skip(path: &KeyPath, patterns: &[String], fixtures: &[String], found: &mut Found) {
+    if fixtures.is_empty() {
+        return;
+    }
+    for (i, pattern) in patterns.iter().enumerate() {
+        let Ok(re) = Regex::new(pattern) else {
+            continue;
+        };
+        let matched = fixtures
+            .iter()
+            .flat_map(|f| f.lines())
+            .any(|line| re.is_match(line));
+        if !matched {
+            found.push((
+                "unmatched-skip",
+                path.index(i),
+                format!("\"{pattern}\" matches no line of the given fixtures"),
+            ));
+        }
+    }
+}
+
+/// Sections whose lines no template, aggregate, or markdown render reads.
+fn unused_sections(config: &FilterConfig, root: &KeyPath, found: &mut Found) {
+    let branches = branches(config, root);
+    if branches
+        .iter()
+        .any(|(_, b)| b.render == Some(RenderMode::Markdown))
+    {
+        return;
+    }
+    let mut used: HashSet<&str> = HashSet::new();
+    for (_, b) in &branches {
+        if let Some(template) = &b.output {
+            used.extend(template_refs(template));
+        }
+        for agg in b.aggregate.iter().flat_map(Aggregates::rules) {
+            used.insert(&agg.from);
+        }
+    }
+    for (i, section) in config.section.iter().enumerate() {
+        let path = root.key("section").index(i);
+        match &section.collect_as {
+            None => found.push((
+                "unused-section",
+                path,
+                "has no collect_as, so nothing can read what it collects".to_string(),
+            )),
+            Some(name) if !used.contains(name.as_str()) => found.push((
+                "unused-section",
+                path.key("collect_as"),
+                format!("\"{name}\" is not used by any branch template or aggregate"),
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn lint(src: &str, fixtures: &[&str]) -> Vec<String> {
+        let config: FilterConfig = toml::from_str(src).unwrap();
+        let fixtures: Vec<String> = fixtures.iter().map(ToString::to_string).collect();
+        warnings(src, &config, &fixtures)
+            .iter()
+            .map(|w| format!("{} {}", w.rule, w.problem))
+            .collect()
+    }
+
+    #[test]
+    fn clean_filter_has_no_warnings() {
+        let src = r#"
+command = "cargo build"
+skip = ["^\\s*Compiling "]
+
+[on_failure]
+tail = 20
+"#;
+        assert!(lint(src, &["   Compiling foo v0.1.0"]).is_empty());
+    }
+
+    #[test]
+    fn later_match_output_containing_earlier_is_unreachable() {
+        let src = r#"command = "git push"
+match_output = [
+  { contains = "rejected", output = "rejected" },
+  { contains = "[rejected]", output = "never" },
+]
+"#;
+        assert_eq!(
+            lint(src, &[]),
+            vec![
+                "unreachable-match-output 4:3: match_output[1]: never fires: match_output[0] matches whenever this rule would"
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_matching_no_fixture_line() {
+        let src = "command = \"make\"\nskip = [\"^make\\\\[\", \"^never$\"]\n";
+        let found = lint(src, &["make[1]: Entering\nbuilt"]);
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(
+            found[0].starts_with("unmatched-skip 2:21: skip[1]:"),
+            "{found:?}"
+        );
+        assert!(lint(src, &[]).is_empty(), "off without fixtures");
+    }
+
+    #[test]
+    fn output_with_extract_and_broad_patterns() {
+        let src = r#"command = "*"
+
+[on_success]
+output = "ok"
+skip = [".*"]
+
+[on_success.extract]
+pattern = "(\\d+)"
+output = "{1}"
+"#;
+        let found = lint(src, &[]);
+        let rules: Vec<&str> = found.iter().map(|w| w.split(' ').next().unwrap()).collect();
+        assert_eq!(
+            rules,
+            vec!["broad-pattern", "output-with-extract", "broad-pattern"],
+            "{found:?}"
+        );
+    }
+
+    #[test]
+    fn unused_sections_are_reported() {
+        let src = r#"command = "cargo test"
+
+[[section]]
+enter = "^failures:"
+collect_as = "failures"
+
+[[section]]
+match = "^test result"
+collect_as = "summary"
+
+[[section]]
+match = "^warning"
+
+[on_failure]
+output = "{failures}"
+"#;
+        let found = lint(src, &[]);
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!(found[0].contains("section[1].collect_as: \"summary\" is not used"));
+        assert!(found[1].contains("section[2]: has no collect_as"));
+    }
+}
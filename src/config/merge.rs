@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use super::ResolvedFilter;
+
+/// Keep one filter per canonical command pattern, in sorted order.
+///
+/// The first filter for a command wins outright unless it sets `merge = true`.
+/// A merging filter is folded into the next filter for the same command: that
+/// filter becomes the winner, with the merging filter's `skip` patterns
+/// appended to its own. Merges chain (local over user over built-in) until a
+/// filter without `merge` is reached; a merging filter with nothing below it
+/// is used as-is.
+pub(super) fn dedup(filters: Vec<ResolvedFilter>) -> Vec<ResolvedFilter> {
+    let mut out: Vec<ResolvedFilter> = Vec::with_capacity(filters.len());
+    let mut slots: HashMap<String, usize> = HashMap::new();
+    for filter in filters {
+        let key = filter.config.command.first().to_string();
+        match slots.get(&key) {
+            None => {
+                slots.insert(key, out.len());
+                out.push(filter);
+            }
+            Some(&i) if out[i].config.merge => {
+                let overlay = std::mem::replace(&mut out[i], filter);
+                absorb(&mut out[i], overlay);
+            }
+            Some(_) => {}
+        }
+    }
+    out
+}
+
+fn absorb(base: &mut ResolvedFilter, overlay: ResolvedFilter) {
+    base.config.skip.extend(overlay.config.skip);
+    let mut overlays = overlay.overlays;
+    overlays.push(overlay.source_path);
+    overlays.append(&mut base.overlays);
+    base.overlays = overlays;
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn filter(path: &str, priority: u8, toml_src: &str) -> ResolvedFilter {
+        ResolvedFilter {
+            config: toml::from_str(toml_src).unwrap(),
+            source_path: PathBuf::from(path),
+            relative_path: PathBuf::from(path),
+            priority,
+            overlays: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn without_merge_first_filter_wins() {
+        let out = dedup(vec![
+            filter("local", 0, "command = \"git log\"\nskip = [\"a\"]"),
+            filter("builtin", u8::MAX, "command = \"git log\"\nskip = [\"b\"]"),
+        ]);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].config.skip, vec!["a"]);
+        assert!(out[0].overlays.is_empty());
+    }
+
+    #[test]
+    fn merge_chains_skip_into_lower_filter() {
+        let out = dedup(vec![
+            filter(
+                "local",
+                0,
+                "command = \"git log\"\nmerge = true\nskip = [\"a\"]",
+            ),
+            filter(
+                "user",
+                1,
+                "command = \"git log\"\nmerge = true\nskip = [\"b\"]",
+            ),
+            filter("other", 1, "command = \"git push\""),
+            filter(
+                "builtin",
+                u8::MAX,
+                "command = \"git log\"\nskip = [\"c\"]\nrun = \"git log -5\"",
+            ),
+        ]);
+        assert_eq!(out.len(), 2);
+        let log = &out[0];
+        assert_eq!(log.source_path, PathBuf::from("builtin"));
+        assert_eq!(log.config.skip, vec!["c", "b", "a"]);
+        assert_eq!(log.config.run.as_deref(), Some("git log -5"));
+        assert_eq!(
+            log.overlays,
+            vec![PathBuf::from("local"), PathBuf::from("user")]
+        );
+        assert!(!log.config.merge);
+    }
+
+    #[test]
+    fn merge_without_base_is_used_as_is() {
+        let out = dedup(vec![filter(
+            "local",
+            0,
+            "command = \"my tool\"\nmerge = true\nskip = [\"x\"]",
+        )]);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].config.skip, vec!["x"]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Optional `pack.toml` manifests describing a directory of filters.
+//!
+//! A manifest applies to the directory it sits in and everything below it.
+//! Its `min_tokf_version` lets a pack opt out of older tokf builds: discovery
+//! skips the whole subtree with a note on stderr instead of loading filters
+//! that use keys this build does not understand.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::discover_filter_files;
+
+/// File name of a pack manifest; never treated as a filter.
+pub const PACK_MANIFEST: &str = "pack.toml";
+
+/// The contents of a `pack.toml`. Every key is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// Oldest tokf release the pack works with (`0.2.0`).
+    pub min_tokf_version: Option<String>,
+}
+
+impl PackManifest {
+    /// Whether this build of tokf satisfies `min_tokf_version`.
+    pub fn is_compatible(&self) -> bool {
+        self.min_tokf_version
+            .as_deref()
+            .is_none_or(|min| meets_min_version(env!("CARGO_PKG_VERSION"), min))
+    }
+}
+
+/// Read the manifest in `dir`, if there is one. An unreadable or invalid
+/// manifest is reported on stderr and treated as absent.
+pub fn load_manifest(dir: &Path) -> Option<PackManifest> {
+    let path = dir.join(PACK_MANIFEST);
+    let content = std::fs::read_to_string(&path).ok()?;
+    match toml::from_str(&content) {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            tracing::warn!("ignoring invalid {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// `current >= min`, comparing dotted numeric versions (`0.1.5`).
+///
+/// Missing components count as 0 and pre-release or build suffixes are
+/// ignored. A `min` that does not parse never blocks a pack.
+pub fn meets_min_version(current: &str, min: &str) -> bool {
+    match (parse_version(current), parse_version(min)) {
+        (Some(current), Some(min)) => current >= min,
+        _ => true,
+    }
+}
+
+fn parse_version(v: &str) -> Option<[u64; 3]> {
+    let core = v.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = [0; 3];
+    for (i, part) in core.split('.').enumerate() {
+        *parts.get_mut(i)? = part.parse().ok()?;
+    }
+    Some(parts)
+}
+
+/// A `pack.toml` found under a search dir.
+#[derive(Debug)]
+pub struct DiscoveredPack {
+    pub manifest: PackManifest,
+    /// Directory holding the manifest.
+    pub dir: PathBuf,
+    /// Index of the search dir it was found in (0 = repo-local, 1 = user).
+    pub priority: u8,
+    /// Filter files in the pack; 0 when it is skipped as incompatible.
+    pub filters: usize,
+}
+
+impl DiscoveredPack {
+    /// The declared name, or the directory name when none is declared.
+    pub fn display_name(&self) -> String {
+        self.manifest.name.clone().unwrap_or_else(|| {
+            self.dir.file_name().map_or_else(
+                || self.dir.display().to_string(),
+                |n| n.to_string_lossy().into_owned(),
+            )
+        })
+    }
+}
+
+/// Every pack manifest under `search_dirs`, in priority then path order.
+/// Packs nested inside an incompatible pack are not reported.
+pub fn discover_packs(search_dirs: &[PathBuf]) -> Vec<DiscoveredPack> {
+    let mut packs = Vec::new();
+    for (priority, dir) in search_dirs.iter().enumerate() {
+        let priority = u8::try_from(priority).unwrap_or(u8::MAX);
+        collect_packs(dir, priority, &mut packs);
+    }
+    packs
+}
+
+fn collect_packs(dir: &Path, priority: u8, packs: &mut Vec<DiscoveredPack>) {
+    let manifest = if dir.join(PACK_MANIFEST).is_file() {
+        load_manifest(dir)
+    } else {
+        None
+    };
+    if let Some(manifest) = manifest {
+        let compatible = manifest.is_compatible();
+        let filters = if compatible {
+            discover_filter_files(dir).len()
+        } else {
+            0
+        };
+        packs.push(DiscoveredPack {
+            manifest,
+            dir: dir.to_path_buf(),
+            priority,
+            filters,
+        });
+        if !compatible {
+            return;
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    subdirs.sort();
+    for sub in subdirs {
+        collect_packs(&sub, priority, packs);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn version_comparison() {
+        assert!(meets_min_version("0.1.5", "0.1.5"));
+        assert!(meets_min_version("0.2.0", "0.1.9"));
+        assert!(meets_min_version("1.0.0", "0.10"));
+        assert!(!meets_min_version("0.1.5", "0.2"));
+        assert!(!meets_min_version("0.9.9", "v1.0.0"));
+        assert!(meets_min_version("1.2.0-beta.1", "1.2.0"));
+        assert!(meets_min_version("0.1.5", "not-a-version"));
+    }
+
+    #[test]
+    fn manifest_parses_all_keys() {
+        let m: PackManifest = toml::from_str(
+            r#"
+name = "acme-ci"
+version = "1.2.0"
+author = "Acme Platform Team"
+description = "CI filters"
+min_tokf_version = "99.0"
+"#,
+        )
+        .unwrap();
+        assert_eq!(m.name.as_deref(), Some("acme-ci"));
+        assert_eq!(m.author.as_deref(), Some("Acme Platform Team"));
+        assert!(!m.is_compatible());
+        assert!(PackManifest::default().is_compatible());
+    }
+
+    #[test]
+    fn discover_packs_reports_nested_manifests() {
+        let dir = TempDir::new().unwrap();
+        let team = dir.path().join("team");
+        fs::create_dir_all(team.join("git")).unwrap();
+        fs::write(team.join(PACK_MANIFEST), "version = \"1.0\"").unwrap();
+        fs::write(team.join("git/log.toml"), "command = \"git log\"").unwrap();
+        let future = dir.path().join("future");
+        fs::create_dir_all(&future).unwrap();
+        fs::write(
+            future.join(PACK_MANIFEST),
+            "name = \"next\"\nmin_tokf_version = \"99.0\"",
+        )
+        .unwrap();
+        fs::write(future.join("x.toml"), "command = \"x\"").unwrap();
+
+        let packs = discover_packs(&[dir.path().to_path_buf()]);
+        assert_eq!(packs.len(), 2);
+        assert_eq!(packs[0].display_name(), "next");
+        assert_eq!(packs[0].filters, 0);
+        assert_eq!(packs[1].display_name(), "team");
+        assert_eq!(packs[1].filters, 1);
+    }
+}
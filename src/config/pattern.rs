@@ -0,0 +1,295 @@
+//! Command pattern syntax shared by filter matching and rewrite rules.
+//!
+//! A pattern is a whitespace-separated list of tokens:
+//! - a literal word (`git`, `push`)
+//! - `*`: any single non-empty token
+//! - `**`: any number of tokens, including none
+//! - `{a|b|c}` inside a token: one of the alternatives (`{yarn|npm|pnpm}`, `py{test|.test}`)
+//! - a trailing `?` on a token: the token is optional (`npx? tsc`); a bare `?`
+//!   is an optional token of any value
+
+use std::fmt::Write as _;
+
+use super::env_prefix_len;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Word {
+    /// One of these literal spellings.
+    Literal(Vec<String>),
+    /// Any single non-empty token.
+    Any,
+    /// Any number of tokens.
+    Rest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    word: Word,
+    optional: bool,
+}
+
+fn parse(pattern: &str) -> Vec<Token> {
+    pattern.split_whitespace().map(parse_token).collect()
+}
+
+fn parse_token(raw: &str) -> Token {
+    match raw {
+        "**" => {
+            return Token {
+                word: Word::Rest,
+                optional: false,
+            };
+        }
+        "*" => {
+            return Token {
+                word: Word::Any,
+                optional: false,
+            };
+        }
+        "?" => {
+            return Token {
+                word: Word::Any,
+                optional: true,
+            };
+        }
+        _ => {}
+    }
+    if let Some(inner) = raw.strip_suffix('?') {
+        let mut token = parse_token(inner);
+        token.optional = true;
+        return token;
+    }
+    Token {
+        word: Word::Literal(expand_braces(raw)),
+        optional: false,
+    }
+}
+
+/// Expand `{a|b}` groups into every spelling they allow. A token with an
+/// unclosed `{` is taken literally.
+fn expand_braces(raw: &str) -> Vec<String> {
+    let Some(open) = raw.find('{') else {
+        return vec![raw.to_string()];
+    };
+    let Some(close) = raw[open..].find('}').map(|i| open + i) else {
+        return vec![raw.to_string()];
+    };
+    let (head, group, tail) = (&raw[..open], &raw[open + 1..close], &raw[close + 1..]);
+    let tails = expand_braces(tail);
+    group
+        .split('|')
+        .flat_map(|alt| tails.iter().map(move |t| format!("{head}{alt}{t}")))
+        .collect()
+}
+
+/// Count literal, required tokens — higher = more specific.
+pub fn pattern_specificity(pattern: &str) -> usize {
+    parse(pattern)
+        .iter()
+        .filter(|t| !t.optional && matches!(t.word, Word::Literal(_)))
+        .count()
+}
+
+/// Every spelling the first word of a command matching `pattern` can have.
+///
+/// A leading optional token contributes its spellings and the next token's
+/// too (`npx? tsc` → `npx`, `tsc`). Empty when the first word can be
+/// anything (`*`, `**`, `?`).
+pub fn leading_programs(pattern: &str) -> Vec<String> {
+    let mut programs = Vec::new();
+    for token in parse(pattern) {
+        let Word::Literal(spellings) = token.word else {
+            return Vec::new();
+        };
+        programs.extend(spellings);
+        if !token.optional {
+            break;
+        }
+    }
+    programs
+}
+
+/// Returns `words_consumed` if pattern matches a prefix of `words`, else `None`.
+///
+/// See the module docs for the token syntax. Optional tokens and `**` match
+/// greedily, backing off when the rest of the pattern would not match.
+/// Trailing args beyond the pattern are allowed (prefix semantics).
+/// Leading `VAR=value` tokens are skipped for matching but counted in
+/// `words_consumed`, so callers keep them as part of the command.
+pub fn pattern_matches_prefix(pattern: &str, words: &[&str]) -> Option<usize> {
+    let env_len = env_prefix_len(words);
+    let tokens = parse(pattern);
+    if tokens.is_empty() {
+        return None;
+    }
+    match_tokens(&tokens, &words[env_len..]).map(|consumed| env_len + consumed)
+}
+
+fn match_tokens(tokens: &[Token], words: &[&str]) -> Option<usize> {
+    let Some((token, rest)) = tokens.split_first() else {
+        return Some(0);
+    };
+    if token.word == Word::Rest {
+        return (0..=words.len())
+            .rev()
+            .find_map(|n| match_tokens(rest, &words[n..]).map(|m| n + m));
+    }
+    let here = words
+        .first()
+        .filter(|w| token_matches(&token.word, w))
+        .and_then(|_| match_tokens(rest, &words[1..]))
+        .map(|m| m + 1);
+    if here.is_some() || !token.optional {
+        return here;
+    }
+    match_tokens(rest, words)
+}
+
+fn token_matches(word: &Word, token: &str) -> bool {
+    match word {
+        Word::Literal(spellings) => spellings.iter().any(|s| s == token),
+        Word::Any | Word::Rest => !token.is_empty(),
+    }
+}
+
+/// Build a rewrite regex pattern for a command pattern string.
+///
+/// `*` becomes `\S+`, `**` any run of tokens, `{a|b}` an alternation, and
+/// optional tokens an optional group. Leading `VAR=value` assignments are
+/// allowed and stay part of the match.
+pub fn command_pattern_to_regex(pattern: &str) -> String {
+    let mut body = String::new();
+    let mut started = false;
+    for token in parse(pattern) {
+        let fragment = match &token.word {
+            Word::Literal(spellings) if spellings.len() == 1 => regex::escape(&spellings[0]),
+            Word::Literal(spellings) => {
+                let escaped: Vec<String> = spellings.iter().map(|s| regex::escape(s)).collect();
+                format!("(?:{})", escaped.join("|"))
+            }
+            Word::Any | Word::Rest => r"\S+".to_string(),
+        };
+        let repeat = match (&token.word, token.optional) {
+            (Word::Rest, _) => "*",
+            (_, true) => "?",
+            (_, false) => "",
+        };
+        if repeat.is_empty() {
+            if started {
+                body.push_str(r"\ ");
+            }
+            body.push_str(&fragment);
+            started = true;
+        } else if started {
+            let _ = write!(body, r"(?:\ {fragment}){repeat}");
+        } else {
+            let _ = write!(body, r"(?:{fragment}\ ){repeat}");
+        }
+    }
+    format!(r"^(?:[A-Za-z_][A-Za-z0-9_]*=\S*\s+)*{body}(\s.*)?$")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Vec<&str> {
+        s.split_whitespace().collect()
+    }
+
+    #[test]
+    fn braces_expand_to_alternatives() {
+        assert_eq!(expand_braces("{yarn|npm}"), vec!["yarn", "npm"]);
+        assert_eq!(expand_braces("py{test|.test}"), vec!["pytest", "py.test"]);
+        assert_eq!(expand_braces("{a|b}{1|2}").len(), 4);
+        assert_eq!(expand_braces("x{y"), vec!["x{y"]);
+    }
+
+    #[test]
+    fn alternation_matches_any_spelling() {
+        let p = "{yarn|npm|pnpm} test";
+        assert_eq!(
+            pattern_matches_prefix(p, &words("pnpm test --watch")),
+            Some(2)
+        );
+        assert_eq!(pattern_matches_prefix(p, &words("yarn test")), Some(2));
+        assert_eq!(pattern_matches_prefix(p, &words("bun test")), None);
+    }
+
+    #[test]
+    fn optional_token_may_be_absent() {
+        assert_eq!(
+            pattern_matches_prefix("npx? tsc", &words("npx tsc")),
+            Some(2)
+        );
+        assert_eq!(
+            pattern_matches_prefix("npx? tsc", &words("tsc --noEmit")),
+            Some(1)
+        );
+        assert_eq!(
+            pattern_matches_prefix("cargo ? test", &words("cargo +nightly test")),
+            Some(3)
+        );
+        assert_eq!(
+            pattern_matches_prefix("cargo ? test", &words("cargo test")),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn double_star_spans_any_number_of_tokens() {
+        let p = "docker ** logs";
+        assert_eq!(pattern_matches_prefix(p, &words("docker logs x")), Some(2));
+        assert_eq!(
+            pattern_matches_prefix(p, &words("docker compose -f a.yml logs")),
+            Some(5)
+        );
+        assert_eq!(pattern_matches_prefix(p, &words("docker ps")), None);
+        assert_eq!(
+            pattern_matches_prefix("make **", &words("make all test")),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn specificity_ignores_wildcards_and_optional_tokens() {
+        assert_eq!(pattern_specificity("{yarn|npm} test"), 2);
+        assert_eq!(pattern_specificity("npx? tsc"), 1);
+        assert_eq!(pattern_specificity("docker ** logs"), 2);
+    }
+
+    #[test]
+    fn leading_programs_expand_alternation_and_optional_tokens() {
+        assert_eq!(leading_programs("git push"), vec!["git"]);
+        assert_eq!(leading_programs("{yarn|npm} test"), vec!["yarn", "npm"]);
+        assert_eq!(leading_programs("npx? tsc"), vec!["npx", "tsc"]);
+        assert!(leading_programs("* build").is_empty());
+        assert!(leading_programs("npx? *").is_empty());
+        assert!(leading_programs("").is_empty());
+    }
+
+    #[test]
+    fn regex_supports_extended_syntax() {
+        let re = |p: &str| regex::Regex::new(&command_pattern_to_regex(p)).unwrap();
+
+        let alt = re("{yarn|npm|pnpm} test");
+        assert!(alt.is_match("pnpm test --watch"));
+        assert!(!alt.is_match("bun test"));
+
+        let opt = re("npx? tsc");
+        assert!(opt.is_match("npx tsc"));
+        assert!(opt.is_match("tsc --noEmit"));
+        assert!(!opt.is_match("npx eslint"));
+
+        let rest = re("docker ** logs");
+        assert!(rest.is_match("docker logs web"));
+        assert!(rest.is_match("docker compose -f a.yml logs"));
+        assert!(!rest.is_match("docker ps"));
+
+        let trailing = re("cargo test?");
+        assert!(trailing.is_match("cargo"));
+        assert!(trailing.is_match("cargo test --lib"));
+        assert!(!trailing.is_match("cargotest"));
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use super::ResolvedFilter;
+
+/// Project-level discovery policy from `config.toml`: which filters to drop
+/// and whether built-in filters may win over local and user ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterPolicy {
+    /// Filter names or globs (`git/log`, `docker/*`) to leave out of discovery.
+    pub disable: Vec<String>,
+    /// When false, built-in filters rank ahead of local and user ones.
+    pub prefer_local: bool,
+}
+
+impl Default for FilterPolicy {
+    fn default() -> Self {
+        Self {
+            disable: Vec::new(),
+            prefer_local: true,
+        }
+    }
+}
+
+impl FilterPolicy {
+    /// Whether `filter` is named by an entry in `disable`.
+    ///
+    /// Entries match the relative path without extension; invalid globs
+    /// fall back to an exact name comparison.
+    pub fn is_disabled(&self, filter: &ResolvedFilter) -> bool {
+        if self.disable.is_empty() {
+            return false;
+        }
+        let name = filter.relative_path.with_extension("");
+        let name = name.to_string_lossy();
+        self.disable.iter().any(|entry| {
+            let entry = entry.strip_suffix(".toml").unwrap_or(entry);
+            glob::Pattern::new(entry).map_or(entry == name, |p| p.matches(&name))
+        })
+    }
+
+    /// Sort rank for a filter's priority tier: lower wins. With
+    /// `prefer_local = false` the built-in tier moves to the front.
+    pub const fn rank(&self, priority: u8) -> u16 {
+        if !self.prefer_local && priority == u8::MAX {
+            0
+        } else {
+            priority as u16 + 1
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn filter(relative_path: &str) -> ResolvedFilter {
+        ResolvedFilter {
+            config: toml::from_str("command = \"x\"").unwrap(),
+            source_path: PathBuf::from(relative_path),
+            relative_path: PathBuf::from(relative_path),
+            priority: u8::MAX,
+            overlays: Vec::new(),
+        }
+    }
+
+    fn disabling(entries: &[&str]) -> FilterPolicy {
+        FilterPolicy {
+            disable: entries.iter().map(ToString::to_string).collect(),
+            ..FilterPolicy::default()
+        }
+    }
+
+    #[test]
+    fn disable_matches_names_and_globs() {
+        let policy = disabling(&["git/log", "docker/*"]);
+        assert!(policy.is_disabled(&filter("git/log.toml")));
+        assert!(policy.is_disabled(&filter("docker/build.toml")));
+        assert!(!policy.is_disabled(&filter("git/push.toml")));
+    }
+
+    #[test]
+    fn disable_accepts_toml_suffix() {
+        assert!(disabling(&["git/log.toml"]).is_disabled(&filter("git/log.toml")));
+    }
+
+    #[test]
+    fn rank_moves_builtins_first_unless_local_preferred() {
+        let local_first = FilterPolicy::default();
+        assert!(local_first.rank(0) < local_first.rank(u8::MAX));
+
+        let builtin_first = FilterPolicy {
+            prefer_local: false,
+            ..FilterPolicy::default()
+        };
+        assert!(builtin_first.rank(u8::MAX) < builtin_first.rank(0));
+        assert!(builtin_first.rank(0) < builtin_first.rank(1));
+    }
+}
@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::policy::FilterPolicy;
+
+/// Global tokf settings loaded from `config.toml`.
+///
+/// Every key is optional so a project file only overrides what it sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Settings {
+    /// Include the embedded stdlib filters in discovery (default: true).
+    pub use_builtin_filters: Option<bool>,
+    /// Append a `[trend]` line after filtered runs (default: false).
+    pub show_trends: Option<bool>,
+    /// Filters to drop from discovery, by name or glob (`git/log`, `docker/*`).
+    pub disable: Option<Vec<String>>,
+    /// Let local and user filters shadow built-in ones (default: true).
+    pub prefer_local: Option<bool>,
+    /// Thousands separator for exact counts in reports (default: from locale).
+    pub number_separator: Option<String>,
+    /// How the discovery cache decides it is stale (default: `mtime`).
+    pub cache_validation: Option<CacheValidation>,
+}
+
+/// How the discovery cache is checked against the filter files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheValidation {
+    /// Compare search dir mtimes: one `stat` per dir, but blind to in-place
+    /// edits and fooled by coarse or skewed clocks.
+    #[default]
+    Mtime,
+    /// Compare a BLAKE3 hash of every filter file: reads each file on every
+    /// lookup, but only content changes count.
+    Hash,
+}
+
+impl Settings {
+    /// Whether the embedded stdlib tier should be discovered.
+    pub fn builtins_enabled(&self) -> bool {
+        self.use_builtin_filters.unwrap_or(true)
+    }
+
+    /// Whether to print a filter's `[trend]` line after each run.
+    pub fn trends_enabled(&self) -> bool {
+        self.show_trends.unwrap_or(false)
+    }
+
+    /// How the discovery cache is validated.
+    pub fn cache_validation(&self) -> CacheValidation {
+        self.cache_validation.unwrap_or_default()
+    }
+
+    /// The discovery policy built from `disable` and `prefer_local`.
+    pub fn filter_policy(&self) -> FilterPolicy {
+        FilterPolicy {
+            disable: self.disable.clone().unwrap_or_default(),
+            prefer_local: self.prefer_local.unwrap_or(true),
+        }
+    }
+
+    /// Fill keys not set in `self` from `lower`.
+    fn or(self, lower: Self) -> Self {
+        Self {
+            use_builtin_filters: self.use_builtin_filters.or(lower.use_builtin_filters),
+            show_trends: self.show_trends.or(lower.show_trends),
+            disable: self.disable.or(lower.disable),
+            prefer_local: self.prefer_local.or(lower.prefer_local),
+            number_separator: self.number_separator.or(lower.number_separator),
+            cache_validation: self.cache_validation.or(lower.cache_validation),
+        }
+    }
+}
+
+/// Load settings from the project and user `config.toml`.
+///
+/// Search order (a key set in an earlier file wins):
+/// 1. `.tokf/config.toml` (project-local)
+/// 2. `~/.config/tokf/config.toml` (user-level; `$TOKF_HOME/config.toml` if set)
+pub fn load_settings() -> Settings {
+    load_settings_from(&settings_search_paths())
+}
+
+fn settings_search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Ok(cwd) = std::env::current_dir() {
+        paths.push(cwd.join(".tokf/config.toml"));
+    }
+
+    if let Some(config) = crate::paths::config_dir() {
+        paths.push(config.join("config.toml"));
+    }
+
+    paths
+}
+
+/// Testable version that accepts explicit paths.
+///
+/// Missing files are skipped; unparseable files are skipped with a warning.
+pub fn load_settings_from(paths: &[PathBuf]) -> Settings {
+    paths
+        .iter()
+        .filter_map(|p| load_file(p))
+        .fold(Settings::default(), Settings::or)
+}
+
+fn load_file(path: &Path) -> Option<Settings> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match toml::from_str(&content) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            tracing::warn!("failed to parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Resolve whether built-in filters are active: `--no-builtin` always
+/// disables them, otherwise `use_builtin_filters` from `config.toml` decides.
+pub fn builtins_enabled(no_builtin_flag: bool) -> bool {
+    !no_builtin_flag && load_settings().builtins_enabled()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn write(dir: &TempDir, content: &str) -> PathBuf {
+        let path = dir.path().join("config.toml");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn defaults_enable_builtins() {
+        let settings = load_settings_from(&[]);
+        assert_eq!(settings, Settings::default());
+        assert!(settings.builtins_enabled());
+    }
+
+    #[test]
+    fn disable_builtins() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "use_builtin_filters = false\n");
+        assert!(!load_settings_from(&[path]).builtins_enabled());
+    }
+
+    #[test]
+    fn project_overrides_user() {
+        let project = TempDir::new().unwrap();
+        let user = TempDir::new().unwrap();
+        let project_path = write(&project, "use_builtin_filters = true\n");
+        let user_path = write(&user, "use_builtin_filters = false\n");
+        assert!(load_settings_from(&[project_path, user_path]).builtins_enabled());
+    }
+
+    #[test]
+    fn unset_key_falls_through_to_user() {
+        let project = TempDir::new().unwrap();
+        let user = TempDir::new().unwrap();
+        let project_path = write(&project, "");
+        let user_path = write(&user, "use_builtin_filters = false\n");
+        assert!(!load_settings_from(&[project_path, user_path]).builtins_enabled());
+    }
+
+    #[test]
+    fn show_trends_defaults_off() {
+        let dir = TempDir::new().unwrap();
+        assert!(!load_settings_from(&[]).trends_enabled());
+        let path = write(&dir, "show_trends = true\n");
+        assert!(load_settings_from(&[path]).trends_enabled());
+    }
+
+    #[test]
+    fn invalid_file_is_skipped() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "use_builtin_filters = \"nope\"\n");
+        assert!(load_settings_from(&[path]).builtins_enabled());
+    }
+
+    #[test]
+    fn missing_file_is_skipped() {
+        let path = PathBuf::from("/tokf_test_nonexistent_dir/config.toml");
+        assert!(load_settings_from(&[path]).builtins_enabled());
+    }
+
+    #[test]
+    fn filter_policy_defaults_to_local_first() {
+        let policy = load_settings_from(&[]).filter_policy();
+        assert!(policy.disable.is_empty());
+        assert!(policy.prefer_local);
+    }
+
+    #[test]
+    fn project_disable_list_replaces_user_list() {
+        let project = TempDir::new().unwrap();
+        let user = TempDir::new().unwrap();
+        let project_path = write(&project, "disable = [\"docker/*\"]\n");
+        let user_path = write(&user, "disable = [\"git/log\"]\nprefer_local = false\n");
+        let policy = load_settings_from(&[project_path, user_path]).filter_policy();
+        assert_eq!(policy.disable, vec!["docker/*"]);
+        assert!(!policy.prefer_local);
+    }
+
+    #[test]
+    fn cache_validation_defaults_to_mtime() {
+        assert_eq!(
+            load_settings_from(&[]).cache_validation(),
+            CacheValidation::Mtime
+        );
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "cache_validation = \"hash\"\n");
+        assert_eq!(
+            load_settings_from(&[path]).cache_validation(),
+            CacheValidation::Hash
+        );
+    }
+}
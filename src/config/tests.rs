@@ -0,0 +1,593 @@
+use std::fs;
+
+use tempfile::TempDir;
+
+use super::*;
+
+// --- pattern_specificity ---
+
+#[test]
+fn specificity_two_literals() {
+    assert_eq!(pattern_specificity("git push"), 2);
+}
+
+#[test]
+fn specificity_wildcard_counts_less() {
+    assert_eq!(pattern_specificity("git *"), 1);
+    assert_eq!(pattern_specificity("* push"), 1);
+}
+
+#[test]
+fn specificity_all_wildcards() {
+    assert_eq!(pattern_specificity("* *"), 0);
+}
+
+#[test]
+fn specificity_ordering() {
+    // "git push" more specific than "git *" more specific than "* push"
+    assert!(pattern_specificity("git push") > pattern_specificity("git *"));
+    assert!(pattern_specificity("git *") == pattern_specificity("* push"));
+}
+
+// --- pattern_matches_prefix ---
+
+#[test]
+fn matches_exact() {
+    let words = ["git", "push"];
+    assert_eq!(pattern_matches_prefix("git push", &words), Some(2));
+}
+
+#[test]
+fn matches_prefix_with_trailing_args() {
+    let words = ["git", "push", "origin", "main"];
+    assert_eq!(pattern_matches_prefix("git push", &words), Some(2));
+}
+
+#[test]
+fn matches_wildcard() {
+    let words = ["npm", "run", "build"];
+    assert_eq!(pattern_matches_prefix("npm run *", &words), Some(3));
+}
+
+#[test]
+fn no_match_different_command() {
+    let words = ["cargo", "test"];
+    assert_eq!(pattern_matches_prefix("git push", &words), None);
+}
+
+#[test]
+fn no_match_too_short() {
+    let words = ["git"];
+    assert_eq!(pattern_matches_prefix("git push", &words), None);
+}
+
+#[test]
+fn empty_pattern_returns_none() {
+    let words = ["git", "push"];
+    assert_eq!(pattern_matches_prefix("", &words), None);
+}
+
+#[test]
+fn empty_words_returns_none() {
+    assert_eq!(pattern_matches_prefix("git push", &[]), None);
+}
+
+#[test]
+fn single_word_pattern_prefix_match() {
+    assert_eq!(pattern_matches_prefix("echo", &["echo"]), Some(1));
+    assert_eq!(pattern_matches_prefix("echo", &["echo", "hello"]), Some(1));
+    assert_eq!(pattern_matches_prefix("echo", &["ls"]), None);
+}
+
+#[test]
+fn matches_after_env_prefix() {
+    let words = ["RUST_LOG=debug", "CI=1", "cargo", "test", "--lib"];
+    assert_eq!(pattern_matches_prefix("cargo test", &words), Some(4));
+    assert_eq!(pattern_matches_prefix("git status", &words), None);
+}
+
+#[test]
+fn env_prefix_only_is_not_a_match() {
+    assert_eq!(pattern_matches_prefix("cargo test", &["CI=1"]), None);
+}
+
+#[test]
+fn env_assignment_detection() {
+    assert!(is_env_assignment("RUST_LOG=debug"));
+    assert!(is_env_assignment("_X="));
+    assert!(!is_env_assignment("1X=a"));
+    assert!(!is_env_assignment("--flag=value"));
+    assert!(!is_env_assignment("cargo"));
+    assert_eq!(env_prefix_len(&["A=1", "B=2", "cmd", "C=3"]), 2);
+}
+
+#[test]
+fn wildcard_rejects_empty_token() {
+    // An empty string slice element is not a valid word match for `*`
+    assert_eq!(pattern_matches_prefix("git *", &["git", ""]), None);
+}
+
+#[test]
+fn wildcard_at_start() {
+    let words = ["my-tool", "subcommand"];
+    assert_eq!(pattern_matches_prefix("* subcommand", &words), Some(2));
+}
+
+#[test]
+fn hyphenated_tool_not_ambiguous() {
+    // golangci-lint run should match "golangci-lint run" but not "golangci-lint"
+    let words = ["golangci-lint", "run"];
+    assert_eq!(pattern_matches_prefix("golangci-lint run", &words), Some(2));
+    assert_eq!(pattern_matches_prefix("golangci-lint", &words), Some(1));
+}
+
+// --- discover_filter_files ---
+
+#[test]
+fn discover_flat_dir() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.toml"), "").unwrap();
+    fs::write(dir.path().join("b.toml"), "").unwrap();
+    fs::write(dir.path().join("not-toml.txt"), "").unwrap();
+
+    let files = discover_filter_files(dir.path());
+    assert_eq!(files.len(), 2);
+    assert!(files[0].ends_with("a.toml"));
+    assert!(files[1].ends_with("b.toml"));
+}
+
+#[test]
+fn discover_nested_dirs() {
+    let dir = TempDir::new().unwrap();
+    let sub = dir.path().join("git");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(sub.join("push.toml"), "").unwrap();
+    fs::write(sub.join("status.toml"), "").unwrap();
+    fs::write(dir.path().join("root.toml"), "").unwrap();
+
+    let files = discover_filter_files(dir.path());
+    assert_eq!(files.len(), 3);
+    // sorted by path: git/push.toml, git/status.toml, root.toml
+    assert!(files[0].ends_with("git/push.toml"));
+    assert!(files[1].ends_with("git/status.toml"));
+    assert!(files[2].ends_with("root.toml"));
+}
+
+#[test]
+fn discover_skips_hidden_entries() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".hidden.toml"), "").unwrap();
+    fs::write(dir.path().join("visible.toml"), "").unwrap();
+    let hidden_dir = dir.path().join(".hiddendir");
+    fs::create_dir_all(&hidden_dir).unwrap();
+    fs::write(hidden_dir.join("inside.toml"), "").unwrap();
+
+    let files = discover_filter_files(dir.path());
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("visible.toml"));
+}
+
+#[test]
+fn discover_nonexistent_dir_returns_empty() {
+    let files = discover_filter_files(Path::new("/no/such/directory/ever"));
+    assert!(files.is_empty());
+}
+
+// --- discover_all_filters ---
+
+#[test]
+fn discover_all_priority_ordering() {
+    let dir1 = TempDir::new().unwrap();
+    let dir2 = TempDir::new().unwrap();
+
+    // dir1 = priority 0 (local), dir2 = priority 1 (user)
+    fs::write(
+        dir1.path().join("my-cmd.toml"),
+        "command = \"my cmd local\"",
+    )
+    .unwrap();
+    fs::write(dir2.path().join("my-cmd.toml"), "command = \"my cmd user\"").unwrap();
+
+    let search_dirs = vec![dir1.path().to_path_buf(), dir2.path().to_path_buf()];
+    let filters = discover_all_filters(&search_dirs, true).unwrap();
+
+    // Should have both (different command strings) plus embedded stdlib
+    assert!(filters.len() >= 2);
+    assert_eq!(filters[0].config.command.first(), "my cmd local");
+    assert_eq!(filters[0].priority, 0);
+}
+
+#[test]
+fn discover_all_dedup_same_command() {
+    let dir1 = TempDir::new().unwrap();
+    let dir2 = TempDir::new().unwrap();
+
+    fs::write(dir1.path().join("a.toml"), "command = \"git push\"").unwrap();
+    fs::write(dir2.path().join("b.toml"), "command = \"git push\"").unwrap();
+
+    let search_dirs = vec![dir1.path().to_path_buf(), dir2.path().to_path_buf()];
+    let filters = discover_all_filters(&search_dirs, true).unwrap();
+
+    // Dedup by first() — only one entry for "git push"
+    let push_entries: Vec<_> = filters
+        .iter()
+        .filter(|f| f.config.command.first() == "git push")
+        .collect();
+    assert_eq!(push_entries.len(), 1);
+    assert_eq!(push_entries[0].priority, 0);
+}
+
+#[test]
+fn discover_all_specificity_ordering() {
+    let dir = TempDir::new().unwrap();
+
+    // More specific patterns should sort first within same priority
+    fs::write(dir.path().join("a.toml"), "command = \"git *\"").unwrap();
+    fs::write(dir.path().join("b.toml"), "command = \"git push\"").unwrap();
+
+    let dirs = vec![dir.path().to_path_buf()];
+    let filters = discover_all_filters(&dirs, true).unwrap();
+
+    // "git push" (specificity=2) should come before "git *" (specificity=1)
+    assert_eq!(filters[0].config.command.first(), "git push");
+    assert_eq!(filters[1].config.command.first(), "git *");
+}
+
+#[test]
+fn discover_all_skips_invalid_toml() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("bad.toml"), "not valid [[[").unwrap();
+    fs::write(dir.path().join("good.toml"), "command = \"my tool\"").unwrap();
+
+    let filters = discover_all_filters(&[dir.path().to_path_buf()], true).unwrap();
+    let my_tool = filters
+        .iter()
+        .filter(|f| f.config.command.first() == "my tool")
+        .count();
+    assert_eq!(my_tool, 1);
+}
+
+#[test]
+fn discover_all_hyphenated_tool_not_ambiguous() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("golangci-lint.toml"),
+        "command = \"golangci-lint run\"",
+    )
+    .unwrap();
+
+    let filters = discover_all_filters(&[dir.path().to_path_buf()], true).unwrap();
+    let golangci: Vec<_> = filters
+        .iter()
+        .filter(|f| f.config.command.first() == "golangci-lint run")
+        .collect();
+    assert_eq!(golangci.len(), 1);
+    let words = ["golangci-lint", "run"];
+    assert_eq!(golangci[0].matches(&words), Some(2));
+
+    let words_no_match = ["golangci", "lint", "run"];
+    assert_eq!(golangci[0].matches(&words_no_match), None);
+}
+
+// --- embedded stdlib tests ---
+
+#[test]
+#[cfg(feature = "stdlib")]
+fn embedded_stdlib_non_empty() {
+    let entries: Vec<_> = STDLIB.find("**/*.toml").unwrap().collect();
+    assert!(
+        entries.len() >= 10,
+        "expected at least 10 embedded filters, got {}",
+        entries.len()
+    );
+}
+
+#[test]
+#[cfg(feature = "stdlib")]
+fn all_embedded_toml_parse() {
+    for entry in STDLIB.find("**/*.toml").unwrap() {
+        if let DirEntry::File(file) = entry {
+            let content = file.contents_utf8().unwrap_or("");
+            assert!(
+                toml::from_str::<FilterConfig>(content).is_ok(),
+                "failed to parse embedded filter: {}",
+                file.path().display()
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "stdlib")]
+fn embedded_filters_in_discover_with_no_dirs() {
+    // With empty search dirs, only embedded stdlib is returned
+    let filters = discover_all_filters(&[], true).unwrap();
+    assert!(
+        !filters.is_empty(),
+        "expected embedded stdlib filters with no search dirs"
+    );
+    let has_git_push = filters
+        .iter()
+        .any(|f| f.config.command.first() == "git push");
+    assert!(has_git_push, "expected git push in embedded stdlib");
+}
+
+#[test]
+fn discover_without_builtin_skips_stdlib() {
+    assert!(discover_all_filters(&[], false).unwrap().is_empty());
+
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("mine.toml"), "command = \"my tool\"").unwrap();
+    let filters = discover_all_filters(&[dir.path().to_path_buf()], false).unwrap();
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0].priority, 0);
+}
+
+#[test]
+fn local_filter_shadows_embedded() {
+    let dir = TempDir::new().unwrap();
+    // Override git push locally
+    fs::write(
+        dir.path().join("push.toml"),
+        "command = \"git push\"\n# local override",
+    )
+    .unwrap();
+
+    let dirs = vec![dir.path().to_path_buf()];
+    let filters = discover_all_filters(&dirs, true).unwrap();
+
+    // "git push" should appear exactly once (local shadows embedded)
+    let push_entries: Vec<_> = filters
+        .iter()
+        .filter(|f| f.config.command.first() == "git push")
+        .collect();
+    assert_eq!(push_entries.len(), 1);
+    assert_eq!(push_entries[0].priority, 0); // local priority
+}
+
+// --- try_load_filter ---
+
+#[test]
+fn test_load_valid_toml() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("test.toml");
+    fs::write(&path, "command = \"echo hello\"").unwrap();
+
+    let config = try_load_filter(&path).unwrap().unwrap();
+    assert_eq!(config.command.first(), "echo hello");
+}
+
+#[test]
+fn test_load_invalid_toml() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("bad.toml");
+    fs::write(&path, "not valid toml [[[").unwrap();
+
+    assert!(try_load_filter(&path).is_err());
+}
+
+#[test]
+fn test_load_nonexistent_returns_none() {
+    let path = PathBuf::from("/tmp/nonexistent-tokf-test-file.toml");
+    assert!(try_load_filter(&path).unwrap().is_none());
+}
+
+#[test]
+fn default_filter_prefers_the_first_dir_and_ignores_run() {
+    let local = TempDir::new().unwrap();
+    let user = TempDir::new().unwrap();
+    fs::write(user.path().join(DEFAULT_FILTER), "dedup = true").unwrap();
+    let dirs = vec![local.path().to_path_buf(), user.path().to_path_buf()];
+
+    let (path, config) = load_default_filter(&dirs).unwrap();
+    assert_eq!(path, user.path().join(DEFAULT_FILTER));
+    assert_eq!(config.command.first(), "_default");
+    assert!(config.dedup);
+
+    fs::write(
+        local.path().join(DEFAULT_FILTER),
+        "command = \"catch-all\"\nrun = \"true\"\n[[step]]\nrun = \"true\"\nas = \"x\"\n",
+    )
+    .unwrap();
+    let (path, config) = load_default_filter(&dirs).unwrap();
+    assert_eq!(path, local.path().join(DEFAULT_FILTER));
+    assert_eq!(config.command.first(), "catch-all");
+    assert!(config.run.is_none() && config.step.is_empty());
+
+    // Never discovered as a regular filter.
+    let filters = discover_filters_with(&dirs, false, &FilterPolicy::default()).unwrap();
+    assert!(filters.is_empty());
+}
+
+#[test]
+fn dir_defaults_merge_into_filters_beside_them_only() {
+    let dir = TempDir::new().unwrap();
+    let cargo = dir.path().join("cargo");
+    fs::create_dir_all(cargo.join("nested")).unwrap();
+    fs::write(
+        cargo.join(dir_defaults::DIR_DEFAULTS),
+        "skip = [\"^\\\\s+Compiling \"]\nstrip_ansi = true\n[env]\nCARGO_TERM_COLOR = \"never\"\n",
+    )
+    .unwrap();
+    fs::write(
+        cargo.join("build.toml"),
+        "command = \"cargo build\"\nskip = [\"^warning\"]\n",
+    )
+    .unwrap();
+    fs::write(cargo.join("nested/doc.toml"), "command = \"cargo doc\"").unwrap();
+
+    let filters =
+        discover_filters_with(&[dir.path().to_path_buf()], false, &FilterPolicy::default())
+            .unwrap();
+    assert_eq!(filters.len(), 2, "_dir.toml is not a filter itself");
+    let build = filters
+        .iter()
+        .find(|f| f.config.command.first() == "cargo build")
+        .unwrap();
+    assert_eq!(build.config.skip, vec![r"^\s+Compiling ", "^warning"]);
+    assert!(build.config.strip_ansi);
+    assert_eq!(build.config.env["CARGO_TERM_COLOR"], "never");
+    let doc = filters
+        .iter()
+        .find(|f| f.config.command.first() == "cargo doc")
+        .unwrap();
+    assert!(doc.config.skip.is_empty() && !doc.config.strip_ansi);
+
+    fs::write(cargo.join(dir_defaults::DIR_DEFAULTS), "skip = [").unwrap();
+    let err = try_load_filter(&cargo.join("build.toml")).unwrap_err();
+    assert!(format!("{err:#}").contains("_dir.toml"), "{err:#}");
+}
+
+#[test]
+fn test_load_real_stdlib_filter() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("filters/git/push.toml");
+    let config = try_load_filter(&path).unwrap().unwrap();
+    assert_eq!(config.command.first(), "git push");
+}
+
+// --- default_search_dirs ---
+
+#[test]
+fn test_default_search_dirs_non_empty_and_starts_with_local() {
+    let dirs = default_search_dirs();
+    assert!(!dirs.is_empty());
+    assert!(
+        dirs[0].is_absolute(),
+        "first dir should be absolute, got: {:?}",
+        dirs[0]
+    );
+    assert!(
+        dirs[0].ends_with(".tokf/filters"),
+        "first dir should end with .tokf/filters, got: {:?}",
+        dirs[0]
+    );
+}
+
+#[test]
+fn test_default_search_dirs_only_local_and_user() {
+    let dirs = default_search_dirs();
+    // Should have at most 2 dirs: local (.tokf/filters) and user config
+    // The binary-adjacent path has been removed; embedded stdlib replaces it.
+    assert!(
+        dirs.len() <= 2,
+        "expected at most 2 search dirs (local + user), got {}: {:?}",
+        dirs.len(),
+        dirs
+    );
+}
+
+// --- command_pattern_to_regex ---
+
+#[test]
+fn regex_from_literal_pattern() {
+    let r = command_pattern_to_regex("git push");
+    let re = regex::Regex::new(&r).unwrap();
+    assert!(re.is_match("git push"));
+    assert!(re.is_match("git push origin main"));
+    assert!(!re.is_match("git status"));
+}
+
+#[test]
+fn regex_from_wildcard_pattern() {
+    let r = command_pattern_to_regex("npm run *");
+    let re = regex::Regex::new(&r).unwrap();
+    assert!(re.is_match("npm run build"));
+    assert!(re.is_match("npm run test --watch"));
+    assert!(!re.is_match("npm run"));
+    assert!(!re.is_match("npm install"));
+}
+
+#[test]
+fn regex_allows_env_prefix() {
+    let r = command_pattern_to_regex("cargo test");
+    let re = regex::Regex::new(&r).unwrap();
+    assert!(re.is_match("RUST_LOG=debug cargo test"));
+    assert!(re.is_match("CI=1 FOO= cargo test --lib"));
+    assert!(!re.is_match("--x=1 cargo test"));
+    assert!(!re.is_match("CI=1 cargo build"));
+}
+
+// --- parallel discovery ---
+
+#[test]
+fn discover_all_many_files_matches_sequential_order() {
+    let dir = TempDir::new().unwrap();
+    // Well above PARALLEL_PARSE_THRESHOLD so the scoped-thread path is exercised.
+    for i in 0..64 {
+        fs::write(
+            dir.path().join(format!("tool-{i:02}.toml")),
+            format!("command = \"tool{i:02} run\""),
+        )
+        .unwrap();
+    }
+    fs::write(dir.path().join("broken.toml"), "not valid [[[").unwrap();
+
+    let filters = discover_all_filters(&[dir.path().to_path_buf()], true).unwrap();
+    let local: Vec<&str> = filters
+        .iter()
+        .filter(|f| f.priority == 0)
+        .map(|f| f.config.command.first())
+        .collect();
+    let expected: Vec<String> = (0..64).map(|i| format!("tool{i:02} run")).collect();
+    assert_eq!(local, expected);
+}
+
+#[test]
+fn discover_all_parallel_keeps_dir_priorities() {
+    let dirs: Vec<TempDir> = (0..3).map(|_| TempDir::new().unwrap()).collect();
+    for (i, d) in dirs.iter().enumerate() {
+        fs::write(d.path().join("x.toml"), format!("command = \"dir{i} cmd\"")).unwrap();
+    }
+    let paths: Vec<PathBuf> = dirs.iter().map(|d| d.path().to_path_buf()).collect();
+    let filters = discover_all_filters(&paths, true).unwrap();
+    for i in 0..3u8 {
+        let f = filters
+            .iter()
+            .find(|f| f.config.command.first() == format!("dir{i} cmd"))
+            .unwrap();
+        assert_eq!(f.priority, i);
+    }
+}
+
+#[test]
+#[allow(clippy::panic)]
+fn join_worker_propagates_panics() {
+    let caught = std::panic::catch_unwind(|| {
+        std::thread::scope(|scope| join_worker(scope.spawn(|| -> u8 { panic!("worker failed") })))
+    });
+    assert!(caught.is_err());
+}
+
+// --- pack manifests ---
+
+#[test]
+fn discover_skips_pack_manifest_file() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("pack.toml"), "name = \"team\"").unwrap();
+    fs::write(dir.path().join("a.toml"), "command = \"a\"").unwrap();
+
+    let files = discover_filter_files(dir.path());
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("a.toml"));
+}
+
+#[test]
+fn discover_all_skips_incompatible_pack() {
+    let dir = TempDir::new().unwrap();
+    let future = dir.path().join("future");
+    fs::create_dir_all(&future).unwrap();
+    fs::write(future.join("pack.toml"), "min_tokf_version = \"999.0.0\"").unwrap();
+    fs::write(future.join("new.toml"), "command = \"new tool\"").unwrap();
+    let current = dir.path().join("current");
+    fs::create_dir_all(&current).unwrap();
+    fs::write(
+        current.join("pack.toml"),
+        format!("min_tokf_version = \"{}\"", env!("CARGO_PKG_VERSION")),
+    )
+    .unwrap();
+    fs::write(current.join("old.toml"), "command = \"old tool\"").unwrap();
+
+    let filters = discover_all_filters(&[dir.path().to_path_buf()], false).unwrap();
+    let commands: Vec<&str> = filters.iter().map(|f| f.config.command.first()).collect();
+    assert_eq!(commands, vec!["old tool"]);
+}
@@ -0,0 +1,652 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A command pattern — either a single string or a list of alternatives.
+///
+/// ```toml
+/// command = "git push"                    # Single
+/// command = ["pnpm test", "npm test"]     # Multiple: any variant
+/// command = "npm run *"                   # Wildcard: * matches one word
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommandPattern {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl CommandPattern {
+    /// All pattern strings for this command.
+    pub fn patterns(&self) -> &[String] {
+        match self {
+            Self::Single(s) => std::slice::from_ref(s),
+            Self::Multiple(v) => v,
+        }
+    }
+
+    /// Canonical (first) pattern string, used for display and dedup.
+    pub fn first(&self) -> &str {
+        match self {
+            Self::Single(s) => s.as_str(),
+            Self::Multiple(v) => v.first().map_or("", String::as_str),
+        }
+    }
+}
+
+impl Default for CommandPattern {
+    fn default() -> Self {
+        Self::Single(String::new())
+    }
+}
+
+/// Top-level filter configuration, deserialized from a `.toml` file.
+// FilterConfig has many independent boolean flags that map directly to TOML keys.
+// Grouping them into enums would not improve clarity here.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterConfig {
+    /// The command this filter applies to (e.g. "git push").
+    pub command: CommandPattern,
+
+    /// Command patterns this filter must not handle (e.g. `"git push --dry-run"`),
+    /// using the same syntax as `command`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Optional override command to actually run instead.
+    pub run: Option<String>,
+
+    /// Stream fed to `replace`, `skip`/`keep`, `dedup` and `json` (default: combined).
+    pub source: Option<StreamSource>,
+
+    /// Patterns for lines to skip (applied before section parsing).
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    /// Append a footer counting the lines `skip` hid, grouped by pattern.
+    #[serde(default)]
+    pub skip_summary: bool,
+
+    /// Patterns for lines to keep (inverse of skip).
+    #[serde(default)]
+    pub keep: Vec<String>,
+
+    /// Pipeline steps to run before filtering.
+    #[serde(default)]
+    pub step: Vec<Step>,
+
+    /// Extract a single value from the output.
+    pub extract: Option<ExtractRule>,
+
+    /// Whole-output matchers checked before any line processing.
+    #[serde(default)]
+    pub match_output: Vec<MatchOutputRule>,
+
+    /// State-machine sections for collecting lines into named groups.
+    #[serde(default)]
+    pub section: Vec<Section>,
+
+    /// Branch taken when the command exits 0.
+    pub on_success: Option<OutputBranch>,
+
+    /// Branch taken when the command exits non-zero.
+    pub on_failure: Option<OutputBranch>,
+
+    /// Branches chosen by the command's arguments, checked in order before
+    /// `on_success` / `on_failure`.
+    #[serde(default)]
+    pub branch: Vec<ArgBranch>,
+
+    /// Structured parsing rules (branch line, file grouping).
+    pub parse: Option<ParseConfig>,
+
+    /// Output formatting configuration.
+    pub output: Option<OutputConfig>,
+
+    /// Fallback behavior when no other rule matches.
+    pub fallback: Option<Fallback>,
+
+    /// Counter recorded with each tracked run, for trend lines.
+    pub trend: Option<TrendConfig>,
+
+    /// Rules that pick diagnostics out of the raw output for `tokf run --sarif`.
+    #[serde(default)]
+    pub diagnostic: Vec<DiagnosticRule>,
+
+    /// Per-line regex replacement steps, applied before skip/keep.
+    #[serde(default)]
+    pub replace: Vec<ReplaceRule>,
+
+    /// Collapse consecutive identical lines (or within a sliding window).
+    #[serde(default)]
+    pub dedup: bool,
+
+    /// Window size for dedup (default: consecutive only).
+    pub dedup_window: Option<usize>,
+
+    /// Merge near-duplicate lines (digits ignored) into the first, suffixed `(xN)`.
+    #[serde(default)]
+    pub dedup_fuzzy: bool,
+
+    /// Similarity (0–1) at which `dedup_fuzzy` merges lines (default: 0.9).
+    pub dedup_threshold: Option<f64>,
+
+    /// Strip ANSI escape sequences before skip/keep pattern matching.
+    #[serde(default)]
+    pub strip_ansi: bool,
+
+    /// Trim leading/trailing whitespace from each line before skip/keep matching.
+    #[serde(default)]
+    pub trim_lines: bool,
+
+    /// Drop spinner frames (braille etc.) and collapse `\r` redraws before skip/keep.
+    #[serde(default)]
+    pub strip_spinners: bool,
+
+    /// Remove all blank lines from the final output.
+    #[serde(default)]
+    pub strip_empty_lines: bool,
+
+    /// Collapse consecutive blank lines into one in the final output.
+    #[serde(default)]
+    pub collapse_empty_lines: bool,
+
+    /// Cap on the final output's lines: the first and last lines are kept
+    /// around a `… N lines omitted …` marker.
+    pub max_output_lines: Option<usize>,
+
+    /// Rewrite absolute paths in the final output: under the git root (or
+    /// the current directory) → relative, the home directory → `~`.
+    #[serde(default)]
+    pub shorten_paths: bool,
+
+    /// Optional Lua/Luau script escape hatch.
+    #[serde(default)]
+    pub lua_script: Option<ScriptConfig>,
+
+    /// Condition that marks a zero-exit run as failed (e.g. `"{tests.count} == 0"`).
+    pub fail_when: Option<String>,
+
+    /// Line-oriented JSON (JSONL) processing, applied after dedup.
+    pub json: Option<JsonConfig>,
+
+    /// Condense stack traces in the pre-filtered output, applied after `json`.
+    pub stacktrace: Option<StacktraceConfig>,
+
+    /// On a failing exit code, append the tail of the raw output when the
+    /// filtered output kept fewer than this fraction of its lines (e.g. `0.02`).
+    pub min_keep_ratio: Option<f64>,
+
+    /// On a failing exit code, append the tail of the raw output when the
+    /// filter removed more than this many lines.
+    pub escalate_when_lines_over: Option<usize>,
+
+    /// Kill the command after this many seconds (overridden by `--timeout`).
+    pub timeout_secs: Option<u64>,
+
+    /// Run the command under a pseudo-terminal (same as `--pty`).
+    #[serde(default)]
+    pub pty: bool,
+
+    /// Save the raw output under `.tokf/last-run/` and point to it at the end
+    /// of the filtered output (same as `--save-raw`).
+    #[serde(default)]
+    pub save_raw: bool,
+
+    /// Environment variables set for the command (e.g. `FORCE_COLOR = "0"`).
+    /// `VAR=value` prefixes typed by the user take precedence.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Add this filter's `skip` patterns to the lower-priority filter with the
+    /// same command instead of replacing it.
+    #[serde(default)]
+    pub merge: bool,
+}
+
+/// A pipeline step that runs a sub-command and captures its output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    /// Command to run.
+    pub run: String,
+
+    /// Name to bind the output to in the template context.
+    #[serde(rename = "as")]
+    pub as_name: Option<String>,
+
+    /// Whether this step is part of a pipeline. Reserved for Phase 2+; unused by
+    /// current filter configs.
+    pub pipeline: Option<bool>,
+}
+
+/// Extracts a value from text using a regex pattern and formats it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractRule {
+    /// Regex pattern with capture groups.
+    pub pattern: String,
+
+    /// Output template using `{1}`, `{2}`, etc. for captures.
+    pub output: String,
+}
+
+/// Matches against the full output and short-circuits with a fixed message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchOutputRule {
+    /// Substring to search for in the combined output.
+    pub contains: String,
+
+    /// Output to emit if the substring is found.
+    pub output: String,
+}
+
+/// A state-machine section that collects lines between enter/exit markers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    /// Name of this section (for diagnostics/debugging).
+    pub name: Option<String>,
+
+    /// Regex that activates this section.
+    pub enter: Option<String>,
+
+    /// Regex that deactivates this section.
+    pub exit: Option<String>,
+
+    /// Regex that individual lines must match to be collected.
+    #[serde(rename = "match")]
+    pub match_pattern: Option<String>,
+
+    /// Regex to split collected content into blocks.
+    pub split_on: Option<String>,
+
+    /// Variable name for the collected lines/blocks.
+    pub collect_as: Option<String>,
+
+    /// Stream this section reads (default: combined).
+    pub source: Option<StreamSource>,
+
+    /// Keep at most this many blocks (or lines, without `split_on`).
+    pub max_blocks: Option<usize>,
+
+    /// Truncate each block to this many lines.
+    pub max_lines_per_block: Option<usize>,
+
+    /// Line appended where items were dropped; `{n}` is the dropped count.
+    pub overflow_note: Option<String>,
+}
+
+/// Output branch for success/failure exit codes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputBranch {
+    /// Template string for the output.
+    pub output: Option<String>,
+
+    /// Aggregation rules for collected sections.
+    pub aggregate: Option<Aggregates>,
+
+    /// Number of lines to keep from the tail.
+    pub tail: Option<usize>,
+
+    /// Tail window that grows with error density (takes precedence over `tail`).
+    pub tail_adaptive: Option<AdaptiveTail>,
+
+    /// Number of lines to keep from the head.
+    pub head: Option<usize>,
+
+    /// Patterns for lines to skip within this branch.
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    /// Patterns for lines to keep within this branch (inverse of skip).
+    #[serde(default)]
+    pub keep: Vec<String>,
+
+    /// Extract rule applied within this branch.
+    pub extract: Option<ExtractRule>,
+
+    /// How collected sections are rendered (default: plain `output` template).
+    pub render: Option<RenderMode>,
+
+    /// Stream this branch filters instead of the top-level `source`.
+    pub source: Option<StreamSource>,
+}
+
+/// An output branch selected by the arguments after the matched command (`[[branch]]`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgBranch {
+    /// Patterns in `command` syntax matched against the remaining args; any
+    /// match selects the branch. Empty matches every invocation.
+    #[serde(default)]
+    pub when_args: Vec<String>,
+
+    /// Only select the branch for this exit outcome (default: either).
+    pub when_exit: Option<ExitOutcome>,
+
+    #[serde(flatten)]
+    pub branch: OutputBranch,
+}
+
+/// Exit outcome an `[[branch]]` is restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitOutcome {
+    /// Exit code 0.
+    Success,
+    /// Any non-zero exit code.
+    Failure,
+}
+
+/// A tail window sized by how many lines near the end match `pattern`.
+///
+/// Starts at `min` lines and grows until it holds `matches` matching lines
+/// (default: every matching line), never exceeding `max`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdaptiveTail {
+    /// Lines always kept from the tail.
+    pub min: usize,
+
+    /// Upper bound on the window.
+    pub max: usize,
+
+    /// Regex marking interesting lines (e.g. `"error|FAILED"`).
+    pub pattern: String,
+
+    /// Stop growing once this many matching lines are inside the window.
+    pub matches: Option<usize>,
+}
+
+/// Which captured output stream a filter stage reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamSource {
+    /// stdout followed by stderr.
+    #[default]
+    Combined,
+    Stdout,
+    Stderr,
+}
+
+/// Rendering style for an output branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderMode {
+    /// Render `output` as a plain template.
+    Text,
+    /// One `### Heading` per collected section (bullets for lines, fenced
+    /// blocks for `split_on` blocks), with the rendered `output` under `### Summary`.
+    Markdown,
+}
+
+/// One aggregate rule or several.
+///
+/// ```toml
+/// [on_success.aggregate]          # Single
+/// [[on_success.aggregate]]        # Multiple: one table per rule
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Aggregates {
+    Single(AggregateRule),
+    Multiple(Vec<AggregateRule>),
+}
+
+impl Aggregates {
+    /// All rules, in order.
+    pub fn rules(&self) -> &[AggregateRule] {
+        match self {
+            Self::Single(rule) => std::slice::from_ref(rule),
+            Self::Multiple(rules) => rules,
+        }
+    }
+}
+
+/// Aggregates values from a collected section using regex extraction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateRule {
+    /// Name of the collected section to aggregate from.
+    pub from: String,
+
+    /// Regex pattern to extract numeric values.
+    pub pattern: String,
+
+    /// Name for the summed value.
+    pub sum: Option<String>,
+
+    /// Name for the count of matching entries.
+    pub count_as: Option<String>,
+
+    /// Name for the largest value.
+    pub max: Option<String>,
+
+    /// Name for the smallest value.
+    pub min: Option<String>,
+
+    /// Name for the mean of the values.
+    pub avg: Option<String>,
+
+    /// Name for the number of distinct captured values.
+    pub unique: Option<String>,
+
+    /// Decimal places for `sum`, `max`, `min` and `avg` (default: as computed).
+    pub round: Option<usize>,
+}
+
+impl AggregateRule {
+    /// The variable names this rule defines.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        [
+            &self.sum,
+            &self.count_as,
+            &self.max,
+            &self.min,
+            &self.avg,
+            &self.unique,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Structured parsing configuration for status-like outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseConfig {
+    /// Rule for extracting the branch name from the first line.
+    pub branch: Option<LineExtract>,
+
+    /// Rule for grouping file entries by status code.
+    pub group: Option<GroupConfig>,
+}
+
+/// Extracts a value from a specific line number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineExtract {
+    /// 1-based line number to extract from.
+    pub line: usize,
+
+    /// Regex pattern with capture groups.
+    pub pattern: String,
+
+    /// Output template using `{1}`, `{2}`, etc. for captures.
+    pub output: String,
+}
+
+/// Groups lines by a key pattern and maps keys to human labels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupConfig {
+    /// Rule for extracting the group key from each line.
+    pub key: ExtractRule,
+
+    /// Map from raw key to human-readable label.
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+/// Output formatting configuration for the final rendered result.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Top-level output format template.
+    pub format: Option<String>,
+
+    /// Format template for each group count line.
+    pub group_counts_format: Option<String>,
+
+    /// Message to emit when there are no items to report.
+    pub empty: Option<String>,
+}
+
+/// Fallback behavior when no specific rule matches: one `[fallback]` table,
+/// or several `[[fallback]]` strategies tried in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Fallback {
+    Single(FallbackConfig),
+    Multiple(Vec<FallbackConfig>),
+}
+
+impl Fallback {
+    /// All strategies, in the order they are tried.
+    pub fn strategies(&self) -> &[FallbackConfig] {
+        match self {
+            Self::Single(f) => std::slice::from_ref(f),
+            Self::Multiple(v) => v,
+        }
+    }
+}
+
+/// One fallback strategy. Lines matching `skip` are dropped first, then
+/// `tail`, `head`, and `max_bytes` trim what is left.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    /// Only use this strategy when the output contains one of these strings.
+    #[serde(default)]
+    pub when_contains: Vec<String>,
+
+    /// Patterns for lines to drop.
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    /// Number of lines to keep from the tail as a last resort.
+    pub tail: Option<usize>,
+
+    /// Number of lines to keep from the head.
+    pub head: Option<usize>,
+
+    /// Cap on the output size, cut at a line boundary where possible.
+    pub max_bytes: Option<usize>,
+}
+
+/// A number pulled from each run's raw output and recorded by tracking,
+/// e.g. the failure count of a test runner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrendConfig {
+    /// Name of the counter, shown in the trend line (e.g. `"failures"`).
+    pub label: String,
+
+    /// Regex whose first capture group is summed over every matching line.
+    /// A matching line without that group counts as 0.
+    pub pattern: String,
+}
+
+impl TrendConfig {
+    /// The counter's value for `output`, or `None` when no line matches
+    /// (or the pattern is invalid).
+    pub fn count(&self, output: &str) -> Option<i64> {
+        let re = crate::regex_cache::compile(&self.pattern)?;
+        let mut total = None;
+        for caps in output.lines().filter_map(|l| re.captures(l)) {
+            let n = caps.get(1).and_then(|m| m.as_str().parse::<i64>().ok());
+            total = Some(total.unwrap_or(0) + n.unwrap_or(0));
+        }
+        total
+    }
+}
+
+/// A `[[diagnostic]]` rule: lines of the raw output matching `pattern`
+/// become diagnostics in the `tokf run --sarif` report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticRule {
+    /// Regex with optional named groups `file`, `line`, `col`, `level`,
+    /// `rule` and `message`. Without `message` the whole line is the message.
+    pub pattern: String,
+
+    /// Level for matches without a `level` group: `"error"`, `"warning"`
+    /// (default) or `"note"`.
+    pub level: Option<String>,
+}
+
+/// One per-line regex replacement step.
+///
+/// Pattern is applied to each line; on match, the line is replaced with the
+/// interpolated output template. Capture groups use `{1}`, `{2}`, … syntax.
+/// Multiple rules run in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaceRule {
+    pub pattern: String,
+    pub output: String,
+}
+
+/// JSON mode: each line (or, with `select`, each value selected from the
+/// whole output) is a JSON record, filtered by predicates and rendered
+/// through a template.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonConfig {
+    /// Parse the whole output as one document and take records from this
+    /// jq-like path, e.g. `.testResults[] | {name, status}`.
+    pub select: Option<String>,
+
+    /// Predicates every record must satisfy, e.g. `reason == "compiler-message"`.
+    #[serde(default, rename = "where")]
+    pub predicates: Vec<String>,
+
+    /// Template variables extracted from each record by dotted path.
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+
+    /// Template rendered per record; `{line}` is the raw JSON. Default: the line.
+    pub output: Option<String>,
+
+    /// Pass lines that are not valid JSON through unchanged (default: drop them).
+    #[serde(default)]
+    pub keep_non_json: bool,
+}
+
+/// `[stacktrace]`: Python, Java, Node, Go and Rust traces reduced to the
+/// exception line(s) and the top in-project frames.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StacktraceConfig {
+    /// In-project frames kept per trace (default 3).
+    pub frames: Option<usize>,
+
+    /// Extra substrings marking a frame as library code (e.g. `"com.acme.gen."`).
+    #[serde(default)]
+    pub library: Vec<String>,
+}
+
+/// Supported scripting languages for the `[lua_script]` escape hatch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLang {
+    Luau,
+}
+
+/// Lua/Luau script escape hatch configuration.
+/// Exactly one of `file` or `source` must be set.
+/// `file` paths resolve relative to the current working directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptConfig {
+    pub lang: ScriptLang,
+    /// Path to a `.luau` file (resolved relative to CWD).
+    pub file: Option<String>,
+    /// Inline Luau source.
+    pub source: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::literal_string_with_formatting_args
+)]
+mod tests;
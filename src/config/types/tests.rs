@@ -0,0 +1,291 @@
+use super::*;
+
+fn load_filter(name: &str) -> FilterConfig {
+    let path = format!("{}/filters/{name}", env!("CARGO_MANIFEST_DIR"));
+    let content = std::fs::read_to_string(&path).unwrap();
+    toml::from_str(&content).unwrap()
+}
+
+// --- CommandPattern deserialization ---
+
+#[test]
+fn test_command_pattern_single() {
+    let cfg: FilterConfig = toml::from_str(r#"command = "git push""#).unwrap();
+    assert_eq!(cfg.command, CommandPattern::Single("git push".to_string()));
+    assert_eq!(cfg.command.first(), "git push");
+    assert_eq!(cfg.command.patterns(), &["git push".to_string()]);
+}
+
+#[test]
+fn test_command_pattern_multiple() {
+    let cfg: FilterConfig = toml::from_str(r#"command = ["pnpm test", "npm test"]"#).unwrap();
+    assert_eq!(
+        cfg.command,
+        CommandPattern::Multiple(vec!["pnpm test".to_string(), "npm test".to_string()])
+    );
+    assert_eq!(cfg.command.first(), "pnpm test");
+    assert_eq!(
+        cfg.command.patterns(),
+        &["pnpm test".to_string(), "npm test".to_string()]
+    );
+}
+
+#[test]
+fn test_command_pattern_wildcard() {
+    let cfg: FilterConfig = toml::from_str(r#"command = "npm run *""#).unwrap();
+    assert_eq!(cfg.command.first(), "npm run *");
+}
+
+// --- Stdlib filter deserialization ---
+
+#[test]
+fn test_deserialize_git_push() {
+    let cfg = load_filter("git/push.toml");
+
+    assert_eq!(cfg.command.first(), "git push");
+    assert_eq!(cfg.match_output.len(), 2);
+    assert_eq!(cfg.match_output[0].contains, "Everything up-to-date");
+    assert_eq!(cfg.match_output[1].contains, "rejected");
+
+    let success = cfg.on_success.unwrap();
+    assert_eq!(success.skip.len(), 8);
+    assert!(success.skip[0].starts_with("^Enumerating"));
+
+    let extract = success.extract.unwrap();
+    assert!(extract.pattern.contains("->"));
+    assert_eq!(extract.output, "ok \u{2713} {remote}");
+
+    let failure = cfg.on_failure.unwrap();
+    assert_eq!(failure.tail, Some(10));
+}
+
+#[test]
+fn test_deserialize_git_status() {
+    let cfg = load_filter("git/status.toml");
+
+    assert_eq!(cfg.command.first(), "git status");
+    assert_eq!(cfg.run.as_deref(), Some("git status --porcelain -b"));
+
+    let parse = cfg.parse.unwrap();
+    let branch = parse.branch.unwrap();
+    assert_eq!(branch.line, 1);
+    assert_eq!(branch.output, "{branch}");
+
+    let group = parse.group.unwrap();
+    assert!(group.labels.contains_key("??"));
+    assert_eq!(group.labels.get("M ").unwrap(), "modified");
+
+    let output = cfg.output.unwrap();
+    assert!(output.format.unwrap().contains("{branch}"));
+    assert_eq!(
+        output.group_counts_format.as_deref(),
+        Some("  {label}: {count}")
+    );
+    assert_eq!(
+        output.empty.as_deref(),
+        Some("clean \u{2014} nothing to commit")
+    );
+}
+
+#[test]
+fn test_deserialize_cargo_test() {
+    let cfg = load_filter("cargo/test.toml");
+
+    assert_eq!(cfg.command.first(), "cargo test");
+    assert!(!cfg.skip.is_empty());
+    assert!(cfg.skip.iter().any(|s| s.contains("Compiling")));
+
+    assert_eq!(cfg.section.len(), 3);
+    assert_eq!(cfg.section[0].name.as_deref(), Some("failures"));
+    assert_eq!(cfg.section[0].collect_as.as_deref(), Some("failure_blocks"));
+    assert_eq!(cfg.section[1].name.as_deref(), Some("failure_names"));
+    assert_eq!(cfg.section[2].name.as_deref(), Some("summary"));
+
+    let success = cfg.on_success.unwrap();
+    let aggregates = success.aggregate.unwrap();
+    let agg = &aggregates.rules()[0];
+    assert_eq!(agg.from, "summary_lines");
+    assert_eq!(agg.sum.as_deref(), Some("passed"));
+    assert_eq!(agg.count_as.as_deref(), Some("suites"));
+    assert!(success.output.unwrap().contains("{passed}"));
+
+    let failure = cfg.on_failure.unwrap();
+    assert!(failure.output.unwrap().contains("FAILURES"));
+
+    let fallback = cfg.fallback.unwrap();
+    assert_eq!(fallback.strategies()[0].tail, Some(5));
+}
+
+#[test]
+fn test_deserialize_git_add() {
+    let cfg = load_filter("git/add.toml");
+
+    assert_eq!(cfg.command.first(), "git add");
+    assert_eq!(cfg.match_output.len(), 1);
+    assert_eq!(cfg.match_output[0].contains, "fatal:");
+
+    let success = cfg.on_success.unwrap();
+    assert_eq!(success.output.as_deref(), Some("ok \u{2713}"));
+
+    let failure = cfg.on_failure.unwrap();
+    assert_eq!(failure.tail, Some(5));
+}
+
+#[test]
+fn test_deserialize_git_commit() {
+    let cfg = load_filter("git/commit.toml");
+
+    assert_eq!(cfg.command.first(), "git commit");
+
+    let success = cfg.on_success.unwrap();
+    let extract = success.extract.unwrap();
+    assert!(extract.pattern.contains("\\w+"));
+    assert_eq!(extract.output, "ok \u{2713} {hash}");
+
+    let failure = cfg.on_failure.unwrap();
+    assert_eq!(failure.tail, Some(5));
+}
+
+#[test]
+fn test_deserialize_git_log() {
+    let cfg = load_filter("git/log.toml");
+
+    assert_eq!(cfg.command.first(), "git log");
+
+    let run = cfg.run.unwrap();
+    assert!(run.contains("{args}"));
+    assert!(run.contains("--oneline"));
+
+    let success = cfg.on_success.unwrap();
+    assert_eq!(success.output.as_deref(), Some("{output}"));
+}
+
+#[test]
+fn test_deserialize_git_diff() {
+    let cfg = load_filter("git/diff.toml");
+
+    assert_eq!(cfg.command.first(), "git diff");
+
+    let run = cfg.run.unwrap();
+    assert!(run.contains("--stat"));
+    assert!(run.contains("{args}"));
+
+    assert_eq!(cfg.match_output.len(), 1);
+    assert_eq!(cfg.match_output[0].contains, "fatal:");
+
+    let success = cfg.on_success.unwrap();
+    assert_eq!(success.output.as_deref(), Some("{output}"));
+
+    let failure = cfg.on_failure.unwrap();
+    assert_eq!(failure.tail, Some(5));
+}
+
+// --- Minimal / defaults ---
+
+#[test]
+fn test_minimal_config_only_command() {
+    let cfg: FilterConfig = toml::from_str(r#"command = "echo""#).unwrap();
+
+    assert_eq!(cfg.command.first(), "echo");
+    assert_eq!(cfg.run, None);
+    assert!(cfg.skip.is_empty());
+    assert!(cfg.keep.is_empty());
+    assert!(cfg.step.is_empty());
+    assert_eq!(cfg.extract, None);
+    assert!(cfg.match_output.is_empty());
+    assert!(cfg.section.is_empty());
+    assert_eq!(cfg.on_success, None);
+    assert_eq!(cfg.on_failure, None);
+    assert_eq!(cfg.parse, None);
+    assert_eq!(cfg.output, None);
+    assert_eq!(cfg.fallback, None);
+    assert!(cfg.replace.is_empty());
+    assert!(!cfg.skip_summary);
+    assert_eq!(cfg.max_output_lines, None);
+    assert!(!cfg.dedup);
+    assert_eq!(cfg.dedup_window, None);
+    assert!(!cfg.dedup_fuzzy);
+    assert_eq!(cfg.dedup_threshold, None);
+    assert!(!cfg.strip_ansi);
+    assert!(!cfg.trim_lines);
+    assert!(!cfg.strip_spinners);
+    assert!(!cfg.strip_empty_lines);
+    assert!(!cfg.collapse_empty_lines);
+    assert_eq!(cfg.lua_script, None);
+    assert_eq!(cfg.fail_when, None);
+    assert_eq!(cfg.json, None);
+}
+
+// --- Negative tests ---
+
+#[test]
+fn test_missing_command_field_fails() {
+    let result: Result<FilterConfig, _> = toml::from_str(r#"run = "echo hello""#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_wrong_type_for_skip_fails() {
+    let result: Result<FilterConfig, _> = toml::from_str(
+        r#"command = "echo"
+skip = "not-an-array""#,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_wrong_type_for_tail_fails() {
+    let result: Result<FilterConfig, _> = toml::from_str(
+        r#"command = "echo"
+[on_success]
+tail = "five""#,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_malformed_toml_fails() {
+    let result: Result<FilterConfig, _> = toml::from_str("command = [unterminated");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_empty_toml_fails() {
+    let result: Result<FilterConfig, _> = toml::from_str("");
+    assert!(result.is_err());
+}
+
+// --- TrendConfig ---
+
+#[test]
+fn trend_count_sums_matching_lines() {
+    let trend = TrendConfig {
+        label: "failures".to_string(),
+        pattern: r"test result: \w+\. \d+ passed; (\d+) failed".to_string(),
+    };
+    let output =
+        "test result: FAILED. 3 passed; 2 failed;\nnoise\ntest result: ok. 5 passed; 0 failed;";
+    assert_eq!(trend.count(output), Some(2));
+    assert_eq!(trend.count("no summary"), None);
+}
+
+#[test]
+fn trend_count_missing_group_is_zero() {
+    let trend = TrendConfig {
+        label: "failures".to_string(),
+        pattern: r"^(?:(\d+) failed, )?\d+ passed".to_string(),
+    };
+    assert_eq!(trend.count("4 passed in 0.1s"), Some(0));
+    assert_eq!(trend.count("1 failed, 4 passed in 0.1s"), Some(1));
+}
+
+#[test]
+fn cargo_test_records_failure_trend() {
+    let cfg = load_filter("cargo/test.toml");
+    let trend = cfg.trend.unwrap();
+    assert_eq!(trend.label, "failures");
+    assert_eq!(
+        trend.count("test result: FAILED. 1 passed; 1 failed; 0 ignored"),
+        Some(1)
+    );
+}
@@ -0,0 +1,221 @@
+//! `tokf daemon`: resolved filters kept in memory behind a Unix socket.
+//!
+//! Every hook call is a fresh `tokf` process that has to load the discovery
+//! cache before it can rewrite anything. While a daemon is running,
+//! `hook handle`, `tokf rewrite` and `tokf run` ask it instead. When no
+//! daemon answers — not started, stopped, a different tokf version, or no
+//! Unix sockets on this platform — they resolve filters themselves exactly
+//! as before, so the daemon is never required.
+//!
+//! Each connection carries one JSON request line and one JSON response
+//! line. Requests name their [`Scope`], because search dirs and the filter
+//! policy depend on the caller's working directory, not the daemon's.
+//! Skip patterns and user rewrite rules stay on the client side for the
+//! same reason.
+
+#[cfg(unix)]
+mod server;
+
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::policy::FilterPolicy;
+use crate::config::settings::load_settings;
+use crate::config::types::FilterConfig;
+
+#[cfg(unix)]
+pub use server::serve;
+
+/// Protocol version: requests from any other tokf build are refused, and
+/// the caller falls back to resolving filters itself.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How long a client waits for the daemon before giving up on it.
+#[cfg(unix)]
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The filters a request is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scope {
+    pub search_dirs: Vec<PathBuf>,
+    pub include_builtin: bool,
+    pub policy: FilterPolicy,
+}
+
+impl Scope {
+    /// `search_dirs` under the settings of the current directory.
+    pub fn current(search_dirs: &[PathBuf], include_builtin: bool) -> Self {
+        Self {
+            search_dirs: search_dirs.to_vec(),
+            include_builtin,
+            policy: load_settings().filter_policy(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Ping,
+    Stop,
+    /// Apply the filter-derived rewrite rules to one command segment.
+    Rewrite {
+        scope: Scope,
+        command: String,
+    },
+    /// Find the filter `tokf run` would use for `words`.
+    Resolve {
+        scope: Scope,
+        words: Vec<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    Pong {
+        version: String,
+        pid: u32,
+        /// Scopes currently held in memory.
+        scopes: usize,
+    },
+    Stopping,
+    Rewritten {
+        command: String,
+    },
+    Resolved {
+        found: Option<Box<Match>>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A filter match, as `tokf run` needs it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Match {
+    pub config: FilterConfig,
+    pub source_path: PathBuf,
+    pub relative_path: PathBuf,
+    pub consumed: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: String,
+    #[serde(flatten)]
+    request: Request,
+}
+
+/// Where the daemon listens: next to the discovery cache.
+pub fn socket_path() -> Option<PathBuf> {
+    crate::paths::cache_dir().map(|d| d.join("daemon.sock"))
+}
+
+/// Whether asking the daemon is worth a try: its socket exists and
+/// `TOKF_NO_DAEMON` is not set.
+pub fn enabled() -> bool {
+    cfg!(unix)
+        && std::env::var_os("TOKF_NO_DAEMON").is_none_or(|v| v.is_empty())
+        && socket_path().is_some_and(|p| p.exists())
+}
+
+/// Send one request. `None` when no daemon answers.
+pub fn request(request: Request) -> Option<Response> {
+    let socket = socket_path()?;
+    let line = serde_json::to_string(&Envelope {
+        version: VERSION.to_string(),
+        request,
+    })
+    .ok()?;
+    let reply = exchange(&socket, &line)
+        .map_err(|e| tracing::debug!("daemon did not answer: {e}"))
+        .ok()?;
+    serde_json::from_str(&reply).ok()
+}
+
+/// The daemon's rewrite of one command segment, if it answers.
+pub fn rewrite(scope: &Scope, command: &str) -> Option<String> {
+    match request(Request::Rewrite {
+        scope: scope.clone(),
+        command: command.to_string(),
+    })? {
+        Response::Rewritten { command } => Some(command),
+        _ => None,
+    }
+}
+
+/// The daemon's filter match for `words`: `Some(None)` means it answered
+/// that no filter matches, `None` that it did not answer.
+pub fn resolve(scope: &Scope, words: &[String]) -> Option<Option<Match>> {
+    match request(Request::Resolve {
+        scope: scope.clone(),
+        words: words.to_vec(),
+    })? {
+        Response::Resolved { found } => Some(found.map(|m| *m)),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn exchange(socket: &Path, line: &str) -> std::io::Result<String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    writeln!(stream, "{line}")?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply)
+}
+
+#[cfg(not(unix))]
+fn exchange(_socket: &Path, _line: &str) -> std::io::Result<String> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_carry_the_version_next_to_the_op() {
+        let line = serde_json::to_string(&Envelope {
+            version: "1.2.3".to_string(),
+            request: Request::Ping,
+        })
+        .unwrap();
+        assert_eq!(line, r#"{"version":"1.2.3","op":"ping"}"#);
+        let back: Envelope = serde_json::from_str(&line).unwrap();
+        assert!(matches!(back.request, Request::Ping));
+    }
+
+    #[test]
+    fn resolved_filter_survives_the_round_trip() {
+        let config: FilterConfig =
+            toml::from_str("command = [\"git status\", \"git st\"]\nskip = [\"^#\"]\n").unwrap();
+        let response = Response::Resolved {
+            found: Some(Box::new(Match {
+                config,
+                source_path: PathBuf::from("/f/git/status.toml"),
+                relative_path: PathBuf::from("git/status.toml"),
+                consumed: 2,
+            })),
+        };
+        let line = serde_json::to_string(&response).unwrap();
+        let back: Response = serde_json::from_str(&line).unwrap();
+        let value = serde_json::to_value(&back).unwrap();
+        assert_eq!(value, serde_json::to_value(&response).unwrap());
+        assert_eq!(
+            value["found"]["config"]["command"],
+            serde_json::json!(["git status", "git st"])
+        );
+        assert_eq!(value["found"]["consumed"], 2);
+    }
+}
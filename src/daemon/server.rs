@@ -0,0 +1,426 @@
+//! The daemon side: an accept loop answering one request per connection,
+//! and a watcher thread that drops scopes whose filter dirs changed.
+//!
+//! A scope is resolved on its first request and kept until the
+//! fingerprint the discovery cache uses — search dir mtimes plus the tokf
+//! binary's — or any filter file under the dirs changes; the next request
+//! then resolves it again. Files are compared by mtime, or by contents
+//! with `cache_validation = "hash"`. Scopes unused for a while, or past
+//! [`MAX_SCOPES`], are dropped as well.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use super::{Envelope, Match, Request, Response, Scope, TIMEOUT, VERSION};
+use crate::config::ResolvedFilter;
+use crate::config::cache::{FileStamp, compute_mtimes, derive_patterns, snapshot};
+use crate::config::discover_filters_with;
+use crate::config::settings::{CacheValidation, load_settings};
+use crate::rewrite;
+use crate::rewrite::rules::apply_rules;
+use crate::rewrite::types::RewriteRule;
+
+/// How often the watcher re-checks filter dirs.
+const POLL: Duration = Duration::from_secs(1);
+
+/// Scopes held at once; past it the least recently used one is dropped.
+const MAX_SCOPES: usize = 64;
+
+/// Scopes unused for this long are dropped; the next request reloads them.
+const IDLE: Duration = Duration::from_mins(30);
+
+/// What a scope's filters were loaded from, to tell when they go stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stamp {
+    search_dirs: Vec<PathBuf>,
+    fingerprint: Vec<(String, u64)>,
+    validation: CacheValidation,
+    files: Vec<FileStamp>,
+}
+
+impl Stamp {
+    fn take(search_dirs: &[PathBuf]) -> Self {
+        let validation = load_settings().cache_validation();
+        Self {
+            search_dirs: search_dirs.to_vec(),
+            fingerprint: compute_mtimes(search_dirs),
+            validation,
+            files: snapshot(search_dirs, validation),
+        }
+    }
+
+    /// Walks (and in hash mode, hashes) every filter file: call it without
+    /// holding the entries lock.
+    fn is_stale(&self) -> bool {
+        compute_mtimes(&self.search_dirs) != self.fingerprint
+            || snapshot(&self.search_dirs, self.validation) != self.files
+    }
+}
+
+/// One scope's resolved filters and the rewrite rules derived from them.
+struct Entry {
+    stamp: Stamp,
+    filters: Vec<ResolvedFilter>,
+    rules: Vec<RewriteRule>,
+    last_used: Instant,
+}
+
+impl Entry {
+    fn load(scope: &Scope) -> anyhow::Result<Self> {
+        // Stamp first: an edit made while discovering marks it stale.
+        let stamp = Stamp::take(&scope.search_dirs);
+        let filters =
+            discover_filters_with(&scope.search_dirs, scope.include_builtin, &scope.policy)?;
+        let rules =
+            rewrite::rules_from_patterns(filters.iter().flat_map(|f| derive_patterns(&f.config)));
+        Ok(Self {
+            stamp,
+            filters,
+            rules,
+            last_used: Instant::now(),
+        })
+    }
+}
+
+/// Entries keyed by their scope's JSON.
+type Entries = Arc<Mutex<HashMap<String, Entry>>>;
+
+/// Listen on `socket` until a `stop` request arrives.
+///
+/// # Errors
+///
+/// Returns an error if another daemon already listens on `socket`, or the
+/// socket cannot be created.
+pub fn serve(socket: &Path) -> anyhow::Result<()> {
+    if UnixStream::connect(socket).is_ok() {
+        anyhow::bail!("a daemon is already listening on {}", socket.display());
+    }
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Left behind by a daemon that did not exit cleanly.
+    let _ = std::fs::remove_file(socket);
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("failed to listen on {}", socket.display()))?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+
+    let entries = Entries::default();
+    let watched = Arc::clone(&entries);
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(POLL);
+            drop_stale(&watched);
+        }
+    });
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        if serve_connection(&stream, &entries) {
+            break;
+        }
+    }
+    let _ = std::fs::remove_file(socket);
+    Ok(())
+}
+
+/// Answer one request. Returns true when the daemon should stop.
+fn serve_connection(stream: &UnixStream, entries: &Entries) -> bool {
+    // A client that never sends a full line must not hold up the others.
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+    let mut line = String::new();
+    if BufReader::new(stream).read_line(&mut line).is_err() {
+        return false;
+    }
+    let response = match serde_json::from_str::<Envelope>(&line) {
+        Ok(envelope) => answer(envelope, entries),
+        Err(e) => Response::Error {
+            message: format!("invalid request: {e}"),
+        },
+    };
+    let stop = matches!(response, Response::Stopping);
+    if let Ok(reply) = serde_json::to_string(&response) {
+        let mut writer = stream;
+        let _ = writeln!(writer, "{reply}");
+    }
+    stop
+}
+
+fn answer(envelope: Envelope, entries: &Entries) -> Response {
+    match envelope.request {
+        Request::Ping => Response::Pong {
+            version: VERSION.to_string(),
+            pid: std::process::id(),
+            scopes: lock(entries).len(),
+        },
+        Request::Stop => Response::Stopping,
+        _ if envelope.version != VERSION => Response::Error {
+            message: format!(
+                "daemon runs tokf {VERSION}, client is tokf {}",
+                envelope.version
+            ),
+        },
+        Request::Rewrite { scope, command } => {
+            with_entry(entries, &scope, |entry| Response::Rewritten {
+                command: apply_rules(&entry.rules, &command),
+            })
+        }
+        Request::Resolve { scope, words } => with_entry(entries, &scope, |entry| {
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            let found = entry.filters.iter().find_map(|f| {
+                f.matches(&words).map(|consumed| {
+                    Box::new(Match {
+                        config: f.config.clone(),
+                        source_path: f.source_path.clone(),
+                        relative_path: f.relative_path.clone(),
+                        consumed,
+                    })
+                })
+            });
+            Response::Resolved { found }
+        }),
+    }
+}
+
+/// Drop entries whose filter files changed or that sat unused past
+/// [`IDLE`]. The files are checked with the lock released, so requests are
+/// not held up by the scan.
+fn drop_stale(entries: &Entries) {
+    let mut stale: Vec<(String, Stamp)> = lock(entries)
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.stamp.clone()))
+        .collect();
+    stale.retain(|(_, stamp)| stamp.is_stale());
+    let mut entries = lock(entries);
+    for (key, stamp) in stale {
+        // Reloaded meanwhile: the new entry has a fresh stamp.
+        if entries.get(&key).is_some_and(|e| e.stamp == stamp) {
+            entries.remove(&key);
+        }
+    }
+    entries.retain(|_, entry| entry.last_used.elapsed() < IDLE);
+}
+
+/// Run `f` on the scope's entry, resolving the scope first if needed.
+/// Discovery runs with the lock released.
+fn with_entry(entries: &Entries, scope: &Scope, f: impl FnOnce(&Entry) -> Response) -> Response {
+    let key = serde_json::to_string(scope).unwrap_or_default();
+    if let Some(entry) = lock(entries).get_mut(&key) {
+        entry.last_used = Instant::now();
+        return f(entry);
+    }
+    let loaded = match Entry::load(scope) {
+        Ok(entry) => entry,
+        Err(e) => {
+            return Response::Error {
+                message: format!("filter discovery failed: {e:#}"),
+            };
+        }
+    };
+    let mut entries = lock(entries);
+    entries.entry(key.clone()).or_insert(loaded);
+    evict_past(&mut entries, MAX_SCOPES);
+    f(&entries[&key])
+}
+
+/// Drop the least recently used entries until at most `cap` remain.
+fn evict_past(entries: &mut HashMap<String, Entry>, cap: usize) {
+    while entries.len() > cap {
+        let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        entries.remove(&oldest);
+    }
+}
+
+fn lock(entries: &Entries) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+    entries.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::config::policy::FilterPolicy;
+
+    fn scope(dir: &Path) -> Scope {
+        Scope {
+            search_dirs: vec![dir.to_path_buf()],
+            include_builtin: false,
+            policy: FilterPolicy::default(),
+        }
+    }
+
+    fn send(entries: &Entries, request: Request) -> Response {
+        answer(
+            Envelope {
+                version: VERSION.to_string(),
+                request,
+            },
+            entries,
+        )
+    }
+
+    #[test]
+    fn rewrites_and_resolves_from_memory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("git-status.toml"),
+            "command = \"git status\"",
+        )
+        .unwrap();
+        let entries = Entries::default();
+
+        let rewritten = send(
+            &entries,
+            Request::Rewrite {
+                scope: scope(dir.path()),
+                command: "git status -s".to_string(),
+            },
+        );
+        let rewritten = serde_json::to_value(rewritten).unwrap();
+        assert_eq!(rewritten["command"], "tokf run git status -s");
+
+        let resolved = send(
+            &entries,
+            Request::Resolve {
+                scope: scope(dir.path()),
+                words: vec!["git".to_string(), "status".to_string()],
+            },
+        );
+        let resolved = serde_json::to_value(resolved).unwrap();
+        assert_eq!(resolved["found"]["consumed"], 2);
+        assert_eq!(lock(&entries).len(), 1, "both requests share one entry");
+    }
+
+    #[test]
+    fn refuses_requests_from_another_version() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let response = answer(
+            Envelope {
+                version: "0.0.0-other".to_string(),
+                request: Request::Rewrite {
+                    scope: scope(dir.path()),
+                    command: "git status".to_string(),
+                },
+            },
+            &Entries::default(),
+        );
+        assert!(matches!(response, Response::Error { .. }), "{response:?}");
+    }
+
+    #[test]
+    fn entries_go_stale_when_a_filter_dir_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut stamp = Entry::load(&scope(dir.path())).unwrap().stamp;
+        assert!(!stamp.is_stale());
+        stamp.fingerprint[0].1 = stamp.fingerprint[0].1.wrapping_sub(1);
+        assert!(stamp.is_stale());
+    }
+
+    #[test]
+    fn entries_go_stale_when_a_filter_is_edited_in_place() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("git/status.toml");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "command = \"git status\"").unwrap();
+        let entries = Entries::default();
+        send(
+            &entries,
+            Request::Rewrite {
+                scope: scope(dir.path()),
+                command: "git status".to_string(),
+            },
+        );
+        let stamp = lock(&entries).values().next().unwrap().stamp.clone();
+        drop_stale(&entries);
+        assert_eq!(lock(&entries).len(), 1);
+
+        // A subdirectory edit leaves the search dir's mtime alone.
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::write(&file, "command = \"git status -s\"").unwrap();
+        assert_eq!(compute_mtimes(&stamp.search_dirs), stamp.fingerprint);
+        drop_stale(&entries);
+        assert!(lock(&entries).is_empty());
+    }
+
+    #[test]
+    fn hash_mode_entries_see_edits_that_keep_mtimes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("status.toml");
+        std::fs::write(&file, "command = \"git status\"").unwrap();
+        let mut stamp = Entry::load(&scope(dir.path())).unwrap().stamp;
+        stamp.validation = CacheValidation::Hash;
+        stamp.files = snapshot(&stamp.search_dirs, CacheValidation::Hash);
+
+        let recorded = std::fs::metadata(&file).unwrap().modified().unwrap();
+        std::fs::write(&file, "command = \"git log\"").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(recorded)
+            .unwrap();
+        assert!(stamp.is_stale());
+    }
+
+    #[test]
+    fn least_recently_used_scopes_are_evicted_past_the_cap() {
+        let dirs: Vec<_> = (0..3).map(|_| tempfile::TempDir::new().unwrap()).collect();
+        let entries = Entries::default();
+        for dir in [&dirs[0], &dirs[1], &dirs[0], &dirs[2]] {
+            send(
+                &entries,
+                Request::Rewrite {
+                    scope: scope(dir.path()),
+                    command: "ls".to_string(),
+                },
+            );
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        evict_past(&mut lock(&entries), 2);
+        let held: Vec<String> = lock(&entries).keys().cloned().collect();
+        let key = |dir: &tempfile::TempDir| serde_json::to_string(&scope(dir.path())).unwrap();
+        assert!(held.contains(&key(&dirs[0])));
+        assert!(!held.contains(&key(&dirs[1])), "least recently used");
+        assert!(held.contains(&key(&dirs[2])));
+    }
+
+    #[test]
+    fn serves_over_the_socket_until_stopped() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let socket = dir.path().join("d.sock");
+        let server = {
+            let socket = socket.clone();
+            std::thread::spawn(move || serve(&socket))
+        };
+        let ping = r#"{"version":"x","op":"ping"}"#;
+        let mut reply = None;
+        for _ in 0..100 {
+            if let Ok(r) = super::super::exchange(&socket, ping) {
+                reply = Some(r);
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let reply: Response = serde_json::from_str(&reply.expect("daemon never came up")).unwrap();
+        assert!(matches!(reply, Response::Pong { .. }), "{reply:?}");
+
+        assert!(serve(&socket).is_err(), "a second daemon must not start");
+
+        super::super::exchange(&socket, r#"{"version":"x","op":"stop"}"#).unwrap();
+        server.join().unwrap().unwrap();
+        assert!(!socket.exists(), "socket removed on stop");
+    }
+}
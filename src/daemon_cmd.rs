@@ -0,0 +1,81 @@
+//! `tokf daemon`: run, query or stop the filter daemon.
+//!
+//! Running it is optional. It only saves hook and `tokf run` calls the
+//! filter discovery they would otherwise do themselves.
+
+use std::path::Path;
+
+use clap::Args;
+
+use tokf::daemon::{self, Request, Response};
+
+use crate::cli_error::{self, ErrorKind};
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Report whether a daemon is running, then exit
+    #[arg(long, conflicts_with = "stop")]
+    status: bool,
+    /// Ask the running daemon to exit
+    #[arg(long)]
+    stop: bool,
+}
+
+pub fn cmd_daemon(args: &DaemonArgs) -> i32 {
+    let Some(socket) = daemon::socket_path() else {
+        return cli_error::report(
+            ErrorKind::Other,
+            &format_args!("could not determine the cache directory for the daemon socket"),
+        );
+    };
+    if args.status {
+        status(&socket)
+    } else if args.stop {
+        stop(&socket)
+    } else {
+        run(&socket)
+    }
+}
+
+fn status(socket: &Path) -> i32 {
+    if let Some(Response::Pong {
+        version,
+        pid,
+        scopes,
+    }) = daemon::request(Request::Ping)
+    {
+        println!(
+            "running: pid {pid}, tokf {version}, {scopes} scope(s) in memory, socket {}",
+            socket.display()
+        );
+        return 0;
+    }
+    println!("not running (socket {})", socket.display());
+    1
+}
+
+fn stop(socket: &Path) -> i32 {
+    if matches!(daemon::request(Request::Stop), Some(Response::Stopping)) {
+        eprintln!("[tokf] daemon stopped");
+    } else {
+        eprintln!("[tokf] daemon: not running ({})", socket.display());
+    }
+    0
+}
+
+#[cfg(unix)]
+fn run(socket: &Path) -> i32 {
+    eprintln!("[tokf] daemon listening on {}", socket.display());
+    match daemon::serve(socket) {
+        Ok(()) => 0,
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("daemon: {e:#}")),
+    }
+}
+
+#[cfg(not(unix))]
+fn run(_socket: &Path) -> i32 {
+    cli_error::report(
+        ErrorKind::Other,
+        &format_args!("the daemon needs Unix domain sockets, which this platform lacks"),
+    )
+}
@@ -0,0 +1,187 @@
+//! `tokf run --diff` and `tokf last --diff`: the raw output against what the
+//! filter kept, as a unified diff, to check a filter before trusting it.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op<'a> {
+    Same(&'a str),
+    Hidden(&'a str),
+    Added(&'a str),
+}
+
+impl Op<'_> {
+    const fn is_change(self) -> bool {
+        !matches!(self, Self::Same(_))
+    }
+}
+
+/// Print the size summary and the diff of `raw` against `filtered` to stderr.
+pub fn print_diff(raw: &str, filtered: &str) {
+    eprint!("{}", render(raw, filtered));
+}
+
+/// The size summary and the diff of `raw` against `filtered`.
+pub fn render(raw: &str, filtered: &str) -> String {
+    let mut out = format!(
+        "[tokf] diff: raw {} \u{2192} filtered {}\n",
+        size(raw),
+        size(filtered)
+    );
+    let raw_lines: Vec<&str> = raw.lines().collect();
+    let filtered_lines: Vec<&str> = filtered.lines().collect();
+    let ops = line_ops(&raw_lines, &filtered_lines);
+    if !ops.iter().any(|op| op.is_change()) {
+        out.push_str("[tokf] diff: filtered output is identical\n");
+        return out;
+    }
+    out.push_str("--- raw\n+++ filtered\n");
+    for (start, end) in hunks(&ops) {
+        write_hunk(&mut out, &ops, start, end);
+    }
+    out
+}
+
+/// `1536 bytes (~384 tokens)`, with the same bytes/4 estimate as tracking.
+fn size(text: &str) -> String {
+    format!("{} bytes (~{} tokens)", text.len(), text.len() / 4)
+}
+
+/// Walk both outputs in order. A filtered line found later in the raw output
+/// hides the raw lines before it; one not found at all was added (rewritten
+/// by `replace` or a template).
+fn line_ops<'a>(raw: &[&'a str], filtered: &[&'a str]) -> Vec<Op<'a>> {
+    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, line) in raw.iter().enumerate() {
+        positions.entry(line).or_default().push(i);
+    }
+
+    let mut ops = Vec::new();
+    let mut next = 0;
+    for line in filtered {
+        let found = positions.get(line).and_then(|at| {
+            let k = at.partition_point(|&i| i < next);
+            at.get(k).copied()
+        });
+        if let Some(k) = found {
+            ops.extend(raw[next..k].iter().map(|l| Op::Hidden(l)));
+            ops.push(Op::Same(line));
+            next = k + 1;
+        } else {
+            ops.push(Op::Added(line));
+        }
+    }
+    ops.extend(raw[next..].iter().map(|l| Op::Hidden(l)));
+    ops
+}
+
+/// Op index ranges `[start, end)` covering each run of changes plus
+/// [`CONTEXT`] lines either side; overlapping ranges are merged.
+fn hunks(ops: &[Op<'_>]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if !op.is_change() {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+fn write_hunk(out: &mut String, ops: &[Op<'_>], start: usize, end: usize) {
+    let in_raw = |op: &&Op<'_>| !matches!(op, Op::Added(_));
+    let in_filtered = |op: &&Op<'_>| !matches!(op, Op::Hidden(_));
+    let (before, hunk) = (&ops[..start], &ops[start..end]);
+    let _ = writeln!(
+        out,
+        "@@ -{} +{} @@",
+        range(
+            before.iter().filter(in_raw).count(),
+            hunk.iter().filter(in_raw).count()
+        ),
+        range(
+            before.iter().filter(in_filtered).count(),
+            hunk.iter().filter(in_filtered).count()
+        )
+    );
+    for op in &ops[start..end] {
+        let _ = match op {
+            Op::Same(l) => writeln!(out, " {l}"),
+            Op::Hidden(l) => writeln!(out, "-{l}"),
+            Op::Added(l) => writeln!(out, "+{l}"),
+        };
+    }
+}
+
+/// Unified-diff range: 1-based start line and length; an empty range names
+/// the line before it.
+fn range(before: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{before},0")
+    } else {
+        format!("{},{len}", before + 1)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_ops_mark_hidden_and_added_lines() {
+        let ops = line_ops(&["a", "noise", "b", "noise"], &["a", "b", "summary"]);
+        assert_eq!(
+            ops,
+            vec![
+                Op::Same("a"),
+                Op::Hidden("noise"),
+                Op::Same("b"),
+                Op::Added("summary"),
+                Op::Hidden("noise"),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_emits_unified_hunks_with_context() {
+        let lines = |skip: &[i32]| -> String {
+            let kept: Vec<String> = (1..=12)
+                .filter(|i| !skip.contains(i))
+                .map(|i| i.to_string())
+                .collect();
+            kept.join("\n") + "\n"
+        };
+        let out = render(&lines(&[]), &lines(&[2, 11]));
+        assert!(
+            out.starts_with(
+                "[tokf] diff: raw 27 bytes (~6 tokens) \u{2192} filtered 22 bytes (~5 tokens)\n"
+            ),
+            "{out}"
+        );
+        assert!(
+            out.contains("--- raw\n+++ filtered\n@@ -1,5 +1,4 @@\n 1\n-2\n 3\n 4\n 5\n"),
+            "{out}"
+        );
+        assert!(
+            out.contains("@@ -8,5 +7,4 @@\n 8\n 9\n 10\n-11\n 12\n"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn identical_output_has_no_hunks() {
+        let out = render("a\nb\n", "a\nb");
+        assert!(out.ends_with("filtered output is identical\n"), "{out}");
+        assert!(!out.contains("@@"));
+    }
+}
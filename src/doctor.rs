@@ -0,0 +1,68 @@
+//! `tokf doctor`: check the local setup for problems tokf cannot report at
+//! the moment they happen, such as hook shims pinned to a moved binary.
+
+use std::path::Path;
+
+use tokf::hook::{self, agent::Agent, shim::ShimStatus};
+
+pub fn cmd_doctor() -> i32 {
+    let current = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("[tokf] cannot locate the running binary: {e}");
+            return 1;
+        }
+    };
+    let mut problems = 0;
+    for agent in Agent::ALL {
+        for (scope, global) in [("project", false), ("global", true)] {
+            let Ok((hook_dir, _)) = hook::install_paths(global, agent) else {
+                continue;
+            };
+            let script = hook::shim_path(&hook_dir, agent);
+            let status = hook::shim::check(&script, &current);
+            let label = match agent {
+                Agent::Claude => scope.to_string(),
+                other => format!("{scope}, {other}"),
+            };
+            let fix = match (global, agent) {
+                (false, Agent::Claude) => "tokf hook install".to_string(),
+                (true, Agent::Claude) => "tokf hook install --global".to_string(),
+                (false, other) => format!("tokf hook install --agent {other}"),
+                (true, other) => format!("tokf hook install --global --agent {other}"),
+            };
+            if let Some(problem) = describe(&status, &current) {
+                problems += 1;
+                println!("✗ hook ({label}) {}: {problem}", script.display());
+                println!("    fix: {fix}");
+            } else if status == ShimStatus::Current {
+                println!("✓ hook ({label}) {}", script.display());
+            }
+        }
+    }
+    if problems == 0 {
+        println!("no problems found");
+        0
+    } else {
+        1
+    }
+}
+
+/// A one-line description of what is wrong with a shim, if anything.
+fn describe(status: &ShimStatus, current: &Path) -> Option<String> {
+    match status {
+        ShimStatus::Missing | ShimStatus::Current => None,
+        ShimStatus::Stale { pinned } => Some(format!(
+            "pinned binary {} no longer exists (falling back to tokf on PATH)",
+            pinned.display()
+        )),
+        ShimStatus::Mismatch { pinned } => Some(format!(
+            "pinned to {}, but this tokf is {}",
+            pinned.display(),
+            current.display()
+        )),
+        ShimStatus::Unrecognized => {
+            Some("not a tokf shim; cannot tell which binary it runs".into())
+        }
+    }
+}
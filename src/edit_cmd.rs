@@ -0,0 +1,80 @@
+//! `tokf edit <filter>`: open an active filter in `$VISUAL` / `$EDITOR`,
+//! then run `tokf check` on the result.
+//!
+//! A built-in filter is first copied to `.tokf/filters/` under the same
+//! relative path (`git/push` → `.tokf/filters/git/push.toml`), so the edit
+//! overrides it for this project.
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use tokf::config;
+use tokf::runner::shell::{shell_command, shell_escape};
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+
+#[derive(Args)]
+pub struct EditArgs {
+    /// Filter relative path without extension (e.g. "git/push")
+    filter: String,
+}
+
+pub fn cmd_edit(args: &EditArgs, include_builtin: bool) -> i32 {
+    match edit(args, include_builtin) {
+        Ok(path) => crate::cli::check::cmd_check(&path),
+        Err(e) => e.report(),
+    }
+}
+
+/// Resolve the file to edit and run the editor on it; returns its path.
+fn edit(args: &EditArgs, include_builtin: bool) -> Result<PathBuf, CliError> {
+    let filters =
+        config::cache::discover_with_cache(&config::default_search_dirs(), include_builtin)
+            .kind(ErrorKind::Config)?;
+    let found = config::find_by_name(&filters, &args.filter).ok_or_else(|| {
+        CliError::new(
+            ErrorKind::FilterNotFound,
+            format_args!("filter not found: {}", args.filter),
+        )
+    })?;
+    let path = if found.priority == u8::MAX {
+        let cwd = std::env::current_dir()
+            .map_err(|e| anyhow::anyhow!("could not determine the current directory: {e}"))?;
+        let dir = cwd.join(".tokf/filters");
+        let name = found.relative_path.to_string_lossy();
+        let dest = crate::eject_cmd::destination(&name, &dir);
+        if dest.exists() {
+            dest
+        } else {
+            crate::eject_cmd::copy_builtin(&name, &dir, false)?
+        }
+    } else {
+        found.source_path.clone()
+    };
+
+    let editor = editor();
+    let status = shell_command(&format!(
+        "{editor} {}",
+        shell_escape(&path.to_string_lossy())
+    ))
+    .status()
+    .map_err(|e| {
+        CliError::new(
+            ErrorKind::Spawn,
+            format_args!("failed to start editor \"{editor}\": {e}"),
+        )
+    })?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("editor \"{editor}\" exited with {status}").into());
+    }
+    Ok(path)
+}
+
+/// `$VISUAL`, else `$EDITOR`, else `vi`.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string())
+}
@@ -0,0 +1,81 @@
+//! `tokf eject <filter>`: write a built-in filter's TOML to the project's
+//! `.tokf/filters/` (or the user filters dir with `--user`) so it can be
+//! customized. The copy keeps the built-in's relative path, so it overrides it.
+
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use tokf::config;
+
+use crate::cli_error::{CliError, ErrorKind};
+
+#[derive(Args)]
+pub struct EjectArgs {
+    /// Built-in filter relative path without extension (e.g. "git/push")
+    filter: String,
+    /// Write to the user filters dir instead of `.tokf/filters/`
+    #[arg(long)]
+    user: bool,
+    /// Overwrite an existing file
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn cmd_eject(args: &EjectArgs) -> i32 {
+    let dir = if args.user {
+        tokf::paths::config_dir().map(|d| d.join("filters"))
+    } else {
+        std::env::current_dir()
+            .ok()
+            .map(|cwd| cwd.join(".tokf/filters"))
+    };
+    let Some(dir) = dir else {
+        return crate::cli_error::report(
+            ErrorKind::Other,
+            &"could not determine the filters directory",
+        );
+    };
+    match copy_builtin(&args.filter, &dir, args.force) {
+        Ok(_) => 0,
+        Err(e) => e.report(),
+    }
+}
+
+/// Where the built-in `name` goes under `dir`.
+pub fn destination(name: &str, dir: &Path) -> PathBuf {
+    let name = name.strip_suffix(".toml").unwrap_or(name);
+    dir.join(format!("{name}.toml"))
+}
+
+/// Write the built-in filter `name` to [`destination`] and return the path.
+/// An existing file is an error unless `force` is set.
+pub fn copy_builtin(name: &str, dir: &Path, force: bool) -> Result<PathBuf, CliError> {
+    let dest = destination(name, dir);
+    let relative = dest.strip_prefix(dir).unwrap_or(&dest);
+    let content = config::get_embedded_filter(relative).ok_or_else(|| {
+        CliError::new(
+            ErrorKind::FilterNotFound,
+            format_args!("no built-in filter named {name}"),
+        )
+    })?;
+    if dest.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{} already exists (use --force to overwrite)",
+            dest.display()
+        )
+        .into());
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| anyhow::anyhow!("failed to create {}: {e}", parent.display()))?;
+    }
+    std::fs::write(&dest, content)
+        .map_err(|e| anyhow::anyhow!("failed to write {}: {e}", dest.display()))?;
+    eprintln!(
+        "[tokf] copied built-in {} to {}",
+        relative.with_extension("").display(),
+        dest.display()
+    );
+    Ok(dest)
+}
@@ -0,0 +1,123 @@
+//! `tokf explain <command>`: which filter handles a command and why, and,
+//! given a fixture, what each stage of that filter does to it.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use clap::Args;
+
+use tokf::config::{self, ResolvedFilter};
+use tokf::filter::{self, explain};
+use tokf::runner::CommandResult;
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// The command string to explain (e.g. "cargo test --lib")
+    command: String,
+    /// Captured output to trace through the filter
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+    /// Exit code to assume for branch selection
+    #[arg(long, default_value_t = 0)]
+    exit_code: i32,
+}
+
+pub fn cmd_explain(args: &ExplainArgs, no_cache: bool, include_builtin: bool) -> i32 {
+    match run(args, no_cache, include_builtin) {
+        Ok(report) => {
+            print!("{report}");
+            0
+        }
+        Err(e) => e.report(),
+    }
+}
+
+fn run(args: &ExplainArgs, no_cache: bool, include_builtin: bool) -> Result<String, CliError> {
+    let search_dirs = config::default_search_dirs();
+    let filters = if no_cache {
+        config::discover_all_filters(&search_dirs, include_builtin)
+    } else {
+        config::cache::discover_with_cache(&search_dirs, include_builtin)
+    }
+    .kind(ErrorKind::Config)?;
+
+    let words: Vec<&str> = args.command.split_whitespace().collect();
+    let mut matching = filters
+        .iter()
+        .filter_map(|f| f.matches(&words).map(|consumed| (f, consumed)));
+    let Some((filter, consumed)) = matching.next() else {
+        return Err(CliError::new(
+            ErrorKind::FilterNotFound,
+            format_args!(
+                "no filter found for \"{}\"; output would pass through",
+                args.command
+            ),
+        ));
+    };
+
+    let mut report = String::new();
+    write_resolution(&mut report, filter, &words, consumed);
+    for (shadowed, _) in matching {
+        let _ = writeln!(
+            report,
+            "  also matches {} [{}], shadowed",
+            display_name(shadowed),
+            shadowed.priority_label()
+        );
+    }
+
+    let Some(fixture) = &args.fixture else {
+        report.push_str("\npass --fixture <file> to trace the filter over captured output\n");
+        return Ok(report);
+    };
+    let text = std::fs::read_to_string(fixture)
+        .map_err(|e| anyhow::anyhow!("failed to read fixture: {}: {e}", fixture.display()))?;
+    let result = CommandResult::from_output(&text, args.exit_code);
+    let rest: Vec<String> = words[consumed..].iter().map(ToString::to_string).collect();
+    write_trace(&mut report, &explain::trace(&filter.config, &result, &rest));
+    let output = filter::apply(&filter.config, &result, &rest).output;
+    let _ = writeln!(report, "\n--- output ---\n{output}");
+    Ok(report)
+}
+
+fn write_resolution(report: &mut String, filter: &ResolvedFilter, words: &[&str], consumed: usize) {
+    let _ = writeln!(
+        report,
+        "filter: {} [{}] {}",
+        display_name(filter),
+        filter.priority_label(),
+        filter.source_path.display()
+    );
+    let pattern = filter
+        .config
+        .command
+        .patterns()
+        .iter()
+        .find(|p| config::pattern_matches_prefix(p, words).is_some())
+        .map_or("?", String::as_str);
+    let _ = writeln!(
+        report,
+        "  pattern \"{pattern}\" matched \"{}\"; args: {:?}",
+        words[..consumed].join(" "),
+        &words[consumed..]
+    );
+}
+
+fn write_trace(report: &mut String, steps: &[explain::TraceStep]) {
+    for (i, step) in steps.iter().enumerate() {
+        let _ = writeln!(report, "\n{}. {}", i + 1, step.stage);
+        for note in &step.notes {
+            let _ = writeln!(report, "   {note}");
+        }
+    }
+}
+
+fn display_name(filter: &ResolvedFilter) -> String {
+    filter
+        .relative_path
+        .with_extension("")
+        .display()
+        .to_string()
+}
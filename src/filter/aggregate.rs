@@ -1,14 +1,25 @@
-use std::collections::HashMap;
-
-use regex::Regex;
+use std::collections::{HashMap, HashSet};
 
 use super::section::SectionMap;
-use crate::config::types::AggregateRule;
+use crate::config::types::{AggregateRule, Aggregates};
+use crate::regex_cache;
+
+/// Run every rule of a branch's `aggregate`; later rules win on a name clash.
+pub fn run_aggregates(aggregates: &Aggregates, sections: &SectionMap) -> HashMap<String, String> {
+    aggregates
+        .rules()
+        .iter()
+        .flat_map(|rule| run_aggregate(rule, sections))
+        .collect()
+}
 
 /// Run an aggregation rule against collected sections.
 ///
-/// Extracts numeric values from section items using a regex pattern,
-/// producing sum and/or count results as string key-value pairs.
+/// Matches each section item against the rule's pattern and computes the
+/// requested statistics over capture group 1: `sum`, `max`, `min` and `avg`
+/// over the values that parse as numbers, `count_as` over all matches, and
+/// `unique` over the distinct captures (the whole match without a group).
+/// `max`, `min` and `avg` are left unset when no value parsed.
 pub fn run_aggregate(rule: &AggregateRule, sections: &SectionMap) -> HashMap<String, String> {
     let mut result = HashMap::new();
 
@@ -16,31 +27,57 @@ pub fn run_aggregate(rule: &AggregateRule, sections: &SectionMap) -> HashMap<Str
         return result;
     };
 
-    let Ok(re) = Regex::new(&rule.pattern) else {
+    let Some(re) = regex_cache::compile(&rule.pattern) else {
         return result;
     };
 
-    let mut sum: i64 = 0;
+    let mut values: Vec<f64> = Vec::new();
+    let mut distinct: HashSet<&str> = HashSet::new();
     let mut count: usize = 0;
 
     for item in section_data.items() {
         if let Some(caps) = re.captures(item) {
             count += 1;
-            if let Some(m) = caps.get(1)
-                && let Ok(n) = m.as_str().parse::<i64>()
+            let captured = caps
+                .get(1)
+                .or_else(|| caps.get(0))
+                .map_or("", |m| m.as_str());
+            distinct.insert(captured);
+            if caps.get(1).is_some()
+                && let Ok(n) = captured.parse::<f64>()
             {
-                sum += n;
+                values.push(n);
             }
         }
     }
 
-    if let Some(ref sum_name) = rule.sum {
-        result.insert(sum_name.clone(), sum.to_string());
+    let number = |n: f64| {
+        rule.round
+            .map_or_else(|| n.to_string(), |places| format!("{n:.places$}"))
+    };
+    let sum = values.iter().fold(0.0, |acc, n| acc + n);
+    let stats = [
+        (&rule.sum, Some(sum)),
+        (&rule.max, values.iter().copied().reduce(f64::max)),
+        (&rule.min, values.iter().copied().reduce(f64::min)),
+        #[allow(clippy::cast_precision_loss)]
+        (
+            &rule.avg,
+            (!values.is_empty()).then(|| sum / values.len() as f64),
+        ),
+    ];
+    for (name, value) in stats {
+        if let (Some(name), Some(value)) = (name, value) {
+            result.insert(name.clone(), number(value));
+        }
     }
 
     if let Some(ref count_name) = rule.count_as {
         result.insert(count_name.clone(), count.to_string());
     }
+    if let Some(ref unique_name) = rule.unique {
+        result.insert(unique_name.clone(), distinct.len().to_string());
+    }
 
     result
 }
@@ -58,6 +95,7 @@ mod tests {
             SectionData {
                 lines: items.into_iter().map(String::from).collect(),
                 blocks: Vec::new(),
+                total: None,
             },
         );
         map
@@ -69,6 +107,11 @@ mod tests {
             pattern: pattern.to_string(),
             sum: sum.map(String::from),
             count_as: count_as.map(String::from),
+            max: None,
+            min: None,
+            avg: None,
+            unique: None,
+            round: None,
         }
     }
 
@@ -158,4 +201,75 @@ mod tests {
         assert_eq!(result["passed"], "20");
         assert_eq!(result["suites"], "3");
     }
+
+    #[test]
+    fn max_min_avg_with_rounding() {
+        let sections = make_sections(
+            "durations",
+            vec!["test_a 0.52s", "test_b 1.25s", "test_c 0.1s", "setup"],
+        );
+        let r = AggregateRule {
+            max: Some("slowest".to_string()),
+            min: Some("fastest".to_string()),
+            avg: Some("mean".to_string()),
+            round: Some(2),
+            ..rule("durations", r"([\d.]+)s$", Some("total"), None)
+        };
+        let result = run_aggregate(&r, &sections);
+        assert_eq!(result["slowest"], "1.25");
+        assert_eq!(result["fastest"], "0.10");
+        assert_eq!(result["mean"], "0.62");
+        assert_eq!(result["total"], "1.87");
+    }
+
+    #[test]
+    fn max_min_avg_unset_without_values() {
+        let sections = make_sections("data", vec!["none here"]);
+        let r = AggregateRule {
+            max: Some("slowest".to_string()),
+            avg: Some("mean".to_string()),
+            ..rule("data", r"(\d+)", None, None)
+        };
+        assert!(run_aggregate(&r, &sections).is_empty());
+    }
+
+    #[test]
+    fn unique_counts_distinct_captures() {
+        let sections = make_sections(
+            "errors",
+            vec!["src/a.rs: E1", "src/b.rs: E2", "src/a.rs: E3", "warning"],
+        );
+        let r = AggregateRule {
+            unique: Some("files".to_string()),
+            ..rule("errors", r"^(\S+): E", None, Some("errors"))
+        };
+        let result = run_aggregate(&r, &sections);
+        assert_eq!(result["files"], "2");
+        assert_eq!(result["errors"], "3");
+    }
+
+    #[test]
+    fn several_rules_per_branch() {
+        let aggregates: Aggregates = toml::from_str::<toml::Value>(
+            r#"
+            [[aggregate]]
+            from = "summary"
+            pattern = '(\d+) passed'
+            sum = "passed"
+
+            [[aggregate]]
+            from = "summary"
+            pattern = '(\d+) failed'
+            sum = "failed"
+            "#,
+        )
+        .unwrap()["aggregate"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let sections = make_sections("summary", vec!["3 passed; 1 failed", "4 passed; 0 failed"]);
+        let result = run_aggregates(&aggregates, &sections);
+        assert_eq!(result["passed"], "7");
+        assert_eq!(result["failed"], "1");
+    }
 }
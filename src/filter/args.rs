@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+/// Template variables describing the arguments the user passed after the
+/// matched command.
+///
+/// - `{args}` — every argument, space-joined
+/// - `{arg1}`, `{arg2}`, … — positional (non-flag) arguments, 1-based
+/// - `{flags}` — the flag arguments, space-joined
+/// - `{flags.NAME}` — `true` for `-NAME`/`--NAME`, or `VALUE` for `--NAME=VALUE`
+///
+/// Arguments after a bare `--` are always positional.
+pub fn arg_vars(args: &[String]) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("args".to_string(), args.join(" "));
+
+    let mut flags = Vec::new();
+    let mut positional = 0;
+    let mut options_done = false;
+    for arg in args {
+        if !options_done && arg == "--" {
+            options_done = true;
+        } else if !options_done && arg.len() > 1 && arg.starts_with('-') {
+            flags.push(arg.as_str());
+            let flag = arg.trim_start_matches('-');
+            let (name, value) = flag.split_once('=').unwrap_or((flag, "true"));
+            vars.insert(format!("flags.{name}"), value.to_string());
+        } else {
+            positional += 1;
+            vars.insert(format!("arg{positional}"), arg.clone());
+        }
+    }
+    vars.insert("flags".to_string(), flags.join(" "));
+    vars
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn vars(args: &[&str]) -> HashMap<String, String> {
+        arg_vars(&args.iter().map(ToString::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn positional_args_skip_flags() {
+        let v = vars(&["--silent", "build", "-v", "extra"]);
+        assert_eq!(v["args"], "--silent build -v extra");
+        assert_eq!(v["arg1"], "build");
+        assert_eq!(v["arg2"], "extra");
+        assert!(!v.contains_key("arg3"));
+    }
+
+    #[test]
+    fn flags_are_joined_and_indexed_by_name() {
+        let v = vars(&["--release", "--target=wasm32", "-q"]);
+        assert_eq!(v["flags"], "--release --target=wasm32 -q");
+        assert_eq!(v["flags.release"], "true");
+        assert_eq!(v["flags.target"], "wasm32");
+        assert_eq!(v["flags.q"], "true");
+    }
+
+    #[test]
+    fn double_dash_ends_flags() {
+        let v = vars(&["-x", "--", "--not-a-flag", "-"]);
+        assert_eq!(v["flags"], "-x");
+        assert_eq!(v["arg1"], "--not-a-flag");
+        assert_eq!(v["arg2"], "-");
+    }
+
+    #[test]
+    fn no_args_gives_empty_strings() {
+        let v = vars(&[]);
+        assert_eq!(v["args"], "");
+        assert_eq!(v["flags"], "");
+    }
+}
@@ -0,0 +1,135 @@
+use crate::config::pattern_matches_prefix;
+use crate::config::types::{ArgBranch, ExitOutcome, FilterConfig, OutputBranch};
+
+/// Select the output branch for a run.
+///
+/// The first `[[branch]]` whose `when_args` and `when_exit` both hold wins;
+/// otherwise exit code 0 → `on_success`, anything else → `on_failure`.
+pub(super) fn select_branch<'a>(
+    config: &'a FilterConfig,
+    exit_code: i32,
+    args: &[String],
+) -> Option<&'a OutputBranch> {
+    if let Some((_, arg_branch)) = find_arg_branch(config, exit_code, args) {
+        return Some(&arg_branch.branch);
+    }
+    if exit_code == 0 {
+        config.on_success.as_ref()
+    } else {
+        config.on_failure.as_ref()
+    }
+}
+
+/// The first matching `[[branch]]` and its index.
+pub(super) fn find_arg_branch<'a>(
+    config: &'a FilterConfig,
+    exit_code: i32,
+    args: &[String],
+) -> Option<(usize, &'a ArgBranch)> {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    config
+        .branch
+        .iter()
+        .enumerate()
+        .find(|(_, b)| exit_matches(b.when_exit, exit_code) && args_match(&b.when_args, &words))
+}
+
+const fn exit_matches(when_exit: Option<ExitOutcome>, exit_code: i32) -> bool {
+    match when_exit {
+        None => true,
+        Some(ExitOutcome::Success) => exit_code == 0,
+        Some(ExitOutcome::Failure) => exit_code != 0,
+    }
+}
+
+fn args_match(patterns: &[String], words: &[&str]) -> bool {
+    patterns.is_empty()
+        || patterns
+            .iter()
+            .any(|p| pattern_matches_prefix(p, words).is_some())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn config(toml_src: &str) -> FilterConfig {
+        toml::from_str(toml_src).unwrap()
+    }
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(ToString::to_string).collect()
+    }
+
+    fn output(branch: Option<&OutputBranch>) -> Option<&str> {
+        branch.and_then(|b| b.output.as_deref())
+    }
+
+    const CARGO_TEST: &str = r#"
+command = "cargo test"
+
+[on_success]
+output = "default"
+
+[[branch]]
+when_args = ["** --doc"]
+output = "doctests"
+
+[[branch]]
+when_args = ["** --lib"]
+when_exit = "failure"
+output = "lib failed"
+"#;
+
+    #[test]
+    fn first_matching_arg_branch_wins() {
+        let c = config(CARGO_TEST);
+        assert_eq!(
+            output(select_branch(&c, 0, &args("--doc"))),
+            Some("doctests")
+        );
+        assert_eq!(
+            output(select_branch(&c, 0, &args("-p core --doc"))),
+            Some("doctests")
+        );
+        assert_eq!(
+            output(select_branch(&c, 0, &args("--lib"))),
+            Some("default")
+        );
+    }
+
+    #[test]
+    fn when_exit_restricts_outcome() {
+        let c = config(CARGO_TEST);
+        assert_eq!(
+            output(select_branch(&c, 101, &args("--lib"))),
+            Some("lib failed")
+        );
+        assert_eq!(select_branch(&c, 101, &args("--bins")), None);
+    }
+
+    #[test]
+    fn subcommand_args_pick_branch() {
+        let c = config(
+            r#"
+command = "kubectl get"
+
+[[branch]]
+when_args = ["{pods|po}"]
+output = "pods"
+
+[[branch]]
+when_args = ["events"]
+output = "events"
+"#,
+        );
+        assert_eq!(output(select_branch(&c, 0, &args("po -A"))), Some("pods"));
+        assert_eq!(
+            output(select_branch(&c, 0, &args("events"))),
+            Some("events")
+        );
+        assert_eq!(select_branch(&c, 0, &args("nodes")), None);
+        assert_eq!(find_arg_branch(&c, 0, &args("events")).unwrap().0, 1);
+    }
+}
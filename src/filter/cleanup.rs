@@ -3,7 +3,7 @@ use std::sync::OnceLock;
 
 use crate::config::types::FilterConfig;
 
-fn ansi_regex() -> &'static Regex {
+pub(super) fn ansi_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
         // Covers:
@@ -43,11 +43,17 @@ pub fn apply_line_cleanup(config: &FilterConfig, lines: &[&str]) -> Vec<String>
 
 /// Post-process the final output string.
 ///
+/// - `shorten_paths`: project-root paths → relative, home → `~` (first)
 /// - `strip_empty_lines`: removes blank and whitespace-only lines
 /// - `collapse_empty_lines`: collapses consecutive blank lines into one
 ///
 /// `strip_empty_lines` takes priority if both flags are set.
 pub fn post_process_output(config: &FilterConfig, output: String) -> String {
+    let output = if config.shorten_paths {
+        super::shorten::shorten(&output, &super::shorten::PathPrefixes::current())
+    } else {
+        output
+    };
     let trailing_newline = output.ends_with('\n');
     if config.strip_empty_lines {
         let filtered: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
@@ -81,11 +87,32 @@ pub fn post_process_output(config: &FilterConfig, output: String) -> String {
     output
 }
 
+/// `max_output_lines`: an output over `max` lines keeps its first half and
+/// last half (the odd line at the end) around a `… N lines omitted …` marker.
+pub fn cap_lines(max: Option<usize>, output: String) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    let Some(max) = max.filter(|&max| lines.len() > max) else {
+        return output;
+    };
+    let head = max / 2;
+    let omitted = lines.len() - max;
+    let noun = if omitted == 1 { "line" } else { "lines" };
+    let marker = format!("… {omitted} {noun} omitted …");
+    let mut kept = lines[..head].to_vec();
+    kept.push(&marker);
+    kept.extend_from_slice(&lines[lines.len() - (max - head)..]);
+    let mut result = kept.join("\n");
+    if output.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
     use super::*;
-    use crate::config::types::{CommandPattern, FilterConfig};
+    use crate::config::types::FilterConfig;
 
     fn minimal_config() -> FilterConfig {
         toml::from_str(r#"command = "echo""#).unwrap()
@@ -137,7 +164,7 @@ mod tests {
         let result = apply_line_cleanup(&cfg, &lines);
         assert_eq!(
             result,
-            vec!["hello".to_string(), "world".to_string(), "".to_string()]
+            vec!["hello".to_string(), "world".to_string(), String::new()]
         );
     }
 
@@ -323,4 +350,22 @@ mod tests {
         assert!(!cfg.strip_empty_lines);
         assert!(!cfg.collapse_empty_lines);
     }
+
+    // --- cap_lines ---
+
+    #[test]
+    fn cap_lines_keeps_head_and_tail_around_a_marker() {
+        let output = "1\n2\n3\n4\n5\n6\n7\n".to_string();
+        assert_eq!(cap_lines(Some(3), output), "1\n… 4 lines omitted …\n6\n7\n");
+        assert_eq!(
+            cap_lines(Some(1), "a\nb".to_string()),
+            "… 1 line omitted …\nb"
+        );
+    }
+
+    #[test]
+    fn cap_lines_leaves_short_output_alone() {
+        assert_eq!(cap_lines(Some(2), "a\nb".to_string()), "a\nb");
+        assert_eq!(cap_lines(None, "a\nb\nc".to_string()), "a\nb\nc");
+    }
 }
@@ -1,4 +1,14 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+
+/// Similarity `dedup_fuzzy` merges at when `dedup_threshold` is unset.
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// How many recent distinct lines a new line is compared to by edit distance;
+/// exact matches after normalization are found however far back they are.
+const FUZZY_CANDIDATES: usize = 32;
+
+/// Longer lines only merge when they normalize to the same text.
+const FUZZY_MAX_CHARS: usize = 300;
 
 /// Collapse duplicate lines within a sliding window.
 ///
@@ -37,10 +47,108 @@ fn dedup_windowed<'a>(lines: &[&'a str], window: usize) -> Vec<&'a str> {
     result
 }
 
+/// Collapse near-duplicate lines into their first occurrence, suffixed `(xN)`.
+///
+/// Lines are compared with digit runs masked (`src/a.rs:12:5` ≈ `src/a.rs:40:9`)
+/// and merge when they then match exactly or their character edit-distance
+/// similarity is at least `threshold` (0–1). Lines of fewer than three words
+/// (blank lines, ` |` gutters, `}`) are never merged. With `window = Some(n)`
+/// a line only merges into one of the last `n` output lines.
+pub fn apply_fuzzy(lines: &[&str], threshold: f64, window: Option<usize>) -> Vec<String> {
+    // (line, masked text for mergeable lines, occurrences)
+    let mut kept: Vec<(&str, Option<String>, usize)> = Vec::with_capacity(lines.len());
+    let mut by_mask: HashMap<String, usize> = HashMap::new();
+    let mut recent: VecDeque<usize> = VecDeque::with_capacity(FUZZY_CANDIDATES);
+    for &line in lines {
+        if line.split_whitespace().nth(2).is_none() {
+            kept.push((line, None, 1));
+            continue;
+        }
+        let masked = mask_digits(line);
+        let oldest = window.map_or(0, |n| kept.len().saturating_sub(n));
+        let found = by_mask
+            .get(&masked)
+            .copied()
+            .filter(|&i| i >= oldest)
+            .or_else(|| {
+                recent.iter().rev().copied().find(|&i| {
+                    i >= oldest
+                        && kept[i]
+                            .1
+                            .as_deref()
+                            .is_some_and(|other| similar(other, &masked, threshold))
+                })
+            });
+        if let Some(i) = found {
+            kept[i].2 += 1;
+            continue;
+        }
+        by_mask.insert(masked.clone(), kept.len());
+        if recent.len() == FUZZY_CANDIDATES {
+            recent.pop_front();
+        }
+        recent.push_back(kept.len());
+        kept.push((line, Some(masked), 1));
+    }
+    kept.into_iter()
+        .map(|(line, _, count)| {
+            if count > 1 {
+                format!("{line} (x{count})")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Replace each run of ASCII digits with `#`.
+fn mask_digits(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    for c in line.chars() {
+        if !c.is_ascii_digit() {
+            out.push(c);
+        } else if !out.ends_with('#') {
+            out.push('#');
+        }
+    }
+    out
+}
+
+/// `1 - distance / longer length >= threshold`, by character edit distance.
+#[allow(clippy::cast_precision_loss)]
+fn similar(a: &str, b: &str, threshold: f64) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest > FUZZY_MAX_CHARS {
+        return false;
+    }
+    let allowed = (1.0 - threshold) * longest as f64;
+    // The length gap alone is a lower bound on the distance.
+    a.len().abs_diff(b.len()) as f64 <= allowed && edit_distance(&a, &b) as f64 <= allowed
+}
+
+/// Levenshtein distance over two rows.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != cb);
+            row[j + 1] = substitute.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    prev[b.len()]
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
     use super::*;
+    use crate::config::types::FilterConfig;
+    use crate::runner::CommandResult;
 
     #[test]
     fn dedup_empty_input() {
@@ -85,4 +193,79 @@ mod tests {
         let lines = vec!["only"];
         assert_eq!(apply_dedup(&lines, None), vec!["only"]);
     }
+
+    #[test]
+    fn fuzzy_merges_warnings_that_differ_in_numbers() {
+        let lines = vec![
+            "warning: unused variable `x` at src/a.rs:12:5",
+            "  |",
+            "warning: unused variable `x` at src/a.rs:40:9",
+            "  |",
+            "warning: unused variable `x` at src/a.rs:7:1",
+        ];
+        assert_eq!(
+            apply_fuzzy(&lines, DEFAULT_THRESHOLD, None),
+            vec![
+                "warning: unused variable `x` at src/a.rs:12:5 (x3)",
+                "  |",
+                "  |",
+            ]
+        );
+    }
+
+    #[test]
+    fn fuzzy_threshold_decides_near_matches() {
+        let lines = vec![
+            "warning: unused import: `std::io`",
+            "warning: unused import: `std::fmt`",
+        ];
+        assert_eq!(apply_fuzzy(&lines, 0.99, None).len(), 2);
+        assert_eq!(
+            apply_fuzzy(&lines, 0.8, None),
+            vec!["warning: unused import: `std::io` (x2)"]
+        );
+        let different = vec![
+            "error: mismatched types here",
+            "warning: unused import here",
+        ];
+        assert_eq!(apply_fuzzy(&different, 0.8, None).len(), 2);
+    }
+
+    #[test]
+    fn fuzzy_window_limits_how_far_back_lines_merge() {
+        let lines = vec![
+            "retrying job 1 now",
+            "other line here",
+            "more text here",
+            "retrying job 2 now",
+        ];
+        assert_eq!(apply_fuzzy(&lines, DEFAULT_THRESHOLD, Some(2)).len(), 4);
+        assert_eq!(
+            apply_fuzzy(&lines, DEFAULT_THRESHOLD, Some(3))[0],
+            "retrying job 1 now (x2)"
+        );
+    }
+
+    #[test]
+    fn apply_dedup_fuzzy_counts_similar_lines() {
+        let config: FilterConfig = toml::from_str(
+            r#"
+    command = "test"
+    skip = ["^\\s+-->"]
+    dedup_fuzzy = true
+    "#,
+        )
+        .unwrap();
+        let result = CommandResult::from_output(
+            "warning: unused variable: `a1`\n  --> src/a.rs:3:9\n\
+             warning: unused variable: `a2`\n  --> src/b.rs:14:5\n\
+             error: could not compile `demo`",
+            1,
+        );
+        let filtered = crate::filter::apply(&config, &result, &[]);
+        assert_eq!(
+            filtered.output,
+            "warning: unused variable: `a1` (x2)\nerror: could not compile `demo`"
+        );
+    }
 }
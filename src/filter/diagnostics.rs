@@ -0,0 +1,151 @@
+//! Diagnostics picked out of a command's raw output by the filter's
+//! `[[diagnostic]]` rules, for `tokf run --sarif`.
+//!
+//! Each line (ANSI codes stripped) is tried against the rules in order and
+//! yields at most one diagnostic, from the first rule that matches. Rules
+//! with an invalid regex never match.
+
+use regex::Captures;
+
+use crate::config::types::DiagnosticRule;
+
+/// How serious a diagnostic is, as SARIF names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    /// The level a tool's own wording maps to: `error`, `fatal` and the like
+    /// are errors; `note`, `info`, `help` and `hint` are notes; anything else
+    /// is a warning.
+    pub fn parse(text: &str) -> Self {
+        match text.to_ascii_lowercase().as_str() {
+            "error" | "err" | "fatal" | "critical" | "e" => Self::Error,
+            "note" | "info" | "information" | "help" | "hint" | "i" => Self::Note,
+            _ => Self::Warning,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+        }
+    }
+}
+
+/// One diagnostic found in the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub level: Level,
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+/// Every diagnostic `rules` find in `output`, in output order.
+pub fn collect(rules: &[DiagnosticRule], output: &str) -> Vec<Diagnostic> {
+    let compiled: Vec<_> = rules
+        .iter()
+        .filter_map(|rule| Some((rule, crate::regex_cache::compile(&rule.pattern)?)))
+        .collect();
+    if compiled.is_empty() {
+        return Vec::new();
+    }
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = super::cleanup::ansi_regex().replace_all(line, "");
+            compiled
+                .iter()
+                .find_map(|(rule, re)| re.captures(&line).map(|caps| from_captures(rule, &caps)))
+        })
+        .collect()
+}
+
+fn from_captures(rule: &DiagnosticRule, caps: &Captures) -> Diagnostic {
+    let group = |name: &str| {
+        caps.name(name)
+            .map(|m| m.as_str().trim())
+            .filter(|s| !s.is_empty())
+    };
+    let number = |name: &str| group(name).and_then(|s| s.parse().ok());
+    Diagnostic {
+        file: group("file").map(str::to_string),
+        line: number("line"),
+        column: number("col"),
+        level: group("level")
+            .or(rule.level.as_deref())
+            .map_or(Level::Warning, Level::parse),
+        rule: group("rule").map(str::to_string),
+        message: group("message")
+            .unwrap_or_else(|| caps.get(0).map_or("", |m| m.as_str().trim()))
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, level: Option<&str>) -> DiagnosticRule {
+        DiagnosticRule {
+            pattern: pattern.to_string(),
+            level: level.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn named_groups_fill_the_diagnostic() {
+        let rules = [rule(
+            r"^(?P<file>[^(]+)\((?P<line>\d+),(?P<col>\d+)\): (?P<level>\w+) (?P<rule>TS\d+): (?P<message>.*)$",
+            None,
+        )];
+        let found = collect(
+            &rules,
+            "\x1b[96msrc/a.ts\x1b[0m(3,5): error TS2322: Type 'string' is not assignable\n\
+             Found 1 error.",
+        );
+        assert_eq!(
+            found,
+            [Diagnostic {
+                file: Some("src/a.ts".to_string()),
+                line: Some(3),
+                column: Some(5),
+                level: Level::Error,
+                rule: Some("TS2322".to_string()),
+                message: "Type 'string' is not assignable".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_levels_default() {
+        let rules = [
+            rule(r"^warning: (?P<message>.+)$", None),
+            rule(r"^(?P<file>\S+): .+$", Some("note")),
+            rule("(unclosed", Some("error")),
+        ];
+        let found = collect(&rules, "warning: unused import\nlib.rs: see here\nplain");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].level, Level::Warning);
+        assert_eq!(found[0].message, "unused import");
+        assert_eq!(found[1].level, Level::Note);
+        assert_eq!(found[1].file.as_deref(), Some("lib.rs"));
+        assert_eq!(found[1].message, "lib.rs: see here");
+    }
+
+    #[test]
+    fn tool_wording_maps_to_sarif_levels() {
+        assert_eq!(Level::parse("FATAL"), Level::Error);
+        assert_eq!(Level::parse("hint"), Level::Note);
+        assert_eq!(Level::parse("warn"), Level::Warning);
+    }
+}
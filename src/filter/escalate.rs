@@ -0,0 +1,99 @@
+//! Safety valve for failing runs: when a filter removed nearly everything
+//! (`min_keep_ratio`) or a lot (`escalate_when_lines_over`), the tail of the
+//! raw output is appended so the actual error is not hidden.
+
+use std::fmt::Write;
+
+use crate::config::types::FilterConfig;
+use crate::runner::CommandResult;
+
+/// Raw lines appended when a threshold is crossed.
+const TAIL_LINES: usize = 20;
+
+/// `output` with the tail of `result`'s raw output appended when the run
+/// failed and a threshold is crossed; unchanged otherwise.
+pub fn apply(config: &FilterConfig, result: &CommandResult, output: String) -> String {
+    if result.exit_code == 0 || !crossed(config, &result.combined, &output) {
+        return output;
+    }
+    let raw: Vec<&str> = result.combined.lines().collect();
+    let tail = &raw[raw.len().saturating_sub(TAIL_LINES)..];
+    let mut escalated = output.trim_end_matches('\n').to_string();
+    if !escalated.is_empty() {
+        escalated.push('\n');
+    }
+    let _ = writeln!(escalated, "--- last {} lines of raw output ---", tail.len());
+    escalated.push_str(&tail.join("\n"));
+    escalated
+}
+
+fn crossed(config: &FilterConfig, raw: &str, output: &str) -> bool {
+    let raw_lines = raw.lines().count();
+    let kept = output.lines().count();
+    if raw_lines == 0 || kept >= raw_lines {
+        return false;
+    }
+    let removed = raw_lines - kept;
+    #[allow(clippy::cast_precision_loss)]
+    let ratio = kept as f64 / raw_lines as f64;
+    config.min_keep_ratio.is_some_and(|min| ratio < min)
+        || config.escalate_when_lines_over.is_some_and(|n| removed > n)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> FilterConfig {
+        toml::from_str(&format!("command = \"x\"\n{toml}")).unwrap()
+    }
+
+    fn raw(lines: usize) -> String {
+        (1..=lines)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn low_keep_ratio_on_failure_appends_the_raw_tail() {
+        let cfg = config("min_keep_ratio = 0.02");
+        let result = CommandResult::from_output(&raw(100), 1);
+        let out = apply(&cfg, &result, "build failed\n".to_string());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "build failed");
+        assert_eq!(lines[1], "--- last 20 lines of raw output ---");
+        assert_eq!(lines[2], "line 81");
+        assert_eq!(lines.last(), Some(&"line 100"));
+    }
+
+    #[test]
+    fn successful_runs_are_never_escalated() {
+        let cfg = config("min_keep_ratio = 0.5");
+        let result = CommandResult::from_output(&raw(100), 0);
+        assert_eq!(apply(&cfg, &result, "ok".to_string()), "ok");
+    }
+
+    #[test]
+    fn ratio_at_or_above_the_minimum_is_kept_as_is() {
+        let cfg = config("min_keep_ratio = 0.02");
+        let result = CommandResult::from_output(&raw(100), 1);
+        assert_eq!(apply(&cfg, &result, "a\nb".to_string()), "a\nb");
+    }
+
+    #[test]
+    fn removed_line_count_threshold() {
+        let cfg = config("escalate_when_lines_over = 5");
+        let failing = |text: &str| CommandResult::from_output(text, 2);
+        assert_eq!(apply(&cfg, &failing(&raw(6)), "x".to_string()), "x");
+        let out = apply(&cfg, &failing(&raw(7)), String::new());
+        assert!(out.starts_with("--- last 7 lines of raw output ---\nline 1\n"));
+    }
+
+    #[test]
+    fn no_thresholds_means_no_escalation() {
+        let result = CommandResult::from_output(&raw(100), 1);
+        assert_eq!(apply(&config(""), &result, String::new()), "");
+    }
+}
@@ -0,0 +1,484 @@
+//! A step-by-step account of what a filter does to one command result.
+//!
+//! Used by `tokf explain`: the stages of [`super::apply`] are walked again with a note
+//! on what each one did; the output reported at the end is `apply`'s own.
+
+use regex::Regex;
+
+use crate::config::types::{FilterConfig, OutputBranch, RenderMode, StreamSource};
+use crate::runner::CommandResult;
+
+use super::section::{self, SectionMap};
+use super::{cleanup, dedup, json, match_output, replace, skip, spinner, stacktrace, tail};
+
+/// How many affected lines to quote under each pattern.
+const SAMPLES: usize = 3;
+
+/// Longer quoted lines and values are cut to this many characters.
+const WIDTH: usize = 60;
+
+/// One pipeline stage and what it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub stage: String,
+    pub notes: Vec<String>,
+}
+
+#[derive(Default)]
+struct Trace(Vec<TraceStep>);
+
+impl Trace {
+    fn step(&mut self, stage: &str, notes: Vec<String>) {
+        self.0.push(TraceStep {
+            stage: stage.to_string(),
+            notes,
+        });
+    }
+}
+
+/// Trace `config` over `result`, stage by stage. Stages the filter does not
+/// configure are left out.
+pub fn trace(config: &FilterConfig, result: &CommandResult, args: &[String]) -> Vec<TraceStep> {
+    let mut t = Trace::default();
+    if !trace_match_output(&mut t, config, &result.combined) {
+        trace_pipeline(&mut t, config, result, args);
+    }
+
+    let filtered = super::apply(config, result, args);
+    let mut notes = vec![format!(
+        "{} lines, {} bytes (input: {} bytes)",
+        filtered.output.lines().count(),
+        filtered.output.len(),
+        result.combined.len()
+    )];
+    if filtered.failed {
+        notes.push("fail_when condition held".to_string());
+    }
+    t.step("output", notes);
+    t.0
+}
+
+/// Returns true when a rule matched and replaced the whole output.
+fn trace_match_output(t: &mut Trace, config: &FilterConfig, combined: &str) -> bool {
+    if config.match_output.is_empty() {
+        return false;
+    }
+    let matched = match_output::find_matching_rule(&config.match_output, combined);
+    let mut notes = Vec::new();
+    for rule in &config.match_output {
+        if matched.is_some_and(|m| std::ptr::eq(m, rule)) {
+            notes.push(format!(
+                "contains {:?}: matched, output is {:?}; later stages skipped",
+                rule.contains, rule.output
+            ));
+            break;
+        }
+        notes.push(format!("contains {:?}: no match", rule.contains));
+    }
+    t.step("match_output", notes);
+    matched.is_some()
+}
+
+fn trace_pipeline(t: &mut Trace, config: &FilterConfig, result: &CommandResult, args: &[String]) {
+    let source = config.source.unwrap_or_default();
+    let lines = trace_prefilter(t, config, result.stream(source), source);
+    let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+    if config.lua_script.is_some() {
+        let replaced = super::run_script(config, &refs, result, args).is_some();
+        let note = if replaced {
+            "returned output; later stages skipped"
+        } else {
+            "returned nothing; the pipeline continues"
+        };
+        t.step("lua_script", vec![note.to_string()]);
+        if replaced {
+            return;
+        }
+    }
+    if config.parse.is_some() {
+        t.step(
+            "parse",
+            vec!["[parse] and [output] render the result; later stages skipped".to_string()],
+        );
+        return;
+    }
+
+    let sections = section::collect_sections_from(&config.section, result);
+    trace_sections(t, config, &sections);
+    let Some(branch) = super::select_branch(config, result.exit_code, args) else {
+        t.step(
+            "branch",
+            vec![format!(
+                "exit code {} → no {} branch; fallback applies",
+                result.exit_code,
+                branch_name(config, result, args)
+            )],
+        );
+        return;
+    };
+    let input = match branch.source {
+        Some(s) if s != source => super::prefilter_lines(config, result.stream(s)).join("\n"),
+        _ => refs.join("\n"),
+    };
+    let ctx = super::BranchCtx {
+        branch,
+        raw: result.stream(branch.source.unwrap_or(source)),
+        input: &input,
+        sections: &sections,
+        section_defs: &config.section,
+        result,
+        args,
+    };
+    trace_branch(t, config, &ctx);
+}
+
+/// Stages 1.5–2.7, as in `prefilter_lines`, noting each one that is set.
+fn trace_prefilter(
+    t: &mut Trace,
+    config: &FilterConfig,
+    text: &str,
+    source: StreamSource,
+) -> Vec<String> {
+    let mut lines: Vec<String> = text.lines().map(ToString::to_string).collect();
+    t.step(
+        "input",
+        vec![format!(
+            "{} lines from {}",
+            lines.len(),
+            stream_name(source)
+        )],
+    );
+
+    if !config.replace.is_empty() {
+        let after = replace::apply_replace(&config.replace, &as_refs(&lines));
+        let changed = lines.iter().zip(&after).filter(|(a, b)| a != b).count();
+        let rules = config.replace.len();
+        t.step(
+            "replace",
+            vec![format!("{rules} rules rewrote {changed} lines")],
+        );
+        lines = after;
+    }
+    if config.strip_ansi || config.trim_lines {
+        let after = cleanup::apply_line_cleanup(config, &as_refs(&lines));
+        let changed = lines.iter().zip(&after).filter(|(a, b)| a != b).count();
+        t.step(
+            "strip_ansi / trim_lines",
+            vec![format!("{changed} lines changed")],
+        );
+        lines = after;
+    }
+    if config.strip_spinners {
+        let after = spinner::collapse_spinners(&as_refs(&lines));
+        t.step(
+            "strip_spinners",
+            vec![count_change(lines.len(), after.len())],
+        );
+        lines = after;
+    }
+
+    let refs = as_refs(&lines);
+    let kept = trace_patterns(t, "skip", &config.skip, &refs, true);
+    let kept = trace_patterns(t, "keep", &config.keep, &kept, false);
+    let fuzzy: Vec<String>;
+    let kept = if config.dedup_fuzzy {
+        fuzzy = dedup::apply_fuzzy(&kept, super::dedup_threshold(config), config.dedup_window);
+        t.step("dedup_fuzzy", vec![count_change(kept.len(), fuzzy.len())]);
+        as_refs(&fuzzy)
+    } else if config.dedup {
+        let after = dedup::apply_dedup(&kept, config.dedup_window);
+        t.step("dedup", vec![count_change(kept.len(), after.len())]);
+        after
+    } else {
+        kept
+    };
+    trace_records(t, config, kept)
+}
+
+/// Stages 2.6–2.7: `json` records, then `stacktrace`.
+fn trace_records(t: &mut Trace, config: &FilterConfig, kept: Vec<&str>) -> Vec<String> {
+    let lines = match config.json {
+        Some(ref json_cfg) => {
+            let after = json::apply_json(json_cfg, &kept);
+            t.step("json", vec![count_change(kept.len(), after.len())]);
+            after
+        }
+        None => kept.into_iter().map(ToString::to_string).collect(),
+    };
+    match config.stacktrace {
+        Some(ref st) => {
+            let after =
+                stacktrace::condense(&as_refs(&lines), &stacktrace::Options::from_config(st));
+            t.step("stacktrace", vec![count_change(lines.len(), after.len())]);
+            after
+        }
+        None => lines,
+    }
+}
+
+/// Note what each `skip` (`drop`) or `keep` pattern did and return the
+/// surviving lines. Each line is credited to the first pattern it matches.
+fn trace_patterns<'a>(
+    t: &mut Trace,
+    stage: &str,
+    patterns: &[String],
+    lines: &[&'a str],
+    drop: bool,
+) -> Vec<&'a str> {
+    if patterns.is_empty() {
+        return lines.to_vec();
+    }
+    let (verb, mark) = if drop {
+        ("removed", '-')
+    } else {
+        ("kept", '+')
+    };
+    let mut notes = Vec::new();
+    let mut unclaimed = lines.to_vec();
+    for pattern in patterns {
+        let Ok(re) = Regex::new(pattern) else {
+            notes.push(format!("{pattern:?}: invalid regex, ignored"));
+            continue;
+        };
+        let (hits, rest): (Vec<&str>, Vec<&str>) = unclaimed.iter().partition(|l| re.is_match(l));
+        notes.push(format!("{pattern:?}: {verb} {} lines", hits.len()));
+        notes.extend(
+            hits.iter()
+                .take(SAMPLES)
+                .map(|l| format!("  {mark} {}", shorten(l))),
+        );
+        unclaimed = rest;
+    }
+    let after = if drop {
+        skip::apply_skip(patterns, lines)
+    } else {
+        skip::apply_keep(patterns, lines)
+    };
+    notes.push(count_change(lines.len(), after.len()));
+    t.step(stage, notes);
+    after
+}
+
+fn trace_sections(t: &mut Trace, config: &FilterConfig, sections: &SectionMap) {
+    if config.section.is_empty() {
+        return;
+    }
+    let notes = config
+        .section
+        .iter()
+        .map(|def| {
+            let label = def
+                .name
+                .as_deref()
+                .or(def.collect_as.as_deref())
+                .unwrap_or("section");
+            def.collect_as
+                .as_ref()
+                .and_then(|k| sections.get(k))
+                .map_or_else(
+                    || format!("{label}: not collected (no collect_as, or an invalid regex)"),
+                    |data| {
+                        format!(
+                            "{label} → {{{}}}: {} lines, {} blocks (count {})",
+                            def.collect_as.as_deref().unwrap_or_default(),
+                            data.lines.len(),
+                            data.blocks.len(),
+                            data.count()
+                        )
+                    },
+                )
+        })
+        .collect();
+    t.step("sections", notes);
+}
+
+fn trace_branch(t: &mut Trace, config: &FilterConfig, ctx: &super::BranchCtx) {
+    let branch = ctx.branch;
+    let mut notes = vec![format!(
+        "exit code {} → {}",
+        ctx.result.exit_code,
+        branch_name(config, ctx.result, ctx.args)
+    )];
+    if let Some(s) = branch.source
+        && s != config.source.unwrap_or_default()
+    {
+        notes.push(format!("reads {} (pre-filtered again)", stream_name(s)));
+    }
+    t.step("branch", notes);
+
+    let markdown = branch.render == Some(RenderMode::Markdown) && !ctx.section_defs.is_empty();
+    if branch.output.is_some() || markdown {
+        trace_template(t, ctx);
+    } else {
+        trace_branch_lines(t, branch, ctx.input);
+    }
+}
+
+/// How the branch for this run is selected, for the `branch` step.
+fn branch_name(config: &FilterConfig, result: &CommandResult, args: &[String]) -> String {
+    match super::branch::find_arg_branch(config, result.exit_code, args) {
+        Some((i, b)) => format!("[[branch]] #{} (when_args {:?})", i + 1, b.when_args),
+        None if result.exit_code == 0 => "[on_success]".to_string(),
+        None => "[on_failure]".to_string(),
+    }
+}
+
+fn trace_template(t: &mut Trace, ctx: &super::BranchCtx) {
+    let sections = ctx.sections;
+    let Some(vars) = super::template_vars(ctx) else {
+        t.step(
+            "template",
+            vec!["sections collected nothing; fallback applies".to_string()],
+        );
+        return;
+    };
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort();
+    let mut notes: Vec<String> = names
+        .into_iter()
+        .map(|name| format!("{{{name}}} = {}", shorten(&vars[name])))
+        .collect();
+    let mut section_names: Vec<&String> = sections.keys().collect();
+    section_names.sort();
+    notes.extend(
+        section_names
+            .into_iter()
+            .map(|name| format!("{{{name}}} = {} items", sections[name].count())),
+    );
+    t.step("template", notes);
+}
+
+/// The non-template branch path of `apply_branch`.
+fn trace_branch_lines(t: &mut Trace, branch: &OutputBranch, input: &str) {
+    let mut lines: Vec<&str> = input.lines().collect();
+    let mut notes = Vec::new();
+    let tail = branch
+        .tail_adaptive
+        .as_ref()
+        .map_or(branch.tail, |rule| Some(tail::adaptive_len(rule, &lines)));
+    if let Some(tail) = tail
+        && lines.len() > tail
+    {
+        notes.push(format!("tail {tail}: {}", count_change(lines.len(), tail)));
+        lines = lines.split_off(lines.len() - tail);
+    }
+    if let Some(head) = branch.head
+        && lines.len() > head
+    {
+        notes.push(format!("head {head}: {}", count_change(lines.len(), head)));
+        lines.truncate(head);
+    }
+    if !notes.is_empty() {
+        t.step("tail / head", notes);
+    }
+    let lines = trace_patterns(t, "branch skip", &branch.skip, &lines, true);
+    let lines = trace_patterns(t, "branch keep", &branch.keep, &lines, false);
+    if let Some(ref rule) = branch.extract {
+        let hit = Regex::new(&rule.pattern).is_ok_and(|re| lines.iter().any(|l| re.is_match(l)));
+        let note = if hit { "matched" } else { "no line matched" };
+        t.step("extract", vec![format!("{:?}: {note}", rule.pattern)]);
+    }
+}
+
+fn as_refs(lines: &[String]) -> Vec<&str> {
+    lines.iter().map(String::as_str).collect()
+}
+
+fn count_change(before: usize, after: usize) -> String {
+    format!("{before} \u{2192} {after} lines")
+}
+
+const fn stream_name(source: StreamSource) -> &'static str {
+    match source {
+        StreamSource::Combined => "combined output",
+        StreamSource::Stdout => "stdout",
+        StreamSource::Stderr => "stderr",
+    }
+}
+
+/// First line of `value`, quoted and cut to [`WIDTH`] characters.
+fn shorten(value: &str) -> String {
+    let first = value.lines().next().unwrap_or_default();
+    let mut out: String = first.chars().take(WIDTH).collect();
+    if first.chars().count() > WIDTH {
+        out.push('\u{2026}');
+    }
+    let extra = value.lines().count().saturating_sub(1);
+    if extra > 0 {
+        format!("{out:?} (+{extra} lines)")
+    } else {
+        format!("{out:?}")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn stages(config: &str, output: &str, exit_code: i32) -> Vec<TraceStep> {
+        let config: FilterConfig = toml::from_str(config).unwrap();
+        trace(&config, &CommandResult::from_output(output, exit_code), &[])
+    }
+
+    fn step<'a>(steps: &'a [TraceStep], stage: &str) -> &'a [String] {
+        &steps.iter().find(|s| s.stage == stage).unwrap().notes
+    }
+
+    #[test]
+    fn skip_credits_each_line_to_first_pattern() {
+        let steps = stages(
+            "command = \"x\"\nskip = [\"^a\", \"^ab\", \"[\"]\n",
+            "ab\nac\nb",
+            0,
+        );
+        let skip = step(&steps, "skip");
+        assert_eq!(skip[0], "\"^a\": removed 2 lines");
+        assert_eq!(skip[1], "  - \"ab\"");
+        assert_eq!(skip[3], "\"^ab\": removed 0 lines");
+        assert_eq!(skip[4], "\"[\": invalid regex, ignored");
+        assert_eq!(skip[5], "3 \u{2192} 1 lines");
+    }
+
+    #[test]
+    fn match_output_stops_at_first_hit() {
+        let steps = stages(
+            "command = \"x\"\n[[match_output]]\ncontains = \"zzz\"\noutput = \"z\"\n\
+             [[match_output]]\ncontains = \"up-to-date\"\noutput = \"ok\"\n",
+            "Everything up-to-date",
+            0,
+        );
+        let names: Vec<&str> = steps.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(names, vec!["match_output", "output"]);
+        assert_eq!(step(&steps, "match_output").len(), 2);
+    }
+
+    #[test]
+    fn branch_and_template_vars_are_reported() {
+        let steps = stages(
+            "command = \"x\"\n[on_failure]\noutput = \"{output}\"\n",
+            "boom",
+            2,
+        );
+        assert_eq!(step(&steps, "branch"), ["exit code 2 → [on_failure]"]);
+        assert!(step(&steps, "template").contains(&"{output} = \"boom\"".to_string()));
+        assert_eq!(
+            step(&steps, "output")[0],
+            "1 lines, 4 bytes (input: 4 bytes)"
+        );
+    }
+
+    #[test]
+    fn missing_branch_falls_back() {
+        let steps = stages(
+            "command = \"x\"\n[on_success]\noutput = \"ok\"\n",
+            "boom",
+            1,
+        );
+        assert_eq!(
+            step(&steps, "branch"),
+            ["exit code 1 → no [on_failure] branch; fallback applies"]
+        );
+    }
+}
@@ -1,31 +1,37 @@
 use regex::Regex;
 
 use crate::config::types::ExtractRule;
+use crate::regex_cache;
 
 /// Apply an extract rule across lines — first match wins.
 ///
 /// Returns the interpolated template on match. On invalid regex or no match,
 /// returns all lines joined with newlines (passthrough).
 pub fn apply_extract(rule: &ExtractRule, lines: &[&str]) -> String {
-    let Ok(re) = Regex::new(&rule.pattern) else {
+    let Some(re) = regex_cache::compile(&rule.pattern) else {
         return lines.join("\n");
     };
 
     for line in lines {
         if let Some(caps) = re.captures(line) {
-            return interpolate(&rule.output, &caps);
+            return interpolate(&rule.output, &re, &caps);
         }
     }
 
     lines.join("\n")
 }
 
-/// Replace `{0}`, `{1}`, `{2}`, ... placeholders with capture groups.
+/// Replace `{name}` and `{0}`, `{1}`, `{2}`, ... placeholders with capture groups.
 ///
-/// Iterates in reverse order so `{10}` is replaced before `{1}`.
-/// Missing groups become empty strings.
-pub(super) fn interpolate(template: &str, caps: &regex::Captures<'_>) -> String {
+/// Named groups (`(?P<name>…)` or `(?<name>…)`) of `re` are substituted first;
+/// they also keep their number. Numbers go in reverse order so `{10}` is
+/// replaced before `{1}`. Groups that did not participate become empty strings.
+pub(super) fn interpolate(template: &str, re: &Regex, caps: &regex::Captures<'_>) -> String {
     let mut result = template.to_string();
+    for name in re.capture_names().flatten() {
+        let value = caps.name(name).map_or("", |m| m.as_str());
+        result = result.replace(&format!("{{{name}}}"), value);
+    }
     let max_group = caps.len().saturating_sub(1);
 
     for i in (0..=max_group).rev() {
@@ -85,21 +91,24 @@ mod tests {
     fn interpolate_replaces_numbered_groups() {
         let re = Regex::new(r"^\[(\S+)\s+(\w+)\]").unwrap();
         let caps = re.captures("[main abc1234] Add feature X").unwrap();
-        assert_eq!(interpolate("ok \u{2713} {2}", &caps), "ok \u{2713} abc1234");
+        assert_eq!(
+            interpolate("ok \u{2713} {2}", &re, &caps),
+            "ok \u{2713} abc1234"
+        );
     }
 
     #[test]
     fn interpolate_group_zero_is_full_match() {
         let re = Regex::new(r"(hello) (world)").unwrap();
         let caps = re.captures("hello world").unwrap();
-        assert_eq!(interpolate("{0}", &caps), "hello world");
+        assert_eq!(interpolate("{0}", &re, &caps), "hello world");
     }
 
     #[test]
     fn interpolate_missing_group_becomes_empty() {
         let re = Regex::new(r"(a)(b)?").unwrap();
         let caps = re.captures("a").unwrap();
-        assert_eq!(interpolate("{1}-{2}", &caps), "a-");
+        assert_eq!(interpolate("{1}-{2}", &re, &caps), "a-");
     }
 
     #[test]
@@ -107,7 +116,24 @@ mod tests {
         // Ensure {10} doesn't get mangled by {1} replacement first
         let re = Regex::new(r"(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)(k)").unwrap();
         let caps = re.captures("abcdefghijk").unwrap();
-        assert_eq!(interpolate("{10}", &caps), "j");
+        assert_eq!(interpolate("{10}", &re, &caps), "j");
+    }
+
+    #[test]
+    fn interpolate_named_groups() {
+        let re = Regex::new(r"(?P<branch>\S+) -> (?<remote>\S+)").unwrap();
+        let caps = re.captures("main -> origin/main").unwrap();
+        assert_eq!(
+            interpolate("{remote} from {branch} ({1})", &re, &caps),
+            "origin/main from main (main)"
+        );
+    }
+
+    #[test]
+    fn interpolate_unmatched_named_group_becomes_empty() {
+        let re = Regex::new(r"(?P<a>a)(?P<b>b)?").unwrap();
+        let caps = re.captures("a").unwrap();
+        assert_eq!(interpolate("{a}-{b}-{other}", &re, &caps), "a--{other}");
     }
 
     #[test]
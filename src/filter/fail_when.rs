@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::config::types::FilterConfig;
+use crate::runner::CommandResult;
+
+use super::section;
+use super::{aggregate, select_branch, template};
+
+/// Comparison operators, longest first so `<=` is not read as `<`.
+const OPERATORS: &[&str] = &["==", "!=", "<=", ">=", "<", ">"];
+
+/// Evaluate the filter's `fail_when` condition against the command result.
+///
+/// The condition is rendered as a template first — sections, the selected
+/// branch's aggregate vars, and `{exit_code}` are all available — and the
+/// rendered text is then compared. Returns `false` when no condition is set.
+pub fn evaluate(config: &FilterConfig, result: &CommandResult, args: &[String]) -> bool {
+    let Some(ref condition) = config.fail_when else {
+        return false;
+    };
+
+    let sections = section::collect_sections_from(&config.section, result);
+
+    let mut vars = select_branch(config, result.exit_code, args)
+        .and_then(|b| b.aggregate.as_ref())
+        .map_or_else(HashMap::new, |aggregates| {
+            aggregate::run_aggregates(aggregates, &sections)
+        });
+    vars.insert("exit_code".to_string(), result.exit_code.to_string());
+
+    let rendered = template::render_template(condition, &vars, &sections);
+    is_true(&rendered)
+}
+
+/// Evaluate a rendered condition like `0 == 0` or `3 > 1`.
+///
+/// Both sides are compared numerically when they parse as numbers, otherwise
+/// as trimmed strings (only `==` / `!=` are meaningful then). A condition with
+/// no operator is true when it is non-empty and not `0` or `false`.
+fn is_true(rendered: &str) -> bool {
+    for op in OPERATORS {
+        if let Some((lhs, rhs)) = rendered.split_once(op) {
+            return compare(lhs.trim(), op, rhs.trim());
+        }
+    }
+    let value = rendered.trim();
+    !(value.is_empty() || value == "0" || value == "false")
+}
+
+fn compare(lhs: &str, op: &str, rhs: &str) -> bool {
+    if let (Ok(l), Ok(r)) = (lhs.parse::<f64>(), rhs.parse::<f64>()) {
+        return match op {
+            "==" => (l - r).abs() < f64::EPSILON,
+            "!=" => (l - r).abs() >= f64::EPSILON,
+            "<=" => l <= r,
+            ">=" => l >= r,
+            "<" => l < r,
+            ">" => l > r,
+            _ => false,
+        };
+    }
+    match op {
+        "==" => lhs == rhs,
+        "!=" => lhs != rhs,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> FilterConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn result(output: &str, exit_code: i32) -> CommandResult {
+        CommandResult {
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code,
+            combined: output.to_string(),
+            timed_out: None,
+            steps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        assert!(is_true("0 == 0"));
+        assert!(!is_true("1 == 0"));
+        assert!(is_true("3 != 0"));
+        assert!(is_true("2 < 3"));
+        assert!(is_true("3 <= 3"));
+        assert!(is_true("4 > 3"));
+        assert!(is_true("3 >= 3"));
+        assert!(!is_true("3 > 3"));
+    }
+
+    #[test]
+    fn string_comparisons() {
+        assert!(is_true("ok == ok"));
+        assert!(is_true("ok != fail"));
+        assert!(!is_true("abc < abd"));
+    }
+
+    #[test]
+    fn bare_values_are_truthy() {
+        assert!(is_true("yes"));
+        assert!(!is_true(""));
+        assert!(!is_true("0"));
+        assert!(!is_true(" false "));
+    }
+
+    #[test]
+    fn no_condition_never_fails() {
+        let cfg = config(r#"command = "t""#);
+        assert!(!evaluate(&cfg, &result("anything", 0), &[]));
+    }
+
+    #[test]
+    fn section_count_condition() {
+        let cfg = config(
+            r#"
+command = "t"
+fail_when = "{tests.count} == 0"
+
+[[section]]
+match = "^test "
+collect_as = "tests"
+"#,
+        );
+        assert!(evaluate(&cfg, &result("running 0 tests\ndone", 0), &[]));
+        assert!(!evaluate(
+            &cfg,
+            &result("test a ... ok\ntest b ... ok", 0),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn aggregate_vars_available() {
+        let cfg = config(
+            r#"
+command = "t"
+fail_when = "{passed} < 1"
+
+[[section]]
+match = "passed"
+collect_as = "summary"
+
+[on_success]
+aggregate = { from = "summary", pattern = '(\d+) passed', sum = "passed" }
+output = "{passed} passed"
+"#,
+        );
+        assert!(evaluate(&cfg, &result("0 passed", 0), &[]));
+        assert!(!evaluate(&cfg, &result("5 passed", 0), &[]));
+    }
+
+    #[test]
+    fn exit_code_var_available() {
+        let cfg = config(
+            r#"
+command = "t"
+fail_when = "{exit_code} == 3"
+"#,
+        );
+        assert!(evaluate(&cfg, &result("", 3), &[]));
+        assert!(!evaluate(&cfg, &result("", 0), &[]));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::types::{FallbackConfig, FilterConfig};
+
+use super::skip;
+
+/// Fallback when no branch matches or sections collected nothing.
+///
+/// The first strategy whose `when_contains` matches (or that has no
+/// condition) is applied. Without one, the output passes through unchanged.
+pub fn apply_fallback(config: &FilterConfig, combined: &str) -> String {
+    let strategy = config.fallback.as_ref().and_then(|fb| {
+        fb.strategies().iter().find(|s| {
+            s.when_contains.is_empty() || s.when_contains.iter().any(|w| combined.contains(w))
+        })
+    });
+    strategy.map_or_else(|| combined.to_string(), |s| apply_strategy(s, combined))
+}
+
+fn apply_strategy(strategy: &FallbackConfig, combined: &str) -> String {
+    let lines: Vec<&str> = combined.lines().collect();
+    let mut lines = skip::apply_skip(&strategy.skip, &lines);
+
+    if let Some(tail) = strategy.tail
+        && lines.len() > tail
+    {
+        lines = lines.split_off(lines.len() - tail);
+    }
+    if let Some(head) = strategy.head {
+        lines.truncate(head);
+    }
+
+    let out = lines.join("\n");
+    match strategy.max_bytes {
+        // A tail-only strategy cares about the end of the output.
+        Some(max) if strategy.tail.is_some() && strategy.head.is_none() => last_bytes(&out, max),
+        Some(max) => first_bytes(&out, max),
+        None => out,
+    }
+}
+
+/// At most `max` bytes from the start, ending on a full line when one fits.
+fn first_bytes(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = &text[..end];
+    cut.rfind('\n').map_or(cut, |i| &cut[..i]).to_string()
+}
+
+/// At most `max` bytes from the end, starting on a full line when one fits.
+fn last_bytes(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let cut = &text[start..];
+    cut.find('\n').map_or(cut, |i| &cut[i + 1..]).to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn config(toml_src: &str) -> FilterConfig {
+        toml::from_str(&format!("command = \"t\"\n{toml_src}")).unwrap()
+    }
+
+    const OUTPUT: &str = "compiling a\ncompiling b\nerror one\nerror two\ndone";
+
+    #[test]
+    fn single_table_still_tails() {
+        let cfg = config("[fallback]\ntail = 2");
+        assert_eq!(apply_fallback(&cfg, OUTPUT), "error two\ndone");
+    }
+
+    #[test]
+    fn no_fallback_passes_through() {
+        assert_eq!(apply_fallback(&config(""), OUTPUT), OUTPUT);
+    }
+
+    #[test]
+    fn skip_then_head() {
+        let cfg = config("[fallback]\nskip = [\"^compiling\"]\nhead = 1");
+        assert_eq!(apply_fallback(&cfg, OUTPUT), "error one");
+    }
+
+    #[test]
+    fn first_matching_strategy_wins() {
+        let cfg = config(
+            r#"
+[[fallback]]
+when_contains = ["panicked at"]
+head = 1
+
+[[fallback]]
+tail = 1
+"#,
+        );
+        assert_eq!(
+            apply_fallback(&cfg, "panicked at x\nnote\nend"),
+            "panicked at x"
+        );
+        assert_eq!(apply_fallback(&cfg, OUTPUT), "done");
+    }
+
+    #[test]
+    fn unmatched_conditions_pass_through() {
+        let cfg = config("[[fallback]]\nwhen_contains = [\"panicked\"]\ntail = 1");
+        assert_eq!(apply_fallback(&cfg, OUTPUT), OUTPUT);
+    }
+
+    #[test]
+    fn max_bytes_cuts_on_line_boundaries() {
+        let cfg = config("[fallback]\nmax_bytes = 25");
+        assert_eq!(apply_fallback(&cfg, OUTPUT), "compiling a\ncompiling b");
+
+        let cfg = config("[fallback]\ntail = 5\nmax_bytes = 15");
+        assert_eq!(apply_fallback(&cfg, OUTPUT), "error two\ndone");
+    }
+
+    #[test]
+    fn max_bytes_respects_char_boundaries() {
+        assert_eq!(first_bytes("ééé", 3), "é");
+        assert_eq!(last_bytes("ééé", 3), "é");
+    }
+}
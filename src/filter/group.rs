@@ -1,8 +1,7 @@
 use std::collections::HashMap;
 
-use regex::Regex;
-
 use crate::config::types::GroupConfig;
+use crate::regex_cache;
 
 use super::extract::interpolate;
 
@@ -17,7 +16,7 @@ pub struct GroupCount {
 /// map keys to labels (raw key as fallback), count per label,
 /// and return results sorted alphabetically by label.
 pub fn collect_groups(config: &GroupConfig, lines: &[&str]) -> Vec<GroupCount> {
-    let Ok(re) = Regex::new(&config.key.pattern) else {
+    let Some(re) = regex_cache::compile(&config.key.pattern) else {
         return Vec::new();
     };
 
@@ -25,7 +24,7 @@ pub fn collect_groups(config: &GroupConfig, lines: &[&str]) -> Vec<GroupCount> {
 
     for line in lines {
         if let Some(caps) = re.captures(line) {
-            let raw_key = interpolate(&config.key.output, &caps);
+            let raw_key = interpolate(&config.key.output, &re, &caps);
             let label = config
                 .labels
                 .get(&raw_key)
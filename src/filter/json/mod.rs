@@ -0,0 +1,298 @@
+mod select;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::config::types::JsonConfig;
+
+use super::section::SectionMap;
+use super::template;
+
+use select::Selector;
+
+/// Parse each line as a JSON record, keep records matching every `where`
+/// predicate, and render each one through the `output` template.
+///
+/// Lines that are not valid JSON are dropped unless `keep_non_json` is set,
+/// in which case they pass through unchanged. With `select`, the whole
+/// input is one JSON document instead (see [`apply_select`]).
+pub fn apply_json(config: &JsonConfig, lines: &[&str]) -> Vec<String> {
+    if let Some(ref expr) = config.select {
+        return apply_select(config, expr, lines);
+    }
+    let mut out = Vec::new();
+    for &line in lines {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            if config.keep_non_json {
+                out.push(line.to_string());
+            }
+            continue;
+        };
+        if let Some(rendered) = render_record(config, &record, line, false) {
+            out.push(rendered);
+        }
+    }
+    out
+}
+
+/// Document mode: parse the input as one JSON value and turn every value
+/// `select` yields into a record.
+///
+/// Text before the first line starting with `{` or `[` is ignored (npm and
+/// friends print a banner first). Input that is not JSON is handled like a
+/// non-JSON line; an invalid `select` passes the input through with a warning.
+fn apply_select(config: &JsonConfig, expr: &str, lines: &[&str]) -> Vec<String> {
+    let passthrough = || lines.iter().map(ToString::to_string).collect();
+    let selector = match Selector::parse(expr) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("invalid json select: {e:#}");
+            return passthrough();
+        }
+    };
+    let start = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with(['{', '[']))
+        .unwrap_or(0);
+    let text = lines[start..].join("\n");
+    let Some(Ok(doc)) = serde_json::Deserializer::from_str(&text)
+        .into_iter::<Value>()
+        .next()
+    else {
+        return if config.keep_non_json {
+            passthrough()
+        } else {
+            Vec::new()
+        };
+    };
+    selector
+        .apply(&doc)
+        .iter()
+        .filter_map(|record| render_record(config, record, &record.to_string(), true))
+        .collect()
+}
+
+/// Apply `where`, then render `output` (or return `raw`). With `object_vars`,
+/// the top-level keys of an object record are template variables too;
+/// `fields` take precedence.
+fn render_record(
+    config: &JsonConfig,
+    record: &Value,
+    raw: &str,
+    object_vars: bool,
+) -> Option<String> {
+    if !config.predicates.iter().all(|p| predicate_holds(p, record)) {
+        return None;
+    }
+    let Some(ref tmpl) = config.output else {
+        return Some(raw.to_string());
+    };
+    let mut vars: HashMap<String, String> = HashMap::new();
+    if object_vars && let Value::Object(map) = record {
+        vars.extend(map.iter().map(|(k, v)| (k.clone(), field_string(v))));
+    }
+    vars.extend(config.fields.iter().map(|(name, path)| {
+        let value = lookup(record, path).map_or_else(String::new, field_string);
+        (name.clone(), value)
+    }));
+    // A user field named `line` wins over the raw-record variable.
+    vars.entry("line".to_string())
+        .or_insert_with(|| raw.to_string());
+    Some(template::render_template(tmpl, &vars, &SectionMap::new()))
+}
+
+/// Resolve a dotted path like `message.spans.0.file_name`.
+///
+/// Numeric segments index into arrays; everything else is an object key.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => current.get(segment),
+        })
+}
+
+/// String form used in templates: strings unquoted, `null` empty,
+/// everything else as compact JSON.
+fn field_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Evaluate `path == value`, `path != value`, or a bare `path` (truthy test).
+///
+/// The right-hand side may be quoted (`"error"`, `'error'`) or a bare literal
+/// (`3`, `true`, `null`); it is compared against the field's JSON text with
+/// string quotes removed. A missing field never equals anything.
+fn predicate_holds(predicate: &str, record: &Value) -> bool {
+    let (path, op, expected) = if let Some((lhs, rhs)) = predicate.split_once("!=") {
+        (lhs, "!=", Some(rhs))
+    } else if let Some((lhs, rhs)) = predicate.split_once("==") {
+        (lhs, "==", Some(rhs))
+    } else {
+        (predicate, "", None)
+    };
+    let found = lookup(record, path.trim());
+
+    let Some(expected) = expected else {
+        return found.is_some_and(|v| !matches!(v, Value::Null | Value::Bool(false)));
+    };
+    let expected = unquote(expected.trim());
+    let equal = found.is_some_and(|v| match v {
+        Value::Null => expected == "null",
+        other => field_string(other) == expected,
+    });
+    if op == "==" { equal } else { !equal }
+}
+
+fn unquote(s: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|r| r.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> JsonConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn record(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn lookup_nested_and_array_paths() {
+        let v = record(r#"{"a":{"b":[{"c":1},{"c":2}]}}"#);
+        assert_eq!(lookup(&v, "a.b.1.c"), Some(&Value::from(2)));
+        assert_eq!(lookup(&v, "a.x"), None);
+        assert_eq!(lookup(&v, "a.b.9"), None);
+    }
+
+    #[test]
+    fn predicates_compare_strings_and_literals() {
+        let v = record(r#"{"level":"error","n":3,"ok":true,"gone":null}"#);
+        assert!(predicate_holds(r#"level == "error""#, &v));
+        assert!(predicate_holds("level == 'error'", &v));
+        assert!(predicate_holds("level != warning", &v));
+        assert!(predicate_holds("n == 3", &v));
+        assert!(predicate_holds("ok == true", &v));
+        assert!(predicate_holds("gone == null", &v));
+        assert!(!predicate_holds("missing == x", &v));
+        assert!(predicate_holds("missing != x", &v));
+    }
+
+    #[test]
+    fn bare_predicate_is_truthy_test() {
+        let v = record(r#"{"ok":true,"no":false,"gone":null,"s":""}"#);
+        assert!(predicate_holds("ok", &v));
+        assert!(predicate_holds("s", &v));
+        assert!(!predicate_holds("no", &v));
+        assert!(!predicate_holds("gone", &v));
+        assert!(!predicate_holds("missing", &v));
+    }
+
+    #[test]
+    fn renders_selected_records() {
+        let cfg = config(
+            r#"
+where = ['reason == "compiler-message"', 'message.level == "error"']
+fields = { level = "message.level", text = "message.message", file = "message.spans.0.file_name" }
+output = "{level}: {text} ({file})"
+"#,
+        );
+        let lines = [
+            r#"{"reason":"compiler-artifact","target":{}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"warning","message":"unused"}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"boom","spans":[{"file_name":"src/a.rs"}]}}"#,
+        ];
+        assert_eq!(apply_json(&cfg, &lines), vec!["error: boom (src/a.rs)"]);
+    }
+
+    #[test]
+    fn non_json_lines_dropped_by_default() {
+        let cfg = config("");
+        let lines = ["Compiling foo", r#"{"a":1}"#];
+        assert_eq!(apply_json(&cfg, &lines), vec![r#"{"a":1}"#]);
+    }
+
+    #[test]
+    fn non_json_lines_kept_when_configured() {
+        let cfg = config(
+            r#"
+keep_non_json = true
+output = "{line}"
+"#,
+        );
+        let lines = ["Compiling foo", r#"{"a":1}"#];
+        assert_eq!(
+            apply_json(&cfg, &lines),
+            vec!["Compiling foo", r#"{"a":1}"#]
+        );
+    }
+
+    #[test]
+    fn select_reads_one_document_after_a_banner() {
+        let cfg = config(
+            r#"
+select = ".results[] | {name, status}"
+where = ['status != "passed"']
+output = "{status}: {name}"
+"#,
+        );
+        let lines = [
+            "> app@1.0.0 test",
+            "{",
+            r#"  "results": [{"name": "a", "status": "passed"},"#,
+            r#"              {"name": "b", "status": "failed"}]"#,
+            "}",
+        ];
+        assert_eq!(apply_json(&cfg, &lines), vec!["failed: b"]);
+    }
+
+    #[test]
+    fn select_without_output_emits_compact_json() {
+        let cfg = config(r#"select = ".items[].id""#);
+        let lines = [r#"{"items":[{"id":1},{"id":"x"}]}"#];
+        assert_eq!(apply_json(&cfg, &lines), vec!["1", r#""x""#]);
+    }
+
+    #[test]
+    fn select_on_non_json_follows_keep_non_json() {
+        let lines = ["error: not json"];
+        assert!(apply_json(&config(r#"select = ".""#), &lines).is_empty());
+        let keep = config("select = \".\"\nkeep_non_json = true");
+        assert_eq!(apply_json(&keep, &lines), vec!["error: not json"]);
+    }
+
+    #[test]
+    fn invalid_select_passes_input_through() {
+        let lines = [r#"{"a":1}"#];
+        assert_eq!(
+            apply_json(&config(r#"select = "a[""#), &lines),
+            vec![r#"{"a":1}"#]
+        );
+    }
+
+    #[test]
+    fn missing_and_null_fields_render_empty() {
+        let cfg = config(
+            r#"
+fields = { a = "a", b = "b", obj = "o" }
+output = "[{a}][{b}][{obj}]"
+"#,
+        );
+        let lines = [r#"{"a":null,"o":{"k":1}}"#];
+        assert_eq!(apply_json(&cfg, &lines), vec![r#"[][][{"k":1}]"#]);
+    }
+}
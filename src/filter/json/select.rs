@@ -0,0 +1,201 @@
+use anyhow::bail;
+use serde_json::{Map, Value};
+
+/// A parsed `select` expression: a `|`-separated pipeline of stages, each
+/// mapping every input value to zero or more outputs (a small jq subset).
+///
+/// - `.` — the value itself
+/// - `.key`, `.key.nested`, `.[0]`, `.key[2]` — object keys and array indexes;
+///   missing ones give `null`
+/// - `.[]`, `.key[]` — every array item (or object value)
+/// - `{name, status, file: .location.path}` — build an object from keys of
+///   the input and paths evaluated against it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stage {
+    Path(Vec<Step>),
+    Object(Vec<(String, Vec<Step>)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Iterate,
+}
+
+impl Selector {
+    /// # Errors
+    /// Returns an error describing the first malformed stage.
+    pub fn parse(expr: &str) -> anyhow::Result<Self> {
+        let stages = expr
+            .split('|')
+            .map(|stage| {
+                let stage = stage.trim();
+                if let Some(body) = stage.strip_prefix('{') {
+                    let Some(body) = body.strip_suffix('}') else {
+                        bail!("unclosed object in \"{stage}\"");
+                    };
+                    parse_object(body).map(Stage::Object)
+                } else {
+                    parse_path(stage).map(Stage::Path)
+                }
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { stages })
+    }
+
+    /// Every value the pipeline produces for `input`, in order.
+    pub fn apply(&self, input: &Value) -> Vec<Value> {
+        self.stages
+            .iter()
+            .fold(vec![input.clone()], |values, stage| {
+                values.iter().flat_map(|v| stage.apply(v)).collect()
+            })
+    }
+}
+
+impl Stage {
+    fn apply(&self, input: &Value) -> Vec<Value> {
+        match self {
+            Self::Path(steps) => walk(input, steps),
+            Self::Object(entries) => {
+                let object: Map<String, Value> = entries
+                    .iter()
+                    .map(|(key, steps)| {
+                        let value = walk(input, steps).into_iter().next();
+                        (key.clone(), value.unwrap_or(Value::Null))
+                    })
+                    .collect();
+                vec![Value::Object(object)]
+            }
+        }
+    }
+}
+
+fn walk(input: &Value, steps: &[Step]) -> Vec<Value> {
+    steps.iter().fold(vec![input.clone()], |values, step| {
+        values
+            .into_iter()
+            .flat_map(|v| match step {
+                Step::Key(key) => vec![v.get(key).cloned().unwrap_or(Value::Null)],
+                Step::Index(i) => vec![v.get(i).cloned().unwrap_or(Value::Null)],
+                Step::Iterate => match v {
+                    Value::Array(items) => items,
+                    Value::Object(map) => map.into_iter().map(|(_, v)| v).collect(),
+                    _ => Vec::new(),
+                },
+            })
+            .collect()
+    })
+}
+
+/// `.`, `.a.b`, `.a[0]`, `.[]`, `.a[].b`.
+fn parse_path(path: &str) -> anyhow::Result<Vec<Step>> {
+    let Some(mut rest) = path.strip_prefix('.') else {
+        bail!("path must start with '.': \"{path}\"");
+    };
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let Some((inner, tail)) = after.split_once(']') else {
+                bail!("unclosed '[' in \"{path}\"");
+            };
+            steps.push(if inner.is_empty() {
+                Step::Iterate
+            } else {
+                let Ok(i) = inner.trim().parse() else {
+                    bail!("bad index \"{inner}\" in \"{path}\"");
+                };
+                Step::Index(i)
+            });
+            rest = tail.strip_prefix('.').unwrap_or(tail);
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if !is_key(key) {
+                bail!("bad key \"{key}\" in \"{path}\"");
+            }
+            steps.push(Step::Key(key.to_string()));
+            rest = &rest[end..];
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+        }
+    }
+    Ok(steps)
+}
+
+/// `name, status, file: .location.path`
+fn parse_object(body: &str) -> anyhow::Result<Vec<(String, Vec<Step>)>> {
+    body.split(',')
+        .map(|entry| {
+            let (key, steps) = match entry.split_once(':') {
+                Some((key, path)) => (key.trim(), parse_path(path.trim())?),
+                None => (entry.trim(), vec![Step::Key(entry.trim().to_string())]),
+            };
+            if !is_key(key) {
+                bail!("bad object key \"{key}\"");
+            }
+            Ok((key.to_string(), steps))
+        })
+        .collect()
+}
+
+fn is_key(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn select(expr: &str, json: &str) -> Vec<Value> {
+        let doc: Value = serde_json::from_str(json).unwrap();
+        Selector::parse(expr).unwrap().apply(&doc)
+    }
+
+    #[test]
+    fn identity_and_keys() {
+        assert_eq!(select(".", "1"), vec![Value::from(1)]);
+        assert_eq!(select(".a.b", r#"{"a":{"b":"x"}}"#), vec![Value::from("x")]);
+        assert_eq!(select(".missing", "{}"), vec![Value::Null]);
+    }
+
+    #[test]
+    fn indexes_and_iteration() {
+        let doc = r#"{"r":[{"n":"a"},{"n":"b"}]}"#;
+        assert_eq!(select(".r[1].n", doc), vec![Value::from("b")]);
+        assert_eq!(
+            select(".r[].n", doc),
+            vec![Value::from("a"), Value::from("b")]
+        );
+        assert_eq!(select(".[]", r#"{"x":1,"y":2}"#).len(), 2);
+        assert!(select(".r[].n[]", doc).is_empty());
+    }
+
+    #[test]
+    fn pipe_into_object_construction() {
+        let doc = r#"{"testResults":[{"name":"a","status":"passed","loc":{"line":3}},{"name":"b","status":"failed"}]}"#;
+        let out = select(".testResults[] | {name, status, line: .loc.line}", doc);
+        assert_eq!(
+            out,
+            vec![
+                serde_json::json!({"name":"a","status":"passed","line":3}),
+                serde_json::json!({"name":"b","status":"failed","line":null}),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for bad in ["a.b", ".a[", ".a[x]", "{name", ".a | {na me}", ".a..b"] {
+            assert!(Selector::parse(bad).is_err(), "{bad} should not parse");
+        }
+    }
+}
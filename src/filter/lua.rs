@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
 use anyhow::Context as _;
-use mlua::Lua;
+use mlua::{Lua, Variadic};
+use regex::Regex;
 
+use super::section::SectionMap;
 use crate::config::types::ScriptConfig;
+use crate::regex_cache;
 
 fn load_source(script_config: &ScriptConfig) -> anyhow::Result<String> {
     match (&script_config.file, &script_config.source) {
@@ -12,41 +17,43 @@ fn load_source(script_config: &ScriptConfig) -> anyhow::Result<String> {
     }
 }
 
+/// What a script sees besides its own source.
+pub struct ScriptInput<'a> {
+    /// Combined output after the top-level pre-filter stages.
+    pub output: &'a str,
+    pub exit_code: i32,
+    /// The arguments after the matched command words.
+    pub args: &'a [String],
+    pub sections: &'a SectionMap,
+    /// Aggregate, `[[step]]` and argument vars, as templates see them.
+    pub vars: &'a HashMap<String, String>,
+}
+
 /// Run a Luau filter script against pre-filtered command output.
 ///
 /// Globals available to the script:
 ///   output: string    — combined output (after top-level skip/keep)
 ///   `exit_code`: integer — command exit code
-///   args: table       — command arguments (1-indexed Lua array)
+///   args: table       — command arguments (1-indexed Lua array); also `remaining_args`
+///   sections: table   — `sections.NAME.lines`, `.blocks` and `.count`
+///   vars: table       — aggregate, step and argument vars (`vars.arg1`, `vars.passed`)
+///   regex.match(text, pattern)    — the captures (or the whole match) of the first match, else nil
+///   regex.gsub(text, pattern, with) — every match replaced (`$1`, `${name}`), and the count
+///   json.decode(text) — a JSON document as Lua values (`null` becomes nil);
+///                       Luau numbers are doubles, so integers past 2^53 are rounded
+///   lines(text?)      — iterator over the lines of `text` (default: `output`)
 ///
 /// Return value:
 ///   string → replace output; nil/no-return → passthrough (output unchanged)
 pub fn run_lua_script(
     script_config: &ScriptConfig,
-    output: &str,
-    exit_code: i32,
-    args: &[String],
+    input: &ScriptInput,
 ) -> anyhow::Result<Option<String>> {
     let source = load_source(script_config)?;
 
     let lua = Lua::new();
-
-    lua.globals()
-        .set("output", output)
-        .context("set output global")?;
-    lua.globals()
-        .set("exit_code", exit_code)
-        .context("set exit_code global")?;
-
-    let args_table = lua.create_table().context("create args table")?;
-    for (i, arg) in args.iter().enumerate() {
-        args_table
-            .set(i + 1, arg.as_str())
-            .with_context(|| format!("set args[{}]", i + 1))?;
-    }
-    lua.globals()
-        .set("args", args_table)
-        .context("set args global")?;
+    set_inputs(&lua, input).context("set script globals")?;
+    set_helpers(&lua, input.output).context("set helper globals")?;
 
     let value: mlua::Value = lua
         .load(source.as_str())
@@ -66,12 +73,155 @@ pub fn run_lua_script(
     }
 }
 
+/// `output`, `exit_code`, `args` / `remaining_args`, `sections` and `vars`.
+fn set_inputs(lua: &Lua, input: &ScriptInput) -> mlua::Result<()> {
+    let globals = lua.globals();
+    globals.set("output", input.output)?;
+    globals.set("exit_code", input.exit_code)?;
+    let args = lua.create_sequence_from(input.args.iter().map(String::as_str))?;
+    globals.set("args", args.clone())?;
+    globals.set("remaining_args", args)?;
+
+    let sections = lua.create_table()?;
+    for (name, data) in input.sections {
+        let section = lua.create_table()?;
+        section.set(
+            "lines",
+            lua.create_sequence_from(data.lines.iter().map(String::as_str))?,
+        )?;
+        section.set(
+            "blocks",
+            lua.create_sequence_from(data.blocks.iter().map(String::as_str))?,
+        )?;
+        section.set("count", data.count())?;
+        sections.set(name.as_str(), section)?;
+    }
+    globals.set("sections", sections)?;
+
+    let vars = lua.create_table_from(
+        input
+            .vars
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    )?;
+    globals.set("vars", vars)
+}
+
+/// `regex.match`, `regex.gsub`, `json.decode` and `lines`.
+fn set_helpers(lua: &Lua, output: &str) -> mlua::Result<()> {
+    let globals = lua.globals();
+
+    let regex = lua.create_table()?;
+    regex.set(
+        "match",
+        lua.create_function(|_, (text, pattern): (String, String)| {
+            let re = compile(&pattern)?;
+            let found: Variadic<Option<String>> = match re.captures(&text) {
+                None => std::iter::once(None).collect(),
+                Some(caps) if caps.len() == 1 => {
+                    std::iter::once(Some(caps[0].to_string())).collect()
+                }
+                Some(caps) => caps
+                    .iter()
+                    .skip(1)
+                    .map(|group| group.map(|m| m.as_str().to_string()))
+                    .collect(),
+            };
+            Ok(found)
+        })?,
+    )?;
+    regex.set(
+        "gsub",
+        lua.create_function(
+            |_, (text, pattern, replacement): (String, String, String)| {
+                let re = compile(&pattern)?;
+                let count = re.find_iter(&text).count();
+                Ok((
+                    re.replace_all(&text, replacement.as_str()).into_owned(),
+                    count,
+                ))
+            },
+        )?,
+    )?;
+    globals.set("regex", regex)?;
+
+    let json = lua.create_table()?;
+    json.set(
+        "decode",
+        lua.create_function(|lua, text: String| {
+            let value: serde_json::Value =
+                serde_json::from_str(&text).map_err(mlua::Error::external)?;
+            json_to_lua(lua, &value)
+        })?,
+    )?;
+    globals.set("json", json)?;
+
+    let output = output.to_string();
+    globals.set(
+        "lines",
+        lua.create_function(move |lua, text: Option<String>| {
+            let text = text.unwrap_or_else(|| output.clone());
+            let mut lines = text
+                .lines()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .into_iter();
+            lua.create_function_mut(move |_, ()| Ok(lines.next()))
+        })?,
+    )
+}
+
+fn compile(pattern: &str) -> mlua::Result<Regex> {
+    regex_cache::compile(pattern)
+        .ok_or_else(|| mlua::Error::runtime(format!("invalid regex: {pattern}")))
+}
+
+/// Objects and arrays become tables; every number is a Luau number.
+fn json_to_lua(lua: &Lua, value: &serde_json::Value) -> mlua::Result<mlua::Value> {
+    Ok(match value {
+        serde_json::Value::Null => mlua::Value::Nil,
+        serde_json::Value::Bool(b) => mlua::Value::Boolean(*b),
+        serde_json::Value::Number(n) => mlua::Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+        serde_json::Value::String(s) => mlua::Value::String(lua.create_string(s)?),
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table()?;
+            for (i, item) in items.iter().enumerate() {
+                table.set(i + 1, json_to_lua(lua, item)?)?;
+            }
+            mlua::Value::Table(table)
+        }
+        serde_json::Value::Object(fields) => {
+            let table = lua.create_table()?;
+            for (key, item) in fields {
+                table.set(key.as_str(), json_to_lua(lua, item)?)?;
+            }
+            mlua::Value::Table(table)
+        }
+    })
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
     use super::*;
     use crate::config::types::{ScriptConfig, ScriptLang};
 
+    fn run(
+        config: &ScriptConfig,
+        output: &str,
+        exit_code: i32,
+        args: &[String],
+    ) -> anyhow::Result<Option<String>> {
+        let input = ScriptInput {
+            output,
+            exit_code,
+            args,
+            sections: &SectionMap::new(),
+            vars: &HashMap::new(),
+        };
+        run_lua_script(config, &input)
+    }
+
     fn inline_script(source: &str) -> ScriptConfig {
         ScriptConfig {
             lang: ScriptLang::Luau,
@@ -83,28 +233,28 @@ mod tests {
     #[test]
     fn lua_returns_string_replaces_output() {
         let config = inline_script(r#"return "replaced""#);
-        let result = run_lua_script(&config, "original", 0, &[]).unwrap();
+        let result = run(&config, "original", 0, &[]).unwrap();
         assert_eq!(result, Some("replaced".to_string()));
     }
 
     #[test]
     fn lua_returns_nil_passthrough() {
         let config = inline_script("return nil");
-        let result = run_lua_script(&config, "original", 0, &[]).unwrap();
+        let result = run(&config, "original", 0, &[]).unwrap();
         assert_eq!(result, None);
     }
 
     #[test]
     fn lua_output_global_available() {
         let config = inline_script("return output");
-        let result = run_lua_script(&config, "hello world", 0, &[]).unwrap();
+        let result = run(&config, "hello world", 0, &[]).unwrap();
         assert_eq!(result, Some("hello world".to_string()));
     }
 
     #[test]
     fn lua_exit_code_global_available() {
         let config = inline_script("return tostring(exit_code)");
-        let result = run_lua_script(&config, "", 0, &[]).unwrap();
+        let result = run(&config, "", 0, &[]).unwrap();
         assert_eq!(result, Some("0".to_string()));
     }
 
@@ -112,7 +262,7 @@ mod tests {
     fn lua_args_global_available() {
         let config = inline_script("return args[1]");
         let args = vec!["hello".to_string()];
-        let result = run_lua_script(&config, "", 0, &args).unwrap();
+        let result = run(&config, "", 0, &args).unwrap();
         assert_eq!(result, Some("hello".to_string()));
     }
 
@@ -123,28 +273,98 @@ mod tests {
             file: Some("/nonexistent/path/script.luau".to_string()),
             source: None,
         };
-        let result = run_lua_script(&config, "", 0, &[]);
+        let result = run(&config, "", 0, &[]);
         assert!(result.is_err());
     }
 
     #[test]
     fn lua_invalid_syntax_returns_err() {
         let config = inline_script("not lua !!!");
-        let result = run_lua_script(&config, "", 0, &[]);
+        let result = run(&config, "", 0, &[]);
         assert!(result.is_err());
     }
 
     #[test]
     fn lua_os_blocked_by_sandbox() {
         let config = inline_script(r#"return os.execute("id")"#);
-        let result = run_lua_script(&config, "", 0, &[]);
+        let result = run(&config, "", 0, &[]);
         assert!(result.is_err());
     }
 
     #[test]
     fn lua_io_blocked_by_sandbox() {
         let config = inline_script("return io.read()");
-        let result = run_lua_script(&config, "", 0, &[]);
+        let result = run(&config, "", 0, &[]);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn lua_sections_vars_and_remaining_args_available() {
+        let mut sections = SectionMap::new();
+        sections.insert(
+            "failures".to_string(),
+            crate::filter::section::SectionData {
+                lines: vec!["FAIL a".to_string(), "FAIL b".to_string()],
+                ..Default::default()
+            },
+        );
+        let vars = HashMap::from([("passed".to_string(), "40".to_string())]);
+        let config = inline_script(
+            "return sections.failures.count .. ' ' .. sections.failures.lines[2] \
+             .. ' ' .. vars.passed .. ' ' .. remaining_args[1]",
+        );
+        let args = vec!["--release".to_string()];
+        let input = ScriptInput {
+            output: "",
+            exit_code: 1,
+            args: &args,
+            sections: &sections,
+            vars: &vars,
+        };
+        let result = run_lua_script(&config, &input).unwrap();
+        assert_eq!(result, Some("2 FAIL b 40 --release".to_string()));
+    }
+
+    #[test]
+    fn lua_regex_helpers() {
+        let config = inline_script(
+            r##"
+local file, line = regex.match(output, [[(\S+\.rs):(\d+)]])
+local whole = regex.match(output, [[\d+]])
+local masked, n = regex.gsub(output, [[\d+]], "#")
+return file .. " " .. line .. " " .. whole .. " " .. masked .. " " .. n
+            "##,
+        );
+        let result = run(&config, "src/a.rs:12:5", 0, &[]).unwrap();
+        assert_eq!(result, Some("src/a.rs 12 12 src/a.rs:#:# 2".to_string()));
+
+        let missing = inline_script(r#"return tostring(regex.match("abc", "x(y)"))"#);
+        let result = run(&missing, "", 0, &[]).unwrap();
+        assert_eq!(result, Some("nil".to_string()));
+
+        let invalid = inline_script(r#"return regex.gsub("abc", "(", "")"#);
+        assert!(run(&invalid, "", 0, &[]).is_err());
+    }
+
+    #[test]
+    fn lua_json_decode_and_lines_iterator() {
+        let config = inline_script(
+            r#"
+local doc = json.decode('{"name": "demo", "tests": [1, 2, 3], "skip": null}')
+local count = 0
+for line in lines() do
+    if line ~= "" then count = count + 1 end
+end
+return doc.name .. " " .. #doc.tests .. " " .. tostring(doc.skip) .. " " .. count
+            "#,
+        );
+        let result = run(&config, "a\n\nb\nc", 0, &[]).unwrap();
+        assert_eq!(result, Some("demo 3 nil 3".to_string()));
+
+        let big = inline_script(r#"return tostring(json.decode("[9007199254740993]")[1] == 2^53)"#);
+        assert_eq!(run(&big, "", 0, &[]).unwrap(), Some("true".to_string()));
+
+        let invalid = inline_script(r#"return json.decode("{")"#);
+        assert!(run(&invalid, "", 0, &[]).is_err());
+    }
 }
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use crate::config::types::Section;
+
+use super::section::SectionMap;
+
+/// Render collected sections as markdown, in `[[section]]` declaration order.
+///
+/// Each non-empty section becomes a `### Heading` (from `name`, else
+/// `collect_as`): lines as a bullet list, `split_on` blocks as fenced code
+/// blocks. A non-empty `summary` (the branch's rendered `output`) is appended
+/// under `### Summary`.
+pub fn render_markdown(defs: &[Section], sections: &SectionMap, summary: Option<&str>) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for def in defs {
+        let Some(ref key) = def.collect_as else {
+            continue;
+        };
+        if !seen.insert(key.as_str()) {
+            continue;
+        }
+        let Some(data) = sections.get(key) else {
+            continue;
+        };
+        let body = if data.blocks.is_empty() {
+            bullets(&data.lines)
+        } else {
+            fenced(&data.blocks)
+        };
+        if body.is_empty() {
+            continue;
+        }
+        let title = heading(def.name.as_deref().unwrap_or(key));
+        parts.push(format!("### {title}\n{body}"));
+    }
+
+    if let Some(summary) = summary.map(str::trim).filter(|s| !s.is_empty()) {
+        parts.push(format!("### Summary\n{summary}"));
+    }
+
+    parts.join("\n\n")
+}
+
+/// `failed_tests` / `failed-tests` → `Failed tests`.
+fn heading(name: &str) -> String {
+    let words = name.replace(['_', '-'], " ");
+    let mut chars = words.trim().chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn bullets(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| format!("- {l}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Each block in its own fence, long enough not to clash with backticks inside.
+fn fenced(blocks: &[String]) -> String {
+    let mut out = String::new();
+    for block in blocks
+        .iter()
+        .map(|b| b.trim_end())
+        .filter(|b| !b.is_empty())
+    {
+        let longest_run = block.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = write!(out, "{fence}\n{block}\n{fence}");
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::filter::section::SectionData;
+
+    fn def(name: Option<&str>, collect_as: &str) -> Section {
+        Section {
+            name: name.map(String::from),
+            enter: None,
+            exit: None,
+            match_pattern: None,
+            split_on: None,
+            collect_as: Some(collect_as.to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
+        }
+    }
+
+    fn lines(items: &[&str]) -> SectionData {
+        SectionData {
+            lines: items.iter().map(ToString::to_string).collect(),
+            blocks: vec![],
+            total: None,
+        }
+    }
+
+    #[test]
+    fn heading_humanizes_names() {
+        assert_eq!(heading("failures"), "Failures");
+        assert_eq!(heading("failed_tests"), "Failed tests");
+        assert_eq!(heading("lint-errors"), "Lint errors");
+        assert_eq!(heading(""), "");
+    }
+
+    #[test]
+    fn lines_render_as_bullets_in_declaration_order() {
+        let defs = vec![def(None, "warnings"), def(Some("failures"), "failed")];
+        let mut sections = SectionMap::new();
+        sections.insert("failed".into(), lines(&["  test_a", "test_b"]));
+        sections.insert("warnings".into(), lines(&["unused import"]));
+
+        assert_eq!(
+            render_markdown(&defs, &sections, Some("2 failed")),
+            "### Warnings\n- unused import\n\n### Failures\n- test_a\n- test_b\n\n### Summary\n2 failed"
+        );
+    }
+
+    #[test]
+    fn blocks_render_as_fences() {
+        let defs = vec![def(None, "failure_blocks")];
+        let mut sections = SectionMap::new();
+        sections.insert(
+            "failure_blocks".into(),
+            SectionData {
+                lines: vec![],
+                blocks: vec!["panicked at a\n  left: 1".into(), "uses ```code```".into()],
+                total: None,
+            },
+        );
+
+        assert_eq!(
+            render_markdown(&defs, &sections, None),
+            "### Failure blocks\n```\npanicked at a\n  left: 1\n```\n````\nuses ```code```\n````"
+        );
+    }
+
+    #[test]
+    fn empty_sections_and_summary_omitted() {
+        let defs = vec![def(None, "empty"), def(None, "missing")];
+        let mut sections = SectionMap::new();
+        sections.insert("empty".into(), lines(&[]));
+
+        assert_eq!(render_markdown(&defs, &sections, Some("  ")), "");
+    }
+
+    #[test]
+    fn shared_collect_as_rendered_once() {
+        let defs = vec![def(None, "errs"), def(None, "errs")];
+        let mut sections = SectionMap::new();
+        sections.insert("errs".into(), lines(&["e1"]));
+
+        assert_eq!(render_markdown(&defs, &sections, None), "### Errs\n- e1");
+    }
+}
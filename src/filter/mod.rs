@@ -1,25 +1,47 @@
 mod aggregate;
+mod args;
+mod branch;
 mod cleanup;
 mod dedup;
+pub mod diagnostics;
+mod escalate;
+pub mod explain;
 mod extract;
+mod fail_when;
+mod fallback;
 mod group;
+mod json;
+#[cfg(feature = "scripting")]
 mod lua;
+mod markdown;
 mod match_output;
 mod parse;
 mod replace;
 pub mod section;
+mod shorten;
 mod skip;
+mod spinner;
+mod stacktrace;
+mod sub_filter;
+mod tail;
 mod template;
 
-use crate::config::types::{FilterConfig, OutputBranch};
+use std::collections::HashMap;
+
+use crate::config::types::{FilterConfig, OutputBranch, RenderMode, Section, StreamSource};
 use crate::runner::CommandResult;
 
+use self::branch::select_branch;
 use self::section::SectionMap;
 
 /// The result of applying a filter to command output.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FilterResult {
     pub output: String,
+    /// True when the filter's `fail_when` condition held for this run.
+    pub failed: bool,
+    /// True when no branch produced output and `[fallback]` (or passthrough) was used.
+    pub used_fallback: bool,
 }
 
 /// Apply a filter configuration to a command result.
@@ -28,18 +50,38 @@ pub struct FilterResult {
 ///
 /// ```text
 /// 1.   match_output  — substring check, first match wins
-/// 1.5. [[replace]]   — per-line regex transformations
+/// 1.5. [[replace]]   — per-line regex transformations (on the `source` stream)
 /// 1.6. strip_ansi / trim_lines — per-line cleanup
+/// 1.7. strip_spinners — drop spinner frames, collapse redraws
 /// 2.   skip/keep     — top-level pre-filtering
-/// 2.5. dedup         — collapse duplicate lines
+/// 2.5. dedup         — collapse duplicate (or, fuzzy, similar) lines
+/// 2.6. json          — JSONL records: filter by predicates, render per record
+/// 2.7. stacktrace    — condense stack traces to the exception and top project frames
 /// 2b.  lua_script    — escape hatch (if configured)
 /// 3.   parse         — alternative structured path
-/// 4.   sections      — state-machine line collection
-/// 5.   select branch — exit code 0 → on_success, else on_failure
+/// 4.   sections      — state-machine line collection (per-section `source`)
+/// 5.   select branch — first matching [[branch]], else exit code 0 → on_success, else on_failure
 /// 6.   apply branch  — render output or fallback
+/// 6.1. skip_summary  — footer counting the lines `skip` hid, by pattern
+/// 6.4. shorten_paths — project root → relative paths, home → `~`
 /// 6.5. strip_empty_lines / collapse_empty_lines — post-process output
+/// 6.55 max_output_lines — keep the first and last lines of a long output
+/// 6.6. escalate      — failing run that lost too much → append the raw tail
+/// 7.   fail_when     — flag the result as failed if the condition holds
 /// ```
-/// Apply stage 1.5 + 1.6 pre-filter transforms (`replace`, `strip_ansi`, `trim_lines`).
+pub fn apply(config: &FilterConfig, result: &CommandResult, args: &[String]) -> FilterResult {
+    let (rendered, used_fallback) = render(config, result, args);
+    let output = cleanup::post_process_output(config, rendered);
+    let output = cleanup::cap_lines(config.max_output_lines, output);
+    FilterResult {
+        output: escalate::apply(config, result, output),
+        failed: fail_when::evaluate(config, result, args),
+        used_fallback,
+    }
+}
+
+/// Apply stage 1.5–1.7 pre-filter transforms (`replace`, `strip_ansi`,
+/// `trim_lines`, `strip_spinners`).
 ///
 /// Returns an owned `Vec<String>` so lifetimes stay simple in `apply`.
 fn build_raw_lines(combined: &str, config: &FilterConfig) -> Vec<String> {
@@ -49,60 +91,54 @@ fn build_raw_lines(combined: &str, config: &FilterConfig) -> Vec<String> {
     } else {
         replace::apply_replace(&config.replace, &initial)
     };
-    if config.strip_ansi || config.trim_lines {
+    let after_cleanup = if config.strip_ansi || config.trim_lines {
         let refs: Vec<&str> = after_replace.iter().map(String::as_str).collect();
         cleanup::apply_line_cleanup(config, &refs)
     } else {
         after_replace
+    };
+    if config.strip_spinners {
+        let refs: Vec<&str> = after_cleanup.iter().map(String::as_str).collect();
+        spinner::collapse_spinners(&refs)
+    } else {
+        after_cleanup
     }
 }
 
-pub fn apply(config: &FilterConfig, result: &CommandResult, args: &[String]) -> FilterResult {
+/// Run stages 1–6 and return the output before post-processing, and whether
+/// it came from the fallback.
+fn render(config: &FilterConfig, result: &CommandResult, args: &[String]) -> (String, bool) {
     // 1. match_output short-circuit
     if let Some(rule) = match_output::find_matching_rule(&config.match_output, &result.combined) {
         let output = match_output::render_output(&rule.output, &rule.contains, &result.combined);
-        return FilterResult {
-            output: cleanup::post_process_output(config, output),
-        };
+        return (output, false);
     }
 
-    // 1.5 + 1.6. Replace + per-line cleanup (strip_ansi, trim_lines)
-    let transformed = build_raw_lines(&result.combined, config);
-    let raw_lines: Vec<&str> = transformed.iter().map(String::as_str).collect();
-
-    // 2. Top-level skip/keep pre-filtering
-    let lines = skip::apply_skip(&config.skip, &raw_lines);
-    let lines = skip::apply_keep(&config.keep, &lines);
+    let (output, used_fallback) = render_filtered(config, result, args);
+    (append_skip_summary(config, result, output), used_fallback)
+}
 
-    // 2.5. Dedup
-    let lines = if config.dedup {
-        dedup::apply_dedup(&lines, config.dedup_window)
-    } else {
-        lines
-    };
+/// Stages 1.5–6, once `match_output` has not matched.
+fn render_filtered(
+    config: &FilterConfig,
+    result: &CommandResult,
+    args: &[String],
+) -> (String, bool) {
+    // 1.5–2.7. Pre-filter the configured source stream
+    let source = config.source.unwrap_or_default();
+    let source_lines = prefilter_lines(config, result.stream(source));
+    let lines: Vec<&str> = source_lines.iter().map(String::as_str).collect();
 
     // 2b. Lua script escape hatch
-    if let Some(ref script_cfg) = config.lua_script {
-        let pre_filtered = lines.join("\n");
-        match lua::run_lua_script(script_cfg, &pre_filtered, result.exit_code, args) {
-            Ok(Some(output)) => {
-                return FilterResult {
-                    output: cleanup::post_process_output(config, output),
-                };
-            }
-            Ok(None) => {} // passthrough → continue normal pipeline
-            Err(e) => eprintln!("[tokf] lua script error: {e:#}"),
-        }
+    if let Some(output) = run_script(config, &lines, result, args) {
+        return (output, false);
     }
 
     // 3. If parse exists → parse+output pipeline
     if let Some(ref parse_config) = config.parse {
         let parse_result = parse::run_parse(parse_config, &lines);
         let output_config = config.output.clone().unwrap_or_default();
-        let output = parse::render_output(&output_config, &parse_result);
-        return FilterResult {
-            output: cleanup::post_process_output(config, output),
-        };
+        return (parse::render_output(&output_config, &parse_result), false);
     }
 
     // 4. Collect sections (from raw output — sections need structural
@@ -111,88 +147,186 @@ pub fn apply(config: &FilterConfig, result: &CommandResult, args: &[String]) ->
     //    unmodified lines. If the command emits ANSI codes in marker lines,
     //    set `strip_ansi = true` AND write patterns that match the raw text,
     //    or configure the command to disable color (e.g. `--no-color`).
-    let has_sections = !config.section.is_empty();
-    let sections = if has_sections {
-        let raw_lines: Vec<&str> = result.combined.lines().collect();
-        section::collect_sections(&config.section, &raw_lines)
-    } else {
-        SectionMap::new()
-    };
+    //    Each section reads its own `source` stream (combined by default).
+    let sections = section::collect_sections_from(&config.section, result);
 
-    // 5. Select branch by exit code
-    let branch = select_branch(config, result.exit_code);
+    // 5. Select branch by args and exit code
+    let branch = select_branch(config, result.exit_code, args);
 
-    // 6. Apply branch with sections, or fallback
+    // 6. Apply branch with sections, or fallback. A branch `source` other
+    //    than the top-level one gets that stream pre-filtered on its own.
     let pre_filtered = lines.join("\n");
-    let output = branch.map_or_else(
-        || apply_fallback(config, &pre_filtered),
-        |b| {
-            apply_branch(b, &pre_filtered, &sections, has_sections)
-                .unwrap_or_else(|| apply_fallback(config, &pre_filtered))
-        },
-    );
+    let fallback = || (fallback::apply_fallback(config, &pre_filtered), true);
+    branch.map_or_else(fallback, |b| {
+        let input = match b.source {
+            Some(s) if s != source => prefilter_lines(config, result.stream(s)).join("\n"),
+            _ => pre_filtered.clone(),
+        };
+        let ctx = BranchCtx {
+            branch: b,
+            raw: result.stream(b.source.unwrap_or(source)),
+            input: &input,
+            sections: &sections,
+            section_defs: &config.section,
+            result,
+            args,
+        };
+        apply_branch(&ctx).map_or_else(fallback, |output| (output, false))
+    })
+}
 
-    FilterResult {
-        output: cleanup::post_process_output(config, output),
+/// 6.1. With `skip_summary`, end the output with a count of the lines `skip`
+/// hid from the source stream.
+fn append_skip_summary(config: &FilterConfig, result: &CommandResult, output: String) -> String {
+    if !config.skip_summary {
+        return output;
+    }
+    let source = result.stream(config.source.unwrap_or_default());
+    let raw = build_raw_lines(source, config);
+    let refs: Vec<&str> = raw.iter().map(String::as_str).collect();
+    match skip::summarize(&config.skip, &refs) {
+        Some(footer) if output.trim().is_empty() => footer,
+        Some(footer) => format!("{}\n{footer}", output.trim_end_matches('\n')),
+        None => output,
+    }
+}
+
+/// Run the `[lua_script]`, if any. `Some` replaces the rest of the pipeline.
+///
+/// Besides the pre-filtered output, the script sees the collected sections
+/// and the vars a template would: the aggregates of the branch that would be
+/// selected, `[[step]]` outputs and the argument vars.
+#[cfg(feature = "scripting")]
+fn run_script(
+    config: &FilterConfig,
+    lines: &[&str],
+    result: &CommandResult,
+    args: &[String],
+) -> Option<String> {
+    let script_cfg = config.lua_script.as_ref()?;
+    let sections = section::collect_sections_from(&config.section, result);
+    let mut vars = select_branch(config, result.exit_code, args)
+        .and_then(|branch| branch.aggregate.as_ref())
+        .map_or_else(HashMap::new, |aggregates| {
+            aggregate::run_aggregates(aggregates, &sections)
+        });
+    vars.extend(result.steps.iter().cloned());
+    vars.extend(args::arg_vars(args));
+    let output = lines.join("\n");
+    let input = lua::ScriptInput {
+        output: &output,
+        exit_code: result.exit_code,
+        args,
+        sections: &sections,
+        vars: &vars,
+    };
+    match lua::run_lua_script(script_cfg, &input) {
+        Ok(output) => output, // None → passthrough, continue normal pipeline
+        Err(e) => {
+            tracing::warn!("lua script error: {e:#}");
+            None
+        }
+    }
+}
+
+/// Built without Luau: a configured script is skipped with a warning.
+#[cfg(not(feature = "scripting"))]
+fn run_script(
+    config: &FilterConfig,
+    _lines: &[&str],
+    _result: &CommandResult,
+    _args: &[String],
+) -> Option<String> {
+    if config.lua_script.is_some() {
+        tracing::warn!("lua_script ignored: built without the `scripting` feature");
     }
+    None
 }
 
-/// Select the output branch based on exit code.
-/// Exit code 0 → `on_success`, anything else → `on_failure`.
-const fn select_branch(config: &FilterConfig, exit_code: i32) -> Option<&OutputBranch> {
-    if exit_code == 0 {
-        config.on_success.as_ref()
+/// Stages 1.5–2.7 (`replace`, line cleanup, `skip`/`keep`, `dedup`, `json`,
+/// `stacktrace`) over one stream's text, returning the surviving lines.
+fn prefilter_lines(config: &FilterConfig, text: &str) -> Vec<String> {
+    // 1.5 + 1.6. Replace + per-line cleanup (strip_ansi, trim_lines)
+    let transformed = build_raw_lines(text, config);
+    let raw_lines: Vec<&str> = transformed.iter().map(String::as_str).collect();
+
+    // 2. Top-level skip/keep pre-filtering
+    let lines = skip::apply_skip(&config.skip, &raw_lines);
+    let lines = skip::apply_keep(&config.keep, &lines);
+
+    // 2.5. Dedup (exact, or fuzzy with an `(xN)` count)
+    let fuzzy: Vec<String>;
+    let lines = if config.dedup_fuzzy {
+        fuzzy = dedup::apply_fuzzy(&lines, dedup_threshold(config), config.dedup_window);
+        fuzzy.iter().map(String::as_str).collect()
+    } else if config.dedup {
+        dedup::apply_dedup(&lines, config.dedup_window)
     } else {
-        config.on_failure.as_ref()
+        lines
+    };
+
+    // 2.6. JSONL records → one rendered line per selected record
+    let lines = match config.json {
+        Some(ref json_cfg) => json::apply_json(json_cfg, &lines),
+        None => lines.into_iter().map(ToString::to_string).collect(),
+    };
+
+    // 2.7. Stack traces → exception line(s) and the top project frames
+    match config.stacktrace {
+        Some(ref st) => {
+            let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+            stacktrace::condense(&refs, &stacktrace::Options::from_config(st))
+        }
+        None => lines,
     }
 }
 
-/// Apply a branch's processing rules to the combined output.
+fn dedup_threshold(config: &FilterConfig) -> f64 {
+    config.dedup_threshold.unwrap_or(dedup::DEFAULT_THRESHOLD)
+}
+
+/// Everything a selected branch renders from.
+struct BranchCtx<'a> {
+    branch: &'a OutputBranch,
+    /// The branch's source stream as the command wrote it.
+    raw: &'a str,
+    /// That stream, pre-filtered; becomes `{output}`.
+    input: &'a str,
+    sections: &'a SectionMap,
+    /// The filter's `[[section]]` definitions; empty when it has none.
+    section_defs: &'a [Section],
+    /// The command's streams, exit code, `[[step]]` outputs and timeout.
+    result: &'a CommandResult,
+    args: &'a [String],
+}
+
+/// Apply `ctx.branch` to `ctx.input`. `None` means use the fallback.
 ///
-/// When `has_sections` is true and the branch has an output template,
-/// the template is rendered with aggregation vars and section data.
-/// Returns `None` when sections were expected but collected nothing
-/// (signals: use fallback).
+/// A branch with an `output` template (or `render = "markdown"` and
+/// sections) renders it with the vars from [`template_vars`]; `None` when
+/// sections were expected but collected nothing from non-empty input.
 ///
-/// Processing order (non-section path):
-/// 1. Fixed `output` string → return immediately
-/// 2. `tail` / `head` truncation
-/// 3. `skip` patterns
-/// 4. `extract` rule
-/// 5. Remaining lines joined with `\n`
-fn apply_branch(
-    branch: &OutputBranch,
-    combined: &str,
-    sections: &SectionMap,
-    has_sections: bool,
-) -> Option<String> {
-    // 1. Aggregation
-    let vars = branch
-        .aggregate
-        .as_ref()
-        .map_or_else(std::collections::HashMap::new, |agg_rule| {
-            aggregate::run_aggregate(agg_rule, sections)
-        });
+/// Otherwise the input lines go through:
+/// 1. `tail_adaptive` / `tail` / `head` truncation
+/// 2. `skip`, then `keep` patterns
+/// 3. `extract` rule, or the remaining lines joined with `\n`
+fn apply_branch(ctx: &BranchCtx) -> Option<String> {
+    let branch = ctx.branch;
+    let markdown = branch.render == Some(RenderMode::Markdown) && !ctx.section_defs.is_empty();
 
-    // 2. Output template
-    if let Some(ref output_tmpl) = branch.output {
-        if has_sections {
-            let any_collected = sections
-                .values()
-                .any(|s| !s.lines.is_empty() || !s.blocks.is_empty());
-            if !any_collected && vars.is_empty() {
-                return None; // sections expected but empty → fallback
-            }
-        }
-        let mut vars = vars;
-        vars.insert("output".to_string(), combined.to_string());
-        return Some(template::render_template(output_tmpl, &vars, sections));
+    // 1–2. Aggregation + output template (or markdown sections)
+    if branch.output.is_some() || markdown {
+        return render_branch_template(ctx);
     }
 
-    // Non-template path (tail/head/skip/extract)
-    let mut lines: Vec<&str> = combined.lines().collect();
+    // Non-template path (tail/head/skip/keep/extract)
+    let mut lines: Vec<&str> = ctx.input.lines().collect();
 
-    if let Some(tail) = branch.tail
+    let tail = branch
+        .tail_adaptive
+        .as_ref()
+        .map_or(branch.tail, |rule| Some(tail::adaptive_len(rule, &lines)));
+    if let Some(tail) = tail
         && lines.len() > tail
     {
         lines = lines.split_off(lines.len() - tail);
@@ -202,6 +336,7 @@ fn apply_branch(
     }
 
     lines = skip::apply_skip(&branch.skip, &lines);
+    lines = skip::apply_keep(&branch.keep, &lines);
 
     if let Some(ref rule) = branch.extract {
         return Some(extract::apply_extract(rule, &lines));
@@ -210,17 +345,81 @@ fn apply_branch(
     Some(lines.join("\n"))
 }
 
-/// Fallback when no branch matches or sections collected nothing.
-fn apply_fallback(config: &FilterConfig, combined: &str) -> String {
-    if let Some(ref fb) = config.fallback
-        && let Some(tail) = fb.tail
-    {
-        let lines: Vec<&str> = combined.lines().collect();
-        if lines.len() > tail {
-            return lines[lines.len() - tail..].join("\n");
+/// Render a branch's `output` template with aggregate and argument vars,
+/// optionally wrapped in markdown section headings.
+fn render_branch_template(ctx: &BranchCtx) -> Option<String> {
+    let vars = template_vars(ctx)?;
+    let summary = ctx
+        .branch
+        .output
+        .as_ref()
+        .map(|tmpl| template::render_template(tmpl, &vars, ctx.sections));
+
+    if ctx.branch.render == Some(RenderMode::Markdown) && !ctx.section_defs.is_empty() {
+        return Some(markdown::render_markdown(
+            ctx.section_defs,
+            ctx.sections,
+            summary.as_deref(),
+        ));
+    }
+    summary
+}
+
+/// Variables for a branch template besides the sections themselves:
+/// aggregates, `[[step]]` outputs, argument vars, `{output}`, the raw
+/// `{stdout}` / `{stderr}` and, if the command was killed, `{timeout}`.
+/// `None` when sections were expected but collected nothing (use fallback).
+/// Empty input renders the template anyway: the fallback would show nothing.
+fn template_vars(ctx: &BranchCtx) -> Option<HashMap<String, String>> {
+    let (result, input) = (ctx.result, ctx.input);
+    let mut vars = ctx
+        .branch
+        .aggregate
+        .as_ref()
+        .map_or_else(HashMap::new, |aggregates| {
+            aggregate::run_aggregates(aggregates, ctx.sections)
+        });
+
+    if !ctx.section_defs.is_empty() {
+        let any_collected = ctx
+            .sections
+            .values()
+            .any(|s| !s.lines.is_empty() || !s.blocks.is_empty());
+        if !any_collected && vars.is_empty() && !input.trim().is_empty() {
+            return None; // sections expected but empty → fallback
         }
     }
-    combined.to_string()
+    vars.extend(result.steps.iter().cloned());
+    vars.extend(args::arg_vars(ctx.args));
+    vars.extend(reduction_vars(ctx.raw, input));
+    vars.insert("output".to_string(), input.to_string());
+    vars.insert(
+        "stdout".to_string(),
+        result.stream(StreamSource::Stdout).to_string(),
+    );
+    vars.insert(
+        "stderr".to_string(),
+        result.stream(StreamSource::Stderr).to_string(),
+    );
+    if let Some(secs) = result.timed_out {
+        vars.insert("timeout".to_string(), secs.to_string());
+    }
+    Some(vars)
+}
+
+/// `{lines_removed}` and `{reduction_pct}`: how much of the branch's raw
+/// source stream the pre-filter dropped before it became `{output}`.
+fn reduction_vars(raw: &str, filtered: &str) -> [(String, String); 2] {
+    let lines_removed = raw.lines().count().saturating_sub(filtered.lines().count());
+    let reduction_pct = if raw.is_empty() {
+        0
+    } else {
+        raw.len().saturating_sub(filtered.len()) * 100 / raw.len()
+    };
+    [
+        ("lines_removed".to_string(), lines_removed.to_string()),
+        ("reduction_pct".to_string(), reduction_pct.to_string()),
+    ]
 }
 
 #[cfg(test)]
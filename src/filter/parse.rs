@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use regex::Regex;
 
 use crate::config::types::{OutputConfig, ParseConfig};
+use crate::regex_cache;
 
 use super::extract::interpolate;
 use super::group::{self, GroupCount};
@@ -21,10 +22,10 @@ pub fn run_parse(config: &ParseConfig, lines: &[&str]) -> ParseResult {
 
     if let Some(ref branch_cfg) = config.branch
         && let Some(line) = lines.get(branch_cfg.line.saturating_sub(1))
-        && let Ok(re) = Regex::new(&branch_cfg.pattern)
+        && let Some(re) = regex_cache::compile(&branch_cfg.pattern)
         && let Some(caps) = re.captures(line)
     {
-        let value = interpolate(&branch_cfg.output, &caps);
+        let value = interpolate(&branch_cfg.output, &re, &caps);
         vars.insert("branch".to_string(), value);
     }
 
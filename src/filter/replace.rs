@@ -1,6 +1,7 @@
 use regex::Regex;
 
 use crate::config::types::ReplaceRule;
+use crate::regex_cache;
 
 /// Apply `[[replace]]` rules to each line, in order.
 ///
@@ -13,11 +14,7 @@ pub fn apply_replace(rules: &[ReplaceRule], lines: &[&str]) -> Vec<String> {
     // Rules with invalid patterns are silently dropped.
     let compiled: Vec<(Regex, &str)> = rules
         .iter()
-        .filter_map(|r| {
-            Regex::new(&r.pattern)
-                .ok()
-                .map(|re| (re, r.output.as_str()))
-        })
+        .filter_map(|r| regex_cache::compile(&r.pattern).map(|re| (re, r.output.as_str())))
         .collect();
 
     lines
@@ -30,7 +27,7 @@ fn apply_rules_to_line(compiled: &[(Regex, &str)], line: &str) -> String {
     let mut current = line.to_string();
     for (re, output_tmpl) in compiled {
         if let Some(caps) = re.captures(&current) {
-            current = super::extract::interpolate(output_tmpl, &caps);
+            current = super::extract::interpolate(output_tmpl, re, &caps);
         }
     }
     current
@@ -63,6 +60,16 @@ mod tests {
         assert_eq!(result, vec!["pkg: 1.0 \u{2192} 2.0".to_string()]);
     }
 
+    #[test]
+    fn replace_named_groups() {
+        let rules = vec![rule(
+            r"^(?P<pkg>\S+)\s+(?P<from>\S+)\s+(?P<to>\S+)",
+            "{pkg}: {from} \u{2192} {to}",
+        )];
+        let result = apply_replace(&rules, &["pkg  1.0  2.0"]);
+        assert_eq!(result, vec!["pkg: 1.0 \u{2192} 2.0".to_string()]);
+    }
+
     #[test]
     fn replace_no_match_passthrough() {
         let rules = vec![rule(r"NOMATCH", "replaced")];
@@ -0,0 +1,114 @@
+use crate::config::types::Section;
+
+use super::SectionData;
+
+/// The `max_blocks` / `max_lines_per_block` caps of one section.
+#[derive(Debug, Default)]
+pub(super) struct Limits {
+    max_blocks: Option<usize>,
+    max_lines_per_block: Option<usize>,
+    overflow_note: Option<String>,
+}
+
+impl Limits {
+    pub(super) fn from_section(section: &Section) -> Self {
+        Self {
+            max_blocks: section.max_blocks,
+            max_lines_per_block: section.max_lines_per_block,
+            overflow_note: section.overflow_note.clone(),
+        }
+    }
+
+    /// Truncate blocks to `max_lines_per_block`, then keep the first
+    /// `max_blocks` items (blocks, or lines when nothing was split). Each cut
+    /// ends with the overflow note, if one is set.
+    pub(super) fn apply(&self, data: &mut SectionData) {
+        if let Some(max) = self.max_lines_per_block {
+            for block in &mut data.blocks {
+                let lines: Vec<&str> = block.lines().collect();
+                if lines.len() > max {
+                    let mut kept = lines[..max].join("\n");
+                    if let Some(note) = self.note(lines.len() - max) {
+                        kept.push('\n');
+                        kept.push_str(&note);
+                    }
+                    *block = kept;
+                }
+            }
+        }
+
+        if let Some(max) = self.max_blocks {
+            let total = data.count();
+            if total > max {
+                let note = self.note(total - max);
+                let items = if data.blocks.is_empty() {
+                    &mut data.lines
+                } else {
+                    &mut data.blocks
+                };
+                items.truncate(max);
+                items.extend(note);
+                data.total = Some(total);
+            }
+        }
+    }
+
+    fn note(&self, dropped: usize) -> Option<String> {
+        self.overflow_note
+            .as_ref()
+            .map(|note| note.replace("{n}", &dropped.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn limits(max_blocks: Option<usize>, per_block: Option<usize>, note: Option<&str>) -> Limits {
+        Limits {
+            max_blocks,
+            max_lines_per_block: per_block,
+            overflow_note: note.map(String::from),
+        }
+    }
+
+    fn data(lines: &[&str], blocks: &[&str]) -> SectionData {
+        SectionData {
+            lines: lines.iter().map(ToString::to_string).collect(),
+            blocks: blocks.iter().map(ToString::to_string).collect(),
+            total: None,
+        }
+    }
+
+    #[test]
+    fn max_blocks_keeps_first_blocks_and_true_count() {
+        let mut d = data(&[], &["a", "b", "c", "d"]);
+        limits(Some(2), None, Some("...and {n} more")).apply(&mut d);
+        assert_eq!(d.blocks, vec!["a", "b", "...and 2 more"]);
+        assert_eq!(d.count(), 4);
+    }
+
+    #[test]
+    fn max_blocks_applies_to_lines_without_split() {
+        let mut d = data(&["1", "2", "3"], &[]);
+        limits(Some(1), None, None).apply(&mut d);
+        assert_eq!(d.lines, vec!["1"]);
+        assert_eq!(d.count(), 3);
+    }
+
+    #[test]
+    fn max_lines_per_block_truncates_each_block() {
+        let mut d = data(&[], &["a\nb\nc", "x"]);
+        limits(None, Some(2), Some("({n} lines hidden)")).apply(&mut d);
+        assert_eq!(d.blocks, vec!["a\nb\n(1 lines hidden)", "x"]);
+        assert_eq!(d.count(), 2);
+    }
+
+    #[test]
+    fn under_the_limits_nothing_changes() {
+        let mut d = data(&[], &["a", "b"]);
+        limits(Some(2), Some(5), Some("more")).apply(&mut d);
+        assert_eq!(d, data(&[], &["a", "b"]));
+    }
+}
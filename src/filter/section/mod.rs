@@ -1,8 +1,12 @@
+mod limits;
+
 use std::collections::HashMap;
 
 use regex::Regex;
 
-use crate::config::types::Section;
+use crate::config::types::{Section, StreamSource};
+use crate::regex_cache;
+use crate::runner::CommandResult;
 
 /// Collected data for a single named section.
 pub type SectionMap = HashMap<String, SectionData>;
@@ -12,12 +16,17 @@ pub type SectionMap = HashMap<String, SectionData>;
 pub struct SectionData {
     pub lines: Vec<String>,
     pub blocks: Vec<String>,
+    /// Item count before `max_blocks` dropped any.
+    pub total: Option<usize>,
 }
 
 impl SectionData {
-    /// Block count if `split_on` was used, otherwise line count.
+    /// Block count if `split_on` was used, otherwise line count. Counts
+    /// items dropped by `max_blocks` too.
     pub const fn count(&self) -> usize {
-        if self.blocks.is_empty() {
+        if let Some(total) = self.total {
+            total
+        } else if self.blocks.is_empty() {
             self.lines.len()
         } else {
             self.blocks.len()
@@ -44,11 +53,12 @@ struct SectionRunner {
     is_stateful: bool,
     active: bool,
     collected: Vec<String>,
+    limits: limits::Limits,
 }
 
 /// Compile an optional regex pattern, returning `None` if absent or invalid.
 fn compile_optional(pattern: Option<&String>) -> Option<Regex> {
-    pattern.and_then(|p| Regex::new(p).ok())
+    pattern.and_then(|p| regex_cache::compile(p))
 }
 
 impl SectionRunner {
@@ -80,6 +90,7 @@ impl SectionRunner {
             is_stateful,
             active: !is_stateful, // stateless sections are always active
             collected: Vec::new(),
+            limits: limits::Limits::from_section(section),
         })
     }
 
@@ -122,11 +133,13 @@ impl SectionRunner {
         let mut data = SectionData {
             lines: self.collected,
             blocks: Vec::new(),
+            total: None,
         };
 
         if let Some(ref re) = self.split_re {
             data.blocks = split_into_blocks(&data.lines, re);
         }
+        self.limits.apply(&mut data);
 
         (self.collect_as, data)
     }
@@ -171,39 +184,56 @@ pub fn collect_sections(sections: &[Section], lines: &[&str]) -> SectionMap {
     runners.into_iter().map(SectionRunner::finish).collect()
 }
 
+/// Like [`collect_sections`], feeding each section the lines of its `source`
+/// stream (combined output unless set).
+pub fn collect_sections_from(sections: &[Section], result: &CommandResult) -> SectionMap {
+    let mut runners: Vec<_> = sections
+        .iter()
+        .filter_map(|s| Some((s.source.unwrap_or_default(), SectionRunner::new(s)?)))
+        .collect();
+
+    for source in [
+        StreamSource::Combined,
+        StreamSource::Stdout,
+        StreamSource::Stderr,
+    ] {
+        if !runners.iter().any(|(s, _)| *s == source) {
+            continue;
+        }
+        for line in result.stream(source).lines() {
+            for (_, runner) in runners.iter_mut().filter(|(s, _)| *s == source) {
+                runner.process_line(line);
+            }
+        }
+    }
+
+    runners.into_iter().map(|(_, r)| r.finish()).collect()
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
     use super::*;
 
-    fn section(
-        name: &str,
-        enter: Option<&str>,
-        exit: Option<&str>,
-        match_pat: Option<&str>,
-        split_on: Option<&str>,
-        collect_as: &str,
-    ) -> Section {
+    /// A section named after its `collect_as`, between `enter` and `exit`.
+    fn section(collect_as: &str, enter: Option<&str>, exit: Option<&str>) -> Section {
         Section {
-            name: Some(name.to_string()),
+            name: Some(collect_as.to_string()),
             enter: enter.map(String::from),
             exit: exit.map(String::from),
-            match_pattern: match_pat.map(String::from),
-            split_on: split_on.map(String::from),
+            match_pattern: None,
+            split_on: None,
             collect_as: Some(collect_as.to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }
     }
 
     #[test]
     fn stateful_basic() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            None,
-            "data",
-        )];
+        let sections = vec![section("data", Some("^BEGIN$"), Some("^END$"))];
         let lines: Vec<&str> = vec!["noise", "BEGIN", "line1", "line2", "END", "noise"];
         let map = collect_sections(&sections, &lines);
         let data = &map["data"];
@@ -214,14 +244,10 @@ mod tests {
 
     #[test]
     fn stateful_with_match_filter() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            Some("^keep"),
-            None,
-            "data",
-        )];
+        let sections = vec![Section {
+            match_pattern: Some("^keep".to_string()),
+            ..section("data", Some("^BEGIN$"), Some("^END$"))
+        }];
         let lines: Vec<&str> = vec!["BEGIN", "keep1", "drop", "keep2", "END"];
         let map = collect_sections(&sections, &lines);
         assert_eq!(map["data"].lines, vec!["keep1", "keep2"]);
@@ -229,14 +255,10 @@ mod tests {
 
     #[test]
     fn stateful_with_split_on() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            Some("^---$"),
-            "data",
-        )];
+        let sections = vec![Section {
+            split_on: Some("^---$".to_string()),
+            ..section("data", Some("^BEGIN$"), Some("^END$"))
+        }];
         let lines: Vec<&str> = vec!["BEGIN", "a", "b", "---", "c", "d", "END"];
         let map = collect_sections(&sections, &lines);
         let data = &map["data"];
@@ -247,14 +269,10 @@ mod tests {
 
     #[test]
     fn stateless_match_only() {
-        let sections = vec![section(
-            "s",
-            None,
-            None,
-            Some("^test result:"),
-            None,
-            "summary",
-        )];
+        let sections = vec![Section {
+            match_pattern: Some("^test result:".to_string()),
+            ..section("summary", None, None)
+        }];
         let lines: Vec<&str> = vec![
             "running 5 tests",
             "test result: ok. 5 passed",
@@ -271,22 +289,11 @@ mod tests {
     #[test]
     fn multiple_simultaneous_sections() {
         let sections = vec![
-            section(
-                "failures",
-                Some("^failures:$"),
-                Some("^test result:"),
-                None,
-                None,
-                "blocks",
-            ),
-            section(
-                "names",
-                Some("^failures:$"),
-                Some("^$"),
-                Some(r"^\s+\S+"),
-                None,
-                "names",
-            ),
+            section("blocks", Some("^failures:$"), Some("^test result:")),
+            Section {
+                match_pattern: Some(r"^\s+\S+".to_string()),
+                ..section("names", Some("^failures:$"), Some("^$"))
+            },
         ];
         let lines: Vec<&str> = vec![
             "failures:",
@@ -307,14 +314,7 @@ mod tests {
 
     #[test]
     fn never_enters() {
-        let sections = vec![section(
-            "s",
-            Some("^NEVER$"),
-            Some("^END$"),
-            None,
-            None,
-            "data",
-        )];
+        let sections = vec![section("data", Some("^NEVER$"), Some("^END$"))];
         let lines: Vec<&str> = vec!["a", "b", "c"];
         let map = collect_sections(&sections, &lines);
         assert!(map["data"].lines.is_empty());
@@ -323,14 +323,7 @@ mod tests {
 
     #[test]
     fn enters_but_never_exits() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            None,
-            "data",
-        )];
+        let sections = vec![section("data", Some("^BEGIN$"), Some("^END$"))];
         let lines: Vec<&str> = vec!["BEGIN", "a", "b", "c"];
         let map = collect_sections(&sections, &lines);
         assert_eq!(map["data"].lines, vec!["a", "b", "c"]);
@@ -338,14 +331,7 @@ mod tests {
 
     #[test]
     fn reentry_after_exit() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            None,
-            "data",
-        )];
+        let sections = vec![section("data", Some("^BEGIN$"), Some("^END$"))];
         let lines: Vec<&str> = vec!["BEGIN", "a", "END", "noise", "BEGIN", "b", "END"];
         let map = collect_sections(&sections, &lines);
         assert_eq!(map["data"].lines, vec!["a", "b"]);
@@ -360,6 +346,10 @@ mod tests {
             match_pattern: None,
             split_on: None,
             collect_as: Some("data".to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }];
         let lines: Vec<&str> = vec!["a", "b"];
         let map = collect_sections(&sections, &lines);
@@ -376,6 +366,10 @@ mod tests {
             match_pattern: None,
             split_on: None,
             collect_as: None,
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }];
         let lines: Vec<&str> = vec!["BEGIN", "a", "END"];
         let map = collect_sections(&sections, &lines);
@@ -384,14 +378,7 @@ mod tests {
 
     #[test]
     fn empty_input() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            None,
-            "data",
-        )];
+        let sections = vec![section("data", Some("^BEGIN$"), Some("^END$"))];
         let lines: Vec<&str> = vec![];
         let map = collect_sections(&sections, &lines);
         assert!(map["data"].lines.is_empty());
@@ -399,14 +386,10 @@ mod tests {
 
     #[test]
     fn consecutive_split_separators_no_empty_blocks() {
-        let sections = vec![section(
-            "s",
-            Some("^BEGIN$"),
-            Some("^END$"),
-            None,
-            Some("^---$"),
-            "data",
-        )];
+        let sections = vec![Section {
+            split_on: Some("^---$".to_string()),
+            ..section("data", Some("^BEGIN$"), Some("^END$"))
+        }];
         let lines: Vec<&str> = vec!["BEGIN", "a", "---", "---", "b", "END"];
         let map = collect_sections(&sections, &lines);
         assert_eq!(map["data"].blocks, vec!["a", "b"]);
@@ -417,6 +400,7 @@ mod tests {
         let data = SectionData {
             lines: vec!["a".to_string(), "b".to_string()],
             blocks: Vec::new(),
+            total: None,
         };
         assert_eq!(data.count(), 2);
         assert_eq!(data.items(), &["a".to_string(), "b".to_string()]);
@@ -427,6 +411,7 @@ mod tests {
         let data = SectionData {
             lines: vec!["a".to_string(), "b".to_string()],
             blocks: vec!["block1".to_string()],
+            total: None,
         };
         assert_eq!(data.count(), 1);
         assert_eq!(data.items(), &["block1".to_string()]);
@@ -441,6 +426,10 @@ mod tests {
             match_pattern: None,
             split_on: None,
             collect_as: Some("data".to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }];
         let lines: Vec<&str> = vec!["BEGIN", "a"];
         let map = collect_sections(&sections, &lines);
@@ -456,6 +445,10 @@ mod tests {
             match_pattern: Some("[invalid".to_string()),
             split_on: None,
             collect_as: Some("data".to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }];
         let lines: Vec<&str> = vec!["a", "b"];
         let map = collect_sections(&sections, &lines);
@@ -471,6 +464,10 @@ mod tests {
             match_pattern: None,
             split_on: Some("[invalid".to_string()),
             collect_as: Some("data".to_string()),
+            source: None,
+            max_blocks: None,
+            max_lines_per_block: None,
+            overflow_note: None,
         }];
         let lines: Vec<&str> = vec!["BEGIN", "a", "END"];
         let map = collect_sections(&sections, &lines);
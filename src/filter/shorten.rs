@@ -0,0 +1,149 @@
+//! `shorten_paths`: absolute paths under the project root become relative
+//! (`/home/u/proj/src/main.rs` → `src/main.rs`, the root itself → `.`), and
+//! the home directory becomes `~`. The project root is the nearest directory
+//! holding `.git` above the current one, else the current directory.
+
+use std::path::{Path, PathBuf};
+
+/// The prefixes [`shorten`] rewrites.
+#[derive(Debug, Clone, Default)]
+pub struct PathPrefixes {
+    pub root: Option<PathBuf>,
+    pub home: Option<PathBuf>,
+}
+
+impl PathPrefixes {
+    /// The git root above the current directory (or the directory itself)
+    /// and the home directory.
+    pub fn current() -> Self {
+        let cwd = std::env::current_dir().ok();
+        let root = cwd.map(|cwd| {
+            cwd.ancestors()
+                .find(|dir| dir.join(".git").exists())
+                .map_or_else(|| cwd.clone(), Path::to_path_buf)
+        });
+        Self {
+            root,
+            home: dirs::home_dir(),
+        }
+    }
+}
+
+/// Rewrite the project root to relative paths, then the home directory to `~`.
+pub fn shorten(text: &str, prefixes: &PathPrefixes) -> String {
+    let mut out = text.to_string();
+    if let Some(root) = prefixes.root.as_deref() {
+        out = replace_prefix(&out, root, "", ".");
+    }
+    if let Some(home) = prefixes.home.as_deref() {
+        out = replace_prefix(&out, home, "~/", "~");
+    }
+    out
+}
+
+/// Replace `prefix` where it starts a path: `prefix/rest` → `nested` + rest,
+/// a bare `prefix` → `bare`. Occurrences inside a longer path
+/// (`/x/home/u`, `/home/user2`) are left alone.
+fn replace_prefix(text: &str, prefix: &Path, nested: &str, bare: &str) -> String {
+    let prefix = prefix.to_string_lossy();
+    let prefix = prefix.trim_end_matches(['/', '\\']);
+    // "/" (or nothing) would turn every absolute path relative
+    if prefix.is_empty() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (at, _) in text.match_indices(prefix) {
+        if at < last || text[..at].chars().next_back().is_some_and(is_path_char) {
+            continue;
+        }
+        let end = at + prefix.len();
+        let (replacement, skip) = match text[end..].chars().next() {
+            Some('/' | '\\') => (nested, 1),
+            Some(c) if is_path_char(c) => continue,
+            _ => (bare, 0),
+        };
+        out.push_str(&text[last..at]);
+        out.push_str(replacement);
+        last = end + skip;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '~' | '@' | '+' | '/' | '\\')
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::config::types::FilterConfig;
+    use crate::runner::CommandResult;
+
+    fn prefixes() -> PathPrefixes {
+        PathPrefixes {
+            root: Some(PathBuf::from("/home/u/proj")),
+            home: Some(PathBuf::from("/home/u")),
+        }
+    }
+
+    #[test]
+    fn root_paths_become_relative_and_home_becomes_tilde() {
+        let text = "error: /home/u/proj/src/main.rs:3:5\n   \
+                    Compiling proj v0.1.0 (/home/u/proj)\n\
+                    note: /home/u/.cargo/registry/src/serde-1.0/lib.rs";
+        assert_eq!(
+            shorten(text, &prefixes()),
+            "error: src/main.rs:3:5\n   Compiling proj v0.1.0 (.)\n\
+             note: ~/.cargo/registry/src/serde-1.0/lib.rs"
+        );
+    }
+
+    #[test]
+    fn prefixes_inside_longer_paths_are_kept() {
+        let text = "/home/u/project2/a /home/user/b /mnt/home/u/proj/c \"/home/u\"";
+        assert_eq!(
+            shorten(text, &prefixes()),
+            "~/project2/a /home/user/b /mnt/home/u/proj/c \"~\""
+        );
+    }
+
+    #[test]
+    fn root_slash_and_missing_prefixes_change_nothing() {
+        let text = "/usr/bin/env /home/u/proj/x";
+        let root = PathPrefixes {
+            root: Some(PathBuf::from("/")),
+            home: None,
+        };
+        assert_eq!(shorten(text, &root), text);
+        assert_eq!(shorten(text, &PathPrefixes::default()), text);
+    }
+
+    #[test]
+    fn apply_shorten_paths_relative_to_the_git_root() {
+        // Tests run from the crate directory, inside the repository.
+        let root = std::env::current_dir()
+            .unwrap()
+            .ancestors()
+            .find(|d| d.join(".git").exists())
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let config: FilterConfig = toml::from_str(
+            r#"
+    command = "test"
+    shorten_paths = true
+
+    [on_failure]
+    output = "{output}"
+    "#,
+        )
+        .unwrap();
+        let result =
+            CommandResult::from_output(&format!("error at {root}/src/lib.rs:3\n  in {root}"), 1);
+        let filtered = crate::filter::apply(&config, &result, &[]);
+        assert_eq!(filtered.output, "error at src/lib.rs:3\n  in .");
+    }
+}
@@ -1,5 +1,7 @@
 use regex::Regex;
 
+use crate::regex_cache;
+
 /// Remove lines matching any of the given patterns.
 ///
 /// Invalid regex patterns are silently dropped. An empty patterns list
@@ -9,7 +11,7 @@ pub fn apply_skip<'a>(patterns: &[String], lines: &[&'a str]) -> Vec<&'a str> {
         return lines.to_vec();
     }
 
-    let compiled: Vec<Regex> = patterns.iter().filter_map(|p| Regex::new(p).ok()).collect();
+    let compiled: Vec<Regex> = regex_cache::compile_all(patterns);
 
     if compiled.is_empty() {
         return lines.to_vec();
@@ -31,7 +33,7 @@ pub fn apply_keep<'a>(patterns: &[String], lines: &[&'a str]) -> Vec<&'a str> {
         return lines.to_vec();
     }
 
-    let compiled: Vec<Regex> = patterns.iter().filter_map(|p| Regex::new(p).ok()).collect();
+    let compiled: Vec<Regex> = regex_cache::compile_all(patterns);
 
     if compiled.is_empty() {
         return lines.to_vec();
@@ -44,10 +46,67 @@ pub fn apply_keep<'a>(patterns: &[String], lines: &[&'a str]) -> Vec<&'a str> {
         .collect()
 }
 
+/// The `skip_summary` footer: how many `lines` the skip `patterns` hide,
+/// grouped by a label taken from each pattern's first word, largest first —
+/// `(1243 lines hidden: 800 compiling, 400 downloading, 43 info)`. Each line
+/// counts for the first pattern it matches. `None` when nothing is hidden.
+pub fn summarize(patterns: &[String], lines: &[&str]) -> Option<String> {
+    let compiled: Vec<(String, Regex)> = patterns
+        .iter()
+        .filter_map(|p| regex_cache::compile(p).map(|re| (label(p), re)))
+        .collect();
+    // (label, count) in first-seen order; the sort below is stable
+    let mut groups: Vec<(&str, usize)> = Vec::new();
+    for line in lines {
+        let Some((name, _)) = compiled.iter().find(|(_, re)| re.is_match(line)) else {
+            continue;
+        };
+        match groups.iter_mut().find(|(g, _)| g == name) {
+            Some(group) => group.1 += 1,
+            None => groups.push((name, 1)),
+        }
+    }
+    let total: usize = groups.iter().map(|(_, n)| n).sum();
+    if total == 0 {
+        return None;
+    }
+    groups.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    let parts: Vec<String> = groups.iter().map(|(g, n)| format!("{n} {g}")).collect();
+    let noun = if total == 1 { "line" } else { "lines" };
+    Some(format!("({total} {noun} hidden: {})", parts.join(", ")))
+}
+
+/// The first word of a pattern's literal text (`^\s+Compiling ` →
+/// `compiling`), or `other` when it has none.
+fn label(pattern: &str) -> String {
+    let mut word = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            // an escape (`\s`, `\d`, `\[`) ends a word and is never part of one
+            chars.next();
+        } else if c.is_alphabetic() {
+            word.push(c);
+            continue;
+        }
+        if word.chars().count() >= 2 {
+            break;
+        }
+        word.clear();
+    }
+    if word.chars().count() >= 2 {
+        word.to_lowercase()
+    } else {
+        "other".to_string()
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
     use super::*;
+    use crate::config::types::FilterConfig;
+    use crate::runner::CommandResult;
 
     #[test]
     fn skip_removes_matching_lines() {
@@ -138,4 +197,69 @@ mod tests {
         let result = apply_skip(&patterns, &lines);
         assert_eq!(result, vec!["d4"]);
     }
+
+    #[test]
+    fn summarize_groups_hidden_lines_by_pattern_word() {
+        let patterns = vec![
+            r"^\s+Compiling ".to_string(),
+            r"^\s+Downloaded ".to_string(),
+            r"^\[INFO\]".to_string(),
+            r"^\s+Downloading ".to_string(),
+        ];
+        let lines = vec![
+            "   Compiling a v1",
+            "  Downloaded b v1",
+            "   Compiling c v1",
+            "error: boom",
+            "[INFO] x",
+            "   Compiling d v1",
+            "  Downloaded e v1",
+        ];
+        assert_eq!(
+            summarize(&patterns, &lines).as_deref(),
+            Some("(6 lines hidden: 3 compiling, 2 downloaded, 1 info)")
+        );
+    }
+
+    #[test]
+    fn summarize_none_when_nothing_hidden() {
+        let patterns = vec!["^debug".to_string()];
+        assert_eq!(summarize(&patterns, &["ok"]), None);
+        assert_eq!(summarize(&[], &["ok"]), None);
+        assert_eq!(
+            summarize(&[r"^\d+%".to_string()], &["50%"]).as_deref(),
+            Some("(1 line hidden: 1 other)")
+        );
+    }
+
+    #[test]
+    fn apply_skip_summary_appends_hidden_counts() {
+        let config: FilterConfig = toml::from_str(
+            r#"
+    command = "test"
+    skip = ["^\\s+Compiling ", "^\\s+Downloaded "]
+    skip_summary = true
+
+    [on_success]
+    output = "ok"
+
+    [on_failure]
+    tail = 5
+    "#,
+        )
+        .unwrap();
+        let input = "  Downloaded a v1\n   Compiling a v1\n   Compiling b v1\nerror: boom";
+        let filtered = crate::filter::apply(&config, &CommandResult::from_output(input, 1), &[]);
+        assert_eq!(
+            filtered.output,
+            "error: boom\n(3 lines hidden: 2 compiling, 1 downloaded)"
+        );
+        let filtered = crate::filter::apply(&config, &CommandResult::from_output(input, 0), &[]);
+        assert_eq!(
+            filtered.output,
+            "ok\n(3 lines hidden: 2 compiling, 1 downloaded)"
+        );
+        let filtered = crate::filter::apply(&config, &CommandResult::from_output("fine", 0), &[]);
+        assert_eq!(filtered.output, "ok");
+    }
 }
@@ -0,0 +1,129 @@
+use super::cleanup::ansi_regex;
+
+/// Non-braille spinner frame sets: circle halves, circle quadrants, squares, blocks.
+const FRAME_CHARS: &[char] = &[
+    '◐', '◓', '◑', '◒', '◴', '◷', '◶', '◵', '◰', '◳', '◲', '◱', '▖', '▘', '▝', '▗',
+];
+
+/// Characters used as spinner animation frames: the whole braille block
+/// (`⠋⠙⠹⠸…`, `⣾⣽⣻…`) plus [`FRAME_CHARS`].
+fn is_spinner_char(c: char) -> bool {
+    ('\u{2800}'..='\u{28FF}').contains(&c) || FRAME_CHARS.contains(&c)
+}
+
+/// Visible text of a line redrawn in place with `\r`: its last non-blank segment.
+fn last_redraw(line: &str) -> &str {
+    line.rsplit('\r')
+        .find(|seg| !seg.trim().is_empty())
+        .unwrap_or("")
+}
+
+/// If the line starts with a spinner frame, return the text after it.
+///
+/// Detection ignores ANSI color codes around the frame; the returned text has
+/// them removed.
+fn strip_frame(line: &str) -> Option<String> {
+    let plain = ansi_regex().replace_all(line, "");
+    let rest = plain.trim_start();
+    let mut chars = rest.chars();
+    if !chars.next().is_some_and(is_spinner_char) {
+        return None;
+    }
+    Some(chars.as_str().trim_start().to_string())
+}
+
+/// Remove spinner animations from captured output.
+///
+/// - `\r` redraws keep only the final visible state of the line
+/// - a leading spinner glyph is stripped from each line
+/// - a run of consecutive spinner lines collapses to its last frame
+///   (frames with no text are dropped entirely)
+pub fn collapse_spinners(lines: &[&str]) -> Vec<String> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut pending: Option<String> = None;
+
+    for &line in lines {
+        let visible = if line.contains('\r') {
+            last_redraw(line)
+        } else {
+            line
+        };
+        if let Some(text) = strip_frame(visible) {
+            pending = Some(text);
+            continue;
+        }
+        if let Some(last) = pending.take()
+            && !last.is_empty()
+        {
+            out.push(last);
+        }
+        out.push(visible.to_string());
+    }
+    if let Some(last) = pending
+        && !last.is_empty()
+    {
+        out.push(last);
+    }
+
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_lines_untouched() {
+        let lines = ["Compiling foo", "", "done | ok"];
+        assert_eq!(collapse_spinners(&lines), lines);
+    }
+
+    #[test]
+    fn braille_run_collapses_to_last_frame() {
+        let lines = [
+            "⠋ Resolving packages",
+            "⠙ Resolving packages",
+            "⠹ Fetching 3/10",
+            "⠸ Fetching 10/10",
+            "Done in 2.1s",
+        ];
+        assert_eq!(
+            collapse_spinners(&lines),
+            vec!["Fetching 10/10", "Done in 2.1s"]
+        );
+    }
+
+    #[test]
+    fn separate_runs_each_keep_last_frame() {
+        let lines = ["◐ build", "◓ build", "built", "⣾ test", "⣽ test 2/2"];
+        assert_eq!(
+            collapse_spinners(&lines),
+            vec!["build", "built", "test 2/2"]
+        );
+    }
+
+    #[test]
+    fn bare_frames_dropped() {
+        let lines = ["⠋", "⠙", "  ⠹  ", "ready"];
+        assert_eq!(collapse_spinners(&lines), vec!["ready"]);
+    }
+
+    #[test]
+    fn carriage_return_redraw_keeps_final_state() {
+        let lines = ["⠋ 1/3\r⠙ 2/3\r⠹ 3/3", "Progress: 10%\rProgress: 100%\r"];
+        assert_eq!(collapse_spinners(&lines), vec!["3/3", "Progress: 100%"]);
+    }
+
+    #[test]
+    fn colored_frame_detected() {
+        let lines = ["\x1b[36m⠋\x1b[39m Linting", "ok"];
+        assert_eq!(collapse_spinners(&lines), vec!["Linting", "ok"]);
+    }
+
+    #[test]
+    fn spinner_char_mid_line_not_stripped() {
+        let lines = ["status: ◐ half"];
+        assert_eq!(collapse_spinners(&lines), vec!["status: ◐ half"]);
+    }
+}
@@ -0,0 +1,519 @@
+//! Stack trace condensing: Python tracebacks, Java and Node `at` traces, Go
+//! panics and Rust panics with their backtraces are reduced to the exception
+//! line(s) and the top in-project frames, most recent first. Library frames
+//! are counted, not shown. Everything outside a trace passes through.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::config::types::StacktraceConfig;
+
+/// In-project frames kept per trace when the config or pipe gives none.
+pub const DEFAULT_FRAMES: usize = 3;
+
+/// Frame text marking library code, for every language.
+const LIBRARY_MARKERS: &[&str] = &[
+    // Python
+    "site-packages/",
+    "dist-packages/",
+    "/lib/python",
+    "<frozen ",
+    // Node
+    "node_modules/",
+    "node:internal",
+    "(internal/",
+    // Go
+    "/go/src/",
+    "/pkg/mod/",
+    // Rust
+    "/rustc/",
+    "/.cargo/registry/",
+];
+
+/// Frame prefixes (after `at ` / the symbol start) marking library code.
+const LIBRARY_PREFIXES: &[&str] = &[
+    // Java and other JVM languages; `java.base/` module names are stripped first
+    "java.",
+    "javax.",
+    "jdk.",
+    "sun.",
+    "com.sun.",
+    "kotlin.",
+    "kotlinx.",
+    "scala.",
+    "org.junit.",
+    "junit.",
+    "org.gradle.",
+    "org.apache.maven.",
+    "org.springframework.",
+    // Go
+    "runtime.",
+    "testing.",
+    // Rust
+    "std::",
+    "core::",
+    "alloc::",
+    "test::",
+    "<std::",
+    "<core::",
+    "<alloc::",
+    "rust_begin_unwind",
+    "__rust",
+];
+
+/// One recognized trace.
+struct Trace {
+    /// Exception or panic line(s).
+    header: Vec<String>,
+    /// One line per frame, most recent first.
+    frames: Vec<Frame>,
+    /// Frames the trace itself elided (Java's "... 12 more").
+    elided: usize,
+}
+
+struct Frame {
+    text: String,
+    library: bool,
+}
+
+/// Condensing options: frames kept per trace and extra library markers.
+pub struct Options<'a> {
+    pub frames: usize,
+    pub library: &'a [String],
+}
+
+impl<'a> Options<'a> {
+    pub fn from_config(config: &'a StacktraceConfig) -> Self {
+        Self {
+            frames: config.frames.unwrap_or(DEFAULT_FRAMES),
+            library: &config.library,
+        }
+    }
+}
+
+/// Condense every stack trace in `lines`; other lines are kept as they are.
+pub fn condense(lines: &[&str], options: &Options<'_>) -> Vec<String> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let parsed = parse_python(lines, i, options)
+            .or_else(|| parse_rust(lines, i, options))
+            .or_else(|| parse_go(lines, i, options))
+            .or_else(|| parse_at_frames(lines, i, options));
+        if let Some((trace, next)) = parsed {
+            render(&trace, options.frames, &mut out);
+            i = next;
+        } else {
+            out.push(lines[i].to_string());
+            i += 1;
+        }
+    }
+    out
+}
+
+fn render(trace: &Trace, keep: usize, out: &mut Vec<String>) {
+    out.extend(trace.header.iter().cloned());
+    let shown: Vec<&Frame> = trace
+        .frames
+        .iter()
+        .filter(|f| !f.library)
+        .take(keep)
+        .collect();
+    out.extend(shown.iter().map(|f| format!("  {}", f.text)));
+    let hidden = trace.frames.len() - shown.len() + trace.elided;
+    if hidden > 0 {
+        let s = if hidden == 1 { "" } else { "s" };
+        out.push(format!("  … {hidden} more frame{s}"));
+    }
+}
+
+fn is_library(text: &str, extra: &[String]) -> bool {
+    let symbol = text.trim_start().trim_start_matches("at ");
+    // Java 9+ module prefix: "java.base/java.lang.Thread.run(...)"
+    let symbol = symbol
+        .split_once('/')
+        .filter(|(module, _)| !module.contains(['(', ' ', ':']))
+        .map_or(symbol, |(_, rest)| rest);
+    LIBRARY_MARKERS.iter().any(|m| text.contains(m))
+        || LIBRARY_PREFIXES.iter().any(|p| symbol.starts_with(p))
+        || extra.iter().any(|m| text.contains(m.as_str()))
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| {
+        // SAFETY: callers pass compile-time constant patterns.
+        #[allow(clippy::expect_used)]
+        Regex::new(pattern).expect("valid stack trace regex")
+    })
+}
+
+/// `Traceback (most recent call last):`, `File "…", line N, in f` frames
+/// with their source lines, then the exception line.
+fn parse_python(lines: &[&str], start: usize, options: &Options<'_>) -> Option<(Trace, usize)> {
+    static FILE: OnceLock<Regex> = OnceLock::new();
+    if lines[start].trim_end() != "Traceback (most recent call last):" {
+        return None;
+    }
+    let file = regex(&FILE, r#"^\s+File "(.+)", line (\d+)(?:, in (.+))?$"#);
+    let mut frames = Vec::new();
+    let mut i = start + 1;
+    while i < lines.len() && lines[i].starts_with(char::is_whitespace) {
+        if let Some(caps) = file.captures(lines[i]) {
+            let path = &caps[1];
+            let text = caps.get(3).map_or_else(
+                || format!("{path}:{}", &caps[2]),
+                |func| format!("{path}:{} in {}", &caps[2], func.as_str()),
+            );
+            frames.push(Frame {
+                library: is_library(path, options.library),
+                text,
+            });
+        }
+        i += 1;
+    }
+    let header = match lines.get(i) {
+        Some(line) if !line.trim().is_empty() => {
+            i += 1;
+            vec![(*line).to_string()]
+        }
+        _ => vec![lines[start].to_string()],
+    };
+    frames.reverse();
+    Some((
+        Trace {
+            header,
+            frames,
+            elided: 0,
+        },
+        i,
+    ))
+}
+
+/// `thread '…' panicked at …`, the panic message, and the `stack backtrace:`
+/// frames (`N: symbol` with an optional `at file:line:col` line) if present.
+/// The `RUST_BACKTRACE` notes are dropped.
+fn parse_rust(lines: &[&str], start: usize, options: &Options<'_>) -> Option<(Trace, usize)> {
+    static FRAME: OnceLock<Regex> = OnceLock::new();
+    static AT: OnceLock<Regex> = OnceLock::new();
+    if !lines[start].starts_with("thread '") || !lines[start].contains("' panicked at ") {
+        return None;
+    }
+    let frame = regex(&FRAME, r"^\s*\d+: (.+)$");
+    let at = regex(&AT, r"^\s+at (.+)$");
+    let is_note = |line: &str| line.starts_with("note: ") || line.trim() == "stack backtrace:";
+
+    let mut header = vec![lines[start].to_string()];
+    let mut i = start + 1;
+    while i < lines.len() && !lines[i].trim().is_empty() && !is_note(lines[i]) {
+        header.push(lines[i].to_string());
+        i += 1;
+    }
+    let mut frames = Vec::new();
+    while i < lines.len() && is_note(lines[i]) {
+        let backtrace = lines[i].trim() == "stack backtrace:";
+        i += 1;
+        if !backtrace {
+            continue;
+        }
+        while let Some(caps) = lines.get(i).and_then(|l| frame.captures(l)) {
+            let symbol = caps[1].trim().to_string();
+            i += 1;
+            let location = lines.get(i).and_then(|l| at.captures(l)).map(|c| {
+                i += 1;
+                c[1].to_string()
+            });
+            let text = location
+                .as_ref()
+                .map_or_else(|| symbol.clone(), |loc| format!("{symbol} at {loc}"));
+            // A frame without a "::" path is a C runtime symbol (`main`, `_start`)
+            let library = is_library(&text, options.library) || !symbol.contains("::");
+            frames.push(Frame { text, library });
+        }
+    }
+    Some((
+        Trace {
+            header,
+            frames,
+            elided: 0,
+        },
+        i,
+    ))
+}
+
+/// `panic: …` / `fatal error: …`, then the goroutine dumps: a function line
+/// followed by a tab-indented `file:line +0x…` line per frame. Only the first
+/// goroutine's frames are kept; later goroutines are counted.
+fn parse_go(lines: &[&str], start: usize, options: &Options<'_>) -> Option<(Trace, usize)> {
+    static GOROUTINE: OnceLock<Regex> = OnceLock::new();
+    static LOCATION: OnceLock<Regex> = OnceLock::new();
+    if !lines[start].starts_with("panic: ") && !lines[start].starts_with("fatal error: ") {
+        return None;
+    }
+    let goroutine = regex(&GOROUTINE, r"^goroutine \d+ \[.*\]:$");
+    let location = regex(&LOCATION, r"^\t(\S+:\d+)(?: \+0x[0-9a-f]+)?$");
+
+    // The message may continue ("[recovered]", "[signal SIGSEGV …]") before
+    // the first goroutine, usually after a blank line.
+    let first = (start + 1..lines.len().min(start + 6)).find(|&j| goroutine.is_match(lines[j]))?;
+    let header: Vec<String> = lines[start..first]
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(ToString::to_string)
+        .collect();
+
+    let mut frames = Vec::new();
+    let mut others = 0;
+    let mut i = first;
+    while i < lines.len() && goroutine.is_match(lines[i]) {
+        let mut seen = 0;
+        i += 1;
+        while i + 1 < lines.len()
+            && !lines[i].starts_with(char::is_whitespace)
+            && !lines[i].trim().is_empty()
+            && let Some(caps) = location.captures(lines[i + 1])
+        {
+            if others == 0 {
+                let func = lines[i].trim_start_matches("created by ");
+                let text = format!("{func} {}", &caps[1]);
+                frames.push(Frame {
+                    library: is_library(&text, options.library),
+                    text,
+                });
+            }
+            seen += 1;
+            i += 2;
+        }
+        if seen == 0 {
+            break;
+        }
+        others += 1;
+        // Goroutine dumps are separated by one blank line
+        if lines.get(i).is_some_and(|l| l.trim().is_empty())
+            && lines.get(i + 1).is_some_and(|l| goroutine.is_match(l))
+        {
+            i += 1;
+        }
+    }
+    let mut header = header;
+    if others > 1 {
+        let s = if others == 2 { "" } else { "s" };
+        header.push(format!("({} other goroutine{s})", others - 1));
+    }
+    Some((
+        Trace {
+            header,
+            frames,
+            elided: 0,
+        },
+        i,
+    ))
+}
+
+/// A header line directly followed by indented `at …` frames: Java (and
+/// Kotlin/Scala) `at pkg.Class.method(File.java:12)`, with `... N more`, and
+/// Node `at fn (/path/file.js:12:5)`. `Caused by:` starts the next trace.
+fn parse_at_frames(lines: &[&str], start: usize, options: &Options<'_>) -> Option<(Trace, usize)> {
+    static AT: OnceLock<Regex> = OnceLock::new();
+    static MORE: OnceLock<Regex> = OnceLock::new();
+    // Java `Class.method(File.java:12)` / `(Native Method)`; Node
+    // `fn (/path:12:5)`, `new Promise (<anonymous>)`, `/path:12:5`
+    let at = regex(
+        &AT,
+        r"^\s+at (?:[\w$.<>/-]+\([^()]*\)|.+ \((?:\S+:\d+:\d+|<anonymous>|index \d+)\)|(?:async )?\S+:\d+:\d+)$",
+    );
+    let more = regex(&MORE, r"^\s+\.\.\. (\d+) more$");
+    let header = lines[start];
+    if header.trim().is_empty()
+        || at.is_match(header)
+        || !lines.get(start + 1).is_some_and(|l| at.is_match(l))
+    {
+        return None;
+    }
+    let mut frames = Vec::new();
+    let mut elided = 0;
+    let mut i = start + 1;
+    while let Some(line) = lines.get(i) {
+        if at.is_match(line) {
+            let text = line.trim().to_string();
+            frames.push(Frame {
+                library: is_library(&text, options.library),
+                text,
+            });
+        } else if let Some(caps) = more.captures(line) {
+            elided += caps[1].parse::<usize>().unwrap_or(0);
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    Some((
+        Trace {
+            header: vec![header.to_string()],
+            frames,
+            elided,
+        },
+        i,
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::config::types::FilterConfig;
+    use crate::runner::CommandResult;
+
+    fn run(text: &str, frames: usize) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+        condense(
+            &lines,
+            &Options {
+                frames,
+                library: &[],
+            },
+        )
+        .join("\n")
+    }
+
+    #[test]
+    fn python_keeps_exception_and_innermost_project_frames() {
+        let text = r#"before
+Traceback (most recent call last):
+  File "/home/u/app/cli.py", line 42, in main
+    run(args)
+  File "/home/u/app/jobs.py", line 10, in run
+    return load(path)
+  File "/usr/lib/python3.12/site-packages/yaml/__init__.py", line 81, in load
+    return loader.get_single_data()
+ValueError: bad document
+after"#;
+        assert_eq!(
+            run(text, 3),
+            "before\nValueError: bad document\n  /home/u/app/jobs.py:10 in run\n  \
+             /home/u/app/cli.py:42 in main\n  … 1 more frame\nafter"
+        );
+    }
+
+    #[test]
+    fn java_counts_library_and_elided_frames() {
+        let text = "Exception in thread \"main\" java.lang.IllegalStateException: boom
+\tat com.acme.App.run(App.java:42)
+\tat java.base/java.util.ArrayList.forEach(ArrayList.java:1596)
+\tat com.acme.App.main(App.java:10)
+Caused by: java.io.IOException: disk full
+\tat com.acme.Store.write(Store.java:88)
+\t... 3 more";
+        assert_eq!(
+            run(text, 1),
+            "Exception in thread \"main\" java.lang.IllegalStateException: boom\n  \
+             at com.acme.App.run(App.java:42)\n  … 2 more frames\n\
+             Caused by: java.io.IOException: disk full\n  \
+             at com.acme.Store.write(Store.java:88)\n  … 3 more frames"
+        );
+    }
+
+    #[test]
+    fn node_skips_node_modules_and_internals() {
+        let text = "TypeError: Cannot read properties of undefined (reading 'id')
+    at handler (/app/src/routes/user.js:12:20)
+    at Layer.handle [as handle_request] (/app/node_modules/express/lib/router/layer.js:95:5)
+    at process.processTicksAndRejections (node:internal/process/task_queues:95:5)";
+        assert_eq!(
+            run(text, 3),
+            "TypeError: Cannot read properties of undefined (reading 'id')\n  \
+             at handler (/app/src/routes/user.js:12:20)\n  … 2 more frames"
+        );
+    }
+
+    #[test]
+    fn go_keeps_first_goroutine_project_frames() {
+        let text = "panic: runtime error: index out of range [3] with length 3
+
+goroutine 1 [running]:
+main.pick(...)
+\t/home/u/proj/main.go:12
+main.main()
+\t/home/u/proj/main.go:20 +0x1d
+
+goroutine 6 [chan receive]:
+main.worker()
+\t/home/u/proj/worker.go:8 +0x2a
+exit status 2";
+        assert_eq!(
+            run(text, 3),
+            "panic: runtime error: index out of range [3] with length 3\n\
+             (1 other goroutine)\n  main.pick(...) /home/u/proj/main.go:12\n  \
+             main.main() /home/u/proj/main.go:20\nexit status 2"
+        );
+    }
+
+    #[test]
+    fn rust_drops_notes_and_std_frames() {
+        let text = "thread 'main' panicked at src/parse.rs:10:5:
+index out of bounds: the len is 3 but the index is 10
+stack backtrace:
+   0: rust_begin_unwind
+             at /rustc/abc/library/std/src/panicking.rs:645:5
+   1: myapp::parse
+             at ./src/parse.rs:10:5
+   2: myapp::main
+             at ./src/main.rs:4:5
+   3: core::ops::function::FnOnce::call_once
+   4: main
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+
+next";
+        assert_eq!(
+            run(text, 1),
+            "thread 'main' panicked at src/parse.rs:10:5:\n\
+             index out of bounds: the len is 3 but the index is 10\n  \
+             myapp::parse at ./src/parse.rs:10:5\n  … 4 more frames\n\nnext"
+        );
+
+        let short = "thread 'tests::x' panicked at src/lib.rs:3:9:
+assertion failed
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace";
+        assert_eq!(
+            run(short, 3),
+            "thread 'tests::x' panicked at src/lib.rs:3:9:\nassertion failed"
+        );
+    }
+
+    #[test]
+    fn plain_output_is_unchanged() {
+        let text = "compiling\n  at the end of the day\nok";
+        assert_eq!(run(text, 3), text);
+    }
+
+    #[test]
+    fn apply_stacktrace_condenses_output_and_fallback() {
+        // Stage 2.7 works on the pre-filtered lines, so the fallback tail sees it too.
+        let config: FilterConfig = toml::from_str(
+            r#"
+    command = "test"
+    skip = ["^debug:"]
+
+    [stacktrace]
+    frames = 1
+    library = ["com.acme.generated."]
+
+    [fallback]
+    tail = 10
+    "#,
+        )
+        .unwrap();
+        let result = CommandResult::from_output(
+            "debug: start\njava.lang.IllegalStateException: boom\n\
+             \tat com.acme.generated.Proxy.run(Proxy.java:3)\n\
+             \tat com.acme.App.run(App.java:42)\n\
+             \tat com.acme.App.main(App.java:10)",
+            1,
+        );
+        let filtered = crate::filter::apply(&config, &result, &[]);
+        assert_eq!(
+            filtered.output,
+            "java.lang.IllegalStateException: boom\n  at com.acme.App.run(App.java:42)\n  … 2 more frames"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! `{var | filter: "git/diff"}`: run a variable's content through another
+//! filter, so a composed filter can reuse one that already exists.
+//!
+//! Filters are looked up by name (`tokf show` style) among the active ones,
+//! discovered once per process. A chain of sub-filters deeper than
+//! [`MAX_NESTING`] (a filter that pipes into itself, say) stops and passes the
+//! text through.
+
+use std::cell::Cell;
+use std::sync::OnceLock;
+
+use crate::config::{self, ResolvedFilter};
+use crate::runner::CommandResult;
+
+/// How many sub-filters may run inside one another.
+const MAX_NESTING: usize = 4;
+
+thread_local! {
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// `text` as the filter `name` renders it for a run that exited
+/// `exit_code`, or `None` when no such filter is active or the nesting limit
+/// is reached.
+pub fn apply_named(name: &str, text: &str, exit_code: i32) -> Option<String> {
+    let filters = active_filters();
+    let Some(found) = config::find_by_name(filters, name) else {
+        tracing::warn!("filter pipe: no filter named \"{name}\"");
+        return None;
+    };
+    let depth = NESTING.get();
+    if depth >= MAX_NESTING {
+        tracing::warn!("filter pipe: \"{name}\" nested too deeply, passing text through");
+        return None;
+    }
+    NESTING.set(depth + 1);
+    let result = CommandResult::from_output(text, exit_code);
+    let output = super::apply(&found.config, &result, &[]).output;
+    NESTING.set(depth);
+    Some(output)
+}
+
+fn active_filters() -> &'static [ResolvedFilter] {
+    static FILTERS: OnceLock<Vec<ResolvedFilter>> = OnceLock::new();
+    FILTERS.get_or_init(|| {
+        let include_builtin = config::settings::load_settings().builtins_enabled();
+        config::cache::discover_with_cache(&config::default_search_dirs(), include_builtin)
+            .unwrap_or_else(|e| {
+                tracing::warn!("filter pipe: filter discovery failed: {e:#}");
+                Vec::new()
+            })
+    })
+}
+
+#[cfg(all(test, feature = "stdlib"))]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_a_builtin_filter_by_name() {
+        assert_eq!(apply_named("git/add", "", 0).as_deref(), Some("ok ✓"));
+        assert_eq!(
+            apply_named("git/add.toml", "fatal: pathspec 'x' did not match", 128).as_deref(),
+            Some("✗ fatal: pathspec 'x' did not match")
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_none() {
+        assert_eq!(apply_named("no/such-filter", "text", 0), None);
+    }
+
+    #[test]
+    fn stops_at_the_nesting_limit() {
+        NESTING.set(MAX_NESTING);
+        assert_eq!(apply_named("git/add", "", 0), None);
+        NESTING.set(0);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::config::types::AdaptiveTail;
+use crate::regex_cache;
+
+/// Number of trailing lines an adaptive tail keeps from `lines`.
+///
+/// The window covers at least `min` lines and extends back to the `matches`-th
+/// matching line from the end (or the earliest match when `matches` is unset).
+/// When that line lies beyond `max`, the window stops at `max`. Output with
+/// no matching lines, or an invalid `pattern`, keeps just `min` lines.
+pub fn adaptive_len(rule: &AdaptiveTail, lines: &[&str]) -> usize {
+    let max = rule.max.max(rule.min);
+    let Some(re) = regex_cache::compile(&rule.pattern) else {
+        return rule.min;
+    };
+
+    let wanted = rule.matches.unwrap_or(usize::MAX);
+    let mut found = 0;
+    let mut needed = rule.min;
+    for (distance, line) in lines.iter().rev().enumerate() {
+        if found >= wanted {
+            break;
+        }
+        if re.is_match(line) {
+            if distance >= max {
+                return max;
+            }
+            found += 1;
+            needed = needed.max(distance + 1);
+        }
+    }
+    needed.min(max)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn rule(min: usize, max: usize, matches: Option<usize>) -> AdaptiveTail {
+        AdaptiveTail {
+            min,
+            max,
+            pattern: "error".to_string(),
+            matches,
+        }
+    }
+
+    fn numbered(errors_at: &[usize], total: usize) -> Vec<String> {
+        (0..total)
+            .map(|i| {
+                if errors_at.contains(&i) {
+                    format!("error {i}")
+                } else {
+                    format!("line {i}")
+                }
+            })
+            .collect()
+    }
+
+    fn len_for(rule: &AdaptiveTail, lines: &[String]) -> usize {
+        let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+        adaptive_len(rule, &refs)
+    }
+
+    #[test]
+    fn quiet_output_keeps_min() {
+        let lines = numbered(&[], 100);
+        assert_eq!(len_for(&rule(5, 40, None), &lines), 5);
+    }
+
+    #[test]
+    fn grows_to_earliest_match() {
+        // Errors at indices 80 and 90 of 100 → 20 lines reach the earlier one.
+        let lines = numbered(&[80, 90], 100);
+        assert_eq!(len_for(&rule(5, 40, None), &lines), 20);
+    }
+
+    #[test]
+    fn capped_at_max() {
+        let lines = numbered(&[10, 95], 100);
+        assert_eq!(len_for(&rule(5, 40, None), &lines), 40);
+        assert_eq!(len_for(&rule(5, 40, Some(1)), &lines), 5);
+    }
+
+    #[test]
+    fn stops_after_enough_matches() {
+        let lines = numbered(&[60, 80, 90], 100);
+        assert_eq!(len_for(&rule(5, 40, Some(2)), &lines), 20);
+    }
+
+    #[test]
+    fn matches_inside_min_do_not_shrink() {
+        let lines = numbered(&[98], 100);
+        assert_eq!(len_for(&rule(5, 40, None), &lines), 5);
+    }
+
+    #[test]
+    fn invalid_pattern_keeps_min() {
+        let mut bad = rule(5, 40, None);
+        bad.pattern = "(unclosed".to_string();
+        assert_eq!(len_for(&bad, &numbered(&[90], 100)), 5);
+    }
+}
@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use super::find_expressions;
+
+/// How block tags are evaluated and plain text is rendered.
+pub trait Scope {
+    /// Whether a `{#if}` / `{#unless}` condition holds.
+    fn truthy(&mut self, cond: &str) -> bool;
+    /// Render `body` once per item of `expr`, or `otherwise` when there are none.
+    fn each(&mut self, expr: &str, body: &str, otherwise: &str) -> String;
+    /// Render text outside any tag (expression substitution).
+    fn render(&mut self, text: &str) -> String;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    If,
+    Unless,
+    Each,
+}
+
+/// A block tag: `{#if cond}`, `{#unless cond}`, `{#each expr}`, `{#else}`, or a close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag<'a> {
+    Open(Kind, &'a str),
+    Else,
+    Close(Kind),
+}
+
+/// Which branch of a block is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Branch {
+    Then,
+    Else,
+    /// Neither branch is emitted as-is (enclosing block skipped, or a loop).
+    Neither,
+}
+
+/// Where an open `{#each}` block's parts are, to hand over at `{/each}`.
+struct Loop<'a> {
+    expr: &'a str,
+    /// Whether the enclosing block is emitting.
+    render: bool,
+    body_start: usize,
+    else_span: Option<Range<usize>>,
+}
+
+/// One open block while expanding.
+struct Frame<'a> {
+    kind: Kind,
+    selected: Branch,
+    in_else: bool,
+    each: Option<Loop<'a>>,
+}
+
+impl Frame<'_> {
+    fn emitting(&self) -> bool {
+        let current = if self.in_else {
+            Branch::Else
+        } else {
+            Branch::Then
+        };
+        self.selected == current
+    }
+}
+
+/// Expand `{#if}` / `{#unless}` / `{#each}` blocks and render the rest via `scope`.
+///
+/// Conditions are only evaluated for blocks whose enclosing block is emitting.
+/// A tag alone on its line takes the whole line with it, so block markup does
+/// not leave blank lines behind. Unbalanced tags disable block handling and
+/// the whole template is rendered as plain text.
+pub fn expand_blocks(template: &str, scope: &mut impl Scope) -> String {
+    let tags = find_tags(template);
+    if tags.is_empty() {
+        return scope.render(template);
+    }
+
+    let mut out = String::with_capacity(template.len());
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pos = 0;
+    for (span, tag) in tags {
+        if stack.last().is_none_or(Frame::emitting) {
+            out.push_str(&scope.render(&template[pos..span.start]));
+        }
+        pos = span.end;
+        let ok = match tag {
+            Tag::Open(kind, arg) => {
+                stack.push(open(&stack, kind, arg, span.end, scope));
+                true
+            }
+            Tag::Else => else_tag(&mut stack, span),
+            Tag::Close(kind) => match stack.pop() {
+                Some(frame) if frame.kind == kind => {
+                    if let Some(each) = frame.each.filter(|l| l.render) {
+                        out.push_str(&render_loop(template, &each, span.start, scope));
+                    }
+                    true
+                }
+                _ => false,
+            },
+        };
+        if !ok {
+            return scope.render(template);
+        }
+    }
+    if !stack.is_empty() {
+        return scope.render(template);
+    }
+    out.push_str(&scope.render(&template[pos..]));
+    out
+}
+
+fn open<'a>(
+    stack: &[Frame<'_>],
+    kind: Kind,
+    arg: &'a str,
+    body_start: usize,
+    scope: &mut impl Scope,
+) -> Frame<'a> {
+    let parent = stack.last().is_none_or(Frame::emitting);
+    let selected = match kind {
+        _ if !parent => Branch::Neither,
+        Kind::Each => Branch::Neither,
+        _ if scope.truthy(arg) != (kind == Kind::Unless) => Branch::Then,
+        _ => Branch::Else,
+    };
+    let each = (kind == Kind::Each).then_some(Loop {
+        expr: arg,
+        render: parent,
+        body_start,
+        else_span: None,
+    });
+    Frame {
+        kind,
+        selected,
+        in_else: false,
+        each,
+    }
+}
+
+/// Switch the innermost block to its `{#else}` branch. `false` if there is none.
+fn else_tag(stack: &mut [Frame<'_>], span: Range<usize>) -> bool {
+    let Some(frame) = stack.last_mut().filter(|f| !f.in_else) else {
+        return false;
+    };
+    frame.in_else = true;
+    if let Some(each) = &mut frame.each {
+        each.else_span = Some(span);
+    }
+    true
+}
+
+fn render_loop(template: &str, each: &Loop<'_>, close_at: usize, scope: &mut impl Scope) -> String {
+    let (body, otherwise) = each.else_span.as_ref().map_or_else(
+        || (&template[each.body_start..close_at], ""),
+        |e| {
+            (
+                &template[each.body_start..e.start],
+                &template[e.end..close_at],
+            )
+        },
+    );
+    scope.each(each.expr, body, otherwise)
+}
+
+/// Top-level block tags with the byte range each one occupies.
+fn find_tags(template: &str) -> Vec<(Range<usize>, Tag<'_>)> {
+    find_expressions(template)
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let tag = parse_tag(&template[start + 1..end - 1])?;
+            Some((standalone_span(template, start, end), tag))
+        })
+        .collect()
+}
+
+fn parse_tag(inner: &str) -> Option<Tag<'_>> {
+    let inner = inner.trim();
+    let (head, rest) = inner
+        .split_once(char::is_whitespace)
+        .map_or((inner, ""), |(h, r)| (h, r.trim()));
+    match (head, rest.is_empty()) {
+        ("#if", false) => Some(Tag::Open(Kind::If, rest)),
+        ("#unless", false) => Some(Tag::Open(Kind::Unless, rest)),
+        ("#each", false) => Some(Tag::Open(Kind::Each, rest)),
+        ("#else", true) => Some(Tag::Else),
+        ("/if", true) => Some(Tag::Close(Kind::If)),
+        ("/unless", true) => Some(Tag::Close(Kind::Unless)),
+        ("/each", true) => Some(Tag::Close(Kind::Each)),
+        _ => None,
+    }
+}
+
+/// Widen `start..end` to the full line (newline included) when the tag is
+/// the only thing on it apart from whitespace.
+fn standalone_span(template: &str, start: usize, end: usize) -> Range<usize> {
+    let line_start = template[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = template[end..]
+        .find('\n')
+        .map_or(template.len(), |i| end + i + 1);
+    let alone =
+        template[line_start..start].trim().is_empty() && template[end..line_end].trim().is_empty();
+    if alone {
+        line_start..line_end
+    } else {
+        start..end
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    /// Conditions are true when listed; loops repeat their body once per
+    /// comma-separated item of the expression, substituting `@`; text is
+    /// left as-is.
+    struct Fake<'a> {
+        true_vars: &'a [&'a str],
+        seen: Vec<String>,
+    }
+
+    impl Scope for Fake<'_> {
+        fn truthy(&mut self, cond: &str) -> bool {
+            self.seen.push(cond.to_string());
+            self.true_vars.contains(&cond)
+        }
+
+        fn each(&mut self, expr: &str, body: &str, otherwise: &str) -> String {
+            let items: Vec<&str> = expr.split(',').filter(|s| !s.is_empty()).collect();
+            if items.is_empty() {
+                return otherwise.to_string();
+            }
+            items.iter().map(|i| body.replace('@', i)).collect()
+        }
+
+        fn render(&mut self, text: &str) -> String {
+            text.to_string()
+        }
+    }
+
+    fn fake<'a>(true_vars: &'a [&'a str]) -> Fake<'a> {
+        Fake {
+            true_vars,
+            seen: Vec::new(),
+        }
+    }
+
+    fn expand(template: &str, true_vars: &[&str]) -> String {
+        expand_blocks(template, &mut fake(true_vars))
+    }
+
+    #[test]
+    fn if_else_selects_branch() {
+        let t = "{#if a}yes{#else}no{/if}";
+        assert_eq!(expand(t, &["a"]), "yes");
+        assert_eq!(expand(t, &[]), "no");
+    }
+
+    #[test]
+    fn unless_inverts_condition() {
+        let t = "{#unless a}none{#else}some{/unless}";
+        assert_eq!(expand(t, &["a"]), "some");
+        assert_eq!(expand(t, &[]), "none");
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let t = "{#if a}A{#if b}B{/if}{#else}{#if b}b{#else}-{/if}{/if}.";
+        assert_eq!(expand(t, &["a", "b"]), "AB.");
+        assert_eq!(expand(t, &["a"]), "A.");
+        assert_eq!(expand(t, &["b"]), "b.");
+        assert_eq!(expand(t, &[]), "-.");
+    }
+
+    #[test]
+    fn conditions_inside_skipped_branches_are_not_evaluated() {
+        let mut scope = fake(&[]);
+        let out = expand_blocks("{#if a}{#if b}x{/if}{/if}", &mut scope);
+        assert_eq!(out, "");
+        assert_eq!(scope.seen, ["a"]);
+    }
+
+    #[test]
+    fn standalone_tags_take_their_line() {
+        let t = "head\n  {#if a}\nbody\n{/if}\ntail";
+        assert_eq!(expand(t, &["a"]), "head\nbody\ntail");
+        assert_eq!(expand(t, &[]), "head\ntail");
+    }
+
+    #[test]
+    fn inline_tags_keep_surrounding_text() {
+        assert_eq!(expand("a {#if x}b{/if} c", &[]), "a  c");
+    }
+
+    #[test]
+    fn each_hands_body_and_else_to_scope() {
+        assert_eq!(expand("[{#each x,y}<@>{/each}]", &[]), "[<x><y>]");
+        assert_eq!(expand("{#each ,}@{#else}empty{/each}", &[]), "empty");
+        assert_eq!(
+            expand("{#each x,y}\n- @\n{/each}\ndone", &[]),
+            "- x\n- y\ndone"
+        );
+    }
+
+    #[test]
+    fn each_body_tags_are_left_to_the_scope() {
+        let mut scope = fake(&["a"]);
+        let out = expand_blocks("{#each x}{#if a}@{/if}{/each}", &mut scope);
+        assert_eq!(out, "{#if a}x{/if}");
+        assert!(scope.seen.is_empty());
+    }
+
+    #[test]
+    fn each_in_skipped_branch_is_dropped() {
+        assert_eq!(expand("{#if a}{#each x}@{/each}{/if}!", &[]), "!");
+    }
+
+    #[test]
+    fn unbalanced_tags_render_as_plain_text() {
+        for t in [
+            "{#if a}x",
+            "x{/if}",
+            "{#if a}x{/unless}",
+            "{#if a}{#else}{#else}{/if}",
+            "{#each a}x{/if}",
+        ] {
+            assert_eq!(expand(t, &["a"]), t);
+        }
+    }
+
+    #[test]
+    fn non_tags_are_ignored() {
+        assert_eq!(
+            expand("{#if}{value}{#else x}", &[]),
+            "{#if}{value}{#else x}"
+        );
+    }
+}
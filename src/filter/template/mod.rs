@@ -1,6 +1,9 @@
+mod blocks;
+mod pipes;
+
 use std::collections::HashMap;
 
-use regex::Regex;
+use crate::regex_cache;
 
 use super::section::SectionMap;
 
@@ -11,6 +14,9 @@ const MAX_DEPTH: usize = 3;
 ///
 /// Variables are looked up first in `vars` (string values), then in `sections`
 /// (collection values). Pipe operations transform the resolved value.
+/// `{#if expr}…{#else}…{/if}` and `{#unless expr}…{/unless}` keep or drop
+/// text (see [`is_truthy`]); `{#each expr}…{#else}…{/each}` repeats its body
+/// per item (see [`item_vars`]).
 pub fn render_template(
     template: &str,
     vars: &HashMap<String, String>,
@@ -28,17 +34,33 @@ fn render_template_inner(
     if depth >= MAX_DEPTH {
         return template.to_string();
     }
+    blocks::expand_blocks(
+        template,
+        &mut BlockScope {
+            vars,
+            sections,
+            depth,
+        },
+    )
+}
 
-    let expressions = find_expressions(template);
+/// Substitute every top-level `{...}` expression in `text`.
+fn render_expressions(
+    text: &str,
+    vars: &HashMap<String, String>,
+    sections: &SectionMap,
+    depth: usize,
+) -> String {
+    let expressions = find_expressions(text);
     if expressions.is_empty() {
-        return template.to_string();
+        return text.to_string();
     }
 
-    let mut result = template.to_string();
+    let mut result = text.to_string();
 
     // Process right-to-left to preserve offsets
     for (start, end) in expressions.into_iter().rev() {
-        let inner = &template[start + 1..end - 1]; // strip { }
+        let inner = &text[start + 1..end - 1]; // strip { }
         let replacement = evaluate_expression(inner, vars, sections, depth);
         result.replace_range(start..end, &replacement);
     }
@@ -46,6 +68,73 @@ fn render_template_inner(
     result
 }
 
+/// Evaluates block tags against the variables of one render call.
+struct BlockScope<'a> {
+    vars: &'a HashMap<String, String>,
+    sections: &'a SectionMap,
+    depth: usize,
+}
+
+impl blocks::Scope for BlockScope<'_> {
+    fn truthy(&mut self, cond: &str) -> bool {
+        is_truthy(&evaluate_value(cond, self.vars, self.sections, self.depth))
+    }
+
+    /// Each item renders `body` with the per-item vars from [`item_vars`].
+    fn each(&mut self, expr: &str, body: &str, otherwise: &str) -> String {
+        let items = into_items(evaluate_value(expr, self.vars, self.sections, self.depth));
+        if items.is_empty() {
+            return render_template_inner(otherwise, self.vars, self.sections, self.depth);
+        }
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let local_vars = item_vars(self.vars, i, item);
+                render_template_inner(body, &local_vars, self.sections, self.depth + 1)
+            })
+            .collect()
+    }
+
+    fn render(&mut self, text: &str) -> String {
+        render_expressions(text, self.vars, self.sections, self.depth)
+    }
+}
+
+/// Variables visible inside `{#each}`: `index` (1-based), `value`,
+/// `first_line`, `rest` (lines after the first), `line_count`, and `name` —
+/// the first word of the first line once `-`, `=`, `_`, `*` and `#`
+/// decoration is trimmed (`---- tests::foo stdout ----` → `tests::foo`).
+fn item_vars(vars: &HashMap<String, String>, i: usize, item: &str) -> HashMap<String, String> {
+    let (first_line, rest) = item.split_once('\n').unwrap_or((item, ""));
+    let name = first_line
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '=' | '_' | '*' | '#'))
+        .split_whitespace()
+        .next()
+        .unwrap_or("");
+    let mut local_vars = vars.clone();
+    for (key, value) in [
+        ("index", (i + 1).to_string()),
+        ("value", item.to_string()),
+        ("first_line", first_line.to_string()),
+        ("rest", rest.to_string()),
+        ("line_count", item.lines().count().to_string()),
+        ("name", name.to_string()),
+    ] {
+        local_vars.insert(key.to_string(), value);
+    }
+    local_vars
+}
+
+/// Items to iterate: a collection as-is, a non-empty string as one item.
+fn into_items(value: Value) -> Vec<String> {
+    match value {
+        Value::Collection(items) => items,
+        Value::Str(s) if s.is_empty() => Vec::new(),
+        Value::Str(s) => vec![s],
+    }
+}
+
 /// Find top-level `{...}` expression spans, handling nested braces and quotes.
 /// Returns (start, end) byte offsets where end is exclusive (points after `}`).
 fn find_expressions(template: &str) -> Vec<(usize, usize)> {
@@ -99,6 +188,7 @@ fn find_matching_close(bytes: &[u8], start: usize) -> Option<usize> {
 }
 
 /// Resolved value — either a single string or a collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Value {
     Str(String),
     Collection(Vec<String>),
@@ -111,6 +201,27 @@ fn evaluate_expression(
     sections: &SectionMap,
     depth: usize,
 ) -> String {
+    match evaluate_value(expr, vars, sections, depth) {
+        Value::Str(s) => s,
+        Value::Collection(items) => items.join(", "),
+    }
+}
+
+/// Block conditions: empty strings, `0`, `false` and empty collections are falsy.
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Str(s) => !matches!(s.trim(), "" | "0" | "false"),
+        Value::Collection(items) => !items.is_empty(),
+    }
+}
+
+/// Resolve the variable and apply the pipe chain, keeping the value's shape.
+fn evaluate_value(
+    expr: &str,
+    vars: &HashMap<String, String>,
+    sections: &SectionMap,
+    depth: usize,
+) -> Value {
     let parts = split_pipes(expr);
     let var_part = parts[0].trim();
     let pipes = &parts[1..];
@@ -122,12 +233,7 @@ fn evaluate_expression(
     for pipe_str in pipes {
         value = apply_pipe(pipe_str.trim(), value, vars, sections, depth);
     }
-
-    // Convert final value to string
-    match value {
-        Value::Str(s) => s,
-        Value::Collection(items) => items.join(", "),
-    }
+    value
 }
 
 /// Split an expression on top-level `|` (not inside quotes or nested braces).
@@ -170,8 +276,8 @@ fn resolve_variable(name: &str, vars: &HashMap<String, String>, sections: &Secti
             return Value::Str(section_data.count().to_string());
         }
 
-        // Unknown property → empty
-        return Value::Str(String::new());
+        // Dotted variables such as `flags.release`; otherwise empty
+        return Value::Str(vars.get(name).cloned().unwrap_or_default());
     }
 
     // Plain variable: check vars first, then sections
@@ -198,8 +304,17 @@ fn apply_pipe(
         apply_join(arg.trim(), value)
     } else if let Some(arg) = pipe.strip_prefix("each:") {
         apply_each(arg.trim(), value, vars, sections, depth)
-    } else if let Some(arg) = pipe.strip_prefix("truncate:") {
+    } else if let Some(arg) = pipe
+        .strip_prefix("truncate:")
+        .or_else(|| pipe.strip_prefix("truncate_chars:"))
+    {
         apply_truncate(arg.trim(), value)
+    } else if let Some(arg) = pipe.strip_prefix("sub:") {
+        pipes::sub(arg, value)
+    } else if let Some(arg) = pipe.strip_prefix("group:") {
+        pipes::group(arg, value)
+    } else if let Some(arg) = pipe.strip_prefix("filter:") {
+        pipes::filter(arg, value)
     } else if pipe == "lines" {
         apply_lines(value)
     } else if let Some(arg) = pipe
@@ -208,7 +323,11 @@ fn apply_pipe(
     {
         apply_keep_pipe(arg.trim(), value)
     } else {
-        value // unknown pipe → passthrough
+        let (name, arg) = pipe
+            .split_once(':')
+            .map_or((pipe, None), |(n, a)| (n.trim(), Some(a)));
+        // unknown pipe → passthrough
+        pipes::apply_simple(name, arg, value).unwrap_or_else(|v| v)
     }
 }
 
@@ -231,16 +350,7 @@ fn apply_each(
     depth: usize,
 ) -> Value {
     let tmpl = parse_string_arg(arg);
-
-    let items = match value {
-        Value::Collection(items) => items,
-        Value::Str(s) => {
-            if s.is_empty() {
-                return Value::Collection(Vec::new());
-            }
-            vec![s]
-        }
-    };
+    let items = into_items(value);
 
     let mapped: Vec<String> = items
         .iter()
@@ -307,7 +417,7 @@ fn apply_lines(value: Value) -> Value {
 /// Strings and invalid patterns pass through unchanged.
 fn apply_keep_pipe(arg: &str, value: Value) -> Value {
     let pattern = parse_string_arg(arg);
-    let Ok(re) = Regex::new(&pattern) else {
+    let Some(re) = regex_cache::compile(&pattern) else {
         return value;
     };
     match value {
@@ -357,310 +467,4 @@ fn unescape(s: &str) -> String {
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
-mod tests {
-    use crate::filter::section::SectionData;
-
-    use super::*;
-
-    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
-        pairs
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect()
-    }
-
-    fn sections_with(name: &str, items: Vec<&str>) -> SectionMap {
-        let mut map = SectionMap::new();
-        map.insert(
-            name.to_string(),
-            SectionData {
-                lines: items.into_iter().map(String::from).collect(),
-                blocks: Vec::new(),
-            },
-        );
-        map
-    }
-
-    fn sections_with_blocks(name: &str, blocks: Vec<&str>) -> SectionMap {
-        let mut map = SectionMap::new();
-        map.insert(
-            name.to_string(),
-            SectionData {
-                lines: Vec::new(),
-                blocks: blocks.into_iter().map(String::from).collect(),
-            },
-        );
-        map
-    }
-
-    #[test]
-    fn simple_variable_substitution() {
-        let v = vars(&[("name", "world")]);
-        assert_eq!(
-            render_template("hello {name}!", &v, &SectionMap::new()),
-            "hello world!"
-        );
-    }
-
-    #[test]
-    fn unknown_variable_empty_string() {
-        let v = HashMap::new();
-        assert_eq!(
-            render_template("hello {unknown}!", &v, &SectionMap::new()),
-            "hello !"
-        );
-    }
-
-    #[test]
-    fn property_access_count() {
-        let s = sections_with("items", vec!["a", "b", "c"]);
-        assert_eq!(
-            render_template("count: {items.count}", &HashMap::new(), &s),
-            "count: 3"
-        );
-    }
-
-    #[test]
-    fn join_with_separator() {
-        let s = sections_with("lines", vec!["a", "b", "c"]);
-        assert_eq!(
-            render_template("{lines | join: \", \"}", &HashMap::new(), &s),
-            "a, b, c"
-        );
-    }
-
-    #[test]
-    fn join_with_newline() {
-        let s = sections_with("lines", vec!["a", "b"]);
-        assert_eq!(
-            render_template("{lines | join: \"\\n\"}", &HashMap::new(), &s),
-            "a\nb"
-        );
-    }
-
-    #[test]
-    fn each_with_index_and_value() {
-        let s = sections_with("items", vec!["foo", "bar"]);
-        assert_eq!(
-            render_template(
-                "{items | each: \"{index}. {value}\" | join: \", \"}",
-                &HashMap::new(),
-                &s
-            ),
-            "1. foo, 2. bar"
-        );
-    }
-
-    #[test]
-    fn each_with_truncate_nested() {
-        let s = sections_with_blocks("blocks", vec!["short", "this is a rather long string"]);
-        assert_eq!(
-            render_template(
-                "{blocks | each: \"{value | truncate: 10}\" | join: \"; \"}",
-                &HashMap::new(),
-                &s
-            ),
-            "short; this is a ...",
-        );
-    }
-
-    #[test]
-    fn truncate_short_string_unchanged() {
-        let v = vars(&[("msg", "short")]);
-        assert_eq!(
-            render_template("{msg | truncate: 100}", &v, &SectionMap::new()),
-            "short"
-        );
-    }
-
-    #[test]
-    fn truncate_long_string_truncated() {
-        let v = vars(&[("msg", "abcdefghij")]);
-        assert_eq!(
-            render_template("{msg | truncate: 5}", &v, &SectionMap::new()),
-            "abcde..."
-        );
-    }
-
-    #[test]
-    fn full_pipe_chain_each_then_join() {
-        let s = sections_with("names", vec!["alice", "bob"]);
-        assert_eq!(
-            render_template(
-                "{names | each: \"- {value}\" | join: \"\\n\"}",
-                &HashMap::new(),
-                &s
-            ),
-            "- alice\n- bob"
-        );
-    }
-
-    #[test]
-    fn no_expressions_passthrough() {
-        assert_eq!(
-            render_template("just text", &HashMap::new(), &SectionMap::new()),
-            "just text"
-        );
-    }
-
-    #[test]
-    fn mixed_vars_and_sections() {
-        let v = vars(&[("passed", "20"), ("suites", "3")]);
-        let s = sections_with("lines", vec!["a", "b"]);
-        assert_eq!(
-            render_template(
-                "{passed} passed ({suites} suites), {lines.count} lines",
-                &v,
-                &s
-            ),
-            "20 passed (3 suites), 2 lines"
-        );
-    }
-
-    #[test]
-    fn empty_collection_empty_string() {
-        let s = sections_with("items", vec![]);
-        assert_eq!(
-            render_template("{items | join: \", \"}", &HashMap::new(), &s),
-            ""
-        );
-    }
-
-    #[test]
-    fn cargo_test_success_template() {
-        let v = vars(&[("passed", "20"), ("suites", "3")]);
-        let template = "\u{2713} cargo test: {passed} passed ({suites} suites)";
-        assert_eq!(
-            render_template(template, &v, &SectionMap::new()),
-            "\u{2713} cargo test: 20 passed (3 suites)"
-        );
-    }
-
-    #[test]
-    fn cargo_test_failure_template() {
-        let mut sections = SectionMap::new();
-        sections.insert(
-            "failure_blocks".to_string(),
-            SectionData {
-                lines: Vec::new(),
-                blocks: vec![
-                    "thread panicked at tests/a.rs".to_string(),
-                    "thread panicked at tests/b.rs".to_string(),
-                ],
-            },
-        );
-        sections.insert(
-            "summary_lines".to_string(),
-            SectionData {
-                lines: vec!["test result: FAILED. 1 passed; 2 failed".to_string()],
-                blocks: Vec::new(),
-            },
-        );
-
-        let template = "FAILURES ({failure_blocks.count}):\n{failure_blocks | each: \"{index}. {value | truncate: 200}\" | join: \"\\n\"}\n\n{summary_lines | join: \"\\n\"}";
-        let result = render_template(template, &HashMap::new(), &sections);
-        assert!(result.starts_with("FAILURES (2):"));
-        assert!(result.contains("1. thread panicked at tests/a.rs"));
-        assert!(result.contains("2. thread panicked at tests/b.rs"));
-        assert!(result.contains("test result: FAILED. 1 passed; 2 failed"));
-    }
-
-    #[test]
-    fn nested_brace_handling() {
-        let v = vars(&[("a", "1"), ("b", "2")]);
-        assert_eq!(
-            render_template("{a}+{b}=3", &v, &SectionMap::new()),
-            "1+2=3"
-        );
-    }
-
-    #[test]
-    fn unescape_escaped_quote() {
-        assert_eq!(super::unescape(r#"say \"hello\""#), "say \"hello\"");
-    }
-
-    // --- Gap 5: lines, keep, where pipes ---
-
-    #[test]
-    fn pipe_lines_splits_string() {
-        let v = vars(&[("msg", "a\nb\nc")]);
-        // lines splits into a collection; join reassembles
-        let result = render_template("{msg | lines | join: \",\"}", &v, &SectionMap::new());
-        assert_eq!(result, "a,b,c");
-    }
-
-    #[test]
-    fn pipe_lines_on_collection_passthrough() {
-        let s = sections_with("items", vec!["x", "y"]);
-        // Already a collection → lines is a no-op
-        let result = render_template("{items | lines | join: \",\"}", &HashMap::new(), &s);
-        assert_eq!(result, "x,y");
-    }
-
-    #[test]
-    fn pipe_keep_filters_collection() {
-        let s = sections_with("lines", vec!["ok line", "error: bad", "ok again"]);
-        let result = render_template(
-            "{lines | keep: \"^error\" | join: \"||\"}",
-            &HashMap::new(),
-            &s,
-        );
-        assert_eq!(result, "error: bad");
-    }
-
-    #[test]
-    fn pipe_where_is_alias_for_keep() {
-        let s = sections_with("lines", vec!["ok line", "error: bad", "ok again"]);
-        let result = render_template(
-            "{lines | where: \"^error\" | join: \"||\"}",
-            &HashMap::new(),
-            &s,
-        );
-        assert_eq!(result, "error: bad");
-    }
-
-    #[test]
-    fn pipe_keep_no_match_returns_empty() {
-        let s = sections_with("lines", vec!["foo", "bar"]);
-        let result = render_template(
-            "{lines | keep: \"^NOMATCH\" | join: \",\"}",
-            &HashMap::new(),
-            &s,
-        );
-        assert_eq!(result, "");
-    }
-
-    #[test]
-    fn pipe_keep_invalid_regex_passthrough() {
-        let s = sections_with("lines", vec!["a", "b"]);
-        // Bad regex → value passes through as-is (collection)
-        let result = render_template(
-            "{lines | keep: \"[invalid\" | join: \",\"}",
-            &HashMap::new(),
-            &s,
-        );
-        assert_eq!(result, "a,b");
-    }
-
-    #[test]
-    fn pipe_lines_then_keep_chain() {
-        let v = vars(&[("log", "ok\nfail\nok")]);
-        let result = render_template(
-            "{log | lines | keep: \"fail\" | join: \",\"}",
-            &v,
-            &SectionMap::new(),
-        );
-        assert_eq!(result, "fail");
-    }
-
-    #[test]
-    fn pipe_lines_then_keep_then_join_chain() {
-        let v = vars(&[("log", "pass\nERROR: bad\npass")]);
-        let result = render_template(
-            "{log | lines | keep: \"^ERROR\" | join: \"\\n\"}",
-            &v,
-            &SectionMap::new(),
-        );
-        assert_eq!(result, "ERROR: bad");
-    }
-}
+mod tests;
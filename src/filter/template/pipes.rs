@@ -0,0 +1,461 @@
+use regex::Captures;
+
+use super::{Value, parse_string_arg};
+use crate::filter::extract::interpolate;
+use crate::filter::stacktrace;
+use crate::regex_cache;
+
+/// Lines kept by `head` / `tail` when no count is given.
+const DEFAULT_HEAD_TAIL: usize = 10;
+
+/// Apply one of the argument-light pipes that only reshape a value.
+///
+/// Hands `value` back as `Err` when `name` is not one of them, so the
+/// caller can try the pipes that need template context (`each`, …).
+pub(super) fn apply_simple(name: &str, arg: Option<&str>, value: Value) -> Result<Value, Value> {
+    let number = |default| arg.and_then(|a| a.trim().parse().ok()).unwrap_or(default);
+    let value = match name {
+        "head" => head(number(DEFAULT_HEAD_TAIL), value),
+        "tail" => tail(number(DEFAULT_HEAD_TAIL), value),
+        "count" => count_of(&value),
+        "trim" => map_items(value, |s| s.trim().to_string()),
+        "upper" => map_items(value, str::to_uppercase),
+        "lower" => map_items(value, str::to_lowercase),
+        "dedent" => dedent(value),
+        "indent" => indent(number(2), value),
+        "tally" => tally(value),
+        "stacktrace" => condense_traces(number(stacktrace::DEFAULT_FRAMES), value),
+        _ => return Err(value),
+    };
+    Ok(value)
+}
+
+/// `| sub: "pattern", "replacement"` — regex replace every match.
+///
+/// The replacement uses the same `{0}`, `{1}`, … capture placeholders as
+/// `[[replace]]`. Applies to each item of a collection. A malformed argument
+/// list or an invalid pattern leaves the value unchanged.
+pub(super) fn sub(arg: &str, value: Value) -> Value {
+    let [pattern, replacement] = split_args(arg)[..] else {
+        return value;
+    };
+    let Some(re) = regex_cache::compile(&parse_string_arg(pattern)) else {
+        return value;
+    };
+    let replacement = parse_string_arg(replacement);
+    map_items(value, |s| {
+        re.replace_all(s, |caps: &Captures<'_>| {
+            interpolate(&replacement, &re, caps)
+        })
+        .into_owned()
+    })
+}
+
+/// `| group: "pattern"` — gather items under the text the pattern's first
+/// group captured (the whole match without a group), groups in first-seen
+/// order. Each group is a header line followed by its items with the match
+/// removed, indented two spaces. Items that don't match follow, unchanged.
+/// An optional second argument keeps only the last N items of each group,
+/// after a "  … N earlier" line. A string is grouped by line; an invalid
+/// pattern leaves the value unchanged.
+pub(super) fn group(arg: &str, value: Value) -> Value {
+    let args = split_args(arg);
+    let Some(re) = regex_cache::compile(&parse_string_arg(args[0])) else {
+        return value;
+    };
+    let last = args.get(1).and_then(|a| a.parse::<usize>().ok());
+    let items = match value {
+        Value::Collection(items) => items,
+        Value::Str(s) => s.lines().map(str::to_string).collect(),
+    };
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut rest = Vec::new();
+    for item in items {
+        let Some(caps) = re.captures(&item) else {
+            rest.push(item);
+            continue;
+        };
+        let whole = caps.get(0).map_or(0..0, |m| m.range());
+        let key = caps
+            .get(1)
+            .or_else(|| caps.get(0))
+            .map_or("", |m| m.as_str());
+        let line = format!("  {}{}", &item[..whole.start], &item[whole.end..]);
+        match groups.iter_mut().find(|(seen, _)| seen == key) {
+            Some((_, lines)) => lines.push(line),
+            None => groups.push((key.to_string(), vec![line])),
+        }
+    }
+    let mut out = Vec::new();
+    for (key, lines) in groups {
+        out.push(key);
+        let skip = last.map_or(0, |n| lines.len().saturating_sub(n));
+        if skip > 0 {
+            out.push(format!("  … {skip} earlier"));
+        }
+        out.extend(lines.into_iter().skip(skip));
+    }
+    out.extend(rest);
+    Value::Collection(out)
+}
+
+/// `| filter: "git/diff"` — render the value through another active filter,
+/// by name as in `tokf show`. An optional second argument is the exit code
+/// the filter sees (default 0), which picks its `[on_failure]` branch when
+/// non-zero. A collection is joined into lines first. An unknown filter
+/// leaves the value unchanged.
+pub(super) fn filter(arg: &str, value: Value) -> Value {
+    let args = split_args(arg);
+    let name = parse_string_arg(args[0]);
+    let exit_code = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(0);
+    let text = match &value {
+        Value::Collection(items) => items.join("\n"),
+        Value::Str(s) => s.clone(),
+    };
+    crate::filter::sub_filter::apply_named(&name, &text, exit_code).map_or(value, Value::Str)
+}
+
+/// Split a pipe argument list on commas outside double quotes.
+fn split_args(arg: &str) -> Vec<&str> {
+    let bytes = arg.as_bytes();
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut in_quote = false;
+    for (i, &ch) in bytes.iter().enumerate() {
+        if ch == b'"' && (i == 0 || bytes[i - 1] != b'\\') {
+            in_quote = !in_quote;
+        } else if ch == b',' && !in_quote {
+            parts.push(arg[last..i].trim());
+            last = i + 1;
+        }
+    }
+    parts.push(arg[last..].trim());
+    parts
+}
+
+/// `| head: N` — first N items, or first N lines of a string.
+fn head(n: usize, value: Value) -> Value {
+    match value {
+        Value::Collection(items) => Value::Collection(items.into_iter().take(n).collect()),
+        Value::Str(s) => Value::Str(s.lines().take(n).collect::<Vec<_>>().join("\n")),
+    }
+}
+
+/// `| tail: N` — last N items, or last N lines of a string.
+fn tail(n: usize, value: Value) -> Value {
+    match value {
+        Value::Collection(items) => {
+            let skip = items.len().saturating_sub(n);
+            Value::Collection(items.into_iter().skip(skip).collect())
+        }
+        Value::Str(s) => {
+            let lines: Vec<&str> = s.lines().collect();
+            let skip = lines.len().saturating_sub(n);
+            Value::Str(lines[skip..].join("\n"))
+        }
+    }
+}
+
+/// `| count` — number of items, or number of lines in a string.
+fn count_of(value: &Value) -> Value {
+    let n = match value {
+        Value::Collection(items) => items.len(),
+        Value::Str(s) => s.lines().count(),
+    };
+    Value::Str(n.to_string())
+}
+
+/// `| tally` — each distinct item once, as `item (N)`, most frequent first
+/// (ties keep first-seen order). A string is tallied by line.
+fn tally(value: Value) -> Value {
+    let items = match value {
+        Value::Collection(items) => items,
+        Value::Str(s) => s.lines().map(str::to_string).collect(),
+    };
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for item in items {
+        match counts.iter_mut().find(|(seen, _)| *seen == item) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((item, 1)),
+        }
+    }
+    counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+    Value::Collection(
+        counts
+            .into_iter()
+            .map(|(item, n)| format!("{item} ({n})"))
+            .collect(),
+    )
+}
+
+fn map_items(value: Value, f: impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::Collection(items) => Value::Collection(items.iter().map(|s| f(s)).collect()),
+        Value::Str(s) => Value::Str(f(&s)),
+    }
+}
+
+/// `| dedent` — strip the leading whitespace common to all non-blank lines.
+///
+/// For a collection, the items are treated as the lines.
+fn dedent(value: Value) -> Value {
+    match value {
+        Value::Collection(items) => {
+            let strip = common_indent(items.iter().map(String::as_str));
+            Value::Collection(items.iter().map(|l| strip_indent(l, strip)).collect())
+        }
+        Value::Str(s) => {
+            let strip = common_indent(s.lines());
+            let lines: Vec<String> = s.lines().map(|l| strip_indent(l, strip)).collect();
+            Value::Str(lines.join("\n"))
+        }
+    }
+}
+
+fn common_indent<'a>(lines: impl Iterator<Item = &'a str>) -> usize {
+    lines
+        .filter(|l| !l.trim().is_empty())
+        .map(indent_width)
+        .min()
+        .unwrap_or(0)
+}
+
+/// Remove up to `n` bytes of indentation (blank lines may be shorter).
+fn strip_indent(line: &str, n: usize) -> String {
+    line[n.min(indent_width(line))..].to_string()
+}
+
+/// Bytes of leading spaces and tabs. Other Unicode whitespace (a non-breaking
+/// space, say) is content, not indentation.
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/// `| stacktrace: N` — condense stack traces to the exception line(s) and
+/// the top N in-project frames (default 3), as `[stacktrace]` does. For a
+/// collection, the items are treated as the lines.
+fn condense_traces(frames: usize, value: Value) -> Value {
+    let options = stacktrace::Options {
+        frames,
+        library: &[],
+    };
+    match value {
+        Value::Collection(items) => {
+            let lines: Vec<&str> = items.iter().map(String::as_str).collect();
+            Value::Collection(stacktrace::condense(&lines, &options))
+        }
+        Value::Str(s) => {
+            let lines: Vec<&str> = s.lines().collect();
+            Value::Str(stacktrace::condense(&lines, &options).join("\n"))
+        }
+    }
+}
+
+/// `| indent: N` — prefix every line (or item) with N spaces. Default 2.
+fn indent(n: usize, value: Value) -> Value {
+    let pad = " ".repeat(n);
+    let indent_line = |l: &str| {
+        if l.is_empty() {
+            String::new()
+        } else {
+            format!("{pad}{l}")
+        }
+    };
+    match value {
+        Value::Collection(items) => {
+            Value::Collection(items.iter().map(|l| indent_line(l)).collect())
+        }
+        Value::Str(s) => Value::Str(s.lines().map(indent_line).collect::<Vec<_>>().join("\n")),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn col(items: &[&str]) -> Value {
+        Value::Collection(items.iter().map(|s| (*s).to_string()).collect())
+    }
+
+    fn apply(name: &str, arg: Option<&str>, value: Value) -> Value {
+        apply_simple(name, arg, value).unwrap()
+    }
+
+    #[test]
+    fn head_and_tail_on_collections_and_strings() {
+        let items = col(&["a", "b", "c", "d"]);
+        assert_eq!(apply("head", Some("2"), items.clone()), col(&["a", "b"]));
+        assert_eq!(
+            apply("tail", Some("3"), items.clone()),
+            col(&["b", "c", "d"])
+        );
+        assert_eq!(apply("tail", Some("9"), items), col(&["a", "b", "c", "d"]));
+        let s = Value::Str("1\n2\n3".to_string());
+        assert_eq!(apply("head", Some("1"), s.clone()), Value::Str("1".into()));
+        assert_eq!(apply("tail", Some("2"), s), Value::Str("2\n3".into()));
+    }
+
+    #[test]
+    fn head_defaults_to_ten() {
+        let many: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let out = apply("head", None, Value::Collection(many));
+        assert_eq!(apply("count", None, out), Value::Str("10".into()));
+    }
+
+    #[test]
+    fn tally_counts_distinct_items_most_frequent_first() {
+        assert_eq!(
+            apply(
+                "tally",
+                None,
+                col(&["semi", "no-console", "semi", "eqeqeq"])
+            ),
+            col(&["semi (2)", "no-console (1)", "eqeqeq (1)"])
+        );
+        assert_eq!(
+            apply("tally", None, Value::Str("b\na\na".into())),
+            col(&["a (2)", "b (1)"])
+        );
+        assert_eq!(apply("tally", None, col(&[])), col(&[]));
+    }
+
+    #[test]
+    fn group_gathers_items_under_their_capture() {
+        let diags = col(&[
+            "b.c:3:1: warning: unused",
+            "a.c:9:2: error: undeclared",
+            "b.c:7:5: warning: shadowed",
+            "undefined reference to `foo'",
+        ]);
+        assert_eq!(
+            group(r#""^([^:\s]+):""#, diags),
+            col(&[
+                "b.c",
+                "  3:1: warning: unused",
+                "  7:5: warning: shadowed",
+                "a.c",
+                "  9:2: error: undeclared",
+                "undefined reference to `foo'",
+            ])
+        );
+        assert_eq!(
+            group(r#""^\[(\w+)\] ""#, Value::Str("[x] a\n[y] b\n[x] c".into())),
+            col(&["x", "  a", "  c", "y", "  b"])
+        );
+        assert_eq!(group(r#""(""#, col(&["a"])), col(&["a"]));
+        assert_eq!(
+            group(r#""^(\w+) ", 2"#, Value::Str("x 1\nx 2\ny 1\nx 3".into())),
+            col(&["x", "  … 1 earlier", "  2", "  3", "y", "  1"])
+        );
+    }
+
+    #[test]
+    fn count_items_or_lines() {
+        assert_eq!(
+            apply("count", None, col(&["a", "b"])),
+            Value::Str("2".into())
+        );
+        assert_eq!(
+            apply("count", None, Value::Str("x\ny\nz".into())),
+            Value::Str("3".into())
+        );
+        assert_eq!(
+            apply("count", None, Value::Str(String::new())),
+            Value::Str("0".into())
+        );
+    }
+
+    #[test]
+    fn trim_upper_lower() {
+        assert_eq!(apply("trim", None, col(&["  a ", "b\t"])), col(&["a", "b"]));
+        assert_eq!(
+            apply("upper", None, Value::Str("ok".into())),
+            Value::Str("OK".into())
+        );
+        assert_eq!(apply("lower", None, col(&["ERR"])), col(&["err"]));
+    }
+
+    #[test]
+    fn stacktrace_condenses_string_and_collection() {
+        let trace =
+            "Error: boom\n    at run (/app/src/run.js:3:9)\n    at main (/app/src/main.js:8:1)";
+        assert_eq!(
+            apply("stacktrace", Some(" 1"), Value::Str(trace.into())),
+            Value::Str("Error: boom\n  at run (/app/src/run.js:3:9)\n  … 1 more frame".into())
+        );
+        assert_eq!(
+            apply("stacktrace", None, Value::Str(trace.into())),
+            Value::Str(
+                "Error: boom\n  at run (/app/src/run.js:3:9)\n  at main (/app/src/main.js:8:1)"
+                    .into()
+            )
+        );
+        let items: Vec<&str> = trace.lines().collect();
+        assert_eq!(
+            apply("stacktrace", Some("1"), col(&items)),
+            col(&[
+                "Error: boom",
+                "  at run (/app/src/run.js:3:9)",
+                "  … 1 more frame"
+            ])
+        );
+    }
+
+    #[test]
+    fn dedent_strips_common_indent() {
+        let s = Value::Str("    fn a() {\n        b();\n\n    }".into());
+        assert_eq!(
+            apply("dedent", None, s),
+            Value::Str("fn a() {\n    b();\n\n}".into())
+        );
+        assert_eq!(
+            apply("dedent", None, col(&["  x", "    y"])),
+            col(&["x", "  y"])
+        );
+    }
+
+    #[test]
+    fn dedent_leaves_unicode_whitespace_as_content() {
+        let s = Value::Str("  \u{a0}a\n\t  b\n \u{3000}".into());
+        assert_eq!(
+            apply("dedent", None, s),
+            Value::Str("\u{a0}a\n b\n\u{3000}".into())
+        );
+    }
+
+    #[test]
+    fn indent_pads_non_empty_lines() {
+        assert_eq!(
+            apply("indent", Some("4"), Value::Str("a\n\nb".into())),
+            Value::Str("    a\n\n    b".into())
+        );
+        assert_eq!(apply("indent", None, col(&["x"])), col(&["  x"]));
+    }
+
+    #[test]
+    fn sub_replaces_every_match_with_captures() {
+        let s = Value::Str("/home/ci/repo/src/a.rs:1 /home/ci/repo/b.rs".into());
+        assert_eq!(
+            sub(r#""/home/ci/repo/(\S+)", "./{1}""#, s),
+            Value::Str("./src/a.rs:1 ./b.rs".into())
+        );
+        assert_eq!(
+            sub(r#""(\d+)", "<{1}>""#, col(&["a1", "b22"])),
+            col(&["a<1>", "b<22>"])
+        );
+    }
+
+    #[test]
+    fn sub_quoted_commas_and_bad_args_pass_through() {
+        let s = Value::Str("a, b".into());
+        assert_eq!(sub(r#"", ", ";""#, s.clone()), Value::Str("a;b".into()));
+        assert_eq!(sub(r#""a""#, s.clone()), s);
+        assert_eq!(sub(r#""(", "x""#, s.clone()), s);
+    }
+
+    #[test]
+    fn unknown_pipe_is_not_handled() {
+        assert!(apply_simple("join", Some("\",\""), col(&["a"])).is_err());
+    }
+}
@@ -7,6 +7,8 @@ fn make_result(combined: &str, exit_code: i32) -> CommandResult {
         stderr: String::new(),
         exit_code,
         combined: combined.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
     }
 }
 
@@ -25,10 +27,14 @@ fn select_branch_success() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     });
-    assert!(select_branch(&config, 0).is_some());
-    assert!(select_branch(&config, 1).is_none());
+    assert!(select_branch(&config, 0, &[]).is_some());
+    assert!(select_branch(&config, 1, &[]).is_none());
 }
 
 #[test]
@@ -40,18 +46,31 @@ fn select_branch_failure() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     });
-    assert!(select_branch(&config, 0).is_none());
-    assert!(select_branch(&config, 1).is_some());
-    assert!(select_branch(&config, 127).is_some());
+    assert!(select_branch(&config, 0, &[]).is_none());
+    assert!(select_branch(&config, 1, &[]).is_some());
+    assert!(select_branch(&config, 127, &[]).is_some());
 }
 
 // --- apply_branch ---
 
-/// Helper: call apply_branch with empty sections (non-section path).
+/// Helper: call `apply_branch` with empty sections (non-section path).
 fn branch_apply(branch: &OutputBranch, combined: &str) -> String {
-    apply_branch(branch, combined, &SectionMap::new(), false).unwrap()
+    apply_branch(&BranchCtx {
+        branch,
+        raw: combined,
+        input: combined,
+        sections: &SectionMap::new(),
+        section_defs: &[],
+        result: &make_result(combined, 0),
+        args: &[],
+    })
+    .unwrap()
 }
 
 #[test]
@@ -62,7 +81,11 @@ fn branch_fixed_output() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "anything"), "ok \u{2713}");
 }
@@ -75,7 +98,11 @@ fn branch_output_template_resolves_output_var() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "hello world"), "hello world");
 }
@@ -88,7 +115,11 @@ fn branch_output_template_with_surrounding_text() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(
         branch_apply(&branch, "line1\nline2"),
@@ -104,7 +135,11 @@ fn branch_tail_truncation() {
         tail: Some(2),
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "a\nb\nc\nd"), "c\nd");
 }
@@ -117,7 +152,11 @@ fn branch_head_truncation() {
         tail: None,
         head: Some(2),
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "a\nb\nc\nd"), "a\nb");
 }
@@ -130,12 +169,39 @@ fn branch_tail_then_head() {
         tail: Some(3),
         head: Some(2),
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     // tail 3 of [a,b,c,d] → [b,c,d], then head 2 → [b,c]
     assert_eq!(branch_apply(&branch, "a\nb\nc\nd"), "b\nc");
 }
 
+#[test]
+fn branch_tail_adaptive_overrides_tail() {
+    let branch = OutputBranch {
+        output: None,
+        aggregate: None,
+        tail: Some(1),
+        head: None,
+        skip: vec![],
+        keep: vec![],
+        extract: None,
+        render: None,
+        tail_adaptive: Some(crate::config::types::AdaptiveTail {
+            min: 2,
+            max: 4,
+            pattern: "^E".to_string(),
+            matches: None,
+        }),
+        source: None,
+    };
+    assert_eq!(branch_apply(&branch, "a\nE1\nb\nc\nd"), "E1\nb\nc\nd");
+    assert_eq!(branch_apply(&branch, "a\nb\nc\nd"), "c\nd");
+}
+
 #[test]
 fn branch_skip_then_join() {
     let branch = OutputBranch {
@@ -144,7 +210,11 @@ fn branch_skip_then_join() {
         tail: None,
         head: None,
         skip: vec!["^noise".to_string()],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(
         branch_apply(&branch, "noise line\nkeep me\nnoise again"),
@@ -160,10 +230,14 @@ fn branch_extract() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: Some(ExtractRule {
             pattern: r"(\S+)\s*->\s*(\S+)".to_string(),
             output: "ok {2}".to_string(),
         }),
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "main -> main"), "ok main");
 }
@@ -176,7 +250,11 @@ fn branch_tail_less_than_lines() {
         tail: Some(10),
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     // Only 3 lines, tail 10 → all lines kept
     assert_eq!(branch_apply(&branch, "a\nb\nc"), "a\nb\nc");
@@ -190,7 +268,11 @@ fn branch_empty_string_returns_empty() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, ""), "");
 }
@@ -203,7 +285,11 @@ fn branch_single_line_no_newline() {
         tail: None,
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "only-line"), "only-line");
 }
@@ -216,7 +302,11 @@ fn branch_tail_zero_returns_empty() {
         tail: Some(0),
         head: None,
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "a\nb\nc"), "");
 }
@@ -229,7 +319,11 @@ fn branch_head_zero_returns_empty() {
         tail: None,
         head: Some(0),
         skip: vec![],
+        keep: vec![],
         extract: None,
+        render: None,
+        tail_adaptive: None,
+        source: None,
     };
     assert_eq!(branch_apply(&branch, "a\nb\nc"), "");
 }
@@ -309,6 +403,14 @@ extract = { pattern = '(\w+) -> (\w+)', output = "pushed {2}" }
     assert_eq!(apply(&config, &result, &[]).output, "pushed main");
 }
 
+#[test]
+fn apply_branch_keep_after_tail() {
+    let config: FilterConfig =
+        toml::from_str("command = \"t\"\n[on_failure]\ntail = 3\nkeep = [\"^error\"]").unwrap();
+    let result = make_result("error: a\nerror: b\nnote\nerror: c", 1);
+    assert_eq!(apply(&config, &result, &[]).output, "error: b\nerror: c");
+}
+
 // --- parse pipeline tests ---
 
 #[test]
@@ -476,6 +578,18 @@ output = "Found {items.count} items in:\n{output}"
     );
 }
 
+#[test]
+fn empty_sections_fall_back_unless_the_input_is_empty() {
+    let config: FilterConfig = toml::from_str(
+        r#"command = "test"
+section = [{ match = "^item:", collect_as = "items" }]
+on_success = { output = "{#if items}{items.count}{#else}no items{/if}" }"#,
+    )
+    .unwrap();
+    assert_eq!(apply(&config, &make_result("", 0), &[]).output, "no items");
+    assert!(apply(&config, &make_result("unrecognised", 0), &[]).used_fallback);
+}
+
 // --- cleanup flag integration tests ---
 
 #[test]
@@ -0,0 +1,110 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use clap::Args;
+
+use tokf::config;
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+
+#[derive(Args)]
+pub struct FilterFileArgs {
+    /// Filter name (e.g. "cargo/test") or a command it matches (e.g. "cargo test")
+    filter: String,
+    /// Files to filter; glob patterns such as "logs/*.txt" are expanded
+    #[arg(required = true)]
+    paths: Vec<String>,
+    /// Write the filtered output to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Exit code to assume for branch selection
+    #[arg(long, default_value_t = 0)]
+    exit_code: i32,
+}
+
+pub fn cmd_filter_file(args: &FilterFileArgs, include_builtin: bool) -> i32 {
+    match run(args, include_builtin) {
+        Ok(()) => 0,
+        Err(e) => e.report(),
+    }
+}
+
+fn run(args: &FilterFileArgs, include_builtin: bool) -> Result<(), CliError> {
+    let cfg = resolve_filter(&args.filter, include_builtin)?;
+    let files = expand_paths(&args.paths)?;
+
+    let mut out = String::new();
+    for (i, path) in files.iter().enumerate() {
+        // Logs often hold stray non-UTF-8 bytes; replace them like live output does.
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+        let content = String::from_utf8_lossy(&bytes);
+        let filtered = filter::apply(
+            &cfg,
+            &CommandResult::from_output(&content, args.exit_code),
+            &[],
+        );
+        if files.len() > 1 {
+            if i > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "==> {} <==", path.display());
+        }
+        if !filtered.output.is_empty() {
+            let _ = writeln!(out, "{}", filtered.output);
+        }
+    }
+
+    if let Some(ref dest) = args.output {
+        return std::fs::write(dest, out)
+            .map_err(|e| anyhow::anyhow!("failed to write {}: {e}", dest.display()).into());
+    }
+    print!("{out}");
+    Ok(())
+}
+
+/// Look the filter up by relative name first, then by command match.
+pub fn resolve_filter(name: &str, include_builtin: bool) -> Result<FilterConfig, CliError> {
+    let search_dirs = config::default_search_dirs();
+    let filters = config::cache::discover_with_cache(&search_dirs, include_builtin)
+        .kind(ErrorKind::Config)?;
+    if let Some(found) = config::find_by_name(&filters, name) {
+        return Ok(found.config.clone());
+    }
+    let words: Vec<&str> = name.split_whitespace().collect();
+    filters
+        .iter()
+        .find(|f| f.matches(&words).is_some())
+        .map(|f| f.config.clone())
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::FilterNotFound,
+                format_args!("filter not found: {name}"),
+            )
+        })
+}
+
+/// Expand glob patterns (sorted); plain paths are kept as given.
+pub fn expand_paths(patterns: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            files.push(PathBuf::from(pattern));
+            continue;
+        }
+        let mut matched: Vec<PathBuf> = glob::glob(pattern)
+            .map_err(|e| anyhow::anyhow!("invalid glob {pattern}: {e}"))?
+            .filter_map(Result::ok)
+            .filter(|p| p.is_file())
+            .collect();
+        if matched.is_empty() {
+            anyhow::bail!("no files match {pattern}");
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    Ok(files)
+}
@@ -0,0 +1,167 @@
+//! Number, byte and percentage formatting shared by `gain`, `statusline` and
+//! other reporting commands.
+
+/// Round a percentage to one decimal place.
+///
+/// Text and JSON reports both print values rounded here, so the two never
+/// disagree in the last digit.
+pub fn round_pct(pct: f64) -> f64 {
+    (pct * 10.0).round() / 10.0
+}
+
+/// `part` as a percentage of `whole`, rounded with [`round_pct`]; 0 when
+/// `whole` is 0.
+#[allow(clippy::cast_precision_loss)]
+pub fn percent(part: i64, whole: i64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        round_pct(part as f64 / whole as f64 * 100.0)
+    }
+}
+
+/// `950`, `12.3k`, `4.1M`.
+pub fn compact(n: i64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let f = n as f64;
+    match n.unsigned_abs() {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", f / 1e3),
+        _ => format!("{:.1}M", f / 1e6),
+    }
+}
+
+/// `512 B`, `4.1 kB`, `1.2 MB` (decimal units, matching [`compact`]).
+pub fn bytes(n: i64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let f = n as f64;
+    match n.unsigned_abs() {
+        0..1_000 => format!("{n} B"),
+        1_000..1_000_000 => format!("{:.1} kB", f / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1} MB", f / 1e6),
+        _ => format!("{:.1} GB", f / 1e9),
+    }
+}
+
+/// Thousands grouping for exact integers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grouping {
+    separator: String,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Self::with_separator(",")
+    }
+}
+
+impl Grouping {
+    pub fn with_separator(separator: &str) -> Self {
+        Self {
+            separator: separator.to_string(),
+        }
+    }
+
+    /// The separator conventionally used by a POSIX locale name such as
+    /// `de_DE.UTF-8`; `,` for `C`, `POSIX`, English and anything unknown.
+    pub fn for_locale(locale: &str) -> Self {
+        let lang = locale
+            .split(['_', '.', '@', '-'])
+            .next()
+            .unwrap_or_default();
+        let separator = match lang {
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" => ".",
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" => {
+                "\u{202f}"
+            }
+            _ => ",",
+        };
+        Self::with_separator(separator)
+    }
+
+    /// `number_separator` from `config.toml` if set, else the separator for
+    /// the first non-empty of `LC_ALL`, `LC_NUMERIC` and `LANG`.
+    pub fn detect() -> Self {
+        if let Some(sep) = crate::config::settings::load_settings().number_separator {
+            return Self::with_separator(&sep);
+        }
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|v| !v.is_empty())
+            .map_or_else(Self::default, |locale| Self::for_locale(&locale))
+    }
+
+    /// `84320` → `84,320` (or `84.320`, `84 320`, … per separator).
+    pub fn format(&self, n: i64) -> String {
+        let digits = n.unsigned_abs().to_string();
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+        if n < 0 {
+            out.push('-');
+        }
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(&self.separator);
+            }
+            out.push(c);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_rounds_to_one_decimal() {
+        assert_eq!(percent(19, 24), 79.2);
+        assert_eq!(percent(3, 4), 75.0);
+        assert_eq!(percent(5, 0), 0.0);
+        assert_eq!(round_pct(33.349), 33.3);
+    }
+
+    #[test]
+    fn compact_numbers() {
+        assert_eq!(compact(0), "0");
+        assert_eq!(compact(999), "999");
+        assert_eq!(compact(12_345), "12.3k");
+        assert_eq!(compact(4_100_000), "4.1M");
+        assert_eq!(compact(-2_500), "-2.5k");
+    }
+
+    #[test]
+    fn byte_sizes() {
+        assert_eq!(bytes(512), "512 B");
+        assert_eq!(bytes(4_096), "4.1 kB");
+        assert_eq!(bytes(1_234_567), "1.2 MB");
+        assert_eq!(bytes(3_000_000_000), "3.0 GB");
+    }
+
+    #[test]
+    fn grouping_basic() {
+        let g = Grouping::default();
+        assert_eq!(g.format(0), "0");
+        assert_eq!(g.format(999), "999");
+        assert_eq!(g.format(1000), "1,000");
+        assert_eq!(g.format(84320), "84,320");
+        assert_eq!(g.format(-73080), "-73,080");
+        assert_eq!(g.format(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn grouping_follows_locale() {
+        assert_eq!(
+            Grouping::for_locale("de_DE.UTF-8").format(1_234_567),
+            "1.234.567"
+        );
+        assert_eq!(
+            Grouping::for_locale("fr_FR.UTF-8").format(1_234),
+            "1\u{202f}234"
+        );
+        assert_eq!(Grouping::for_locale("en_US.UTF-8").format(1_234), "1,234");
+        assert_eq!(Grouping::for_locale("C").format(1_234), "1,234");
+        assert_eq!(Grouping::with_separator("'").format(1_234), "1'234");
+    }
+}
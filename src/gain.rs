@@ -1,142 +1,428 @@
-use tokf::tracking;
+use clap::{ArgGroup, Args, Subcommand, ValueEnum};
 
-pub fn cmd_gain(daily: bool, by_filter: bool, json: bool) -> i32 {
+use tokf::config;
+use tokf::format::{self, Grouping};
+use tokf::tracking::efficiency::{self, FilterEfficiency};
+use tokf::tracking::export::{self, ExportFormat};
+use tokf::tracking::retention::{self, RetentionPolicy};
+use tokf::tracking::trend;
+use tokf::tracking::{self, GainQuery, Period};
+
+use crate::cli::run::RunRecord;
+use crate::cli_error::{self, ErrorKind};
+
+// Each bool maps directly to a CLI flag; the breakdown flags are mutually exclusive.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Args)]
+#[command(group(ArgGroup::new("breakdown").args(["daily", "weekly", "monthly", "by-filter"])))]
+pub struct GainArgs {
+    #[command(subcommand)]
+    action: Option<GainAction>,
+    /// Show daily breakdown
+    #[arg(long)]
+    daily: bool,
+    /// Show weekly breakdown
+    #[arg(long)]
+    weekly: bool,
+    /// Show monthly breakdown
+    #[arg(long)]
+    monthly: bool,
+    /// Show breakdown by filter
+    #[arg(long, name = "by-filter")]
+    by_filter: bool,
+    /// Split --by-filter rows by tool and subcommand
+    #[arg(long, requires = "by-filter")]
+    by_subcommand: bool,
+    /// Output as JSON
+    #[arg(long)]
+    json: bool,
+    /// Print exact counts with thousands separators instead of 12.4k / 1.2 MB
+    #[arg(long)]
+    exact: bool,
+    /// Only count runs on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    since: Option<String>,
+    /// Only count runs on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    until: Option<String>,
+    /// Use UTC dates for --since/--until and breakdowns instead of local time
+    #[arg(long)]
+    utc: bool,
+    /// Ignore repeats of the same command and output within N seconds
+    #[arg(long, value_name = "SECS")]
+    dedup_window: Option<u64>,
+}
+
+#[derive(Subcommand)]
+enum GainAction {
+    /// Dump raw tracking events as CSV or JSONL (oldest first; honours
+    /// --since, --until and --dedup-window)
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = FormatArg::Jsonl)]
+        format: FormatArg,
+    },
+    /// Delete old tracking events and compact the DB
+    Prune {
+        /// Keep events from the last N days [default: `TOKF_DB_KEEP_DAYS` or 90]
+        #[arg(long, value_name = "DAYS")]
+        keep_days: Option<u32>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Csv,
+    Jsonl,
+}
+
+impl GainArgs {
+    /// The requested rollup period, if any (clap allows at most one).
+    const fn period(&self) -> Option<Period> {
+        if self.daily {
+            Some(Period::Day)
+        } else if self.weekly {
+            Some(Period::Week)
+        } else if self.monthly {
+            Some(Period::Month)
+        } else {
+            None
+        }
+    }
+}
+
+/// Accept `YYYY-MM-DD` only — the format event dates are compared against.
+fn parse_date(s: &str) -> Result<String, String> {
+    let b = s.as_bytes();
+    let shape_ok = b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b.iter()
+            .enumerate()
+            .all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit());
+    if shape_ok {
+        Ok(s.to_string())
+    } else {
+        Err(format!("expected YYYY-MM-DD, got \"{s}\""))
+    }
+}
+
+pub fn cmd_gain(args: &GainArgs) -> i32 {
     let Some(path) = tracking::db_path() else {
-        eprintln!("[tokf] error: cannot determine DB path");
-        return 1;
+        return cli_error::report(ErrorKind::Other, &"cannot determine DB path");
     };
     let conn = match tracking::open_db(&path) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[tokf] error opening DB: {e:#}");
-            return 1;
+            return cli_error::report(ErrorKind::Other, &format_args!("opening DB: {e:#}"));
         }
     };
 
-    if daily {
-        cmd_gain_daily(&conn, json)
-    } else if by_filter {
-        cmd_gain_by_filter(&conn, json)
-    } else {
-        cmd_gain_summary(&conn, json)
+    let query = GainQuery {
+        dedup_window: args.dedup_window,
+        since: args.since.clone(),
+        until: args.until.clone(),
+        utc: args.utc,
+    };
+    match args.action {
+        Some(GainAction::Export { format }) => return cmd_gain_export(&conn, format, &query),
+        Some(GainAction::Prune { keep_days }) => return cmd_gain_prune(&conn, keep_days),
+        None => {}
+    }
+
+    let units = Units::new(args.exact);
+    match args.period() {
+        Some(period) => cmd_gain_period(&conn, period, args.json, &query, &units),
+        None if args.by_filter => {
+            cmd_gain_by_filter(&conn, args.json, args.by_subcommand, &query, &units)
+        }
+        None => cmd_gain_summary(&conn, args.json, &query, &units),
     }
 }
 
-fn cmd_gain_summary(conn: &rusqlite::Connection, json: bool) -> i32 {
-    match tracking::query_summary(conn) {
+fn cmd_gain_summary(
+    conn: &rusqlite::Connection,
+    json: bool,
+    query: &GainQuery,
+    units: &Units,
+) -> i32 {
+    match tracking::query_summary(conn, query) {
         Ok(s) => {
             if json {
                 match serde_json::to_string_pretty(&s) {
                     Ok(out) => println!("{out}"),
                     Err(e) => {
-                        eprintln!("[tokf] error: {e}");
-                        return 1;
+                        return cli_error::report(ErrorKind::Other, &e);
                     }
                 }
             } else {
                 println!("tokf gain summary");
-                println!("  total runs:     {}", s.total_commands);
+                println!("  total runs:     {}", units.count(s.total_commands));
                 println!(
                     "  input tokens:   {} est.",
-                    format_num(s.total_input_tokens)
+                    units.tokens(s.total_input_tokens)
                 );
                 println!(
                     "  output tokens:  {} est.",
-                    format_num(s.total_output_tokens)
+                    units.tokens(s.total_output_tokens)
                 );
                 println!(
                     "  tokens saved:   {} est. ({:.1}%)",
-                    format_num(s.tokens_saved),
+                    units.tokens(s.tokens_saved),
                     s.savings_pct
                 );
             }
             0
         }
-        Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
     }
 }
 
-fn cmd_gain_by_filter(conn: &rusqlite::Connection, json: bool) -> i32 {
-    match tracking::query_by_filter(conn) {
-        Ok(rows) => {
-            if json {
-                match serde_json::to_string_pretty(&rows) {
-                    Ok(out) => println!("{out}"),
-                    Err(e) => {
-                        eprintln!("[tokf] error: {e}");
-                        return 1;
-                    }
-                }
-            } else {
-                println!("tokf gain by filter");
-                for r in &rows {
-                    println!(
-                        "  {:30}  runs: {:4}  saved: {} est. ({:.1}%)",
-                        r.filter_name,
-                        r.commands,
-                        format_num(r.tokens_saved),
-                        r.savings_pct
-                    );
-                }
-            }
-            0
+fn cmd_gain_by_filter(
+    conn: &rusqlite::Connection,
+    json: bool,
+    by_subcommand: bool,
+    query: &GainQuery,
+    units: &Units,
+) -> i32 {
+    let rows = if by_subcommand {
+        tracking::shape::query_by_subcommand(conn, query)
+    } else {
+        tracking::query_by_filter(conn, query)
+    };
+    // Efficiency is per filter, so it is left out of the per-subcommand split.
+    let efficiency = if by_subcommand {
+        Ok(Vec::new())
+    } else {
+        efficiency::query_efficiency(conn, query)
+    };
+    let (rows, efficiency) = match (rows, efficiency) {
+        (Ok(rows), Ok(efficiency)) => (rows, efficiency),
+        (Err(e), _) | (_, Err(e)) => {
+            return cli_error::report(ErrorKind::Other, &format_args!("{e:#}"));
         }
-        Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
+    };
+    if json {
+        let rows: Vec<FilterRow<'_>> = rows
+            .iter()
+            .map(|gain| FilterRow {
+                gain,
+                efficiency: efficiency
+                    .iter()
+                    .find(|e| gain.command.is_none() && e.filter_name == gain.filter_name),
+            })
+            .collect();
+        match serde_json::to_string_pretty(&rows) {
+            Ok(out) => println!("{out}"),
+            Err(e) => return cli_error::report(ErrorKind::Other, &e),
         }
+        return 0;
     }
+    println!("tokf gain by filter");
+    for r in &rows {
+        let name = r.command.as_ref().map_or_else(
+            || r.filter_name.clone(),
+            |c| format!("{} ({c})", r.filter_name),
+        );
+        println!(
+            "  {:30}  runs: {:4}  saved: {} est. ({:.1}%)",
+            name,
+            r.commands,
+            units.tokens(r.tokens_saved),
+            r.savings_pct
+        );
+    }
+    print_efficiency(&efficiency, units);
+    0
+}
+
+/// A `--by-filter --json` row: the gain figures plus the filter's efficiency.
+#[derive(serde::Serialize)]
+struct FilterRow<'a> {
+    #[serde(flatten)]
+    gain: &'a tracking::FilterGain,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    efficiency: Option<&'a FilterEfficiency>,
 }
 
-fn cmd_gain_daily(conn: &rusqlite::Connection, json: bool) -> i32 {
-    match tracking::query_daily(conn) {
+fn print_efficiency(rows: &[FilterEfficiency], units: &Units) {
+    if rows.is_empty() {
+        return;
+    }
+    println!("\nefficiency (average reduction per run, best first)");
+    for e in rows {
+        println!(
+            "  {:30}  runs: {:4}  avg: {:5.1}%  median out: {}  fallback: {:.0}%",
+            e.filter_name,
+            e.runs,
+            e.avg_reduction_pct,
+            units.bytes(e.median_output_bytes),
+            e.fallback_pct
+        );
+        for tip in &e.recommendations {
+            println!("    \u{2192} {tip}");
+        }
+    }
+}
+
+fn cmd_gain_period(
+    conn: &rusqlite::Connection,
+    period: Period,
+    json: bool,
+    query: &GainQuery,
+    units: &Units,
+) -> i32 {
+    match tracking::query_by_period(conn, period, query) {
         Ok(rows) => {
             if json {
                 match serde_json::to_string_pretty(&rows) {
                     Ok(out) => println!("{out}"),
                     Err(e) => {
-                        eprintln!("[tokf] error: {e}");
-                        return 1;
+                        return cli_error::report(ErrorKind::Other, &e);
                     }
                 }
             } else {
-                println!("tokf gain daily");
+                let label = match period {
+                    Period::Day => "daily",
+                    Period::Week => "weekly",
+                    Period::Month => "monthly",
+                };
+                println!("tokf gain {label}");
                 for r in &rows {
                     println!(
                         "  {}  runs: {:4}  saved: {} est. ({:.1}%)",
                         r.date,
                         r.commands,
-                        format_num(r.tokens_saved),
+                        units.tokens(r.tokens_saved),
                         r.savings_pct
                     );
                 }
             }
             0
         }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
+    }
+}
+
+fn cmd_gain_export(conn: &rusqlite::Connection, format: FormatArg, query: &GainQuery) -> i32 {
+    let format = match format {
+        FormatArg::Csv => ExportFormat::Csv,
+        FormatArg::Jsonl => ExportFormat::Jsonl,
+    };
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let result = export::export_events(conn, query, format, &mut out)
+        .and_then(|()| std::io::Write::flush(&mut out).map_err(Into::into));
+    match result {
+        Ok(()) => 0,
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
+    }
+}
+
+fn cmd_gain_prune(conn: &rusqlite::Connection, keep_days: Option<u32>) -> i32 {
+    let keep_days = keep_days.unwrap_or_else(|| RetentionPolicy::from_env().keep_days);
+    let result = retention::prune(conn, keep_days)
+        .and_then(|removed| retention::vacuum(conn).map(|()| removed));
+    match result {
+        Ok(removed) => {
+            eprintln!("[tokf] pruned {removed} events older than {keep_days} days");
+            0
+        }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
+    }
+}
+
+/// Record one `tokf run` in the tracking DB. Failures are reported, never fatal.
+pub fn record_run(record: &RunRecord<'_>) {
+    let Some(path) = tracking::db_path() else {
+        eprintln!("[tokf] tracking: cannot determine DB path");
+        return;
+    };
+    let conn = match tracking::open_db(&path) {
+        Ok(c) => c,
         Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
+            eprintln!("[tokf] tracking error (db open): {e:#}");
+            return;
         }
+    };
+    // Leading `VAR=value` words often carry secrets; never store them.
+    let args = &record.command_args[config::env_prefix_len(record.command_args)..];
+    let command = args.join(" ");
+    let event = tracking::build_event(
+        &command,
+        record.filter_name,
+        record.input_bytes,
+        record.output.len(),
+        record.filter_time_ms,
+        record.exit_code,
+    )
+    .with_output(record.output)
+    .with_args(args)
+    .with_fallback(record.used_fallback);
+    let event = match record.counter {
+        Some((label, value)) => event.with_counter(label, value),
+        None => event,
+    };
+    if let Err(e) = tracking::record_event(&conn, &event) {
+        eprintln!("[tokf] tracking error (record): {e:#}");
+    }
+    if let (Some(filter), Some((label, _))) = (record.filter_name, record.counter) {
+        print_trend(&conn, filter, label);
+    }
+    let policy = tracking::retention::RetentionPolicy::from_env();
+    if let Err(e) = tracking::retention::auto_prune(&conn, &policy) {
+        eprintln!("[tokf] tracking error (prune): {e:#}");
     }
 }
 
-fn format_num(n: i64) -> String {
-    // Simple thousands-separator formatting without extra deps.
-    let s = n.abs().to_string();
-    let chunks: Vec<&str> = s
-        .as_bytes()
-        .rchunks(3)
-        .rev()
-        .map(|c| std::str::from_utf8(c).unwrap_or(""))
-        .collect();
-    let formatted = chunks.join(",");
-    if n < 0 {
-        format!("-{formatted}")
-    } else {
-        formatted
+/// Append the `[trend]` line for this filter when `show_trends` is on.
+fn print_trend(conn: &rusqlite::Connection, filter_name: &str, label: &str) {
+    if !config::settings::load_settings().trends_enabled() {
+        return;
+    }
+    match trend::recent_values(conn, filter_name, label, trend::TREND_RUNS) {
+        Ok(values) => {
+            if let Some(line) = trend::format_trend(label, &values) {
+                println!("[tokf] {line}");
+            }
+        }
+        Err(e) => eprintln!("[tokf] tracking error (trend): {e:#}"),
+    }
+}
+
+/// How token and byte counts are printed: compact (`12.4k`, `1.2 MB`) by
+/// default, exact with locale thousands separators under `--exact`.
+struct Units {
+    grouping: Grouping,
+    exact: bool,
+}
+
+impl Units {
+    fn new(exact: bool) -> Self {
+        Self {
+            grouping: Grouping::detect(),
+            exact,
+        }
+    }
+
+    fn tokens(&self, n: i64) -> String {
+        if self.exact {
+            self.grouping.format(n)
+        } else {
+            format::compact(n)
+        }
+    }
+
+    fn bytes(&self, n: i64) -> String {
+        if self.exact {
+            format!("{} B", self.grouping.format(n))
+        } else {
+            format::bytes(n)
+        }
+    }
+
+    /// Run counts are always exact.
+    fn count(&self, n: i64) -> String {
+        self.grouping.format(n)
     }
 }
 
@@ -145,11 +431,28 @@ mod tests {
     use super::*;
 
     #[test]
-    fn format_num_basic() {
-        assert_eq!(format_num(0), "0");
-        assert_eq!(format_num(999), "999");
-        assert_eq!(format_num(1000), "1,000");
-        assert_eq!(format_num(84320), "84,320");
-        assert_eq!(format_num(-73080), "-73,080");
+    fn units_are_compact_unless_exact() {
+        let compact = Units {
+            grouping: Grouping::default(),
+            exact: false,
+        };
+        assert_eq!(compact.tokens(84_320), "84.3k");
+        assert_eq!(compact.bytes(1_234_567), "1.2 MB");
+        assert_eq!(compact.count(1_500), "1,500");
+
+        let exact = Units {
+            grouping: Grouping::with_separator("."),
+            exact: true,
+        };
+        assert_eq!(exact.tokens(84_320), "84.320");
+        assert_eq!(exact.bytes(4_096), "4.096 B");
+    }
+
+    #[test]
+    fn parse_date_accepts_iso_dates_only() {
+        assert_eq!(parse_date("2025-02-01"), Ok("2025-02-01".to_string()));
+        assert!(parse_date("2025-2-1").is_err());
+        assert!(parse_date("02/01/2025").is_err());
+        assert!(parse_date("2025-02-01'; --").is_err());
     }
 }
@@ -0,0 +1,11 @@
+//! Content fingerprints that must stay stable across runs and Rust versions.
+
+/// 64-bit FNV-1a — stable across Rust versions, unlike `DefaultHasher`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,230 @@
+//! Per-agent hook adapters.
+//!
+//! Each supported agent has its own hook event name, shell tool, input and
+//! response JSON, and config file. An [`Agent`] translates between those and
+//! the shared [`HookEvent`]; the rewrite itself is agent-independent.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::settings;
+use super::shim::SHIM_FILE;
+use super::types::{CodexHookInput, HookEvent, HookInput, HookResponse};
+
+/// A coding agent tokf can install a hook for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Agent {
+    /// Claude Code: `PreToolUse` on `Bash`, `.claude/settings.json`.
+    #[default]
+    Claude,
+    /// Gemini CLI: `BeforeTool` on `run_shell_command`, `.gemini/settings.json`.
+    Gemini,
+    /// Codex CLI: `PreToolUse` on `shell`, `.codex/config.toml`.
+    Codex,
+}
+
+impl Agent {
+    pub const ALL: [Self; 3] = [Self::Claude, Self::Gemini, Self::Codex];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Claude => "claude",
+            Self::Gemini => "gemini",
+            Self::Codex => "codex",
+        }
+    }
+
+    /// Hook event fired before a tool runs.
+    pub const fn event(self) -> &'static str {
+        match self {
+            Self::Claude | Self::Codex => "PreToolUse",
+            Self::Gemini => "BeforeTool",
+        }
+    }
+
+    /// Name of the agent's shell tool, used as the hook matcher.
+    pub const fn shell_tool(self) -> &'static str {
+        match self {
+            Self::Claude => "Bash",
+            Self::Gemini => "run_shell_command",
+            Self::Codex => "shell",
+        }
+    }
+
+    /// Shim file name inside a hook directory. Claude Code keeps the
+    /// unprefixed name so existing installs stay valid.
+    pub fn shim_file(self) -> String {
+        match self {
+            Self::Claude => SHIM_FILE.to_string(),
+            other => format!("{}-{SHIM_FILE}", other.name()),
+        }
+    }
+
+    /// Arguments the shim passes to `tokf`.
+    pub(crate) fn handle_args(self) -> String {
+        match self {
+            Self::Claude => "hook handle".to_string(),
+            other => format!("hook handle --agent {}", other.name()),
+        }
+    }
+
+    /// The agent's config file for a project-local or global install.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the home or current directory is unknown.
+    pub fn config_path(self, global: bool) -> anyhow::Result<PathBuf> {
+        let base = if global {
+            if self == Self::Codex
+                && let Some(home) = std::env::var_os("CODEX_HOME").filter(|h| !h.is_empty())
+            {
+                return Ok(PathBuf::from(home).join("config.toml"));
+            }
+            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("could not determine home directory"))?
+        } else {
+            std::env::current_dir()?
+        };
+        Ok(match self {
+            Self::Claude => base.join(".claude/settings.json"),
+            Self::Gemini => base.join(".gemini/settings.json"),
+            Self::Codex => base.join(".codex/config.toml"),
+        })
+    }
+
+    /// Parse hook input JSON into the shared event.
+    pub(crate) fn parse_event(self, json: &str) -> Option<HookEvent> {
+        match self {
+            Self::Claude | Self::Gemini => serde_json::from_str::<HookInput>(json)
+                .ok()
+                .map(HookEvent::from),
+            Self::Codex => serde_json::from_str::<CodexHookInput>(json)
+                .ok()
+                .map(HookEvent::from),
+        }
+    }
+
+    /// The response telling the agent to run `rewritten` instead.
+    pub(crate) fn response(self, event: &HookEvent, rewritten: String) -> serde_json::Value {
+        match self {
+            Self::Claude => {
+                serde_json::to_value(HookResponse::rewrite(rewritten)).unwrap_or_default()
+            }
+            Self::Gemini => serde_json::json!({
+                "decision": "allow",
+                "hookSpecificOutput": {
+                    "hookEventName": self.event(),
+                    "tool_input": { "command": rewritten }
+                }
+            }),
+            Self::Codex => {
+                let mut argv = if event.shell.is_empty() {
+                    vec!["sh".to_string(), "-c".to_string()]
+                } else {
+                    event.shell.clone()
+                };
+                argv.push(rewritten);
+                serde_json::json!({
+                    "hookSpecificOutput": {
+                        "hookEventName": self.event(),
+                        "permissionDecision": "allow",
+                        "updatedInput": { "command": argv }
+                    }
+                })
+            }
+        }
+    }
+
+    /// Register `hook_command` in the agent's config file.
+    pub(crate) fn register(self, config_path: &Path, hook_command: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Claude | Self::Gemini => {
+                settings::patch_json(config_path, self.event(), self.shell_tool(), hook_command)
+            }
+            Self::Codex => {
+                settings::patch_toml(config_path, self.event(), self.shell_tool(), hook_command)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Agent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Agent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or_else(|| format!("unknown agent \"{s}\" (expected claude, gemini or codex)"))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn event(agent: Agent, json: &str) -> HookEvent {
+        agent.parse_event(json).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for agent in Agent::ALL {
+            assert_eq!(agent.name().parse::<Agent>().unwrap(), agent);
+        }
+        assert!("cursor".parse::<Agent>().is_err());
+    }
+
+    #[test]
+    fn shim_files_are_distinct() {
+        assert_eq!(Agent::Claude.shim_file(), SHIM_FILE);
+        assert_eq!(Agent::Gemini.shim_file(), format!("gemini-{SHIM_FILE}"));
+        assert_ne!(Agent::Gemini.shim_file(), Agent::Codex.shim_file());
+    }
+
+    #[test]
+    fn gemini_response_replaces_tool_input() {
+        let e = event(
+            Agent::Gemini,
+            r#"{"hook_event_name":"BeforeTool","tool_name":"run_shell_command","tool_input":{"command":"git status"}}"#,
+        );
+        assert_eq!(e.tool_name, "run_shell_command");
+        let value = Agent::Gemini.response(&e, "tokf run git status".to_string());
+        assert_eq!(value["decision"], "allow");
+        assert_eq!(value["hookSpecificOutput"]["hookEventName"], "BeforeTool");
+        assert_eq!(
+            value["hookSpecificOutput"]["tool_input"]["command"],
+            "tokf run git status"
+        );
+    }
+
+    #[test]
+    fn codex_response_keeps_the_shell() {
+        let e = event(
+            Agent::Codex,
+            r#"{"tool_name":"shell","tool_input":{"command":["zsh","-lc","cargo test"]}}"#,
+        );
+        let value = Agent::Codex.response(&e, "tokf run cargo test".to_string());
+        assert_eq!(
+            value["hookSpecificOutput"]["updatedInput"]["command"],
+            serde_json::json!(["zsh", "-lc", "tokf run cargo test"])
+        );
+
+        let e = event(
+            Agent::Codex,
+            r#"{"tool_name":"shell","tool_input":{"command":["cargo","test"]}}"#,
+        );
+        let value = Agent::Codex.response(&e, "tokf run cargo test".to_string());
+        assert_eq!(
+            value["hookSpecificOutput"]["updatedInput"]["command"],
+            serde_json::json!(["sh", "-c", "tokf run cargo test"])
+        );
+    }
+}
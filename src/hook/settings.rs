@@ -0,0 +1,338 @@
+//! Registering the hook in an agent's config file.
+//!
+//! Every supported agent groups hooks the same way — `hooks.<Event>` is a
+//! list of `{ matcher, hooks = [{ type = "command", command }] }` — in JSON
+//! (Claude Code, Gemini CLI) or TOML (Codex CLI). Re-installing replaces the
+//! previous tokf entry and leaves everything else alone.
+
+use std::path::Path;
+
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+
+/// Whether a hook command is one tokf installed.
+fn is_tokf_command(cmd: &str) -> bool {
+    cmd.contains("tokf") && cmd.contains("hook")
+}
+
+/// Add the hook to a JSON settings file.
+pub fn patch_json(
+    settings_path: &Path,
+    event: &str,
+    matcher: &str,
+    hook_command: &str,
+) -> anyhow::Result<()> {
+    let name = file_name(settings_path);
+    let mut settings: serde_json::Value = if settings_path.exists() {
+        let content = std::fs::read_to_string(settings_path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("corrupt {name} at {}: {e}", settings_path.display()))?
+    } else {
+        serde_json::json!({})
+    };
+
+    let tokf_hook_entry = serde_json::json!({
+        "matcher": matcher,
+        "hooks": [{ "type": "command", "command": hook_command }]
+    });
+
+    // Get or create the hooks.<event> array
+    let hooks = settings
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{name} is not an object"))?
+        .entry("hooks")
+        .or_insert_with(|| serde_json::json!({}));
+
+    let entries = hooks
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{name} hooks is not an object"))?
+        .entry(event)
+        .or_insert_with(|| serde_json::json!([]));
+
+    let arr = entries
+        .as_array_mut()
+        .ok_or_else(|| anyhow::anyhow!("hooks.{event} is not an array"))?;
+
+    // Remove any existing tokf hook entries (idempotent install)
+    arr.retain(|entry| {
+        let dominated_by_tokf =
+            entry
+                .get("hooks")
+                .and_then(|h| h.as_array())
+                .is_some_and(|hooks| {
+                    hooks.iter().any(|h| {
+                        h.get("command")
+                            .and_then(serde_json::Value::as_str)
+                            .is_some_and(is_tokf_command)
+                    })
+                });
+        !dominated_by_tokf
+    });
+
+    arr.push(tokf_hook_entry);
+
+    write_atomic(settings_path, &serde_json::to_string_pretty(&settings)?)
+}
+
+/// Add the hook to a TOML config file, keeping its comments and layout.
+pub fn patch_toml(
+    config_path: &Path,
+    event: &str,
+    matcher: &str,
+    hook_command: &str,
+) -> anyhow::Result<()> {
+    let name = file_name(config_path);
+    let content = if config_path.exists() {
+        std::fs::read_to_string(config_path)?
+    } else {
+        String::new()
+    };
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| anyhow::anyhow!("corrupt {name} at {}: {e}", config_path.display()))?;
+
+    let hooks = doc
+        .entry("hooks")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("{name} hooks is not a table"))?;
+    hooks.set_implicit(true);
+    let entries = hooks
+        .entry(event)
+        .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| anyhow::anyhow!("hooks.{event} is not an array of tables"))?;
+
+    entries.retain(|entry| {
+        let dominated_by_tokf = entry
+            .get("hooks")
+            .and_then(Item::as_array)
+            .is_some_and(|hooks| {
+                hooks.iter().any(|h| {
+                    h.as_inline_table()
+                        .and_then(|t| t.get("command"))
+                        .and_then(Value::as_str)
+                        .is_some_and(is_tokf_command)
+                })
+            });
+        !dominated_by_tokf
+    });
+
+    let mut command = InlineTable::new();
+    command.insert("type", "command".into());
+    command.insert("command", hook_command.into());
+    let mut entry = Table::new();
+    entry.insert("matcher", toml_edit::value(matcher));
+    entry.insert(
+        "hooks",
+        toml_edit::value(toml_edit::Array::from_iter([command])),
+    );
+    entries.push(entry);
+
+    write_atomic(config_path, &doc.to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
+
+/// Write to a temp file next to `path`, then rename over it.
+fn write_atomic(path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = std::path::PathBuf::from(tmp_name);
+    std::fs::write(&tmp_path, content)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn patch_settings(settings_path: &Path, hook: &Path) -> anyhow::Result<()> {
+        patch_json(
+            settings_path,
+            "PreToolUse",
+            "Bash",
+            &format!("'{}'", hook.display()),
+        )
+    }
+
+    fn read_json(path: &Path) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn patch_creates_new_settings_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings = dir.path().join(".claude/settings.json");
+        let hook = dir.path().join("hook.sh");
+
+        patch_settings(&settings, &hook).unwrap();
+
+        let value = read_json(&settings);
+        let pre_tool = &value["hooks"]["PreToolUse"];
+        assert!(pre_tool.is_array());
+        assert_eq!(pre_tool.as_array().unwrap().len(), 1);
+        assert_eq!(pre_tool[0]["matcher"], "Bash");
+    }
+
+    #[test]
+    fn patch_preserves_existing_settings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings_path = dir.path().join("settings.json");
+        let hook = dir.path().join("hook.sh");
+
+        std::fs::write(
+            &settings_path,
+            r#"{"customKey": "customValue", "hooks": {"PostToolUse": []}}"#,
+        )
+        .unwrap();
+
+        patch_settings(&settings_path, &hook).unwrap();
+
+        let value = read_json(&settings_path);
+        assert_eq!(value["customKey"], "customValue");
+        assert!(value["hooks"]["PostToolUse"].is_array());
+        assert!(value["hooks"]["PreToolUse"].is_array());
+    }
+
+    #[test]
+    fn patch_idempotent_install() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings_path = dir.path().join("settings.json");
+        let hook = dir.path().join("tokf-hook.sh");
+
+        // Install twice
+        patch_settings(&settings_path, &hook).unwrap();
+        patch_settings(&settings_path, &hook).unwrap();
+
+        let value = read_json(&settings_path);
+        let arr = value["hooks"]["PreToolUse"].as_array().unwrap();
+        assert_eq!(
+            arr.len(),
+            1,
+            "should have exactly one hook entry after double install"
+        );
+    }
+
+    #[test]
+    fn patch_preserves_non_tokf_hooks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings_path = dir.path().join("settings.json");
+        let hook = dir.path().join("tokf-hook.sh");
+
+        std::fs::write(
+            &settings_path,
+            r#"{
+  "hooks": {
+    "PreToolUse": [
+      {
+        "matcher": "Bash",
+        "hooks": [{ "type": "command", "command": "/other/tool.sh" }]
+      }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+
+        patch_settings(&settings_path, &hook).unwrap();
+
+        let value = read_json(&settings_path);
+        let arr = value["hooks"]["PreToolUse"].as_array().unwrap();
+        assert_eq!(
+            arr.len(),
+            2,
+            "should have both the existing hook and the new tokf hook"
+        );
+    }
+
+    #[test]
+    fn patch_fails_on_corrupt_settings_json() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings_path = dir.path().join("settings.json");
+        let hook = dir.path().join("hook.sh");
+
+        std::fs::write(&settings_path, "not valid json {{{").unwrap();
+
+        let result = patch_settings(&settings_path, &hook);
+        assert!(result.is_err());
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains("corrupt settings.json"),
+            "expected corrupt error, got: {err}"
+        );
+    }
+
+    #[test]
+    fn patch_json_uses_the_agent_event() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let settings_path = dir.path().join("settings.json");
+        patch_json(
+            &settings_path,
+            "BeforeTool",
+            "run_shell_command",
+            "/x/.tokf/hooks/gemini-pre-tool-use.sh",
+        )
+        .unwrap();
+
+        let value = read_json(&settings_path);
+        assert_eq!(
+            value["hooks"]["BeforeTool"][0]["matcher"],
+            "run_shell_command"
+        );
+        assert!(value["hooks"].get("PreToolUse").is_none());
+    }
+
+    #[test]
+    fn patch_toml_keeps_comments_and_other_hooks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("config.toml");
+        std::fs::write(
+            &config,
+            "# my codex setup\nmodel = \"o4\"\n\n[[hooks.PreToolUse]]\nmatcher = \"shell\"\nhooks = [{ type = \"command\", command = \"/other/tool.sh\" }]\n",
+        )
+        .unwrap();
+        let hook = "'/home/u/.tokf/hooks/codex-pre-tool-use.sh'";
+
+        patch_toml(&config, "PreToolUse", "shell", hook).unwrap();
+        patch_toml(&config, "PreToolUse", "shell", hook).unwrap();
+
+        let content = std::fs::read_to_string(&config).unwrap();
+        assert!(content.starts_with("# my codex setup\n"), "{content}");
+        let value: toml::Table = toml::from_str(&content).unwrap();
+        assert_eq!(value["model"].as_str(), Some("o4"));
+        let entries = value["hooks"]["PreToolUse"].as_array().unwrap();
+        assert_eq!(entries.len(), 2, "{content}");
+        assert_eq!(entries[1]["matcher"].as_str(), Some("shell"));
+        assert_eq!(entries[1]["hooks"][0]["command"].as_str(), Some(hook));
+    }
+
+    #[test]
+    fn patch_toml_creates_missing_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".codex/config.toml");
+        patch_toml(&config, "PreToolUse", "shell", "tokf-hook").unwrap();
+        let content = std::fs::read_to_string(&config).unwrap();
+        assert!(content.contains("[[hooks.PreToolUse]]"), "{content}");
+    }
+
+    #[test]
+    fn patch_toml_fails_on_corrupt_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("config.toml");
+        std::fs::write(&config, "not = [valid").unwrap();
+        let err = patch_toml(&config, "PreToolUse", "shell", "x")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("corrupt config.toml"), "{err}");
+    }
+}
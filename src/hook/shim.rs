@@ -0,0 +1,260 @@
+use std::path::{Path, PathBuf};
+
+use super::agent::Agent;
+use crate::runner::shell::posix_quote;
+
+/// State of an installed hook shim relative to the running binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShimStatus {
+    /// No shim at that path.
+    Missing,
+    /// The shim pins the running binary.
+    Current,
+    /// The pinned binary exists but is not the running one.
+    Mismatch { pinned: PathBuf },
+    /// The pinned binary is gone; the shim is relying on `tokf` from `PATH`.
+    Stale { pinned: PathBuf },
+    /// The file exists but was not written by `tokf hook install`.
+    Unrecognized,
+}
+
+/// File name of the shim inside a hook directory.
+#[cfg(not(windows))]
+pub const SHIM_FILE: &str = "pre-tool-use.sh";
+
+/// File name of the shim inside a hook directory.
+#[cfg(windows)]
+pub const SHIM_FILE: &str = "pre-tool-use.cmd";
+
+/// Shim script pinning `tokf`, with a fallback to `tokf` on `PATH`, in this
+/// platform's format.
+///
+/// The fallback covers a pinned binary that has been moved or deleted. With
+/// neither available it exits 0 without output, which every supported agent
+/// treats as "no rewrite".
+pub fn render(tokf: &Path, agent: Agent) -> String {
+    if cfg!(windows) {
+        render_cmd(tokf, agent)
+    } else {
+        render_sh(tokf, agent)
+    }
+}
+
+/// The POSIX `sh` shim.
+pub fn render_sh(tokf: &Path, agent: Agent) -> String {
+    let quoted = posix_quote(&tokf.to_string_lossy());
+    let args = agent.handle_args();
+    format!(
+        "#!/bin/sh\n\
+         if [ -x {quoted} ]; then\n  exec {quoted} {args}\nfi\n\
+         command -v tokf >/dev/null 2>&1 && exec tokf {args}\n\
+         exit 0\n"
+    )
+}
+
+/// The Windows batch shim. Paths cannot contain `"`; `%` is doubled so it
+/// is not expanded as a variable.
+pub fn render_cmd(tokf: &Path, agent: Agent) -> String {
+    let quoted = format!("\"{}\"", tokf.to_string_lossy().replace('%', "%%"));
+    let args = agent.handle_args();
+    format!(
+        "@echo off\r\n\
+         if exist {quoted} {quoted} {args} & exit /b\r\n\
+         where tokf >nul 2>nul || exit /b 0\r\n\
+         tokf {args}\r\n"
+    )
+}
+
+/// Write the shim for the running binary, making it executable.
+pub(super) fn write(hook_dir: &Path, hook_script: &Path, agent: Agent) -> anyhow::Result<()> {
+    std::fs::create_dir_all(hook_dir)?;
+
+    let tokf_path = std::env::current_exe()?;
+    std::fs::write(hook_script, render(&tokf_path, agent))?;
+
+    // Make executable on Unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(hook_script, perms)?;
+    }
+
+    Ok(())
+}
+
+/// The binary path a shim pins: from its first `exec '<path>' hook handle ...`
+/// line, or the `if exist "<path>"` line of a batch shim. Also recognises
+/// shims written before the `PATH` fallback existed.
+pub fn pinned_binary(content: &str) -> Option<PathBuf> {
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("if exist \"") {
+            let (path, _) = rest.split_once('"')?;
+            return Some(PathBuf::from(path.replace("%%", "%")));
+        }
+        let (quoted, _) = line.strip_prefix("exec ")?.rsplit_once(" hook handle")?;
+        let inner = quoted.strip_prefix('\'')?.strip_suffix('\'')?;
+        Some(PathBuf::from(inner.replace("'\\''", "'")))
+    })
+}
+
+/// Compare the shim at `hook_script` with the `current` binary.
+pub fn check(hook_script: &Path, current: &Path) -> ShimStatus {
+    let Ok(content) = std::fs::read_to_string(hook_script) else {
+        return ShimStatus::Missing;
+    };
+    let Some(pinned) = pinned_binary(&content) else {
+        return ShimStatus::Unrecognized;
+    };
+    if !pinned.exists() {
+        return ShimStatus::Stale { pinned };
+    }
+    if same_file(&pinned, current) {
+        ShimStatus::Current
+    } else {
+        ShimStatus::Mismatch { pinned }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_creates_executable_script() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hook_dir = dir.path().join("hooks");
+        let hook_script = hook_dir.join(SHIM_FILE);
+
+        write(&hook_dir, &hook_script, Agent::Claude).unwrap();
+
+        let content = std::fs::read_to_string(&hook_script).unwrap();
+        #[cfg(not(windows))]
+        assert!(content.starts_with("#!/bin/sh\n"));
+        assert!(
+            content.contains("hook handle"),
+            "expected 'hook handle' in script, got: {content}"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = std::fs::metadata(&hook_script).unwrap().permissions();
+            assert!(perms.mode() & 0o111 != 0, "script should be executable");
+        }
+    }
+
+    #[test]
+    fn write_quotes_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hook_dir = dir.path().join("hooks");
+        let hook_script = hook_dir.join(SHIM_FILE);
+
+        write(&hook_dir, &hook_script, Agent::Claude).unwrap();
+
+        let content = std::fs::read_to_string(&hook_script).unwrap();
+        // The pinned path should be quoted
+        #[cfg(not(windows))]
+        let expected = "exec '";
+        #[cfg(windows)]
+        let expected = "if exist \"";
+        assert!(
+            content.contains(expected),
+            "expected quoted path in script, got: {content}"
+        );
+    }
+
+    #[test]
+    fn rendered_shim_falls_back_to_path() {
+        let content = render_sh(Path::new("/opt/tokf"), Agent::Claude);
+        assert!(content.contains("if [ -x '/opt/tokf' ]"));
+        assert!(content.contains("command -v tokf"));
+    }
+
+    #[test]
+    fn batch_shim_falls_back_to_path() {
+        let content = render_cmd(Path::new(r"C:\Tools\tokf.exe"), Agent::Claude);
+        assert!(content.starts_with("@echo off\r\n"));
+        assert!(
+            content.contains(r#"if exist "C:\Tools\tokf.exe" "C:\Tools\tokf.exe" hook handle"#)
+        );
+        assert!(content.contains("where tokf >nul 2>nul || exit /b 0"));
+    }
+
+    #[test]
+    fn pinned_binary_round_trips_quotes() {
+        let path = Path::new("/Users/o'brien/bin/tokf");
+        assert_eq!(
+            pinned_binary(&render_sh(path, Agent::Claude)).as_deref(),
+            Some(path)
+        );
+    }
+
+    #[test]
+    fn pinned_binary_reads_batch_shim() {
+        let path = Path::new(r"C:\Users\100%\tokf.exe");
+        let content = render_cmd(path, Agent::Claude);
+        assert!(content.contains("100%%"));
+        assert_eq!(pinned_binary(&content).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn agent_shims_pass_the_agent() {
+        let path = Path::new("/opt/tokf");
+        let content = render_sh(path, Agent::Gemini);
+        assert!(content.contains("exec '/opt/tokf' hook handle --agent gemini\n"));
+        assert!(content.contains("exec tokf hook handle --agent gemini\n"));
+        assert_eq!(pinned_binary(&content).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn pinned_binary_reads_legacy_shim() {
+        let legacy = "#!/bin/sh\nexec '/usr/local/bin/tokf' hook handle\n";
+        assert_eq!(
+            pinned_binary(legacy),
+            Some(PathBuf::from("/usr/local/bin/tokf"))
+        );
+        assert_eq!(pinned_binary("#!/bin/sh\necho hi\n"), None);
+    }
+
+    #[test]
+    fn check_classifies_shims() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = dir.path().join("pre-tool-use.sh");
+        let current = dir.path().join("tokf");
+        let other = dir.path().join("tokf-old");
+        std::fs::write(&current, "").unwrap();
+        std::fs::write(&other, "").unwrap();
+
+        assert_eq!(check(&script, &current), ShimStatus::Missing);
+
+        std::fs::write(&script, render(&current, Agent::Claude)).unwrap();
+        assert_eq!(check(&script, &current), ShimStatus::Current);
+
+        std::fs::write(&script, render(&other, Agent::Claude)).unwrap();
+        assert_eq!(
+            check(&script, &current),
+            ShimStatus::Mismatch {
+                pinned: other.clone()
+            }
+        );
+
+        std::fs::remove_file(&other).unwrap();
+        assert_eq!(
+            check(&script, &current),
+            ShimStatus::Stale { pinned: other }
+        );
+
+        std::fs::write(&script, "#!/bin/sh\necho custom\n").unwrap();
+        assert_eq!(check(&script, &current), ShimStatus::Unrecognized);
+    }
+}
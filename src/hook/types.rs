@@ -1,5 +1,29 @@
 use serde::{Deserialize, Serialize};
 
+use crate::runner::shell::posix_quote;
+
+/// A tool call as every agent adapter sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookEvent {
+    pub tool_name: String,
+    /// The shell script the tool will run, if it is a shell tool.
+    pub command: Option<String>,
+    /// For agents that send an argv (Codex): the shell and flag the script
+    /// is passed to, e.g. `["bash", "-lc"]`. Empty when the agent sends a
+    /// plain command string or an argv that is not a shell invocation.
+    pub shell: Vec<String>,
+}
+
+impl From<HookInput> for HookEvent {
+    fn from(input: HookInput) -> Self {
+        Self {
+            tool_name: input.tool_name,
+            command: input.tool_input.command,
+            shell: Vec::new(),
+        }
+    }
+}
+
 /// Claude Code `PreToolUse` hook input (read from stdin).
 #[derive(Debug, Clone, Deserialize)]
 pub struct HookInput {
@@ -13,6 +37,73 @@ pub struct ToolInput {
     pub command: Option<String>,
 }
 
+/// Codex CLI hook input: like [`HookInput`], but the shell tool's `command`
+/// is usually an argv.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CodexHookInput {
+    pub tool_name: String,
+    pub tool_input: CodexToolInput,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CodexToolInput {
+    pub command: Option<CodexCommand>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CodexCommand {
+    Script(String),
+    Argv(Vec<String>),
+}
+
+impl From<CodexHookInput> for HookEvent {
+    fn from(input: CodexHookInput) -> Self {
+        let (shell, command) = match input.tool_input.command {
+            Some(CodexCommand::Script(script)) => (Vec::new(), Some(script)),
+            Some(CodexCommand::Argv(argv)) => {
+                let (shell, script) = split_shell_argv(argv);
+                (shell, Some(script))
+            }
+            None => (Vec::new(), None),
+        };
+        Self {
+            tool_name: input.tool_name,
+            command,
+            shell,
+        }
+    }
+}
+
+/// Split `["bash", "-lc", "<script>"]` into the shell and its script. Any
+/// other argv becomes a script of its words, quoted where needed.
+fn split_shell_argv(mut argv: Vec<String>) -> (Vec<String>, String) {
+    let is_shell = argv.len() == 3
+        && matches!(argv[1].as_str(), "-c" | "-lc")
+        && matches!(
+            argv[0].rsplit('/').next(),
+            Some("sh" | "bash" | "zsh" | "dash")
+        );
+    if is_shell {
+        let script = argv.pop().unwrap_or_default();
+        return (argv, script);
+    }
+    let words: Vec<String> = argv.iter().map(|w| quote_word(w)).collect();
+    (Vec::new(), words.join(" "))
+}
+
+fn quote_word(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        posix_quote(word)
+    }
+}
+
 /// Response to send back when rewriting a command.
 #[derive(Debug, Clone, Serialize)]
 pub struct HookResponse {
@@ -103,6 +194,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn codex_shell_argv_is_split_into_shell_and_script() {
+        let json = r#"{"tool_name":"shell","tool_input":{"command":["bash","-lc","git status"]}}"#;
+        let event = HookEvent::from(serde_json::from_str::<CodexHookInput>(json).unwrap());
+        assert_eq!(event.command.as_deref(), Some("git status"));
+        assert_eq!(event.shell, ["bash", "-lc"]);
+    }
+
+    #[test]
+    fn codex_plain_argv_is_joined_with_quoting() {
+        let json = r#"{"tool_name":"shell","tool_input":{"command":["git","commit","-m","a b"]}}"#;
+        let event = HookEvent::from(serde_json::from_str::<CodexHookInput>(json).unwrap());
+        assert_eq!(event.command.as_deref(), Some("git commit -m 'a b'"));
+        assert!(event.shell.is_empty());
+
+        let json = r#"{"tool_name":"shell","tool_input":{"command":"cargo test"}}"#;
+        let event = HookEvent::from(serde_json::from_str::<CodexHookInput>(json).unwrap());
+        assert_eq!(event.command.as_deref(), Some("cargo test"));
+    }
+
     #[test]
     fn deserialize_extra_fields_ignored() {
         let json = r#"{"tool_name":"Bash","tool_input":{"command":"ls","timeout":5000},"session_id":"abc"}"#;
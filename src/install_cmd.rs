@@ -0,0 +1,81 @@
+//! `tokf install <source>` and `tokf update`: shared filter packs.
+
+use clap::Args;
+
+use tokf::registry::{self, Installed, Source};
+
+use crate::cli_error::{self, ErrorKind};
+
+#[derive(Args)]
+pub struct InstallArgs {
+    /// Git URL (optionally `#<branch-or-tag>`) or `.tar.gz` / `.tgz` / `.tar` URL
+    source: String,
+    /// Directory name for the pack (default: derived from the URL)
+    #[arg(long)]
+    name: Option<String>,
+    /// Replace an existing filters dir of that name that no pack installed
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+pub struct UpdateArgs {
+    /// Only update this pack (default: every installed pack)
+    name: Option<String>,
+}
+
+pub fn cmd_install(args: &InstallArgs) -> i32 {
+    let source = Source::parse(&args.source);
+    let Some(name) = args.name.clone().or_else(|| source.default_name()) else {
+        return cli_error::report(
+            ErrorKind::Other,
+            &format_args!(
+                "cannot derive a pack name from \"{}\"; pass --name",
+                args.source
+            ),
+        );
+    };
+    match registry::install(&source, &name, args.force) {
+        Ok(installed) => {
+            report(&installed);
+            0
+        }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
+    }
+}
+
+pub fn cmd_update(args: &UpdateArgs) -> i32 {
+    let results = match registry::update(args.name.as_deref()) {
+        Ok(results) => results,
+        Err(e) => return cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
+    };
+    if results.is_empty() {
+        eprintln!("[tokf] no packs installed (see `tokf install`)");
+        return 0;
+    }
+    let mut failed = 0;
+    for (name, result) in results {
+        match result {
+            Ok(installed) => report(&installed),
+            Err(e) => {
+                failed += 1;
+                eprintln!("[tokf] update of pack \"{name}\" failed: {e:#}");
+            }
+        }
+    }
+    i32::from(failed > 0)
+}
+
+fn report(installed: &Installed) {
+    let entry = &installed.entry;
+    let what = match installed.previous.as_deref() {
+        None => "installed",
+        Some(v) if v == entry.version => "unchanged",
+        Some(_) => "updated",
+    };
+    eprintln!(
+        "[tokf] {what} pack \"{}\" ({} filters) at {}",
+        entry.name, installed.filters, entry.version
+    );
+    eprintln!("[tokf]   into {}", installed.dir.display());
+}
@@ -0,0 +1,88 @@
+//! JSON shapes for `tokf ls --json`, `tokf which --json` and
+//! `tokf show --resolved --json`, for editor plugins and scripts.
+//!
+//! Fields are only ever added, never renamed or removed.
+
+use serde::Serialize;
+
+use tokf::config::ResolvedFilter;
+use tokf::config::types::FilterConfig;
+
+use crate::cli_error::{self, ErrorKind};
+
+/// One filter as `ls` and `which` report it.
+#[derive(Serialize)]
+pub struct FilterEntry {
+    /// Relative path without extension (`git/push`), as `tokf show` takes it.
+    pub name: String,
+    /// The first command pattern.
+    pub command: String,
+    pub patterns: Vec<String>,
+    /// `"local"`, `"user"` or `"built-in"`.
+    pub priority: &'static str,
+    pub specificity: usize,
+    /// The filter file, or `<built-in>/…` for embedded filters.
+    pub source: String,
+    /// `which --all` only: `"wins"`, `"merged"` or `"shadowed"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+}
+
+impl FilterEntry {
+    pub fn new(filter: &ResolvedFilter) -> Self {
+        Self {
+            name: display_name(filter),
+            command: filter.config.command.first().to_string(),
+            patterns: filter.config.command.patterns().to_vec(),
+            priority: filter.priority_label(),
+            specificity: filter.specificity(),
+            source: filter.source_path.display().to_string(),
+            status: None,
+        }
+    }
+}
+
+/// `tokf show --resolved --json`: the effective config and the files it was
+/// built from.
+#[derive(Serialize)]
+pub struct ResolvedEntry<'a> {
+    pub name: String,
+    pub source: String,
+    pub merged: Vec<String>,
+    pub config: &'a FilterConfig,
+}
+
+impl<'a> ResolvedEntry<'a> {
+    pub fn new(filter: &'a ResolvedFilter) -> Self {
+        Self {
+            name: display_name(filter),
+            source: filter.source_path.display().to_string(),
+            merged: filter
+                .overlays
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            config: &filter.config,
+        }
+    }
+}
+
+/// Relative path without the `.toml` extension.
+pub fn display_name(filter: &ResolvedFilter) -> String {
+    filter
+        .relative_path
+        .with_extension("")
+        .display()
+        .to_string()
+}
+
+/// Print `value` as pretty JSON on stdout; 0 on success.
+pub fn print(value: &impl Serialize) -> i32 {
+    match serde_json::to_string_pretty(value) {
+        Ok(out) => {
+            crate::stdout::print_line(&out);
+            0
+        }
+        Err(e) => cli_error::report(ErrorKind::Other, &e),
+    }
+}
@@ -0,0 +1,63 @@
+//! `tokf last`: print the output of the most recent run saved under
+//! `.tokf/last-run/`, so the details a filter hid can be read without
+//! running the command again.
+
+use clap::Args;
+
+use crate::cli_error::{self, ErrorKind};
+use crate::{diff, last_run, stdout};
+
+#[derive(Args)]
+pub struct LastArgs {
+    /// Print the unfiltered output (the default)
+    #[arg(long, conflicts_with_all = ["filtered", "diff"])]
+    raw: bool,
+    /// Print what the filter printed
+    #[arg(long, conflicts_with = "diff")]
+    filtered: bool,
+    /// Print the raw output against the filtered one as a unified diff
+    #[arg(long)]
+    diff: bool,
+}
+
+pub fn cmd_last(args: &LastArgs) -> i32 {
+    let Some(dir) = last_run::dir() else {
+        return cli_error::report(
+            ErrorKind::Other,
+            &format_args!("could not determine the current directory"),
+        );
+    };
+    let Some(log) = last_run::logs(&dir).pop() else {
+        return cli_error::report(
+            ErrorKind::Other,
+            &format_args!(
+                "no saved run in {} (use `tokf run --save-raw` or `save_raw = true`)",
+                dir.display()
+            ),
+        );
+    };
+    let read = |path: &std::path::Path| {
+        std::fs::read_to_string(path).map_err(|e| {
+            cli_error::report(
+                ErrorKind::Other,
+                &format_args!("failed to read {}: {e}", path.display()),
+            )
+        })
+    };
+    let text = if args.diff {
+        read(&log).and_then(|raw| {
+            read(&last_run::filtered_path(&log)).map(|filtered| diff::render(&raw, &filtered))
+        })
+    } else if args.filtered {
+        read(&last_run::filtered_path(&log))
+    } else {
+        read(&log)
+    };
+    match text {
+        Ok(text) => {
+            stdout::print_line(text.trim_end_matches('\n'));
+            0
+        }
+        Err(code) => code,
+    }
+}
@@ -0,0 +1,109 @@
+//! `.tokf/last-run/`: the raw output of runs whose filter may have hidden
+//! the details (`save_raw = true` or `tokf run --save-raw`).
+//!
+//! Each run gets a `<unix millis>.log` file, and the filtered output ends with
+//! a pointer to it. What the filter printed goes next to it in
+//! `<unix millis>.filtered`, for `tokf last`. Only the newest [`KEEP`] runs
+//! are kept.
+
+use std::path::{Path, PathBuf};
+
+/// How many runs to keep; older ones are removed on save.
+const KEEP: usize = 20;
+
+/// `.tokf/last-run/` under the current directory.
+pub fn dir() -> Option<PathBuf> {
+    std::env::current_dir()
+        .ok()
+        .map(|cwd| cwd.join(".tokf/last-run"))
+}
+
+/// Save `raw` and append a `full output: <path>` line to `output`. Nothing is
+/// saved when the filter left the output unchanged.
+pub fn attach(raw: &str, output: &mut String) {
+    if raw == output {
+        return;
+    }
+    let Some(dir) = dir() else { return };
+    match save(&dir, raw, output) {
+        Ok(path) => {
+            let shown = std::env::current_dir()
+                .ok()
+                .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+                .unwrap_or(path);
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("full output: ");
+            output.push_str(&shown.to_string_lossy());
+        }
+        Err(e) => tracing::warn!("could not save raw output to {}: {e}", dir.display()),
+    }
+}
+
+fn save(dir: &Path, raw: &str, filtered: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let path = dir.join(format!("{millis}.log"));
+    std::fs::write(&path, raw)?;
+    std::fs::write(filtered_path(&path), filtered)?;
+    prune(dir);
+    Ok(path)
+}
+
+/// Where the filtered output of the run saved at `log` is kept.
+pub fn filtered_path(log: &Path) -> PathBuf {
+    log.with_extension("filtered")
+}
+
+/// The saved logs in `dir`, oldest first.
+pub fn logs(dir: &Path) -> Vec<PathBuf> {
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    // Millisecond names have the same width, so they sort by time.
+    logs.sort();
+    logs
+}
+
+fn prune(dir: &Path) {
+    let logs = logs(dir);
+    let excess = logs.len().saturating_sub(KEEP);
+    for old in &logs[..excess] {
+        let _ = std::fs::remove_file(old);
+        let _ = std::fs::remove_file(filtered_path(old));
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_keeps_the_newest_logs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for i in 0..KEEP + 3 {
+            save_at(tmp.path(), 1000 + i);
+        }
+        std::fs::write(tmp.path().join("notes.txt"), "").unwrap();
+        prune(tmp.path());
+        let left = logs(tmp.path());
+        assert_eq!(left.len(), KEEP);
+        assert!(left[0].ends_with("1003.log"));
+        assert!(!tmp.path().join("1002.filtered").exists());
+        assert!(tmp.path().join("1003.filtered").exists());
+        assert!(tmp.path().join("notes.txt").exists());
+    }
+
+    fn save_at(dir: &Path, millis: usize) {
+        let log = dir.join(format!("{millis}.log"));
+        std::fs::write(&log, "").unwrap();
+        std::fs::write(filtered_path(&log), "").unwrap();
+    }
+}
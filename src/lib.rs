@@ -1,7 +1,14 @@
 pub mod config;
+pub mod daemon;
 pub mod filter;
+pub mod format;
+mod hash;
 pub mod hook;
+pub mod paths;
+mod regex_cache;
+pub mod registry;
 pub mod rewrite;
 pub mod runner;
 pub mod skill;
+#[cfg(feature = "tracking")]
 pub mod tracking;
@@ -0,0 +1,72 @@
+//! `tokf lint <filter>`: quality warnings beyond what `tokf check` rejects.
+
+use std::path::PathBuf;
+
+use clap::Args;
+
+use tokf::config;
+use tokf::config::lint::warnings::{self, Warning};
+
+use crate::cli_error::{CliError, ErrorKind, ResultExt as _};
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Path to the filter file
+    filter_path: PathBuf,
+    /// Sample output of the command; skip patterns matching none of it are
+    /// reported (repeatable)
+    #[arg(long, value_name = "PATH")]
+    fixture: Vec<PathBuf>,
+    /// Output warnings as a JSON array
+    #[arg(long)]
+    json: bool,
+}
+
+/// Exit 0 when the filter is clean, 1 when there are warnings.
+pub fn cmd_lint(args: &LintArgs) -> i32 {
+    match run(args) {
+        Ok(found) => {
+            if args.json {
+                match serde_json::to_string_pretty(&found) {
+                    Ok(out) => println!("{out}"),
+                    Err(e) => return crate::cli_error::report(ErrorKind::Other, &e),
+                }
+            } else {
+                for w in &found {
+                    eprintln!(
+                        "[tokf] {}:{} [{}]",
+                        args.filter_path.display(),
+                        w.problem,
+                        w.rule
+                    );
+                }
+            }
+            i32::from(!found.is_empty())
+        }
+        Err(e) => e.report(),
+    }
+}
+
+fn run(args: &LintArgs) -> Result<Vec<Warning>, CliError> {
+    let path = &args.filter_path;
+    let cfg = config::try_load_filter(path)
+        .kind(ErrorKind::Config)?
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::FilterNotFound,
+                format_args!("file not found: {}", path.display()),
+            )
+        })?;
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))?;
+    let fixtures = args
+        .fixture
+        .iter()
+        .map(|f| {
+            std::fs::read(f)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", f.display()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(warnings::warnings(&source, &cfg, &fixtures))
+}
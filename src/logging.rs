@@ -0,0 +1,84 @@
+//! tokf's own diagnostics: `--log-level`, `--log-format` and `TOKF_LOG`.
+//!
+//! Warnings and errors are shown by default. `TOKF_LOG` (or `--log-level`,
+//! which wins) takes `tracing` filter directives, so `debug` turns on
+//! everything and `tokf::config=trace,warn` narrows it to one module. Hooks
+//! run without a command line of their own; `TOKF_LOG_FORMAT=json` switches
+//! them to one JSON object per event, easy to pick out of a transcript.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Filter used when neither `--log-level` nor `TOKF_LOG` is set.
+const DEFAULT_LEVEL: &str = "warn";
+
+/// How log events are written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `[tokf] warning: …`
+    #[default]
+    Text,
+    /// One JSON object per event, with timestamp, level and target
+    Json,
+}
+
+/// Install the global subscriber. `level` and `format` come from the command
+/// line and override `TOKF_LOG` / `TOKF_LOG_FORMAT`.
+pub fn init(level: Option<&str>, format: Option<LogFormat>) {
+    let directives = level
+        .map(str::to_string)
+        .or_else(|| std::env::var("TOKF_LOG").ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        eprintln!("[tokf] warning: invalid log level \"{directives}\": {e}");
+        EnvFilter::new(DEFAULT_LEVEL)
+    });
+    let format = format.unwrap_or_else(|| match std::env::var("TOKF_LOG_FORMAT").as_deref() {
+        Ok("json") => LogFormat::Json,
+        _ => LogFormat::Text,
+    });
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let _ = match format {
+        LogFormat::Text => builder.event_format(Text).try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+}
+
+/// `[tokf] <level>: message key=value`, matching tokf's other stderr lines.
+struct Text;
+
+impl<S, N> FormatEvent<S, N> for Text
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        write!(writer, "[tokf] {}", prefix(*event.metadata().level()))?;
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+const fn prefix(level: Level) -> &'static str {
+    match level {
+        Level::ERROR => "error: ",
+        Level::WARN => "warning: ",
+        Level::INFO => "",
+        Level::DEBUG => "debug: ",
+        _ => "trace: ",
+    }
+}
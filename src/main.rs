@@ -1,18 +1,44 @@
+mod audit;
+mod bench;
 mod cache_cmd;
+mod cli;
+mod cli_error;
+mod daemon_cmd;
+mod diff;
+mod doctor;
+mod edit_cmd;
+mod eject_cmd;
+mod explain;
+mod filter_file;
+#[cfg(feature = "tracking")]
 mod gain;
-
-use std::path::Path;
-
-use clap::{Parser, Subcommand};
+mod install_cmd;
+mod inventory;
+mod last_cmd;
+mod last_run;
+mod lint_cmd;
+mod logging;
+mod mcp;
+mod packs_cmd;
+mod sarif;
+mod shell_init;
+mod show;
+mod statusline;
+mod stdout;
+mod steps;
+#[cfg(feature = "ui")]
+mod ui;
+
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand};
 
 use tokf::config;
-use tokf::config::types::FilterConfig;
-use tokf::filter;
-use tokf::hook;
+use tokf::hook::{self, agent::Agent};
 use tokf::rewrite;
-use tokf::runner;
 use tokf::skill;
-use tokf::tracking;
+
+use cli_error::{ErrorFormat, ErrorKind};
 
 #[derive(Parser)]
 #[command(
@@ -37,22 +63,65 @@ struct Cli {
     #[arg(long, global = true)]
     no_cache: bool,
 
+    /// Ignore the embedded built-in filters (same as `use_builtin_filters = false`)
+    #[arg(long, global = true)]
+    no_builtin: bool,
+
+    /// How tokf reports its own errors (not the wrapped command's) on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
+    /// Which of tokf's own log events to show, as `TOKF_LOG` directives
+    /// (`debug`, `tokf::config=trace`); overrides `TOKF_LOG`
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// How log events are written to stderr; overrides `TOKF_LOG_FORMAT`
+    #[arg(long, global = true, value_enum)]
+    log_format: Option<logging::LogFormat>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Args)]
+struct RunOptions {
+    /// Kill the command after SECS seconds (overrides the filter's `timeout_secs`)
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// Run the command under a pseudo-terminal (same as `pty = true`)
+    #[arg(long)]
+    pty: bool,
+    /// Also print a diff of the raw and filtered output to stderr
+    #[arg(long)]
+    diff: bool,
+    /// Save the raw output to `.tokf/last-run/` and point to it (same as `save_raw = true`)
+    #[arg(long)]
+    save_raw: bool,
+    /// Write what the filter's `[[diagnostic]]` rules find in the raw output to PATH as SARIF
+    #[arg(long, value_name = "PATH")]
+    sarif: Option<PathBuf>,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run a command and filter its output
     Run {
+        #[command(flatten)]
+        opts: RunOptions,
         #[arg(trailing_var_arg = true, required = true)]
         command_args: Vec<String>,
     },
+    /// Print the output of the last run saved with `--save-raw` / `save_raw`
+    Last(last_cmd::LastArgs),
     /// Validate a filter TOML file
     Check {
         /// Path to the filter file
         filter_path: String,
     },
+    /// Warn about filter mistakes that still load: unreachable rules,
+    /// unused sections, overly broad patterns
+    Lint(lint_cmd::LintArgs),
     /// Apply a filter to a fixture file
     Test {
         /// Path to the filter file
@@ -64,7 +133,14 @@ enum Commands {
         exit_code: i32,
     },
     /// List available filters
-    Ls,
+    Ls {
+        /// List filter packs (directories with a `pack.toml`) instead
+        #[arg(long)]
+        packs: bool,
+        /// Output the filters as a JSON array
+        #[arg(long, conflicts_with = "packs")]
+        json: bool,
+    },
     /// Rewrite a command string (apply filter-derived rules)
     Rewrite {
         /// The command string to rewrite
@@ -74,13 +150,30 @@ enum Commands {
     Which {
         /// The command string to look up (e.g. "git push origin main")
         command: String,
+        /// List every matching filter in priority order, including shadowed ones
+        #[arg(long)]
+        all: bool,
+        /// Output as JSON (an object, or an array with --all)
+        #[arg(long)]
+        json: bool,
     },
     /// Show the TOML source of an active filter
     Show {
         /// Filter relative path without extension (e.g. "git/push")
         filter: String,
+        /// Print the effective config after `merge = true` filters are folded in
+        #[arg(long)]
+        resolved: bool,
+        /// With --resolved, print the effective config as JSON
+        #[arg(long, requires = "resolved")]
+        json: bool,
     },
-    /// Claude Code hook management
+    /// Open a filter in $EDITOR (copying a built-in to .tokf/filters/ first),
+    /// then check it
+    Edit(edit_cmd::EditArgs),
+    /// Copy a built-in filter to .tokf/filters/ (or the user dir) to customize it
+    Eject(eject_cmd::EjectArgs),
+    /// Agent hook management (Claude Code, Gemini CLI, Codex CLI)
     Hook {
         #[command(subcommand)]
         action: HookAction,
@@ -95,18 +188,42 @@ enum Commands {
         #[command(subcommand)]
         action: cache_cmd::CacheAction,
     },
-    /// Show token savings statistics
-    Gain {
-        /// Show daily breakdown
-        #[arg(long)]
-        daily: bool,
-        /// Show breakdown by filter
-        #[arg(long, name = "by-filter")]
-        by_filter: bool,
-        /// Output as JSON
-        #[arg(long)]
-        json: bool,
+    /// Measure tokf's own overhead on this machine
+    Bench {
+        #[command(subcommand)]
+        action: bench::BenchAction,
     },
+    /// Show token savings statistics
+    #[cfg(feature = "tracking")]
+    Gain(gain::GainArgs),
+    /// Apply a filter to a file's contents (e.g. a saved CI log)
+    FilterFile(filter_file::FilterFileArgs),
+    /// Compare two versions of a filter across fixtures: lines hidden,
+    /// lines revealed, and size deltas
+    Audit(audit::AuditArgs),
+    /// Browse filters and try them on sample output in a terminal UI
+    #[cfg(feature = "ui")]
+    Ui(ui::UiArgs),
+    /// Trace which filter handles a command and what each stage does to a
+    /// fixture
+    Explain(explain::ExplainArgs),
+    /// Check the local setup, e.g. hook shims pinned to a moved binary
+    Doctor,
+    /// Install a filter pack from a git URL or tarball into the user filters dir
+    Install(install_cmd::InstallArgs),
+    /// Re-fetch installed filter packs from their recorded sources
+    Update(install_cmd::UpdateArgs),
+    /// Serve `run_filtered` and `filter_text` as an MCP tool server on stdio
+    ServeMcp,
+    /// Keep resolved filters in memory and answer hook and `tokf run`
+    /// lookups over a Unix socket (runs in the foreground)
+    Daemon(daemon_cmd::DaemonArgs),
+    /// Print shell functions that run configured commands through `tokf run`
+    /// in an interactive terminal (`eval "$(tokf shell-init zsh)"`)
+    ShellInit(shell_init::ShellInitArgs),
+    /// Print a one-line status (tokens saved today, filter count) for
+    /// Claude Code's statusline or a shell prompt
+    Statusline(statusline::StatuslineArgs),
 }
 
 #[derive(Subcommand)]
@@ -121,381 +238,89 @@ enum SkillAction {
 
 #[derive(Subcommand)]
 enum HookAction {
-    /// Handle a `PreToolUse` hook invocation (reads JSON from stdin)
-    Handle,
-    /// Install the hook into Claude Code settings
+    /// Handle a pre-tool hook invocation (reads JSON from stdin)
+    Handle {
+        /// Agent sending the hook input: claude, gemini or codex
+        #[arg(long, default_value_t = Agent::Claude)]
+        agent: Agent,
+    },
+    /// Install the hook into an agent's settings
     Install {
-        /// Install globally (~/.config/tokf) instead of project-local (.tokf)
+        /// Install globally (~/.config/tokf, or `$TOKF_HOME`) instead of project-local (.tokf)
         #[arg(long)]
         global: bool,
+        /// Agent to install for: claude (.claude/settings.json), gemini
+        /// (.gemini/settings.json) or codex (.codex/config.toml)
+        #[arg(long, default_value_t = Agent::Claude)]
+        agent: Agent,
     },
 }
 
-/// Find the first filter that matches `command_args` using the discovery model.
-/// Returns `(Option<FilterConfig>, words_consumed)`.
-fn find_filter(
-    command_args: &[String],
-    verbose: bool,
-    no_cache: bool,
-) -> anyhow::Result<(Option<FilterConfig>, usize)> {
-    let search_dirs = config::default_search_dirs();
-    let resolved = if no_cache {
-        config::discover_all_filters(&search_dirs)?
-    } else {
-        config::cache::discover_with_cache(&search_dirs)?
-    };
-    let words: Vec<&str> = command_args.iter().map(String::as_str).collect();
-
-    for filter in &resolved {
-        if let Some(consumed) = filter.matches(&words) {
-            if verbose {
-                eprintln!(
-                    "[tokf] matched {} (command: \"{}\") in {}",
-                    filter.relative_path.display(),
-                    filter.config.command.first(),
-                    filter
-                        .source_path
-                        .parent()
-                        .map_or("?", |p| p.to_str().unwrap_or("?")),
-                );
-            }
-            return Ok((Some(filter.config.clone()), consumed));
-        }
-    }
-
-    if verbose {
-        eprintln!(
-            "[tokf] no filter found for '{}', passing through",
-            words.join(" ")
-        );
-    }
-    Ok((None, 0))
-}
-
-fn run_command(
-    filter_cfg: Option<&FilterConfig>,
-    words_consumed: usize,
-    command_args: &[String],
-    remaining_args: &[String],
-) -> anyhow::Result<runner::CommandResult> {
-    if let Some(cfg) = filter_cfg
-        && let Some(run_cmd) = &cfg.run
-    {
-        runner::execute_shell(run_cmd, remaining_args)
-    } else if words_consumed > 0 {
-        let cmd_str = command_args[..words_consumed].join(" ");
-        runner::execute(&cmd_str, remaining_args)
-    } else {
-        runner::execute(&command_args[0], remaining_args)
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn record_run(
-    command_args: &[String],
-    filter_name: Option<&str>,
-    input_bytes: usize,
-    output_bytes: usize,
-    filter_time_ms: u128,
-    exit_code: i32,
-) {
-    let Some(path) = tracking::db_path() else {
-        eprintln!("[tokf] tracking: cannot determine DB path");
-        return;
-    };
-    let conn = match tracking::open_db(&path) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("[tokf] tracking error (db open): {e:#}");
-            return;
-        }
-    };
-    let command = command_args.join(" ");
-    let event = tracking::build_event(
-        &command,
-        filter_name,
-        input_bytes,
-        output_bytes,
-        filter_time_ms,
-        exit_code,
-    );
-    if let Err(e) = tracking::record_event(&conn, &event) {
-        eprintln!("[tokf] tracking error (record): {e:#}");
-    }
-}
-
-fn cmd_run(command_args: &[String], cli: &Cli) -> anyhow::Result<i32> {
-    let (filter_cfg, words_consumed) = if cli.no_filter {
-        (None, 0)
-    } else {
-        find_filter(command_args, cli.verbose, cli.no_cache)?
-    };
-
-    let remaining_args: Vec<String> = if words_consumed > 0 {
-        command_args[words_consumed..].to_vec()
-    } else if command_args.len() > 1 {
-        command_args[1..].to_vec()
-    } else {
-        vec![]
-    };
-
-    let cmd_result = run_command(
-        filter_cfg.as_ref(),
-        words_consumed,
-        command_args,
-        &remaining_args,
-    )?;
-
-    let Some(cfg) = filter_cfg else {
-        let bytes = cmd_result.combined.len();
-        if !cmd_result.combined.is_empty() {
-            println!("{}", cmd_result.combined);
-        }
-        // filter_time_ms = 0: no filter was applied, not 0ms of filtering.
-        record_run(command_args, None, bytes, bytes, 0, cmd_result.exit_code);
-        return Ok(cmd_result.exit_code);
-    };
-
-    let input_bytes = cmd_result.combined.len();
-    let start = std::time::Instant::now();
-    let filtered = filter::apply(&cfg, &cmd_result, &remaining_args);
-    let elapsed = start.elapsed();
-
-    if cli.timing {
-        eprintln!("[tokf] filter took {:.1}ms", elapsed.as_secs_f64() * 1000.0);
-    }
-
-    let output_bytes = filtered.output.len();
-    if !filtered.output.is_empty() {
-        println!("{}", filtered.output);
-    }
-
-    let filter_name = cfg.command.first();
-    record_run(
-        command_args,
-        Some(filter_name),
-        input_bytes,
-        output_bytes,
-        elapsed.as_millis(),
-        cmd_result.exit_code,
-    );
-
-    Ok(cmd_result.exit_code)
-}
-
-fn cmd_check(filter_path: &Path) -> i32 {
-    match config::try_load_filter(filter_path) {
-        Ok(Some(cfg)) => {
-            eprintln!(
-                "[tokf] {} is valid (command: \"{}\")",
-                filter_path.display(),
-                cfg.command.first()
-            );
-            0
-        }
-        Ok(None) => {
-            eprintln!("[tokf] file not found: {}", filter_path.display());
-            1
-        }
-        Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }
-    }
-}
-
-fn cmd_test(
-    filter_path: &Path,
-    fixture_path: &Path,
-    exit_code: i32,
-    cli: &Cli,
-) -> anyhow::Result<i32> {
-    let cfg = config::try_load_filter(filter_path)?
-        .ok_or_else(|| anyhow::anyhow!("filter not found: {}", filter_path.display()))?;
-
-    let fixture = std::fs::read_to_string(fixture_path)
-        .map_err(|e| anyhow::anyhow!("failed to read fixture: {}: {e}", fixture_path.display()))?;
-    let combined = fixture.trim_end().to_string();
-
-    let cmd_result = runner::CommandResult {
-        stdout: String::new(),
-        stderr: String::new(),
-        exit_code,
-        combined,
-    };
-
-    let start = std::time::Instant::now();
-    let filtered = filter::apply(&cfg, &cmd_result, &[]);
-    let elapsed = start.elapsed();
-
-    if cli.timing {
-        eprintln!("[tokf] filter took {:.1}ms", elapsed.as_secs_f64() * 1000.0);
-    }
-
-    if !filtered.output.is_empty() {
-        println!("{}", filtered.output);
-    }
-
-    Ok(0)
-}
-
-// Note: cmd_ls, cmd_which, and cmd_show always use the cache. The --no-cache flag
-// only affects `tokf run`. Pass --no-cache to `tokf run` if you need uncached resolution.
-fn cmd_ls(verbose: bool) -> i32 {
-    let search_dirs = config::default_search_dirs();
-    let Ok(filters) = config::cache::discover_with_cache(&search_dirs) else {
-        eprintln!("[tokf] error: failed to discover filters");
-        return 1;
-    };
-
-    for filter in &filters {
-        // Display: relative path without .toml extension  →  command
-        let display_name = filter
-            .relative_path
-            .with_extension("")
-            .display()
-            .to_string();
-        println!(
-            "{display_name}  \u{2192}  {}",
-            filter.config.command.first()
-        );
-
-        if verbose {
-            eprintln!(
-                "[tokf]   source: {}  [{}]",
-                filter.source_path.display(),
-                filter.priority_label()
-            );
-            let patterns = filter.config.command.patterns();
-            if patterns.len() > 1 {
-                for p in patterns {
-                    eprintln!("[tokf]     pattern: \"{p}\"");
-                }
-            }
-        }
-    }
-
-    0
-}
-
-fn cmd_which(command: &str, verbose: bool) -> i32 {
-    let search_dirs = config::default_search_dirs();
-    let Ok(filters) = config::cache::discover_with_cache(&search_dirs) else {
-        eprintln!("[tokf] error: failed to discover filters");
-        return 1;
-    };
-
-    let words: Vec<&str> = command.split_whitespace().collect();
-
-    for filter in &filters {
-        if filter.matches(&words).is_some() {
-            let display_name = filter
-                .relative_path
-                .with_extension("")
-                .display()
-                .to_string();
-            println!(
-                "{}  [{}]  command: \"{}\"",
-                display_name,
-                filter.priority_label(),
-                filter.config.command.first()
-            );
-            if verbose {
-                eprintln!("[tokf] source: {}", filter.source_path.display());
-            }
-            return 0;
-        }
-    }
-
-    eprintln!("[tokf] no filter found for \"{command}\"");
-    1
-}
-
 fn main() {
     let cli = Cli::parse();
+    cli_error::set_format(cli.error_format);
+    logging::init(cli.log_level.as_deref(), cli.log_format);
+    let builtins = || config::settings::builtins_enabled(cli.no_builtin);
     let exit_code = match &cli.command {
-        Commands::Run { command_args } => cmd_run(command_args, &cli).unwrap_or_else(|e| {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }),
-        Commands::Check { filter_path } => cmd_check(Path::new(filter_path)),
+        Commands::Run { opts, command_args } => {
+            cli::run::cmd_run(command_args, opts, &cli).unwrap_or_else(|e| e.report())
+        }
+        Commands::Last(args) => last_cmd::cmd_last(args),
+        Commands::Check { filter_path } => cli::check::cmd_check(Path::new(filter_path)),
+        Commands::Lint(args) => lint_cmd::cmd_lint(args),
         Commands::Test {
             filter_path,
             fixture_path,
             exit_code,
-        } => cmd_test(
+        } => cli::check::cmd_test(
             Path::new(filter_path),
             Path::new(fixture_path),
             *exit_code,
             &cli,
         )
-        .unwrap_or_else(|e| {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }),
-        Commands::Ls => cmd_ls(cli.verbose),
-        Commands::Rewrite { command } => cmd_rewrite(command),
-        Commands::Which { command } => cmd_which(command, cli.verbose),
-        Commands::Show { filter } => cmd_show(filter),
+        .unwrap_or_else(|e| e.report()),
+        Commands::Ls { packs: true, .. } => packs_cmd::cmd_ls_packs(),
+        Commands::Ls { packs: false, json } => cli::ls::cmd_ls(cli.verbose, *json, builtins()),
+        Commands::Rewrite { command } => cmd_rewrite(command, builtins()),
+        Commands::Which { command, all, json } => {
+            cli::which::cmd_which(command, *all, cli.verbose, *json, builtins())
+        }
+        Commands::Show {
+            filter,
+            resolved,
+            json,
+        } => show::cmd_show(filter, *resolved, *json, builtins()),
+        Commands::Edit(args) => edit_cmd::cmd_edit(args, builtins()),
+        Commands::Eject(args) => eject_cmd::cmd_eject(args),
         Commands::Hook { action } => match action {
-            HookAction::Handle => cmd_hook_handle(),
-            HookAction::Install { global } => cmd_hook_install(*global),
+            HookAction::Handle { agent } => cmd_hook_handle(*agent),
+            HookAction::Install { global, agent } => cmd_hook_install(*global, *agent),
         },
         Commands::Skill { action } => match action {
             SkillAction::Install { global } => cmd_skill_install(*global),
         },
-        Commands::Cache { action } => cache_cmd::run_cache_action(action),
-        Commands::Gain {
-            daily,
-            by_filter,
-            json,
-        } => gain::cmd_gain(*daily, *by_filter, *json),
+        Commands::Cache { action } => cache_cmd::run_cache_action(action, builtins()),
+        Commands::Bench { action } => bench::run_bench_action(action),
+        #[cfg(feature = "tracking")]
+        Commands::Gain(args) => gain::cmd_gain(args),
+        Commands::FilterFile(args) => filter_file::cmd_filter_file(args, builtins()),
+        Commands::Audit(args) => audit::cmd_audit(args),
+        #[cfg(feature = "ui")]
+        Commands::Ui(args) => ui::cmd_ui(args, builtins()),
+        Commands::Explain(args) => explain::cmd_explain(args, cli.no_cache, builtins()),
+        Commands::Doctor => doctor::cmd_doctor(),
+        Commands::Install(args) => install_cmd::cmd_install(args),
+        Commands::Update(args) => install_cmd::cmd_update(args),
+        Commands::ServeMcp => mcp::cmd_serve_mcp(builtins()),
+        Commands::Daemon(args) => daemon_cmd::cmd_daemon(args),
+        Commands::ShellInit(args) => shell_init::cmd_shell_init(args, builtins()),
+        Commands::Statusline(args) => statusline::cmd_statusline(args, cli.no_cache, builtins()),
     };
     std::process::exit(exit_code);
 }
 
-fn cmd_show(filter: &str) -> i32 {
-    // Normalize: strip ".toml" suffix if present
-    let filter_name = filter.strip_suffix(".toml").unwrap_or(filter);
-
-    let search_dirs = config::default_search_dirs();
-    let Ok(filters) = config::cache::discover_with_cache(&search_dirs) else {
-        eprintln!("[tokf] error: failed to discover filters");
-        return 1;
-    };
-
-    let found = filters
-        .iter()
-        .find(|f| f.relative_path.with_extension("").to_string_lossy() == filter_name);
-
-    let Some(resolved) = found else {
-        eprintln!("[tokf] filter not found: {filter}");
-        return 1;
-    };
-
-    let content = if resolved.priority == u8::MAX {
-        if let Some(c) = config::get_embedded_filter(&resolved.relative_path) {
-            c.to_string()
-        } else {
-            eprintln!("[tokf] error: embedded filter not readable");
-            return 1;
-        }
-    } else {
-        match std::fs::read_to_string(&resolved.source_path) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("[tokf] error reading filter: {e}");
-                return 1;
-            }
-        }
-    };
-
-    print!("{content}");
-    0
-}
-
-fn cmd_rewrite(command: &str) -> i32 {
-    let result = rewrite::rewrite(command);
+fn cmd_rewrite(command: &str, include_builtin: bool) -> i32 {
+    let result = rewrite::rewrite(command, include_builtin);
     println!("{result}");
     0
 }
@@ -503,24 +328,18 @@ fn cmd_rewrite(command: &str) -> i32 {
 fn cmd_skill_install(global: bool) -> i32 {
     match skill::install(global) {
         Ok(()) => 0,
-        Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
     }
 }
 
-fn cmd_hook_handle() -> i32 {
-    hook::handle();
+fn cmd_hook_handle(agent: Agent) -> i32 {
+    hook::handle(agent);
     0
 }
 
-fn cmd_hook_install(global: bool) -> i32 {
-    match hook::install(global) {
+fn cmd_hook_install(global: bool, agent: Agent) -> i32 {
+    match hook::install(global, agent) {
         Ok(()) => 0,
-        Err(e) => {
-            eprintln!("[tokf] error: {e:#}");
-            1
-        }
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("{e:#}")),
     }
 }
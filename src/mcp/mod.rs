@@ -0,0 +1,173 @@
+//! `tokf serve-mcp`: tokf as an MCP tool server on stdin/stdout.
+//!
+//! Messages are newline-delimited JSON-RPC 2.0. Only the parts of the Model
+//! Context Protocol a tool server needs are implemented: `initialize`,
+//! `ping`, `tools/list` and `tools/call`. Notifications get no reply, and
+//! nothing but protocol messages is ever written to stdout — tokf's own
+//! notes still go to stderr.
+
+mod tools;
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{Value, json};
+
+use crate::cli_error::{self, ErrorKind};
+
+/// Protocol revisions this server speaks, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC error: code and message.
+type RpcError = (i64, String);
+
+pub fn cmd_serve_mcp(include_builtin: bool) -> i32 {
+    eprintln!("[tokf] MCP server ready on stdio");
+    match serve(io::stdin().lock(), io::stdout().lock(), include_builtin) {
+        Ok(()) => 0,
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("MCP transport: {e}")),
+    }
+}
+
+/// Answer requests from `reader` on `writer` until the client closes its end.
+fn serve(reader: impl BufRead, mut writer: impl Write, include_builtin: bool) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(&line, include_builtin) {
+            writeln!(writer, "{response}")?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The response to one message, or `None` for notifications.
+fn handle_message(line: &str, include_builtin: bool) -> Option<Value> {
+    let msg: Value = match serde_json::from_str(line) {
+        Ok(msg) => msg,
+        Err(e) => {
+            return Some(error_response(
+                &Value::Null,
+                PARSE_ERROR,
+                &format!("parse error: {e}"),
+            ));
+        }
+    };
+    // Without an id this is a notification (or a stray response): no reply.
+    let id = msg.get("id")?;
+    let Some(method) = msg.get("method").and_then(Value::as_str) else {
+        return Some(error_response(id, INVALID_REQUEST, "missing method"));
+    };
+    let params = msg.get("params").unwrap_or(&Value::Null);
+    Some(match dispatch(method, params, include_builtin) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn dispatch(method: &str, params: &Value, include_builtin: bool) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(initialize(params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools::definitions() })),
+        "tools/call" => tools::call(params, include_builtin),
+        _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+    }
+}
+
+/// Agree on the client's protocol revision when it is one we speak,
+/// otherwise offer the newest.
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "tokf", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Use run_filtered instead of a shell tool for build, test and \
+                         VCS commands: it returns the same result with far less output."
+    })
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn exchange(input: &str) -> Vec<Value> {
+        let mut out = Vec::new();
+        serve(input.as_bytes(), &mut out, false).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn initialize_negotiates_the_protocol_version() {
+        let replies = exchange(concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"initialize","params":{"protocolVersion":"1999-01-01"}}"#,
+            "\n",
+        ));
+        assert_eq!(replies.len(), 2, "notifications get no reply");
+        assert_eq!(replies[0]["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(replies[0]["result"]["serverInfo"]["name"], "tokf");
+        assert_eq!(
+            replies[1]["result"]["protocolVersion"],
+            PROTOCOL_VERSIONS[0]
+        );
+    }
+
+    #[test]
+    fn tools_list_offers_both_tools() {
+        let replies = exchange("{\"jsonrpc\":\"2.0\",\"id\":\"a\",\"method\":\"tools/list\"}\n");
+        let names: Vec<&str> = replies[0]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["run_filtered", "filter_text"]);
+        assert_eq!(replies[0]["id"], "a");
+    }
+
+    #[test]
+    fn protocol_errors_use_json_rpc_codes() {
+        let replies = exchange(concat!(
+            "not json\n",
+            r#"{"jsonrpc":"2.0","id":1,"method":"resources/list"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"nope"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":3}"#,
+            "\n",
+        ));
+        assert_eq!(replies[0]["error"]["code"], PARSE_ERROR);
+        assert!(replies[0]["id"].is_null());
+        assert_eq!(replies[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[2]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[3]["error"]["code"], INVALID_REQUEST);
+    }
+}
@@ -0,0 +1,321 @@
+//! The `run_filtered` and `filter_text` tools.
+//!
+//! Both go through the same filter resolution and application as `tokf run`
+//! and `tokf filter-file`. A tool that fails (a command that cannot be
+//! spawned, an unknown filter) answers with `isError` rather than a protocol
+//! error, so the agent sees why.
+
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use serde_json::{Value, json};
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+use super::{INVALID_PARAMS, RpcError};
+use crate::cli::run::RunRecord;
+use crate::filter_file::resolve_filter;
+
+pub fn definitions() -> Value {
+    json!([
+        {
+            "name": "run_filtered",
+            "title": "Run a command with filtered output",
+            "description": "Run one command (no pipes or shell operators) and return its output \
+                            condensed by the matching tokf filter, with the exit code. Output of \
+                            commands no filter matches is returned unchanged.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "Command line, e.g. \"cargo test --lib\"; quotes group words"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Kill the command after this many seconds"
+                    }
+                },
+                "required": ["command"]
+            },
+            "outputSchema": metadata_schema(),
+        },
+        {
+            "name": "filter_text",
+            "title": "Filter captured output",
+            "description": "Condense output captured elsewhere (a CI log, a previous run) with \
+                            a tokf filter.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "The output to filter" },
+                    "filter": {
+                        "type": "string",
+                        "description": "Filter name (\"cargo/test\") or a command it matches (\"cargo test\")"
+                    },
+                    "exit_code": {
+                        "type": "integer",
+                        "description": "Exit code of the command that produced the text (default 0)"
+                    }
+                },
+                "required": ["text", "filter"]
+            },
+            "outputSchema": metadata_schema(),
+        }
+    ])
+}
+
+fn metadata_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "exit_code": { "type": "integer" },
+            "filter": { "type": ["string", "null"] },
+            "raw_bytes": { "type": "integer" },
+            "filtered_bytes": { "type": "integer" },
+            "raw_tokens_est": { "type": "integer" },
+            "filtered_tokens_est": { "type": "integer" },
+            "timed_out": { "type": "boolean" }
+        },
+        "required": ["exit_code", "filter", "raw_bytes", "filtered_bytes"]
+    })
+}
+
+pub fn call(params: &Value, include_builtin: bool) -> Result<Value, RpcError> {
+    let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+    let args = params.get("arguments").unwrap_or(&Value::Null);
+    let result = match name {
+        "run_filtered" => run_filtered(args, include_builtin),
+        "filter_text" => filter_text(args, include_builtin),
+        _ => return Err((INVALID_PARAMS, format!("unknown tool: {name}"))),
+    };
+    Ok(result.unwrap_or_else(
+        |message| json!({ "content": [{ "type": "text", "text": message }], "isError": true }),
+    ))
+}
+
+fn run_filtered(args: &Value, include_builtin: bool) -> Result<Value, String> {
+    let line = str_arg(args, "command")?;
+    let words = split_words(line)?;
+    if words.is_empty() {
+        return Err("command is empty".to_string());
+    }
+    let (cfg, consumed) = crate::cli::run::find_filter(&words, false, false, include_builtin)
+        .map_err(|e| format!("filter discovery failed: {e:#}"))?;
+    let head_len = crate::cli::run::head_len(&words, consumed);
+    let remaining = words[head_len..].to_vec();
+    let opts = crate::RunOptions {
+        timeout: args.get("timeout_secs").and_then(Value::as_u64),
+        pty: false,
+        diff: false,
+        save_raw: false,
+        sarif: None,
+    };
+    let steps = cfg.as_ref().map(|c| {
+        let timeout = crate::cli::run::command_timeout(Some(c), &opts);
+        crate::steps::run_steps(c, &remaining, timeout, include_builtin)
+    });
+    let mut result =
+        crate::cli::run::run_command(cfg.as_ref(), head_len, &words, &remaining, &opts)
+            .map_err(|e| format!("failed to run {}: {e:#}", words[0]))?;
+    result.steps = steps.unwrap_or_default();
+
+    let start = std::time::Instant::now();
+    let (output, failed, used_fallback) = apply(cfg.as_ref(), &result, &remaining);
+    let exit_code = crate::cli::run::effective_exit_code(result.exit_code, failed);
+    let filter_name = cfg.as_ref().map(|c| c.command.first());
+    let counter = cfg
+        .as_ref()
+        .and_then(|c| c.trend.as_ref())
+        .and_then(|t| t.count(&result.combined).map(|n| (t.label.as_str(), n)));
+    crate::cli::run::record_run(&RunRecord {
+        command_args: &words,
+        filter_name,
+        input_bytes: result.combined.len(),
+        output: &output,
+        filter_time_ms: if cfg.is_some() {
+            start.elapsed().as_millis()
+        } else {
+            0
+        },
+        exit_code,
+        counter,
+        used_fallback,
+    });
+
+    let mut meta = metadata(exit_code, filter_name, &result.combined, &output);
+    meta["timed_out"] = json!(result.timed_out.is_some());
+    Ok(tool_result(&output, &meta))
+}
+
+fn filter_text(args: &Value, include_builtin: bool) -> Result<Value, String> {
+    let text = str_arg(args, "text")?;
+    let name = str_arg(args, "filter")?;
+    let exit_code = args
+        .get("exit_code")
+        .and_then(Value::as_i64)
+        .and_then(|c| i32::try_from(c).ok())
+        .unwrap_or(0);
+    let cfg = resolve_filter(name, include_builtin).map_err(|e| e.to_string())?;
+    let result = CommandResult::from_output(text, exit_code);
+    let (output, _, _) = apply(Some(&cfg), &result, &[]);
+    let meta = metadata(exit_code, Some(cfg.command.first()), text, &output);
+    Ok(tool_result(&output, &meta))
+}
+
+/// Filtered output, `fail_when`, and whether the fallback was used. Without
+/// a filter, or if the filter panics, the raw output is returned as is —
+/// a panic must not take the server down.
+fn apply(
+    cfg: Option<&FilterConfig>,
+    result: &CommandResult,
+    args: &[String],
+) -> (String, bool, bool) {
+    let Some(cfg) = cfg else {
+        return (result.combined.clone(), false, false);
+    };
+    if let Ok(filtered) = catch_unwind(AssertUnwindSafe(|| filter::apply(cfg, result, args))) {
+        return (filtered.output, filtered.failed, filtered.used_fallback);
+    }
+    tracing::error!(
+        "filter \"{}\" panicked; returning unfiltered output",
+        cfg.command.first()
+    );
+    (result.combined.clone(), false, false)
+}
+
+fn metadata(exit_code: i32, filter: Option<&str>, raw: &str, filtered: &str) -> Value {
+    json!({
+        "exit_code": exit_code,
+        "filter": filter,
+        "raw_bytes": raw.len(),
+        "filtered_bytes": filtered.len(),
+        "raw_tokens_est": raw.len() / 4,
+        "filtered_tokens_est": filtered.len() / 4,
+    })
+}
+
+/// The output as text content, followed by a one-line summary for clients
+/// that ignore `structuredContent`.
+fn tool_result(output: &str, meta: &Value) -> Value {
+    let filter = meta["filter"].as_str().unwrap_or("none");
+    let summary = format!(
+        "[tokf] exit {} · filter {filter} · {} → {} bytes",
+        meta["exit_code"], meta["raw_bytes"], meta["filtered_bytes"]
+    );
+    json!({
+        "content": [
+            { "type": "text", "text": output },
+            { "type": "text", "text": summary }
+        ],
+        "structuredContent": meta,
+        "isError": false
+    })
+}
+
+fn str_arg<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("missing string argument \"{key}\""))
+}
+
+/// Split a command line into words like a POSIX shell, without expansions:
+/// whitespace separates words, quotes group them, and a backslash escapes
+/// the next character outside single quotes. Unquoted shell operators are
+/// refused, since the words are run as one program.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => w.push(c),
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            '|' | '&' | ';' | '<' | '>' | '`' => {
+                return Err(format!(
+                    "shell operator '{c}' is not supported; run one command at a time"
+                ));
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words_handles_quotes_and_escapes() {
+        assert_eq!(
+            split_words(r#"git commit -m "fix: a \"b\"" --author='A B' x\ y"#).unwrap(),
+            [
+                "git",
+                "commit",
+                "-m",
+                r#"fix: a "b""#,
+                "--author=A B",
+                "x y"
+            ]
+        );
+        assert_eq!(split_words("  ").unwrap(), Vec::<String>::new());
+        assert_eq!(split_words("echo ''").unwrap(), ["echo", ""]);
+        assert!(split_words("echo 'open").is_err());
+    }
+
+    #[test]
+    fn split_words_refuses_unquoted_operators() {
+        let err = split_words("cargo test | head").unwrap_err();
+        assert!(err.contains("'|'"), "{err}");
+        assert_eq!(split_words("echo 'a|b'").unwrap(), ["echo", "a|b"]);
+    }
+
+    #[test]
+    fn failing_tools_report_is_error() {
+        let result = call(
+            &json!({ "name": "filter_text", "arguments": { "text": "x" } }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(
+            result["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .contains("\"filter\"")
+        );
+    }
+}
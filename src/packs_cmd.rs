@@ -0,0 +1,45 @@
+//! `tokf ls --packs`: filter packs described by a `pack.toml`.
+
+use tokf::config;
+use tokf::config::pack::{DiscoveredPack, discover_packs};
+
+pub fn cmd_ls_packs() -> i32 {
+    let packs = discover_packs(&config::default_search_dirs());
+    if packs.is_empty() {
+        eprintln!("[tokf] no packs found (a pack is a filters directory with a pack.toml)");
+        return 0;
+    }
+    for pack in &packs {
+        print_pack(pack);
+    }
+    0
+}
+
+fn print_pack(pack: &DiscoveredPack) {
+    let m = &pack.manifest;
+    let tier = if pack.priority == 0 { "local" } else { "user" };
+    let version = m
+        .version
+        .as_deref()
+        .map(|v| format!(" {v}"))
+        .unwrap_or_default();
+    println!(
+        "{}{version}  [{tier}]  {} filters  {}",
+        pack.display_name(),
+        pack.filters,
+        pack.dir.display()
+    );
+    match (&m.description, &m.author) {
+        (Some(desc), Some(author)) => println!("    {desc} — {author}"),
+        (Some(desc), None) => println!("    {desc}"),
+        (None, Some(author)) => println!("    by {author}"),
+        (None, None) => {}
+    }
+    if !m.is_compatible() {
+        println!(
+            "    skipped: requires tokf >= {} (this is {})",
+            m.min_tokf_version.as_deref().unwrap_or("?"),
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! User-level locations for tokf's config, cache, and tracking data.
+//!
+//! Setting `TOKF_HOME` puts all three under one directory, which keeps CI
+//! runners and tests hermetic:
+//!
+//! | What | With `TOKF_HOME` | Default |
+//! |---|---|---|
+//! | filters, `config.toml`, `rewrites.toml`, hooks | `$TOKF_HOME/` | `{config_dir}/tokf/` |
+//! | discovery cache | `$TOKF_HOME/cache/` | `{cache_dir}/tokf/` |
+//! | `tracking.db` | `$TOKF_HOME/` | `{data_local_dir}/tokf/` |
+//!
+//! Project-local `.tokf/` directories are unaffected.
+
+use std::path::PathBuf;
+
+/// `TOKF_HOME`, made absolute so it survives later CWD changes. Empty → unset.
+pub fn tokf_home() -> Option<PathBuf> {
+    let home = std::env::var_os("TOKF_HOME").filter(|v| !v.is_empty())?;
+    std::path::absolute(&home).ok()
+}
+
+/// User-level config directory: filters, settings, rewrites, global hooks.
+pub fn config_dir() -> Option<PathBuf> {
+    tokf_home().or_else(|| dirs::config_dir().map(|d| d.join("tokf")))
+}
+
+/// Directory for the filter discovery cache when no project `.tokf/` exists.
+pub fn cache_dir() -> Option<PathBuf> {
+    tokf_home()
+        .map(|h| h.join("cache"))
+        .or_else(|| dirs::cache_dir().map(|d| d.join("tokf")))
+}
+
+/// Directory holding the tracking database.
+pub fn data_dir() -> Option<PathBuf> {
+    tokf_home().or_else(|| dirs::data_local_dir().map(|d| d.join("tokf")))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+
+    fn with_home<T>(value: &str, f: impl FnOnce() -> T) -> T {
+        // SAFETY: test-only env mutation; #[serial] prevents races with other tests.
+        unsafe { std::env::set_var("TOKF_HOME", value) };
+        let result = f();
+        unsafe { std::env::remove_var("TOKF_HOME") };
+        result
+    }
+
+    /// Must run serially: mutates the global process environment.
+    #[test]
+    #[serial]
+    fn tokf_home_relocates_every_dir() {
+        let (config, cache, data) =
+            with_home("/ci/tokf", || (config_dir(), cache_dir(), data_dir()));
+        assert_eq!(config, Some(PathBuf::from("/ci/tokf")));
+        assert_eq!(cache, Some(PathBuf::from("/ci/tokf/cache")));
+        assert_eq!(data, Some(PathBuf::from("/ci/tokf")));
+    }
+
+    #[test]
+    #[serial]
+    fn relative_tokf_home_is_made_absolute() {
+        let home = with_home("ci-home", tokf_home).unwrap();
+        assert!(home.is_absolute());
+        assert!(home.ends_with("ci-home"));
+    }
+
+    #[test]
+    #[serial]
+    fn empty_tokf_home_is_ignored() {
+        let config = with_home("", config_dir);
+        assert_eq!(config, dirs::config_dir().map(|d| d.join("tokf")));
+    }
+}
@@ -0,0 +1,72 @@
+//! Process-wide cache of compiled regexes, keyed by pattern string.
+//!
+//! Filters, rewrite rules and templates name their regexes as strings and
+//! used to compile them on every application. Watch mode, streaming and
+//! multi-step pipelines apply the same filter many times per process, so
+//! each pattern is compiled once here and cloned out (a `Regex` clone is a
+//! reference-count bump).
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use regex::Regex;
+
+/// Entries kept before the cache is flushed. Filters use a few dozen
+/// patterns; the cap only guards against templates building patterns from
+/// output (`| keep: "…"`) in a long-running process.
+const MAX_ENTRIES: usize = 4096;
+
+/// Invalid patterns are cached too, as `None`, so they are not re-parsed.
+static CACHE: LazyLock<Mutex<HashMap<String, Option<Regex>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The compiled form of `pattern`, or `None` if it is not a valid regex.
+pub fn compile(pattern: &str) -> Option<Regex> {
+    let Ok(mut cache) = CACHE.lock() else {
+        // A panic while holding the lock poisons it; compile uncached.
+        return Regex::new(pattern).ok();
+    };
+    if let Some(entry) = cache.get(pattern) {
+        return entry.clone();
+    }
+    if cache.len() >= MAX_ENTRIES {
+        cache.clear();
+    }
+    let compiled = Regex::new(pattern).ok();
+    cache.insert(pattern.to_string(), compiled.clone());
+    compiled
+}
+
+/// Compile every valid pattern in `patterns`, dropping invalid ones.
+pub fn compile_all<S: AsRef<str>>(patterns: &[S]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|p| compile(p.as_ref()))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_once_and_reuses() {
+        let a = compile(r"^regex-cache-test-\d+$").unwrap();
+        let b = compile(r"^regex-cache-test-\d+$").unwrap();
+        assert_eq!(a.as_str(), b.as_str());
+        assert!(b.is_match("regex-cache-test-42"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_none() {
+        assert!(compile("(regex-cache-unclosed").is_none());
+        assert!(compile("(regex-cache-unclosed").is_none());
+    }
+
+    #[test]
+    fn compile_all_drops_invalid() {
+        let compiled = compile_all(&["^a", "(bad", "b$"]);
+        assert_eq!(compiled.len(), 2);
+    }
+}
@@ -0,0 +1,85 @@
+//! Fetching pack sources with the system `git`, `curl` and `tar`.
+
+use std::path::Path;
+use std::process::Command;
+
+use super::{Source, SourceKind};
+use crate::hash::fnv1a;
+
+/// Fetch `source` into `dest` (which must not exist yet) and return its version:
+/// the checked-out commit for git, a content fingerprint for tarballs.
+pub(super) fn fetch(source: &Source, dest: &Path, scratch: &Path) -> anyhow::Result<String> {
+    match source.kind {
+        SourceKind::Git => fetch_git(source, dest),
+        SourceKind::Tarball => fetch_tarball(&source.url, dest, scratch),
+    }
+}
+
+fn fetch_git(source: &Source, dest: &Path) -> anyhow::Result<String> {
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet", "--depth", "1"]);
+    if let Some(git_ref) = &source.git_ref {
+        clone.args(["--branch", git_ref]);
+    }
+    clone.arg("--").arg(&source.url).arg(dest);
+    run(&mut clone, "git")?;
+
+    let head = run(
+        Command::new("git")
+            .arg("-C")
+            .arg(dest)
+            .args(["rev-parse", "HEAD"]),
+        "git",
+    )?;
+    // The checkout is copied into the filters dir; history is not needed.
+    std::fs::remove_dir_all(dest.join(".git"))?;
+    Ok(head.trim().to_string())
+}
+
+fn fetch_tarball(url: &str, dest: &Path, scratch: &Path) -> anyhow::Result<String> {
+    let archive = scratch.join("pack.tar");
+    run(
+        Command::new("curl")
+            .args([
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                "--output",
+            ])
+            .arg(&archive)
+            .arg("--")
+            .arg(url),
+        "curl",
+    )?;
+    let version = format!("fnv1a:{:016x}", fnv1a(&std::fs::read(&archive)?));
+
+    std::fs::create_dir_all(dest)?;
+    // `tar -xf` detects gzip/bzip2/xz compression itself.
+    run(
+        Command::new("tar")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(dest),
+        "tar",
+    )?;
+    std::fs::remove_file(&archive)?;
+    Ok(version)
+}
+
+/// Run `cmd` to completion and return its stdout, or an error carrying its stderr.
+fn run(cmd: &mut Command, program: &str) -> anyhow::Result<String> {
+    let output = cmd.output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            anyhow::anyhow!("`{program}` not found on PATH; it is needed to fetch this pack")
+        } else {
+            anyhow::anyhow!("failed to run {program}: {e}")
+        }
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{program} failed: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
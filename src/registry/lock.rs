@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use super::SourceKind;
+
+/// One installed pack as recorded in `packs.lock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    /// Directory name under the user filters dir.
+    pub name: String,
+    /// The URL the pack was fetched from.
+    pub source: String,
+    pub kind: SourceKind,
+    /// Git branch or tag requested with `url#ref`, if any.
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    /// Commit hash for git sources, content fingerprint for tarballs.
+    pub version: String,
+}
+
+/// The `packs.lock` file: every pack installed with `tokf install`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub pack: Vec<LockEntry>,
+}
+
+impl Lockfile {
+    /// Read the lockfile at `path`. A missing file is an empty lockfile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(anyhow::Error::new(e)
+                    .context(format!("failed to read lockfile: {}", path.display())));
+            }
+        };
+        toml::from_str(&content)
+            .with_context(|| format!("failed to parse lockfile: {}", path.display()))
+    }
+
+    /// Write the lockfile to `path`, creating its parent directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or file I/O fails.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string(self).context("serialize lockfile")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("failed to write lockfile: {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockEntry> {
+        self.pack.iter().find(|p| p.name == name)
+    }
+
+    /// Record `entry`, replacing any pack of the same name in place.
+    pub fn upsert(&mut self, entry: LockEntry) {
+        if let Some(existing) = self.pack.iter_mut().find(|p| p.name == entry.name) {
+            *existing = entry;
+        } else {
+            self.pack.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn entry(name: &str, version: &str) -> LockEntry {
+        LockEntry {
+            name: name.to_string(),
+            source: format!("https://example.com/{name}.git"),
+            kind: SourceKind::Git,
+            git_ref: None,
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn missing_lockfile_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let lock = Lockfile::load(&dir.path().join("packs.lock")).unwrap();
+        assert!(lock.pack.is_empty());
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/packs.lock");
+        let mut lock = Lockfile::default();
+        lock.upsert(entry("team", "abc123"));
+        lock.upsert(LockEntry {
+            kind: SourceKind::Tarball,
+            git_ref: Some("v1".to_string()),
+            ..entry("ci", "fnv1a:0011")
+        });
+        lock.save(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[[pack]]"), "content: {content}");
+        assert!(content.contains("ref = \"v1\""), "content: {content}");
+        assert_eq!(Lockfile::load(&path).unwrap(), lock);
+    }
+
+    #[test]
+    fn upsert_replaces_by_name() {
+        let mut lock = Lockfile::default();
+        lock.upsert(entry("team", "old"));
+        lock.upsert(entry("other", "x"));
+        lock.upsert(entry("team", "new"));
+        assert_eq!(lock.pack.len(), 2);
+        assert_eq!(lock.pack[0].name, "team");
+        assert_eq!(lock.get("team").unwrap().version, "new");
+    }
+}
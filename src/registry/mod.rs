@@ -0,0 +1,398 @@
+//! Filter packs shared over git or HTTPS tarballs.
+//!
+//! `tokf install <source>` fetches a pack into `{config_dir}/filters/<name>/`
+//! (`$TOKF_HOME/filters/<name>/` if set) and records where it came from in
+//! `{config_dir}/packs.lock`; `tokf update` re-fetches every recorded pack.
+//!
+//! A source is a git URL, optionally with `#<branch-or-tag>`, or a URL ending
+//! in `.tar.gz`, `.tgz` or `.tar`. Within the fetched tree, a single wrapping
+//! directory (as in GitHub tarballs) is stepped into, and a `filters/`
+//! subdirectory is used as the pack root when present. A pack whose
+//! `pack.toml` asks for a newer tokf is refused.
+
+mod fetch;
+pub mod lock;
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::discover_filter_files;
+use crate::config::pack::{self, PACK_MANIFEST};
+use lock::{LockEntry, Lockfile};
+
+const TARBALL_SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".tar"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Git,
+    Tarball,
+}
+
+/// Where a pack is fetched from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub url: String,
+    pub kind: SourceKind,
+    /// Branch or tag to check out (git only).
+    pub git_ref: Option<String>,
+}
+
+impl Source {
+    /// Parse a source spec: a tarball URL, or a git URL with an optional `#ref`.
+    pub fn parse(spec: &str) -> Self {
+        let path = spec.split(['?', '#']).next().unwrap_or(spec);
+        if TARBALL_SUFFIXES.iter().any(|s| path.ends_with(s)) {
+            return Self {
+                url: spec.to_string(),
+                kind: SourceKind::Tarball,
+                git_ref: None,
+            };
+        }
+        let (url, git_ref) = match spec.split_once('#') {
+            Some((url, r)) if !r.is_empty() => (url, Some(r.to_string())),
+            _ => (spec.trim_end_matches('#'), None),
+        };
+        Self {
+            url: url.to_string(),
+            kind: SourceKind::Git,
+            git_ref,
+        }
+    }
+
+    /// The pack name implied by the URL: its last path segment without
+    /// `.git` or an archive extension.
+    pub fn default_name(&self) -> Option<String> {
+        let path = self.url.split(['?', '#']).next().unwrap_or(&self.url);
+        let last = path.trim_end_matches('/').rsplit(['/', ':']).next()?;
+        let stem = TARBALL_SUFFIXES
+            .iter()
+            .chain(&[".git"])
+            .find_map(|s| last.strip_suffix(s))
+            .unwrap_or(last);
+        is_valid_name(stem).then(|| stem.to_string())
+    }
+
+    fn from_lock(entry: &LockEntry) -> Self {
+        Self {
+            url: entry.source.clone(),
+            kind: entry.kind,
+            git_ref: entry.git_ref.clone(),
+        }
+    }
+}
+
+/// Pack names become directory names, so keep them to one plain path segment.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Result of installing (or re-installing) one pack.
+#[derive(Debug)]
+pub struct Installed {
+    pub entry: LockEntry,
+    /// Number of filter files in the pack.
+    pub filters: usize,
+    /// Version recorded before this install, if the pack was already present.
+    pub previous: Option<String>,
+    pub dir: PathBuf,
+}
+
+/// The user-level filters dir packs are installed into.
+///
+/// # Errors
+///
+/// Returns an error if no config directory can be determined.
+pub fn filters_dir() -> anyhow::Result<PathBuf> {
+    crate::paths::config_dir()
+        .map(|d| d.join("filters"))
+        .ok_or_else(|| anyhow::anyhow!("could not determine the tokf config directory"))
+}
+
+/// The lockfile recording installed packs.
+///
+/// # Errors
+///
+/// Returns an error if no config directory can be determined.
+pub fn lock_path() -> anyhow::Result<PathBuf> {
+    crate::paths::config_dir()
+        .map(|d| d.join("packs.lock"))
+        .ok_or_else(|| anyhow::anyhow!("could not determine the tokf config directory"))
+}
+
+/// Install `source` as pack `name` into the user filters dir.
+///
+/// An existing `filters/<name>` is only replaced if the lockfile records it
+/// as an installed pack, or with `force`.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid, `filters/<name>` holds filters
+/// not installed as a pack (without `force`), fetching fails, the pack holds
+/// no filters, or the filters dir or lockfile cannot be written.
+pub fn install(source: &Source, name: &str, force: bool) -> anyhow::Result<Installed> {
+    install_to(&filters_dir()?, &lock_path()?, source, name, force)
+}
+
+/// Re-fetch installed packs from their recorded sources: all of them, or
+/// only `name`. Each pack's result is returned separately so one failing
+/// source does not block the rest.
+///
+/// # Errors
+///
+/// Returns an error if the lockfile cannot be read or `name` is not installed.
+pub fn update(name: Option<&str>) -> anyhow::Result<Vec<(String, anyhow::Result<Installed>)>> {
+    update_in(&filters_dir()?, &lock_path()?, name)
+}
+
+/// Core install logic with explicit paths (testable).
+pub(crate) fn install_to(
+    filters_dir: &Path,
+    lock_path: &Path,
+    source: &Source,
+    name: &str,
+    force: bool,
+) -> anyhow::Result<Installed> {
+    anyhow::ensure!(is_valid_name(name), "invalid pack name \"{name}\"");
+    let dir = filters_dir.join(name);
+    anyhow::ensure!(
+        force || !dir.exists() || Lockfile::load(lock_path)?.get(name).is_some(),
+        "{} exists and was not installed by `tokf install`; \
+         pass --force to replace it, or --name to install elsewhere",
+        dir.display()
+    );
+    // Hidden, so discovery never sees a half-fetched pack.
+    let staging = filters_dir.join(format!(".install-{name}"));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+    let result = fetch_and_place(filters_dir, lock_path, source, name, &staging);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn fetch_and_place(
+    filters_dir: &Path,
+    lock_path: &Path,
+    source: &Source,
+    name: &str,
+    staging: &Path,
+) -> anyhow::Result<Installed> {
+    let fetched = staging.join("src");
+    let version = fetch::fetch(source, &fetched, staging)?;
+    let root = pack_root(&fetched)?;
+    if let Some(manifest) = pack::load_manifest(&root)
+        && !manifest.is_compatible()
+    {
+        anyhow::bail!(
+            "pack requires tokf >= {} (this is {})",
+            manifest.min_tokf_version.as_deref().unwrap_or("?"),
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    let filters = discover_filter_files(&root).len();
+    anyhow::ensure!(filters > 0, "no filter files found in {}", source.url);
+
+    let dir = filters_dir.join(name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::rename(&root, &dir)?;
+
+    let mut lock = Lockfile::load(lock_path)?;
+    let previous = lock.get(name).map(|e| e.version.clone());
+    let entry = LockEntry {
+        name: name.to_string(),
+        source: source.url.clone(),
+        kind: source.kind,
+        git_ref: source.git_ref.clone(),
+        version,
+    };
+    lock.upsert(entry.clone());
+    lock.save(lock_path)?;
+
+    Ok(Installed {
+        entry,
+        filters,
+        previous,
+        dir,
+    })
+}
+
+pub(crate) fn update_in(
+    filters_dir: &Path,
+    lock_path: &Path,
+    name: Option<&str>,
+) -> anyhow::Result<Vec<(String, anyhow::Result<Installed>)>> {
+    let lock = Lockfile::load(lock_path)?;
+    if let Some(name) = name {
+        anyhow::ensure!(lock.get(name).is_some(), "pack \"{name}\" is not installed");
+    }
+    Ok(lock
+        .pack
+        .iter()
+        .filter(|e| name.is_none_or(|n| n == e.name))
+        .map(|e| {
+            let result = install_to(
+                filters_dir,
+                lock_path,
+                &Source::from_lock(e),
+                &e.name,
+                false,
+            );
+            (e.name.clone(), result)
+        })
+        .collect())
+}
+
+/// The directory within a fetched tree that holds the pack's filters.
+///
+/// A `pack.toml` next to a `filters/` directory is copied into it, so the
+/// installed pack keeps its manifest.
+fn pack_root(fetched: &Path) -> anyhow::Result<PathBuf> {
+    let mut root = fetched.to_path_buf();
+    if let Some(only) = single_subdir(&root) {
+        root = only;
+    }
+    let nested = root.join("filters");
+    if !nested.is_dir() {
+        return Ok(root);
+    }
+    let manifest = root.join(PACK_MANIFEST);
+    if manifest.is_file() && !nested.join(PACK_MANIFEST).exists() {
+        std::fs::copy(&manifest, nested.join(PACK_MANIFEST))?;
+    }
+    Ok(nested)
+}
+
+/// The only entry of `dir`, if it has exactly one and that entry is a directory.
+fn single_subdir(dir: &Path) -> Option<PathBuf> {
+    let mut entries = std::fs::read_dir(dir).ok()?.filter_map(Result::ok);
+    let only = entries.next()?;
+    if entries.next().is_some() || !only.path().is_dir() {
+        return None;
+    }
+    Some(only.path())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn parse_git_sources() {
+        let s = Source::parse("https://github.com/acme/tokf-filters.git");
+        assert_eq!(s.kind, SourceKind::Git);
+        assert_eq!(s.git_ref, None);
+        assert_eq!(s.default_name().as_deref(), Some("tokf-filters"));
+
+        let s = Source::parse("git@github.com:acme/ci-filters.git#v1.2");
+        assert_eq!(s.url, "git@github.com:acme/ci-filters.git");
+        assert_eq!(s.git_ref.as_deref(), Some("v1.2"));
+        assert_eq!(s.default_name().as_deref(), Some("ci-filters"));
+    }
+
+    #[test]
+    fn parse_tarball_sources() {
+        let s = Source::parse("https://example.com/packs/team-1.0.tar.gz");
+        assert_eq!(s.kind, SourceKind::Tarball);
+        assert_eq!(s.default_name().as_deref(), Some("team-1.0"));
+
+        let s = Source::parse("https://example.com/dl/pack.tgz?token=x");
+        assert_eq!(s.kind, SourceKind::Tarball);
+        assert_eq!(s.default_name().as_deref(), Some("pack"));
+    }
+
+    #[test]
+    fn names_are_single_plain_segments() {
+        assert!(is_valid_name("team_filters-2.0"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name(".hidden"));
+        assert!(!is_valid_name("a/b"));
+        assert!(!is_valid_name(".."));
+    }
+
+    #[test]
+    fn pack_root_steps_into_wrapper_and_filters_dir() {
+        let dir = TempDir::new().unwrap();
+        let wrapped = dir.path().join("acme-filters-abc123/filters/git");
+        fs::create_dir_all(&wrapped).unwrap();
+        fs::write(wrapped.join("log.toml"), "command = \"git log\"").unwrap();
+
+        fs::write(
+            dir.path().join("acme-filters-abc123").join(PACK_MANIFEST),
+            "name = \"acme\"",
+        )
+        .unwrap();
+
+        let root = pack_root(dir.path()).unwrap();
+        assert!(root.ends_with("acme-filters-abc123/filters"));
+        assert!(root.join(PACK_MANIFEST).is_file());
+    }
+
+    #[test]
+    fn pack_root_keeps_flat_trees() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.toml"), "command = \"a\"").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        assert_eq!(pack_root(dir.path()).unwrap(), dir.path());
+    }
+
+    #[test]
+    fn update_unknown_pack_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let err = update_in(dir.path(), &dir.path().join("packs.lock"), Some("nope"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not installed"), "err: {err}");
+    }
+
+    #[test]
+    fn install_rejects_path_like_names() {
+        let dir = TempDir::new().unwrap();
+        let source = Source::parse("https://example.com/x.git");
+        let err = install_to(
+            dir.path(),
+            &dir.path().join("packs.lock"),
+            &source,
+            "../x",
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("invalid pack name"), "err: {err}");
+    }
+
+    #[test]
+    fn install_keeps_a_user_authored_directory() {
+        let dir = TempDir::new().unwrap();
+        let mine = dir.path().join("git/push.toml");
+        fs::create_dir_all(mine.parent().unwrap()).unwrap();
+        fs::write(&mine, "command = \"git push\"\n# mine\n").unwrap();
+
+        let source = Source::parse("https://example.com/git.git");
+        let err = install_to(
+            dir.path(),
+            &dir.path().join("packs.lock"),
+            &source,
+            "git",
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("--force"), "err: {err}");
+        assert!(fs::read_to_string(&mine).unwrap().contains("# mine"));
+        assert!(!dir.path().join(".install-git").exists());
+    }
+}
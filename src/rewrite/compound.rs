@@ -1,32 +1,105 @@
-use regex::Regex;
+/// Quoting state while scanning a shell command.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+/// Byte ranges `(start, end)` of chain operators that sit outside quotes.
+fn find_operators(bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut ops = Vec::new();
+    let mut quote = Quote::None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let ch = bytes[i];
+        let mut op_len = 0;
+        match (quote, ch) {
+            (Quote::Single, b'\'') | (Quote::Double, b'"') => quote = Quote::None,
+            (Quote::Single, _) => {}
+            (_, b'\\') => i += 1, // skip the escaped byte
+            (Quote::None, b'\'') => quote = Quote::Single,
+            (Quote::None, b'"') => quote = Quote::Double,
+            (Quote::None, b'&' | b'|') if bytes.get(i + 1) == Some(&ch) => op_len = 2,
+            (Quote::None, b';' | b'\n') => op_len = 1,
+            _ => {}
+        }
+        if op_len > 0 {
+            ops.push((i, i + op_len));
+            i += op_len;
+        } else {
+            i += 1;
+        }
+    }
 
-/// Matches shell chain operators (`&&`, `||`, `;`, newline). Pipes excluded intentionally.
-const CHAIN_PATTERN: &str = r"( *&& *| *\|\| *| *; *|\n)";
+    ops
+}
 
 /// Split a compound shell command at chain operators (`&&`, `||`, `;`, newline).
 ///
 /// Returns `(segment, separator)` pairs; the last separator is always `""`.
-/// Pipes (`|`) are not treated as chain operators — `tokf run cmd | head` is valid
-/// shell and lets the outer shell pass tokf's filtered output through the pipe.
+/// Separators carry their surrounding spaces so joining the pairs reproduces
+/// the input exactly.
+///
+/// Operators inside single or double quotes, or escaped with `\`, are not
+/// split on. Pipes (`|`) are not treated as chain operators — `tokf run cmd | head`
+/// is valid shell and lets the outer shell pass tokf's filtered output through the pipe.
 pub fn split_compound(input: &str) -> Vec<(String, String)> {
-    let Ok(re) = Regex::new(CHAIN_PATTERN) else {
-        return vec![(input.to_string(), String::new())];
-    };
-    if !re.is_match(input) {
-        return vec![(input.to_string(), String::new())];
-    }
+    let bytes = input.as_bytes();
     let mut parts = Vec::new();
-    let mut last = 0;
-    for m in re.find_iter(input) {
-        parts.push((input[last..m.start()].to_string(), m.as_str().to_string()));
-        last = m.end();
-    }
-    if last <= input.len() {
-        parts.push((input[last..].to_string(), String::new()));
+    let mut seg_start = 0;
+
+    for (op_start, op_end) in find_operators(bytes) {
+        // Widen the separator to absorb surrounding spaces.
+        let mut left = op_start;
+        while left > seg_start && bytes[left - 1] == b' ' {
+            left -= 1;
+        }
+        let mut right = op_end;
+        while right < bytes.len() && bytes[right] == b' ' {
+            right += 1;
+        }
+        parts.push((
+            input[seg_start..left].to_string(),
+            input[left..right].to_string(),
+        ));
+        seg_start = right;
     }
+
+    parts.push((input[seg_start..].to_string(), String::new()));
     parts
 }
 
+/// Returns true if the command uses constructs that make per-segment rewriting
+/// unsafe: subshells `( … )`, command substitution `$( … )` or backticks.
+///
+/// Single-quoted text is inert and ignored. Double-quoted text is still checked,
+/// because `$( … )` and backticks expand inside double quotes.
+pub fn has_unsafe_construct(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    let mut quote = Quote::None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let ch = bytes[i];
+        let substitution = ch == b'`' || (ch == b'$' && bytes.get(i + 1) == Some(&b'('));
+        match (quote, ch) {
+            (Quote::Single, b'\'') | (Quote::Double, b'"') => quote = Quote::None,
+            (Quote::Single, _) => {}
+            (_, b'\\') => i += 1,
+            _ if substitution => return true,
+            (Quote::None, b'(' | b')') => return true,
+            (Quote::None, b'\'') => quote = Quote::Single,
+            (Quote::None, b'"') => quote = Quote::Double,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    false
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used)]
 mod tests {
@@ -66,4 +139,69 @@ mod tests {
         assert_eq!(parts[0].0, "git add .");
         assert_eq!(parts[1].0, "git status");
     }
+
+    #[test]
+    fn split_roundtrips_input() {
+        let input = "a  &&b ;c||  d\ne";
+        let joined: String = split_compound(input)
+            .into_iter()
+            .map(|(seg, sep)| seg + &sep)
+            .collect();
+        assert_eq!(joined, input);
+    }
+
+    #[test]
+    fn operators_inside_quotes_not_split() {
+        let parts = split_compound(r#"git commit -m "a && b; c" && git push"#);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0, r#"git commit -m "a && b; c""#);
+        assert_eq!(parts[1].0, "git push");
+
+        let parts = split_compound("echo 'x || y'");
+        assert_eq!(parts.len(), 1);
+    }
+
+    #[test]
+    fn escaped_operator_not_split() {
+        let parts = split_compound(r"echo a \; git status");
+        assert_eq!(parts.len(), 1);
+    }
+
+    #[test]
+    fn pipe_or_not_confused_with_pipe() {
+        let parts = split_compound("cargo test | tail -3 || true");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0, "cargo test | tail -3");
+        assert_eq!(parts[1].0, "true");
+    }
+
+    // --- has_unsafe_construct ---
+
+    #[test]
+    fn plain_commands_are_safe() {
+        assert!(!has_unsafe_construct("git status && git push"));
+        assert!(!has_unsafe_construct("git diff | head -5"));
+    }
+
+    #[test]
+    fn subshell_is_unsafe() {
+        assert!(has_unsafe_construct("(cd sub && cargo test)"));
+    }
+
+    #[test]
+    fn command_substitution_is_unsafe() {
+        assert!(has_unsafe_construct("cargo test -j $(nproc)"));
+        assert!(has_unsafe_construct("git log `git rev-parse HEAD`"));
+        assert!(has_unsafe_construct(r#"echo "$(date)""#));
+    }
+
+    #[test]
+    fn single_quoted_constructs_are_inert() {
+        assert!(!has_unsafe_construct("git commit -m 'fix (again) $(x)'"));
+    }
+
+    #[test]
+    fn double_quoted_parens_are_inert() {
+        assert!(!has_unsafe_construct(r#"git commit -m "fix (again)""#));
+    }
 }
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::types::SkipConfig;
+
+/// Where and under which environment a rewrite is being considered.
+#[derive(Debug, Clone, Default)]
+pub struct SkipContext {
+    pub cwd: Option<PathBuf>,
+    pub home: Option<PathBuf>,
+    pub vars: HashMap<String, String>,
+}
+
+impl SkipContext {
+    /// The current process's directory, home and environment.
+    pub fn current() -> Self {
+        Self {
+            cwd: std::env::current_dir().ok(),
+            home: dirs::home_dir(),
+            vars: std::env::vars().collect(),
+        }
+    }
+}
+
+/// True when a `[skip]` `env` or `dirs` condition holds, so nothing should
+/// be rewritten here, whatever the command.
+pub fn context_disables(skip: &SkipConfig, ctx: &SkipContext) -> bool {
+    skip.env.iter().any(|cond| env_matches(cond, &ctx.vars))
+        || ctx.cwd.as_deref().is_some_and(|cwd| {
+            skip.dirs
+                .iter()
+                .any(|g| dir_matches(g, cwd, ctx.home.as_deref()))
+        })
+}
+
+/// `VAR=value` holds when `VAR` is exactly `value`; a bare `VAR` holds when
+/// it is set and non-empty.
+fn env_matches(cond: &str, vars: &HashMap<String, String>) -> bool {
+    match cond.split_once('=') {
+        Some((name, value)) => vars.get(name).is_some_and(|v| v == value),
+        None => vars.get(cond).is_some_and(|v| !v.is_empty()),
+    }
+}
+
+/// Whether `cwd` or one of its parents matches the glob, so `~/experiments`
+/// and `~/experiments/**` both cover everything below it. A leading `~/` is
+/// the home directory. Invalid globs never match.
+fn dir_matches(glob: &str, cwd: &Path, home: Option<&Path>) -> bool {
+    let expanded = match (glob.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        (Some(_), None) => return false,
+        (None, _) => glob.to_string(),
+    };
+    let expanded = expanded.trim_end_matches('/');
+    let base = expanded.strip_suffix("/**").unwrap_or(expanded);
+    let Ok(pattern) = glob::Pattern::new(base) else {
+        tracing::warn!("invalid skip dir \"{glob}\"");
+        return false;
+    };
+    cwd.ancestors().any(|dir| pattern.matches_path(dir))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn ctx(cwd: &str, vars: &[(&str, &str)]) -> SkipContext {
+        SkipContext {
+            cwd: Some(PathBuf::from(cwd)),
+            home: Some(PathBuf::from("/home/u")),
+            vars: vars
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        }
+    }
+
+    fn skip(env: &[&str], dirs: &[&str]) -> SkipConfig {
+        SkipConfig {
+            env: env.iter().map(ToString::to_string).collect(),
+            dirs: dirs.iter().map(ToString::to_string).collect(),
+            ..SkipConfig::default()
+        }
+    }
+
+    #[test]
+    fn env_conditions() {
+        let cfg = skip(&["TOKF_DISABLE=1", "CI"], &[]);
+        assert!(context_disables(&cfg, &ctx("/", &[("TOKF_DISABLE", "1")])));
+        assert!(!context_disables(&cfg, &ctx("/", &[("TOKF_DISABLE", "0")])));
+        assert!(context_disables(&cfg, &ctx("/", &[("CI", "true")])));
+        assert!(!context_disables(&cfg, &ctx("/", &[("CI", "")])));
+        assert!(!context_disables(&cfg, &ctx("/", &[])));
+    }
+
+    #[test]
+    fn dir_globs_cover_subdirectories() {
+        let cfg = skip(&[], &["~/experiments/**", "/tmp/scratch", "/srv/*/sandbox"]);
+        assert!(context_disables(&cfg, &ctx("/home/u/experiments/a/b", &[])));
+        assert!(context_disables(&cfg, &ctx("/home/u/experiments", &[])));
+        assert!(context_disables(&cfg, &ctx("/tmp/scratch/x", &[])));
+        assert!(context_disables(&cfg, &ctx("/srv/app/sandbox/src", &[])));
+        assert!(!context_disables(&cfg, &ctx("/home/u/projects", &[])));
+        assert!(!context_disables(&cfg, &ctx("/srv/app", &[])));
+    }
+
+    #[test]
+    fn home_glob_without_home_never_matches() {
+        let cfg = skip(&[], &["~/experiments"]);
+        let mut c = ctx("/home/u/experiments", &[]);
+        c.home = None;
+        assert!(!context_disables(&cfg, &c));
+    }
+}
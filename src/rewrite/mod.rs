@@ -1,14 +1,19 @@
 pub mod types;
 
 pub(crate) mod compound;
+pub(crate) mod conditions;
 pub(crate) mod rules;
 pub(crate) mod user_config;
 
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 use crate::config;
-use compound::split_compound;
-use rules::{apply_rules, should_skip};
+use crate::config::cache::CachedPattern;
+use crate::daemon;
+use compound::{has_unsafe_construct, split_compound};
+use conditions::SkipContext;
+use rules::{apply_rules, is_external_command, should_skip};
 use types::{RewriteConfig, RewriteRule};
 
 pub use user_config::load_user_config;
@@ -19,36 +24,93 @@ pub use user_config::load_user_config;
 /// `^{command_pattern}(\s.*)?$` → `tokf run {0}`
 ///
 /// Handles `CommandPattern::Multiple` (one rule per pattern string) and
-/// wildcards (`*` → `\S+` in the regex).
-pub(crate) fn build_rules_from_filters(search_dirs: &[PathBuf]) -> Vec<RewriteRule> {
-    let mut rules = Vec::new();
-    let mut seen_patterns: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    let Ok(filters) = config::cache::discover_with_cache(search_dirs) else {
-        return rules;
-    };
-
-    for filter in filters {
-        for pattern in filter.config.command.patterns() {
-            if !seen_patterns.insert(pattern.clone()) {
-                continue;
-            }
+/// wildcards (`*` → `\S+` in the regex). Leading `VAR=value` assignments
+/// are matched too, so `CI=1 npm test` becomes `tokf run CI=1 npm test`.
+///
+/// Patterns and their regexes come pre-derived from the binary cache;
+/// patterns whose regex failed to compile are left out.
+pub(crate) fn build_rules_from_filters(
+    search_dirs: &[PathBuf],
+    include_builtin: bool,
+) -> Vec<RewriteRule> {
+    config::cache::discover_patterns_with_cache(search_dirs, include_builtin)
+        .map(rules_from_patterns)
+        .unwrap_or_default()
+}
+
+/// One `tokf run` rule per distinct valid pattern, in order.
+pub(crate) fn rules_from_patterns(
+    patterns: impl IntoIterator<Item = CachedPattern>,
+) -> Vec<RewriteRule> {
+    let mut seen_patterns: HashSet<String> = HashSet::new();
+    patterns
+        .into_iter()
+        .filter(|cached| cached.valid && seen_patterns.insert(cached.pattern.clone()))
+        .map(|cached| RewriteRule {
+            match_pattern: cached.rewrite_regex,
+            replace: "tokf run {0}".to_string(),
+        })
+        .collect()
+}
+
+/// Filter-derived rules for one rewrite. They are asked of the daemon while
+/// it answers, and built locally on first use otherwise.
+struct FilterRules<'a> {
+    search_dirs: &'a [PathBuf],
+    include_builtin: bool,
+    /// Cleared once the daemon fails to answer.
+    daemon: Option<daemon::Scope>,
+    local: Option<Vec<RewriteRule>>,
+    /// A `_default.toml` is in scope, so unmatched commands are wrapped too.
+    catch_all: bool,
+}
+
+impl<'a> FilterRules<'a> {
+    fn new(search_dirs: &'a [PathBuf], include_builtin: bool) -> Self {
+        Self {
+            search_dirs,
+            include_builtin,
+            daemon: daemon::enabled().then(|| daemon::Scope::current(search_dirs, include_builtin)),
+            local: None,
+            catch_all: search_dirs
+                .iter()
+                .any(|dir| dir.join(config::DEFAULT_FILTER).is_file()),
+        }
+    }
 
-            let regex_str = config::command_pattern_to_regex(pattern);
-            rules.push(RewriteRule {
-                match_pattern: regex_str,
-                replace: "tokf run {0}".to_string(),
-            });
+    fn apply(&mut self, segment: &str) -> String {
+        let rewritten = self.apply_filters(segment);
+        if rewritten == segment && self.catch_all && is_external_command(segment) {
+            return format!("tokf run {segment}");
         }
+        rewritten
     }
 
-    rules
+    fn apply_filters(&mut self, segment: &str) -> String {
+        if let Some(scope) = &self.daemon {
+            if let Some(rewritten) = daemon::rewrite(scope, segment) {
+                return rewritten;
+            }
+            self.daemon = None;
+        }
+        let rules = self.local.get_or_insert_with(|| {
+            build_rules_from_filters(self.search_dirs, self.include_builtin)
+        });
+        apply_rules(rules, segment)
+    }
 }
 
 /// Top-level rewrite function. Orchestrates skip check, user rules, and filter rules.
-pub fn rewrite(command: &str) -> String {
+///
+/// `include_builtin` controls whether stdlib filters produce rewrite rules.
+pub fn rewrite(command: &str, include_builtin: bool) -> String {
     let user_config = load_user_config().unwrap_or_default();
-    rewrite_with_config(command, &user_config, &config::default_search_dirs())
+    rewrite_with_config(
+        command,
+        &user_config,
+        &config::default_search_dirs(),
+        include_builtin,
+    )
 }
 
 /// Testable version with explicit config and search dirs.
@@ -56,13 +118,12 @@ pub(crate) fn rewrite_with_config(
     command: &str,
     user_config: &RewriteConfig,
     search_dirs: &[PathBuf],
+    include_builtin: bool,
 ) -> String {
-    let user_skip_patterns = user_config
-        .skip
-        .as_ref()
-        .map_or(&[] as &[String], |s| &s.patterns);
+    let skip = user_config.skip.clone().unwrap_or_default();
+    let ctx = SkipContext::current();
 
-    if should_skip(command, user_skip_patterns) {
+    if should_skip(command, &skip, &ctx) {
         return command.to_string();
     }
 
@@ -71,10 +132,16 @@ pub(crate) fn rewrite_with_config(
         return user_result;
     }
 
-    let filter_rules = build_rules_from_filters(search_dirs);
+    // Subshells and command substitution can't be rewritten segment-by-segment
+    // without changing what runs where — leave them for the shell as written.
+    if has_unsafe_construct(command) {
+        return command.to_string();
+    }
+
+    let mut filter_rules = FilterRules::new(search_dirs, include_builtin);
     let segments = split_compound(command);
     if segments.len() == 1 {
-        return apply_rules(&filter_rules, command);
+        return filter_rules.apply(command);
     }
 
     // Compound command: rewrite each segment independently so every sub-command
@@ -83,10 +150,10 @@ pub(crate) fn rewrite_with_config(
     let mut out = String::with_capacity(command.len() + segments.len() * 9);
     for (seg, sep) in &segments {
         let trimmed = seg.trim();
-        let rewritten = if trimmed.is_empty() || should_skip(trimmed, user_skip_patterns) {
+        let rewritten = if trimmed.is_empty() || should_skip(trimmed, &skip, &ctx) {
             trimmed.to_string()
         } else {
-            let r = apply_rules(&filter_rules, trimmed);
+            let r = filter_rules.apply(trimmed);
             if r != trimmed {
                 changed = true;
             }
@@ -109,10 +176,11 @@ mod tests {
 
     // --- build_rules_from_filters ---
 
+    #[cfg(feature = "stdlib")]
     #[test]
     fn build_rules_from_empty_dir() {
         let dir = TempDir::new().unwrap();
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         // Empty disk dir — embedded stdlib is always present
         assert!(
             !rules.is_empty(),
@@ -134,7 +202,7 @@ mod tests {
         )
         .unwrap();
 
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         let patterns: Vec<&str> = rules.iter().map(|r| r.match_pattern.as_str()).collect();
 
         let has_cargo = patterns
@@ -143,8 +211,8 @@ mod tests {
         let has_git = patterns
             .iter()
             .any(|p| p.contains("git") && p.contains("status"));
-        assert!(has_cargo, "expected cargo test pattern in {:?}", patterns);
-        assert!(has_git, "expected git status pattern in {:?}", patterns);
+        assert!(has_cargo, "expected cargo test pattern in {patterns:?}");
+        assert!(has_git, "expected git status pattern in {patterns:?}");
 
         let cargo_rule = rules
             .iter()
@@ -178,8 +246,10 @@ mod tests {
         )
         .unwrap();
 
-        let rules =
-            build_rules_from_filters(&[dir1.path().to_path_buf(), dir2.path().to_path_buf()]);
+        let rules = build_rules_from_filters(
+            &[dir1.path().to_path_buf(), dir2.path().to_path_buf()],
+            true,
+        );
         let git_status_count = rules
             .iter()
             .filter(|r| r.match_pattern.contains("git") && r.match_pattern.contains("status"))
@@ -196,7 +266,7 @@ mod tests {
         fs::write(dir.path().join("bad.toml"), "not valid [[[").unwrap();
         fs::write(dir.path().join("good.toml"), "command = \"my-tool\"").unwrap();
 
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         assert!(
             rules.iter().any(|r| r.match_pattern.contains("my\\-tool")),
             "expected my-tool rule in {:?}",
@@ -212,7 +282,7 @@ mod tests {
         fs::write(git_dir.join("push.toml"), "command = \"git push\"").unwrap();
         fs::write(git_dir.join("status.toml"), "command = \"git status\"").unwrap();
 
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         let patterns: Vec<&str> = rules.iter().map(|r| r.match_pattern.as_str()).collect();
         assert!(patterns.iter().any(|p| p.contains("push")));
         assert!(patterns.iter().any(|p| p.contains("status")));
@@ -227,7 +297,7 @@ mod tests {
         )
         .unwrap();
 
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         let patterns: Vec<&str> = rules.iter().map(|r| r.match_pattern.as_str()).collect();
         assert!(patterns.iter().any(|p| p.contains("pnpm")));
         assert!(
@@ -242,7 +312,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         fs::write(dir.path().join("npm-run.toml"), r#"command = "npm run *""#).unwrap();
 
-        let rules = build_rules_from_filters(&[dir.path().to_path_buf()]);
+        let rules = build_rules_from_filters(&[dir.path().to_path_buf()], true);
         let npm_run_rule = rules
             .iter()
             .find(|r| r.match_pattern.contains("npm") && r.match_pattern.contains("run"))
@@ -265,10 +335,26 @@ mod tests {
         .unwrap();
 
         let config = RewriteConfig::default();
-        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()], true);
         assert_eq!(result, "tokf run git status");
     }
 
+    #[cfg(feature = "stdlib")]
+    #[test]
+    fn rewrite_without_builtin_ignores_stdlib() {
+        let dir = TempDir::new().unwrap();
+        let config = RewriteConfig::default();
+        let dirs = [dir.path().to_path_buf()];
+        assert_eq!(
+            rewrite_with_config("git status", &config, &dirs, true),
+            "tokf run git status"
+        );
+        assert_eq!(
+            rewrite_with_config("git status", &config, &dirs, false),
+            "git status"
+        );
+    }
+
     #[test]
     fn rewrite_with_filter_match_with_args() {
         let dir = TempDir::new().unwrap();
@@ -279,17 +365,44 @@ mod tests {
         .unwrap();
 
         let config = RewriteConfig::default();
-        let result =
-            rewrite_with_config("git status --short", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config(
+            "git status --short",
+            &config,
+            &[dir.path().to_path_buf()],
+            true,
+        );
         assert_eq!(result, "tokf run git status --short");
     }
 
+    #[test]
+    fn rewrite_with_env_prefix_keeps_assignments() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("cargo-test.toml"),
+            "command = \"cargo test\"",
+        )
+        .unwrap();
+
+        let config = RewriteConfig::default();
+        let result = rewrite_with_config(
+            "RUST_LOG=debug cargo test",
+            &config,
+            &[dir.path().to_path_buf()],
+            true,
+        );
+        assert_eq!(result, "tokf run RUST_LOG=debug cargo test");
+    }
+
     #[test]
     fn rewrite_builtin_skip_tokf() {
         let dir = TempDir::new().unwrap();
         let config = RewriteConfig::default();
-        let result =
-            rewrite_with_config("tokf run git status", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config(
+            "tokf run git status",
+            &config,
+            &[dir.path().to_path_buf()],
+            true,
+        );
         assert_eq!(result, "tokf run git status");
     }
 
@@ -297,7 +410,12 @@ mod tests {
     fn rewrite_no_match_passthrough() {
         let dir = TempDir::new().unwrap();
         let config = RewriteConfig::default();
-        let result = rewrite_with_config("unknown-cmd foo", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config(
+            "unknown-cmd foo",
+            &config,
+            &[dir.path().to_path_buf()],
+            true,
+        );
         assert_eq!(result, "unknown-cmd foo");
     }
 
@@ -317,7 +435,7 @@ mod tests {
                 replace: "custom-wrapper {0}".to_string(),
             }],
         };
-        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()], true);
         assert_eq!(result, "custom-wrapper git status");
     }
 
@@ -333,10 +451,11 @@ mod tests {
         let config = RewriteConfig {
             skip: Some(types::SkipConfig {
                 patterns: vec!["^git status".to_string()],
+                ..types::SkipConfig::default()
             }),
             rewrite: vec![],
         };
-        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()]);
+        let result = rewrite_with_config("git status", &config, &[dir.path().to_path_buf()], true);
         assert_eq!(result, "git status");
     }
 
@@ -357,6 +476,7 @@ mod tests {
             "git add foo && git status",
             &config,
             &[dir.path().to_path_buf()],
+            true,
         );
         assert_eq!(r, "tokf run git add foo && tokf run git status");
     }
@@ -375,6 +495,7 @@ mod tests {
             "unknown-cmd && git status",
             &config,
             &[dir.path().to_path_buf()],
+            true,
         );
         assert_eq!(r, "unknown-cmd && tokf run git status");
     }
@@ -389,11 +510,52 @@ mod tests {
             "git diff HEAD | head -5",
             &config,
             &[dir.path().to_path_buf()],
+            true,
         );
         // Pipe is NOT a chain separator — the whole string is one segment.
         assert_eq!(r, "tokf run git diff HEAD | head -5");
     }
 
+    #[test]
+    fn rewrite_compound_quoted_operator_not_split() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("git-commit.toml"),
+            "command = \"git commit\"",
+        )
+        .unwrap();
+        fs::write(dir.path().join("git-push.toml"), "command = \"git push\"").unwrap();
+
+        let config = RewriteConfig::default();
+        let r = rewrite_with_config(
+            "git commit -m 'a && b' && git push",
+            &config,
+            &[dir.path().to_path_buf()],
+            true,
+        );
+        assert_eq!(r, "tokf run git commit -m 'a && b' && tokf run git push");
+    }
+
+    #[test]
+    fn rewrite_subshell_left_untouched() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("git-status.toml"),
+            "command = \"git status\"",
+        )
+        .unwrap();
+
+        let config = RewriteConfig::default();
+        let dirs = [dir.path().to_path_buf()];
+        for cmd in [
+            "(cd sub && git status)",
+            "git status && echo $(date)",
+            "git status `pwd`",
+        ] {
+            assert_eq!(rewrite_with_config(cmd, &config, &dirs, true), cmd);
+        }
+    }
+
     #[test]
     fn rewrite_compound_no_match_passthrough() {
         let dir = TempDir::new().unwrap();
@@ -402,6 +564,7 @@ mod tests {
             "unknown-a && unknown-b",
             &config,
             &[dir.path().to_path_buf()],
+            true,
         );
         assert_eq!(r, "unknown-a && unknown-b");
     }
@@ -1,27 +1,35 @@
 use regex::Regex;
 
-use super::types::RewriteRule;
+use crate::regex_cache;
+
+use super::conditions::{SkipContext, context_disables};
+use super::types::{RewriteRule, SkipConfig};
 
 /// Built-in skip patterns that are always active.
 /// - `^tokf ` prevents double-wrapping
 /// - `<<` prevents rewriting heredocs
 const BUILTIN_SKIP_PATTERNS: &[&str] = &["^tokf ", "<<"];
 
-/// Check if a command should be skipped (not rewritten).
-pub fn should_skip(command: &str, user_patterns: &[String]) -> bool {
+/// Check if a command should be skipped (not rewritten): built-in patterns,
+/// the user's `[skip]` patterns, and `[skip]` `env`/`dirs` conditions on `ctx`.
+pub fn should_skip(command: &str, skip: &SkipConfig, ctx: &SkipContext) -> bool {
     for pattern in BUILTIN_SKIP_PATTERNS {
-        if let Ok(re) = Regex::new(pattern)
+        if let Some(re) = regex_cache::compile(pattern)
             && re.is_match(command)
         {
             return true;
         }
     }
 
-    for pattern in user_patterns {
+    if context_disables(skip, ctx) {
+        return true;
+    }
+
+    for pattern in &skip.patterns {
         match Regex::new(pattern) {
             Ok(re) if re.is_match(command) => return true,
             Err(e) => {
-                eprintln!("[tokf] warning: invalid skip pattern \"{pattern}\": {e}");
+                tracing::warn!("invalid skip pattern \"{pattern}\": {e}");
             }
             _ => {}
         }
@@ -30,10 +38,39 @@ pub fn should_skip(command: &str, user_patterns: &[String]) -> bool {
     false
 }
 
+/// Shell builtins and keywords: run under `tokf run` they would lose their
+/// effect on the shell (`cd`, `export`) or break the surrounding syntax
+/// (`do`, `fi`), so the catch-all filter never wraps them.
+const SHELL_BUILTINS: &[&str] = &[
+    "!", ".", ":", "[[", "{", "}", "alias", "bg", "builtin", "case", "cd", "declare", "do", "done",
+    "elif", "else", "esac", "eval", "exec", "exit", "export", "fg", "fi", "for", "function",
+    "hash", "if", "jobs", "local", "popd", "pushd", "read", "readonly", "return", "select", "set",
+    "shift", "shopt", "source", "then", "time", "trap", "typeset", "ulimit", "umask", "unalias",
+    "unset", "until", "wait", "while",
+];
+
+/// Whether `command` runs a program, so the catch-all filter can wrap it:
+/// leading `VAR=value` assignments are skipped, and a bare assignment or a
+/// shell builtin is not.
+pub fn is_external_command(command: &str) -> bool {
+    command
+        .split_whitespace()
+        .find(|word| !is_assignment(word))
+        .is_some_and(|program| !SHELL_BUILTINS.contains(&program))
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
 /// Apply the first matching rewrite rule. Returns the original command if none match.
 pub fn apply_rules(rules: &[RewriteRule], command: &str) -> String {
     for rule in rules {
-        let Ok(re) = Regex::new(&rule.match_pattern) else {
+        let Some(re) = regex_cache::compile(&rule.match_pattern) else {
             continue;
         };
 
@@ -68,10 +105,18 @@ fn interpolate_rewrite(template: &str, caps: &regex::Captures<'_>, full_input: &
 }
 
 #[cfg(test)]
-#[allow(clippy::unwrap_used, clippy::expect_used)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::trivial_regex)]
 mod tests {
     use super::*;
 
+    fn should_skip(command: &str, patterns: &[String]) -> bool {
+        let skip = SkipConfig {
+            patterns: patterns.to_vec(),
+            ..SkipConfig::default()
+        };
+        super::should_skip(command, &skip, &SkipContext::default())
+    }
+
     // --- should_skip ---
 
     #[test]
@@ -100,6 +145,18 @@ mod tests {
         assert!(!should_skip("git status", &patterns));
     }
 
+    #[test]
+    fn skip_everything_when_context_condition_holds() {
+        let skip = SkipConfig {
+            env: vec!["TOKF_DISABLE=1".to_string()],
+            ..SkipConfig::default()
+        };
+        let mut ctx = SkipContext::default();
+        assert!(!super::should_skip("git status", &skip, &ctx));
+        ctx.vars.insert("TOKF_DISABLE".to_string(), "1".to_string());
+        assert!(super::should_skip("git status", &skip, &ctx));
+    }
+
     #[test]
     fn no_skip_normal_commands() {
         assert!(!should_skip("git status", &[]));
@@ -107,6 +164,24 @@ mod tests {
         assert!(!should_skip("ls -la", &[]));
     }
 
+    // --- is_external_command ---
+
+    #[test]
+    fn external_commands_skip_leading_assignments() {
+        assert!(is_external_command("make build"));
+        assert!(is_external_command("CI=1 RUST_LOG=debug ./run.sh"));
+        assert!(!is_external_command("FOO=bar"));
+        assert!(!is_external_command(""));
+    }
+
+    #[test]
+    fn builtins_and_keywords_are_not_external() {
+        assert!(!is_external_command("cd src"));
+        assert!(!is_external_command("FOO=1 export BAR"));
+        assert!(!is_external_command("do echo $x"));
+        assert!(!is_external_command("done"));
+    }
+
     // --- apply_rules ---
 
     #[test]
@@ -11,12 +11,22 @@ pub struct RewriteConfig {
     pub rewrite: Vec<RewriteRule>,
 }
 
-/// Extra skip patterns from user config.
+/// Extra skip patterns and conditions from user config.
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct SkipConfig {
     /// Regex patterns — if any matches the command, rewriting is skipped.
     #[serde(default)]
     pub patterns: Vec<String>,
+
+    /// Environment conditions (`VAR=value`, or `VAR` for set and non-empty);
+    /// while any holds, nothing is rewritten.
+    #[serde(default)]
+    pub env: Vec<String>,
+
+    /// Directory globs (`~/experiments/**`); nothing is rewritten in or
+    /// below a matching directory.
+    #[serde(default)]
+    pub dirs: Vec<String>,
 }
 
 /// A single rewrite rule: match a command and replace it.
@@ -94,10 +104,10 @@ replace = "tokf run {0}"
 
     #[test]
     fn deserialize_empty_skip_patterns() {
-        let toml_str = r#"
+        let toml_str = r"
 [skip]
 patterns = []
-"#;
+";
         let config: RewriteConfig = toml::from_str(toml_str).unwrap();
         let skip = config.skip.unwrap();
         assert!(skip.patterns.is_empty());
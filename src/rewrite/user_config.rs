@@ -6,7 +6,7 @@ use super::types::RewriteConfig;
 ///
 /// Search order:
 /// 1. `.tokf/rewrites.toml` (project-local)
-/// 2. `~/.config/tokf/rewrites.toml` (user-level)
+/// 2. `~/.config/tokf/rewrites.toml` (user-level; `$TOKF_HOME/rewrites.toml` if set)
 pub fn load_user_config() -> Option<RewriteConfig> {
     load_user_config_from(&config_search_paths())
 }
@@ -18,8 +18,8 @@ fn config_search_paths() -> Vec<PathBuf> {
         paths.push(cwd.join(".tokf/rewrites.toml"));
     }
 
-    if let Some(config) = dirs::config_dir() {
-        paths.push(config.join("tokf/rewrites.toml"));
+    if let Some(config) = crate::paths::config_dir() {
+        paths.push(config.join("rewrites.toml"));
     }
 
     paths
@@ -32,7 +32,7 @@ pub fn load_user_config_from(paths: &[PathBuf]) -> Option<RewriteConfig> {
             match toml::from_str(&content) {
                 Ok(config) => return Some(config),
                 Err(e) => {
-                    eprintln!("[tokf] warning: failed to parse {}: {e}", path.display());
+                    tracing::warn!("failed to parse {}: {e}", path.display());
                     return None;
                 }
             }
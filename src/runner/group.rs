@@ -0,0 +1,86 @@
+use std::process::Child;
+
+/// Kill `child` and every process in its group (the child leads its own group).
+#[cfg(unix)]
+pub fn kill_process_group(child: &mut Child) {
+    let killed = group_id(child).is_some_and(|pgid| {
+        // SAFETY: kill(2) has no memory-safety preconditions.
+        unsafe { libc::kill(-pgid, libc::SIGKILL) == 0 }
+    });
+    if !killed {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_process_group(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(unix)]
+fn group_id(child: &Child) -> Option<libc::pid_t> {
+    libc::pid_t::try_from(child.id()).ok().filter(|&id| id > 0)
+}
+
+/// Signals passed on to the child's process group while it runs.
+#[cfg(unix)]
+const FORWARDED: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Process group that forwarded signals go to (0 = none).
+#[cfg(unix)]
+static CHILD_GROUP: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn forward(sig: libc::c_int) {
+    let pgid = CHILD_GROUP.load(std::sync::atomic::Ordering::SeqCst);
+    if pgid > 0 {
+        // SAFETY: kill(2) is async-signal-safe.
+        unsafe { libc::kill(-pgid, sig) };
+    }
+}
+
+/// While alive, relays SIGINT/SIGTERM/SIGHUP received by tokf to a child
+/// that leads its own process group or session.
+///
+/// Such a child is outside the terminal's foreground group, so without this
+/// Ctrl-C would stop tokf and leave the command running. The child decides
+/// how to react; tokf keeps waiting and reports its exit status as usual.
+pub struct ForwardSignals {
+    #[cfg(unix)]
+    previous: Vec<(libc::c_int, libc::sighandler_t)>,
+}
+
+impl ForwardSignals {
+    #[cfg(unix)]
+    pub fn to(child: &Child) -> Self {
+        let Some(pgid) = group_id(child) else {
+            return Self {
+                previous: Vec::new(),
+            };
+        };
+        CHILD_GROUP.store(pgid, std::sync::atomic::Ordering::SeqCst);
+        let handler = forward as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        let previous = FORWARDED
+            .iter()
+            // SAFETY: `forward` only performs async-signal-safe operations.
+            .map(|&sig| (sig, unsafe { libc::signal(sig, handler) }))
+            .collect();
+        Self { previous }
+    }
+
+    #[cfg(not(unix))]
+    pub const fn to(_child: &Child) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ForwardSignals {
+    fn drop(&mut self) {
+        for &(sig, handler) in &self.previous {
+            // SAFETY: restores the handler that was installed before `to`.
+            unsafe { libc::signal(sig, handler) };
+        }
+        CHILD_GROUP.store(0, std::sync::atomic::Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,518 @@
+use std::ffi::OsString;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::config::is_env_assignment;
+use crate::config::types::StreamSource;
+
+mod group;
+mod pty;
+pub mod shell;
+
+pub use pty::execute_pty;
+pub(crate) use shell::shell_escape;
+
+/// Exit code reported for a command killed after exceeding its timeout
+/// (the same code `timeout(1)` uses).
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// How often a running command is polled while a timeout is pending.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct CommandResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+    pub combined: String,
+    /// `Some(secs)` when the command was killed after running for `secs` seconds.
+    pub timed_out: Option<u64>,
+    /// Filtered output of the filter's `[[step]]`s, by their `as` name.
+    pub steps: Vec<(String, String)>,
+}
+
+impl CommandResult {
+    /// Wrap output captured elsewhere (a fixture or log file) as if a command
+    /// had printed it to stdout and exited with `exit_code`.
+    pub fn from_output(text: &str, exit_code: i32) -> Self {
+        let text = text.trim_end().to_string();
+        Self {
+            stdout: text.clone(),
+            stderr: String::new(),
+            exit_code,
+            combined: text,
+            timed_out: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Text of the selected stream, without trailing whitespace.
+    pub fn stream(&self, source: StreamSource) -> &str {
+        match source {
+            StreamSource::Combined => &self.combined,
+            StreamSource::Stdout => self.stdout.trim_end(),
+            StreamSource::Stderr => self.stderr.trim_end(),
+        }
+    }
+
+    pub(crate) const fn mark_timed_out(&mut self, timeout: Duration) {
+        self.exit_code = TIMEOUT_EXIT_CODE;
+        self.timed_out = Some(timeout.as_secs());
+    }
+}
+
+pub(crate) fn build_result(output: &std::process::Output) -> CommandResult {
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    #[cfg(unix)]
+    let exit_code = {
+        use std::os::unix::process::ExitStatusExt;
+        output
+            .status
+            .code()
+            .unwrap_or_else(|| output.status.signal().map_or(1, |s| 128 + s))
+    };
+    #[cfg(not(unix))]
+    let exit_code = output.status.code().unwrap_or(1);
+
+    let combined = match (stdout.is_empty(), stderr.is_empty()) {
+        (true, true) => String::new(),
+        (false, true) => stdout.clone(),
+        (true, false) => stderr.clone(),
+        (false, false) => format!("{}\n{}", stdout.trim_end(), stderr),
+    };
+    let combined = combined.trim_end().to_string();
+
+    CommandResult {
+        stdout,
+        stderr,
+        exit_code,
+        combined,
+        timed_out: None,
+        steps: Vec::new(),
+    }
+}
+
+/// Run `cmd` to completion, or until `timeout` elapses.
+///
+/// On timeout the whole process group is killed, whatever output was produced
+/// so far is kept, and the exit code is [`TIMEOUT_EXIT_CODE`].
+///
+/// # Errors
+///
+/// Returns an error if the process fails to spawn or cannot be waited on.
+pub fn execute_command(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandResult> {
+    let Some(timeout) = timeout else {
+        return Ok(build_result(&cmd.output()?));
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Lead a new process group so a timeout also reaches grandchildren.
+        cmd.process_group(0);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Outside the terminal's foreground group, so Ctrl-C must be passed on.
+    let _forward = group::ForwardSignals::to(&child);
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let (status, timed_out) = wait_with_timeout(&mut child, timeout)?;
+
+    let output = Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    };
+    let mut result = build_result(&output);
+    if timed_out {
+        result.mark_timed_out(timeout);
+    }
+    Ok(result)
+}
+
+/// Poll `child` until it exits or `timeout` elapses, killing its process group
+/// in the latter case. Returns the exit status and whether it timed out.
+pub(crate) fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Duration,
+) -> std::io::Result<(std::process::ExitStatus, bool)> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+        if Instant::now() >= deadline {
+            group::kill_process_group(child);
+            return Ok((child.wait()?, true));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Read a child pipe to EOF on a background thread.
+pub(crate) fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Execute a command with the given arguments.
+///
+/// Leading `VAR=value` words in `command` are set in the child's environment,
+/// as a shell would, rather than treated as the program name.
+///
+/// # Errors
+///
+/// Returns an error if the command string is empty or the process fails to spawn.
+pub fn execute(command: &str, args: &[String]) -> anyhow::Result<CommandResult> {
+    execute_with_timeout(command, args, None)
+}
+
+/// Like [`execute`], killing the command once `timeout` elapses.
+///
+/// # Errors
+///
+/// Returns an error if the command string is empty or the process fails to spawn.
+pub fn execute_with_timeout(
+    command: &str,
+    args: &[String],
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandResult> {
+    execute_command(build_command(command, args)?, timeout)
+}
+
+/// Build the [`Command`] that [`execute`] runs, without spawning it.
+///
+/// # Errors
+///
+/// Returns an error if the command string is empty.
+pub fn build_command(command: &str, args: &[String]) -> anyhow::Result<Command> {
+    let mut parts = command.split_whitespace().peekable();
+    let mut env = Vec::new();
+    while let Some(assignment) = parts.next_if(|w| is_env_assignment(w)) {
+        env.extend(assignment.split_once('='));
+    }
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("empty command"))?;
+    let base_args: Vec<&str> = parts.collect();
+
+    let mut cmd = Command::new(program);
+    cmd.envs(env).args(&base_args).args(args);
+    Ok(cmd)
+}
+
+/// Execute a shell command with `{args}` interpolation.
+///
+/// # Errors
+///
+/// Returns an error if the shell process fails to spawn.
+pub fn execute_shell(run: &str, args: &[String]) -> anyhow::Result<CommandResult> {
+    execute_shell_with_env(run, args, &[], None)
+}
+
+/// Like [`execute_shell`], with `VAR=value` assignments set for the shell and
+/// an optional timeout.
+///
+/// # Errors
+///
+/// Returns an error if the shell process fails to spawn.
+pub fn execute_shell_with_env(
+    run: &str,
+    args: &[String],
+    env: &[String],
+    timeout: Option<Duration>,
+) -> anyhow::Result<CommandResult> {
+    execute_command(build_shell_command(run, args, env), timeout)
+}
+
+/// Build the shell [`Command`] for a `run` override, without spawning it:
+/// `sh -c` on Unix, `cmd /C` on Windows.
+pub fn build_shell_command(run: &str, args: &[String], env: &[String]) -> Command {
+    let joined_args = args
+        .iter()
+        .map(|a| shell_escape(a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    #[allow(clippy::literal_string_with_formatting_args)]
+    let shell_cmd = run.replace("{args}", &joined_args);
+
+    let mut cmd = shell::shell_command(&shell_cmd);
+    cmd.envs(env.iter().filter_map(|a| a.split_once('=')));
+    cmd
+}
+
+/// Set a filter's `[env]` on `cmd`, leaving variables the command line
+/// already assigns (`VAR=value cargo test`) untouched.
+pub fn apply_filter_env<'a>(
+    cmd: &mut Command,
+    env: impl IntoIterator<Item = (&'a String, &'a String)>,
+) {
+    let explicit: Vec<OsString> = cmd.get_envs().map(|(k, _)| k.to_owned()).collect();
+    for (key, value) in env {
+        if !explicit.iter().any(|k| k == key.as_str()) {
+            cmd.env(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::literal_string_with_formatting_args
+)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // --- execute tests ---
+
+    #[test]
+    fn test_execute_echo() {
+        let result = execute("echo hello", &[]).unwrap();
+        assert_eq!(result.stdout.trim(), "hello");
+        assert_eq!(result.exit_code, 0);
+        assert!(result.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_execute_env_prefix_sets_env() {
+        let args = vec!["-c".to_string(), "echo $TOKF_TEST_VAR".to_string()];
+        let result = execute("TOKF_TEST_VAR=hi sh", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "hi");
+    }
+
+    #[test]
+    fn test_execute_env_prefix_only_is_error() {
+        assert!(execute("FOO=1", &[]).is_err());
+    }
+
+    #[test]
+    fn filter_env_is_set_unless_command_line_assigns_it() {
+        let env = HashMap::from([
+            ("TOKF_A".to_string(), "filter".to_string()),
+            ("TOKF_B".to_string(), "filter".to_string()),
+        ]);
+        let args = vec!["-c".to_string(), "echo $TOKF_A $TOKF_B".to_string()];
+        let mut cmd = build_command("TOKF_A=user sh", &args).unwrap();
+        apply_filter_env(&mut cmd, &env);
+        let result = execute_command(cmd, None).unwrap();
+        assert_eq!(result.stdout.trim(), "user filter");
+    }
+
+    #[test]
+    fn test_execute_shell_with_env() {
+        let env = vec!["TOKF_TEST_VAR=shell".to_string()];
+        let result = execute_shell_with_env("echo $TOKF_TEST_VAR", &[], &env, None).unwrap();
+        assert_eq!(result.stdout.trim(), "shell");
+    }
+
+    #[test]
+    fn test_execute_with_args() {
+        let args = vec!["hello".to_string(), "world".to_string()];
+        let result = execute("echo", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "hello world");
+    }
+
+    #[test]
+    fn test_execute_embedded_and_extra_args() {
+        let args = vec!["world".to_string()];
+        let result = execute("echo hello", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "hello world");
+    }
+
+    #[test]
+    fn test_execute_failure() {
+        let result = execute("false", &[]).unwrap();
+        assert_ne!(result.exit_code, 0);
+    }
+
+    #[test]
+    fn test_execute_specific_exit_code() {
+        let result = execute_shell("exit 42", &[]).unwrap();
+        assert_eq!(result.exit_code, 42);
+    }
+
+    #[test]
+    fn test_execute_empty_command() {
+        let result = execute("", &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_execute_whitespace_only_command() {
+        let result = execute("   ", &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_execute_nonexistent_command() {
+        let result = execute("nonexistent_cmd_xyz", &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_execute_args_with_special_characters() {
+        // execute() uses Command::new (no shell), so special chars are passed literally
+        let args = vec!["hello world".to_string()];
+        let result = execute("echo", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "hello world");
+        assert_eq!(result.exit_code, 0);
+    }
+
+    // --- execute_shell tests ---
+
+    #[test]
+    fn test_execute_shell_basic() {
+        let result = execute_shell("echo hello", &[]).unwrap();
+        assert_eq!(result.stdout.trim(), "hello");
+        assert_eq!(result.exit_code, 0);
+    }
+
+    #[test]
+    fn test_execute_shell_args_interpolation() {
+        let args = vec!["a".to_string(), "b".to_string()];
+        let result = execute_shell("echo {args}", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "a b");
+    }
+
+    #[test]
+    fn test_execute_shell_args_empty() {
+        let result = execute_shell("echo {args} done", &[]).unwrap();
+        assert_eq!(result.stdout.trim(), "done");
+    }
+
+    #[test]
+    fn test_execute_shell_args_escaped() {
+        let args = vec!["hello world".to_string()];
+        let result = execute_shell("echo {args}", &args).unwrap();
+        assert_eq!(result.stdout.trim(), "hello world");
+    }
+
+    #[test]
+    fn test_execute_shell_args_with_semicolon() {
+        let args = vec!["; echo injected".to_string()];
+        let result = execute_shell("echo {args}", &args).unwrap();
+        let stdout = result.stdout.trim();
+        // The semicolon should be escaped and printed literally, not executed
+        assert!(stdout.contains("; echo injected"));
+        // "injected" should not appear as a separate execution
+        assert!(!stdout.contains("\ninjected"));
+    }
+
+    // --- build_result / combined field tests ---
+
+    #[test]
+    fn test_execute_stderr() {
+        let result = execute_shell("echo err >&2", &[]).unwrap();
+        assert!(result.stderr.contains("err"));
+        assert!(result.stdout.is_empty());
+        assert_eq!(result.combined, "err");
+    }
+
+    #[test]
+    fn test_combined_both_empty() {
+        let result = execute("true", &[]).unwrap();
+        assert!(result.stdout.is_empty());
+        assert!(result.stderr.is_empty());
+        assert_eq!(result.combined, "");
+    }
+
+    #[test]
+    fn test_combined_stdout_only() {
+        let result = execute("echo hello", &[]).unwrap();
+        assert_eq!(result.combined, "hello");
+    }
+
+    #[test]
+    fn test_combined_stderr_only() {
+        let result = execute_shell("echo err >&2", &[]).unwrap();
+        assert_eq!(result.combined, "err");
+    }
+
+    #[test]
+    fn test_combined_both_streams() {
+        let result = execute_shell("echo out && echo err >&2", &[]).unwrap();
+        assert_eq!(result.combined, "out\nerr");
+    }
+
+    #[test]
+    fn test_stream_selects_source() {
+        let result = execute_shell("echo out && echo err >&2", &[]).unwrap();
+        assert_eq!(result.stream(StreamSource::Stdout), "out");
+        assert_eq!(result.stream(StreamSource::Stderr), "err");
+        assert_eq!(result.stream(StreamSource::Combined), "out\nerr");
+    }
+
+    #[test]
+    fn test_combined_no_double_newline() {
+        // stdout from echo ends with \n; combined should not have a blank line between streams
+        let result = execute_shell("echo out && echo err >&2", &[]).unwrap();
+        assert!(!result.combined.contains("\n\n"));
+    }
+
+    // --- timeout ---
+
+    #[test]
+    fn test_timeout_not_reached() {
+        let result = execute_with_timeout("echo hi", &[], Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(result.combined, "hi");
+        assert_eq!(result.timed_out, None);
+    }
+
+    #[test]
+    fn test_timeout_keeps_partial_output() {
+        let start = Instant::now();
+        let result = execute_shell_with_env(
+            "echo started; sleep 5; echo never",
+            &[],
+            &[],
+            Some(Duration::from_millis(300)),
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(result.exit_code, TIMEOUT_EXIT_CODE);
+        assert_eq!(result.combined, "started");
+        assert_eq!(result.timed_out, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_grandchildren() {
+        // The backgrounded sleep holds the pipe open; only a group kill ends it.
+        let start = Instant::now();
+        let result =
+            execute_shell_with_env("sleep 5 & wait", &[], &[], Some(Duration::from_millis(300)))
+                .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(result.exit_code, TIMEOUT_EXIT_CODE);
+    }
+
+    // --- signal handling (unix only) ---
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_signal_exit_code() {
+        // SIGTERM = 15, expected exit code = 128 + 15 = 143
+        let result = execute_shell("kill -TERM $$", &[]).unwrap();
+        assert_eq!(result.exit_code, 143);
+    }
+}
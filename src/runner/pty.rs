@@ -0,0 +1,171 @@
+use std::process::Command;
+use std::time::Duration;
+
+use super::CommandResult;
+
+/// Terminal size reported to the child (rows, columns).
+#[cfg(unix)]
+const PTY_SIZE: (u16, u16) = (40, 120);
+
+/// Run `cmd` attached to a new pseudo-terminal, so it sees a TTY on stdout and
+/// stderr and keeps its interactive output (colors, progress, layout).
+///
+/// Both streams arrive interleaved on the terminal, so everything lands in
+/// `stdout` (with `\r\n` normalized to `\n`) and `stderr` is empty. stdin is
+/// `/dev/null`. `timeout` behaves as in [`super::execute_command`].
+///
+/// # Errors
+///
+/// Returns an error if no pseudo-terminal can be allocated, the process fails
+/// to spawn, or (on non-Unix platforms) always.
+#[cfg(unix)]
+pub fn execute_pty(mut cmd: Command, timeout: Option<Duration>) -> anyhow::Result<CommandResult> {
+    use std::fs::File;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Output, Stdio};
+
+    let (master, slave) = open_pty()?;
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+    if std::env::var_os("TERM").is_none() {
+        cmd.env("TERM", "xterm-256color");
+    }
+    // SAFETY: only async-signal-safe libc calls run between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            // New session (and process group) with the pty as controlling terminal.
+            if libc::setsid() == -1
+                || libc::ioctl(libc::STDOUT_FILENO, libc::TIOCSCTTY as _, 0) == -1
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn()?;
+    // The child runs in its own session, so Ctrl-C must be passed on.
+    let _forward = super::group::ForwardSignals::to(&child);
+    // Close our copies of the slave so the reader sees EOF once the child exits.
+    drop(cmd);
+    let reader = super::drain(Some(File::from(master)));
+
+    let (status, timed_out) = match timeout {
+        Some(t) => super::wait_with_timeout(&mut child, t)?,
+        None => (child.wait()?, false),
+    };
+    let output = Output {
+        status,
+        stdout: normalize_newlines(&reader.join().unwrap_or_default()),
+        stderr: Vec::new(),
+    };
+    let mut result = super::build_result(&output);
+    if let Some(t) = timeout.filter(|_| timed_out) {
+        result.mark_timed_out(t);
+    }
+    Ok(result)
+}
+
+#[cfg(not(unix))]
+pub fn execute_pty(_cmd: Command, _timeout: Option<Duration>) -> anyhow::Result<CommandResult> {
+    anyhow::bail!("pty mode is only supported on Unix")
+}
+
+/// Allocate a pseudo-terminal pair `(master, slave)`, both close-on-exec.
+#[cfg(unix)]
+fn open_pty() -> std::io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let (rows, cols) = PTY_SIZE;
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let mut master = -1;
+    let mut slave = -1;
+    // SAFETY: the out-pointers are valid; a null name and termios are allowed.
+    let rc = unsafe {
+        libc::openpty(
+            &raw mut master,
+            &raw mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &raw const size,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: openpty succeeded, so both descriptors are open and owned by us.
+    let fds = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    for fd in [master, slave] {
+        // SAFETY: `fd` is a valid open descriptor owned by `fds`.
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    Ok(fds)
+}
+
+/// The terminal's output processing turns `\n` into `\r\n`; undo that so
+/// filters see the same line endings as piped output.
+#[cfg(unix)]
+fn normalize_newlines(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().peekable();
+    while let Some(&b) = iter.next() {
+        if b == b'\r' && iter.peek() == Some(&&b'\n') {
+            continue;
+        }
+        out.push(b);
+    }
+    out
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::runner::{TIMEOUT_EXIT_CODE, build_shell_command};
+
+    fn sh(script: &str) -> Command {
+        build_shell_command(script, &[], &[])
+    }
+
+    #[test]
+    fn child_sees_a_tty() {
+        let result = execute_pty(sh("[ -t 1 ] && [ -t 2 ] && echo tty"), None).unwrap();
+        assert_eq!(result.exit_code, 0);
+        assert_eq!(result.combined, "tty");
+    }
+
+    #[test]
+    fn streams_merge_and_newlines_normalize() {
+        let result = execute_pty(sh("echo out; echo err >&2; exit 3"), None).unwrap();
+        assert_eq!(result.exit_code, 3);
+        assert_eq!(result.stdout, "out\nerr\n");
+        assert!(result.stderr.is_empty());
+    }
+
+    #[test]
+    fn reports_terminal_size() {
+        let result = execute_pty(sh("stty size </dev/tty"), None).unwrap();
+        assert_eq!(result.combined, "40 120");
+    }
+
+    #[test]
+    fn timeout_kills_pty_child() {
+        let result = execute_pty(
+            sh("echo started; sleep 5"),
+            Some(Duration::from_millis(300)),
+        )
+        .unwrap();
+        assert_eq!(result.exit_code, TIMEOUT_EXIT_CODE);
+        assert_eq!(result.combined, "started");
+    }
+
+    #[test]
+    fn normalize_keeps_lone_carriage_returns() {
+        assert_eq!(normalize_newlines(b"a\r\nb\rc\r\n"), b"a\nb\rc\n");
+    }
+}
@@ -0,0 +1,98 @@
+//! The platform shell used for `run` overrides and hook commands.
+//!
+//! Unix runs `sh -c` with POSIX single-quoting; Windows runs `cmd /D /S /C`
+//! with double-quoting. Both quoting functions are always compiled so either
+//! form can be produced (and tested) on any platform.
+
+use std::process::Command;
+
+/// Quote `arg` for POSIX `sh` (single-quote wrapping).
+pub fn posix_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Quote `arg` for a `cmd.exe` command line read by the usual Windows
+/// argument parser: wrapped in double quotes, with embedded quotes and the
+/// backslashes before them escaped.
+pub fn cmd_quote(arg: &str) -> String {
+    let mut out = String::with_capacity(arg.len() + 2);
+    out.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                out.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                out.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            out.push(c);
+        }
+    }
+    // Backslashes before the closing quote must not escape it.
+    out.extend(std::iter::repeat_n('\\', backslashes * 2));
+    out.push('"');
+    out
+}
+
+/// Escape a string for safe inclusion in a command for this platform's shell.
+#[cfg(not(windows))]
+pub fn shell_escape(arg: &str) -> String {
+    posix_quote(arg)
+}
+
+/// Escape a string for safe inclusion in a command for this platform's shell.
+#[cfg(windows)]
+pub fn shell_escape(arg: &str) -> String {
+    cmd_quote(arg)
+}
+
+/// A [`Command`] running `script` through `sh -c`.
+#[cfg(not(windows))]
+pub fn shell_command(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    cmd
+}
+
+/// A [`Command`] running `script` through `cmd /D /S /C`.
+///
+/// The script is passed verbatim: the standard library's argument quoting
+/// would escape quotes that `cmd` does not understand.
+#[cfg(windows)]
+pub fn shell_command(script: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/D", "/S", "/C"])
+        .raw_arg(format!("\"{script}\""));
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_quote_escapes_single_quotes() {
+        assert_eq!(posix_quote("plain"), "'plain'");
+        assert_eq!(posix_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn cmd_quote_escapes_quotes_and_trailing_backslashes() {
+        assert_eq!(cmd_quote("plain"), "\"plain\"");
+        assert_eq!(cmd_quote("a b"), "\"a b\"");
+        assert_eq!(cmd_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(cmd_quote(r"C:\dir\"), r#""C:\dir\\""#);
+        assert_eq!(cmd_quote(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(
+            cmd_quote(r"C:\Program Files\tokf.exe"),
+            r#""C:\Program Files\tokf.exe""#
+        );
+    }
+}
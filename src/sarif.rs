@@ -0,0 +1,132 @@
+//! `tokf run --sarif <path>`: the diagnostics a filter's `[[diagnostic]]`
+//! rules find in the raw output, written as a SARIF 2.1.0 log so filtered
+//! lint and build runs can still feed code-scanning UIs.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use tokf::config::types::FilterConfig;
+use tokf::filter::diagnostics::{self, Diagnostic};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Write the report for a run. Without a filter, or a filter without rules,
+/// the log has no results, so a CI upload step always finds the file.
+/// Failing to write is reported and otherwise ignored: the command's own
+/// exit code still stands.
+pub fn write_report(path: &Path, filter: Option<&FilterConfig>, raw_output: &str) {
+    let found = filter.map_or_else(Vec::new, |cfg| {
+        diagnostics::collect(&cfg.diagnostic, raw_output)
+    });
+    let cwd = std::env::current_dir().ok();
+    let text = serde_json::to_string_pretty(&log(&found, cwd.as_deref())).unwrap_or_default();
+    if let Err(e) = std::fs::write(path, text + "\n") {
+        eprintln!("[tokf] failed to write SARIF to {}: {e}", path.display());
+    }
+}
+
+/// A SARIF log with one run holding `found`.
+fn log(found: &[Diagnostic], cwd: Option<&Path>) -> Value {
+    let rules: BTreeSet<&str> = found.iter().filter_map(|d| d.rule.as_deref()).collect();
+    let results: Vec<Value> = found.iter().map(|d| result(d, cwd)).collect();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "tokf",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                    "rules": rules.into_iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn result(d: &Diagnostic, cwd: Option<&Path>) -> Value {
+    let mut result = json!({
+        "level": d.level.as_str(),
+        "message": { "text": d.message },
+    });
+    if let Some(rule) = &d.rule {
+        result["ruleId"] = json!(rule);
+    }
+    if let Some(file) = &d.file {
+        let mut location = json!({ "artifactLocation": { "uri": uri(file, cwd) } });
+        if let Some(line) = d.line {
+            location["region"] = json!({ "startLine": line });
+            if let Some(column) = d.column {
+                location["region"]["startColumn"] = json!(column);
+            }
+        }
+        result["locations"] = json!([{ "physicalLocation": location }]);
+    }
+    result
+}
+
+/// `file` as a SARIF URI: relative to `cwd` where it lies below it, a
+/// `file://` URI for other absolute paths, with `/` separators throughout.
+fn uri(file: &str, cwd: Option<&Path>) -> String {
+    let path = Path::new(file);
+    let relative = cwd.and_then(|cwd| path.strip_prefix(cwd).ok());
+    match relative {
+        Some(rel) => rel.to_string_lossy().replace('\\', "/"),
+        None if path.is_absolute() => format!("file://{}", file.replace('\\', "/")),
+        None => file.trim_start_matches("./").replace('\\', "/"),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use tokf::filter::diagnostics::Level;
+
+    fn diagnostic(file: Option<&str>, rule: Option<&str>) -> Diagnostic {
+        Diagnostic {
+            file: file.map(str::to_string),
+            line: Some(3),
+            column: Some(5),
+            level: Level::Error,
+            rule: rule.map(str::to_string),
+            message: "boom".to_string(),
+        }
+    }
+
+    #[test]
+    fn results_carry_rules_and_locations() {
+        let found = [
+            diagnostic(Some("/work/src/a.ts"), Some("TS2322")),
+            diagnostic(None, None),
+        ];
+        let log = log(&found, Some(Path::new("/work")));
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"], json!([{ "id": "TS2322" }]));
+        assert_eq!(
+            run["results"][0],
+            json!({
+                "ruleId": "TS2322",
+                "level": "error",
+                "message": { "text": "boom" },
+                "locations": [{ "physicalLocation": {
+                    "artifactLocation": { "uri": "src/a.ts" },
+                    "region": { "startLine": 3, "startColumn": 5 },
+                }}],
+            })
+        );
+        assert!(run["results"][1].get("locations").is_none());
+    }
+
+    #[test]
+    fn uris_are_relative_inside_the_working_dir() {
+        let cwd = Some(Path::new("/work"));
+        assert_eq!(uri("./src/a.py", cwd), "src/a.py");
+        assert_eq!(uri("/elsewhere/b.py", cwd), "file:///elsewhere/b.py");
+    }
+}
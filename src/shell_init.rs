@@ -0,0 +1,140 @@
+//! `tokf shell-init <shell>`: shell functions that route interactive
+//! commands through `tokf run`, for terminals without an agent hook.
+//!
+//! One function is defined per program a filter pattern can start with
+//! (`git`, `cargo`, ...). Each call asks `tokf rewrite` whether the exact
+//! command would be wrapped, so skip patterns and user rewrite rules apply
+//! as they do in the hook; anything else — including output piped or
+//! redirected away from a terminal — runs the real program untouched.
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use clap::{Args, ValueEnum};
+
+use tokf::config;
+
+use crate::cli_error::{self, ErrorKind};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+}
+
+impl Shell {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+        }
+    }
+
+    const fn rc_file(self) -> &'static str {
+        match self {
+            Self::Bash => "~/.bashrc",
+            Self::Zsh => "~/.zshrc",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ShellInitArgs {
+    /// Shell to generate functions for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn cmd_shell_init(args: &ShellInitArgs, include_builtin: bool) -> i32 {
+    let search_dirs = config::default_search_dirs();
+    let patterns = match config::cache::discover_patterns_with_cache(&search_dirs, include_builtin)
+    {
+        Ok(patterns) => patterns,
+        Err(e) => return cli_error::report(ErrorKind::Config, &format_args!("{e:#}")),
+    };
+    let programs = wrapped_programs(
+        patterns
+            .iter()
+            .filter(|p| p.valid)
+            .map(|p| p.pattern.as_str()),
+    );
+    print!("{}", render(args.shell, &programs));
+    0
+}
+
+/// Distinct leading programs of `patterns` that can be shell function names.
+fn wrapped_programs<'a>(patterns: impl Iterator<Item = &'a str>) -> BTreeSet<String> {
+    patterns
+        .flat_map(config::leading_programs)
+        .filter(|p| is_function_name(p) && p != "tokf")
+        .collect()
+}
+
+/// Names both bash and zsh accept for `function NAME { ... }` without quoting.
+fn is_function_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'))
+}
+
+fn render(shell: Shell, programs: &BTreeSet<String>) -> String {
+    let mut out = format!(
+        "# tokf shell integration for {name}: add to {rc}\n\
+         #   eval \"$(tokf shell-init {name})\"\n\
+         # Re-run after adding filters to wrap their commands too.\n\
+         __tokf_wrap() {{\n  \
+           if [ -t 1 ] && [ \"$(command tokf rewrite \"$*\" 2>/dev/null)\" = \"tokf run $*\" ]; then\n    \
+             command tokf run \"$@\"\n  \
+           else\n    \
+             command \"$@\"\n  \
+           fi\n\
+         }}\n",
+        name = shell.name(),
+        rc = shell.rc_file(),
+    );
+    for program in programs {
+        // `function NAME` rather than `NAME()`: an alias of the same name
+        // would otherwise expand inside the definition.
+        let _ = writeln!(
+            out,
+            "function {program} {{ __tokf_wrap {program} \"$@\"; }}"
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn programs_are_deduplicated_and_sanitized() {
+        let programs = wrapped_programs(
+            [
+                "git push",
+                "git status",
+                "{yarn|npm} test",
+                "npx? tsc",
+                "* build",
+                "tokf run",
+                "-x y",
+            ]
+            .into_iter(),
+        );
+        let programs: Vec<&str> = programs.iter().map(String::as_str).collect();
+        assert_eq!(programs, ["git", "npm", "npx", "tsc", "yarn"]);
+    }
+
+    #[test]
+    fn render_defines_one_function_per_program() {
+        let programs = BTreeSet::from(["cargo".to_string(), "git".to_string()]);
+        let out = render(Shell::Zsh, &programs);
+        assert!(out.contains("eval \"$(tokf shell-init zsh)\""));
+        assert!(out.contains("__tokf_wrap() {"));
+        assert!(out.contains("function cargo { __tokf_wrap cargo \"$@\"; }\n"));
+        assert!(out.contains("function git { __tokf_wrap git \"$@\"; }\n"));
+    }
+}
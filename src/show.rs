@@ -0,0 +1,66 @@
+//! `tokf show <filter>`: print the TOML source of an active filter, or with
+//! `--resolved` the effective config after `merge = true` filters are applied
+//! (as JSON with `--json`).
+
+use std::fmt::Write as _;
+
+use tokf::config::{self, ResolvedFilter};
+
+use crate::cli_error::{self, ErrorKind};
+
+pub fn cmd_show(filter: &str, resolved: bool, json: bool, include_builtin: bool) -> i32 {
+    let search_dirs = config::default_search_dirs();
+    let filters = match config::cache::discover_with_cache(&search_dirs, include_builtin) {
+        Ok(filters) => filters,
+        Err(e) => return crate::cli::discovery_failed(&e),
+    };
+
+    let Some(found) = config::find_by_name(&filters, filter) else {
+        return cli_error::report(
+            ErrorKind::FilterNotFound,
+            &format_args!("filter not found: {filter}"),
+        );
+    };
+
+    if json {
+        return crate::inventory::print(&crate::inventory::ResolvedEntry::new(found));
+    }
+
+    let content = if resolved {
+        match render_resolved(found) {
+            Ok(c) => c,
+            Err(e) => {
+                return cli_error::report(ErrorKind::Other, &format_args!("rendering filter: {e}"));
+            }
+        }
+    } else {
+        match source(found) {
+            Ok(c) => c,
+            Err(e) => return cli_error::report(ErrorKind::Other, &e),
+        }
+    };
+
+    print!("{content}");
+    0
+}
+
+/// The TOML file `found` was loaded from, embedded or on disk.
+pub fn source(found: &ResolvedFilter) -> Result<String, String> {
+    if found.priority == u8::MAX {
+        config::get_embedded_filter(&found.relative_path)
+            .map(str::to_string)
+            .ok_or_else(|| "embedded filter not readable".to_string())
+    } else {
+        std::fs::read_to_string(&found.source_path).map_err(|e| format!("reading filter: {e}"))
+    }
+}
+
+/// The effective config as TOML, headed by the files it was built from.
+fn render_resolved(filter: &ResolvedFilter) -> Result<String, toml::ser::Error> {
+    let mut out = format!("# base: {}\n", filter.source_path.display());
+    for overlay in &filter.overlays {
+        let _ = writeln!(out, "# merged: {}", overlay.display());
+    }
+    out.push_str(&toml::to_string(&filter.config)?);
+    Ok(out)
+}
@@ -0,0 +1,149 @@
+//! `tokf statusline`: one short line for Claude Code's `statusLine` command
+//! or a shell prompt.
+//!
+//! It runs on every prompt redraw, so it never fails and never waits long:
+//! the last line is reused for `--ttl` seconds, the tracking DB is opened
+//! read-only with a short lock budget, and anything unavailable is left out.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Args;
+
+use tokf::config;
+use tokf::format;
+
+/// How long to wait on a locked tracking DB before giving up.
+#[cfg(feature = "tracking")]
+const DB_BUDGET: Duration = Duration::from_millis(50);
+
+#[derive(Args)]
+pub struct StatuslineArgs {
+    /// Reuse the previous line for SECS seconds (0 recomputes every time)
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    ttl: u64,
+}
+
+pub fn cmd_statusline(args: &StatuslineArgs, no_cache: bool, include_builtin: bool) -> i32 {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let cache = cache_path();
+    if !no_cache
+        && let Some(line) = cache
+            .as_deref()
+            .and_then(|p| read_cached(p, &cwd, Duration::from_secs(args.ttl)))
+    {
+        println!("{line}");
+        return 0;
+    }
+
+    let line = render(saved_today(), filter_count(include_builtin));
+    if let Some(path) = &cache {
+        write_cached(path, &cwd, &line);
+    }
+    println!("{line}");
+    0
+}
+
+fn cache_path() -> Option<PathBuf> {
+    tokf::paths::cache_dir().map(|d| d.join("statusline.txt"))
+}
+
+/// The cached line, if it was written for `cwd` less than `ttl` ago.
+fn read_cached(path: &Path, cwd: &Path, ttl: Duration) -> Option<String> {
+    let age = std::fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .elapsed()
+        .ok()?;
+    if age >= ttl {
+        return None;
+    }
+    let content = std::fs::read_to_string(path).ok()?;
+    let (dir, line) = content.split_once('\n')?;
+    (Path::new(dir) == cwd).then(|| line.trim_end().to_string())
+}
+
+/// Best effort: a statusline that cannot cache still prints.
+fn write_cached(path: &Path, cwd: &Path, line: &str) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(path, format!("{}\n{line}\n", cwd.display()));
+}
+
+/// Tokens saved today (local date); `None` when there is nothing to read.
+#[cfg(feature = "tracking")]
+fn saved_today() -> Option<i64> {
+    use tokf::tracking::{self, GainQuery};
+
+    let path = tracking::db_path()?;
+    if !path.exists() {
+        return Some(0);
+    }
+    let conn = tracking::open_db_read_only(&path, DB_BUDGET).ok()?;
+    let today = tracking::today(&conn, false).ok()?;
+    let query = GainQuery {
+        since: Some(today.clone()),
+        until: Some(today),
+        ..GainQuery::default()
+    };
+    tracking::query_summary(&conn, &query)
+        .ok()
+        .map(|s| s.tokens_saved)
+}
+
+#[cfg(not(feature = "tracking"))]
+const fn saved_today() -> Option<i64> {
+    None
+}
+
+fn filter_count(include_builtin: bool) -> Option<usize> {
+    config::cache::discover_with_cache(&config::default_search_dirs(), include_builtin)
+        .ok()
+        .map(|filters| filters.len())
+}
+
+fn render(saved_today: Option<i64>, filters: Option<usize>) -> String {
+    let mut parts = Vec::new();
+    if let Some(saved) = saved_today {
+        parts.push(format!("{} tokens saved today", format::compact(saved)));
+    }
+    if let Some(n) = filters {
+        parts.push(format!("{n} filters"));
+    }
+    if parts.is_empty() {
+        return "tokf".to_string();
+    }
+    format!("tokf: {}", parts.join(" \u{b7} "))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_leaves_out_missing_parts() {
+        assert_eq!(
+            render(Some(12_345), Some(42)),
+            "tokf: 12.3k tokens saved today \u{b7} 42 filters"
+        );
+        assert_eq!(render(None, Some(3)), "tokf: 3 filters");
+        assert_eq!(render(None, None), "tokf");
+    }
+
+    #[test]
+    fn cache_is_per_directory_and_expires() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("statusline.txt");
+        write_cached(&path, Path::new("/proj"), "tokf: 3 filters");
+        let ttl = Duration::from_mins(1);
+        assert_eq!(
+            read_cached(&path, Path::new("/proj"), ttl).as_deref(),
+            Some("tokf: 3 filters")
+        );
+        assert_eq!(read_cached(&path, Path::new("/other"), ttl), None);
+        assert_eq!(read_cached(&path, Path::new("/proj"), Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,51 @@
+//! Printing command output when the reader may already be gone.
+
+use std::io::{self, Write};
+
+/// Print `text` and a newline, flushed. A closed pipe (`tokf run … | head -1`)
+/// is not an error: the reader got what it wanted, and the run must still be
+/// recorded and exit with the command's own code.
+pub fn print_line(text: &str) {
+    if let Err(e) = write_line(&mut io::stdout().lock(), text)
+        && e.kind() != io::ErrorKind::BrokenPipe
+    {
+        eprintln!("[tokf] failed to write output: {e}");
+    }
+}
+
+fn write_line(out: &mut impl Write, text: &str) -> io::Result<()> {
+    out.write_all(text.as_bytes())?;
+    out.write_all(b"\n")?;
+    out.flush()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_line_appends_newline() {
+        let mut out = Vec::new();
+        write_line(&mut out, "ok").unwrap();
+        assert_eq!(out, b"ok\n");
+    }
+
+    #[test]
+    fn closed_pipe_surfaces_as_broken_pipe() {
+        let err = write_line(&mut ClosedPipe, "ok").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
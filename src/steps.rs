@@ -0,0 +1,126 @@
+//! `[[step]]` pipelines: sub-commands a filter runs before its own command,
+//! whose output the filter's templates can use.
+//!
+//! Steps run in order through the shell, with the filter's `[env]`. Each
+//! step's output goes through the filter that handles its command (as with
+//! `tokf run`), and is bound to its `as` name: later steps get it as a
+//! shell-escaped `{name}` in their `run`, the templates as `{name}`. Steps of
+//! the filters applied to step output are not run.
+
+use std::time::Duration;
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::{self, shell::shell_command, shell::shell_escape};
+
+/// Run `cfg`'s steps and return their filtered output by `as` name. A step
+/// that cannot be run binds an empty string and logs a warning.
+pub fn run_steps(
+    cfg: &FilterConfig,
+    args: &[String],
+    timeout: Option<Duration>,
+    include_builtin: bool,
+) -> Vec<(String, String)> {
+    let mut bound: Vec<(String, String)> = Vec::new();
+    for step in &cfg.step {
+        let script = expand(&step.run, args, &bound);
+        let mut cmd = shell_command(&script);
+        runner::apply_filter_env(&mut cmd, &cfg.env);
+        let output = match runner::execute_command(cmd, timeout) {
+            Ok(result) => filtered(&script, &result, include_builtin),
+            Err(e) => {
+                tracing::warn!("step \"{}\" failed to run: {e:#}", step.run);
+                String::new()
+            }
+        };
+        tracing::debug!(step = %script, bytes = output.len(), "ran step");
+        if let Some(name) = &step.as_name {
+            bound.retain(|(n, _)| n != name);
+            bound.push((name.clone(), output));
+        }
+    }
+    bound
+}
+
+/// `result` through the filter for `script`'s command, or as is when no
+/// filter handles it.
+fn filtered(script: &str, result: &runner::CommandResult, include_builtin: bool) -> String {
+    let words: Vec<String> = script.split_whitespace().map(str::to_string).collect();
+    match crate::cli::run::find_filter(&words, false, false, include_builtin) {
+        Ok((Some(cfg), consumed)) => {
+            let rest = words.get(consumed..).unwrap_or_default();
+            filter::apply(&cfg, result, rest).output
+        }
+        _ => result.combined.clone(),
+    }
+}
+
+/// Replace `{args}` in `run` with the escaped arguments, and `{name}` with
+/// the escaped output bound to `name`. Replacements are not scanned again, so
+/// output containing `{…}` stays put.
+fn expand(run: &str, args: &[String], bound: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(run.len());
+    let mut rest = run;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let value = tail.find('}').and_then(|end| {
+            let name = &tail[1..end];
+            if name == "args" {
+                let joined = args.iter().map(|a| shell_escape(a)).collect::<Vec<_>>();
+                return Some((end, joined.join(" ")));
+            }
+            bound
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| (end, shell_escape(v)))
+        });
+        if let Some((end, escaped)) = value {
+            out.push_str(&escaped);
+            rest = &tail[end + 1..];
+        } else {
+            out.push('{');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::literal_string_with_formatting_args
+)]
+mod tests {
+    use super::*;
+
+    fn bound(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| ((*n).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expand_escapes_bound_names_only() {
+        let vars = bound(&[("branch", "main; rm -rf /"), ("count", "3")]);
+        let args = vec!["a b".to_string()];
+        assert_eq!(
+            expand("echo {branch} {count} {args} {unknown}", &args, &vars),
+            format!(
+                "echo {} {} {} {{unknown}}",
+                shell_escape("main; rm -rf /"),
+                shell_escape("3"),
+                shell_escape("a b")
+            )
+        );
+    }
+
+    #[test]
+    fn expand_does_not_rescan_replacements() {
+        let vars = bound(&[("a", "{args} {b}"), ("b", "x")]);
+        assert_eq!(expand("{a}", &[], &vars), shell_escape("{args} {b}"));
+    }
+}
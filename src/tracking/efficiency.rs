@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+use super::GainQuery;
+use crate::format::round_pct;
+
+/// Fewer runs than this are too few to judge a filter by.
+const MIN_RUNS: usize = 5;
+/// Average reduction (percent) below which a filter is flagged as weak.
+const LOW_REDUCTION_PCT: f64 = 30.0;
+/// Share of runs (percent) hitting the fallback before it is flagged.
+const HIGH_FALLBACK_PCT: f64 = 25.0;
+/// Median filtered output (bytes) above which a cap is suggested.
+const LARGE_OUTPUT_BYTES: i64 = 4096;
+
+/// Add the `used_fallback` column to databases created before it existed.
+pub(super) fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = 'used_fallback'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .context("inspect events table")?
+        > 0;
+    if !has_column {
+        conn.execute_batch("ALTER TABLE events ADD COLUMN used_fallback INTEGER;")
+            .context("add used_fallback column")?;
+    }
+    Ok(())
+}
+
+/// How well one filter compresses its command's output, per run.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FilterEfficiency {
+    pub filter_name: String,
+    pub runs: i64,
+    /// Mean of each run's byte reduction, in percent (negative when output grew).
+    pub avg_reduction_pct: f64,
+    pub median_output_bytes: i64,
+    /// Share of runs where no branch matched and the fallback was used, in percent.
+    pub fallback_pct: f64,
+    /// Concrete tuning suggestions; empty when the filter looks fine.
+    pub recommendations: Vec<String>,
+}
+
+/// One recorded run, as far as efficiency is concerned.
+struct Run {
+    input_bytes: i64,
+    output_bytes: i64,
+    exit_code: i32,
+    used_fallback: bool,
+}
+
+/// Per-filter efficiency, best average reduction first. Passthrough runs
+/// are left out: there is no filter to tune.
+///
+/// # Errors
+/// Returns an error if the SQL query fails.
+pub fn query_efficiency(
+    conn: &Connection,
+    query: &GainQuery,
+) -> anyhow::Result<Vec<FilterEfficiency>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT filter_name, input_bytes, output_bytes, exit_code,
+                COALESCE(used_fallback, 0)
+         FROM {}
+         WHERE filter_name IS NOT NULL",
+        query.source(conn)?
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            Run {
+                input_bytes: row.get(1)?,
+                output_bytes: row.get(2)?,
+                exit_code: row.get(3)?,
+                used_fallback: row.get(4)?,
+            },
+        ))
+    })?;
+
+    let mut by_filter: BTreeMap<String, Vec<Run>> = BTreeMap::new();
+    for row in rows {
+        let (name, run) = row.context("read efficiency row")?;
+        by_filter.entry(name).or_default().push(run);
+    }
+
+    let mut result: Vec<FilterEfficiency> = by_filter
+        .into_iter()
+        .map(|(name, runs)| summarize(name, &runs))
+        .collect();
+    result.sort_by(|a, b| b.avg_reduction_pct.total_cmp(&a.avg_reduction_pct));
+    Ok(result)
+}
+
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_wrap)]
+fn summarize(filter_name: String, runs: &[Run]) -> FilterEfficiency {
+    let count = runs.len() as f64;
+    let avg_reduction_pct = runs
+        .iter()
+        .map(|r| {
+            if r.input_bytes == 0 {
+                0.0
+            } else {
+                (r.input_bytes - r.output_bytes) as f64 / r.input_bytes as f64 * 100.0
+            }
+        })
+        .sum::<f64>()
+        / count;
+    let mut outputs: Vec<i64> = runs.iter().map(|r| r.output_bytes).collect();
+    outputs.sort_unstable();
+    let median_output_bytes = outputs[outputs.len() / 2];
+    let fallbacks: Vec<&Run> = runs.iter().filter(|r| r.used_fallback).collect();
+    let fallback_pct = fallbacks.len() as f64 / count * 100.0;
+
+    let mut recommendations = Vec::new();
+    if runs.len() >= MIN_RUNS {
+        if fallback_pct >= HIGH_FALLBACK_PCT {
+            let failing = fallbacks.iter().filter(|r| r.exit_code != 0).count();
+            let branch = if failing * 2 >= fallbacks.len() {
+                "on_failure"
+            } else {
+                "on_success"
+            };
+            recommendations.push(format!(
+                "fallback fired in {fallback_pct:.0}% of runs — add an {branch} branch"
+            ));
+        }
+        if avg_reduction_pct < LOW_REDUCTION_PCT {
+            recommendations.push(format!(
+                "only {avg_reduction_pct:.0}% average reduction — add skip patterns or a head/tail limit"
+            ));
+        }
+        if median_output_bytes > LARGE_OUTPUT_BYTES {
+            recommendations.push(format!(
+                "median output is {median_output_bytes} bytes — cap it with tail, max_blocks or a summary template"
+            ));
+        }
+    }
+
+    FilterEfficiency {
+        filter_name,
+        runs: runs.len() as i64,
+        avg_reduction_pct: round_pct(avg_reduction_pct),
+        median_output_bytes,
+        fallback_pct: round_pct(fallback_pct),
+        recommendations,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::tracking::{build_event, open_db, record_event};
+
+    fn run(input_bytes: i64, output_bytes: i64, exit_code: i32, used_fallback: bool) -> Run {
+        Run {
+            input_bytes,
+            output_bytes,
+            exit_code,
+            used_fallback,
+        }
+    }
+
+    #[test]
+    fn summarize_averages_per_run_reduction() {
+        let e = summarize(
+            "git push".to_string(),
+            &[
+                run(1000, 100, 0, false),
+                run(1000, 700, 0, false),
+                run(0, 0, 0, false),
+            ],
+        );
+        assert_eq!(e.runs, 3);
+        assert_eq!(e.avg_reduction_pct, 40.0);
+        assert_eq!(e.median_output_bytes, 100);
+        assert_eq!(e.fallback_pct, 0.0);
+        assert!(e.recommendations.is_empty(), "too few runs to judge");
+    }
+
+    #[test]
+    fn frequent_failing_fallback_suggests_on_failure() {
+        let mut runs: Vec<Run> = (0..3).map(|_| run(1000, 50, 0, false)).collect();
+        runs.extend((0..2).map(|_| run(1000, 50, 1, true)));
+        let e = summarize("cargo test".to_string(), &runs);
+        assert_eq!(e.fallback_pct, 40.0);
+        assert_eq!(
+            e.recommendations,
+            vec!["fallback fired in 40% of runs — add an on_failure branch"]
+        );
+    }
+
+    #[test]
+    fn weak_and_large_filters_get_suggestions() {
+        let runs: Vec<Run> = (0..5).map(|_| run(10_000, 9_000, 0, false)).collect();
+        let e = summarize("docker build".to_string(), &runs);
+        assert_eq!(e.recommendations.len(), 2);
+        assert!(e.recommendations[0].starts_with("only 10% average reduction"));
+        assert!(e.recommendations[1].starts_with("median output is 9000 bytes"));
+    }
+
+    #[test]
+    fn query_reads_recorded_fallback_runs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let conn = open_db(&dir.path().join("t.db")).unwrap();
+        for (i, fallback) in [false, true].into_iter().enumerate() {
+            let event = build_event(
+                &format!("cargo test {i}"),
+                Some("cargo test"),
+                1000,
+                250,
+                1,
+                1,
+            )
+            .with_fallback(fallback);
+            record_event(&conn, &event).unwrap();
+        }
+        let passthrough = build_event("ls", None, 10, 10, 0, 0);
+        record_event(&conn, &passthrough).unwrap();
+
+        let rows = query_efficiency(&conn, &GainQuery::default()).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].filter_name, "cargo test");
+        assert_eq!(rows[0].avg_reduction_pct, 75.0);
+        assert_eq!(rows[0].fallback_pct, 50.0);
+    }
+}
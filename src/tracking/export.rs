@@ -0,0 +1,226 @@
+use std::io::Write;
+
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+use super::GainQuery;
+
+/// Version of the export record layout. Bump when columns change meaning or
+/// are removed; adding a trailing column is backwards compatible.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// One raw tracking event as written by `tokf gain export`.
+#[derive(Debug, serde::Serialize)]
+pub struct ExportRecord {
+    pub schema_version: u32,
+    pub id: i64,
+    pub timestamp: String,
+    pub command: String,
+    pub filter_name: Option<String>,
+    pub input_bytes: i64,
+    pub output_bytes: i64,
+    pub input_tokens_est: i64,
+    pub output_tokens_est: i64,
+    pub filter_time_ms: i64,
+    pub exit_code: i32,
+    pub event_id: Option<String>,
+    pub tool: Option<String>,
+    pub subcommand: Option<String>,
+    pub arg_count: Option<i64>,
+    pub has_flags: Option<bool>,
+}
+
+/// CSV header, in the same order as the `ExportRecord` fields.
+const CSV_HEADER: &str = "schema_version,id,timestamp,command,filter_name,input_bytes,\
+                          output_bytes,input_tokens_est,output_tokens_est,filter_time_ms,\
+                          exit_code,event_id,tool,subcommand,arg_count,has_flags";
+
+/// Output format for [`export_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+/// Stream the events selected by `query` (date range, dedup window), oldest
+/// first, to `out` in the given format.
+///
+/// # Errors
+/// Returns an error if the query fails or `out` cannot be written.
+pub fn export_events(
+    conn: &Connection,
+    query: &GainQuery,
+    format: ExportFormat,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    if format == ExportFormat::Csv {
+        writeln!(out, "{CSV_HEADER}")?;
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, timestamp, command, filter_name, input_bytes, output_bytes,
+                input_tokens_est, output_tokens_est, filter_time_ms, exit_code, event_id,
+                tool, subcommand, arg_count, has_flags
+         FROM {}
+         ORDER BY id",
+        query.source(conn)?
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ExportRecord {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            command: row.get(2)?,
+            filter_name: row.get(3)?,
+            input_bytes: row.get(4)?,
+            output_bytes: row.get(5)?,
+            input_tokens_est: row.get(6)?,
+            output_tokens_est: row.get(7)?,
+            filter_time_ms: row.get(8)?,
+            exit_code: row.get(9)?,
+            event_id: row.get(10)?,
+            tool: row.get(11)?,
+            subcommand: row.get(12)?,
+            arg_count: row.get(13)?,
+            has_flags: row.get(14)?,
+        })
+    })?;
+
+    for row in rows {
+        let record = row.context("read event row")?;
+        match format {
+            ExportFormat::Csv => writeln!(out, "{}", csv_line(&record))?,
+            ExportFormat::Jsonl => {
+                serde_json::to_writer(&mut *out, &record).context("serialize event")?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn csv_line(r: &ExportRecord) -> String {
+    [
+        r.schema_version.to_string(),
+        r.id.to_string(),
+        csv_field(&r.timestamp),
+        csv_field(&r.command),
+        csv_field(r.filter_name.as_deref().unwrap_or("")),
+        r.input_bytes.to_string(),
+        r.output_bytes.to_string(),
+        r.input_tokens_est.to_string(),
+        r.output_tokens_est.to_string(),
+        r.filter_time_ms.to_string(),
+        r.exit_code.to_string(),
+        csv_field(r.event_id.as_deref().unwrap_or("")),
+        csv_field(r.tool.as_deref().unwrap_or("")),
+        csv_field(r.subcommand.as_deref().unwrap_or("")),
+        r.arg_count.map_or_else(String::new, |n| n.to_string()),
+        r.has_flags.map_or_else(String::new, |b| b.to_string()),
+    ]
+    .join(",")
+}
+
+/// Quote a field per RFC 4180 when it contains a comma, quote, or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::tracking::{build_event, open_db, record_event};
+
+    fn db_with_events() -> (tempfile::TempDir, Connection) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let conn = open_db(&dir.path().join("tracking.db")).unwrap();
+        record_event(
+            &conn,
+            &build_event("git status", Some("git status"), 400, 40, 2, 0),
+        )
+        .unwrap();
+        record_event(
+            &conn,
+            &build_event(r#"echo "a, b""#, None, 8, 8, 0, 1).with_output("a, b"),
+        )
+        .unwrap();
+        (dir, conn)
+    }
+
+    fn export(conn: &Connection, format: ExportFormat) -> String {
+        let mut buf = Vec::new();
+        export_events(conn, &GainQuery::default(), format, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn csv_has_header_and_quoted_fields() {
+        let (_dir, conn) = db_with_events();
+        let csv = export(&conn, ExportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("1,1,"), "row: {}", lines[1]);
+        assert!(lines[1].contains(",git status,git status,400,40,100,10,2,0,"));
+        assert!(lines[2].contains(r#","echo ""a, b""",,8,8,2,2,0,1,"#));
+    }
+
+    #[test]
+    fn jsonl_rows_carry_schema_version() {
+        let (_dir, conn) = db_with_events();
+        let jsonl = export(&conn, ExportFormat::Jsonl);
+        let rows: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["schema_version"], EXPORT_SCHEMA_VERSION);
+        assert_eq!(rows[0]["filter_name"], "git status");
+        assert!(rows[0]["event_id"].is_null());
+        assert_eq!(rows[1]["command"], r#"echo "a, b""#);
+        assert!(rows[1]["event_id"].is_string());
+        assert_eq!(rows[0]["tool"], "git");
+        assert_eq!(rows[0]["subcommand"], "status");
+        assert_eq!(rows[1]["arg_count"], 2);
+    }
+
+    #[test]
+    fn empty_db_exports_header_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let conn = open_db(&dir.path().join("tracking.db")).unwrap();
+        assert_eq!(export(&conn, ExportFormat::Csv), format!("{CSV_HEADER}\n"));
+        assert_eq!(export(&conn, ExportFormat::Jsonl), "");
+    }
+
+    #[test]
+    fn query_limits_exported_rows() {
+        let (_dir, conn) = db_with_events();
+        conn.execute(
+            "UPDATE events SET timestamp = '2025-01-01T00:00:00Z', unix_time = 1735689600
+             WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        let query = GainQuery {
+            since: Some("2025-06-01".to_string()),
+            ..GainQuery::default()
+        };
+        let mut buf = Vec::new();
+        export_events(&conn, &query, ExportFormat::Jsonl, &mut buf).unwrap();
+        let jsonl = String::from_utf8(buf).unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.contains(r#""id":2"#), "jsonl: {jsonl}");
+    }
+
+    #[test]
+    fn csv_field_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -1,8 +1,20 @@
+pub mod efficiency;
+pub mod export;
+pub mod retention;
+pub mod shape;
+mod timezone;
+pub mod trend;
+
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 use rusqlite::Connection;
 
+use crate::hash::fnv1a;
+
+pub use shape::CommandShape;
+pub use timezone::today;
+
 #[derive(Debug)]
 pub struct TrackingEvent {
     pub command: String,
@@ -13,6 +25,56 @@ pub struct TrackingEvent {
     pub output_tokens_est: i64,
     pub filter_time_ms: i64,
     pub exit_code: i32,
+    /// Fingerprint of the emitted output; events without one are never deduped.
+    pub output_hash: Option<String>,
+    /// Normalized tool / subcommand / argument summary of `command`.
+    pub shape: CommandShape,
+    /// The filter's `[trend]` counter for this run: label and value.
+    pub counter: Option<(String, i64)>,
+    /// No branch produced output, so the filter's fallback was used.
+    pub used_fallback: bool,
+}
+
+impl TrackingEvent {
+    /// Attach a fingerprint of the emitted output so retries can be deduped.
+    #[must_use]
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output_hash = Some(format!("{:016x}", fnv1a(output.as_bytes())));
+        self
+    }
+
+    /// Derive the shape from the original argv instead of the joined command,
+    /// so quoted arguments containing spaces count as one word.
+    #[must_use]
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        self.shape = CommandShape::from_words(args.iter().map(String::as_str));
+        self
+    }
+
+    /// Record the filter's `[trend]` counter with this run.
+    #[must_use]
+    pub fn with_counter(mut self, label: &str, value: i64) -> Self {
+        self.counter = Some((label.to_owned(), value));
+        self
+    }
+
+    /// Mark whether the filter fell back instead of rendering a branch.
+    #[must_use]
+    pub const fn with_fallback(mut self, used_fallback: bool) -> Self {
+        self.used_fallback = used_fallback;
+        self
+    }
+}
+
+/// Width of the timestamp bucket used in event ids. Identical runs (same
+/// command and output) recorded within one bucket collapse to a single row.
+pub const EVENT_ID_BUCKET_SECS: u64 = 10;
+
+/// Deterministic event id: hash of command, timestamp bucket and output hash.
+pub fn event_id(command: &str, unix_secs: u64, output_hash: &str) -> String {
+    let bucket = unix_secs / EVENT_ID_BUCKET_SECS;
+    let key = format!("{command}\0{bucket}\0{output_hash}");
+    format!("{:016x}", fnv1a(key.as_bytes()))
 }
 
 #[derive(serde::Serialize)]
@@ -26,6 +88,7 @@ pub struct GainSummary {
 
 #[derive(serde::Serialize)]
 pub struct DailyGain {
+    /// Period label: `YYYY-MM-DD`, `YYYY-Www`, or `YYYY-MM`.
     pub date: String,
     pub commands: i64,
     pub input_tokens: i64,
@@ -37,6 +100,9 @@ pub struct DailyGain {
 #[derive(serde::Serialize)]
 pub struct FilterGain {
     pub filter_name: String,
+    /// `tool subcommand` — only set by [`shape::query_by_subcommand`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
     pub commands: i64,
     pub input_tokens: i64,
     pub output_tokens: i64,
@@ -45,12 +111,12 @@ pub struct FilterGain {
 }
 
 /// Returns the DB path: `TOKF_DB_PATH` env var overrides; else
-/// `dirs::data_local_dir()/tokf/tracking.db`.
+/// `dirs::data_local_dir()/tokf/tracking.db` (`$TOKF_HOME/tracking.db` if set).
 pub fn db_path() -> Option<PathBuf> {
     if let Ok(p) = std::env::var("TOKF_DB_PATH") {
         return Some(PathBuf::from(p));
     }
-    dirs::data_local_dir().map(|d| d.join("tokf").join("tracking.db"))
+    crate::paths::data_dir().map(|d| d.join("tracking.db"))
 }
 
 /// Open or create the DB at `path`, running `CREATE TABLE IF NOT EXISTS`.
@@ -74,13 +140,69 @@ pub fn open_db(path: &Path) -> anyhow::Result<Connection> {
             input_tokens_est  INTEGER NOT NULL,
             output_tokens_est INTEGER NOT NULL,
             filter_time_ms    INTEGER NOT NULL,
-            exit_code         INTEGER NOT NULL
+            exit_code         INTEGER NOT NULL,
+            output_hash       TEXT,
+            event_id          TEXT,
+            tool              TEXT,
+            subcommand        TEXT,
+            arg_count         INTEGER,
+            has_flags         INTEGER,
+            counter_name      TEXT,
+            counter_value     INTEGER,
+            unix_time         INTEGER,
+            used_fallback     INTEGER
         );",
     )
     .context("create events table")?;
+    migrate_event_id(&conn)?;
+    shape::migrate(&conn)?;
+    trend::migrate(&conn)?;
+    timezone::migrate(&conn)?;
+    efficiency::migrate(&conn)?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS events_timestamp ON events(timestamp);")
+        .context("create timestamp index")?;
     Ok(conn)
 }
 
+/// Open an existing DB read-only, giving up on a locked DB after `budget`.
+/// No tables are created or migrated.
+///
+/// # Errors
+/// Returns an error if the DB does not exist or cannot be opened.
+pub fn open_db_read_only(path: &Path, budget: std::time::Duration) -> anyhow::Result<Connection> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("open db at {}", path.display()))?;
+    conn.busy_timeout(budget).context("set busy timeout")?;
+    Ok(conn)
+}
+
+/// Add the `output_hash` / `event_id` columns to databases created before
+/// they existed, and ensure the unique index that makes inserts idempotent.
+fn migrate_event_id(conn: &Connection) -> anyhow::Result<()> {
+    let has_event_id: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = 'event_id'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .context("inspect events table")?
+        > 0;
+    if !has_event_id {
+        conn.execute_batch(
+            "ALTER TABLE events ADD COLUMN output_hash TEXT;
+             ALTER TABLE events ADD COLUMN event_id TEXT;",
+        )
+        .context("add event_id columns")?;
+    }
+    // `events_command_hash` keeps the `--dedup-window` lookup from scanning every row.
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS events_event_id ON events(event_id);
+         CREATE INDEX IF NOT EXISTS events_command_hash ON events(command, output_hash);",
+    )
+    .context("create event_id indexes")?;
+    Ok(())
+}
+
 /// Pure constructor — no I/O. Computes token estimates from bytes.
 #[allow(clippy::too_many_arguments)]
 pub fn build_event(
@@ -108,23 +230,52 @@ pub fn build_event(
         output_tokens_est,
         filter_time_ms: filter_time_ms_i64,
         exit_code,
+        output_hash: None,
+        shape: CommandShape::from_words(command.split_whitespace()),
+        counter: None,
+        used_fallback: false,
     }
 }
 
-/// Insert one row; timestamp set by `SQLite` `strftime` in the SQL.
+/// Insert one row; `timestamp` and `unix_time` set by `SQLite` `strftime` in the SQL.
 ///
 /// # Errors
 /// Returns an error if the INSERT fails.
 pub fn record_event(conn: &Connection, event: &TrackingEvent) -> anyhow::Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    record_event_at(conn, event, now)
+}
+
+/// Like [`record_event`], but with the clock used for the event id supplied.
+///
+/// Events carrying an output hash get a deterministic id; a second insert with
+/// the same id (a retry within the same bucket) is silently ignored.
+///
+/// # Errors
+/// Returns an error if the INSERT fails.
+pub fn record_event_at(
+    conn: &Connection,
+    event: &TrackingEvent,
+    unix_secs: u64,
+) -> anyhow::Result<()> {
+    let id = event
+        .output_hash
+        .as_deref()
+        .map(|h| event_id(&event.command, unix_secs, h));
     conn.execute(
-        "INSERT INTO events
+        "INSERT OR IGNORE INTO events
             (timestamp, command, filter_name,
              input_bytes, output_bytes,
              input_tokens_est, output_tokens_est,
-             filter_time_ms, exit_code)
+             filter_time_ms, exit_code, output_hash, event_id,
+             tool, subcommand, arg_count, has_flags,
+             counter_name, counter_value, unix_time, used_fallback)
          VALUES
             (strftime('%Y-%m-%dT%H:%M:%SZ','now'),
-             ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+             ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+             CAST(strftime('%s','now') AS INTEGER), ?17)",
         rusqlite::params![
             event.command,
             event.filter_name,
@@ -134,21 +285,104 @@ pub fn record_event(conn: &Connection, event: &TrackingEvent) -> anyhow::Result<
             event.output_tokens_est,
             event.filter_time_ms,
             event.exit_code,
+            event.output_hash,
+            id,
+            event.shape.tool,
+            event.shape.subcommand,
+            event.shape.arg_count,
+            event.shape.has_flags,
+            event.counter.as_ref().map(|(name, _)| name),
+            event.counter.as_ref().map(|(_, value)| value),
+            event.used_fallback,
         ],
     )
     .context("insert event")?;
     Ok(())
 }
 
+/// Filters shared by the gain queries.
+#[derive(Debug, Clone, Default)]
+pub struct GainQuery {
+    /// Drop retries: repeats of the same command and output within N seconds.
+    pub dedup_window: Option<u64>,
+    /// Inclusive lower bound on the event date, `YYYY-MM-DD`.
+    pub since: Option<String>,
+    /// Inclusive upper bound on the event date, `YYYY-MM-DD`.
+    pub until: Option<String>,
+    /// Use UTC calendar dates instead of the local time zone (`TZ`) for
+    /// `since` / `until` and period buckets.
+    pub utc: bool,
+}
+
+impl GainQuery {
+    /// Row source for gain queries.
+    ///
+    /// `since` / `until` become epoch bounds on `unix_time` (the start of
+    /// `since`, and the start of the day after `until`), so the
+    /// `events_unix_time` index serves the range.
+    ///
+    /// With a dedup window, an event is dropped when an earlier event with the same
+    /// command and output hash was recorded at most `secs` seconds before it — the
+    /// signature of a retry. Events without an output hash are always kept.
+    fn source(&self, conn: &Connection) -> anyhow::Result<String> {
+        let day_start = |date: &Option<String>, days| {
+            date.as_deref()
+                .map(|d| timezone::day_start(conn, d, days, self.utc))
+                .transpose()
+        };
+        let since = day_start(&self.since, 0)?.unwrap_or(i64::MIN);
+        let until = day_start(&self.until, 1)?.unwrap_or(i64::MAX);
+        let dedup = self.dedup_window.map_or_else(String::new, |secs| {
+            format!(
+                "AND (e.output_hash IS NULL OR NOT EXISTS (
+                    SELECT 1 FROM events p
+                    WHERE p.command = e.command
+                      AND p.output_hash = e.output_hash
+                      AND p.id < e.id
+                      AND (julianday(e.timestamp) - julianday(p.timestamp)) * 86400 <= {secs}))"
+            )
+        });
+        Ok(format!(
+            "(SELECT * FROM events e
+              WHERE e.unix_time >= {since} AND e.unix_time < {until}
+                {dedup})"
+        ))
+    }
+}
+
+/// Time bucket for [`query_by_period`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    /// `YYYY-MM-DD`
+    Day,
+    /// `YYYY-MM-DD` of the week's Monday (weeks never split across years)
+    Week,
+    /// `YYYY-MM`
+    Month,
+}
+
+impl Period {
+    fn key_sql(self, utc: bool) -> String {
+        match self {
+            Self::Day => timezone::date_sql("unix_time", utc, ""),
+            Self::Week => timezone::date_sql("unix_time", utc, "'weekday 0', '-6 days'"),
+            Self::Month => format!("substr({}, 1, 7)", timezone::date_sql("unix_time", utc, "")),
+        }
+    }
+}
+
 /// # Errors
 /// Returns an error if the SQL query fails.
-pub fn query_summary(conn: &Connection) -> anyhow::Result<GainSummary> {
+pub fn query_summary(conn: &Connection, query: &GainQuery) -> anyhow::Result<GainSummary> {
     let row = conn
         .query_row(
-            "SELECT COUNT(*), COALESCE(SUM(input_tokens_est),0),
-                    COALESCE(SUM(output_tokens_est),0),
-                    COALESCE(SUM(input_tokens_est - output_tokens_est),0)
-             FROM events",
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(input_tokens_est),0),
+                        COALESCE(SUM(output_tokens_est),0),
+                        COALESCE(SUM(input_tokens_est - output_tokens_est),0)
+                 FROM {}",
+                query.source(conn)?
+            ),
             [],
             |row| {
                 Ok((
@@ -162,13 +396,7 @@ pub fn query_summary(conn: &Connection) -> anyhow::Result<GainSummary> {
         .context("query summary")?;
 
     let (total_commands, total_input_tokens, total_output_tokens, tokens_saved) = row;
-    let savings_pct = if total_input_tokens == 0 {
-        0.0
-    } else {
-        #[allow(clippy::cast_precision_loss)]
-        let pct = tokens_saved as f64 / total_input_tokens as f64 * 100.0;
-        pct
-    };
+    let savings_pct = crate::format::percent(tokens_saved, total_input_tokens);
 
     Ok(GainSummary {
         total_commands,
@@ -181,15 +409,16 @@ pub fn query_summary(conn: &Connection) -> anyhow::Result<GainSummary> {
 
 /// # Errors
 /// Returns an error if the SQL query fails.
-pub fn query_by_filter(conn: &Connection) -> anyhow::Result<Vec<FilterGain>> {
-    let mut stmt = conn.prepare(
+pub fn query_by_filter(conn: &Connection, query: &GainQuery) -> anyhow::Result<Vec<FilterGain>> {
+    let mut stmt = conn.prepare(&format!(
         "SELECT COALESCE(filter_name, 'passthrough'), COUNT(*),
                 SUM(input_tokens_est), SUM(output_tokens_est),
                 SUM(input_tokens_est - output_tokens_est)
-         FROM events
+         FROM {}
          GROUP BY filter_name
          ORDER BY SUM(input_tokens_est - output_tokens_est) DESC",
-    )?;
+        query.source(conn)?
+    ))?;
 
     let rows = stmt.query_map([], |row| {
         let input_tokens: i64 = row.get(2)?;
@@ -207,14 +436,10 @@ pub fn query_by_filter(conn: &Connection) -> anyhow::Result<Vec<FilterGain>> {
     for row in rows {
         let (filter_name, commands, input_tokens, output_tokens, tokens_saved) =
             row.context("read filter row")?;
-        #[allow(clippy::cast_precision_loss)]
-        let savings_pct = if input_tokens == 0 {
-            0.0
-        } else {
-            tokens_saved as f64 / input_tokens as f64 * 100.0
-        };
+        let savings_pct = crate::format::percent(tokens_saved, input_tokens);
         result.push(FilterGain {
             filter_name,
+            command: None,
             commands,
             input_tokens,
             output_tokens,
@@ -227,15 +452,29 @@ pub fn query_by_filter(conn: &Connection) -> anyhow::Result<Vec<FilterGain>> {
 
 /// # Errors
 /// Returns an error if the SQL query fails.
-pub fn query_daily(conn: &Connection) -> anyhow::Result<Vec<DailyGain>> {
-    let mut stmt = conn.prepare(
-        "SELECT substr(timestamp, 1, 10), COUNT(*),
+pub fn query_daily(conn: &Connection, query: &GainQuery) -> anyhow::Result<Vec<DailyGain>> {
+    query_by_period(conn, Period::Day, query)
+}
+
+/// Per-period rollup, newest first. `DailyGain::date` holds the period label.
+///
+/// # Errors
+/// Returns an error if the SQL query fails.
+pub fn query_by_period(
+    conn: &Connection,
+    period: Period,
+    query: &GainQuery,
+) -> anyhow::Result<Vec<DailyGain>> {
+    let key = period.key_sql(query.utc);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {key}, COUNT(*),
                 SUM(input_tokens_est), SUM(output_tokens_est),
                 SUM(input_tokens_est - output_tokens_est)
-         FROM events
-         GROUP BY substr(timestamp, 1, 10)
-         ORDER BY substr(timestamp, 1, 10) DESC",
-    )?;
+         FROM {}
+         GROUP BY {key}
+         ORDER BY {key} DESC",
+        query.source(conn)?
+    ))?;
 
     let rows = stmt.query_map([], |row| {
         let input_tokens: i64 = row.get(2)?;
@@ -252,13 +491,8 @@ pub fn query_daily(conn: &Connection) -> anyhow::Result<Vec<DailyGain>> {
     let mut result = Vec::new();
     for row in rows {
         let (date, commands, input_tokens, output_tokens, tokens_saved) =
-            row.context("read daily row")?;
-        #[allow(clippy::cast_precision_loss)]
-        let savings_pct = if input_tokens == 0 {
-            0.0
-        } else {
-            tokens_saved as f64 / input_tokens as f64 * 100.0
-        };
+            row.context("read period row")?;
+        let savings_pct = crate::format::percent(tokens_saved, input_tokens);
         result.push(DailyGain {
             date,
             commands,
@@ -272,4 +506,5 @@ pub fn query_daily(conn: &Connection) -> anyhow::Result<Vec<DailyGain>> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::float_cmp)]
 mod tests;
@@ -0,0 +1,187 @@
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+/// Default number of days of events kept by `tokf gain prune` and auto-prune.
+pub const DEFAULT_KEEP_DAYS: u32 = 90;
+
+/// Default DB size above which a write triggers an automatic prune (50 MiB).
+pub const DEFAULT_MAX_DB_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Retention settings for the tracking DB.
+///
+/// Overridable via `TOKF_DB_KEEP_DAYS` and `TOKF_DB_MAX_BYTES`
+/// (`TOKF_DB_MAX_BYTES=0` disables auto-prune).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub keep_days: u32,
+    pub max_db_bytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_days: DEFAULT_KEEP_DAYS,
+            max_db_bytes: DEFAULT_MAX_DB_BYTES,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Defaults, overridden by the environment. Unparseable values are ignored.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            keep_days: env_number("TOKF_DB_KEEP_DAYS").unwrap_or(defaults.keep_days),
+            max_db_bytes: env_number("TOKF_DB_MAX_BYTES").unwrap_or(defaults.max_db_bytes),
+        }
+    }
+}
+
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.trim().parse().ok()
+}
+
+/// Delete events older than `keep_days` days. Returns the number of rows removed.
+///
+/// # Errors
+/// Returns an error if the DELETE fails.
+pub fn prune(conn: &Connection, keep_days: u32) -> anyhow::Result<usize> {
+    conn.execute(
+        "DELETE FROM events
+         WHERE timestamp < strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)",
+        [format!("-{keep_days} days")],
+    )
+    .context("prune events")
+}
+
+/// Rebuild the DB file so space freed by [`prune`] is returned to the OS.
+///
+/// # Errors
+/// Returns an error if `VACUUM` fails (e.g. inside an open transaction).
+pub fn vacuum(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute_batch("VACUUM;").context("vacuum db")
+}
+
+/// Current DB size in bytes (`page_count * page_size`).
+///
+/// # Errors
+/// Returns an error if the pragmas cannot be read.
+pub fn db_size_bytes(conn: &Connection) -> anyhow::Result<u64> {
+    let size: i64 = conn
+        .query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )
+        .context("read db size")?;
+    Ok(u64::try_from(size).unwrap_or(0))
+}
+
+/// Prune and vacuum when the DB has grown past `policy.max_db_bytes`.
+///
+/// Cheap when under the threshold (two pragmas). Vacuums only if rows were
+/// actually removed, so a DB full of recent events is not rebuilt on every write.
+/// Returns the number of rows removed.
+///
+/// # Errors
+/// Returns an error if any of the underlying statements fail.
+pub fn auto_prune(conn: &Connection, policy: &RetentionPolicy) -> anyhow::Result<usize> {
+    if policy.max_db_bytes == 0 || db_size_bytes(conn)? <= policy.max_db_bytes {
+        return Ok(0);
+    }
+    let removed = prune(conn, policy.keep_days)?;
+    if removed > 0 {
+        vacuum(conn)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::tracking::{build_event, open_db, record_event};
+
+    fn db() -> (tempfile::TempDir, Connection) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let conn = open_db(&dir.path().join("tracking.db")).unwrap();
+        (dir, conn)
+    }
+
+    /// Insert one event `days_ago` days in the past.
+    fn insert_days_ago(conn: &Connection, days_ago: u32) {
+        record_event(conn, &build_event("cmd", None, 400, 100, 0, 0)).unwrap();
+        conn.execute(
+            "UPDATE events SET timestamp = strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)
+             WHERE id = last_insert_rowid()",
+            [format!("-{days_ago} days")],
+        )
+        .unwrap();
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM events", [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn prune_removes_only_old_events() {
+        let (_dir, conn) = db();
+        for days in [0, 10, 89, 91, 400] {
+            insert_days_ago(&conn, days);
+        }
+        assert_eq!(prune(&conn, 90).unwrap(), 2);
+        assert_eq!(count(&conn), 3);
+    }
+
+    #[test]
+    fn prune_zero_days_clears_past_events() {
+        let (_dir, conn) = db();
+        insert_days_ago(&conn, 1);
+        insert_days_ago(&conn, 2);
+        assert_eq!(prune(&conn, 0).unwrap(), 2);
+        assert_eq!(count(&conn), 0);
+    }
+
+    #[test]
+    fn vacuum_succeeds() {
+        let (_dir, conn) = db();
+        insert_days_ago(&conn, 0);
+        vacuum(&conn).unwrap();
+        assert!(db_size_bytes(&conn).unwrap() > 0);
+    }
+
+    #[test]
+    fn auto_prune_noop_under_threshold() {
+        let (_dir, conn) = db();
+        insert_days_ago(&conn, 400);
+        let policy = RetentionPolicy::default();
+        assert_eq!(auto_prune(&conn, &policy).unwrap(), 0);
+        assert_eq!(count(&conn), 1);
+    }
+
+    #[test]
+    fn auto_prune_runs_over_threshold() {
+        let (_dir, conn) = db();
+        insert_days_ago(&conn, 400);
+        insert_days_ago(&conn, 1);
+        let policy = RetentionPolicy {
+            keep_days: 30,
+            max_db_bytes: 1,
+        };
+        assert_eq!(auto_prune(&conn, &policy).unwrap(), 1);
+        assert_eq!(count(&conn), 1);
+    }
+
+    #[test]
+    fn auto_prune_disabled_with_zero_max() {
+        let (_dir, conn) = db();
+        insert_days_ago(&conn, 400);
+        let policy = RetentionPolicy {
+            keep_days: 30,
+            max_db_bytes: 0,
+        };
+        assert_eq!(auto_prune(&conn, &policy).unwrap(), 0);
+        assert_eq!(count(&conn), 1);
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+use super::{FilterGain, GainQuery};
+
+/// Normalized view of a command line, stored next to the raw command so
+/// analytics can group `cargo test --lib` and `cargo test -p foo` together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandShape {
+    /// Program name without its directory (`/usr/bin/git` → `git`).
+    pub tool: Option<String>,
+    /// First argument when it looks like a subcommand word (`push`, `run:dev`).
+    pub subcommand: Option<String>,
+    /// Number of words after the program.
+    pub arg_count: i64,
+    /// Whether any argument starts with `-`.
+    pub has_flags: bool,
+}
+
+impl CommandShape {
+    /// Derive the shape from the command's words (program first).
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut words = words.into_iter();
+        let Some(program) = words.next() else {
+            return Self::default();
+        };
+        let tool = program.rsplit('/').next().unwrap_or(program).to_string();
+        let args: Vec<&str> = words.collect();
+        let subcommand = args
+            .first()
+            .filter(|w| is_subcommand_word(w))
+            .map(|w| (*w).to_string());
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        let arg_count = args.len() as i64;
+        Self {
+            tool: Some(tool),
+            subcommand,
+            arg_count,
+            has_flags: args.iter().any(|a| a.len() > 1 && a.starts_with('-')),
+        }
+    }
+
+    /// `tool subcommand`, or just `tool` when there is no subcommand.
+    pub fn label(&self) -> Option<String> {
+        let tool = self.tool.as_deref()?;
+        Some(
+            self.subcommand
+                .as_deref()
+                .map_or_else(|| tool.to_string(), |sub| format!("{tool} {sub}")),
+        )
+    }
+}
+
+/// Subcommands are plain words: no paths, file names, flags, or `key=value`.
+fn is_subcommand_word(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+}
+
+/// Add the shape columns to databases created before they existed and
+/// backfill them from the stored command (split on whitespace).
+pub(super) fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    let has_tool: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = 'tool'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .context("inspect events table")?
+        > 0;
+    if has_tool {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "ALTER TABLE events ADD COLUMN tool TEXT;
+         ALTER TABLE events ADD COLUMN subcommand TEXT;
+         ALTER TABLE events ADD COLUMN arg_count INTEGER;
+         ALTER TABLE events ADD COLUMN has_flags INTEGER;",
+    )
+    .context("add command shape columns")?;
+
+    let rows: Vec<(i64, String)> = conn
+        .prepare("SELECT id, command FROM events")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()
+        .context("read commands for backfill")?;
+    let mut update = conn.prepare(
+        "UPDATE events SET tool = ?1, subcommand = ?2, arg_count = ?3, has_flags = ?4
+         WHERE id = ?5",
+    )?;
+    for (id, command) in rows {
+        let shape = CommandShape::from_words(command.split_whitespace());
+        update
+            .execute(rusqlite::params![
+                shape.tool,
+                shape.subcommand,
+                shape.arg_count,
+                shape.has_flags,
+                id
+            ])
+            .context("backfill command shape")?;
+    }
+    Ok(())
+}
+
+/// Like [`super::query_by_filter`], with each filter split by `tool subcommand`.
+///
+/// Unfiltered runs appear as `passthrough`, grouped the same way — the
+/// commands that would benefit most from a new filter.
+///
+/// # Errors
+/// Returns an error if the SQL query fails.
+pub fn query_by_subcommand(
+    conn: &Connection,
+    query: &GainQuery,
+) -> anyhow::Result<Vec<FilterGain>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COALESCE(filter_name, 'passthrough'),
+                CASE WHEN tool IS NULL THEN NULL
+                     WHEN subcommand IS NULL THEN tool
+                     ELSE tool || ' ' || subcommand END,
+                COUNT(*),
+                SUM(input_tokens_est), SUM(output_tokens_est),
+                SUM(input_tokens_est - output_tokens_est)
+         FROM {}
+         GROUP BY filter_name, tool, subcommand
+         ORDER BY SUM(input_tokens_est - output_tokens_est) DESC",
+        query.source(conn)?
+    ))?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        let (filter_name, command, commands, input_tokens, output_tokens, tokens_saved) =
+            row.context("read subcommand row")?;
+        let savings_pct = crate::format::percent(tokens_saved, input_tokens);
+        result.push(FilterGain {
+            filter_name,
+            command,
+            commands,
+            input_tokens,
+            output_tokens,
+            tokens_saved,
+            savings_pct,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn shape(command: &str) -> CommandShape {
+        CommandShape::from_words(command.split_whitespace())
+    }
+
+    #[test]
+    fn tool_and_subcommand() {
+        let s = shape("/usr/bin/git push origin main");
+        assert_eq!(s.tool.as_deref(), Some("git"));
+        assert_eq!(s.subcommand.as_deref(), Some("push"));
+        assert_eq!(s.arg_count, 3);
+        assert!(!s.has_flags);
+        assert_eq!(s.label().as_deref(), Some("git push"));
+    }
+
+    #[test]
+    fn flags_detected_and_not_subcommands() {
+        let s = shape("cargo --verbose test -- --nocapture");
+        assert_eq!(s.subcommand, None);
+        assert!(s.has_flags);
+        assert_eq!(s.label().as_deref(), Some("cargo"));
+    }
+
+    #[test]
+    fn paths_and_assignments_are_not_subcommands() {
+        assert_eq!(shape("ls /tmp").subcommand, None);
+        assert_eq!(shape("python script.py").subcommand, None);
+        assert_eq!(shape("make CC=clang").subcommand, None);
+        assert_eq!(
+            shape("npm run build:prod").subcommand.as_deref(),
+            Some("run")
+        );
+        assert!(!shape("cat -").has_flags);
+    }
+
+    #[test]
+    fn empty_command() {
+        assert_eq!(shape(""), CommandShape::default());
+        assert_eq!(shape("").label(), None);
+    }
+}
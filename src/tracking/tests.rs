@@ -105,7 +105,7 @@ fn record_event_all_fields_persisted() {
     assert_eq!(ec, 0);
 }
 
-/// C1+C2: verify exit_code and filter_time_ms are readable with non-zero values.
+/// C1+C2: verify `exit_code` and `filter_time_ms` are readable with non-zero values.
 #[test]
 fn record_event_exit_code_and_filter_time_persisted() {
     let (_dir, conn) = temp_db();
@@ -158,7 +158,7 @@ fn build_event_passthrough_filter_name_none() {
 #[test]
 fn query_summary_empty_db() {
     let (_dir, conn) = temp_db();
-    let s = query_summary(&conn).expect("summary");
+    let s = query_summary(&conn, &GainQuery::default()).expect("summary");
     assert_eq!(s.total_commands, 0);
     assert_eq!(s.total_input_tokens, 0);
     assert_eq!(s.total_output_tokens, 0);
@@ -172,7 +172,7 @@ fn query_summary_with_events() {
     // input_tokens 100, output_tokens 25 → saved 75
     let ev = build_event("cmd", Some("f"), 400, 100, 5, 0);
     record_event(&conn, &ev).expect("record");
-    let s = query_summary(&conn).expect("summary");
+    let s = query_summary(&conn, &GainQuery::default()).expect("summary");
     assert_eq!(s.total_commands, 1);
     assert_eq!(s.total_input_tokens, 100);
     assert_eq!(s.total_output_tokens, 25);
@@ -185,7 +185,7 @@ fn query_summary_zero_input_no_divide_by_zero() {
     let (_dir, conn) = temp_db();
     let ev = build_event("cmd", None, 0, 0, 0, 0);
     record_event(&conn, &ev).expect("record");
-    let s = query_summary(&conn).expect("summary");
+    let s = query_summary(&conn, &GainQuery::default()).expect("summary");
     assert_eq!(s.savings_pct, 0.0); // must not panic or NaN
 }
 
@@ -205,12 +205,12 @@ fn query_summary_accumulates_multiple_events() {
     for ev in &events {
         record_event(&conn, ev).expect("record");
     }
-    let s = query_summary(&conn).expect("summary");
+    let s = query_summary(&conn, &GainQuery::default()).expect("summary");
     assert_eq!(s.total_commands, 3);
     assert_eq!(s.total_input_tokens, 600); // (400+800+1200)/4
     assert_eq!(s.total_output_tokens, 125); // (100+400+0)/4
     assert_eq!(s.tokens_saved, 475); // 600-125
-    assert!((s.savings_pct - 79.166_666).abs() < 0.01);
+    assert_eq!(s.savings_pct, 79.2);
 }
 
 // --- query_by_filter ---
@@ -222,7 +222,7 @@ fn query_by_filter_groups_correctly() {
         let ev = build_event("cmd", Some(fname), 400, 100, 0, 0);
         record_event(&conn, &ev).expect("record");
     }
-    let rows = query_by_filter(&conn).expect("query");
+    let rows = query_by_filter(&conn, &GainQuery::default()).expect("query");
     assert_eq!(rows.len(), 3);
     assert!(rows.iter().all(|r| r.commands == 1));
 }
@@ -232,7 +232,7 @@ fn query_by_filter_null_shown_as_passthrough() {
     let (_dir, conn) = temp_db();
     let ev = build_event("echo hi", None, 200, 200, 0, 0);
     record_event(&conn, &ev).expect("record");
-    let rows = query_by_filter(&conn).expect("query");
+    let rows = query_by_filter(&conn, &GainQuery::default()).expect("query");
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0].filter_name, "passthrough");
 }
@@ -247,7 +247,7 @@ fn query_by_filter_mixed_null_and_named() {
     )
     .expect("record");
     record_event(&conn, &build_event("echo hi", None, 200, 200, 0, 0)).expect("record");
-    let rows = query_by_filter(&conn).expect("query");
+    let rows = query_by_filter(&conn, &GainQuery::default()).expect("query");
     assert_eq!(rows.len(), 2);
     let names: Vec<&str> = rows.iter().map(|r| r.filter_name.as_str()).collect();
     assert!(names.contains(&"git status"), "rows: {names:?}");
@@ -262,7 +262,7 @@ fn query_by_filter_ordered_by_savings_desc() {
     // "big":   400 in → 100 tokens,  0 out →  0 tokens, saved 100
     record_event(&conn, &build_event("cmd", Some("small"), 100, 80, 0, 0)).expect("record");
     record_event(&conn, &build_event("cmd", Some("big"), 400, 0, 0, 0)).expect("record");
-    let rows = query_by_filter(&conn).expect("query");
+    let rows = query_by_filter(&conn, &GainQuery::default()).expect("query");
     assert_eq!(rows.len(), 2);
     assert_eq!(
         rows[0].filter_name, "big",
@@ -280,7 +280,395 @@ fn query_daily_groups_by_date() {
         let ev = build_event("cmd", None, 400, 100, 0, 0);
         record_event(&conn, &ev).expect("record");
     }
-    let rows = query_daily(&conn).expect("query");
+    let rows = query_daily(&conn, &GainQuery::default()).expect("query");
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0].commands, 2);
 }
+
+// --- event ids / dedup ---
+
+fn count_events(conn: &Connection) -> i64 {
+    conn.query_row("SELECT COUNT(*) FROM events", [], |r| r.get(0))
+        .expect("count")
+}
+
+#[test]
+fn event_id_is_deterministic_within_bucket() {
+    let a = event_id("cargo test", 1_000, "abc");
+    assert_eq!(
+        a,
+        event_id("cargo test", 1_000 + EVENT_ID_BUCKET_SECS - 1, "abc")
+    );
+    assert_ne!(
+        a,
+        event_id("cargo test", 1_000 + EVENT_ID_BUCKET_SECS, "abc")
+    );
+    assert_ne!(a, event_id("cargo build", 1_000, "abc"));
+    assert_ne!(a, event_id("cargo test", 1_000, "abd"));
+}
+
+#[test]
+fn with_output_sets_stable_hash() {
+    let a = build_event("x", None, 0, 0, 0, 0).with_output("ok");
+    let b = build_event("x", None, 0, 0, 0, 0).with_output("ok");
+    let c = build_event("x", None, 0, 0, 0, 0).with_output("fail");
+    assert!(a.output_hash.is_some());
+    assert_eq!(a.output_hash, b.output_hash);
+    assert_ne!(a.output_hash, c.output_hash);
+}
+
+#[test]
+fn record_event_retry_in_same_bucket_ignored() {
+    let (_dir, conn) = temp_db();
+    let ev = build_event("cargo test", None, 100, 10, 0, 0).with_output("ok");
+    record_event_at(&conn, &ev, 1_000).expect("first");
+    record_event_at(&conn, &ev, 1_001).expect("retry must not error");
+    assert_eq!(count_events(&conn), 1);
+}
+
+#[test]
+fn record_event_different_bucket_kept() {
+    let (_dir, conn) = temp_db();
+    let ev = build_event("cargo test", None, 100, 10, 0, 0).with_output("ok");
+    record_event_at(&conn, &ev, 1_000).expect("first");
+    record_event_at(&conn, &ev, 1_000 + EVENT_ID_BUCKET_SECS).expect("second");
+    assert_eq!(count_events(&conn), 2);
+}
+
+#[test]
+fn record_event_without_output_hash_never_deduped() {
+    let (_dir, conn) = temp_db();
+    let ev = build_event("cargo test", None, 100, 10, 0, 0);
+    record_event_at(&conn, &ev, 1_000).expect("first");
+    record_event_at(&conn, &ev, 1_000).expect("second");
+    assert_eq!(count_events(&conn), 2);
+}
+
+#[test]
+fn open_db_migrates_legacy_schema() {
+    let dir = TempDir::new().expect("tempdir");
+    let path = dir.path().join("tracking.db");
+    {
+        let legacy = Connection::open(&path).expect("open");
+        legacy
+            .execute_batch(
+                "CREATE TABLE events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL, command TEXT NOT NULL,
+                    filter_name TEXT, input_bytes INTEGER NOT NULL,
+                    output_bytes INTEGER NOT NULL, input_tokens_est INTEGER NOT NULL,
+                    output_tokens_est INTEGER NOT NULL, filter_time_ms INTEGER NOT NULL,
+                    exit_code INTEGER NOT NULL);
+                 INSERT INTO events VALUES (1, '2025-01-01T00:00:00Z', 'old', NULL, 4, 4, 1, 1, 0, 0);",
+            )
+            .expect("legacy schema");
+    }
+    let conn = open_db(&path).expect("migrate");
+    let backfilled: i64 = conn
+        .query_row("SELECT unix_time FROM events WHERE id = 1", [], |r| {
+            r.get(0)
+        })
+        .expect("unix_time");
+    assert_eq!(backfilled, 1_735_689_600);
+    let ev = build_event("new", None, 8, 4, 0, 0).with_output("x");
+    record_event_at(&conn, &ev, 1_000).expect("record");
+    record_event_at(&conn, &ev, 1_000).expect("retry");
+    assert_eq!(count_events(&conn), 2);
+}
+
+#[test]
+fn query_dedup_window_collapses_retries() {
+    let (_dir, conn) = temp_db();
+    let ev = build_event("cargo test", Some("cargo test"), 400, 40, 0, 0).with_output("ok");
+    // Different buckets, so both rows are stored despite identical output.
+    record_event_at(&conn, &ev, 1_000).expect("first");
+    record_event_at(&conn, &ev, 2_000).expect("second");
+    let other = build_event("git status", None, 40, 40, 0, 0).with_output("clean");
+    record_event_at(&conn, &other, 1_000).expect("other");
+
+    let all = query_summary(&conn, &GainQuery::default()).expect("summary");
+    assert_eq!(all.total_commands, 3);
+    let dedup = GainQuery {
+        dedup_window: Some(60),
+        ..GainQuery::default()
+    };
+    let deduped = query_summary(&conn, &dedup).expect("summary");
+    assert_eq!(deduped.total_commands, 2);
+    assert_eq!(deduped.total_input_tokens, 110);
+
+    let by_filter = query_by_filter(&conn, &dedup).expect("by filter");
+    let cargo = by_filter
+        .iter()
+        .find(|r| r.filter_name == "cargo test")
+        .expect("cargo row");
+    assert_eq!(cargo.commands, 1);
+
+    let daily = query_daily(&conn, &dedup).expect("daily");
+    assert_eq!(daily[0].commands, 2);
+}
+
+#[test]
+fn dedup_lookup_uses_command_hash_index() {
+    let (_dir, conn) = temp_db();
+    let plan: Vec<String> = conn
+        .prepare(
+            "EXPLAIN QUERY PLAN SELECT 1 FROM events p
+             WHERE p.command = 'x' AND p.output_hash = 'y' AND p.id < 10",
+        )
+        .expect("prepare")
+        .query_map([], |row| row.get::<_, String>(3))
+        .expect("plan")
+        .collect::<Result<_, _>>()
+        .expect("rows");
+    assert!(
+        plan.iter().any(|d| d.contains("events_command_hash")),
+        "plan: {plan:?}"
+    );
+}
+
+// --- date ranges / periods ---
+
+/// Insert one event per timestamp, overriding the `now` default.
+fn insert_at(conn: &Connection, timestamps: &[&str]) {
+    for ts in timestamps {
+        record_event(conn, &build_event("cmd", None, 400, 100, 0, 0)).expect("record");
+        conn.execute(
+            "UPDATE events SET timestamp = ?1, unix_time = CAST(strftime('%s', ?1) AS INTEGER)
+             WHERE id = last_insert_rowid()",
+            [ts],
+        )
+        .expect("set timestamp");
+    }
+}
+
+/// Date ranges and buckets in UTC, independent of the machine's `TZ`.
+fn utc() -> GainQuery {
+    GainQuery {
+        utc: true,
+        ..GainQuery::default()
+    }
+}
+
+#[test]
+fn query_since_until_inclusive() {
+    let (_dir, conn) = temp_db();
+    insert_at(
+        &conn,
+        &[
+            "2025-01-31T23:59:59Z",
+            "2025-02-01T00:00:00Z",
+            "2025-02-14T12:00:00Z",
+            "2025-02-28T10:00:00Z",
+            "2025-03-01T00:00:00Z",
+        ],
+    );
+    let feb = GainQuery {
+        since: Some("2025-02-01".to_string()),
+        until: Some("2025-02-28".to_string()),
+        ..utc()
+    };
+    assert_eq!(
+        query_summary(&conn, &feb).expect("summary").total_commands,
+        3
+    );
+
+    let since_only = GainQuery {
+        since: Some("2025-02-15".to_string()),
+        ..utc()
+    };
+    assert_eq!(
+        query_summary(&conn, &since_only)
+            .expect("summary")
+            .total_commands,
+        2
+    );
+    let by_filter = query_by_filter(&conn, &feb).expect("by filter");
+    assert_eq!(by_filter[0].commands, 3);
+}
+
+#[test]
+fn date_range_is_served_by_the_unix_time_index() {
+    let (_dir, conn) = temp_db();
+    let feb = GainQuery {
+        since: Some("2025-02-01".to_string()),
+        until: Some("2025-02-28".to_string()),
+        ..utc()
+    };
+    let sql = format!(
+        "EXPLAIN QUERY PLAN SELECT COUNT(*) FROM {}",
+        feb.source(&conn).expect("source")
+    );
+    let mut stmt = conn.prepare(&sql).expect("prepare");
+    let plan: Vec<String> = stmt
+        .query_map([], |row| row.get(3))
+        .expect("plan")
+        .collect::<Result<_, _>>()
+        .expect("rows");
+    assert!(
+        plan.iter().any(|step| step.contains("events_unix_time")),
+        "{plan:?}"
+    );
+}
+
+#[test]
+fn invalid_date_bound_is_an_error() {
+    let (_dir, conn) = temp_db();
+    let query = GainQuery {
+        since: Some("2025-02-30x".to_string()),
+        ..utc()
+    };
+    assert!(query_summary(&conn, &query).is_err());
+}
+
+#[test]
+fn query_by_period_week_and_month() {
+    let (_dir, conn) = temp_db();
+    // 2025-02-03 and 2025-02-05 share a Monday-based week; 2025-02-10 starts the next.
+    insert_at(
+        &conn,
+        &[
+            "2025-01-30T09:00:00Z",
+            "2025-02-03T09:00:00Z",
+            "2025-02-05T09:00:00Z",
+            "2025-02-10T09:00:00Z",
+        ],
+    );
+    let q = utc();
+
+    let months = query_by_period(&conn, Period::Month, &q).expect("months");
+    let labels: Vec<(&str, i64)> = months
+        .iter()
+        .map(|r| (r.date.as_str(), r.commands))
+        .collect();
+    assert_eq!(labels, vec![("2025-02", 3), ("2025-01", 1)]);
+
+    let weeks = query_by_period(&conn, Period::Week, &q).expect("weeks");
+    let labels: Vec<(&str, i64)> = weeks
+        .iter()
+        .map(|r| (r.date.as_str(), r.commands))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![("2025-02-10", 1), ("2025-02-03", 2), ("2025-01-27", 1)]
+    );
+}
+
+#[test]
+fn query_by_period_week_spans_new_year() {
+    let (_dir, conn) = temp_db();
+    // Monday 2025-12-29 through Sunday 2026-01-04 is one week.
+    insert_at(
+        &conn,
+        &[
+            "2025-12-29T09:00:00Z",
+            "2026-01-01T09:00:00Z",
+            "2026-01-04T23:00:00Z",
+            "2026-01-05T09:00:00Z",
+        ],
+    );
+    let weeks = query_by_period(&conn, Period::Week, &utc()).expect("weeks");
+    let labels: Vec<(&str, i64)> = weeks
+        .iter()
+        .map(|r| (r.date.as_str(), r.commands))
+        .collect();
+    assert_eq!(labels, vec![("2026-01-05", 1), ("2025-12-29", 3)]);
+}
+
+#[test]
+fn query_by_period_respects_range() {
+    let (_dir, conn) = temp_db();
+    insert_at(&conn, &["2025-01-30T09:00:00Z", "2025-02-03T09:00:00Z"]);
+    let q = GainQuery {
+        since: Some("2025-02-01".to_string()),
+        ..utc()
+    };
+    let months = query_by_period(&conn, Period::Month, &q).expect("months");
+    assert_eq!(months.len(), 1);
+    assert_eq!(months[0].date, "2025-02");
+}
+
+#[test]
+fn utc_day_boundary_is_exact() {
+    let (_dir, conn) = temp_db();
+    insert_at(&conn, &["2025-02-28T23:59:59Z", "2025-03-01T00:00:00Z"]);
+    let days = query_by_period(&conn, Period::Day, &utc()).expect("days");
+    let labels: Vec<&str> = days.iter().map(|r| r.date.as_str()).collect();
+    assert_eq!(labels, vec!["2025-03-01", "2025-02-28"]);
+}
+
+// --- command shape ---
+
+#[test]
+fn record_event_stores_command_shape() {
+    let (_dir, conn) = temp_db();
+    let args: Vec<String> = ["git", "commit", "-m", "two words"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let ev = build_event("git commit -m two words", None, 40, 4, 0, 0).with_args(&args);
+    record_event(&conn, &ev).expect("record");
+    let row: (String, String, i64, bool) = conn
+        .query_row(
+            "SELECT tool, subcommand, arg_count, has_flags FROM events",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .expect("row");
+    assert_eq!(row, ("git".to_string(), "commit".to_string(), 3, true));
+}
+
+#[test]
+fn query_by_subcommand_splits_filters_and_passthrough() {
+    let (_dir, conn) = temp_db();
+    for cmd in ["cargo test --lib", "cargo test -p foo", "cargo build"] {
+        let ev = build_event(cmd, Some("cargo"), 400, 40, 0, 0);
+        record_event(&conn, &ev).expect("record");
+    }
+    let ev = build_event("terraform plan -out x", None, 80, 80, 0, 0);
+    record_event(&conn, &ev).expect("record");
+
+    let rows = shape::query_by_subcommand(&conn, &GainQuery::default()).expect("query");
+    let summary: Vec<(&str, Option<&str>, i64)> = rows
+        .iter()
+        .map(|r| (r.filter_name.as_str(), r.command.as_deref(), r.commands))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("cargo", Some("cargo test"), 2),
+            ("cargo", Some("cargo build"), 1),
+            ("passthrough", Some("terraform plan"), 1),
+        ]
+    );
+}
+
+#[test]
+fn open_db_backfills_command_shape() {
+    let dir = TempDir::new().expect("tempdir");
+    let path = dir.path().join("tracking.db");
+    {
+        let legacy = Connection::open(&path).expect("open");
+        legacy
+            .execute_batch(
+                "CREATE TABLE events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL, command TEXT NOT NULL,
+                    filter_name TEXT, input_bytes INTEGER NOT NULL,
+                    output_bytes INTEGER NOT NULL, input_tokens_est INTEGER NOT NULL,
+                    output_tokens_est INTEGER NOT NULL, filter_time_ms INTEGER NOT NULL,
+                    exit_code INTEGER NOT NULL, output_hash TEXT, event_id TEXT);
+                 INSERT INTO events VALUES
+                    (1, '2025-01-01T00:00:00Z', 'npm run build', NULL, 4, 4, 1, 1, 0, 0, NULL, NULL);",
+            )
+            .expect("legacy schema");
+    }
+    let conn = open_db(&path).expect("migrate");
+    let row: (String, String, i64, bool) = conn
+        .query_row(
+            "SELECT tool, subcommand, arg_count, has_flags FROM events",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .expect("row");
+    assert_eq!(row, ("npm".to_string(), "run".to_string(), 2, false));
+}
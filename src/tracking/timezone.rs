@@ -0,0 +1,107 @@
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+/// Add the `unix_time` column to databases created before it existed and
+/// fill it in from the ISO `timestamp` text.
+pub(super) fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    let has_unix_time: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = 'unix_time'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .context("inspect events table")?
+        > 0;
+    if !has_unix_time {
+        conn.execute_batch(
+            "ALTER TABLE events ADD COLUMN unix_time INTEGER;
+             UPDATE events SET unix_time = CAST(strftime('%s', timestamp) AS INTEGER);",
+        )
+        .context("add unix_time column")?;
+    }
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS events_unix_time ON events(unix_time);")
+        .context("create unix_time index")?;
+    Ok(())
+}
+
+/// SQL for the calendar date of the epoch `column`, in the local time zone
+/// (`TZ`) or in UTC. `modifiers` are extra `SQLite` date modifiers such as
+/// `'weekday 0', '-6 days'`.
+pub(super) fn date_sql(column: &str, utc: bool, modifiers: &str) -> String {
+    let zone = if utc { "" } else { ", 'localtime'" };
+    let extra = if modifiers.is_empty() {
+        String::new()
+    } else {
+        format!(", {modifiers}")
+    };
+    format!("date({column}, 'unixepoch'{zone}{extra})")
+}
+
+/// The epoch second at which the day `days` after `date` (`YYYY-MM-DD`)
+/// starts, in the local time zone or in UTC.
+///
+/// # Errors
+/// Returns an error if `date` is not a valid date or the SQL query fails.
+pub(super) fn day_start(
+    conn: &Connection,
+    date: &str,
+    days: i64,
+    utc: bool,
+) -> anyhow::Result<i64> {
+    let zone = if utc { "" } else { ", 'utc'" };
+    conn.query_row(
+        &format!("SELECT CAST(strftime('%s', ?1, ?2{zone}) AS INTEGER)"),
+        rusqlite::params![date, format!("{days:+} days")],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+    .context("convert date")?
+    .with_context(|| format!("invalid date {date:?}"))
+}
+
+/// Today's date, `YYYY-MM-DD`, in the local time zone or in UTC.
+///
+/// # Errors
+/// Returns an error if the SQL query fails.
+pub fn today(conn: &Connection, utc: bool) -> anyhow::Result<String> {
+    conn.query_row(
+        &format!(
+            "SELECT {}",
+            date_sql("CAST(strftime('%s', 'now') AS INTEGER)", utc, "")
+        ),
+        [],
+        |row| row.get(0),
+    )
+    .context("read current date")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_sql_builds_modifier_chain() {
+        assert_eq!(
+            date_sql("unix_time", true, ""),
+            "date(unix_time, 'unixepoch')"
+        );
+        assert_eq!(
+            date_sql("e.unix_time", false, "'start of month'"),
+            "date(e.unix_time, 'unixepoch', 'localtime', 'start of month')"
+        );
+    }
+
+    #[test]
+    fn day_start_counts_whole_days_in_utc() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(
+            day_start(&conn, "2025-02-01", 0, true).unwrap(),
+            1_738_368_000
+        );
+        assert_eq!(
+            day_start(&conn, "2025-02-28", 1, true).unwrap(),
+            1_740_787_200
+        );
+        assert!(day_start(&conn, "not a date", 0, true).is_err());
+    }
+}
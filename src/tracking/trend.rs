@@ -0,0 +1,80 @@
+use anyhow::Context as _;
+use rusqlite::Connection;
+
+/// Number of runs (including the current one) a trend line spans.
+pub const TREND_RUNS: usize = 5;
+
+/// Add the counter columns to databases created before they existed.
+pub(super) fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    let has_counter: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('events') WHERE name = 'counter_name'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .context("inspect events table")?
+        > 0;
+    if !has_counter {
+        conn.execute_batch(
+            "ALTER TABLE events ADD COLUMN counter_name TEXT;
+             ALTER TABLE events ADD COLUMN counter_value INTEGER;",
+        )
+        .context("add counter columns")?;
+    }
+    Ok(())
+}
+
+/// The last `runs` recorded values of `counter` for `filter_name`, oldest first.
+///
+/// # Errors
+/// Returns an error if the SQL query fails.
+pub fn recent_values(
+    conn: &Connection,
+    filter_name: &str,
+    counter: &str,
+    runs: usize,
+) -> anyhow::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT counter_value FROM events
+         WHERE filter_name = ?1 AND counter_name = ?2 AND counter_value IS NOT NULL
+         ORDER BY id DESC LIMIT ?3",
+    )?;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    let limit = runs as i64;
+    let mut values = stmt
+        .query_map(rusqlite::params![filter_name, counter, limit], |row| {
+            row.get::<_, i64>(0)
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("read counter values")?;
+    values.reverse();
+    Ok(values)
+}
+
+/// `failures: 3 → 1 over last 4 runs`. `None` with fewer than two runs.
+pub fn format_trend(label: &str, values: &[i64]) -> Option<String> {
+    let (first, last) = (values.first()?, values.last()?);
+    if values.len() < 2 {
+        return None;
+    }
+    Some(format!(
+        "{label}: {first} \u{2192} {last} over last {} runs",
+        values.len()
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_needs_two_runs() {
+        assert_eq!(format_trend("failures", &[]), None);
+        assert_eq!(format_trend("failures", &[3]), None);
+        assert_eq!(
+            format_trend("failures", &[3, 2, 2, 1]).as_deref(),
+            Some("failures: 3 \u{2192} 1 over last 4 runs")
+        );
+    }
+}
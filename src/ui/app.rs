@@ -0,0 +1,165 @@
+//! State of the `tokf ui` browser, kept apart from the terminal so it can be
+//! driven by tests.
+
+use tokf::config::ResolvedFilter;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+/// What the lower right pane shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Filtered,
+    Sample,
+}
+
+pub struct App {
+    pub filters: Vec<ResolvedFilter>,
+    pub selected: usize,
+    /// Where the sample came from (a path, `stdin` or `pasted`).
+    pub sample_name: String,
+    pub sample: String,
+    /// 0 selects `[on_success]`, 1 `[on_failure]`.
+    pub exit_code: i32,
+    pub pane: Pane,
+    pub scroll: u16,
+    /// TOML of the selected filter.
+    pub source: String,
+    /// The sample through the selected filter.
+    pub output: String,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(filters: Vec<ResolvedFilter>, sample_name: String, sample: String) -> Self {
+        let mut app = Self {
+            filters,
+            selected: 0,
+            sample_name,
+            sample,
+            exit_code: 0,
+            pane: Pane::Filtered,
+            scroll: 0,
+            source: String::new(),
+            output: String::new(),
+            quit: false,
+        };
+        app.refresh();
+        app
+    }
+
+    pub fn current(&self) -> Option<&ResolvedFilter> {
+        self.filters.get(self.selected)
+    }
+
+    /// Select the filter named `name` (`git/push`), if there is one.
+    pub fn select_named(&mut self, name: &str) -> bool {
+        let Some(found) = tokf::config::find_by_name(&self.filters, name) else {
+            return false;
+        };
+        let source_path = found.source_path.clone();
+        self.selected = self
+            .filters
+            .iter()
+            .position(|f| f.source_path == source_path)
+            .unwrap_or(0);
+        self.refresh();
+        true
+    }
+
+    /// Move the selection by `delta`, clamped to the list.
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.filters.len().saturating_sub(1);
+        let next = self.selected.saturating_add_signed(delta).min(last);
+        if next != self.selected {
+            self.selected = next;
+            self.refresh();
+        }
+    }
+
+    /// Switch between the success and failure branch.
+    pub fn toggle_exit_code(&mut self) {
+        self.exit_code = i32::from(self.exit_code == 0);
+        self.refresh();
+    }
+
+    pub const fn toggle_pane(&mut self) {
+        self.pane = match self.pane {
+            Pane::Filtered => Pane::Sample,
+            Pane::Sample => Pane::Filtered,
+        };
+        self.scroll = 0;
+    }
+
+    pub fn set_sample(&mut self, name: &str, text: String) {
+        self.sample_name = name.to_string();
+        self.sample = text;
+        self.refresh();
+    }
+
+    pub const fn scroll_by(&mut self, delta: i16) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    /// Recompute the preview and the filtered output for the selection.
+    fn refresh(&mut self) {
+        self.scroll = 0;
+        let Some(found) = self.filters.get(self.selected) else {
+            self.source.clear();
+            self.output.clear();
+            return;
+        };
+        self.source = crate::show::source(found).unwrap_or_else(|e| format!("# {e}"));
+        let result = CommandResult::from_output(&self.sample, self.exit_code);
+        self.output = filter::apply(&found.config, &result, &[]).output;
+    }
+}
+
+#[cfg(all(test, feature = "stdlib"))]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn app(sample: &str) -> App {
+        let policy = tokf::config::policy::FilterPolicy::default();
+        let filters = tokf::config::discover_filters_with(&[], true, &policy).unwrap();
+        App::new(filters, "test".to_string(), sample.to_string())
+    }
+
+    #[test]
+    fn selection_shows_the_filter_source_and_output() {
+        let mut app = app("");
+        assert!(app.select_named("git/add"));
+        assert!(app.source.contains("command = \"git add\""));
+        assert_eq!(app.output, "ok \u{2713}");
+    }
+
+    #[test]
+    fn exit_code_switches_the_branch() {
+        let mut app = app("fatal: pathspec 'x' did not match");
+        app.select_named("git/add");
+        app.toggle_exit_code();
+        assert_eq!(app.exit_code, 1);
+        assert_eq!(app.output, "\u{2717} fatal: pathspec 'x' did not match");
+        app.toggle_exit_code();
+        assert_eq!(app.exit_code, 0);
+    }
+
+    #[test]
+    fn new_sample_is_filtered_at_once() {
+        let mut app = app("");
+        app.select_named("git/add");
+        app.toggle_exit_code();
+        app.set_sample("pasted", "fatal: bad".to_string());
+        assert_eq!(app.sample_name, "pasted");
+        assert_eq!(app.output, "\u{2717} fatal: bad");
+    }
+
+    #[test]
+    fn moving_stays_in_bounds() {
+        let mut app = app("");
+        app.move_by(-3);
+        assert_eq!(app.selected, 0);
+        app.move_by(isize::MAX);
+        assert_eq!(app.selected, app.filters.len() - 1);
+    }
+}
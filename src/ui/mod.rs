@@ -0,0 +1,130 @@
+//! `tokf ui`: browse the active filters in the terminal and see, as you
+//! move through them, what each one makes of a sample of command output.
+//!
+//! The sample comes from `--sample FILE`, from stdin when it is piped, or
+//! from pasting into the terminal (which replaces it). Tab switches between
+//! the success and failure branch.
+
+mod app;
+mod view;
+
+use std::io::{IsTerminal as _, Read as _};
+use std::path::PathBuf;
+
+use clap::Args;
+use ratatui::crossterm::event::{
+    self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers,
+};
+use ratatui::crossterm::execute;
+
+use tokf::config;
+
+use crate::cli_error::{self, ErrorKind};
+
+use self::app::App;
+
+#[derive(Args)]
+pub struct UiArgs {
+    /// Filter to select first (e.g. "cargo/test")
+    filter: Option<String>,
+    /// Command output to try the filters on
+    #[arg(long, value_name = "FILE")]
+    sample: Option<PathBuf>,
+    /// Start on the failure branch (exit code 1)
+    #[arg(long)]
+    failure: bool,
+}
+
+pub fn cmd_ui(args: &UiArgs, include_builtin: bool) -> i32 {
+    if !std::io::stdout().is_terminal() {
+        return cli_error::report(ErrorKind::Other, &"tokf ui needs a terminal");
+    }
+    let search_dirs = config::default_search_dirs();
+    let filters = match config::cache::discover_with_cache(&search_dirs, include_builtin) {
+        Ok(filters) => filters,
+        Err(e) => return crate::cli::discovery_failed(&e),
+    };
+    let (sample_name, sample) = match read_sample(args) {
+        Ok(sample) => sample,
+        Err(e) => return cli_error::report(ErrorKind::Other, &e),
+    };
+
+    let mut app = App::new(filters, sample_name, sample);
+    if let Some(name) = &args.filter
+        && !app.select_named(name)
+    {
+        return cli_error::report(
+            ErrorKind::FilterNotFound,
+            &format_args!("filter not found: {name}"),
+        );
+    }
+    if args.failure {
+        app.toggle_exit_code();
+    }
+
+    match run(&mut app) {
+        Ok(()) => 0,
+        Err(e) => cli_error::report(ErrorKind::Other, &format_args!("terminal error: {e}")),
+    }
+}
+
+/// The sample named by `--sample`, else piped stdin, else nothing.
+fn read_sample(args: &UiArgs) -> Result<(String, String), String> {
+    if let Some(path) = &args.sample {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        return Ok((
+            path.display().to_string(),
+            String::from_utf8_lossy(&bytes).into_owned(),
+        ));
+    }
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Ok(("none".to_string(), String::new()));
+    }
+    let mut bytes = Vec::new();
+    stdin
+        .lock()
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("failed to read stdin: {e}"))?;
+    Ok((
+        "stdin".to_string(),
+        String::from_utf8_lossy(&bytes).into_owned(),
+    ))
+}
+
+fn run(app: &mut App) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    execute!(std::io::stdout(), EnableBracketedPaste)?;
+    let result = (|| {
+        while !app.quit {
+            terminal.draw(|frame| view::draw(frame, app))?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => handle_key(app, key),
+                Event::Paste(text) => app.set_sample("pasted", text),
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+    let _ = execute!(std::io::stdout(), DisableBracketedPaste);
+    ratatui::restore();
+    result
+}
+
+fn handle_key(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => app.quit = true,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => app.quit = true,
+        KeyCode::Up | KeyCode::Char('k') => app.move_by(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.move_by(1),
+        KeyCode::Home => app.move_by(isize::MIN),
+        KeyCode::End => app.move_by(isize::MAX),
+        KeyCode::Tab => app.toggle_exit_code(),
+        KeyCode::Char('v') => app.toggle_pane(),
+        KeyCode::PageDown => app.scroll_by(10),
+        KeyCode::PageUp => app.scroll_by(-10),
+        _ => {}
+    }
+}
@@ -0,0 +1,142 @@
+//! Drawing the `tokf ui` screen: the filter list on the left, the selected
+//! filter's TOML and the filtered sample on the right, key help at the bottom.
+
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+
+use super::app::{App, Pane};
+
+const HELP: &str = concat!(
+    " \u{2191}\u{2193} select  tab success/failure  v sample/filtered",
+    "  pgup/pgdn scroll  paste new sample  q quit"
+);
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [main, help] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+    let [list, right] =
+        Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+    let [source, output] =
+        Layout::vertical([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(right);
+
+    draw_list(frame, app, list);
+    frame.render_widget(
+        Paragraph::new(app.source.as_str()).block(Block::bordered().title(source_title(app))),
+        source,
+    );
+    draw_output(frame, app, output);
+    frame.render_widget(
+        Line::from(HELP).style(Style::new().add_modifier(Modifier::DIM)),
+        help,
+    );
+}
+
+fn draw_list(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .filters
+        .iter()
+        .map(|f| ListItem::new(crate::inventory::display_name(f)))
+        .collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(format!(" Filters ({}) ", app.filters.len())))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn source_title(app: &App) -> String {
+    app.current().map_or_else(
+        || " no filters ".to_string(),
+        |f| {
+            format!(
+                " {} [{}] {} ",
+                crate::inventory::display_name(f),
+                f.priority_label(),
+                f.source_path.display()
+            )
+        },
+    )
+}
+
+fn draw_output(frame: &mut Frame, app: &App, area: Rect) {
+    let branch = if app.exit_code == 0 {
+        "on_success"
+    } else {
+        "on_failure"
+    };
+    let raw_lines = app.sample.lines().count();
+    let (title, text) = match app.pane {
+        Pane::Filtered => (
+            format!(
+                " Filtered \u{b7} exit {} ({branch}) \u{b7} {raw_lines} \u{2192} {} lines ",
+                app.exit_code,
+                app.output.lines().count()
+            ),
+            app.output.as_str(),
+        ),
+        Pane::Sample => (
+            format!(
+                " Sample \u{b7} {} \u{b7} {raw_lines} lines ",
+                app.sample_name
+            ),
+            app.sample.as_str(),
+        ),
+    };
+    let body = if app.sample.is_empty() {
+        "(no sample: paste command output here, or start with `tokf ui --sample FILE`)"
+    } else {
+        text
+    };
+    frame.render_widget(
+        Paragraph::new(body)
+            .block(Block::bordered().title(title))
+            .scroll((app.scroll, 0)),
+        area,
+    );
+}
+
+#[cfg(all(test, feature = "stdlib"))]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(usize::from(buffer.area.width))
+            .map(|row| {
+                row.iter()
+                    .map(ratatui::buffer::Cell::symbol)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn shows_list_source_and_filtered_output() {
+        let policy = tokf::config::policy::FilterPolicy::default();
+        let filters = tokf::config::discover_filters_with(&[], true, &policy).unwrap();
+        let mut app = App::new(filters, "log.txt".to_string(), "fatal: bad".to_string());
+        app.select_named("git/add");
+        app.toggle_exit_code();
+        let shown = screen(&app);
+        assert!(shown.contains("> git/add"), "{shown}");
+        assert!(shown.contains("command = \"git add\""), "{shown}");
+        assert!(shown.contains("exit 1 (on_failure)"), "{shown}");
+        assert!(shown.contains("\u{2717} fatal: bad"), "{shown}");
+
+        app.toggle_pane();
+        assert!(screen(&app).contains("Sample \u{b7} log.txt \u{b7} 1 lines"));
+    }
+}
@@ -0,0 +1,63 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn audit(dir: &TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .current_dir(dir.path())
+        .arg("audit")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn setup() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let write = |name: &str, content: &str| std::fs::write(dir.path().join(name), content).unwrap();
+    write("old.toml", "command = \"t\"\nskip = [\"^noise\"]\n");
+    write(
+        "new.toml",
+        "command = \"t\"\nskip = [\"^noise\", \"^debug\"]\n",
+    );
+    write("a.txt", "noise 1\ndebug: cache hit\nresult ok\n");
+    write("b.txt", "result ok\n");
+    dir
+}
+
+#[test]
+fn audit_reports_hidden_lines_and_deltas() {
+    let dir = setup();
+    let out = audit(&dir, &["old.toml", "new.toml", "*.txt"]);
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("a.txt: 26 → 9 bytes (-17)"),
+        "stdout: {stdout}"
+    );
+    assert!(stdout.contains("  - debug: cache hit"), "stdout: {stdout}");
+    assert!(
+        !stdout.contains("b.txt:"),
+        "unchanged fixtures are not listed: {stdout}"
+    );
+    assert!(
+        stdout.contains("1 of 2 fixtures changed; output 35 → 18 bytes (-17)"),
+        "stdout: {stdout}"
+    );
+}
+
+#[test]
+fn audit_reversed_reports_revealed_lines() {
+    let dir = setup();
+    let out = audit(&dir, &["new.toml", "old.toml", "a.txt"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("  + debug: cache hit"), "stdout: {stdout}");
+}
+
+#[test]
+fn audit_missing_filter_exits_not_found() {
+    let dir = setup();
+    let out = audit(&dir, &["gone.toml", "new.toml", "a.txt"]);
+    assert_eq!(out.status.code(), Some(66));
+}
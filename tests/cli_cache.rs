@@ -1,3 +1,5 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
@@ -166,3 +168,51 @@ fn second_run_hits_cache() {
         "cache file should not be rewritten on cache hit"
     );
 }
+
+#[test]
+fn cache_warm_then_stats_counts_lookups() {
+    let tmp = setup_project_dir();
+    let cache = cache_path(&tmp);
+
+    let warm = tokf()
+        .current_dir(tmp.path())
+        .args(["cache", "warm"])
+        .output()
+        .unwrap();
+    assert!(
+        warm.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&warm.stderr)
+    );
+    assert!(cache.exists(), "warm should write {}", cache.display());
+
+    tokf()
+        .current_dir(tmp.path())
+        .args(["run", "echo", "hello"])
+        .output()
+        .unwrap();
+
+    let stats = tokf()
+        .current_dir(tmp.path())
+        .args(["cache", "stats"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&stats.stdout);
+    assert!(stdout.contains("hits: 1\n"), "got: {stdout}");
+    assert!(stdout.contains("misses: 0\n"), "got: {stdout}");
+    assert!(stdout.contains("hit rate: 100.0%"), "got: {stdout}");
+
+    tokf()
+        .current_dir(tmp.path())
+        .args(["cache", "clear"])
+        .output()
+        .unwrap();
+    let stats = tokf()
+        .current_dir(tmp.path())
+        .args(["cache", "stats"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&stats.stdout);
+    assert!(stdout.contains("entries: 0\n"), "got: {stdout}");
+    assert!(stdout.contains("hits: 0\n"), "got: {stdout}");
+}
@@ -0,0 +1,79 @@
+#![cfg(unix)]
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::{Child, Command, Output, Stdio};
+use std::time::Duration;
+
+fn tokf(dir: &std::path::Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
+    cmd.current_dir(dir)
+        .env("TOKF_HOME", dir.join("home"))
+        .env_remove("TOKF_NO_DAEMON");
+    cmd
+}
+
+fn output(dir: &std::path::Path, args: &[&str]) -> Output {
+    tokf(dir).args(args).output().unwrap()
+}
+
+/// Start `tokf daemon` and wait until it answers `--status`.
+fn start_daemon(dir: &std::path::Path) -> Child {
+    let child = tokf(dir)
+        .arg("daemon")
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let up = (0..200).any(|_| {
+        std::thread::sleep(Duration::from_millis(25));
+        output(dir, &["daemon", "--status"]).status.success()
+    });
+    assert!(up, "daemon did not come up");
+    child
+}
+
+#[test]
+fn daemon_answers_rewrites_and_stops() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters).unwrap();
+    std::fs::write(filters.join("my-tool.toml"), "command = \"my-tool\"\n").unwrap();
+
+    let mut daemon = start_daemon(dir.path());
+    let status = output(dir.path(), &["daemon", "--status"]);
+    assert!(String::from_utf8_lossy(&status.stdout).starts_with("running: pid"));
+
+    let rewrite = output(dir.path(), &["--no-builtin", "rewrite", "my-tool --fast"]);
+    assert_eq!(
+        String::from_utf8_lossy(&rewrite.stdout).trim(),
+        "tokf run my-tool --fast"
+    );
+    let run = output(dir.path(), &["--no-builtin", "-v", "run", "my-tool"]);
+    assert!(
+        String::from_utf8_lossy(&run.stderr).contains("matched my-tool.toml"),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+
+    assert!(output(dir.path(), &["daemon", "--stop"]).status.success());
+    assert!(daemon.wait().unwrap().success());
+    assert!(!output(dir.path(), &["daemon", "--status"]).status.success());
+}
+
+#[test]
+fn commands_work_without_a_daemon() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters).unwrap();
+    std::fs::write(filters.join("my-tool.toml"), "command = \"my-tool\"\n").unwrap();
+    // A socket file with nobody listening, as left by a killed daemon.
+    std::fs::create_dir_all(dir.path().join("home/cache")).unwrap();
+    std::fs::write(dir.path().join("home/cache/daemon.sock"), "").unwrap();
+
+    let rewrite = output(dir.path(), &["--no-builtin", "rewrite", "my-tool"]);
+    assert_eq!(
+        String::from_utf8_lossy(&rewrite.stdout).trim(),
+        "tokf run my-tool"
+    );
+    let stop = output(dir.path(), &["daemon", "--stop"]);
+    assert!(String::from_utf8_lossy(&stop.stderr).contains("not running"));
+}
@@ -0,0 +1,85 @@
+#![cfg(unix)]
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn tokf(dir: &TempDir, editor: &str) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
+    cmd.current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .env("TOKF_NO_DAEMON", "1")
+        .env_remove("VISUAL")
+        .env("EDITOR", editor);
+    cmd
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn edit_copies_a_builtin_to_the_project_and_checks_it() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir, "sed -i.bak 's/^# /## /'")
+        .args(["edit", "git/push"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("copied built-in git/push"), "{stderr}");
+    assert!(stderr.contains("is valid"), "{stderr}");
+
+    let local = dir.path().join(".tokf/filters/git/push.toml");
+    assert!(
+        std::fs::read_to_string(&local)
+            .unwrap()
+            .contains("command = \"git push\"")
+    );
+
+    // The copy now wins, so a second edit opens it instead of copying again.
+    let output = tokf(&dir, "true")
+        .args(["edit", "git/push"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("copied"), "{stderr}");
+}
+
+#[test]
+fn edit_reports_problems_left_by_the_editor() {
+    let dir = TempDir::new().unwrap();
+    let filters = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters).unwrap();
+    std::fs::write(filters.join("my-tool.toml"), "command = \"my tool\"\n").unwrap();
+
+    let output = tokf(&dir, "sh -c 'echo \"skip = [\\\"(\\\"]\" >> \"$1\"' editor")
+        .args(["edit", "my-tool"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(78), "{stderr}");
+    assert!(stderr.contains("skip[0]: invalid regex"), "{stderr}");
+}
+
+#[test]
+fn edit_unknown_filter_is_not_found() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir, "true")
+        .args(["edit", "no/such-filter"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(66));
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn failing_editor_skips_the_check() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir, "false")
+        .args(["edit", "git/push"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("editor \"false\" exited"), "{stderr}");
+}
@@ -0,0 +1,76 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn tokf(dir: &TempDir) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
+    cmd.current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .env("TOKF_NO_DAEMON", "1");
+    cmd
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn eject_writes_the_builtin_to_the_project() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir).args(["eject", "git/push"]).output().unwrap();
+    assert!(output.status.success());
+
+    let local = dir.path().join(".tokf/filters/git/push.toml");
+    assert!(
+        std::fs::read_to_string(&local)
+            .unwrap()
+            .contains("command = \"git push\"")
+    );
+    let which = tokf(&dir).args(["which", "git push"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&which.stdout).contains("[local]"));
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn eject_user_writes_to_the_user_filters_dir() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir)
+        .args(["eject", "cargo/test", "--user"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dir.path().join("home/filters/cargo/test.toml").exists());
+    assert!(!dir.path().join(".tokf").exists());
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn eject_refuses_to_overwrite_without_force() {
+    let dir = TempDir::new().unwrap();
+    let local = dir.path().join(".tokf/filters/git/push.toml");
+    std::fs::create_dir_all(local.parent().unwrap()).unwrap();
+    std::fs::write(&local, "command = \"git push\"\n# mine\n").unwrap();
+
+    let output = tokf(&dir).args(["eject", "git/push"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already exists"), "{stderr}");
+    assert!(std::fs::read_to_string(&local).unwrap().contains("# mine"));
+
+    let output = tokf(&dir)
+        .args(["eject", "git/push", "--force"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!std::fs::read_to_string(&local).unwrap().contains("# mine"));
+}
+
+#[test]
+fn eject_unknown_builtin_is_not_found() {
+    let dir = TempDir::new().unwrap();
+    let output = tokf(&dir)
+        .args(["eject", "no/such-filter"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(66));
+    assert!(!dir.path().join(".tokf").exists());
+}
@@ -0,0 +1,66 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::{Command, Output};
+
+fn tokf(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn json_error(output: &Output) -> serde_json::Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr.lines().last().unwrap_or_default();
+    serde_json::from_str(line).expect("stderr should end with a JSON error")
+}
+
+#[test]
+fn spawn_failure_exits_127() {
+    let output = tokf(&["run", "--no-filter", "tokf-no-such-binary-xyz"]);
+    assert_eq!(output.status.code(), Some(127));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("[tokf] error:"), "stderr: {stderr}");
+}
+
+#[test]
+fn json_error_format_for_spawn_failure() {
+    let output = tokf(&[
+        "--error-format",
+        "json",
+        "run",
+        "--no-filter",
+        "tokf-no-such-binary-xyz",
+    ]);
+    assert_eq!(output.status.code(), Some(127));
+    let err = json_error(&output);
+    assert_eq!(err["kind"], "spawn");
+    assert_eq!(err["exit_code"], 127);
+    assert!(!err["message"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn json_error_format_for_invalid_config() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let bad = dir.path().join("bad.toml");
+    std::fs::write(&bad, "not valid [[[ toml").unwrap();
+    let output = tokf(&["check", bad.to_str().unwrap(), "--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(78));
+    assert_eq!(json_error(&output)["kind"], "config");
+}
+
+#[test]
+fn json_error_format_for_missing_filter() {
+    let output = tokf(&["--error-format=json", "show", "no/such/filter"]);
+    assert_eq!(output.status.code(), Some(66));
+    let err = json_error(&output);
+    assert_eq!(err["kind"], "filter_not_found");
+    assert_eq!(err["message"], "filter not found: no/such/filter");
+}
+
+#[test]
+fn wrapped_command_exit_code_is_untouched() {
+    let output = tokf(&["--error-format", "json", "run", "sh", "-c", "exit 3"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,87 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn explain(dir: &TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .env("TOKF_HOME", dir.path().join("home"))
+        .current_dir(dir.path())
+        .args(["--no-builtin", "--no-cache", "explain"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn setup() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let filters = dir.path().join(".tokf/filters/tool");
+    std::fs::create_dir_all(&filters).unwrap();
+    std::fs::write(
+        filters.join("build.toml"),
+        r#"command = "tool build"
+skip = ["^noise"]
+
+[on_failure]
+output = "failed: {output}"
+"#,
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("out.txt"), "noise 1\nerror: boom\n").unwrap();
+    dir
+}
+
+#[test]
+fn explain_traces_stages_over_fixture() {
+    let dir = setup();
+    let out = explain(
+        &dir,
+        &[
+            "tool build --release",
+            "--fixture",
+            "out.txt",
+            "--exit-code",
+            "1",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    for expected in [
+        "filter: tool/build [local]",
+        "pattern \"tool build\" matched \"tool build\"; args: [\"--release\"]",
+        "\"^noise\": removed 1 lines",
+        "  - \"noise 1\"",
+        "exit code 1 → [on_failure]",
+        "{output} = \"error: boom\"",
+        "--- output ---\nfailed: error: boom",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {expected:?} in:\n{stdout}"
+        );
+    }
+}
+
+#[test]
+fn explain_without_fixture_shows_resolution_only() {
+    let dir = setup();
+    let out = explain(&dir, &["tool build"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success());
+    assert!(stdout.contains("filter: tool/build"), "stdout: {stdout}");
+    assert!(stdout.contains("pass --fixture"), "stdout: {stdout}");
+}
+
+#[test]
+fn explain_unmatched_command_fails() {
+    let dir = setup();
+    let out = explain(&dir, &["other thing"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("no filter found"), "stderr: {stderr}");
+}
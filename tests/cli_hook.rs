@@ -1,20 +1,26 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::process::{Command, Stdio};
 
 fn tokf() -> Command {
     Command::new(env!("CARGO_BIN_EXE_tokf"))
 }
 
-fn manifest_dir() -> &'static str {
+const fn manifest_dir() -> &'static str {
     env!("CARGO_MANIFEST_DIR")
 }
 
 /// Helper: pipe JSON to `tokf hook handle` from a fresh tempdir.
 /// Embedded stdlib is always available, so no filters need to be copied.
 fn hook_handle_with_stdlib(json: &str) -> (String, bool) {
+    hook_handle_for_agent(json, "claude")
+}
+
+fn hook_handle_for_agent(json: &str, agent: &str) -> (String, bool) {
     let dir = tempfile::TempDir::new().unwrap();
 
     let mut child = tokf()
-        .args(["hook", "handle"])
+        .args(["hook", "handle", "--agent", agent])
         .current_dir(dir.path())
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -35,6 +41,7 @@ fn hook_handle_with_stdlib(json: &str) -> (String, bool) {
 
 // --- tokf hook handle ---
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn hook_handle_rewrites_bash_git_status() {
     let json = r#"{"tool_name":"Bash","tool_input":{"command":"git status"}}"#;
@@ -56,6 +63,7 @@ fn hook_handle_rewrites_bash_git_status() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn hook_handle_rewrites_bash_with_args() {
     let json = r#"{"tool_name":"Bash","tool_input":{"command":"git push origin main"}}"#;
@@ -150,6 +158,7 @@ fn hook_handle_always_exits_zero() {
     assert!(success, "hook handle should always exit 0");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn hook_handle_fixture_bash() {
     let fixture = format!(
@@ -279,6 +288,32 @@ fn hook_handle_multiple_pattern_non_variant_silent() {
     );
 }
 
+#[cfg(feature = "stdlib")]
+#[test]
+fn hook_handle_gemini_rewrites_run_shell_command() {
+    let json = r#"{"hook_event_name":"BeforeTool","tool_name":"run_shell_command","tool_input":{"command":"git status"}}"#;
+    let (stdout, success) = hook_handle_for_agent(json, "gemini");
+    assert!(success);
+    let value: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(
+        value["hookSpecificOutput"]["tool_input"]["command"],
+        "tokf run git status"
+    );
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn hook_handle_codex_rewrites_shell_argv() {
+    let json = r#"{"tool_name":"shell","tool_input":{"command":["bash","-lc","git status"]}}"#;
+    let (stdout, success) = hook_handle_for_agent(json, "codex");
+    assert!(success);
+    let value: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(
+        value["hookSpecificOutput"]["updatedInput"]["command"],
+        serde_json::json!(["bash", "-lc", "tokf run git status"])
+    );
+}
+
 // --- tokf hook install ---
 
 #[test]
@@ -398,3 +433,48 @@ fn hook_install_shows_info_on_stderr() {
         "expected settings path, got: {stderr}"
     );
 }
+
+#[test]
+fn hook_install_gemini_and_codex() {
+    let dir = tempfile::TempDir::new().unwrap();
+
+    for agent in ["gemini", "codex"] {
+        let output = tokf()
+            .args(["hook", "install", "--agent", agent])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "hook install --agent {agent} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let gemini = std::fs::read_to_string(dir.path().join(".gemini/settings.json")).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&gemini).unwrap();
+    assert_eq!(
+        value["hooks"]["BeforeTool"][0]["matcher"],
+        "run_shell_command"
+    );
+
+    let codex = std::fs::read_to_string(dir.path().join(".codex/config.toml")).unwrap();
+    assert!(codex.contains("[[hooks.PreToolUse]]"), "{codex}");
+    assert!(codex.contains("codex-pre-tool-use.sh"), "{codex}");
+    assert!(
+        dir.path()
+            .join(".tokf/hooks/gemini-pre-tool-use.sh")
+            .exists()
+    );
+    assert!(!dir.path().join(".claude").exists());
+}
+
+#[test]
+fn hook_install_rejects_unknown_agent() {
+    let output = tokf()
+        .args(["hook", "install", "--agent", "cursor"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown agent"));
+}
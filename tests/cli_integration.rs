@@ -1,10 +1,12 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::process::Command;
 
 fn tokf() -> Command {
     Command::new(env!("CARGO_BIN_EXE_tokf"))
 }
 
-fn manifest_dir() -> &'static str {
+const fn manifest_dir() -> &'static str {
     env!("CARGO_MANIFEST_DIR")
 }
 
@@ -118,6 +120,391 @@ fn run_timing_with_matched_filter() {
     );
 }
 
+#[test]
+fn run_env_prefix_passthrough_sets_env() {
+    let output = tokf()
+        .args(["run", "TOKF_TEST_VAR=hi", "sh", "-c", "echo $TOKF_TEST_VAR"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hi");
+}
+
+#[test]
+fn run_env_prefix_still_matches_filter() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("echo.toml"),
+        "command = \"echo\"\n[on_success]\noutput = \"filtered\"",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "CI=1", "echo", "hello"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "filtered");
+}
+
+fn write_fail_when_filter(dir: &std::path::Path) {
+    let filters_dir = dir.join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("echo.toml"),
+        "command = \"echo\"\nfail_when = \"{tests.count} == 0\"\n\n\
+         [[section]]\nmatch = \"^test \"\ncollect_as = \"tests\"\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn run_unmatched_command_uses_default_filter() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("_default.toml"),
+        "skip = [\"^noise\"]\nmax_output_lines = 2\nrun = \"echo hijacked\"\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "-v", "printf", "a\\nnoise\\nb\\nc\\n"])
+        .current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "a\n… 1 line omitted …\nc"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("_default.toml"), "got: {stderr}");
+
+    // Matched filters and `tokf which` never see it.
+    let which = tokf()
+        .args(["which", "printf x"])
+        .current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&which.stdout).contains("_default"));
+}
+
+#[test]
+fn run_fail_when_overrides_zero_exit() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_fail_when_filter(dir.path());
+
+    let output = tokf()
+        .args(["run", "echo", "running 0 tests"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("fail_when"), "got: {stderr}");
+}
+
+#[test]
+fn run_fail_when_not_met_keeps_success() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_fail_when_filter(dir.path());
+
+    let output = tokf()
+        .args(["run", "echo", "test a ... ok"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn run_timeout_flag_kills_command() {
+    let start = std::time::Instant::now();
+    let output = tokf()
+        .args([
+            "run",
+            "--no-filter",
+            "--timeout",
+            "1",
+            "sh",
+            "-c",
+            "echo partial; sleep 10",
+        ])
+        .output()
+        .unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(8));
+    assert_eq!(output.status.code(), Some(124));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "partial");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("timed out after 1s"), "got: {stderr}");
+}
+
+#[test]
+#[allow(clippy::literal_string_with_formatting_args)]
+fn run_filter_timeout_routes_to_on_failure() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("slow.toml"),
+        "command = \"slow-tool\"\nrun = \"echo partial; sleep 10\"\ntimeout_secs = 1\n\n\
+         [on_success]\noutput = \"done\"\n\n\
+         [on_failure]\noutput = \"timed out after {timeout}s: {output}\"\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "slow-tool"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "timed out after 1s: partial"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_timeout_forwards_interrupt_to_command() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let pid_file = dir.path().join("pid");
+    let script = format!("echo $$ > {}; exec sleep 30", pid_file.display());
+    let mut child = tokf()
+        .args(["run", "--no-filter", "--timeout", "60", "sh", "-c", &script])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let sleep_pid: i32 = loop {
+        if let Some(pid) = std::fs::read_to_string(&pid_file)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        {
+            break pid;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "command never started"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+
+    let tokf_pid = i32::try_from(child.id()).unwrap();
+    // SAFETY: plain kill(2) calls on processes this test spawned.
+    unsafe { libc::kill(tokf_pid, libc::SIGINT) };
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130));
+    // SAFETY: signal 0 only checks that the process still exists.
+    assert_ne!(
+        unsafe { libc::kill(sleep_pid, 0) },
+        0,
+        "command outlived tokf"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_pty_flag_gives_command_a_tty() {
+    let script = "[ -t 1 ] && echo tty || echo notty";
+    let plain = tokf()
+        .args(["run", "--no-filter", "sh", "-c", script])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&plain.stdout).trim(), "notty");
+
+    let pty = tokf()
+        .args(["run", "--no-filter", "--pty", "sh", "-c", script])
+        .output()
+        .unwrap();
+    assert!(pty.status.success());
+    assert_eq!(String::from_utf8_lossy(&pty.stdout).trim(), "tty");
+}
+
+#[cfg(unix)]
+#[test]
+fn run_filter_pty_merges_streams() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("tty-tool.toml"),
+        "command = \"tty-tool\"\nrun = \"[ -t 2 ] && echo err-on-tty >&2\"\npty = true\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "tty-tool"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "err-on-tty");
+}
+
+#[cfg(unix)]
+#[test]
+fn run_diff_prints_hidden_lines_to_stderr() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("noisy.toml"),
+        "command = \"noisy\"\nrun = \"printf 'keep\\\\nnoise\\\\n'\"\nskip = [\"^noise\"]\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "--diff", "noisy"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "keep\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "[tokf] diff: raw 10 bytes (~2 tokens) \u{2192} filtered 4 bytes (~1 tokens)"
+        ),
+        "stderr: {stderr}"
+    );
+    assert!(
+        stderr.contains("--- raw\n+++ filtered\n@@ -1,2 +1,1 @@\n keep\n-noise\n"),
+        "stderr: {stderr}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_save_raw_keeps_full_output_and_points_to_it() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("noisy.toml"),
+        "command = \"noisy\"\nrun = \"printf 'keep\\\\nnoise\\\\n'\"\nskip = [\"^noise\"]\nsave_raw = true\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "noisy"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (kept, pointer) = stdout.trim_end().split_once('\n').unwrap();
+    assert_eq!(kept, "keep");
+    let saved = pointer.strip_prefix("full output: ").unwrap();
+    assert!(saved.starts_with(".tokf/last-run/"), "{saved}");
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join(saved)).unwrap(),
+        "keep\nnoise"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn last_replays_the_saved_run() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("noisy.toml"),
+        "command = \"noisy\"\nrun = \"printf 'keep\\\\nnoise\\\\n'\"\nskip = [\"^noise\"]\n",
+    )
+    .unwrap();
+    let last = |args: &[&str]| {
+        let output = tokf()
+            .arg("last")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+
+    let (code, _, stderr) = last(&[]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("no saved run"), "{stderr}");
+
+    let run = tokf()
+        .args(["run", "--save-raw", "noisy"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(run.status.success());
+
+    assert_eq!(last(&[]).1, "keep\nnoise\n");
+    assert_eq!(last(&["--raw"]).1, "keep\nnoise\n");
+    assert_eq!(last(&["--filtered"]).1, "keep\n");
+    let (_, diff, _) = last(&["--diff"]);
+    assert!(diff.contains("--- raw\n+++ filtered\n"), "{diff}");
+    assert!(diff.contains("-noise\n"), "{diff}");
+    assert_eq!(last(&["--raw", "--diff"]).0, Some(2), "flags conflict");
+}
+
+#[cfg(unix)]
+#[test]
+#[allow(clippy::literal_string_with_formatting_args)]
+fn run_steps_bind_output_for_later_steps_and_templates() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("report.toml"),
+        r#"command = "report"
+run = "echo main"
+
+[[step]]
+run = "printf 'a\nb\nc\n'"
+as = "files"
+
+[[step]]
+run = "printf '%s\n' {files} | wc -l | tr -d ' '"
+as = "count"
+
+[[step]]
+run = "seq 3"
+as = "nums"
+
+[on_success]
+output = "{output}: {count} files ({files}), {nums}"
+"#,
+    )
+    .unwrap();
+    // Step output goes through the filter for its own command.
+    std::fs::write(
+        filters_dir.join("seq.toml"),
+        "command = \"seq\"\nskip = [\"^2$\"]\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "report"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "main: 3 files (a\nb\nc), 1\n3\n"
+    );
+}
+
 // --- tokf check ---
 
 #[test]
@@ -139,7 +526,7 @@ fn check_nonexistent_file() {
         .output()
         .unwrap();
     assert!(!output.status.success());
-    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.status.code(), Some(66));
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
         stderr.contains("not found"),
@@ -158,7 +545,7 @@ fn check_invalid_toml() {
         .output()
         .unwrap();
     assert!(!output.status.success());
-    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.status.code(), Some(78));
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
         stderr.contains("error"),
@@ -166,6 +553,57 @@ fn check_invalid_toml() {
     );
 }
 
+#[test]
+fn check_reports_invalid_regex_with_key_path() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filter = dir.path().join("bad-regex.toml");
+    std::fs::write(&filter, "command = \"x\"\nskip = [\"(oops\"]\n").unwrap();
+
+    let output = tokf()
+        .args(["check", filter.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(78));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("bad-regex.toml:2:9: skip[0]: invalid regex: unclosed group"),
+        "stderr: {stderr}"
+    );
+}
+
+// --- tokf lint ---
+
+#[test]
+fn lint_json_lists_warnings_and_exits_one() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filter = dir.path().join("f.toml");
+    std::fs::write(&filter, "command = \"x\"\nskip = [\"^never$\"]\n").unwrap();
+    let fixture = dir.path().join("out.txt");
+    std::fs::write(&fixture, "hello\n").unwrap();
+
+    let output = tokf()
+        .args(["lint", filter.to_str().unwrap(), "--json", "--fixture"])
+        .arg(&fixture)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["rule"], "unmatched-skip");
+    assert_eq!(json[0]["key"], "skip[0]");
+    assert_eq!(json[0]["line"], 2);
+}
+
+#[test]
+fn lint_clean_stdlib_filter_exits_zero() {
+    let filter = format!("{}/filters/git/push.toml", manifest_dir());
+    let output = tokf().args(["lint", &filter]).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
 // --- tokf test ---
 
 #[test]
@@ -176,7 +614,7 @@ fn test_nonexistent_filter_exits_with_error() {
         .output()
         .unwrap();
     assert!(!output.status.success());
-    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.status.code(), Some(66));
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
         stderr.contains("filter not found"),
@@ -284,6 +722,7 @@ fn ls_exits_zero() {
     assert!(output.status.success());
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn ls_stdlib_contains_all_expected_filters() {
     // Embedded stdlib is always available — no need to copy filters
@@ -397,6 +836,7 @@ fn ls_verbose_shows_source() {
 
 // --- tokf which ---
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn which_git_push_finds_stdlib() {
     // Embedded stdlib is always available — no need to copy filters
@@ -415,6 +855,7 @@ fn which_git_push_finds_stdlib() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn which_git_push_with_trailing_args() {
     let dir = tempfile::TempDir::new().unwrap();
@@ -447,6 +888,7 @@ fn which_unknown_command_exits_one() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn which_shows_priority_label() {
     // Embedded stdlib filter shows [built-in] when no local override
@@ -484,6 +926,113 @@ fn which_shows_local_label_for_local_filter() {
     );
 }
 
+#[cfg(feature = "stdlib")]
+#[test]
+fn which_all_lists_shadowed_filters_after_the_winner() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters/git");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(filters_dir.join("push.toml"), "command = \"git push\"").unwrap();
+
+    let output = tokf()
+        .args(["which", "--all", "git push origin main"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "got: {stdout}");
+    assert!(lines[0].starts_with("git/push  [local]  specificity: 2"));
+    assert!(lines[0].ends_with("\u{2190} wins"), "got: {stdout}");
+    assert!(lines[1].starts_with("git/push  [built-in]"));
+    assert!(!lines[1].contains("wins"), "got: {stdout}");
+}
+
+#[test]
+fn ls_and_which_json_describe_filters() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("my-tool.toml"),
+        r#"command = ["my tool", "mytool"]"#,
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["ls", "--json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mine = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["name"] == "my-tool")
+        .expect("local filter listed");
+    assert_eq!(mine["command"], "my tool");
+    assert_eq!(mine["patterns"], serde_json::json!(["my tool", "mytool"]));
+    assert_eq!(mine["priority"], "local");
+
+    let output = tokf()
+        .args(["which", "--json", "mytool --flag"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let found: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(found["name"], "my-tool");
+    assert!(found.get("status").is_none());
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn which_all_json_marks_the_winner() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters/git");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(filters_dir.join("push.toml"), "command = \"git push\"").unwrap();
+
+    let output = tokf()
+        .args(["which", "--all", "--json", "git push"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let found: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses: Vec<_> = found
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| {
+            (
+                f["priority"].as_str().unwrap(),
+                f["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(statuses, [("local", "wins"), ("built-in", "shadowed")]);
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn show_resolved_json_has_the_effective_config() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output = tokf()
+        .args(["show", "git/push", "--resolved", "--json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let shown: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(shown["name"], "git/push");
+    assert_eq!(shown["merged"], serde_json::json!([]));
+    assert_eq!(shown["config"]["command"], "git push");
+}
+
 #[test]
 fn ls_verbose_shows_all_patterns_for_multiple() {
     let dir = tempfile::TempDir::new().unwrap();
@@ -556,6 +1105,7 @@ fn which_skips_invalid_toml_silently() {
 
 // --- tokf show ---
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn show_git_push_prints_toml() {
     let dir = tempfile::TempDir::new().unwrap();
@@ -576,6 +1126,7 @@ fn show_git_push_prints_toml() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn show_with_toml_extension_works() {
     let dir = tempfile::TempDir::new().unwrap();
@@ -593,10 +1144,10 @@ fn show_with_toml_extension_works() {
 }
 
 #[test]
-fn show_nonexistent_exits_one() {
+fn show_nonexistent_exits_not_found() {
     let output = tokf().args(["show", "no/such/filter"]).output().unwrap();
     assert!(!output.status.success());
-    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.status.code(), Some(66));
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
         stderr.contains("filter not found"),
@@ -628,6 +1179,37 @@ fn show_local_filter_prints_disk_content() {
     );
 }
 
+#[cfg(feature = "stdlib")]
+#[test]
+fn show_resolved_folds_merge_filter_into_builtin() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("log-extra.toml"),
+        "command = \"git log\"\nmerge = true\nskip = [\"^Merge\"]\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["show", "git/log", "--resolved"])
+        .current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# base: <built-in>"), "got: {stdout}");
+    assert!(stdout.contains("# merged: "), "got: {stdout}");
+    assert!(stdout.contains("log-extra.toml"), "got: {stdout}");
+    assert!(stdout.contains("^Merge"), "got: {stdout}");
+    assert!(
+        stdout.contains("git log --oneline"),
+        "built-in run should survive the merge, got: {stdout}"
+    );
+}
+
+#[cfg(feature = "stdlib")]
 #[test]
 fn show_cargo_build_nested_embedded_path() {
     // Verifies that show works for nested paths (cargo/build) in the embedded stdlib
@@ -652,6 +1234,7 @@ fn show_cargo_build_nested_embedded_path() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn run_embedded_filter_from_empty_dir() {
     // From a directory with no local .tokf/filters, the embedded stdlib should still be active.
@@ -670,6 +1253,7 @@ fn run_embedded_filter_from_empty_dir() {
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn ls_verbose_shows_builtin_for_embedded_filter() {
     // From a dir with no local filters, embedded stdlib filters should show source as <built-in>
@@ -686,3 +1270,213 @@ fn ls_verbose_shows_builtin_for_embedded_filter() {
         "expected '<built-in>' in verbose ls output for embedded filters, got: {stderr}"
     );
 }
+
+// --- built-in filters disabled ---
+
+fn dir_with_local_filter() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(filters_dir.join("my-tool.toml"), "command = \"my tool\"").unwrap();
+    dir
+}
+
+#[test]
+fn ls_no_builtin_flag_lists_only_local_filters() {
+    let dir = dir_with_local_filter();
+    let output = tokf()
+        .args(["ls", "--no-builtin"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "my-tool  \u{2192}  my tool");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("built-in filters disabled"),
+        "expected mode notice, got: {stderr}"
+    );
+}
+
+#[test]
+fn project_config_disables_builtin_filters() {
+    let dir = dir_with_local_filter();
+    std::fs::write(
+        dir.path().join(".tokf/config.toml"),
+        "use_builtin_filters = false\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["ls"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("git/push"), "stdlib listed: {stdout}");
+    assert!(stdout.contains("my-tool"));
+
+    let output = tokf()
+        .args(["which", "git push"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+// --- tokf filter-file ---
+
+fn dir_with_log_filter() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters/ci");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("build.toml"),
+        "command = \"ci build\"\nskip = [\"^debug:\"]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("a.log"), "debug: x\nerror: a\n").unwrap();
+    std::fs::write(dir.path().join("b.log"), "error: b\ndebug: y\n").unwrap();
+    dir
+}
+
+#[test]
+fn filter_file_by_name() {
+    let dir = dir_with_log_filter();
+    let output = tokf()
+        .args(["filter-file", "ci/build", "a.log"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "error: a\n");
+}
+
+#[test]
+fn filter_file_by_command_and_glob() {
+    let dir = dir_with_log_filter();
+    let output = tokf()
+        .args(["filter-file", "ci build", "*.log"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "==> a.log <==\nerror: a\n\n==> b.log <==\nerror: b\n"
+    );
+}
+
+#[test]
+fn filter_file_tolerates_invalid_utf8() {
+    let dir = dir_with_log_filter();
+    std::fs::write(
+        dir.path().join("c.log"),
+        b"debug: z\nerror: \xff bad byte\n",
+    )
+    .unwrap();
+    let output = tokf()
+        .args(["filter-file", "ci/build", "c.log"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "error: \u{fffd} bad byte\n"
+    );
+}
+
+#[test]
+fn filter_file_writes_output_file() {
+    let dir = dir_with_log_filter();
+    let output = tokf()
+        .args(["filter-file", "ci/build", "b.log", "-o", "out.txt"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let written = std::fs::read_to_string(dir.path().join("out.txt")).unwrap();
+    assert_eq!(written, "error: b\n");
+}
+
+#[test]
+fn filter_file_unknown_filter_fails() {
+    let dir = dir_with_log_filter();
+    let output = tokf()
+        .args(["filter-file", "no/such", "a.log"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("filter not found"), "got: {stderr}");
+}
+
+#[test]
+fn filter_file_unmatched_glob_fails() {
+    let dir = dir_with_log_filter();
+    let output = tokf()
+        .args(["filter-file", "ci/build", "*.txt"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no files match"), "got: {stderr}");
+}
+
+#[test]
+fn run_sarif_writes_diagnostics_the_filter_hides() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("lint.toml"),
+        "command = \"sh\"\n\
+         [on_failure]\noutput = \"lint failed\"\n\
+         [[diagnostic]]\n\
+         pattern = '^(?P<file>\\S+):(?P<line>\\d+): (?P<level>\\w+): (?P<message>.*)$'\n",
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["run", "--sarif", "out.sarif", "sh", "-c"])
+        .arg("echo 'src/a.py:7: error: bad thing'; echo noise; exit 2")
+        .current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "lint failed"
+    );
+
+    let log: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("out.sarif")).unwrap())
+            .unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["level"], "error");
+    assert_eq!(results[0]["message"]["text"], "bad thing");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "src/a.py");
+    assert_eq!(location["region"]["startLine"], 7);
+
+    // Without a matching filter the log is still written, empty.
+    let output = tokf()
+        .args(["run", "--no-filter", "--sarif", "empty.sarif", "true"])
+        .current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let log: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("empty.sarif")).unwrap())
+            .unwrap();
+    assert_eq!(log["runs"][0]["results"], serde_json::json!([]));
+}
@@ -0,0 +1,103 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+fn tokf(dir: &TempDir) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
+    cmd.current_dir(dir.path())
+        .env("TOKF_HOME", dir.path().join("home"))
+        .env("TOKF_NO_DAEMON", "1")
+        .env_remove("TOKF_LOG")
+        .env_remove("TOKF_LOG_FORMAT");
+    cmd
+}
+
+/// Pipe a Claude Code `Bash` event for `git status` into `tokf hook handle`.
+fn hook_handle(mut cmd: Command) -> Output {
+    let mut child = cmd
+        .args(["hook", "handle"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"tool_name":"Bash","tool_input":{"command":"git status"}}"#)
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn debug_events_are_hidden_by_default() {
+    let dir = TempDir::new().unwrap();
+    let output = hook_handle(tokf(&dir));
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn tokf_log_enables_debug_events() {
+    let dir = TempDir::new().unwrap();
+    let mut cmd = tokf(&dir);
+    cmd.env("TOKF_LOG", "debug");
+    let output = hook_handle(cmd);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[tokf] debug: hook: rewrote command"),
+        "{stderr}"
+    );
+    // The hook's answer on stdout is untouched.
+    let response: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is the hook response");
+    assert!(response["hookSpecificOutput"].is_object());
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn json_log_format_writes_one_object_per_event() {
+    let dir = TempDir::new().unwrap();
+    let mut cmd = tokf(&dir);
+    cmd.args(["--log-level", "debug", "--log-format", "json"]);
+    let output = hook_handle(cmd);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let rewrite = events
+        .iter()
+        .find(|e| e["message"] == "hook: rewrote command")
+        .expect("a rewrite event");
+    assert_eq!(rewrite["level"], "DEBUG");
+    assert_eq!(rewrite["command"], "git status");
+    assert_eq!(rewrite["rewritten"], "tokf run git status");
+}
+
+#[test]
+fn log_level_flag_overrides_tokf_log() {
+    let dir = TempDir::new().unwrap();
+    let mut cmd = tokf(&dir);
+    cmd.env("TOKF_LOG", "debug").args(["--log-level", "error"]);
+    let output = hook_handle(cmd);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn warnings_keep_their_text_format() {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join(".tokf")).unwrap();
+    std::fs::write(dir.path().join(".tokf/config.toml"), "not = [valid").unwrap();
+    let output = tokf(&dir).args(["rewrite", "ls"]).output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[tokf] warning: failed to parse"),
+        "{stderr}"
+    );
+}
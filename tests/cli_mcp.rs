@@ -0,0 +1,113 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Send newline-delimited JSON-RPC `messages` to `tokf serve-mcp` in `dir`
+/// and collect the replies.
+fn mcp_session(dir: &std::path::Path, messages: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .arg("serve-mcp")
+        .current_dir(dir)
+        .env("TOKF_HOME", dir.join("home"))
+        .env("TOKF_DB_PATH", dir.join("tracking.db"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        for msg in messages {
+            writeln!(stdin, "{msg}").unwrap();
+        }
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "serve-mcp failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+fn call(id: u32, tool: &str, arguments: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "params": { "name": tool, "arguments": arguments }
+    })
+}
+
+fn with_filter() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters).unwrap();
+    std::fs::write(
+        filters.join("noisy.toml"),
+        "command = \"sh -c\"\nskip = [\"^noise\"]\n",
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn run_filtered_returns_filtered_output_and_metadata() {
+    let dir = with_filter();
+    let replies = mcp_session(
+        dir.path(),
+        &[
+            serde_json::json!({"jsonrpc":"2.0","id":0,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}),
+            serde_json::json!({"jsonrpc":"2.0","method":"notifications/initialized"}),
+            call(
+                1,
+                "run_filtered",
+                &serde_json::json!({ "command": "sh -c 'echo noise; echo kept; exit 3'" }),
+            ),
+        ],
+    );
+    assert_eq!(replies.len(), 2);
+    let result = &replies[1]["result"];
+    assert_eq!(result["isError"], false);
+    assert_eq!(result["content"][0]["text"], "kept");
+    assert_eq!(result["structuredContent"]["exit_code"], 3);
+    assert_eq!(result["structuredContent"]["filter"], "sh -c");
+    assert_eq!(
+        result["structuredContent"]["raw_bytes"],
+        "noise\nkept".len()
+    );
+}
+
+#[test]
+fn filter_text_applies_a_named_filter() {
+    let dir = with_filter();
+    let replies = mcp_session(
+        dir.path(),
+        &[call(
+            7,
+            "filter_text",
+            &serde_json::json!({ "text": "noise 1\nsignal\nnoise 2\n", "filter": "noisy" }),
+        )],
+    );
+    assert_eq!(replies[0]["id"], 7);
+    assert_eq!(replies[0]["result"]["content"][0]["text"], "signal");
+}
+
+#[test]
+fn run_filtered_reports_spawn_failures_as_tool_errors() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let replies = mcp_session(
+        dir.path(),
+        &[call(
+            1,
+            "run_filtered",
+            &serde_json::json!({ "command": "definitely-not-a-real-binary-xyz" }),
+        )],
+    );
+    assert_eq!(replies[0]["result"]["isError"], true);
+}
@@ -0,0 +1,88 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tempfile::TempDir;
+
+/// Prints far more than a pipe buffer holds, then exits 3.
+const SCRIPT: &str = "seq 1 200000; exit 3";
+
+struct Piped {
+    exit_code: Option<i32>,
+    head: String,
+    stderr: String,
+}
+
+/// `tokf <args> | head -1`, with its own `TOKF_HOME` and tracking DB.
+fn run_into_head(home: &Path, args: &[&str]) -> Piped {
+    let mut tokf = Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .env("TOKF_HOME", home)
+        .env("TOKF_DB_PATH", home.join("tracking.db"))
+        .current_dir(home)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let head = Command::new("head")
+        .arg("-1")
+        .stdin(tokf.stdout.take().unwrap())
+        .output()
+        .unwrap();
+    let out = tokf.wait_with_output().unwrap();
+    Piped {
+        exit_code: out.status.code(),
+        head: String::from_utf8_lossy(&head.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+    }
+}
+
+#[cfg(feature = "tracking")]
+fn recorded_runs(home: &Path) -> i64 {
+    let conn = tokf::tracking::open_db(&home.join("tracking.db")).unwrap();
+    tokf::tracking::query_summary(&conn, &tokf::tracking::GainQuery::default())
+        .unwrap()
+        .total_commands
+}
+
+#[test]
+fn passthrough_into_closed_pipe_keeps_exit_code() {
+    let home = TempDir::new().unwrap();
+    let piped = run_into_head(home.path(), &["run", "--no-filter", "sh", "-c", SCRIPT]);
+    assert_eq!(piped.head, "1\n");
+    assert_eq!(piped.exit_code, Some(3), "stderr: {}", piped.stderr);
+    assert!(
+        !piped.stderr.contains("panicked"),
+        "stderr: {}",
+        piped.stderr
+    );
+    assert!(
+        !piped.stderr.contains("Broken pipe"),
+        "stderr: {}",
+        piped.stderr
+    );
+    #[cfg(feature = "tracking")]
+    assert_eq!(recorded_runs(home.path()), 1);
+}
+
+#[test]
+fn filtered_output_into_closed_pipe_keeps_exit_code() {
+    let home = TempDir::new().unwrap();
+    std::fs::create_dir_all(home.path().join("filters")).unwrap();
+    std::fs::write(
+        home.path().join("filters/sh.toml"),
+        "command = \"sh\"\nskip = [\"^0$\"]\n",
+    )
+    .unwrap();
+    let piped = run_into_head(home.path(), &["run", "sh", "-c", SCRIPT]);
+    assert_eq!(piped.head, "1\n");
+    assert_eq!(piped.exit_code, Some(3), "stderr: {}", piped.stderr);
+    assert!(
+        !piped.stderr.contains("panicked"),
+        "stderr: {}",
+        piped.stderr
+    );
+    #[cfg(feature = "tracking")]
+    assert_eq!(recorded_runs(home.path()), 1);
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::process::Command;
 
 fn tokf() -> Command {
@@ -24,72 +26,96 @@ fn rewrite_with_stdlib(command: &str) -> String {
 
 // --- Filter-derived rewrites ---
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_status() {
     let result = rewrite_with_stdlib("git status");
     assert_eq!(result, "tokf run git status");
 }
 
+#[test]
+fn rewrite_no_builtin_ignores_stdlib() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output = tokf()
+        .args(["rewrite", "--no-builtin", "git status"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "git status");
+}
+
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_status_with_args() {
     let result = rewrite_with_stdlib("git status --short");
     assert_eq!(result, "tokf run git status --short");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_cargo_test() {
     let result = rewrite_with_stdlib("cargo test");
     assert_eq!(result, "tokf run cargo test");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_cargo_test_with_args() {
     let result = rewrite_with_stdlib("cargo test --lib");
     assert_eq!(result, "tokf run cargo test --lib");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_push() {
     let result = rewrite_with_stdlib("git push");
     assert_eq!(result, "tokf run git push");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_diff() {
     let result = rewrite_with_stdlib("git diff");
     assert_eq!(result, "tokf run git diff");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_log() {
     let result = rewrite_with_stdlib("git log");
     assert_eq!(result, "tokf run git log");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_add() {
     let result = rewrite_with_stdlib("git add");
     assert_eq!(result, "tokf run git add");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_git_commit() {
     let result = rewrite_with_stdlib("git commit");
     assert_eq!(result, "tokf run git commit");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_cargo_build() {
     let result = rewrite_with_stdlib("cargo build");
     assert_eq!(result, "tokf run cargo build");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_cargo_clippy() {
     let result = rewrite_with_stdlib("cargo clippy");
     assert_eq!(result, "tokf run cargo clippy");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn rewrite_ls() {
     let result = rewrite_with_stdlib("ls -la");
@@ -118,6 +144,37 @@ fn rewrite_unknown_command_passthrough() {
     assert_eq!(result, "unknown-cmd foo bar");
 }
 
+#[test]
+fn rewrite_wraps_unmatched_commands_when_default_filter_exists() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(filters_dir.join("_default.toml"), "dedup = true").unwrap();
+    std::fs::write(
+        dir.path().join(".tokf/rewrites.toml"),
+        r#"
+[skip]
+patterns = ["^make deploy"]
+"#,
+    )
+    .unwrap();
+
+    let rewrite = |command: &str| {
+        let output = tokf()
+            .args(["rewrite", "--no-builtin", command])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    assert_eq!(rewrite("make build"), "tokf run make build");
+    assert_eq!(rewrite("cd src && make"), "cd src && tokf run make");
+    assert_eq!(rewrite("export CI=1"), "export CI=1");
+    assert_eq!(rewrite("make deploy"), "make deploy");
+}
+
 // --- With user rewrites.toml ---
 
 #[test]
@@ -350,3 +407,53 @@ fn rewrite_always_exits_zero() {
     assert!(output.status.success());
     assert_eq!(output.status.code(), Some(0));
 }
+
+// --- Shell integration ---
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn shell_init_wraps_local_and_stdlib_programs() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let filters_dir = dir.path().join(".tokf/filters");
+    std::fs::create_dir_all(&filters_dir).unwrap();
+    std::fs::write(
+        filters_dir.join("mytool.toml"),
+        r#"command = "my-tool build""#,
+    )
+    .unwrap();
+
+    let output = tokf()
+        .args(["shell-init", "bash"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let script = String::from_utf8_lossy(&output.stdout);
+    assert!(script.contains("function my-tool { __tokf_wrap my-tool \"$@\"; }"));
+    assert!(script.contains("function git { __tokf_wrap git \"$@\"; }"));
+    assert!(!script.contains("function tokf "));
+}
+
+#[test]
+fn shell_init_script_runs_unfiltered_without_a_terminal() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let init = tokf()
+        .args(["shell-init", "bash"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let script = format!("{}\ngit --version", String::from_utf8_lossy(&init.stdout));
+    let Ok(output) = std::process::Command::new("bash")
+        .args(["-c", &script])
+        .current_dir(dir.path())
+        .output()
+    else {
+        return; // bash not installed
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("git version"));
+}
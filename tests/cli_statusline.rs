@@ -0,0 +1,43 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+/// `tokf statusline` with `TOKF_HOME` pointed at `home`, run from an empty `cwd`.
+fn statusline(home: &Path, cwd: &Path, extra: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_tokf"))
+        .env("TOKF_HOME", home)
+        .env_remove("TOKF_DB_PATH")
+        .current_dir(cwd)
+        .arg("statusline")
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "statusline must never fail");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn statusline_prints_one_line() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+    let line = statusline(home.path(), cwd.path(), &["--no-builtin"]);
+    assert_eq!(line.lines().count(), 1, "line: {line}");
+    assert!(line.contains("0 filters"), "line: {line}");
+    #[cfg(feature = "tracking")]
+    assert!(line.contains("0 tokens saved today"), "line: {line}");
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn statusline_reuses_the_cached_line() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+    let first = statusline(home.path(), cwd.path(), &["--no-builtin"]);
+    // Within the TTL the cached line wins, even though builtins are back on.
+    assert_eq!(statusline(home.path(), cwd.path(), &[]), first);
+    assert_ne!(statusline(home.path(), cwd.path(), &["--no-cache"]), first);
+    assert_ne!(statusline(home.path(), cwd.path(), &["--ttl", "0"]), first);
+}
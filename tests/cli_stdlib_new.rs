@@ -2,6 +2,7 @@
 
 use tokf::config::types::FilterConfig;
 use tokf::filter;
+use tokf::filter::diagnostics;
 use tokf::runner::CommandResult;
 
 fn load_config(path: &str) -> FilterConfig {
@@ -24,6 +25,8 @@ fn make_result(fixture: &str, exit_code: i32) -> CommandResult {
         stderr: String::new(),
         exit_code,
         combined: fixture.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
     }
 }
 
@@ -366,6 +369,31 @@ fn pytest_fail_shows_failure_context() {
     );
 }
 
+#[test]
+fn pytest_fail_keeps_parametrized_ids() {
+    let config = load_config("filters/pytest.toml");
+    let fixture = load_fixture("pytest/parametrized_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("test_parse[input1-expected1]\ntests/test_parse.py:12: in test_parse\n")
+    );
+    assert!(filtered.output.contains(
+        "\ntest_render[{'title': 'A very long title that goes on', 'body': 'lorem ipsum dol…\n"
+    ));
+    assert!(filtered.output.contains(
+        "FAILED tests/test_render.py::test_render[{'title': 'A very long title that goes on', \
+         'body': 'lorem i…] - AssertionError: assert False\n"
+    ));
+    assert!(
+        filtered
+            .output
+            .ends_with("stopping after 2 failures\n2 failed, 12 deselected in 0.31s")
+    );
+    assert!(!filtered.output.contains("\n\n\n"));
+}
+
 // --- tsc ---
 
 #[test]
@@ -408,6 +436,18 @@ fn tsc_errors_strips_found_n_errors_line() {
     );
 }
 
+#[test]
+fn tsc_errors_become_diagnostics() {
+    let config = load_config("filters/tsc.toml");
+    let found = diagnostics::collect(&config.diagnostic, &load_fixture("tsc/errors.txt"));
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[1].file.as_deref(), Some("src/utils.ts"));
+    assert_eq!((found[1].line, found[1].column), (Some(10), Some(5)));
+    assert_eq!(found[1].rule.as_deref(), Some("TS2305"));
+    assert_eq!(found[2].level, diagnostics::Level::Warning);
+    assert_eq!(found[2].message, "Object is possibly 'null'.");
+}
+
 // --- docker/ps ---
 
 #[test]
@@ -688,3 +728,1349 @@ fn prisma_generate_failure_shows_error() {
         filtered.output
     );
 }
+
+// --- terraform/plan ---
+
+#[test]
+fn terraform_plan_lists_resources_by_action() {
+    let config = load_config("filters/terraform/plan.toml");
+    let fixture = load_fixture("terraform/plan_changes.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "Plan: 3 to add, 1 to change, 2 to destroy.\n\
+         - aws_iam_role.old\n\
+         ~ aws_instance.web\n\
+         + aws_s3_bucket.logs\n\
+         + aws_s3_bucket_versioning.logs\n\
+         -/+ aws_security_group.web"
+    );
+}
+
+#[test]
+fn terraform_plan_no_changes_is_one_line() {
+    let config = load_config("filters/terraform/plan.toml");
+    let fixture = load_fixture("terraform/plan_no_changes.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ terraform plan: no changes");
+}
+
+#[test]
+fn terraform_plan_failure_keeps_error_blocks() {
+    let config = load_config("filters/terraform/plan.toml");
+    let fixture = load_fixture("terraform/plan_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with("✗ terraform plan failed\n"));
+    assert!(
+        filtered
+            .output
+            .contains("Error: Reference to undeclared input variable\n  on main.tf line 14"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.contains("Error: Unsupported argument"));
+    assert!(!filtered.output.contains('│') && !filtered.output.contains("Reading..."));
+}
+
+// --- terraform/apply ---
+
+#[test]
+fn terraform_apply_success_shows_result_and_outputs() {
+    let config = load_config("filters/terraform/apply.toml");
+    let fixture = load_fixture("terraform/apply_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "✓ Apply complete! Resources: 1 added, 1 changed, 1 destroyed.\n\
+         bucket_name = \"acme-logs\"\n\
+         instance_ip = \"10.0.1.17\""
+    );
+}
+
+#[test]
+fn terraform_apply_failure_counts_completed_operations() {
+    let config = load_config("filters/terraform/apply.toml");
+    let fixture = load_fixture("terraform/apply_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered.output.starts_with(
+            "✗ terraform apply failed (1 operations completed)\nError: creating S3 Bucket"
+        ),
+        "got: {}",
+        filtered.output
+    );
+    assert!(!filtered.output.contains("Still modifying"));
+}
+
+// --- eslint ---
+
+#[test]
+fn eslint_groups_problems_by_file_with_rule_counts() {
+    let config = load_config("filters/eslint.toml");
+    let fixture = load_fixture("eslint/errors.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("/home/dev/app/src/api/client.ts\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .contains("\n\n/home/dev/app/src/utils/format.ts\n")
+    );
+    assert!(filtered.output.ends_with(
+        "rules: @typescript-eslint/no-unused-vars (2), no-console (2), eqeqeq (1), \
+         react/prop-types (1)\n\
+         ✖ 6 problems (4 errors, 2 warnings)"
+    ));
+}
+
+#[test]
+fn eslint_caps_the_number_of_files() {
+    let config = load_config("filters/eslint.toml");
+    let fixture = load_fixture("eslint/many_files_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.contains("page10.tsx"));
+    assert!(!filtered.output.contains("page11.tsx"));
+    assert!(filtered.output.contains("  … 2 more\n"));
+    assert!(
+        filtered
+            .output
+            .contains("rules: semi (12), no-unused-vars (12)")
+    );
+}
+
+#[test]
+fn eslint_success_is_one_line() {
+    let config = load_config("filters/eslint.toml");
+    let filtered = filter::apply(&config, &make_result("", 0), &[]);
+    assert_eq!(filtered.output, "✓ eslint: no problems");
+
+    let fixture = load_fixture("eslint/warnings.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "✖ 2 problems (0 errors, 2 warnings) · no-console (2)"
+    );
+}
+
+// --- prettier ---
+
+#[test]
+fn prettier_check_lists_only_files_that_would_change() {
+    let config = load_config("filters/prettier.toml");
+    let fixture = load_fixture("prettier/check_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "src/api/client.ts\n\
+         src/components/Button.tsx\n\
+         README.md\n\
+         ✗ prettier: 3 files need formatting (run prettier --write)"
+    );
+}
+
+#[test]
+fn prettier_check_keeps_syntax_errors() {
+    let config = load_config("filters/prettier.toml");
+    let fixture = load_fixture("prettier/syntax_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 2), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("[error] src/broken.ts: SyntaxError: ';' expected. (3:14)\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.ends_with(
+        "src/api/client.ts\n✗ prettier: 1 files need formatting (run prettier --write)"
+    ));
+}
+
+#[test]
+fn prettier_success_is_one_line() {
+    let config = load_config("filters/prettier.toml");
+    let cases = [
+        (
+            "prettier/check_clean.txt",
+            "✓ prettier: all files formatted",
+        ),
+        (
+            "prettier/write_success.txt",
+            "✓ prettier: rewrote 3 files · README.md, src/api/client.ts, src/components/Button.tsx",
+        ),
+        (
+            "prettier/write_unchanged.txt",
+            "✓ prettier: nothing to change (3 files checked)",
+        ),
+    ];
+    for (fixture, expected) in cases {
+        let filtered = filter::apply(&config, &make_result(&load_fixture(fixture), 0), &[]);
+        assert_eq!(filtered.output, expected, "{fixture}");
+    }
+}
+
+// --- jest ---
+
+#[test]
+fn jest_pass_extracts_count() {
+    let config = load_config("filters/jest.toml");
+    let fixture = load_fixture("jest/pass.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ jest: 23 passed");
+}
+
+#[test]
+fn jest_fail_keeps_failing_tests_and_assertions() {
+    let config = load_config("filters/jest.toml");
+    let fixture = load_fixture("jest/fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered.output.starts_with(
+            "● Button › calls onClick when clicked\n  \
+             expect(jest.fn()).toHaveBeenCalledTimes(expected)\n  \
+             Expected number of calls: 1\n  \
+             Received number of calls: 0\n"
+        ),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.contains("  Received: \"Submit\"\n"));
+    assert!(
+        filtered.output.ends_with(
+            "Tests:       2 failed, 22 passed, 24 total\nSnapshots:   3 passed, 3 total"
+        )
+    );
+    for noise in ["PASS", "console", "● Console", "fetching", "at Object"] {
+        assert!(
+            !filtered.output.contains(noise),
+            "{noise} in {}",
+            filtered.output
+        );
+    }
+}
+
+#[test]
+fn jest_snapshot_mismatch_keeps_the_diff() {
+    let config = load_config("filters/jest.toml");
+    let fixture = load_fixture("jest/snapshot_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("  -   <h2>\n  +   <h3>\n  -   </h2>\n  +   </h3>\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .contains("Snapshots:   1 failed, 2 passed, 3 total")
+    );
+}
+
+// --- vitest ---
+
+#[test]
+fn vitest_pass_extracts_count() {
+    let config = load_config("filters/vitest.toml");
+    let fixture = load_fixture("vitest/pass.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ vitest: 14 passed");
+}
+
+#[test]
+fn vitest_fail_keeps_failing_tests_and_assertions() {
+    let config = load_config("filters/vitest.toml");
+    let fixture = load_fixture("vitest/fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "FAIL  src/components/Button.test.tsx > Button > calls onClick when clicked\n\
+         AssertionError: expected \"spy\" to be called 1 times, but got 0 times\n\
+         ❯ src/components/Button.test.tsx:20:21\n\
+         FAIL  src/components/Button.test.tsx > Button > renders the label\n\
+         AssertionError: expected 'Submit' to be 'Save' // Object.is equality\n\
+         - Expected\n\
+         + Received\n\
+         - Save\n\
+         + Submit\n\
+         ❯ src/components/Button.test.tsx:27:32\n\
+         \n\
+         Test Files  1 failed | 3 passed (4)\n\
+         Tests  2 failed | 13 passed (15)"
+    );
+}
+
+#[test]
+fn vitest_snapshot_mismatch_keeps_the_diff() {
+    let config = load_config("filters/vitest.toml");
+    let fixture = load_fixture("vitest/snapshot_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("Error: Snapshot `Card > matches snapshot 1` mismatched\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.contains("-   <h2>\n+   <h3>\n"));
+    assert!(filtered.output.contains("\nSnapshots  1 failed\n"));
+    assert!(!filtered.output.contains("expect(tree)"));
+}
+
+// --- docker/build ---
+
+#[test]
+fn docker_build_success_keeps_steps_and_digest() {
+    let config = load_config("filters/docker/build.toml");
+    let fixture = load_fixture("docker/build_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "FROM docker.io/library/node:20-alpine\n\
+         WORKDIR /app\n\
+         COPY package.json package-lock.json ./\n\
+         RUN npm ci --omit=dev\n\
+         COPY . .\n\
+         RUN npm run build\n\
+         ✓ built myapp:latest sha256:9f8e7d6c5b4a (2 cached)"
+    );
+}
+
+#[test]
+fn docker_build_failure_keeps_the_error_excerpt() {
+    let config = load_config("filters/docker/build.toml");
+    let fixture = load_fixture("docker/build_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with(" > [4/6] RUN npm ci --omit=dev:\n0.812 npm ERR! code EUSAGE\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .contains("   8 | >>> RUN npm ci --omit=dev\n")
+    );
+    assert!(filtered.output.ends_with("exit code: 1"));
+    assert!(!filtered.output.contains("#8 "));
+    assert!(!filtered.output.contains("------"));
+}
+
+// --- docker/compose-up ---
+
+#[test]
+fn docker_compose_up_keeps_final_states() {
+    let config = load_config("filters/docker/compose-up.toml");
+    let fixture = load_fixture("docker/compose-up_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "db Pulled\n\
+         Network app_default Created\n\
+         Volume \"app_pgdata\" Created\n\
+         Container app-db-1 Created\n\
+         Container app-web-1 Created\n\
+         Container app-db-1 Started\n\
+         Container app-db-1 Healthy\n\
+         Container app-web-1 Started"
+    );
+}
+
+#[test]
+fn docker_compose_up_failure_ends_with_the_error() {
+    let config = load_config("filters/docker/compose-up.toml");
+    let fixture = load_fixture("docker/compose-up_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered.output.starts_with("Network app_default Created\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.ends_with("port is already allocated"));
+    assert!(!filtered.output.contains("Creating"));
+    assert!(!filtered.output.contains("Starting"));
+}
+
+// --- kubectl/apply ---
+
+#[test]
+fn kubectl_apply_summarizes_changed_and_unchanged() {
+    let config = load_config("filters/kubectl/apply.toml");
+    let fixture = load_fixture("kubectl/apply_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "configmap/web-config configured\n\
+         deployment.apps/web configured\n\
+         horizontalpodautoscaler.autoscaling/web created\n\
+         ✓ kubectl apply: 3 changed, 4 unchanged"
+    );
+}
+
+#[test]
+fn kubectl_apply_failure_keeps_errors() {
+    let config = load_config("filters/kubectl/apply.toml");
+    let fixture = load_fixture("kubectl/apply_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("configmap/web-config configured\nThe Deployment \"web\" is invalid"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .ends_with("namespaces \"web-staging\" not found")
+    );
+    assert!(!filtered.output.contains("unchanged"));
+}
+
+// --- kubectl/describe ---
+
+#[test]
+fn kubectl_describe_drops_labels_and_routine_events() {
+    let config = load_config("filters/kubectl/describe.toml");
+    let fixture = load_fixture("kubectl/describe_pod.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    for dropped in [
+        "pod-template-hash",
+        "prometheus.io",
+        "Tolerations",
+        "NoExecute",
+        "Successfully assigned",
+        "Pulling",
+        "kubelet",
+    ] {
+        assert!(
+            !filtered.output.contains(dropped),
+            "{dropped} in {}",
+            filtered.output
+        );
+    }
+    assert!(
+        filtered
+            .output
+            .contains("      Reason:       ImagePullBackOff\n")
+    );
+    assert!(filtered.output.ends_with(
+        "Events:\n  \
+         Warning Failed (x4 over 5m10s): Failed to pull image \"acme/web:1.4.1\": rpc error: \
+         code = NotFound desc = failed to pull and unpack image \"docker.io/acme/web:1.4.1\": not found\n  \
+         Warning Failed (x4 over 5m10s): Error: ErrImagePull\n  \
+         Normal BackOff (x21 over 5m9s): Back-off pulling image \"acme/web:1.4.1\"\n  \
+         Warning Failed (x21 over 5m9s): Error: ImagePullBackOff"
+    ));
+}
+
+// --- kubectl/logs ---
+
+#[test]
+fn kubectl_logs_collapses_repeats_within_the_window() {
+    let config = load_config("filters/kubectl/logs.toml");
+    let fixture = load_fixture("kubectl/logs_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "starting web 1.4.1\n\
+         connecting to postgres://db:5432/app\n\
+         listening on :8080\n\
+         GET /healthz 200 1ms\n\
+         GET /api/users 200 18ms\n\
+         ERROR redis: connection refused (retrying in 1s)\n\
+         redis: connected\n\
+         POST /api/orders 201 42ms"
+    );
+}
+
+#[test]
+fn kubectl_logs_keeps_the_last_lines() {
+    let config = load_config("filters/kubectl/logs.toml");
+    let fixture: Vec<String> = (1..=150).map(|i| format!("request {i} done")).collect();
+    let filtered = filter::apply(&config, &make_result(&fixture.join("\n"), 0), &[]);
+    assert_eq!(filtered.output.lines().count(), 100);
+    assert!(filtered.output.starts_with("request 51 done\n"));
+}
+
+// --- mvn/test ---
+
+#[test]
+fn mvn_test_success_is_one_line() {
+    let config = load_config("filters/mvn/test.toml");
+    let fixture = load_fixture("mvn/test_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ BUILD SUCCESS · 10 tests (1 skipped)");
+}
+
+#[test]
+fn mvn_test_failure_keeps_stack_trace_heads() {
+    let config = load_config("filters/mvn/test.toml");
+    let fixture = load_fixture("mvn/test_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "✗ com.acme.web.OrderServiceTest.rejectsEmptyCart\n  \
+         org.opentest4j.AssertionFailedError: expected: <400> but was: <200>\n    \
+         at com.acme.web.OrderServiceTest.rejectsEmptyCart(OrderServiceTest.java:42)\n\
+         ✗ com.acme.web.OrderServiceTest.appliesDiscount\n  \
+         java.lang.NullPointerException: Cannot invoke \"com.acme.core.Money.times(int)\" because \"price\" is null\n    \
+         at com.acme.web.OrderService.total(OrderService.java:31)\n    \
+         at com.acme.web.OrderServiceTest.appliesDiscount(OrderServiceTest.java:57)\n\
+         acme-parent SUCCESS (0.210 s)\n\
+         core SUCCESS (3.101 s)\n\
+         web FAILURE (4.512 s)\n\
+         Tests run: 10, Failures: 1, Errors: 1, Skipped: 0\n\
+         ✗ BUILD FAILURE: web: There are test failures."
+    );
+}
+
+// --- mvn/package ---
+
+#[test]
+fn mvn_package_success_lists_artifacts() {
+    let config = load_config("filters/mvn/package.toml");
+    let fixture = load_fixture("mvn/package_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "/home/dev/web/target/web-1.4.0.jar\n✓ BUILD SUCCESS · 4 tests (0 skipped)"
+    );
+}
+
+#[test]
+fn mvn_package_compile_failure_lists_errors_once() {
+    let config = load_config("filters/mvn/package.toml");
+    let fixture = load_fixture("mvn/package_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(filtered.output.matches("cannot find symbol").count(), 1);
+    assert!(
+        filtered.output.starts_with(
+            "/home/dev/web/src/main/java/com/acme/web/OrderService.java:[31,22] cannot find symbol\n  \
+             symbol:   variable tax\n"
+        ),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .ends_with("✗ BUILD FAILURE: web: Compilation failure: Compilation failure:")
+    );
+    assert!(!filtered.output.contains("[Help 1]"));
+}
+
+// --- gradle/build ---
+
+#[test]
+fn gradle_build_success_is_one_line() {
+    let config = load_config("filters/gradle/build.toml");
+    let fixture = load_fixture("gradle/build_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ BUILD SUCCESSFUL in 14s");
+}
+
+#[test]
+fn gradle_build_failure_keeps_failing_tests() {
+    let config = load_config("filters/gradle/build.toml");
+    let fixture = load_fixture("gradle/build_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "✗ OrderServiceTest > rejectsEmptyCart()\n    \
+         org.opentest4j.AssertionFailedError: expected: <400> but was: <200>\n      \
+         at com.acme.web.OrderServiceTest.rejectsEmptyCart(OrderServiceTest.java:42)\n\
+         ✗ OrderServiceTest > appliesDiscount()\n    \
+         java.lang.NullPointerException at OrderServiceTest.java:57\n\
+         10 tests completed, 2 failed\n\
+         Execution failed for task ':test'.\n\
+         > There were failing tests. See the report at: \
+         file:///home/dev/web/build/reports/tests/test/index.html\n\
+         ✗ BUILD FAILED in 6s"
+    );
+}
+
+#[test]
+fn gradle_build_compile_error_keeps_the_error() {
+    let config = load_config("filters/gradle/build.toml");
+    let fixture = load_fixture("gradle/build_compile_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered.output.starts_with(
+            "/home/dev/web/src/main/java/com/acme/web/OrderService.java:31: error: cannot find symbol\n"
+        ),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .contains("Execution failed for task ':compileJava'.")
+    );
+    assert!(!filtered.output.contains("--scan"));
+}
+
+// --- pip/install ---
+
+#[test]
+fn pip_install_counts_packages_and_keeps_conflicts() {
+    let config = load_config("filters/pip/install.toml");
+    let fixture = load_fixture("pip/install_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "⚠ botocore 1.29.0 requires urllib3<1.27,>=1.25.4, but you have urllib3 2.2.1 \
+         which is incompatible.\n\
+         ✓ pip: installed 3 packages (3 already satisfied)"
+    );
+}
+
+#[test]
+fn pip_install_all_satisfied_is_one_line() {
+    let config = load_config("filters/pip/install.toml");
+    let fixture = load_fixture("pip/install_satisfied.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "✓ pip: nothing to install (6 already satisfied)"
+    );
+}
+
+#[test]
+fn pip_install_failure_drops_download_noise() {
+    let config = load_config("filters/pip/install.toml");
+    let fixture = load_fixture("pip/install_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered.output.starts_with("ERROR: Cannot install"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(
+        filtered
+            .output
+            .contains("botocore 1.29.0 depends on urllib3<1.27")
+    );
+    assert!(!filtered.output.contains("[notice]"));
+
+    let fixture = load_fixture("pip/install_build_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("Error: pg_config executable not found.")
+    );
+    assert!(!filtered.output.contains("Requirement already satisfied"));
+    assert!(!filtered.output.contains("━"));
+}
+
+// --- poetry/install ---
+
+#[test]
+fn poetry_install_success_is_one_line() {
+    let config = load_config("filters/poetry/install.toml");
+    for (fixture, expected) in [
+        (
+            "poetry/install_success.txt",
+            "✓ poetry: 3 installs, 1 update, 0 removals",
+        ),
+        (
+            "poetry/install_up_to_date.txt",
+            "✓ poetry: dependencies up to date",
+        ),
+    ] {
+        let filtered = filter::apply(&config, &make_result(&load_fixture(fixture), 0), &[]);
+        assert_eq!(filtered.output, expected, "{fixture}");
+    }
+}
+
+#[test]
+fn poetry_install_build_error_keeps_the_build_output() {
+    let config = load_config("filters/poetry/install.toml");
+    let fixture = load_fixture("poetry/install_build_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("  - Installing psycopg2 (2.9.9): Failed\n")
+    );
+    assert!(
+        filtered
+            .output
+            .contains("Error: pg_config executable not found.")
+    );
+    assert!(!filtered.output.contains("Installing requests"));
+    assert!(!filtered.output.contains("chef.py"));
+    assert!(!filtered.output.contains("│"));
+}
+
+// --- uv ---
+
+#[test]
+fn uv_sync_success_is_one_line() {
+    let config = load_config("filters/uv.toml");
+    for (fixture, expected) in [
+        (
+            "uv/sync_success.txt",
+            "✓ uv: 42 resolved, 5 packages installed, 1 package uninstalled",
+        ),
+        (
+            "uv/sync_up_to_date.txt",
+            "✓ uv: 42 resolved, nothing to install",
+        ),
+    ] {
+        let filtered = filter::apply(&config, &make_result(&load_fixture(fixture), 0), &[]);
+        assert_eq!(filtered.output, expected, "{fixture}");
+    }
+}
+
+#[test]
+fn uv_failure_keeps_the_resolver_error() {
+    let config = load_config("filters/uv.toml");
+    let fixture = load_fixture("uv/sync_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("No solution found when resolving dependencies")
+    );
+    assert!(filtered.output.contains("requirements are unsatisfiable."));
+
+    let fixture = load_fixture("uv/pip_install_build_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("error: Failed to prepare distributions\n"),
+        "got: {}",
+        filtered.output
+    );
+}
+
+// --- git/fetch ---
+
+#[test]
+fn git_fetch_compacts_updated_refs() {
+    let config = load_config("filters/git/fetch.toml");
+    let fixture = load_fixture("git/fetch_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "From github.com:acme/web\n  \
+         3f2a1b4..9c8d7e6 main -> origin/main\n  \
+         [new branch] feature/login -> origin/feature/login\n  \
+         1a2b3c4...5d6e7f8 wip -> origin/wip  (forced update)\n  \
+         [new tag] v1.4.0 -> v1.4.0\n\
+         ✓ git fetch: 4 updated"
+    );
+}
+
+#[test]
+fn git_fetch_nothing_new_is_up_to_date() {
+    let config = load_config("filters/git/fetch.toml");
+    let filtered = filter::apply(&config, &make_result("", 0), &[]);
+    assert_eq!(filtered.output, "✓ git fetch: up to date");
+}
+
+// --- git/pull ---
+
+#[test]
+fn git_pull_fast_forward_is_one_line() {
+    let config = load_config("filters/git/pull.toml");
+    let fixture = load_fixture("git/pull_fast_forward.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "✓ git pull: fast-forward 3f2a1b4..9c8d7e6, 3 files changed, 50 insertions(+), 5 deletions(-)"
+    );
+}
+
+#[test]
+fn git_pull_conflict_lists_only_conflicted_files() {
+    let config = load_config("filters/git/pull.toml");
+    let fixture = load_fixture("git/pull_conflict_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "✗ Automatic merge failed; fix conflicts and then commit the result.\n  \
+         src/app.rs (content)\n  \
+         src/old.rs (modify/delete)"
+    );
+}
+
+#[test]
+fn git_pull_other_failures_drop_the_advice() {
+    let config = load_config("filters/git/pull.toml");
+    let fixture = load_fixture("git/pull_divergent_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 128), &[]);
+    assert_eq!(
+        filtered.output,
+        "fatal: Need to specify how to reconcile divergent branches."
+    );
+}
+
+// --- git/rebase ---
+
+#[test]
+fn git_rebase_success_shows_branch_and_progress() {
+    let config = load_config("filters/git/rebase.toml");
+    let fixture = load_fixture("git/rebase_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ git rebase: feature/login (3/3)");
+}
+
+#[test]
+fn git_rebase_conflict_keeps_files_and_next_commands() {
+    let config = load_config("filters/git/rebase.toml");
+    let fixture = load_fixture("git/rebase_conflict_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "✗ could not apply 5d6e7f8... Add login form\n  \
+         src/app.rs (content)\n\
+         next: git rebase --continue | git rebase --skip | git rebase --abort"
+    );
+}
+
+// --- cargo/update ---
+
+#[test]
+fn cargo_update_lists_version_changes_and_keeps_warnings() {
+    let config = load_config("filters/cargo/update.toml");
+    let fixture = load_fixture("cargo/update_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(filtered.output.starts_with("  anyhow 1.0.79 → 1.0.86\n"));
+    assert!(
+        filtered
+            .output
+            .contains("\n+ clap_lex 0.7.0\n- clap_lex 0.6.0\n")
+    );
+    assert!(
+        filtered
+            .output
+            .contains("  tokio 1.36.0 → 1.37.0 (available: v1.38.0, requires Rust 1.80)\n")
+    );
+    assert!(
+        filtered
+            .output
+            .contains("warning: package `time v0.3.30` in Cargo.lock is yanked")
+    );
+    assert!(!filtered.output.contains("crates.io index"));
+    assert!(
+        filtered
+            .output
+            .ends_with("✓ cargo update: 4 updated, 1 added, 1 removed")
+    );
+}
+
+#[test]
+fn cargo_update_without_changes_is_one_line() {
+    let config = load_config("filters/cargo/update.toml");
+    let fixture = load_fixture("cargo/update_up_to_date.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ cargo update: lock file up to date");
+}
+
+// --- cargo/add ---
+
+#[test]
+fn cargo_add_keeps_only_the_added_dependencies() {
+    let config = load_config("filters/cargo/add.toml");
+    let fixture = load_fixture("cargo/add_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "+ serde 1.0.203 (dependencies)\n\
+         + tokio 1.38.0 (dev-dependencies)\n\
+         warning: translating `serde-json` to `serde_json`\n\
+         + serde_json 1.0.117 (dependencies)"
+    );
+}
+
+// --- rustup/update ---
+
+#[test]
+fn rustup_update_is_one_line_per_toolchain() {
+    let config = load_config("filters/rustup/update.toml");
+    let fixture = load_fixture("rustup/update_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "warning: Force-skipping unavailable component 'miri-x86_64-unknown-linux-gnu'\n  \
+         stable: 1.78.0 → 1.79.0\n  \
+         nightly: unchanged (1.81.0-nightly)"
+    );
+}
+
+#[test]
+fn rustup_update_failure_keeps_the_error() {
+    let config = load_config("filters/rustup/update.toml");
+    let fixture = load_fixture("rustup/update_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with("error: component 'rls'"));
+    assert!(!filtered.output.contains("info:"));
+}
+
+// --- make ---
+
+#[test]
+fn make_success_groups_warnings_and_counts_targets() {
+    let config = load_config("filters/make.toml");
+    let fixture = load_fixture("make/build_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "main.c\n  \
+         12:9: warning: unused variable 'x' [-Wunused-variable]\n  \
+         30:5: warning: implicit declaration of function 'helper' \
+         [-Wimplicit-function-declaration]\n\
+         util.c\n  \
+         40:12: warning: comparison of integer expressions of different signedness: \
+         'int' and 'size_t' [-Wsign-compare]\n\
+         ✓ make: 4 targets built"
+    );
+}
+
+#[test]
+fn make_failure_keeps_diagnostics_and_failed_rules() {
+    let config = load_config("filters/make.toml");
+    let fixture = load_fixture("make/build_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 2), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("util.c\n  30:5: error: 'foo' undeclared")
+    );
+    assert!(
+        filtered
+            .output
+            .ends_with("make: *** [Makefile:5: all] Error 2")
+    );
+    assert!(!filtered.output.contains("Entering directory"));
+    assert!(!filtered.output.contains("gcc -Wall"));
+
+    let fixture = load_fixture("make/link_error.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 2), &[]);
+    assert!(filtered.output.contains("undefined reference to `helper'"));
+    assert!(!filtered.output.contains("gcc -o app"));
+}
+
+#[test]
+fn make_failure_without_compiler_errors_shows_the_tail() {
+    let config = load_config("filters/make.toml");
+    let fixture = load_fixture("make/test_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 2), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("test_init ... FAILED\n  expected 3, got 4")
+    );
+}
+
+// --- cmake/build ---
+
+#[test]
+fn cmake_build_success_counts_targets() {
+    let config = load_config("filters/cmake/build.toml");
+    for fixture in ["cmake/build_success.txt", "cmake/build_ninja_success.txt"] {
+        let filtered = filter::apply(&config, &make_result(&load_fixture(fixture), 0), &[]);
+        assert!(
+            filtered.output.ends_with("✓ cmake --build: 2 targets"),
+            "{fixture}: {}",
+            filtered.output
+        );
+        assert!(!filtered.output.contains("Building C object"));
+    }
+}
+
+#[test]
+fn cmake_build_ninja_failure_names_the_failed_step() {
+    let config = load_config("filters/cmake/build.toml");
+    let fixture = load_fixture("cmake/build_ninja_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "/home/ci/proj/src/util.c\n  \
+         30:5: error: 'foo' undeclared (first use in this function)\n\
+         FAILED: CMakeFiles/util.dir/src/util.c.o\n\
+         ninja: build stopped: subcommand failed."
+    );
+}
+
+// --- aws/s3 ---
+
+#[test]
+fn aws_s3_sync_lists_transfers_without_progress() {
+    let config = load_config("filters/aws/s3.toml");
+    let fixture = load_fixture("aws/s3_sync_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("dist/app.js → s3://acme-web/app.js\n"),
+        "got: {}",
+        filtered.output
+    );
+    assert!(filtered.output.contains("\ndeleted s3://acme-web/old.js\n"));
+    assert!(!filtered.output.contains("Completed"));
+    assert!(
+        filtered
+            .output
+            .ends_with("✓ aws s3: upload (5), delete (1)")
+    );
+}
+
+#[test]
+fn aws_s3_long_listings_are_capped() {
+    let config = load_config("filters/aws/s3.toml");
+    let fixture = load_fixture("aws/s3_cp_many_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output.lines().count(), 22);
+    assert!(
+        filtered
+            .output
+            .ends_with("… 10 more\n✓ aws s3: download (30)")
+    );
+}
+
+#[test]
+fn aws_s3_failure_keeps_only_failed_transfers() {
+    let config = load_config("filters/aws/s3.toml");
+    let fixture = load_fixture("aws/s3_cp_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("upload failed: dist/index.html to s3://acme-web/index.html")
+    );
+    assert_eq!(filtered.output.lines().count(), 1);
+}
+
+// --- aws/ec2-describe-instances ---
+
+#[test]
+fn aws_ec2_describe_instances_is_one_row_per_instance() {
+    let config = load_config("filters/aws/ec2-describe-instances.toml");
+    let fixture = load_fixture("aws/ec2-describe-instances_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "i-0a1b2c3d4e5f60001  running  t3.micro  10.0.1.12  web-1\n\
+         i-0a1b2c3d4e5f60002  running  t3.small  10.0.1.13  web-2\n\
+         i-0a1b2c3d4e5f60003  stopped  m5.large  -  -\n\
+         ✓ 3 instances: running (2), stopped (1)"
+    );
+}
+
+#[test]
+fn aws_ec2_describe_instances_asks_for_text_rows() {
+    let config = load_config("filters/aws/ec2-describe-instances.toml");
+    let run = config.run.as_deref().unwrap_or_default();
+    assert!(run.contains("--output text --query"), "{run}");
+    assert!(run.ends_with("{args}"), "{run}");
+}
+
+// --- aws/logs-tail ---
+
+#[test]
+fn aws_logs_tail_drops_stream_names_and_invocation_noise() {
+    let config = load_config("filters/aws/logs-tail.toml");
+    let fixture = load_fixture("aws/logs-tail_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "10:15:02 INFO processing order 1042\n\
+         10:15:02 ERROR payment declined: card_expired\n\
+         10:15:02 REPORT 182.41 ms, 71 MB"
+    );
+}
+
+// --- npm/install ---
+
+#[test]
+fn npm_install_keeps_deprecations_and_vulnerabilities() {
+    let config = load_config("filters/npm/install.toml");
+    let fixture = load_fixture("npm/install_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    let lines: Vec<&str> = filtered.output.lines().collect();
+    assert_eq!(lines.len(), 5, "got: {}", filtered.output);
+    assert!(lines[1].starts_with("npm warn deprecated glob@7.2.3"));
+    assert_eq!(lines[3], "3 vulnerabilities (1 moderate, 2 high)");
+    assert_eq!(
+        lines[4],
+        "✓ npm: added 312 packages, removed 4 packages, changed 2 packages, \
+         and audited 845 packages in 12s"
+    );
+}
+
+#[test]
+fn npm_install_up_to_date_is_one_line() {
+    let config = load_config("filters/npm/install.toml");
+    let fixture = load_fixture("npm/install_up_to_date.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "✓ npm: up to date, audited 845 packages in 2s"
+    );
+}
+
+#[test]
+fn npm_install_failure_keeps_the_error_block() {
+    let config = load_config("filters/npm/install.toml");
+    let fixture = load_fixture("npm/install_eresolve_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with("npm error code ERESOLVE\n"));
+    assert!(
+        filtered
+            .output
+            .contains("npm error peer react@\"^18.0.0\" from react-dom@18.2.0\n")
+    );
+    assert!(!filtered.output.contains("A complete log"));
+
+    let fixture = load_fixture("npm/install_gyp_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .contains("fatal error: node.h: No such file or directory")
+    );
+    assert!(filtered.output.contains("gyp ERR! build error"));
+    assert!(!filtered.output.contains("gyp info"));
+    assert!(!filtered.output.contains("Entering directory"));
+}
+
+// --- yarn/install ---
+
+#[test]
+fn yarn_install_keeps_peer_warnings_and_duration() {
+    let config = load_config("filters/yarn/install.toml");
+    let fixture = load_fixture("yarn/install_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "warning \" > react-dom@18.2.0\" has unmet peer dependency \"react@^18.2.0\".\n\
+         warning \"eslint-config-next > @typescript-eslint/parser@5.62.0\" has incorrect \
+         peer dependency \"typescript@>=4.7\".\n\
+         ✓ yarn install: done in 12.34s"
+    );
+
+    let fixture = load_fixture("yarn/install_berry_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(filtered.output.starts_with("➤ YN0002: │ web@workspace:."));
+    assert!(
+        filtered
+            .output
+            .ends_with("\n✓ yarn install: done with warnings in 4s 515ms")
+    );
+}
+
+#[test]
+fn yarn_install_build_failure_keeps_the_build_output() {
+    let config = load_config("filters/yarn/install.toml");
+    let fixture = load_fixture("yarn/install_build_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("error /home/ci/web/node_modules/bcrypt: Command failed.\n")
+    );
+    assert!(filtered.output.contains("fatal error: node.h"));
+    assert!(!filtered.output.contains("[4/4]"));
+    assert!(!filtered.output.contains("info Visit"));
+}
+
+// --- tox ---
+
+#[test]
+fn tox_pass_shows_env_results() {
+    let config = load_config("filters/tox.toml");
+    let fixture = load_fixture("tox/pass.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(
+        filtered.output,
+        "py311: OK (6.12s)\nlint: OK (2.03s)\ncongratulations :) (8.21 seconds)"
+    );
+}
+
+#[test]
+fn tox_fail_keeps_only_the_failing_env_output() {
+    let config = load_config("filters/tox.toml");
+    let fixture = load_fixture("tox/env_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with(".....F......"));
+    assert!(filtered.output.contains(
+        "1 failed, 11 passed in 0.44s\n\
+         py312: exit 1 (0.52 seconds) /home/ci/proj> pytest -q pid=48213\n\n\
+         py311: OK (6.12s)\npy312: FAIL code 1 (5.87s)\n"
+    ));
+    assert!(!filtered.output.contains("12 passed in 0.41s"));
+    assert!(!filtered.output.contains("All checks passed!"));
+    assert!(!filtered.output.contains("install_package"));
+
+    let fixture = load_fixture("tox/tox3_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with("...........F"));
+    assert!(
+        filtered
+            .output
+            .ends_with("\n\npy38: commands succeeded\npy39: commands failed")
+    );
+    assert!(!filtered.output.contains("12 passed"));
+    assert!(!filtered.output.contains("summary"));
+}
+
+// --- ruff/check ---
+
+#[test]
+fn ruff_check_clean_and_fixed() {
+    let config = load_config("filters/ruff/check.toml");
+    let fixture = load_fixture("ruff/check_pass.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "✓ ruff: all checks passed");
+
+    let fixture = load_fixture("ruff/check_fix_applied.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert_eq!(filtered.output, "Found 3 errors (3 fixed, 0 remaining).");
+}
+
+#[test]
+fn ruff_check_counts_rules_and_files() {
+    let config = load_config("filters/ruff/check.toml");
+    let fixture = load_fixture("ruff/check_concise_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "rules: F401 (3), E741 (1), I001 (1), B011 (1)\n\
+         files: src/app/main.py (3), src/app/util.py (2), tests/test_main.py (1)\n\
+         Found 6 errors.\n\
+         [*] 4 fixable with the `--fix` option."
+    );
+
+    let fixture = load_fixture("ruff/check_full_fail.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.starts_with(
+        "rules: F401 (2), E741 (1)\nfiles: src/app/main.py (2), src/app/util.py (1)\n"
+    ));
+}
+
+#[test]
+fn ruff_check_concise_violations_become_diagnostics() {
+    let config = load_config("filters/ruff/check.toml");
+    let fixture = load_fixture("ruff/check_concise_fail.txt");
+    let found = diagnostics::collect(&config.diagnostic, &fixture);
+    assert_eq!(found.len(), 6);
+    assert_eq!(found[0].rule.as_deref(), Some("F401"));
+    assert_eq!(found[0].message, "`os` imported but unused");
+    assert!(found.iter().all(|d| d.level == diagnostics::Level::Warning));
+}
+
+// --- gh/run-view, gh/run-watch ---
+
+#[test]
+fn gh_run_view_keeps_failed_jobs_and_steps() {
+    let config = load_config("filters/gh/run-view.toml");
+    let fixture = load_fixture("gh/run-view_failed_run.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("X main CI · 9876543210\nJOBS\n")
+    );
+    assert!(filtered.output.contains(
+        "X test (ubuntu-latest) in 2m10s (ID 27143110002)\n  X Run tests\n- test (macos-latest)"
+    ));
+    assert!(
+        filtered
+            .output
+            .contains("ANNOTATIONS\nX Process completed with exit code 101.\n")
+    );
+    assert!(!filtered.output.contains("Set up job"));
+    assert!(!filtered.output.contains("Triggered via"));
+    assert!(!filtered.output.contains("View this run on GitHub"));
+}
+
+#[test]
+fn gh_run_view_log_tails_each_step_without_timestamps() {
+    let config = load_config("filters/gh/run-view.toml");
+    let fixture = load_fixture("gh/run-view_log_failed.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(
+        filtered
+            .output
+            .starts_with("test (ubuntu-latest) › Run tests\n  … 29 earlier\n")
+    );
+    assert!(filtered.output.contains(
+        "  error: test failed, to rerun pass `--lib`\n  \
+         error: Process completed with exit code 101.\n\
+         test (ubuntu-latest) › Post Run actions/checkout@v4\n"
+    ));
+    assert!(!filtered.output.contains("2024-05-01T"));
+    assert!(!filtered.output.contains("##["));
+    assert!(!filtered.output.contains("crate24 "));
+}
+
+#[test]
+fn gh_run_watch_keeps_the_last_snapshot() {
+    let config = load_config("filters/gh/run-watch.toml");
+    let fixture = load_fixture("gh/run-watch_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert_eq!(
+        filtered.output,
+        "X main CI · 9876543210\nJOBS\n✓ lint in 32s (ID 27143110001)\n\
+         X test (ubuntu-latest) in 2m10s (ID 27143110002)\n  X Run tests\n\
+         ANNOTATIONS\nX Process completed with exit code 101.\n\
+         test (ubuntu-latest): .github#12\n\
+         X Run CI (9876543210) completed with 'failure'"
+    );
+
+    let fixture = load_fixture("gh/run-watch_success.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(
+        filtered
+            .output
+            .ends_with("\n✓ Run CI (9876543210) completed with 'success'")
+    );
+    assert!(!filtered.output.contains("Refreshing"));
+    assert!(!filtered.output.contains("* "));
+}
+
+// --- [stacktrace] in npm/run, kubectl/logs ---
+
+#[test]
+fn npm_run_crash_keeps_project_frames() {
+    let config = load_config("filters/npm/run.toml");
+    let fixture = load_fixture("npm/run_crash_failure.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 1), &[]);
+    assert!(filtered.output.contains(
+        "TypeError: Cannot read properties of undefined (reading 'id')\n  \
+         at getProfileId (/app/src/routes/user.js:12:25)\n  \
+         at handler (/app/src/routes/user.js:30:18)\n  … 8 more frames\n"
+    ));
+    assert!(!filtered.output.contains("node_modules"));
+    assert!(
+        filtered
+            .output
+            .ends_with("npm error command sh -c node src/server.js")
+    );
+}
+
+#[test]
+fn kubectl_logs_condenses_python_traceback() {
+    let config = load_config("filters/kubectl/logs.toml");
+    let fixture = load_fixture("kubectl/logs_traceback.txt");
+    let filtered = filter::apply(&config, &make_result(&fixture, 0), &[]);
+    assert!(filtered.output.ends_with(
+        "ERROR [app] Exception on /orders/42 [GET]\n\
+         psycopg.errors.UndefinedColumn: column \"archived_at\" does not exist\n  \
+         /srv/app/orders/store.py:54 in load_order\n  \
+         /srv/app/orders/views.py:27 in show\n  … 5 more frames"
+    ));
+    assert!(!filtered.output.contains("site-packages"));
+}
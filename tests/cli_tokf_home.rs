@@ -0,0 +1,128 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+/// `tokf` with `TOKF_HOME` pointed at `home`, run from an empty `cwd`.
+fn tokf(home: &Path, cwd: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
+    cmd.env("TOKF_HOME", home)
+        .env_remove("TOKF_DB_PATH")
+        .current_dir(cwd);
+    cmd
+}
+
+#[test]
+fn tokf_home_supplies_user_filters() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+    std::fs::create_dir_all(home.path().join("filters")).unwrap();
+    std::fs::write(
+        home.path().join("filters/ci-tool.toml"),
+        "command = \"ci-tool\"",
+    )
+    .unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["which", "ci-tool"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ci-tool"), "stdout: {stdout}");
+}
+
+#[cfg(feature = "tracking")]
+#[test]
+fn tokf_home_holds_cache_and_tracking_db() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["run", "echo", "hi"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(home.path().join("tracking.db").exists());
+    assert!(home.path().join("cache/manifest.bin").exists());
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["cache", "info"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = home.path().join("cache/manifest.bin");
+    assert!(
+        stdout.contains(&*expected.to_string_lossy()),
+        "stdout: {stdout}"
+    );
+}
+
+#[test]
+fn tokf_home_global_hook_install() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .env("HOME", cwd.path())
+        .args(["hook", "install", "--global"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(home.path().join("hooks/pre-tool-use.sh").exists());
+}
+
+#[test]
+fn tokf_home_settings_apply() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+    std::fs::write(
+        home.path().join("config.toml"),
+        "use_builtin_filters = false",
+    )
+    .unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["which", "git status"])
+        .output()
+        .unwrap();
+    assert!(
+        !output.status.success(),
+        "builtin filter should be disabled"
+    );
+}
+
+#[test]
+fn update_without_packs_is_a_no_op() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["update"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no packs installed"), "stderr: {stderr}");
+}
+
+#[test]
+fn install_rejects_invalid_pack_name() {
+    let home = TempDir::new().unwrap();
+    let cwd = TempDir::new().unwrap();
+
+    let output = tokf(home.path(), cwd.path())
+        .args(["install", "https://example.com/x.git", "--name", "../x"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid pack name"), "stderr: {stderr}");
+    assert!(!home.path().join("filters").exists());
+}
@@ -1,6 +1,10 @@
+#![cfg(feature = "tracking")]
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::path::Path;
 use std::process::Command;
 use tempfile::TempDir;
+use tokf::tracking;
 
 fn tokf_with_db(db_path: &Path) -> Command {
     let mut cmd = Command::new(env!("CARGO_BIN_EXE_tokf"));
@@ -113,6 +117,37 @@ fn gain_daily_json_is_array() {
     assert!(parsed.is_array(), "expected array, got: {parsed}");
 }
 
+#[test]
+fn gain_daily_buckets_in_local_time_unless_utc() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hello"])
+        .output()
+        .expect("run");
+    {
+        let conn = tracking::open_db(&db).expect("open");
+        conn.execute(
+            "UPDATE events SET timestamp = '2025-03-01T02:00:00Z', unix_time = 1740794400",
+            [],
+        )
+        .expect("set time");
+    }
+    let day = |extra: &[&str]| {
+        let out = tokf_with_db(&db)
+            .env("TZ", "EST5")
+            .args(["gain", "--daily", "--json"])
+            .args(extra)
+            .output()
+            .expect("gain daily");
+        let parsed: serde_json::Value = serde_json::from_slice(&out.stdout).expect("valid JSON");
+        parsed[0]["date"].as_str().unwrap_or_default().to_string()
+    };
+    assert_eq!(day(&[]), "2025-02-28");
+    assert_eq!(day(&["--utc"]), "2025-03-01");
+    assert_eq!(day(&["--since", "2025-03-01"]), "");
+}
+
 #[test]
 fn gain_by_filter_shows_filter_name() {
     let dir = temp_db_dir();
@@ -141,7 +176,6 @@ fn gain_tokens_saved_positive_after_filtered_run() {
     // We'll synthesize a high-byte input via a longer string to guarantee savings.
     // Actually we just verify the JSON structure; savings can be 0 for passthrough.
     // Insert via the DB directly so we control the data.
-    use tokf::tracking;
     let path = db.clone();
     let conn = tracking::open_db(&path).expect("open");
     let ev = tracking::build_event("git status", Some("git status"), 4000, 400, 5, 0);
@@ -189,3 +223,191 @@ fn gain_by_filter_json_output_is_array() {
     let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
     assert!(parsed.is_array(), "expected array, got: {parsed}");
 }
+
+#[test]
+fn gain_monthly_text_output() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hi"])
+        .output()
+        .expect("run");
+    let out = tokf_with_db(&db)
+        .args(["gain", "--monthly"])
+        .output()
+        .expect("gain monthly");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success());
+    assert!(stdout.contains("tokf gain monthly"), "stdout: {stdout}");
+    assert!(stdout.contains("runs:    1"), "stdout: {stdout}");
+}
+
+#[test]
+fn gain_breakdown_flags_are_exclusive() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    let out = tokf_with_db(&db)
+        .args(["gain", "--daily", "--weekly"])
+        .output()
+        .expect("gain");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("cannot be used with"), "stderr: {stderr}");
+}
+
+#[test]
+fn gain_until_before_any_run_counts_nothing() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hi"])
+        .output()
+        .expect("run");
+    let out = tokf_with_db(&db)
+        .args(["gain", "--until", "2000-01-01", "--json"])
+        .output()
+        .expect("gain until");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(parsed["total_commands"], 0);
+}
+
+#[test]
+fn gain_rejects_malformed_date() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    let out = tokf_with_db(&db)
+        .args(["gain", "--since", "last week"])
+        .output()
+        .expect("gain since");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("YYYY-MM-DD"), "stderr: {stderr}");
+}
+
+#[test]
+fn gain_export_csv_and_jsonl() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hi"])
+        .output()
+        .expect("run");
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "export", "--format", "csv"])
+        .output()
+        .expect("gain export csv");
+    assert!(out.status.success());
+    let csv = String::from_utf8_lossy(&out.stdout);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2, "csv: {csv}");
+    assert!(lines[0].starts_with("schema_version,id,timestamp,command"));
+    assert!(lines[1].contains(",echo hi,"), "csv: {csv}");
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "export"])
+        .output()
+        .expect("gain export jsonl");
+    assert!(out.status.success());
+    let jsonl = String::from_utf8_lossy(&out.stdout);
+    let row: serde_json::Value = serde_json::from_str(jsonl.trim()).expect("one JSON row");
+    assert_eq!(row["schema_version"], 1);
+    assert_eq!(row["command"], "echo hi");
+}
+
+#[test]
+fn gain_export_applies_date_range() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hi"])
+        .output()
+        .expect("run");
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "--until", "2000-01-01", "export"])
+        .output()
+        .expect("gain export");
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "");
+}
+
+#[test]
+fn run_does_not_record_env_assignments() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "API_TOKEN=hunter2", "echo", "hi"])
+        .output()
+        .expect("run");
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "export"])
+        .output()
+        .expect("gain export");
+    let jsonl = String::from_utf8_lossy(&out.stdout);
+    assert!(!jsonl.contains("hunter2"), "jsonl: {jsonl}");
+    let row: serde_json::Value = serde_json::from_str(jsonl.trim()).expect("one JSON row");
+    assert_eq!(row["command"], "echo hi");
+}
+
+#[test]
+fn gain_prune_removes_old_events() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    tokf_with_db(&db)
+        .args(["run", "echo", "hi"])
+        .output()
+        .expect("run");
+    {
+        let conn = tracking::open_db(&db).expect("open");
+        conn.execute("UPDATE events SET timestamp = '2000-01-01T00:00:00Z'", [])
+            .expect("age events");
+    }
+    let out = tokf_with_db(&db)
+        .args(["gain", "prune", "--keep-days", "30"])
+        .output()
+        .expect("gain prune");
+    assert!(out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("pruned 1 events"), "stderr: {stderr}");
+
+    let conn = tracking::open_db(&db).expect("open");
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM events", [], |r| r.get(0))
+        .expect("count");
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn gain_by_subcommand_groups_passthrough_runs() {
+    let dir = temp_db_dir();
+    let db = dir.path().join("tracking.db");
+    for args in [["echo", "one"], ["echo", "two"]] {
+        tokf_with_db(&db)
+            .arg("run")
+            .args(args)
+            .output()
+            .expect("run");
+    }
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "--by-filter", "--by-subcommand", "--json"])
+        .output()
+        .expect("gain by subcommand");
+    assert!(out.status.success());
+    let rows: serde_json::Value = serde_json::from_slice(&out.stdout).expect("json");
+    let rows = rows.as_array().expect("array");
+    let mut commands: Vec<&str> = rows.iter().filter_map(|r| r["command"].as_str()).collect();
+    commands.sort_unstable();
+    assert_eq!(commands, ["echo one", "echo two"], "rows: {rows:?}");
+    assert!(rows.iter().all(|r| r["filter_name"] == "passthrough"));
+
+    let out = tokf_with_db(&db)
+        .args(["gain", "--by-subcommand"])
+        .output()
+        .expect("gain without --by-filter");
+    assert!(!out.status.success());
+}
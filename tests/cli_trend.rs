@@ -0,0 +1,79 @@
+#![cfg(feature = "tracking")]
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+const FILTER: &str = r#"command = "echo"
+
+[trend]
+label = "failures"
+pattern = '^(\d+) failed'
+"#;
+
+struct Env {
+    home: TempDir,
+    cwd: TempDir,
+}
+
+impl Env {
+    fn new(settings: &str) -> Self {
+        let home = TempDir::new().unwrap();
+        std::fs::create_dir_all(home.path().join("filters")).unwrap();
+        std::fs::write(home.path().join("filters/echo.toml"), FILTER).unwrap();
+        std::fs::write(home.path().join("config.toml"), settings).unwrap();
+        Self {
+            home,
+            cwd: TempDir::new().unwrap(),
+        }
+    }
+
+    fn run(&self, output: &str) -> String {
+        let out = Command::new(env!("CARGO_BIN_EXE_tokf"))
+            .env("TOKF_HOME", self.home.path())
+            .env_remove("TOKF_DB_PATH")
+            .current_dir(self.cwd.path())
+            .args(["run", "echo", output])
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    }
+}
+
+#[test]
+fn trend_line_follows_output_when_enabled() {
+    let env = Env::new("show_trends = true\n");
+    let first = env.run("3 failed");
+    assert!(
+        !first.contains("[tokf]"),
+        "single run has no trend: {first}"
+    );
+    env.run("2 failed");
+    let last = env.run("1 failed");
+    assert!(
+        last.ends_with("[tokf] failures: 3 \u{2192} 1 over last 3 runs\n"),
+        "stdout: {last}"
+    );
+}
+
+#[test]
+fn trend_line_off_by_default() {
+    let env = Env::new("");
+    env.run("3 failed");
+    let last = env.run("1 failed");
+    assert_eq!(last, "1 failed\n");
+}
+
+#[test]
+fn runs_without_a_count_are_not_in_the_trend() {
+    let env = Env::new("show_trends = true\n");
+    env.run("4 failed");
+    env.run("no summary here");
+    let last = env.run("0 failed");
+    assert!(
+        last.contains("failures: 4 \u{2192} 0 over last 2 runs"),
+        "stdout: {last}"
+    );
+}
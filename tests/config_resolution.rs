@@ -15,7 +15,7 @@ fn stdlib_dir() -> PathBuf {
 #[test]
 fn test_discover_git_push_from_stdlib() {
     let dirs = vec![stdlib_dir()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
     let git_push = filters
         .iter()
         .find(|f| f.config.command.first() == "git push")
@@ -26,22 +26,28 @@ fn test_discover_git_push_from_stdlib() {
 #[test]
 fn test_all_stdlib_filters_load() {
     let dirs = vec![stdlib_dir()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
-    // 27 stdlib filters: git/(add,commit,diff,log,push,show,status), cargo/(build,check,clippy,install,test),
-    // ls, npm/run, pnpm/(add,install), go/(build,vet), pytest, tsc,
-    // docker/(images,ps), kubectl/get, gh/(issue,pr), next/build, prisma/generate
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
+    // 61 stdlib filters: git/(add,commit,diff,fetch,log,pull,push,rebase,show,status),
+    // cargo/(add,build,check,clippy,install,test,update),
+    // ls, npm/(install,run), pnpm/(add,install), yarn/install, go/(build,vet), pytest, tsc,
+    // docker/(build,compose-up,images,ps), kubectl/(apply,describe,get,logs), gh/(issue,pr,run-view,run-watch),
+    // next/build, prisma/generate, terraform/(apply,plan), eslint, prettier,
+    // jest, vitest, mvn/(package,test), gradle/build,
+    // pip/install, poetry/install, uv, tox, ruff/check, rustup/update, make, cmake/build,
+    // aws/(ec2-describe-instances,logs-tail,s3)
     assert_eq!(
         filters.len(),
-        27,
-        "expected 27 stdlib filters, got {}",
+        61,
+        "expected 61 stdlib filters, got {}",
         filters.len()
     );
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn test_discover_returns_ok_for_nonexistent_dir() {
     let dirs = vec![PathBuf::from("/no/such/directory/ever")];
-    let result = config::discover_all_filters(&dirs);
+    let result = config::discover_all_filters(&dirs, true);
     assert!(result.is_ok());
     // Embedded stdlib is always included even when search dirs don't exist
     let filters = result.unwrap();
@@ -54,7 +60,7 @@ fn test_discover_returns_ok_for_nonexistent_dir() {
 #[test]
 fn test_discover_nonexistent_command_returns_none() {
     let dirs = vec![stdlib_dir()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
     let words = ["totally", "nonexistent", "command"];
     let found = filters.iter().any(|f| f.matches(&words).is_some());
     assert!(!found);
@@ -62,10 +68,11 @@ fn test_discover_nonexistent_command_returns_none() {
 
 // --- Embedded stdlib ---
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn test_embedded_filters_available_with_empty_dirs() {
     // Embedded stdlib appears even with no search dirs
-    let filters = config::discover_all_filters(&[]).unwrap();
+    let filters = config::discover_all_filters(&[], true).unwrap();
     assert!(!filters.is_empty());
     let has_git_push = filters
         .iter()
@@ -73,10 +80,11 @@ fn test_embedded_filters_available_with_empty_dirs() {
     assert!(has_git_push, "embedded git push not found");
 }
 
+#[cfg(feature = "stdlib")]
 #[test]
 fn test_embedded_filter_priority_label_is_builtin() {
     // Embedded filters should report [built-in] priority label
-    let filters = config::discover_all_filters(&[]).unwrap();
+    let filters = config::discover_all_filters(&[], true).unwrap();
     let git_push = filters
         .iter()
         .find(|f| f.config.command.first() == "git push")
@@ -93,7 +101,7 @@ fn test_local_filter_shadows_embedded() {
     fs::write(dir.path().join("push.toml"), r#"command = "git push""#).unwrap();
 
     let dirs = vec![dir.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
 
     // Should appear exactly once (local shadows embedded)
     let push_entries: Vec<_> = filters
@@ -108,12 +116,48 @@ fn test_local_filter_shadows_embedded() {
     assert_eq!(push_entries[0].priority_label(), "local");
 }
 
+#[cfg(feature = "stdlib")]
+#[test]
+fn test_disabled_builtin_is_not_discovered() {
+    use tokf::config::policy::FilterPolicy;
+
+    let policy = FilterPolicy {
+        disable: vec!["git/log".to_string(), "docker/*".to_string()],
+        ..FilterPolicy::default()
+    };
+    let filters = config::discover_filters_with(&[], true, &policy).unwrap();
+    assert!(config::find_by_name(&filters, "git/log").is_none());
+    assert!(config::find_by_name(&filters, "docker/ps").is_none());
+    assert!(config::find_by_name(&filters, "git/push").is_some());
+}
+
+#[cfg(feature = "stdlib")]
+#[test]
+fn test_prefer_local_false_lets_builtin_win() {
+    use tempfile::TempDir;
+    use tokf::config::policy::FilterPolicy;
+
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("push.toml"), r#"command = "git push""#).unwrap();
+    let dirs = vec![dir.path().to_path_buf()];
+    let policy = FilterPolicy {
+        prefer_local: false,
+        ..FilterPolicy::default()
+    };
+    let filters = config::discover_filters_with(&dirs, true, &policy).unwrap();
+    let push = filters
+        .iter()
+        .find(|f| f.config.command.first() == "git push")
+        .unwrap();
+    assert_eq!(push.priority_label(), "built-in");
+}
+
 // --- CommandPattern matching ---
 
 #[test]
 fn test_single_pattern_match() {
     let dirs = vec![stdlib_dir()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
     let git_push = filters
         .iter()
         .find(|f| f.config.command.first() == "git push")
@@ -130,6 +174,29 @@ fn test_single_pattern_match() {
     assert_eq!(git_push.matches(&["git", "status"]), None);
 }
 
+#[test]
+fn test_exclude_pattern_opts_out() {
+    use tempfile::TempDir;
+
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("push.toml"),
+        "command = \"git push\"\nexclude = [\"git push --dry-run\", \"git push ** --porcelain\"]",
+    )
+    .unwrap();
+
+    let dirs = vec![dir.path().to_path_buf()];
+    let filters = config::discover_all_filters(&dirs, false).unwrap();
+    let push = &filters[0];
+
+    assert_eq!(push.matches(&["git", "push", "origin"]), Some(2));
+    assert_eq!(push.matches(&["git", "push", "--dry-run"]), None);
+    assert_eq!(
+        push.matches(&["git", "push", "origin", "--porcelain"]),
+        None
+    );
+}
+
 #[test]
 fn test_multiple_pattern_match() {
     use tempfile::TempDir;
@@ -142,7 +209,7 @@ fn test_multiple_pattern_match() {
     .unwrap();
 
     let dirs = vec![dir.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
     let test_runner = filters
         .iter()
         .find(|f| f.config.command.first() == "pnpm test")
@@ -161,7 +228,7 @@ fn test_wildcard_pattern_match() {
     fs::write(dir.path().join("npm-run.toml"), r#"command = "npm run *""#).unwrap();
 
     let dirs = vec![dir.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
     let npm_run = filters
         .iter()
         .find(|f| f.config.command.first() == "npm run *")
@@ -186,8 +253,8 @@ fn test_priority_first_match_wins() {
     fs::write(dir1.path().join("a.toml"), r#"command = "git push""#).unwrap();
     fs::write(dir2.path().join("b.toml"), r#"command = "git push""#).unwrap();
 
-    let dirs = vec![dir1.path().to_path_buf(), dir2.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let search_dirs = vec![dir1.path().to_path_buf(), dir2.path().to_path_buf()];
+    let filters = config::discover_all_filters(&search_dirs, true).unwrap();
 
     // Dedup: only one entry for "git push"
     let push_entries: Vec<_> = filters
@@ -207,7 +274,7 @@ fn test_specificity_ordering() {
     fs::write(dir.path().join("b.toml"), r#"command = "git push""#).unwrap();
 
     let dirs = vec![dir.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
 
     // "git push" (specificity 2) before "git *" (specificity 1)
     assert_eq!(filters[0].config.command.first(), "git push");
@@ -227,7 +294,7 @@ fn test_nested_dir_discovery() {
     fs::write(git_dir.join("status.toml"), r#"command = "git status""#).unwrap();
 
     let dirs = vec![dir.path().to_path_buf()];
-    let filters = config::discover_all_filters(&dirs).unwrap();
+    let filters = config::discover_all_filters(&dirs, true).unwrap();
 
     let local: Vec<_> = filters.iter().filter(|f| f.priority == 0).collect();
     assert_eq!(local.len(), 2);
@@ -19,6 +19,8 @@ fn result(output: &str, exit_code: i32) -> CommandResult {
         stderr: String::new(),
         exit_code,
         combined: output.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
     }
 }
 
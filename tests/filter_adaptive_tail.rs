@@ -0,0 +1,83 @@
+//! Integration tests for `tail_adaptive` on output branches.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+const ADAPTIVE_FILTER: &str = r#"
+command = "make"
+
+[on_failure]
+tail_adaptive = { min = 3, max = 8, pattern = "error|FAILED" }
+"#;
+
+fn config(toml: &str) -> FilterConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn run(cfg: &FilterConfig, lines: &[&str]) -> String {
+    let result = CommandResult::from_output(&lines.join("\n"), 2);
+    filter::apply(cfg, &result, &[]).output
+}
+
+#[test]
+fn quiet_failure_stays_short() {
+    let cfg = config(ADAPTIVE_FILTER);
+    let output = run(
+        &cfg,
+        &[
+            "building",
+            "linking",
+            "step 1",
+            "step 2",
+            "make: *** [all] 2",
+        ],
+    );
+    assert_eq!(output, "step 1\nstep 2\nmake: *** [all] 2");
+}
+
+#[test]
+fn dense_failure_grows_to_include_errors() {
+    let cfg = config(ADAPTIVE_FILTER);
+    let output = run(
+        &cfg,
+        &[
+            "noise",
+            "noise",
+            "a.c:1: error: x",
+            "a.c:2: error: y",
+            "note",
+            "note",
+            "note",
+            "make: *** [all] 2",
+        ],
+    );
+    assert_eq!(output.lines().count(), 6);
+    assert!(output.starts_with("a.c:1: error: x"), "got: {output}");
+}
+
+#[test]
+fn window_never_exceeds_max() {
+    let cfg = config(ADAPTIVE_FILTER);
+    let mut lines = vec!["FAILED first"];
+    lines.extend(std::iter::repeat_n("filler", 20));
+    let output = run(&cfg, &lines);
+    assert_eq!(output.lines().count(), 8);
+    assert!(!output.contains("FAILED"));
+}
+
+#[test]
+fn matches_limits_growth() {
+    let cfg = config(
+        r#"
+command = "make"
+
+[on_failure]
+tail_adaptive = { min = 1, max = 10, pattern = "^error", matches = 1 }
+"#,
+    );
+    let output = run(&cfg, &["error one", "ok", "error two", "ok", "done"]);
+    assert_eq!(output, "error two\nok\ndone");
+}
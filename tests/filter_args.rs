@@ -0,0 +1,102 @@
+//! Integration tests for `{args}`, `{argN}`, `{flags}` and the reduction
+//! variables in branch templates.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+const NPM_RUN_FILTER: &str = r#"
+command = "npm run"
+
+[on_success]
+output = "✓ npm run {arg1}: ok{#if flags.silent} (silent){/if}"
+
+[on_failure]
+output = "✗ npm run {args} [{flags}]"
+"#;
+
+fn run(exit_code: i32, args: &[&str]) -> String {
+    let cfg: FilterConfig = toml::from_str(NPM_RUN_FILTER).unwrap();
+    let result = CommandResult::from_output("> build\n> tsc", exit_code);
+    let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+    filter::apply(&cfg, &result, &args).output
+}
+
+#[test]
+fn positional_arg_is_echoed() {
+    assert_eq!(run(0, &["build"]), "✓ npm run build: ok");
+}
+
+#[test]
+fn flag_is_visible_by_name() {
+    assert_eq!(
+        run(0, &["--silent", "build"]),
+        "✓ npm run build: ok (silent)"
+    );
+}
+
+#[test]
+fn args_and_flags_are_joined() {
+    assert_eq!(
+        run(1, &["test", "--watch", "--ci=true"]),
+        "✗ npm run test --watch --ci=true [--watch --ci=true]"
+    );
+}
+
+#[test]
+fn missing_args_render_empty() {
+    assert_eq!(run(1, &[]), "✗ npm run  []");
+}
+
+#[test]
+fn lines_removed_and_reduction_pct_describe_prefilter() {
+    let cfg: FilterConfig = toml::from_str(
+        r#"
+command = "make"
+skip = ["^make\\["]
+
+[on_success]
+output = "{output}\n… ({lines_removed} lines hidden, {reduction_pct}% smaller)"
+"#,
+    )
+    .unwrap();
+    let result = CommandResult::from_output(
+        "make[1]: Entering directory\nmake[1]: Leaving directory\nbuilt app",
+        0,
+    );
+    assert_eq!(
+        filter::apply(&cfg, &result, &[]).output,
+        "built app\n… (2 lines hidden, 85% smaller)"
+    );
+}
+
+#[test]
+fn reduction_vars_measure_the_branch_stream() {
+    let cfg: FilterConfig = toml::from_str(
+        r#"
+command = "make"
+skip = ["^make\\["]
+
+[on_success]
+source = "stdout"
+output = "{output} ({lines_removed} lines hidden, {reduction_pct}% smaller)"
+"#,
+    )
+    .unwrap();
+    let stdout = "make[1]: Entering directory\nbuilt app";
+    let stderr = "warning: clock skew detected\n".repeat(20);
+    let result = CommandResult {
+        stdout: stdout.to_string(),
+        stderr: stderr.clone(),
+        exit_code: 0,
+        combined: format!("{stdout}\n{stderr}"),
+        timed_out: None,
+        steps: Vec::new(),
+    };
+    assert_eq!(
+        filter::apply(&cfg, &result, &[]).output,
+        "built app (1 lines hidden, 75% smaller)"
+    );
+}
@@ -24,6 +24,8 @@ fn make_result(fixture: &str, exit_code: i32) -> CommandResult {
         stderr: String::new(),
         exit_code,
         combined: fixture.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
     }
 }
 
@@ -79,10 +81,28 @@ fn cargo_test_compile_error_falls_back_to_tail() {
     let filtered = filter::apply(&config, &result, &[]);
 
     // No sections matched → fallback tail = 5
-    let lines: Vec<&str> = filtered.output.lines().collect();
-    assert_eq!(lines.len(), 5, "expected 5 lines from fallback tail");
+    assert_eq!(
+        filtered.output.lines().count(),
+        5,
+        "expected 5 lines from fallback tail"
+    );
     assert!(
         filtered.output.contains("could not compile `tokf` (lib)"),
         "expected compiler error in tail"
     );
 }
+
+#[test]
+fn cargo_test_fail_without_failure_blocks_omits_header() {
+    let config = load_config();
+    let result = make_result(
+        "running 1 test\ntest result: FAILED. 0 passed; 1 failed; 0 ignored",
+        101,
+    );
+    let filtered = filter::apply(&config, &result, &[]);
+
+    assert_eq!(
+        filtered.output,
+        "test result: FAILED. 0 passed; 1 failed; 0 ignored"
+    );
+}
@@ -24,6 +24,8 @@ fn make_result(fixture: &str, exit_code: i32) -> CommandResult {
         stderr: String::new(),
         exit_code,
         combined: fixture.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
     }
 }
 
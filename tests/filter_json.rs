@@ -0,0 +1,107 @@
+//! Integration tests for the JSON (`[json]`) filter mode: JSONL and `select`.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+fn config(toml: &str) -> FilterConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn result(output: &str, exit_code: i32) -> CommandResult {
+    CommandResult {
+        stdout: String::new(),
+        stderr: String::new(),
+        exit_code,
+        combined: output.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
+    }
+}
+
+fn load_fixture(path: &str) -> String {
+    let full = format!("{}/{path}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&full)
+        .unwrap()
+        .trim_end()
+        .to_string()
+}
+
+const CARGO_JSON_FILTER: &str = r#"
+command = "cargo build --message-format=json"
+
+[json]
+where = ['reason == "compiler-message"']
+fields = { level = "message.level", msg = "message.message", file = "message.spans.0.file_name", line = "message.spans.0.line_start" }
+output = "{level}: {msg} ({file}:{line})"
+"#;
+
+#[test]
+fn json_mode_renders_compiler_messages() {
+    let cfg = config(CARGO_JSON_FILTER);
+    let fixture = load_fixture("tests/fixtures/cargo/build_json.txt");
+    let filtered = filter::apply(&cfg, &result(&fixture, 101), &[]);
+    assert_eq!(
+        filtered.output,
+        "warning: unused variable: `x` (src/main.rs:3)\n\
+         error: mismatched types (src/lib.rs:12)"
+    );
+}
+
+#[test]
+fn json_mode_feeds_branch_output_template() {
+    let cfg = config(&format!(
+        "{CARGO_JSON_FILTER}\n[on_failure]\noutput = \"build failed:\\n{{output}}\"\n"
+    ));
+    let fixture = load_fixture("tests/fixtures/cargo/build_json.txt");
+    let filtered = filter::apply(&cfg, &result(&fixture, 101), &[]);
+    assert!(filtered.output.starts_with("build failed:\nwarning:"));
+    assert!(filtered.output.ends_with("(src/lib.rs:12)"));
+}
+
+#[test]
+fn json_mode_composes_with_keep() {
+    let cfg = config(
+        r#"
+command = "eslint"
+keep = ['"severity":2']
+
+[json]
+fields = { rule = "ruleId" }
+output = "{rule}"
+"#,
+    );
+    let input = "{\"ruleId\":\"no-unused-vars\",\"severity\":1}\n\
+                 {\"ruleId\":\"no-undef\",\"severity\":2}";
+    let filtered = filter::apply(&cfg, &result(input, 1), &[]);
+    assert_eq!(filtered.output, "no-undef");
+}
+
+const JEST_JSON_FILTER: &str = r#"
+command = "jest --json"
+
+[json]
+select = ".testResults[].assertionResults[] | {title, status, file: .location.file}"
+where = ['status == "failed"']
+output = "✗ {title}"
+
+[on_success]
+output = "✓ all tests passed"
+
+[on_failure]
+output = "{output}"
+"#;
+
+#[test]
+fn json_select_renders_failed_assertions() {
+    let cfg = config(JEST_JSON_FILTER);
+    let doc = r#"{"numFailedTests":1,"testResults":[
+        {"assertionResults":[{"title":"adds","status":"passed"},{"title":"divides","status":"failed"}]},
+        {"assertionResults":[{"title":"rounds","status":"passed"}]}]}"#;
+    let filtered = filter::apply(&cfg, &result(doc, 1), &[]);
+    assert_eq!(filtered.output, "✗ divides");
+    let filtered = filter::apply(&cfg, &result(doc, 0), &[]);
+    assert_eq!(filtered.output, "✓ all tests passed");
+}
@@ -1,3 +1,4 @@
+#![cfg(feature = "scripting")]
 #![allow(clippy::unwrap_used, clippy::expect_used)]
 
 use std::fs;
@@ -9,7 +10,7 @@ fn tokf() -> Command {
     Command::new(env!("CARGO_BIN_EXE_tokf"))
 }
 
-/// Write a TOML filter to a temp file; return (TempDir, filter_path).
+/// Write a TOML filter to a temp file; return `(TempDir, filter_path)`.
 fn write_filter(content: &str) -> (TempDir, std::path::PathBuf) {
     let tmp = TempDir::new().unwrap();
     let path = tmp.path().join("filter.toml");
@@ -17,7 +18,7 @@ fn write_filter(content: &str) -> (TempDir, std::path::PathBuf) {
     (tmp, path)
 }
 
-/// Write a fixture to a temp file; return (TempDir, fixture_path).
+/// Write a fixture to a temp file; return `(TempDir, fixture_path)`.
 fn write_fixture(content: &str) -> (TempDir, std::path::PathBuf) {
     let tmp = TempDir::new().unwrap();
     let path = tmp.path().join("fixture.txt");
@@ -221,3 +222,51 @@ file = "{}"
         "from file: hello"
     );
 }
+
+#[test]
+fn lua_filter_sees_sections_and_aggregate_vars() {
+    let (_ftmp, filter) = write_filter(
+        r#"
+command = "test"
+
+[[section]]
+name = "failures"
+match = "^FAIL "
+collect_as = "failures"
+
+[on_failure.aggregate]
+from = "failures"
+pattern = "^FAIL (\\S+)"
+count_as = "failed"
+
+[lua_script]
+lang = "luau"
+source = '''
+local names = {}
+for _, line in ipairs(sections.failures.lines) do
+    table.insert(names, (regex.match(line, "^FAIL (\\S+)")))
+end
+return vars.failed .. " failed: " .. table.concat(names, ", ")
+'''
+"#,
+    );
+    let (_xtmp, fixture) = write_fixture("ok a\nFAIL b\nok c\nFAIL d\n");
+
+    let output = tokf()
+        .args([
+            "test",
+            filter.to_str().unwrap(),
+            fixture.to_str().unwrap(),
+            "--exit-code",
+            "1",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "2 failed: b, d",
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,98 @@
+//! Integration tests for `render = "markdown"` output branches.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+fn config(toml: &str) -> FilterConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn result(output: &str, exit_code: i32) -> CommandResult {
+    CommandResult {
+        stdout: String::new(),
+        stderr: String::new(),
+        exit_code,
+        combined: output.to_string(),
+        timed_out: None,
+        steps: Vec::new(),
+    }
+}
+
+const TEST_FILTER: &str = r#"
+command = "pytest"
+
+[[section]]
+name = "failures"
+match = '^FAILED '
+collect_as = "failed"
+
+[[section]]
+name = "tracebacks"
+enter = '^_{3,} '
+exit = '^=+ short test summary'
+split_on = '^_{3,} '
+collect_as = "traces"
+
+[on_failure]
+render = "markdown"
+output = "{failed.count} failed"
+
+[on_success]
+output = "ok"
+"#;
+
+const PYTEST_OUTPUT: &str = "\
+============================= test session starts ==============================
+collected 3 items
+
+tests/test_a.py .FF                                                      [100%]
+
+=================================== FAILURES ===================================
+___________________________________ test_one ___________________________________
+    assert 1 == 2
+E   assert 1 == 2
+___________________________________ test_two ___________________________________
+E   KeyError: 'x'
+=========================== short test summary info ============================
+FAILED tests/test_a.py::test_one - assert 1 == 2
+FAILED tests/test_a.py::test_two - KeyError: 'x'
+========================= 2 failed, 1 passed in 0.12s ==========================";
+
+#[test]
+fn markdown_branch_renders_headed_sections() {
+    let cfg = config(TEST_FILTER);
+    let out = filter::apply(&cfg, &result(PYTEST_OUTPUT, 1), &[]).output;
+
+    assert!(
+        out.starts_with("### Failures\n- FAILED tests/test_a.py::test_one"),
+        "{out}"
+    );
+    assert!(out.contains("\n\n### Tracebacks\n```\n"), "{out}");
+    assert!(out.contains("E   KeyError: 'x'\n```"), "{out}");
+    assert!(out.ends_with("```\n\n### Summary\n2 failed"), "{out}");
+}
+
+#[test]
+fn markdown_branch_without_output_template() {
+    let cfg = config(&TEST_FILTER.replace("output = \"{failed.count} failed\"\n", ""));
+    let out = filter::apply(&cfg, &result(PYTEST_OUTPUT, 1), &[]).output;
+    assert!(out.starts_with("### Failures\n"), "{out}");
+    assert!(!out.contains("### Summary\n"), "{out}");
+}
+
+#[test]
+fn markdown_branch_falls_back_when_nothing_collected() {
+    let cfg = config(TEST_FILTER);
+    let out = filter::apply(&cfg, &result("boom", 1), &[]).output;
+    assert_eq!(out, "boom");
+}
+
+#[test]
+fn other_branch_unaffected() {
+    let cfg = config(TEST_FILTER);
+    let out = filter::apply(&cfg, &result(PYTEST_OUTPUT, 0), &[]).output;
+    assert_eq!(out, "ok");
+}
@@ -0,0 +1,123 @@
+//! Integration tests for `source = "stdout" | "stderr" | "combined"` and the
+//! `{stdout}` / `{stderr}` template variables.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+fn config(toml: &str) -> FilterConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn result(stdout: &str, stderr: &str, exit_code: i32) -> CommandResult {
+    CommandResult {
+        stdout: format!("{stdout}\n"),
+        stderr: format!("{stderr}\n"),
+        exit_code,
+        combined: format!("{stdout}\n{stderr}"),
+        timed_out: None,
+        steps: Vec::new(),
+    }
+}
+
+#[test]
+fn top_level_source_limits_skip_keep_input() {
+    let cfg = config(
+        r#"
+command = "tool"
+source = "stderr"
+keep = ["^warning"]
+"#,
+    );
+    let out = filter::apply(
+        &cfg,
+        &result(
+            "warning: in stdout\npayload",
+            "warning: in stderr\nnoise",
+            0,
+        ),
+        &[],
+    );
+    assert_eq!(out.output, "warning: in stderr");
+}
+
+#[test]
+fn section_reads_its_own_stream() {
+    let cfg = config(
+        r#"
+command = "tool"
+
+[[section]]
+name = "diagnostics"
+match = "^error"
+collect_as = "errors"
+source = "stderr"
+
+[[section]]
+name = "everything"
+match = "^error"
+collect_as = "all_errors"
+
+[on_failure]
+output = "{errors.count} on stderr, {all_errors.count} total"
+"#,
+    );
+    let out = filter::apply(
+        &cfg,
+        &result("error: echoed in payload", "error: a\nerror: b", 1),
+        &[],
+    );
+    assert_eq!(out.output, "2 on stderr, 3 total");
+}
+
+#[test]
+fn branch_source_overrides_top_level() {
+    let cfg = config(
+        r#"
+command = "tool"
+skip = ["^debug"]
+
+[on_success]
+source = "stdout"
+
+[on_failure]
+source = "stderr"
+tail = 1
+"#,
+    );
+    let ok = filter::apply(&cfg, &result("debug x\nresult", "debug y\nwarn", 0), &[]);
+    assert_eq!(ok.output, "result");
+
+    let failed = filter::apply(&cfg, &result("result", "debug y\nfirst\nlast", 1), &[]);
+    assert_eq!(failed.output, "last");
+}
+
+#[test]
+fn stdout_and_stderr_template_vars() {
+    let cfg = config(
+        r#"
+command = "tool"
+
+[on_failure]
+output = "payload: {stdout}\ndiagnostics: {stderr}"
+"#,
+    );
+    let out = filter::apply(&cfg, &result("42", "boom", 1), &[]);
+    assert_eq!(out.output, "payload: 42\ndiagnostics: boom");
+}
+
+#[test]
+fn fixture_output_counts_as_stdout() {
+    let cfg = config(
+        r#"
+command = "tool"
+
+[on_success]
+output = "[{stdout}] [{stderr}]"
+"#,
+    );
+    let out = filter::apply(&cfg, &CommandResult::from_output("saved\n", 0), &[]);
+    assert_eq!(out.output, "[saved] []");
+}
@@ -0,0 +1,199 @@
+//! Golden snapshots: every stdlib filter rendered against each of its fixtures.
+//!
+//! Fixtures for `filters/<dir>/<name>.toml` are `tests/fixtures/<dir>/<name>*.txt`,
+//! `tests/fixtures/<dir>_<name>_*.txt`, or every file in `tests/fixtures/<name>/`
+//! for top-level filters. A fixture whose name mentions a failure (`fail`,
+//! `error`, `fatal`, `rejected`, `not_repo`) is rendered with exit code 1.
+//!
+//! Output is compared with `tests/golden/<filter>/<fixture>.txt`. To accept
+//! changes, rerun with `TOKF_BLESS=1` (or `just bless`) and review the diff.
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use tokf::config::types::FilterConfig;
+use tokf::filter;
+use tokf::runner::CommandResult;
+
+const FAILURE_MARKERS: &[&str] = &["fail", "error", "fatal", "rejected", "not_repo"];
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn files_with_ext(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return out;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            out.extend(files_with_ext(&path, ext));
+        } else if path.extension().is_some_and(|e| e == ext) {
+            out.push(path);
+        }
+    }
+    out.sort();
+    out
+}
+
+/// Filter name (`git/status`) → its fixtures, relative to `tests/fixtures`.
+fn fixtures_for(name: &str, fixtures: &[String]) -> Vec<String> {
+    let flat = name.replace('/', "_");
+    fixtures
+        .iter()
+        .filter(|f| {
+            f.starts_with(&format!("{name}_"))
+                || *f == &format!("{name}.txt")
+                || f.starts_with(&format!("{flat}_"))
+                || Path::new(f.as_str()).parent() == Some(Path::new(name))
+        })
+        .cloned()
+        .collect()
+}
+
+fn exit_code_for(fixture: &str) -> i32 {
+    i32::from(FAILURE_MARKERS.iter().any(|m| fixture.contains(m)))
+}
+
+fn render(filter_path: &Path, fixture_path: &Path, exit_code: i32) -> String {
+    let cfg: FilterConfig = toml::from_str(&std::fs::read_to_string(filter_path).unwrap())
+        .map_err(|e| format!("{}: {e}", filter_path.display()))
+        .unwrap();
+    let fixture = std::fs::read_to_string(fixture_path).unwrap();
+    let result = CommandResult::from_output(&fixture, exit_code);
+    let filtered = filter::apply(&cfg, &result, &[]);
+    format!(
+        "# exit_code: {exit_code}, failed: {}\n{}\n",
+        filtered.failed, filtered.output
+    )
+}
+
+/// Compare `actual` with the committed snapshot, writing it when blessing.
+fn compare(snapshot: &Path, actual: &str, bless: bool, problems: &mut String) {
+    let committed = std::fs::read_to_string(snapshot).ok();
+    if committed.as_deref() == Some(actual) {
+        return;
+    }
+    if bless {
+        std::fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+        std::fs::write(snapshot, actual).unwrap();
+        return;
+    }
+    let rel = snapshot.strip_prefix(root()).unwrap().display().to_string();
+    match committed {
+        None => {
+            let _ = writeln!(problems, "{rel}: missing snapshot");
+        }
+        Some(committed) => {
+            let _ = writeln!(
+                problems,
+                "{rel}: output changed\n--- committed\n{committed}+++ actual\n{actual}"
+            );
+        }
+    }
+}
+
+/// Snapshots without a fixture are deleted when blessing, reported otherwise.
+fn remove_stale(
+    golden_dir: &Path,
+    expected: &BTreeSet<PathBuf>,
+    bless: bool,
+    problems: &mut String,
+) {
+    for stale in files_with_ext(golden_dir, "txt") {
+        if expected.contains(&stale) {
+            continue;
+        }
+        if bless {
+            std::fs::remove_file(&stale).unwrap();
+        } else {
+            let rel = stale.strip_prefix(root()).unwrap().display().to_string();
+            let _ = writeln!(problems, "{rel}: stale snapshot (no matching fixture)");
+        }
+    }
+}
+
+#[test]
+fn stdlib_filters_match_golden_snapshots() {
+    let bless = std::env::var_os("TOKF_BLESS").is_some_and(|v| !v.is_empty() && v != "0");
+    let filters_dir = root().join("filters");
+    let fixtures_dir = root().join("tests/fixtures");
+    let golden_dir = root().join("tests/golden");
+
+    let fixtures: Vec<String> = files_with_ext(&fixtures_dir, "txt")
+        .iter()
+        .map(|p| {
+            p.strip_prefix(&fixtures_dir)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+
+    let mut problems = String::new();
+    let mut expected_snapshots = BTreeSet::new();
+    for filter_path in files_with_ext(&filters_dir, "toml") {
+        let rel = filter_path.strip_prefix(&filters_dir).unwrap();
+        let name = rel.with_extension("").to_string_lossy().into_owned();
+        let cases = fixtures_for(&name, &fixtures);
+        if cases.is_empty() {
+            let _ = writeln!(problems, "{name}: no fixtures in tests/fixtures");
+        }
+        for fixture in cases {
+            let stem = Path::new(&fixture)
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            let snapshot = golden_dir.join(&name).join(format!("{stem}.txt"));
+            expected_snapshots.insert(snapshot.clone());
+            let actual = render(
+                &filter_path,
+                &fixtures_dir.join(&fixture),
+                exit_code_for(&fixture),
+            );
+            compare(&snapshot, &actual, bless, &mut problems);
+        }
+    }
+
+    remove_stale(&golden_dir, &expected_snapshots, bless, &mut problems);
+
+    assert!(
+        problems.is_empty(),
+        "golden snapshots differ; rerun with TOKF_BLESS=1 to accept:\n{problems}"
+    );
+}
+
+#[test]
+fn failure_fixtures_use_non_zero_exit() {
+    assert_eq!(exit_code_for("git_push_rejected.txt"), 1);
+    assert_eq!(exit_code_for("cargo/check_failure.txt"), 1);
+    assert_eq!(exit_code_for("cargo_clippy_warning.txt"), 0);
+    assert_eq!(exit_code_for("git_status_clean.txt"), 0);
+}
+
+#[test]
+fn fixture_lookup_covers_flat_and_nested_layouts() {
+    let fixtures: Vec<String> = [
+        "git_status_clean.txt",
+        "git/show_success.txt",
+        "pytest/pass.txt",
+        "git_stash.txt",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+    assert_eq!(
+        fixtures_for("git/status", &fixtures),
+        ["git_status_clean.txt"]
+    );
+    assert_eq!(
+        fixtures_for("git/show", &fixtures),
+        ["git/show_success.txt"]
+    );
+    assert_eq!(fixtures_for("pytest", &fixtures), ["pytest/pass.txt"]);
+}